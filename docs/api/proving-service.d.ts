@@ -0,0 +1,749 @@
+// Generated from the proving-service API models by `cargo run --bin api-schema -- --typescript`.
+// Do not edit; regenerate it when the models change.
+
+export declare const API_SCHEMA_VERSION: 18;
+
+export interface BannedMatch {
+  purl: string;
+  /**
+   * Name of the reason code, e.g. `security`
+   */
+  reason: string;
+  value: number;
+}
+
+export interface BannedPurl {
+  purl: string;
+  /**
+   * Reason code stored in the purl's leaf; must be nonzero. `1` (security) when absent
+   */
+  value?: number;
+}
+
+/**
+ * Reason code carried by a banned (value-carrying) leaf
+ */
+export interface BannedReason {
+  leaf_index: string;
+  purl: string;
+  reason: string;
+  value: number;
+}
+
+export interface CompactMerkleProof {
+  /**
+   * Hex bitmap of the levels carrying a sibling; absent from uncompressed proofs
+   */
+  bitmap?: string;
+  /**
+   * Identifier the proof is about; older clients send it as `purl`
+   */
+  key: string;
+  /**
+   * How `key` maps to its leaf index; a purl when absent
+   */
+  key_type?: KeyType;
+  /**
+   * Hex, like `siblings` and `bitmap`; a CBOR request may send all three as byte strings
+   */
+  leaf_index: string;
+  siblings: string[];
+  value: string;
+}
+
+/**
+ * A stored proof of the same request made by a different guest image
+ */
+export interface EarlierProof {
+  image_id: string[];
+  proof_id: string;
+}
+
+/**
+ * The prover settings a proof was made with, recorded so it can be made again alike
+ */
+export interface EffectiveProverOptions {
+  hashfn: HashFn;
+  segment_po2: number;
+}
+
+/**
+ * The first proof the guest found keeping a request from being compliant
+ */
+export interface FirstFailure {
+  /**
+   * Position of the failing proof in the request; absent when no single proof failed, as for unparsable proofs or a strict-mode list mismatch
+   */
+  index?: number | null;
+  /**
+   * Leaf index of the failing proof's key (`compute_purl_hash` for a purl); absent with `index` or when the key maps to no leaf
+   */
+  key_hash?: string | null;
+  /**
+   * `sbom_common::failure::name` of the committed code, e.g. "bad_hex" or "root_mismatch"
+   */
+  reason: string;
+}
+
+/**
+ * Body of `POST /generate-proofs`: a banned list to build the tree from and the SBOM purls to prove against it
+ */
+export interface GenerateProofsRequest {
+  /**
+   * Every entry of the banned list; a later duplicate purl overrides an earlier one
+   */
+  banned: BannedPurl[];
+  /**
+   * SBOM purls to produce non-membership proofs for
+   */
+  purls: string[];
+}
+
+/**
+ * The tree built from a banned list and proofs of the queried purls, ready to send to `POST /prove-merkle-compact` as `depth`, `root` and `merkle_proofs`
+ */
+export interface GenerateProofsResponse {
+  /**
+   * Queried purls the list bans; no proof is produced for them
+   */
+  banned: BannedMatch[];
+  /**
+   * Distinct purls in the banned list
+   */
+  banned_count: number;
+  depth: number;
+  /**
+   * Non-membership proofs of the queried purls the list does not ban, in request order
+   */
+  merkle_proofs: CompactMerkleProof[];
+  /**
+   * Hex root of the tree built from the banned list
+   */
+  root: string;
+}
+
+/**
+ * Hash function the prover builds its STARK seals with
+ * - `"sha-256"`: 
+ * - `"blake2b"`: 
+ * - `"poseidon2"`: The only one the recursion circuit supports, so the only one for `succinct` and `groth16` receipts and the fast path
+ */
+export type HashFn = "sha-256" | "blake2b" | "poseidon2";
+
+/**
+ * Body of `POST /proofs/import`: proof documents made elsewhere, each imported or rejected on its own
+ */
+export interface ImportProofsRequest {
+  /**
+   * Parsed one at a time, so a malformed document is reported without failing the rest
+   */
+  proofs: ImportedProof[];
+}
+
+export interface ImportProofsResponse {
+  duplicates: number;
+  imported: number;
+  invalid: number;
+  /**
+   * One per submitted document, in request order
+   */
+  results: ImportResult[];
+}
+
+export interface ImportResult {
+  error?: string | null;
+  index: number;
+  /**
+   * Id the proof is stored under; absent when the document is invalid
+   */
+  proof_id?: string | null;
+  status: ImportStatus;
+}
+
+/**
+ * - `"imported"`: 
+ * - `"invalid"`: 
+ * - `"duplicate"`: A proof with the same id is already stored; it is left as it is
+ */
+export type ImportStatus = "imported" | "invalid" | "duplicate";
+
+/**
+ * A proof to import, bare or in the `sbom-proof` envelope. A proof record as `GET /proofs/{id}` returns it is one, provided its receipt is inline. What the receipt commits to is read from its journal; the other fields are taken as given once they agree with it.
+ */
+export interface ImportedProof {
+  /**
+   * Must be the image ID this instance proves with
+   */
+  image_id: string[];
+  /**
+   * Checked against the journal's list info hash when given
+   */
+  list_info?: ListInfo | null;
+  /**
+   * Base64 receipt, as in `ProofArtifact::proof`
+   */
+  proof: string;
+  /**
+   * Keys checked, in proving order; as many as the journal's proof count when given
+   */
+  purls?: string[];
+  reasons?: ImportedReason[];
+  /**
+   * Hex canonical digest of the request the receipt was proven for; with the image ID and the journal it determines the proof id
+   */
+  request_digest: string;
+  /**
+   * When the proof was made; the time of the import when absent
+   */
+  timestamp?: number | null;
+}
+
+export interface ImportedReason {
+  leaf_index: string;
+  purl: string;
+  value: number;
+}
+
+/**
+ * - `"purl"`: Package URL; the leaf index is SHA-256 of the purl as given
+ * - `"cpe"`: CPE name; the leaf index is SHA-256 of its canonical form (see [`compute_cpe_hash`])
+ * - `"raw_hash"`: The key is the leaf index itself, as 64 hex characters
+ */
+export type KeyType = "purl" | "cpe" | "raw_hash";
+
+/**
+ * Provenance of the banned list the proofs were drawn from
+ */
+export interface ListInfo {
+  /**
+   * Number of keys checked against the list; must equal the number of proofs
+   */
+  entry_count: number;
+  publisher: string;
+  source_uri: string;
+  version: string;
+}
+
+/**
+ * A generated proof as returned to callers and persisted by the proof store. Fields are serialized in this order, which is the persisted record format.
+ */
+export interface ProofArtifact {
+  banned_list_hash: string;
+  /**
+   * Answered from the proof cache: an identical request was proven recently and this is that proof, so `generation_duration_ms` is 0. Always false in stored records
+   */
+  cached: boolean;
+  compliant: boolean;
+  /**
+   * Digest of the default hash table the guest verified against, as committed in the journal
+   */
+  defaults_digest: string;
+  /**
+   * The receipt is a fake from a prover in dev mode and proves nothing; set from the receipt itself, so also for fakes from other provers or imports
+   */
+  dev_mode: boolean;
+  /**
+   * The request had no more proofs than `FAST_PATH_MAX_PROOFS` and took the fast path: validated on one thread and proven in small segments, compressed to a succinct receipt unless a Groth16 one was requested
+   */
+  fast_path: boolean;
+  /**
+   * Why the request is not compliant, as committed in the journal; `None` when it is
+   */
+  first_failure?: FirstFailure | null;
+  /**
+   * Wall-clock time spent proving, retries included; excludes waiting for a prover
+   */
+  generation_duration_ms: number;
+  image_id: string[];
+  /**
+   * The receipt was proven elsewhere and imported with `POST /proofs/import`; the proving fields then describe the import, and `purls` and `reasons` are as submitted
+   */
+  imported: boolean;
+  /**
+   * API key id the import was signed with; `None` for proofs made here and for unsigned imports
+   */
+  imported_by?: string | null;
+  /**
+   * Hex journal bytes, as in `receipt.journal.bytes`; lets consumers read the outputs without decoding `proof`
+   */
+  journal: string;
+  /**
+   * Kind of identifier in `purls` and `reasons`
+   */
+  key_type: KeyType;
+  /**
+   * Leaf index of each of `purls`; present only in records whose keys are sealed
+   */
+  leaf_indexes?: string[] | null;
+  list_info?: ListInfo | null;
+  /**
+   * Present only when the request carried `list_info`
+   */
+  list_info_hash?: string | null;
+  /**
+   * Base64 receipt; `None` when it exceeded the inline limit and `proof_url` links to it
+   */
+  proof?: string | null;
+  proof_count: number;
+  /**
+   * Key of the proof in the store and the retrieval endpoints: derived from the request digest, the image ID and the journal (see `digest::proof_id`), so re-proving an identical request with the same guest image yields the same id
+   */
+  proof_id: string;
+  proof_size: number;
+  /**
+   * Statement `compliant` attests, as requested by `mode`
+   */
+  proof_type: ProofType;
+  proof_url?: string | null;
+  /**
+   * How many times the prover ran; more than 1 when transient failures were retried
+   */
+  prove_attempts: number;
+  /**
+   * Backend that produced the receipt: "local", "bonsai" or "custom", or "imported"
+   */
+  prover_backend: string;
+  /**
+   * Settings the local zkVM proved with; `None` for other backends and imports
+   */
+  prover_opts?: EffectiveProverOptions | null;
+  /**
+   * Remote session that produced the receipt; `None` for local proving
+   */
+  prover_session_id?: string | null;
+  /**
+   * Every key checked, in proving order: the list `banned_list_hash` commits to, which a verifier recomputes when it is passed as `banned_list`. Named for the default key type; see `key_type`. Only previews in records whose keys are sealed
+   */
+  purls: string[];
+  reasons: BannedReason[];
+  reasons_hash: string;
+  /**
+   * Receipt actually produced: "composite", "succinct", "groth16", or "fake" in dev mode
+   */
+  receipt_kind: string;
+  /**
+   * SHA-256 (hex) of the receipt; names the stored payload when it is linked
+   */
+  receipt_sha256: string;
+  reproducible: boolean;
+  /**
+   * Present when an identical request was proven before by another guest image: the latest such proof, which stays stored under its own id and image ID
+   */
+  reproves?: EarlierProof | null;
+  /**
+   * Canonical digest of the request (hex); see `digest::canonical_request_digest`
+   */
+  request_digest: string;
+  /**
+   * UUIDv7 assigned when the request arrived; unique per proving run
+   */
+  request_id: string;
+  /**
+   * Present only when the request was signed with an API key; the journal commits all zeros otherwise
+   */
+  requester_commitment?: string | null;
+  root_alias?: string | null;
+  root_hash: string;
+  /**
+   * Present only when the request carried `banned_list_salt`
+   */
+  salt_commitment?: string | null;
+  /**
+   * Hex seal of a succinct or Groth16 receipt; `None` for a composite one, which has a seal per segment. A Groth16 seal is prefixed with the 4-byte verifier selector, as the RISC Zero verifier contracts expect
+   */
+  seal?: string | null;
+  /**
+   * `purls` and the keys of `reasons` sealed under the tenant's redaction key; present only in records of tenants whose keys are redacted
+   */
+  sealed_purls?: SealedKeys | null;
+  /**
+   * Host-side sequence number, increasing across restarts; orders proofs that share a timestamp or were stamped by a clock that stepped back
+   */
+  sequence: number;
+  /**
+   * Template version the request was run from, if any
+   */
+  template?: TemplateRef | null;
+  timestamp: number;
+}
+
+/**
+ * How the proofs in a request are encoded
+ * - `"compact"`: A bitmap plus only the siblings that differ from DEFAULTS; the canonical form
+ * - `"uncompressed"`: No bitmap and one sibling for every level, depth 0 first, as the Go merkle-proof-service returns them with `compress: false`. Compressed on arrival
+ */
+export type ProofEncoding = "compact" | "uncompressed";
+
+/**
+ * A proof document with what is needed to interpret it
+ */
+export interface ProofEnvelope_for_ProofArtifact {
+  /**
+   * Service and version that wrote the document, as `name/version`
+   */
+  created_by: string;
+  data: ProofArtifact;
+  /**
+   * Always "sbom-proof", [`PROOF_FORMAT`]
+   */
+  format: string;
+  format_version: number;
+  /**
+   * JSON Schema of the service API, which describes `data`
+   */
+  schema_url: string;
+}
+
+/**
+ * Where the proofs of a template run come from
+ */
+export type ProofSource = { kind: "snapshot"; purls: string[] } | { kind: "supplied" };
+
+/**
+ * The fields of a persisted [`ProofArtifact`] that `GET /proofs` lists
+ */
+export interface ProofSummary {
+  compliant: boolean;
+  /**
+   * Absent from records written before the duration was recorded
+   */
+  generation_duration_ms?: number | null;
+  /**
+   * Guest image the proof was made with, which verifying it needs
+   */
+  image_id?: string[];
+  /**
+   * The proof was imported rather than made here
+   */
+  imported?: boolean;
+  proof_id: string;
+  root_hash: string;
+  /**
+   * 0 for records written before sequence numbers were assigned
+   */
+  sequence?: number;
+  timestamp: number;
+}
+
+/**
+ * A stored template version, as returned by `POST /templates` and `GET /templates/{name}`
+ */
+export interface ProofTemplate {
+  /**
+   * Unix timestamp (seconds) the version was stored
+   */
+  created_at: number;
+  /**
+   * Strict mode: the banned_list_hash of every run must equal this hex hash
+   */
+  expected_banned_list_hash?: string | null;
+  /**
+   * Strict mode for `supplied` proofs: every run must cover exactly these purls. A `snapshot` source implies its purls
+   */
+  expected_purls?: string[] | null;
+  /**
+   * Provenance committed by runs that do not bring their own
+   */
+  list_info?: ListInfo | null;
+  mode?: ProofType;
+  /**
+   * 1 to 64 ASCII letters, digits, `-` and `_`
+   */
+  name: string;
+  receipt_kind?: ReceiptKind;
+  /**
+   * Root aliases runs may prove against; when not empty, every run must name one of them and cannot give a hex root
+   */
+  root_aliases?: string[];
+  source: ProofSource;
+  /**
+   * 1 for a new name, one more than the latest version on every update
+   */
+  version: number;
+}
+
+/**
+ * - `"non_membership"`: Compliant when every leaf value is 0
+ * - `"membership"`: Compliant when every leaf value is nonzero
+ */
+export type ProofType = "non_membership" | "membership";
+
+export interface ProofValidationResult {
+  computed_root?: string | null;
+  error?: string | null;
+  index: number;
+  purl: string;
+  valid: boolean;
+}
+
+export interface ProveCompactMerkleRequest {
+  /**
+   * Hex 32-byte secret that blinds the committed banned_list_hash. Only its hash is committed and returned; the salt itself is never stored
+   */
+  banned_list_salt?: string | null;
+  depth: number;
+  /**
+   * Strict mode: the banned_list_hash of the proven purls must equal this hex hash
+   */
+  expected_banned_list_hash?: string | null;
+  /**
+   * Strict mode: the proofs must cover exactly these purls; they are proven in this order
+   */
+  expected_purls?: string[] | null;
+  /**
+   * Prove even when an identical request was proven recently, instead of answering with that proof from the cache
+   */
+  force?: boolean;
+  /**
+   * Where the banned list came from; its hash is committed in the journal
+   */
+  list_info?: ListInfo | null;
+  /**
+   * Largest response body the client wants; a larger one links to the proof instead. Can only tighten the server's MAX_RESPONSE_BYTES
+   */
+  max_response_bytes?: number | null;
+  merkle_proofs: CompactMerkleProof[];
+  /**
+   * Statement to prove: that none of the keys are in the tree (`non_membership`, value 0 in every proof) or that all of them are (`membership`, nonzero values)
+   */
+  mode?: ProofType;
+  /**
+   * Encoding of `merkle_proofs`; never guessed from their shape
+   */
+  proof_encoding?: ProofEncoding;
+  /**
+   * Prover settings for this request only, e.g. smaller segments for a large batch. Refused by backends other than the local zkVM
+   */
+  prover_opts?: ProverOptions | null;
+  /**
+   * Receipt to produce; `succinct` and `groth16` also return the seal separately for on-chain verification
+   */
+  receipt_kind?: ReceiptKind;
+  /**
+   * Pin every nondeterministic input so repeated runs produce identical journals
+   */
+  reproducible?: boolean;
+  /**
+   * Hex root; may be omitted when `root_alias` is given
+   */
+  root?: string;
+  /**
+   * Named root resolved through the configured alias registry
+   */
+  root_alias?: string | null;
+  /**
+   * Prove even when a proof does not fold up to `root` on the host, which yields a non-compliant attestation; such requests are refused before proving by default
+   */
+  skip_preverify?: boolean;
+  /**
+   * Unix timestamp (seconds) used instead of the system clock; required when `reproducible`
+   */
+  timestamp?: number | null;
+}
+
+/**
+ * RISC Zero prover settings. Unset ones fall back to the server's `PROVER_*` settings, and those to RISC Zero's defaults
+ */
+export interface ProverOptions {
+  hashfn?: HashFn | null;
+  /**
+   * log2 of the cycles per segment, 14 to 22. Smaller segments prove in less memory, but there are more of them
+   */
+  segment_po2?: number | null;
+}
+
+/**
+ * Which receipt the prover produces
+ * - `"composite"`: One STARK per segment; the fastest to produce and the largest
+ * - `"succinct"`: The segments recursively compressed into a single STARK of constant size
+ * - `"groth16"`: A succinct receipt wrapped in a Groth16 SNARK, small enough for EVM calldata
+ */
+export type ReceiptKind = "composite" | "succinct" | "groth16";
+
+/**
+ * Keys of a stored record, readable only with the tenant's [`RedactionKey`]
+ */
+export interface SealedKeys {
+  /**
+   * Always [`SEAL_ALGORITHM`]
+   */
+  algorithm: string;
+  /**
+   * Base64 ciphertext of the JSON-encoded [`RecordKeys`]
+   */
+  ciphertext: string;
+  /**
+   * Hex 16-byte nonce, unique per sealed record
+   */
+  nonce: string;
+  /**
+   * Hex HMAC-SHA256 over nonce and ciphertext
+   */
+  tag: string;
+}
+
+/**
+ * Body of `POST /templates`: a named, partial prove request for a recurring job. Runs add the root and, for `supplied` proofs, the proofs themselves
+ */
+export interface TemplateDefinition {
+  /**
+   * Strict mode: the banned_list_hash of every run must equal this hex hash
+   */
+  expected_banned_list_hash?: string | null;
+  /**
+   * Strict mode for `supplied` proofs: every run must cover exactly these purls. A `snapshot` source implies its purls
+   */
+  expected_purls?: string[] | null;
+  /**
+   * Provenance committed by runs that do not bring their own
+   */
+  list_info?: ListInfo | null;
+  mode?: ProofType;
+  /**
+   * 1 to 64 ASCII letters, digits, `-` and `_`
+   */
+  name: string;
+  receipt_kind?: ReceiptKind;
+  /**
+   * Root aliases runs may prove against; when not empty, every run must name one of them and cannot give a hex root
+   */
+  root_aliases?: string[];
+  source: ProofSource;
+}
+
+/**
+ * A template version, as recorded in the proofs run from it
+ */
+export interface TemplateRef {
+  name: string;
+  version: number;
+}
+
+/**
+ * Body of `POST /templates/{name}/run`: the parts of the request that change per run
+ */
+export interface TemplateRun {
+  /**
+   * Prove even when an identical run was proven recently
+   */
+  force?: boolean;
+  /**
+   * Replaces the template's `list_info` for this run, e.g. with this week's version
+   */
+  list_info?: ListInfo | null;
+  max_response_bytes?: number | null;
+  /**
+   * Required by templates with `supplied` proofs and refused by the others
+   */
+  merkle_proofs?: CompactMerkleProof[] | null;
+  /**
+   * Encoding of `merkle_proofs`
+   */
+  proof_encoding?: ProofEncoding;
+  /**
+   * Hex root; may be omitted when `root_alias` is given
+   */
+  root?: string;
+  root_alias?: string | null;
+}
+
+export interface ValidateCompactMerkleResponse {
+  banned_list_hash: string;
+  /**
+   * True only if every proof is valid and its value is one `proof_type` accepts
+   */
+  compliant: boolean;
+  /**
+   * Always false: this result comes from host-side recomputation, not from a receipt
+   */
+  cryptographic: boolean;
+  /**
+   * Kind of identifier the proofs are keyed by
+   */
+  key_type: KeyType;
+  list_info?: ListInfo | null;
+  /**
+   * Commitment the guest would produce over `list_info`; present only when it was given
+   */
+  list_info_hash?: string | null;
+  proof_count: number;
+  /**
+   * Statement `compliant` refers to, as requested by `mode`
+   */
+  proof_type: ProofType;
+  reasons: BannedReason[];
+  /**
+   * Commitment the guest would produce over (leaf_index, value); present only when `valid`
+   */
+  reasons_hash?: string | null;
+  results: ProofValidationResult[];
+  root_alias?: string | null;
+  root_hash: string;
+  /**
+   * Hash of the salt blinding `banned_list_hash`; present only when one was given
+   */
+  salt_commitment?: string | null;
+  valid: boolean;
+}
+
+/**
+ * How stored proofs would fare under a candidate banned list
+ */
+export interface WhatIfReport {
+  /**
+   * Distinct keys that caused a flip, sorted, at most `WHATIF_SAMPLE_SIZE` of them
+   */
+  affected_purls: string[];
+  /**
+   * More keys caused a flip than `affected_purls` lists
+   */
+  affected_purls_truncated: boolean;
+  /**
+   * Distinct purls in the candidate list
+   */
+  candidate_purls: number;
+  /**
+   * Compliant non-membership proofs among them, the only ones a banned list can flip
+   */
+  evaluated: number;
+  /**
+   * Evaluated proofs with a key the candidate list bans
+   */
+  flipped: number;
+  /**
+   * Per proven root, most flipped first
+   */
+  roots: WhatIfRoot[];
+  /**
+   * Stored proofs in the time window
+   */
+  scanned: number;
+}
+
+/**
+ * Body of `POST /whatif`: a candidate banned list to replay stored proofs against
+ */
+export interface WhatIfRequest {
+  /**
+   * Purls the candidate list would ban
+   */
+  purls: string[];
+  /**
+   * Only proofs with a timestamp at or after this Unix time
+   */
+  since?: number | null;
+  /**
+   * Only proofs with a timestamp at or before this Unix time
+   */
+  until?: number | null;
+}
+
+export interface WhatIfRoot {
+  evaluated: number;
+  flipped: number;
+  /**
+   * Alias an evaluated proof of this root was requested by, if any was
+   */
+  root_alias?: string | null;
+  root_hash: string;
+}
@@ -0,0 +1,312 @@
+// Generated from the verifier-service API models by `cargo run --bin api-schema -- --typescript`.
+// Do not edit; regenerate it when the models change.
+
+export declare const API_SCHEMA_VERSION: 8;
+
+/**
+ * A metadata field of a proof file that disagrees with the journal of its own receipt
+ */
+export interface FileInconsistency {
+  field: string;
+  file_value: string;
+  journal_value: string;
+}
+
+/**
+ * The first proof the guest found keeping the proofs from being compliant
+ */
+export interface FirstFailure {
+  /**
+   * Position of the failing proof in the proven request; absent when no single proof failed, as for unparsable proofs or a strict-mode list mismatch
+   */
+  index?: number | null;
+  /**
+   * Leaf index of the failing proof's key (`compute_purl_hash` for a purl); absent with `index` or when the key maps to no leaf
+   */
+  key_hash?: string | null;
+  /**
+   * `sbom_common::failure::name` of the committed code, e.g. "bad_hex" or "root_mismatch"
+   */
+  reason: string;
+}
+
+/**
+ * - `"purl"`: Package URL; the leaf index is SHA-256 of the purl as given
+ * - `"cpe"`: CPE name; the leaf index is SHA-256 of its canonical form (see [`compute_cpe_hash`])
+ * - `"raw_hash"`: The key is the leaf index itself, as 64 hex characters
+ */
+export type KeyType = "purl" | "cpe" | "raw_hash";
+
+/**
+ * A proof record as persisted by the proving-service (`proof_<timestamp>.json`). Only the fields that can be checked against the journal are read.
+ */
+export interface ProofFile {
+  banned_list_hash: string;
+  compliant: boolean;
+  /**
+   * Absent in files written before the guest committed its default hash table
+   */
+  defaults_digest?: string | null;
+  image_id: string[];
+  /**
+   * Absent in files written before non-purl keys, which are purl lists
+   */
+  key_type?: KeyType;
+  list_info?: ProofFileListInfo | null;
+  list_info_hash?: string | null;
+  /**
+   * Base64 receipt; absent when the receipt was stored separately and linked
+   */
+  proof?: string | null;
+  proof_count?: number | null;
+  proof_size?: number | null;
+  /**
+   * Absent in files written before membership proofs, which are non-membership proofs
+   */
+  proof_type?: ProofType;
+  reasons_hash?: string | null;
+  /**
+   * Absent in files of unsigned requests and files written before proofs were bound to their requester
+   */
+  requester_commitment?: string | null;
+  root_hash: string;
+  salt_commitment?: string | null;
+}
+
+export interface ProofFileListInfo {
+  entry_count: number;
+  publisher: string;
+  source_uri: string;
+  version: string;
+}
+
+/**
+ * - `"non_membership"`: Compliant when every leaf value is 0
+ * - `"membership"`: Compliant when every leaf value is nonzero
+ */
+export type ProofType = "non_membership" | "membership";
+
+export interface VerifyFileResponse {
+  banned_list_hash: string;
+  compliant: boolean;
+  file_consistency: FileInconsistency[];
+  image_id: string[];
+  proof_count?: number | null;
+  /**
+   * The receipt verifies against the file's image ID
+   */
+  proof_verified: boolean;
+  /**
+   * Kind of the receipt that verified: "composite", "succinct", "groth16" or "fake"
+   */
+  receipt_kind: string;
+  /**
+   * Values decoded from the journal, which are authoritative over the file's
+   */
+  root_hash: string;
+  /**
+   * The receipt verifies, but the file's metadata does not describe it
+   */
+  tampered: boolean;
+}
+
+export interface VerifyProofRequest {
+  /**
+   * Revealed banned list, e.g. the `purls` of the proof: the journal's banned_list_hash is recomputed from these keys, in order, so `banned_list_hash` may then be omitted
+   */
+  banned_list?: string[] | null;
+  /**
+   * May be omitted when `banned_list` is revealed or `expected_list_version` is given instead
+   */
+  banned_list_hash?: string;
+  /**
+   * Revealed hex salt that blinded the banned list commitment; requires `banned_list`
+   */
+  banned_list_salt?: string | null;
+  compliant: boolean;
+  /**
+   * Hex commitment to the banned list provenance (list_info) the receipt must contain
+   */
+  expected_list_info_hash?: string | null;
+  /**
+   * Published list version, resolved to a banned_list_hash through the signed list catalog
+   */
+  expected_list_version?: string | null;
+  /**
+   * Reject receipts that committed fewer proofs than this
+   */
+  expected_min_proof_count?: number | null;
+  /**
+   * Hex commitment to the (leaf_index, value) pairs the receipt must contain
+   */
+  expected_reasons_hash?: string | null;
+  /**
+   * Hex commitment to the API key id the proof must be bound to
+   */
+  expected_requester_commitment?: string | null;
+  generation_duration_ms?: number | null;
+  /**
+   * May be omitted with `proof_url`
+   */
+  image_id?: string[];
+  /**
+   * Kind of identifier the banned list is keyed by; the journal must state the same
+   */
+  key_type?: KeyType;
+  /**
+   * Base64 receipt; omitted when `proof_url` is given instead
+   */
+  proof?: string;
+  /**
+   * Receipt size in bytes as reported by the prover, used to diagnose truncation
+   */
+  proof_size?: number | null;
+  /**
+   * Statement `compliant` is claimed to attest; the journal must state the same
+   */
+  proof_type?: ProofType;
+  /**
+   * HTTPS URL of a proof file as persisted by the proving-service, fetched instead of sending `proof`. The file supplies the receipt, and `image_id`, `root_hash`, `banned_list_hash` and `proof_size` where the request leaves them out
+   */
+  proof_url?: string | null;
+  /**
+   * Named root resolved through this verifier's alias registry
+   */
+  root_alias?: string | null;
+  root_hash?: string;
+}
+
+export interface VerifyProofResponse {
+  banned_list_hash: string;
+  /**
+   * True when the banned list was revealed and checked against the journal
+   */
+  banned_list_revealed: boolean;
+  compliant: boolean;
+  /**
+   * True when the journal predates proof_count, so no count could be checked
+   */
+  count_unverified: boolean;
+  /**
+   * Digest of the default hash table the guest verified against; absent when the journal predates defaults_digest
+   */
+  defaults_digest?: string | null;
+  /**
+   * Why the proofs are not compliant, as committed in the journal; absent when they are and for journals that predate first_failure
+   */
+  first_failure?: FirstFailure | null;
+  generation_duration_ms?: number | null;
+  image_id: string[];
+  /**
+   * Kind of identifier the journal states the banned list is keyed by
+   */
+  key_type: string;
+  /**
+   * Present only when the journal committed provenance for the banned list
+   */
+  list_info_hash?: string | null;
+  /**
+   * Published list version the banned_list_hash was resolved from via the list catalog
+   */
+  list_version?: string | null;
+  proof_count?: number | null;
+  /**
+   * Statement the journal states `compliant` attests
+   */
+  proof_type: string;
+  proof_verified: boolean;
+  reasons_hash?: string | null;
+  /**
+   * Kind of the receipt that verified: "composite", "succinct", "groth16" or "fake"
+   */
+  receipt_kind: string;
+  /**
+   * Commitment to the API key id the proof is bound to; absent for proofs of unsigned requests and journals that predate requester_commitment
+   */
+  requester_commitment?: string | null;
+  root_alias?: string | null;
+  root_hash: string;
+  /**
+   * Present only when the journal's banned_list_hash is blinded with a salt
+   */
+  salt_commitment?: string | null;
+}
+
+/**
+ * Query parameters of `/verify/raw`: the claims of `VerifyProofRequest`, with the receipt sent as the raw request body instead of base64 in JSON
+ */
+export interface VerifyRawQuery {
+  banned_list_hash?: string;
+  compliant: boolean;
+  expected_list_info_hash?: string | null;
+  expected_list_version?: string | null;
+  expected_min_proof_count?: number | null;
+  expected_reasons_hash?: string | null;
+  expected_requester_commitment?: string | null;
+  generation_duration_ms?: number | null;
+  /**
+   * Comma-separated image ID words
+   */
+  image_id: string;
+  key_type?: KeyType;
+  proof_size?: number | null;
+  proof_type?: ProofType;
+  root_alias?: string | null;
+  root_hash?: string;
+}
+
+/**
+ * Body of `/verify-receipt`: only the receipt and the image ID it must verify against. Nothing about the journal is expected; its outputs are returned instead
+ */
+export interface VerifyReceiptRequest {
+  image_id: string[];
+  /**
+   * Base64 receipt
+   */
+  proof: string;
+}
+
+/**
+ * Everything the journal of a verified receipt states, decoded without comparing it to any expectation. Fields the journal's layout predates are absent
+ */
+export interface VerifyReceiptResponse {
+  banned_list_hash: string;
+  compliant: boolean;
+  defaults_digest?: string | null;
+  /**
+   * Why the proofs are not compliant; absent when they are and for journals that predate first_failure
+   */
+  first_failure?: FirstFailure | null;
+  image_id: string[];
+  /**
+   * Kind of identifier the journal states the banned list is keyed by
+   */
+  key_type: string;
+  /**
+   * Present only when the journal committed provenance for the banned list
+   */
+  list_info_hash?: string | null;
+  proof_count?: number | null;
+  /**
+   * Statement the journal states `compliant` attests
+   */
+  proof_type: string;
+  /**
+   * The receipt verifies against the image ID
+   */
+  proof_verified: boolean;
+  reasons_hash?: string | null;
+  /**
+   * Kind of the receipt that verified: "composite", "succinct", "groth16" or "fake"
+   */
+  receipt_kind: string;
+  /**
+   * Present only when the proof is bound to the API key of a signed request
+   */
+  requester_commitment?: string | null;
+  root_hash: string;
+  /**
+   * Present only when the journal's banned_list_hash is blinded with a salt
+   */
+  salt_commitment?: string | null;
+}
@@ -0,0 +1,85 @@
+package handlers
+
+import (
+	"bytes"
+	"encoding/json"
+	"flag"
+	"net/http"
+	"net/http/httptest"
+	"os"
+	"path/filepath"
+	"testing"
+
+	"merkle-proof-service/service"
+
+	"github.com/gin-gonic/gin"
+)
+
+var update = flag.Bool("update", false, "rewrite the fixtures shared with the Rust implementation")
+
+// proveBatchFixture is the /prove-batch response for provedPurls against the snapshot
+// fixture written by the service tests. sbom-common must produce the same bytes.
+const (
+	snapshotFixture   = "../../proving-service/common/testdata/go-smt-snapshot.json"
+	proveBatchFixture = "../../proving-service/common/testdata/go-smt-prove-batch.json"
+)
+
+// provedPurls are three leaves of the snapshot fixture followed by two absent purls
+var provedPurls = []string{
+	"pkg:maven/org.apache.logging.log4j/log4j-core@2.14.1",
+	"pkg:npm/event-stream@3.3.6",
+	"pkg:maven/org.springframework/spring-beans@5.3.17",
+	"pkg:npm/left-pad@1.3.0",
+	"pkg:maven/org.apache.commons/commons-lang3@3.12.0",
+}
+
+func TestProveBatchFixture(t *testing.T) {
+	snapshot, err := os.ReadFile(snapshotFixture)
+	if err != nil {
+		t.Fatal(err)
+	}
+	var meta struct {
+		Root string `json:"root"`
+	}
+	if err := json.Unmarshal(snapshot, &meta); err != nil {
+		t.Fatal(err)
+	}
+
+	storage, err := service.NewStorage(filepath.Join(t.TempDir(), "smt.db"))
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer storage.Close()
+	if err := storage.StoreSMT(meta.Root, snapshot); err != nil {
+		t.Fatal(err)
+	}
+
+	body, err := json.Marshal(ProveBatchRequest{Root: meta.Root, PURLs: provedPurls, Compress: true})
+	if err != nil {
+		t.Fatal(err)
+	}
+	gin.SetMode(gin.TestMode)
+	w := httptest.NewRecorder()
+	c, _ := gin.CreateTestContext(w)
+	c.Request = httptest.NewRequest(http.MethodPost, "/prove-batch", bytes.NewReader(body))
+	c.Request.Header.Set("Content-Type", "application/json")
+	NewHandler(service.NewSMTService(storage)).ProveBatch(c)
+	if w.Code != http.StatusOK {
+		t.Fatalf("status %d: %s", w.Code, w.Body.String())
+	}
+
+	got := w.Body.Bytes()
+	if *update {
+		if err := os.WriteFile(proveBatchFixture, got, 0o644); err != nil {
+			t.Fatal(err)
+		}
+	}
+
+	want, err := os.ReadFile(proveBatchFixture)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if !bytes.Equal(got, want) {
+		t.Errorf("%s is stale: rerun with -update, then run the sbom-common tests", proveBatchFixture)
+	}
+}
@@ -0,0 +1,57 @@
+package service
+
+import (
+	"bytes"
+	"encoding/json"
+	"flag"
+	"math/big"
+	"os"
+	"testing"
+)
+
+var update = flag.Bool("update", false, "rewrite the fixtures shared with the Rust implementation")
+
+// snapshotFixture is the stored form of the fixture tree. sbom-common imports it and
+// must export it again byte for byte.
+const snapshotFixture = "../../proving-service/common/testdata/go-smt-snapshot.json"
+
+// fixtureItems are the leaves of the fixture tree
+var fixtureItems = map[string]int64{
+	"pkg:maven/org.apache.logging.log4j/log4j-core@2.14.1": 1,
+	"pkg:npm/event-stream@3.3.6":                           2,
+	"pkg:pypi/ctx@0.2.6":                                   1,
+	"pkg:maven/org.springframework/spring-beans@5.3.17":    3,
+	"pkg:npm/ua-parser-js@0.7.29":                          1,
+}
+
+func TestSnapshotFixture(t *testing.T) {
+	smt, err := newSMT(256)
+	if err != nil {
+		t.Fatal(err)
+	}
+	items := make(map[string]*big.Int, len(fixtureItems))
+	for purl, value := range fixtureItems {
+		items[purl] = big.NewInt(value)
+	}
+	if _, err := smt.Build(items); err != nil {
+		t.Fatal(err)
+	}
+
+	got, err := json.Marshal(smt)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if *update {
+		if err := os.WriteFile(snapshotFixture, got, 0o644); err != nil {
+			t.Fatal(err)
+		}
+	}
+
+	want, err := os.ReadFile(snapshotFixture)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if !bytes.Equal(got, want) {
+		t.Errorf("%s is stale: rerun with -update, then run the sbom-common tests", snapshotFixture)
+	}
+}
@@ -93,9 +93,15 @@ class ProofService:
             root=root, depth=depth, merkle_proofs=merkle_proofs
         )
 
-        proven_banned_list_hash = proving_response.get("banned_list_hash")
-        compliant = proving_response.get("compliant", False)
-        proof_root_hash_raw = proving_response.get("root_hash", root_hash)
+        # The proof comes in the "sbom-proof" envelope, or bare from older services
+        proof = (
+            proving_response["data"]
+            if proving_response.get("format") == "sbom-proof"
+            else proving_response
+        )
+        proven_banned_list_hash = proof.get("banned_list_hash")
+        compliant = proof.get("compliant", False)
+        proof_root_hash_raw = proof.get("root_hash", root_hash)
         proof_root_hash = _normalize_hash(proof_root_hash_raw)
 
         if not proven_banned_list_hash:
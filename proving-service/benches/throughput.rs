@@ -0,0 +1,164 @@
+//! Host-side throughput benchmarks.
+//!
+//! Run with `cargo bench --bench throughput`; `bench-gate` compares the results against
+//! `benchmark/baseline.json`. Set `BENCH_RECEIPT` to a raw receipt file (as served by
+//! `/proofs/{id}/raw`) to include receipt (de)serialization.
+
+use criterion::{
+    BatchSize, BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main,
+};
+use proving_service::digest::canonical_request_digest;
+use proving_service::models::{CompactMerkleProof, ProveCompactMerkleRequest};
+use proving_service::utils::{compute_compact_root, compute_purl_hash, hash_value, hex_to_bytes32};
+use proving_service::{ProverConfig, ProverService};
+use risc0_zkvm::Receipt;
+use risc0_zkvm::serde::{from_slice, to_vec};
+
+const FIXTURE: &str = include_str!("../benchmark/data/merkleproofs/batch_proof_200.json");
+
+fn fixture() -> ProveCompactMerkleRequest {
+    serde_json::from_str(FIXTURE).expect("fixture is a valid prove request")
+}
+
+/// `count` proofs, cycling through the 200 fixture proofs
+fn proofs(count: usize) -> Vec<CompactMerkleProof> {
+    fixture()
+        .merkle_proofs
+        .into_iter()
+        .cycle()
+        .take(count)
+        .collect()
+}
+
+/// The per-proof work host validation does: parse, check the key binding, fold to the root
+fn recompute(proof: &CompactMerkleProof) -> [u8; 32] {
+    let bitmap = hex_to_bytes32(&proof.bitmap).unwrap();
+    let leaf_index = hex_to_bytes32(&proof.leaf_index).unwrap();
+    assert_eq!(leaf_index, compute_purl_hash(&proof.key));
+    let siblings: Vec<[u8; 32]> = proof
+        .siblings
+        .iter()
+        .map(|s| hex_to_bytes32(s).unwrap())
+        .collect();
+    compute_compact_root(
+        &hash_value(&proof.value).unwrap(),
+        &leaf_index,
+        &bitmap,
+        &siblings,
+    )
+    .unwrap()
+}
+
+fn host_validation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("host_validation");
+    for count in [1_000, 5_000] {
+        let proofs = proofs(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &proofs, |b, proofs| {
+            b.iter(|| proofs.iter().map(recompute).count())
+        });
+    }
+    group.finish();
+}
+
+/// Proofs in the largest request of fixture proofs whose siblings fit the guest's budget
+const SERVICE_VALIDATION_PROOFS: usize = 2_500;
+
+/// The service's own preflight of a request, on one thread and on every core
+fn service_validation(c: &mut Criterion) {
+    let service = ProverService::without_prover(ProverConfig {
+        proofs_dir: std::env::temp_dir().join("throughput-bench"),
+        max_proofs_per_request: SERVICE_VALIDATION_PROOFS,
+        ..ProverConfig::default()
+    });
+    let request = || ProveCompactMerkleRequest {
+        merkle_proofs: proofs(SERVICE_VALIDATION_PROOFS),
+        ..fixture()
+    };
+    let single_thread = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("service_validation");
+    group.throughput(Throughput::Elements(SERVICE_VALIDATION_PROOFS as u64));
+    group.bench_function("sequential", |b| {
+        b.iter_batched(
+            request,
+            |request| single_thread.install(|| service.validate(request).unwrap()),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("parallel", |b| {
+        b.iter_batched(
+            request,
+            |request| service.validate(request).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn canonical_hashing(c: &mut Criterion) {
+    let mut request = fixture();
+    request.merkle_proofs = proofs(10_000);
+    for (i, proof) in request.merkle_proofs.iter_mut().enumerate() {
+        proof.key = format!("{}#{}", proof.key, i);
+    }
+
+    let mut group = c.benchmark_group("canonical_hashing");
+    group.throughput(Throughput::Elements(10_000));
+    group.bench_function("10000", |b| {
+        b.iter(|| canonical_request_digest(black_box(&request)))
+    });
+    group.finish();
+}
+
+fn hex_decode(c: &mut Criterion) {
+    let hashes: Vec<String> = proofs(1_000).into_iter().flat_map(|p| p.siblings).collect();
+
+    let mut group = c.benchmark_group("hex_decode");
+    group.throughput(Throughput::Bytes(
+        hashes.iter().map(|h| h.len() as u64).sum(),
+    ));
+    group.bench_function("siblings", |b| {
+        b.iter(|| {
+            for h in &hashes {
+                black_box(hex_to_bytes32(h).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn receipt_serde(c: &mut Criterion) {
+    let Ok(path) = std::env::var("BENCH_RECEIPT") else {
+        return;
+    };
+    let bytes = std::fs::read(&path).expect("BENCH_RECEIPT is readable");
+    let words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    let receipt: Receipt = from_slice(&words).expect("BENCH_RECEIPT is a valid receipt");
+
+    let mut group = c.benchmark_group("receipt_serde");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("serialize", |b| {
+        b.iter(|| to_vec(black_box(&receipt)).unwrap())
+    });
+    group.bench_function("deserialize", |b| {
+        b.iter(|| from_slice::<Receipt, u32>(black_box(&words)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    host_validation,
+    service_validation,
+    canonical_hashing,
+    hex_decode,
+    receipt_serde
+);
+criterion_main!(benches);
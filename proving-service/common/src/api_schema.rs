@@ -0,0 +1,335 @@
+//! JSON Schema and TypeScript definitions of the services' HTTP models.
+//!
+//! Each service lists its request and response models in an [`ApiSchema`], which renders
+//! them as a JSON Schema document (served at `GET /schema`) and as a `.d.ts` file for
+//! frontend clients. Both are generated from the serde derives, so they follow renames,
+//! defaults and skipped fields exactly. Checked-in copies live in `docs/api`, and each
+//! service's `api-schema` binary fails in check mode when they are stale, so a model
+//! change cannot ship without regenerating them.
+
+use std::fmt::Write as _;
+
+use schemars::JsonSchema;
+use schemars::r#gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{InstanceType, Metadata, RootSchema, Schema, SchemaObject, SingleOrVec};
+
+/// Prefix of references to other definitions in the generated schemas
+const DEFINITIONS_PREFIX: &str = "#/definitions/";
+
+pub struct ApiSchema {
+    title: &'static str,
+    version: u32,
+    generator: SchemaGenerator,
+}
+
+impl ApiSchema {
+    /// An empty schema for the API named `title`. Bump `version` whenever a model changes
+    /// shape, so clients can tell which definitions they were built against.
+    pub fn new(title: &'static str, version: u32) -> Self {
+        Self {
+            title,
+            version,
+            generator: SchemaSettings::draft07().into_generator(),
+        }
+    }
+
+    /// Add `T` and every type it refers to
+    pub fn with<T: JsonSchema>(mut self) -> Self {
+        self.generator.subschema_for::<T>();
+        self
+    }
+
+    pub fn title(&self) -> &'static str {
+        self.title
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Every model as a definition of one draft-07 document. Validate a payload against
+    /// `{"$ref": "<document>#/definitions/<Model>"}`.
+    pub fn json_schema(&self) -> RootSchema {
+        let mut schema = SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                title: Some(format!("{} API", self.title)),
+                description: Some(format!(
+                    "Request and response models of the {}, version {}",
+                    self.title, self.version
+                )),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        schema
+            .extensions
+            .insert("version".to_string(), self.version.into());
+
+        RootSchema {
+            meta_schema: self.generator.settings().meta_schema.clone(),
+            schema,
+            definitions: self.generator.definitions().clone(),
+        }
+    }
+
+    /// Every model as a TypeScript declaration, in name order
+    pub fn typescript(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "// Generated from the {} API models by `cargo run --bin api-schema -- --typescript`.",
+            self.title
+        );
+        let _ = writeln!(out, "// Do not edit; regenerate it when the models change.");
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "export declare const API_SCHEMA_VERSION: {};",
+            self.version
+        );
+
+        for (name, schema) in self.generator.definitions() {
+            let _ = writeln!(out);
+            write_declaration(&mut out, name, schema);
+        }
+        out
+    }
+}
+
+/// Handle the command line of an `api-schema` binary:
+///
+/// ```text
+/// api-schema                                print the JSON Schema to stdout
+/// api-schema --typescript                   print the TypeScript definitions to stdout
+/// api-schema --check <schema.json> <d.ts>   fail if either file differs from the models
+/// ```
+pub fn cli(schema: &ApiSchema) {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let json = serde_json::to_string_pretty(&schema.json_schema()).expect("schema serializes");
+
+    match args.as_slice() {
+        [] => println!("{json}"),
+        [flag] if flag == "--typescript" => print!("{}", schema.typescript()),
+        [flag, json_path, ts_path] if flag == "--check" => {
+            let json_golden = read_golden(json_path);
+            let ts_golden = read_golden(ts_path);
+            let current: serde_json::Value =
+                serde_json::from_str(&json).expect("schema is valid JSON");
+            let json_matches = serde_json::from_str::<serde_json::Value>(&json_golden)
+                .map(|golden| golden == current)
+                .unwrap_or(false);
+            let ts_matches = ts_golden == schema.typescript();
+            if !json_matches || !ts_matches {
+                let stale = match (json_matches, ts_matches) {
+                    (false, false) => format!("'{json_path}' and '{ts_path}' differ"),
+                    (false, true) => format!("'{json_path}' differs"),
+                    _ => format!("'{ts_path}' differs"),
+                };
+                eprintln!(
+                    "{stale} from the {} API models. If a model changed intentionally, bump API_SCHEMA_VERSION and regenerate them with `cargo run --bin api-schema > {json_path}` and `cargo run --bin api-schema -- --typescript > {ts_path}`",
+                    schema.title()
+                );
+                std::process::exit(1);
+            }
+            eprintln!("'{json_path}' and '{ts_path}' are up to date");
+        }
+        _ => {
+            eprintln!("Usage: api-schema [--typescript | --check <schema.json> <types.d.ts>]");
+            std::process::exit(2);
+        }
+    }
+}
+
+fn read_golden(path: &str) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read '{path}': {e}");
+        std::process::exit(2);
+    })
+}
+
+/// An interface for plain objects, a type alias for everything else
+fn write_declaration(out: &mut String, name: &str, schema: &Schema) {
+    let Schema::Object(object) = schema else {
+        let _ = writeln!(out, "export type {} = {};", name, ts_type(schema));
+        return;
+    };
+
+    let mut doc = description(object).unwrap_or_default().to_string();
+    // Variant docs of a string enum would be lost in the union, so they join the type's
+    if let Some(variants) = object.subschemas.as_ref().and_then(|s| s.one_of.as_ref()) {
+        for variant in variants {
+            if let Schema::Object(variant) = variant
+                && let Some(values) = &variant.enum_values
+            {
+                let variant_doc = description(variant).unwrap_or("").replace('\n', " ");
+                for value in values {
+                    let _ = write!(doc, "\n- `{value}`: {variant_doc}");
+                }
+            }
+        }
+    }
+    write_doc(out, doc.trim(), "");
+
+    match object.object.as_ref() {
+        Some(fields) if !fields.properties.is_empty() && object.subschemas.is_none() => {
+            let _ = writeln!(out, "export interface {name} {{");
+            for (field, schema) in &fields.properties {
+                if let Schema::Object(field_object) = schema {
+                    write_doc(out, description(field_object).unwrap_or(""), "  ");
+                }
+                let optional = if fields.required.contains(field) {
+                    ""
+                } else {
+                    "?"
+                };
+                let _ = writeln!(
+                    out,
+                    "  {}{}: {};",
+                    property_name(field),
+                    optional,
+                    ts_type(schema)
+                );
+            }
+            let _ = writeln!(out, "}}");
+        }
+        _ => {
+            let _ = writeln!(out, "export type {} = {};", name, ts_type(schema));
+        }
+    }
+}
+
+fn write_doc(out: &mut String, doc: &str, indent: &str) {
+    if doc.is_empty() {
+        return;
+    }
+    let _ = writeln!(out, "{indent}/**");
+    for line in doc.lines() {
+        let line = line.replace("*/", "*\\/");
+        if line.is_empty() {
+            let _ = writeln!(out, "{indent} *");
+        } else {
+            let _ = writeln!(out, "{indent} * {line}");
+        }
+    }
+    let _ = writeln!(out, "{indent} */");
+}
+
+fn description(object: &SchemaObject) -> Option<&str> {
+    object.metadata.as_ref()?.description.as_deref()
+}
+
+/// Quote property names that are not plain identifiers
+fn property_name(name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        name.to_string()
+    } else {
+        format!("{name:?}")
+    }
+}
+
+/// The TypeScript type accepting the same values as `schema`
+fn ts_type(schema: &Schema) -> String {
+    let object = match schema {
+        Schema::Bool(true) => return "unknown".to_string(),
+        Schema::Bool(false) => return "never".to_string(),
+        Schema::Object(object) => object,
+    };
+
+    if let Some(reference) = &object.reference {
+        return reference
+            .strip_prefix(DEFINITIONS_PREFIX)
+            .unwrap_or(reference)
+            .to_string();
+    }
+    if let Some(values) = &object.enum_values {
+        return union(values.iter().map(|value| value.to_string()));
+    }
+    if let Some(value) = &object.const_value {
+        return value.to_string();
+    }
+    if let Some(subschemas) = &object.subschemas {
+        if let Some(all_of) = &subschemas.all_of
+            && let [single] = all_of.as_slice()
+        {
+            return ts_type(single);
+        }
+        if let Some(members) = subschemas.any_of.as_ref().or(subschemas.one_of.as_ref()) {
+            return union(members.iter().map(ts_type));
+        }
+    }
+
+    match &object.instance_type {
+        Some(SingleOrVec::Single(instance)) => instance_type(object, **instance),
+        Some(SingleOrVec::Vec(instances)) => union(
+            instances
+                .iter()
+                .map(|instance| instance_type(object, *instance)),
+        ),
+        None => "unknown".to_string(),
+    }
+}
+
+fn instance_type(object: &SchemaObject, instance: InstanceType) -> String {
+    match instance {
+        InstanceType::Null => "null".to_string(),
+        InstanceType::Boolean => "boolean".to_string(),
+        InstanceType::Integer | InstanceType::Number => "number".to_string(),
+        InstanceType::String => "string".to_string(),
+        InstanceType::Array => {
+            let item = match object.array.as_ref().and_then(|a| a.items.as_ref()) {
+                Some(SingleOrVec::Single(item)) => ts_type(item),
+                Some(SingleOrVec::Vec(items)) => {
+                    return format!(
+                        "[{}]",
+                        items.iter().map(ts_type).collect::<Vec<_>>().join(", ")
+                    );
+                }
+                None => "unknown".to_string(),
+            };
+            if item.contains(' ') {
+                format!("({item})[]")
+            } else {
+                format!("{item}[]")
+            }
+        }
+        InstanceType::Object => match object.object.as_ref() {
+            Some(fields) if !fields.properties.is_empty() => {
+                let members: Vec<String> = fields
+                    .properties
+                    .iter()
+                    .map(|(field, schema)| {
+                        let optional = if fields.required.contains(field) {
+                            ""
+                        } else {
+                            "?"
+                        };
+                        format!("{}{}: {}", property_name(field), optional, ts_type(schema))
+                    })
+                    .collect();
+                format!("{{ {} }}", members.join("; "))
+            }
+            Some(fields) => match fields.additional_properties.as_deref() {
+                Some(Schema::Bool(false)) => "Record<string, never>".to_string(),
+                Some(values) => format!("Record<string, {}>", ts_type(values)),
+                None => "Record<string, unknown>".to_string(),
+            },
+            None => "Record<string, unknown>".to_string(),
+        },
+    }
+}
+
+/// `a | b`, without duplicates
+fn union(members: impl Iterator<Item = String>) -> String {
+    let mut unique: Vec<String> = Vec::new();
+    for member in members {
+        if !unique.contains(&member) {
+            unique.push(member);
+        }
+    }
+    unique.join(" | ")
+}
@@ -0,0 +1,174 @@
+//! Self-describing envelope of persisted proofs.
+//!
+//! A proof document on disk or in a response names its own format, so a file found long
+//! after the service that wrote it is gone can still be interpreted:
+//!
+//! ```json
+//! {
+//!   "format": "sbom-proof",
+//!   "format_version": 1,
+//!   "schema_url": "https://prover.example/schema",
+//!   "created_by": "proving-service/0.3.0",
+//!   "data": { "proof_id": "…", "root_hash": "…", … }
+//! }
+//! ```
+//!
+//! Documents written before the envelope are the bare `data` object. [`open`] reads both,
+//! so readers can accept legacy files for as long as their deployment allows.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// `format` of every enveloped proof document
+pub const PROOF_FORMAT: &str = "sbom-proof";
+
+/// Envelope layout written by this build. Readers accept every version up to it.
+pub const PROOF_FORMAT_VERSION: u32 = 1;
+
+/// A proof document with what is needed to interpret it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ProofEnvelope<T> {
+    /// Always "sbom-proof", [`PROOF_FORMAT`]
+    pub format: String,
+    pub format_version: u32,
+    /// JSON Schema of the service API, which describes `data`
+    pub schema_url: String,
+    /// Service and version that wrote the document, as `name/version`
+    pub created_by: String,
+    pub data: T,
+}
+
+impl<T> ProofEnvelope<T> {
+    /// `data` in an envelope of the current format version
+    pub fn new(data: T, schema_url: impl Into<String>, created_by: impl Into<String>) -> Self {
+        Self {
+            format: PROOF_FORMAT.to_string(),
+            format_version: PROOF_FORMAT_VERSION,
+            schema_url: schema_url.into(),
+            created_by: created_by.into(),
+            data,
+        }
+    }
+}
+
+/// Why a proof document could not be opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvelopeError {
+    /// The document predates envelopes and the reader no longer accepts those
+    Bare,
+    /// The document is an envelope of some other format
+    UnknownFormat(String),
+    /// The envelope is newer than this build understands
+    UnsupportedVersion(u32),
+    /// The envelope lacks a field or has one of the wrong type
+    Malformed(String),
+}
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvelopeError::Bare => write!(
+                f,
+                "the document is a bare proof without the '{PROOF_FORMAT}' envelope, which is no longer accepted"
+            ),
+            EnvelopeError::UnknownFormat(format) => write!(
+                f,
+                "the document's format is '{format}', expected '{PROOF_FORMAT}'"
+            ),
+            EnvelopeError::UnsupportedVersion(version) => write!(
+                f,
+                "the document is in format version {version}, but this build reads up to version {PROOF_FORMAT_VERSION}"
+            ),
+            EnvelopeError::Malformed(reason) => write!(f, "malformed proof envelope: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+/// A proof document's payload and the envelope it came in, if any
+#[derive(Debug, Clone, PartialEq)]
+pub struct Opened {
+    pub data: Value,
+    /// `None` for a bare legacy document
+    pub envelope: Option<ProofEnvelope<()>>,
+}
+
+impl Opened {
+    pub fn is_bare(&self) -> bool {
+        self.envelope.is_none()
+    }
+
+    /// The document as it was opened: `data` back in its envelope, or bare
+    pub fn into_document(self) -> Value {
+        match self.envelope {
+            Some(envelope) => serde_json::json!({
+                "format": envelope.format,
+                "format_version": envelope.format_version,
+                "schema_url": envelope.schema_url,
+                "created_by": envelope.created_by,
+                "data": self.data,
+            }),
+            None => self.data,
+        }
+    }
+}
+
+/// Take the payload out of `document`. An object with both `format` and `data` is an
+/// envelope and must be a readable one; anything else is a bare legacy document, which
+/// is returned as it is when `accept_bare` allows and refused otherwise.
+pub fn open(document: Value, accept_bare: bool) -> Result<Opened, EnvelopeError> {
+    let Value::Object(mut fields) = document else {
+        return bare(document, accept_bare);
+    };
+    if !(fields.contains_key("format") && fields.contains_key("data")) {
+        return bare(Value::Object(fields), accept_bare);
+    }
+
+    let text = |fields: &serde_json::Map<String, Value>, name: &str| match fields.get(name) {
+        Some(Value::String(value)) => Ok(value.clone()),
+        _ => Err(EnvelopeError::Malformed(format!(
+            "'{name}' must be a string"
+        ))),
+    };
+    let format = text(&fields, "format")?;
+    if format != PROOF_FORMAT {
+        return Err(EnvelopeError::UnknownFormat(format));
+    }
+    let format_version = fields
+        .get("format_version")
+        .and_then(Value::as_u64)
+        .and_then(|version| u32::try_from(version).ok())
+        .filter(|&version| version > 0)
+        .ok_or_else(|| {
+            EnvelopeError::Malformed("'format_version' must be a positive integer".to_string())
+        })?;
+    if format_version > PROOF_FORMAT_VERSION {
+        return Err(EnvelopeError::UnsupportedVersion(format_version));
+    }
+    let envelope = ProofEnvelope {
+        format,
+        format_version,
+        schema_url: text(&fields, "schema_url")?,
+        created_by: text(&fields, "created_by")?,
+        data: (),
+    };
+
+    Ok(Opened {
+        data: fields.remove("data").unwrap_or_default(),
+        envelope: Some(envelope),
+    })
+}
+
+fn bare(document: Value, accept_bare: bool) -> Result<Opened, EnvelopeError> {
+    if !accept_bare {
+        return Err(EnvelopeError::Bare);
+    }
+    Ok(Opened {
+        data: document,
+        envelope: None,
+    })
+}
@@ -0,0 +1,41 @@
+//! Values of the journal's `first_failure` field: why the first proof that kept a request
+//! from being compliant failed.
+
+pub const NONE: u32 = 0;
+/// The proofs JSON did not parse, so no proof can be named
+pub const MALFORMED_PROOFS: u32 = 1;
+/// The proof's value was not a decimal u64
+pub const BAD_VALUE: u32 = 2;
+/// The bitmap, leaf_index or a sibling was not a 32-byte hex string
+pub const BAD_HEX: u32 = 3;
+/// The leaf_index was not the one the proof's key maps to
+pub const LEAF_INDEX_MISMATCH: u32 = 4;
+/// The bitmap marked more siblings than the proof carried
+pub const MISSING_SIBLING: u32 = 5;
+/// The proof folded to a root other than the committed one
+pub const ROOT_MISMATCH: u32 = 6;
+/// Every proof verified, but this one's value is not one the proof type accepts
+pub const VALUE_REJECTED: u32 = 7;
+/// Strict mode: the proof keys did not hash to the expected banned list hash, so no
+/// single proof can be named
+pub const LIST_MISMATCH: u32 = 8;
+
+pub fn name(failure: u32) -> &'static str {
+    match failure {
+        NONE => "none",
+        MALFORMED_PROOFS => "malformed_proofs",
+        BAD_VALUE => "bad_value",
+        BAD_HEX => "bad_hex",
+        LEAF_INDEX_MISMATCH => "leaf_index_mismatch",
+        MISSING_SIBLING => "missing_sibling",
+        ROOT_MISMATCH => "root_mismatch",
+        VALUE_REJECTED => "value_rejected",
+        LIST_MISMATCH => "list_mismatch",
+        _ => "unknown",
+    }
+}
+
+/// Whether `first_failure_index` and `first_failure_key_hash` name a proof for this failure
+pub fn names_proof(failure: u32) -> bool {
+    matches!(failure, BAD_VALUE..=VALUE_REJECTED)
+}
@@ -0,0 +1,154 @@
+//! Framing of the inputs the host writes to the compact merkle guest.
+//!
+//! Every guest input starts with an [`InputHeader`]. The guest dispatches on its
+//! `protocol_version`; the host picks the highest version both sides support, reading
+//! the guest's side from the manifest shipped next to its ELF.
+//!
+//! - Version 1 emulates the original unframed input: the header is followed by the proofs
+//!   JSON string and then the public inputs, each as one `env::read` value.
+//! - Version 2 is followed by `section_count` [`Section`]s. Each carries its payload as
+//!   RISC Zero serde words so a guest can skip sections it does not understand. Unknown
+//!   sections marked `mandatory` make the guest reject the input instead.
+
+/// Header followed by the two legacy values in fixed order
+pub const PROTOCOL_LEGACY: u32 = 1;
+/// Header followed by tagged sections
+pub const PROTOCOL_SECTIONS: u32 = 2;
+/// Protocol versions this build of the host can write, oldest first
+pub const HOST_PROTOCOLS: &[u32] = &[PROTOCOL_LEGACY, PROTOCOL_SECTIONS];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputHeader {
+    pub protocol_version: u32,
+    /// Reserved; must be 0
+    pub flags: u32,
+    /// Number of sections that follow; 0 for the legacy protocol
+    pub section_count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Section {
+    pub tag: u32,
+    /// A guest that does not know `tag` must reject the input rather than skip it
+    pub mandatory: bool,
+    /// The section value encoded with RISC Zero serde
+    pub payload: Vec<u32>,
+}
+
+/// Longest hex string accepted for a 32-byte hash: 64 digits and an optional `0x` prefix.
+/// Strings are checked against it before they are decoded.
+pub const MAX_HASH_HEX_LEN: usize = 66;
+
+/// Cap on the summed length of the sibling hex strings of all proofs in one input. It is
+/// part of the guest image, so a journal also attests the bound its input was held to;
+/// worst-case proofs (a sibling on every level) fit about 120 to a request.
+pub const MAX_SIBLING_BYTES: usize = 2 * 1024 * 1024;
+
+/// Section tags
+pub mod tag {
+    /// The proofs as a JSON string
+    pub const PROOFS: u32 = 1;
+    /// `MerklePublicInputs`
+    pub const PUBLIC_INPUTS: u32 = 2;
+    /// The 32-byte salt blinding the banned list commitment. Always sent as mandatory, so
+    /// a guest that cannot blind rejects the input instead of committing the bare list hash
+    pub const BLINDING_SALT: u32 = 3;
+    /// `ProofType::code` as a u32. Sent, as mandatory, only for membership proofs, so a
+    /// guest that cannot prove membership rejects the input; absent means non-membership
+    pub const PROOF_TYPE: u32 = 4;
+    /// The 32-byte requester commitment. Sent, as mandatory, only for authenticated
+    /// requests, so a guest that cannot commit it rejects the input; absent means the
+    /// request was unauthenticated
+    pub const REQUESTER: u32 = 5;
+}
+
+/// Values of the journal's `input_status` field
+pub mod status {
+    pub const OK: u32 = 0;
+    /// The header named a protocol version the guest does not implement
+    pub const UNSUPPORTED_PROTOCOL: u32 = 1;
+    /// A section the guest does not know was marked mandatory
+    pub const UNKNOWN_MANDATORY_SECTION: u32 = 2;
+    /// Reserved flags were set, or a required section was missing, repeated or undecodable
+    pub const MALFORMED_INPUT: u32 = 3;
+    /// A proof marked a sibling equal to the default hash of its level; the canonical
+    /// compact encoding never does, so the proof was not produced by a conforming prover
+    pub const NON_CANONICAL_PROOF: u32 = 4;
+    /// The proofs did not all use the same key type, so the journal could not state one
+    pub const MIXED_KEY_TYPES: u32 = 5;
+    /// A proof's sibling count differed from the number of bits set in its bitmap
+    pub const SIBLING_COUNT_MISMATCH: u32 = 6;
+    /// A hash string was longer than `MAX_HASH_HEX_LEN`, or the siblings of all proofs
+    /// together exceeded `MAX_SIBLING_BYTES`
+    pub const OVERSIZED_INPUT: u32 = 7;
+    /// The proofs JSON nested deeper than `json_limits::PROOFS_JSON_LIMITS` allows
+    pub const JSON_TOO_DEEP: u32 = 8;
+    /// The proofs JSON held more tokens than `json_limits::PROOFS_JSON_LIMITS` allows
+    pub const JSON_TOO_MANY_TOKENS: u32 = 9;
+
+    pub fn name(status: u32) -> &'static str {
+        match status {
+            OK => "ok",
+            UNSUPPORTED_PROTOCOL => "unsupported_protocol",
+            UNKNOWN_MANDATORY_SECTION => "unknown_mandatory_section",
+            MALFORMED_INPUT => "malformed_input",
+            NON_CANONICAL_PROOF => "non_canonical_proof",
+            MIXED_KEY_TYPES => "mixed_key_types",
+            SIBLING_COUNT_MISMATCH => "sibling_count_mismatch",
+            OVERSIZED_INPUT => "oversized_input",
+            JSON_TOO_DEEP => "json_too_deep",
+            JSON_TOO_MANY_TOKENS => "json_too_many_tokens",
+            _ => "unknown",
+        }
+    }
+}
+
+/// Highest protocol version supported by both the host and the guest, if any
+pub fn negotiate(guest_protocols: &[u32]) -> Option<u32> {
+    HOST_PROTOCOLS
+        .iter()
+        .copied()
+        .filter(|version| guest_protocols.contains(version))
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_newest_shared_protocol_is_chosen() {
+        assert_eq!(negotiate(&[PROTOCOL_LEGACY]), Some(PROTOCOL_LEGACY));
+        assert_eq!(
+            negotiate(&[PROTOCOL_SECTIONS, PROTOCOL_LEGACY]),
+            Some(PROTOCOL_SECTIONS)
+        );
+        // Versions only the guest knows are ignored, not guessed at
+        assert_eq!(negotiate(&[PROTOCOL_SECTIONS, 9]), Some(PROTOCOL_SECTIONS));
+        assert_eq!(negotiate(&[9]), None);
+        assert_eq!(negotiate(&[]), None);
+    }
+
+    #[test]
+    fn section_tags_and_statuses_are_distinct() {
+        let tags = [
+            tag::PROOFS,
+            tag::PUBLIC_INPUTS,
+            tag::BLINDING_SALT,
+            tag::PROOF_TYPE,
+            tag::REQUESTER,
+        ];
+        for (i, a) in tags.iter().enumerate() {
+            assert!(tags[i + 1..].iter().all(|b| a != b), "tag {a} reused");
+        }
+
+        let names: [&str; 10] = core::array::from_fn(|code| status::name(code as u32));
+        for (code, name) in names.iter().enumerate() {
+            assert_ne!(*name, "unknown", "status {code} has no name");
+            assert!(!names[code + 1..].contains(name), "{name} reused");
+        }
+        assert_eq!(status::name(10), "unknown");
+    }
+}
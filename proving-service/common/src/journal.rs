@@ -0,0 +1,337 @@
+//! Byte-level description of the journal committed by the compact merkle guest.
+//!
+//! The guest commits its outputs with RISC Zero's word-oriented serde. This module is
+//! the single description of that layout; the journal-schema generator and the
+//! verifier-service `/info` endpoint both render it, so changing the guest outputs
+//! without updating it is caught by the generator's check mode.
+
+/// Bump whenever a field is added, removed, reordered or changes type.
+pub const JOURNAL_SCHEMA_VERSION: u32 = 11;
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct JournalField {
+    pub name: &'static str,
+    pub rust_type: &'static str,
+    /// Number of 32-bit words the field occupies in the journal
+    pub words: usize,
+    pub description: &'static str,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct JournalLayout {
+    pub name: &'static str,
+    pub schema_version: u32,
+    pub encoding: &'static str,
+    pub fields: &'static [JournalField],
+    pub encoding_notes: &'static [&'static str],
+}
+
+impl JournalLayout {
+    pub fn total_words(&self) -> usize {
+        self.fields.iter().map(|f| f.words).sum()
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.total_words() * 4
+    }
+}
+
+const ROOT_HASH: JournalField = JournalField {
+    name: "root_hash",
+    rust_type: "[u8; 32]",
+    words: 32,
+    description: "Root of the sparse merkle tree every proof was checked against",
+};
+
+const BANNED_LIST_HASH: JournalField = JournalField {
+    name: "banned_list_hash",
+    rust_type: "[u8; 32]",
+    words: 32,
+    description: "SHA-256 of the JSON array of proof keys, in request order; with a blinding salt, SHA-256 of the 32-byte salt followed by that JSON",
+};
+
+const COMPLIANT: JournalField = JournalField {
+    name: "compliant",
+    rust_type: "bool",
+    words: 1,
+    description: "True only if every proof verified against root_hash with a value proof_type accepts; value 0 (non-membership) where proof_type is absent",
+};
+
+const PROOF_COUNT: JournalField = JournalField {
+    name: "proof_count",
+    rust_type: "u32",
+    words: 1,
+    description: "Number of compact proofs the guest parsed and checked (0 if the input did not parse)",
+};
+
+const REASONS_HASH: JournalField = JournalField {
+    name: "reasons_hash",
+    rust_type: "[u8; 32]",
+    words: 32,
+    description: "SHA-256 over leaf_index || value (u64 big-endian) per proof in request order; all zero if any proof failed to verify",
+};
+
+const LIST_INFO_HASH: JournalField = JournalField {
+    name: "list_info_hash",
+    rust_type: "[u8; 32]",
+    words: 32,
+    description: "SHA-256 of the banned list provenance (length-prefixed source_uri, publisher, version, then entry_count as u64 big-endian); all zero if no list_info was supplied",
+};
+
+const INPUT_STATUS: JournalField = JournalField {
+    name: "input_status",
+    rust_type: "u32",
+    words: 1,
+    description: "0 if the guest accepted its input; otherwise why it rejected the input, such as unsupported framing or a non-canonical proof (see sbom_common::input::status), in which case every other field is zero or the empty-list hash and compliant is false",
+};
+
+const SALT_COMMITMENT: JournalField = JournalField {
+    name: "salt_commitment",
+    rust_type: "[u8; 32]",
+    words: 32,
+    description: "SHA-256 of the salt blinding banned_list_hash; all zero if the list commitment is not blinded",
+};
+
+const KEY_TYPE: JournalField = JournalField {
+    name: "key_type",
+    rust_type: "u32",
+    words: 1,
+    description: "Kind of identifier every proof was keyed by: 0 purl, 1 cpe, 2 raw_hash (see sbom_common::KeyType); 0 when the input was rejected",
+};
+
+const PROOF_TYPE: JournalField = JournalField {
+    name: "proof_type",
+    rust_type: "u32",
+    words: 1,
+    description: "Statement compliant attests: 0 non-membership (every value is 0), 1 membership (every value is nonzero); see sbom_common::ProofType. 0 when the input was rejected",
+};
+
+const DEFAULTS_DIGEST: JournalField = JournalField {
+    name: "defaults_digest",
+    rust_type: "[u8; 32]",
+    words: 32,
+    description: "SHA-256 over the guest's table of default subtree hashes (DEFAULTS[0] to DEFAULTS[256], 32 bytes each); equals sbom_common::DEFAULTS_DIGEST unless the guest was built with a different table. Committed even when the input was rejected",
+};
+
+const REQUESTER_COMMITMENT: JournalField = JournalField {
+    name: "requester_commitment",
+    rust_type: "[u8; 32]",
+    words: 32,
+    description: "sbom_common::compute_requester_commitment of the API key id the host authenticated the request under; all zero for an unauthenticated request or rejected input",
+};
+
+const FIRST_FAILURE: JournalField = JournalField {
+    name: "first_failure",
+    rust_type: "u32",
+    words: 1,
+    description: "Why the first proof that kept the request from being compliant failed (see sbom_common::failure): 0 when compliant or the input was rejected, e.g. 2 bad value, 3 bad hex, 5 missing sibling, 6 root mismatch",
+};
+
+const FIRST_FAILURE_INDEX: JournalField = JournalField {
+    name: "first_failure_index",
+    rust_type: "u32",
+    words: 1,
+    description: "Position in the request of the proof first_failure names; 0 when first_failure names no proof",
+};
+
+const FIRST_FAILURE_KEY_HASH: JournalField = JournalField {
+    name: "first_failure_key_hash",
+    rust_type: "[u8; 32]",
+    words: 32,
+    description: "Leaf index the key of that proof maps to (compute_purl_hash for a purl); all zero when first_failure names no proof or the key maps to no leaf",
+};
+
+const ENCODING_NOTES: &[&str] = &[
+    "Fields are encoded in declaration order with no names, tags or padding between them",
+    "Every u8, u32 and bool occupies one little-endian u32 word; bool is 0 or 1",
+    "Fixed-size arrays carry no length prefix",
+    "Journal bytes are the concatenation of the words in little-endian order",
+];
+
+/// Layout of `MerklePublicOutputs` as committed by the current compact merkle guest.
+pub const MERKLE_PUBLIC_OUTPUTS: JournalLayout = JournalLayout {
+    name: "MerklePublicOutputs",
+    schema_version: JOURNAL_SCHEMA_VERSION,
+    encoding: "risc0-serde",
+    fields: &[
+        ROOT_HASH,
+        BANNED_LIST_HASH,
+        COMPLIANT,
+        PROOF_COUNT,
+        REASONS_HASH,
+        LIST_INFO_HASH,
+        INPUT_STATUS,
+        SALT_COMMITMENT,
+        KEY_TYPE,
+        PROOF_TYPE,
+        DEFAULTS_DIGEST,
+        REQUESTER_COMMITMENT,
+        FIRST_FAILURE,
+        FIRST_FAILURE_INDEX,
+        FIRST_FAILURE_KEY_HASH,
+    ],
+    encoding_notes: ENCODING_NOTES,
+};
+
+/// Layout committed by guests built before the `first_failure` fields were added.
+pub const MERKLE_PUBLIC_OUTPUTS_V10: JournalLayout = JournalLayout {
+    name: "MerklePublicOutputs",
+    schema_version: 10,
+    encoding: "risc0-serde",
+    fields: &[
+        ROOT_HASH,
+        BANNED_LIST_HASH,
+        COMPLIANT,
+        PROOF_COUNT,
+        REASONS_HASH,
+        LIST_INFO_HASH,
+        INPUT_STATUS,
+        SALT_COMMITMENT,
+        KEY_TYPE,
+        PROOF_TYPE,
+        DEFAULTS_DIGEST,
+        REQUESTER_COMMITMENT,
+    ],
+    encoding_notes: ENCODING_NOTES,
+};
+
+/// Layout committed by guests built before `requester_commitment` was added.
+pub const MERKLE_PUBLIC_OUTPUTS_V9: JournalLayout = JournalLayout {
+    name: "MerklePublicOutputs",
+    schema_version: 9,
+    encoding: "risc0-serde",
+    fields: &[
+        ROOT_HASH,
+        BANNED_LIST_HASH,
+        COMPLIANT,
+        PROOF_COUNT,
+        REASONS_HASH,
+        LIST_INFO_HASH,
+        INPUT_STATUS,
+        SALT_COMMITMENT,
+        KEY_TYPE,
+        PROOF_TYPE,
+        DEFAULTS_DIGEST,
+    ],
+    encoding_notes: ENCODING_NOTES,
+};
+
+/// Layout committed by guests built before `defaults_digest` was added.
+pub const MERKLE_PUBLIC_OUTPUTS_V8: JournalLayout = JournalLayout {
+    name: "MerklePublicOutputs",
+    schema_version: 8,
+    encoding: "risc0-serde",
+    fields: &[
+        ROOT_HASH,
+        BANNED_LIST_HASH,
+        COMPLIANT,
+        PROOF_COUNT,
+        REASONS_HASH,
+        LIST_INFO_HASH,
+        INPUT_STATUS,
+        SALT_COMMITMENT,
+        KEY_TYPE,
+        PROOF_TYPE,
+    ],
+    encoding_notes: ENCODING_NOTES,
+};
+
+/// Layout committed by guests built before membership proofs added `proof_type`.
+pub const MERKLE_PUBLIC_OUTPUTS_V7: JournalLayout = JournalLayout {
+    name: "MerklePublicOutputs",
+    schema_version: 7,
+    encoding: "risc0-serde",
+    fields: &[
+        ROOT_HASH,
+        BANNED_LIST_HASH,
+        COMPLIANT,
+        PROOF_COUNT,
+        REASONS_HASH,
+        LIST_INFO_HASH,
+        INPUT_STATUS,
+        SALT_COMMITMENT,
+        KEY_TYPE,
+    ],
+    encoding_notes: ENCODING_NOTES,
+};
+
+/// Layout committed by guests built before non-purl keys added `key_type`.
+pub const MERKLE_PUBLIC_OUTPUTS_V6: JournalLayout = JournalLayout {
+    name: "MerklePublicOutputs",
+    schema_version: 6,
+    encoding: "risc0-serde",
+    fields: &[
+        ROOT_HASH,
+        BANNED_LIST_HASH,
+        COMPLIANT,
+        PROOF_COUNT,
+        REASONS_HASH,
+        LIST_INFO_HASH,
+        INPUT_STATUS,
+        SALT_COMMITMENT,
+    ],
+    encoding_notes: ENCODING_NOTES,
+};
+
+/// Layout committed by guests built before banned list blinding added `salt_commitment`.
+pub const MERKLE_PUBLIC_OUTPUTS_V5: JournalLayout = JournalLayout {
+    name: "MerklePublicOutputs",
+    schema_version: 5,
+    encoding: "risc0-serde",
+    fields: &[
+        ROOT_HASH,
+        BANNED_LIST_HASH,
+        COMPLIANT,
+        PROOF_COUNT,
+        REASONS_HASH,
+        LIST_INFO_HASH,
+        INPUT_STATUS,
+    ],
+    encoding_notes: ENCODING_NOTES,
+};
+
+/// Layout committed by guests built before the framed input protocol added `input_status`.
+pub const MERKLE_PUBLIC_OUTPUTS_V4: JournalLayout = JournalLayout {
+    name: "MerklePublicOutputs",
+    schema_version: 4,
+    encoding: "risc0-serde",
+    fields: &[
+        ROOT_HASH,
+        BANNED_LIST_HASH,
+        COMPLIANT,
+        PROOF_COUNT,
+        REASONS_HASH,
+        LIST_INFO_HASH,
+    ],
+    encoding_notes: ENCODING_NOTES,
+};
+
+/// Layout committed by guests built before `list_info_hash` was added.
+pub const MERKLE_PUBLIC_OUTPUTS_V3: JournalLayout = JournalLayout {
+    name: "MerklePublicOutputs",
+    schema_version: 3,
+    encoding: "risc0-serde",
+    fields: &[ROOT_HASH, BANNED_LIST_HASH, COMPLIANT, PROOF_COUNT, REASONS_HASH],
+    encoding_notes: ENCODING_NOTES,
+};
+
+/// Layout committed by guests built before `reasons_hash` was added.
+pub const MERKLE_PUBLIC_OUTPUTS_V2: JournalLayout = JournalLayout {
+    name: "MerklePublicOutputs",
+    schema_version: 2,
+    encoding: "risc0-serde",
+    fields: &[ROOT_HASH, BANNED_LIST_HASH, COMPLIANT, PROOF_COUNT],
+    encoding_notes: ENCODING_NOTES,
+};
+
+/// Layout committed by guests built before `proof_count` was added.
+/// Verifiers still accept these journals but cannot check the proof count.
+pub const MERKLE_PUBLIC_OUTPUTS_V1: JournalLayout = JournalLayout {
+    name: "MerklePublicOutputs",
+    schema_version: 1,
+    encoding: "risc0-serde",
+    fields: &[ROOT_HASH, BANNED_LIST_HASH, COMPLIANT],
+    encoding_notes: ENCODING_NOTES,
+};
@@ -0,0 +1,145 @@
+//! Bounds on the shape of a JSON document, checked before it is parsed.
+//!
+//! serde_json parses recursively, so a document nested thousands of levels deep costs
+//! stack in proportion to its depth, and one with millions of tiny values costs memory
+//! in proportion to their number. [`check_json_limits`] scans the raw bytes once with a
+//! counter per bound and no stack, so a hostile document is turned away for the price of
+//! reading it. It does not validate the document; anything it lets through still goes
+//! to the real parser.
+
+/// How deep and how large a document may be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonLimits {
+    /// Most arrays and objects open at once; a flat array is depth 1
+    pub max_depth: usize,
+    /// Most tokens: every string (object keys included), number, literal and opening
+    /// bracket counts as one
+    pub max_tokens: usize,
+}
+
+/// Limits the guest holds the proofs JSON to. The host writes that JSON itself as an array
+/// of proof objects, each with an array of siblings, so it is never deeper than 3; the
+/// token bound lies well above what `MAX_SIBLING_BYTES` of worst-case proofs needs.
+pub const PROOFS_JSON_LIMITS: JsonLimits = JsonLimits {
+    max_depth: 3,
+    max_tokens: 1 << 20,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonLimitError {
+    /// Nesting went past `max_depth` at byte `offset`
+    TooDeep { offset: usize },
+    /// The token count went past `max_tokens` at byte `offset`
+    TooManyTokens { offset: usize },
+}
+
+/// Scan `json` and fail at the first byte that takes it past `limits`. Stops early, so a
+/// rejected document is never read further than the point it broke a bound.
+pub fn check_json_limits(json: &[u8], limits: &JsonLimits) -> Result<(), JsonLimitError> {
+    let mut depth = 0usize;
+    let mut tokens = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut in_scalar = false;
+
+    for (offset, &byte) in json.iter().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        let starts_token = match byte {
+            b'"' => {
+                in_string = true;
+                in_scalar = false;
+                true
+            }
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > limits.max_depth {
+                    return Err(JsonLimitError::TooDeep { offset });
+                }
+                in_scalar = false;
+                true
+            }
+            b']' | b'}' => {
+                // Unbalanced closers are left for the parser to reject
+                depth = depth.saturating_sub(1);
+                in_scalar = false;
+                false
+            }
+            b',' | b':' | b' ' | b'\t' | b'\n' | b'\r' => {
+                in_scalar = false;
+                false
+            }
+            // Numbers and the literals true, false and null run until a delimiter
+            _ => !core::mem::replace(&mut in_scalar, true),
+        };
+
+        if starts_token {
+            tokens += 1;
+            if tokens > limits.max_tokens {
+                return Err(JsonLimitError::TooManyTokens { offset });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn limits(max_depth: usize, max_tokens: usize) -> JsonLimits {
+        JsonLimits {
+            max_depth,
+            max_tokens,
+        }
+    }
+
+    #[test]
+    fn nesting_past_the_depth_limit_is_refused_where_it_happens() {
+        assert_eq!(check_json_limits(b"[1, [2], {}]", &limits(2, 100)), Ok(()));
+        assert_eq!(
+            check_json_limits(b"[1, [2, [3]]]", &limits(2, 100)),
+            Err(JsonLimitError::TooDeep { offset: 8 })
+        );
+        // Closing a container gives its level back
+        assert_eq!(
+            check_json_limits(b"[[1], [2], [3]]", &limits(2, 100)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn brackets_inside_strings_do_not_nest() {
+        let json = br#"["[[[", "\"{{{", {"]": "}"}]"#;
+        assert_eq!(check_json_limits(json, &limits(2, 100)), Ok(()));
+    }
+
+    #[test]
+    fn every_value_key_and_container_is_one_token() {
+        // [ 12 true "a" { "k" null } = 7 tokens
+        let json = br#"[12, true, "a", {"k": null}]"#;
+        assert_eq!(check_json_limits(json, &limits(2, 7)), Ok(()));
+        assert_eq!(
+            check_json_limits(json, &limits(2, 6)),
+            Err(JsonLimitError::TooManyTokens { offset: 22 })
+        );
+    }
+
+    #[test]
+    fn proofs_json_fits_the_guest_limits() {
+        let proofs = br#"[{"key": "pkg:npm/a@1", "siblings": ["00", "11"], "bitmap": "03"}]"#;
+        assert_eq!(check_json_limits(proofs, &PROOFS_JSON_LIMITS), Ok(()));
+        assert_eq!(
+            check_json_limits(br#"[{"siblings": [["00"]]}]"#, &PROOFS_JSON_LIMITS),
+            Err(JsonLimitError::TooDeep { offset: 15 })
+        );
+    }
+}
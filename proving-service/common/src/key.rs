@@ -0,0 +1,151 @@
+//! Kinds of identifiers a banned list can be keyed by.
+//!
+//! Every tree maps SHA-256 leaf indices to values; the key type says how an identifier
+//! becomes its leaf index. Proofs bind to their key through this mapping, so host and
+//! guest must agree on it exactly.
+
+use sha2::{Digest, Sha256};
+
+use crate::{compute_purl_hash, hex_to_bytes32};
+
+/// Number of colon-separated components in a complete CPE 2.3 formatted string:
+/// `cpe`, `2.3` and the eleven attributes from part to other.
+pub const CPE23_COMPONENTS: usize = 13;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum KeyType {
+    /// Package URL; the leaf index is SHA-256 of the purl as given
+    #[default]
+    Purl,
+    /// CPE name; the leaf index is SHA-256 of its canonical form (see [`compute_cpe_hash`])
+    Cpe,
+    /// The key is the leaf index itself, as 64 hex characters
+    RawHash,
+}
+
+impl KeyType {
+    /// Value committed in the journal's `key_type` field
+    pub const fn code(self) -> u32 {
+        match self {
+            KeyType::Purl => 0,
+            KeyType::Cpe => 1,
+            KeyType::RawHash => 2,
+        }
+    }
+
+    pub const fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(KeyType::Purl),
+            1 => Some(KeyType::Cpe),
+            2 => Some(KeyType::RawHash),
+            _ => None,
+        }
+    }
+
+    /// Name as used in requests and responses
+    pub const fn name(self) -> &'static str {
+        match self {
+            KeyType::Purl => "purl",
+            KeyType::Cpe => "cpe",
+            KeyType::RawHash => "raw_hash",
+        }
+    }
+}
+
+/// Compute the leaf index of a CPE name.
+///
+/// The name is trimmed and ASCII-lowercased, since CPE matching is case-insensitive. A
+/// CPE 2.3 formatted string (`cpe:2.3:...`) with fewer than [`CPE23_COMPONENTS`]
+/// components is padded with `*` (ANY), so `cpe:2.3:a:vendor:product` and its fully
+/// spelled-out form share a leaf. Escaped colons (`\:`) do not separate components.
+/// Other forms, such as CPE 2.2 URIs, are only trimmed and lowercased.
+pub fn compute_cpe_hash(cpe: &str) -> [u8; 32] {
+    let cpe = cpe.trim();
+    let mut hasher = Sha256::new();
+    let mut components = 1;
+    let mut escaped = false;
+
+    for &b in cpe.as_bytes() {
+        if escaped {
+            escaped = false;
+        } else if b == b'\\' {
+            escaped = true;
+        } else if b == b':' {
+            components += 1;
+        }
+        hasher.update([b.to_ascii_lowercase()]);
+    }
+
+    let formatted = cpe.len() >= 8 && cpe.as_bytes()[..8].eq_ignore_ascii_case(b"cpe:2.3:");
+    if formatted {
+        for _ in components..CPE23_COMPONENTS {
+            hasher.update(b":*");
+        }
+    }
+
+    hasher.finalize().into()
+}
+
+/// Leaf index a key of the given type must be proven at, or `None` when a raw hash key
+/// is not 64 hex characters.
+pub fn compute_leaf_index(key_type: KeyType, key: &str) -> Option<[u8; 32]> {
+    match key_type {
+        KeyType::Purl => Some(compute_purl_hash(key)),
+        KeyType::Cpe => Some(compute_cpe_hash(key)),
+        KeyType::RawHash => hex_to_bytes32(key).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpe_names_are_canonicalized() {
+        let full = compute_cpe_hash("cpe:2.3:a:vendor:product:*:*:*:*:*:*:*:*");
+        for equivalent in [
+            "cpe:2.3:a:vendor:product",
+            "  CPE:2.3:A:Vendor:Product  ",
+            "cpe:2.3:a:vendor:product:*:*",
+        ] {
+            assert_eq!(compute_cpe_hash(equivalent), full, "{equivalent}");
+        }
+        // An escaped colon belongs to the product name rather than separating components
+        assert_ne!(
+            compute_cpe_hash("cpe:2.3:a:vendor:pro\\:duct"),
+            compute_cpe_hash("cpe:2.3:a:vendor:pro:duct")
+        );
+        // A CPE 2.2 URI is not padded
+        let uri = "cpe:/a:vendor:product";
+        assert_eq!(compute_cpe_hash(uri), <[u8; 32]>::from(Sha256::digest(uri)));
+    }
+
+    #[test]
+    fn each_key_type_maps_keys_to_its_own_leaf() {
+        let purl = "pkg:npm/a@1";
+        assert_eq!(
+            compute_leaf_index(KeyType::Purl, purl),
+            Some(compute_purl_hash(purl))
+        );
+        assert_eq!(
+            compute_leaf_index(KeyType::Cpe, "CPE:2.3:a:v:p"),
+            Some(compute_cpe_hash("cpe:2.3:a:v:p"))
+        );
+
+        let digest = "ab".repeat(32);
+        assert_eq!(
+            compute_leaf_index(KeyType::RawHash, &digest),
+            Some([0xab; 32])
+        );
+        assert_eq!(compute_leaf_index(KeyType::RawHash, purl), None);
+        assert_eq!(compute_leaf_index(KeyType::RawHash, &digest[2..]), None);
+
+        for key_type in [KeyType::Purl, KeyType::Cpe, KeyType::RawHash] {
+            assert_eq!(KeyType::from_code(key_type.code()), Some(key_type));
+        }
+        assert_eq!(KeyType::from_code(3), None);
+    }
+}
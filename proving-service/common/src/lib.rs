@@ -5,42 +5,59 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-#[cfg(not(feature = "std"))]
-use risc0_zkvm::guest::sha::Impl as Sha256Impl;
-#[cfg(not(feature = "std"))]
-use risc0_zkvm::guest::sha::rust_crypto::Sha256;
-#[cfg(not(feature = "std"))]
-use sha2::Digest;
+// The guest patches sha2 with RISC Zero's accelerated fork, so the same code uses the
+// SHA-256 precompile inside the zkVM and the portable implementation on the host.
+use sha2::{Digest, Sha256};
 
+#[cfg(feature = "schemars")]
+pub mod api_schema;
+#[cfg(feature = "envelope")]
+pub mod envelope;
+pub mod failure;
 #[cfg(feature = "std")]
-use sha2::{Digest, Sha256};
+pub mod input;
+pub mod journal;
+pub mod json_limits;
+pub mod key;
+#[cfg(feature = "std")]
+pub mod messages;
+#[cfg(feature = "observability")]
+pub mod observability;
+#[cfg(feature = "std")]
+pub mod proof;
+pub mod proof_type;
+#[cfg(feature = "std")]
+pub mod smt;
+
+pub use key::{KeyType, compute_cpe_hash, compute_leaf_index};
+pub use proof_type::ProofType;
 
 /// Hash a value (as a decimal string) to create a leaf hash.
 /// The value is converted to a 32-byte big-endian representation, then hashed.
-pub fn hash_value(value: &str) -> [u8; 32] {
-    let mut padded_bytes = [0u8; 32];
+/// A value [`parse_leaf_value`] rejects is an error rather than the leaf of 0, which
+/// would pass for non-membership.
+pub fn hash_value(value: &str) -> Result<[u8; 32], ValueError> {
+    parse_leaf_value(value).map(hash_leaf).ok_or(ValueError)
+}
 
-    // Parse the value string as a decimal number (should be "0" for non-membership)
-    if let Ok(val) = parse_u64(value) {
-        // Convert to big-endian bytes (right-aligned in the 32-byte array)
-        let val_bytes = val.to_be_bytes();
-        padded_bytes[32 - val_bytes.len()..].copy_from_slice(&val_bytes);
-    }
+/// A leaf value that is not a non-empty decimal u64
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueError;
+
+/// Hash a numeric leaf value: 32-byte big-endian, right-aligned, then SHA-256.
+pub fn hash_leaf(value: u64) -> [u8; 32] {
+    let mut padded_bytes = [0u8; 32];
+    let val_bytes = value.to_be_bytes();
+    padded_bytes[32 - val_bytes.len()..].copy_from_slice(&val_bytes);
 
-    #[cfg(not(feature = "std"))]
-    let mut hasher = Sha256::<Sha256Impl>::new();
-    #[cfg(feature = "std")]
     let mut hasher = Sha256::new();
 
-    hasher.update(&padded_bytes);
+    hasher.update(padded_bytes);
     hasher.finalize().into()
 }
 
 /// Hash two 32-byte values together
 pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
-    #[cfg(not(feature = "std"))]
-    let mut hasher = Sha256::<Sha256Impl>::new();
-    #[cfg(feature = "std")]
     let mut hasher = Sha256::new();
 
     hasher.update(left);
@@ -50,9 +67,6 @@ pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
 
 /// Compute the 32-byte hash of a purl (used as the path in the SMT)
 pub fn compute_purl_hash(purl: &str) -> [u8; 32] {
-    #[cfg(not(feature = "std"))]
-    let mut hasher = Sha256::<Sha256Impl>::new();
-    #[cfg(feature = "std")]
     let mut hasher = Sha256::new();
 
     hasher.update(purl.as_bytes());
@@ -61,36 +75,67 @@ pub fn compute_purl_hash(purl: &str) -> [u8; 32] {
 
 /// Convert hex string to 32-byte array.
 /// Uses manual parsing to avoid external dependencies.
+/// The string must be exactly 64 hex characters after an optional `0x` prefix, so the
+/// host and the guest accept and reject exactly the same inputs.
 pub fn hex_to_bytes32(hex_str: &str) -> Result<[u8; 32], HexError> {
-    let hex_clean = hex_str.strip_prefix("0x").unwrap_or(hex_str);
+    let hex_clean = hex_str.strip_prefix("0x").unwrap_or(hex_str).as_bytes();
+    if hex_clean.len() < 64 {
+        return Err(HexError::TooShort);
+    }
+    if hex_clean.len() > 64 {
+        return Err(HexError::TooLong);
+    }
 
+    // Work on bytes rather than str slices: non-ASCII input must be an error, not a panic
     let mut bytes = [0u8; 32];
-    for i in 0..32 {
-        if i * 2 + 2 > hex_clean.len() {
-            return Err(HexError::TooShort);
-        }
-        let byte_str = &hex_clean[i*2..i*2+2];
-        bytes[i] = parse_hex_byte(byte_str)?;
+    for (byte, pair) in bytes.iter_mut().zip(hex_clean.chunks_exact(2)) {
+        *byte = (hex_char_to_nibble(pair[0])? << 4) | hex_char_to_nibble(pair[1])?;
     }
     Ok(bytes)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum HexError {
-    TooShort,
-    InvalidCharacter,
+/// Lower-case hex digits of `bytes`, the inverse of [`hex_to_bytes32`]. Needs no
+/// allocation, so the guest can use it too.
+pub fn bytes32_to_hex(bytes: &[u8; 32]) -> [u8; 64] {
+    let mut hex = [0u8; 64];
+    write_hex(bytes, &mut hex);
+    hex
 }
 
-fn parse_hex_byte(s: &str) -> Result<u8, HexError> {
-    let bytes = s.as_bytes();
-    if bytes.len() != 2 {
-        return Err(HexError::TooShort);
-    }
+/// [`bytes32_to_hex`] as a `String`
+#[cfg(feature = "std")]
+pub fn bytes32_to_hex_string(bytes: &[u8; 32]) -> String {
+    bytes_to_hex(bytes)
+}
+
+/// [`bytes32_to_hex`] prefixed with `0x`, as some consumers (e.g. Solidity tooling) expect
+#[cfg(feature = "std")]
+pub fn bytes32_to_prefixed_hex(bytes: &[u8; 32]) -> String {
+    format!("0x{}", bytes_to_hex(bytes))
+}
 
-    let high = hex_char_to_nibble(bytes[0])?;
-    let low = hex_char_to_nibble(bytes[1])?;
+/// Lower-case hex of a byte string of any length
+#[cfg(feature = "std")]
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    let mut hex = vec![0u8; bytes.len() * 2];
+    write_hex(bytes, &mut hex);
+    String::from_utf8(hex).expect("hex digits are ASCII")
+}
+
+/// Write two lower-case hex digits per byte of `bytes` into `hex`
+fn write_hex(bytes: &[u8], hex: &mut [u8]) {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    for (byte, pair) in bytes.iter().zip(hex.chunks_exact_mut(2)) {
+        pair[0] = DIGITS[(byte >> 4) as usize];
+        pair[1] = DIGITS[(byte & 0x0f) as usize];
+    }
+}
 
-    Ok((high << 4) | low)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexError {
+    TooShort,
+    TooLong,
+    InvalidCharacter,
 }
 
 fn hex_char_to_nibble(c: u8) -> Result<u8, HexError> {
@@ -102,6 +147,159 @@ fn hex_char_to_nibble(c: u8) -> Result<u8, HexError> {
     }
 }
 
+/// Parse a leaf value as a non-empty decimal u64.
+/// Value 0 marks non-membership; nonzero values are the reason code stored for a banned purl.
+pub fn parse_leaf_value(value: &str) -> Option<u64> {
+    if value.is_empty() {
+        return None;
+    }
+    parse_u64(value).ok()
+}
+
+/// Commitment to the (leaf_index, value) pairs of a proof set, in request order.
+/// Each entry contributes the 32-byte leaf_index followed by the value as 8 big-endian bytes.
+pub fn compute_reasons_hash<I>(entries: I) -> [u8; 32]
+where
+    I: IntoIterator<Item = ([u8; 32], u64)>,
+{
+    let mut hasher = Sha256::new();
+
+    for (leaf_index, value) in entries {
+        hasher.update(leaf_index);
+        hasher.update(value.to_be_bytes());
+    }
+    hasher.finalize().into()
+}
+
+/// Commitment to the provenance of a banned list: where it came from, who published it,
+/// which version and how many entries it has. String fields are length-prefixed
+/// (u64 big-endian) so adjacent fields cannot be re-split; entry_count follows as u64 big-endian.
+pub fn compute_list_info_hash(
+    source_uri: &str,
+    publisher: &str,
+    version: &str,
+    entry_count: u64,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    for field in [source_uri, publisher, version] {
+        hasher.update((field.len() as u64).to_be_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hasher.update(entry_count.to_be_bytes());
+    hasher.finalize().into()
+}
+
+/// Commitment to a banned list: SHA-256 of its keys' JSON array, as `serde_json` encodes
+/// it, or, with a blinding salt, SHA-256 of the salt followed by the JSON. A blinded
+/// commitment cannot be confirmed by hashing candidate lists without knowing the salt.
+///
+/// The one definition the guest, the proving service and the verifier hash lists with.
+/// The JSON is streamed into the hash rather than built, so none of them needs a JSON
+/// encoder to agree on it.
+pub fn compute_banned_list_commitment<S: AsRef<str>>(
+    banned_list: &[S],
+    salt: Option<&[u8; 32]>,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    if let Some(salt) = salt {
+        hasher.update(salt);
+    }
+    hasher.update(b"[");
+    for (i, key) in banned_list.iter().enumerate() {
+        if i > 0 {
+            hasher.update(b",");
+        }
+        update_json_string(&mut hasher, key.as_ref());
+    }
+    hasher.update(b"]");
+    hasher.finalize().into()
+}
+
+/// The unblinded banned list commitment, as strict mode and list catalogs compare it.
+pub fn compute_banned_list_hash<S: AsRef<str>>(banned_list: &[S]) -> [u8; 32] {
+    compute_banned_list_commitment(banned_list, None)
+}
+
+/// Hash `value` as a JSON string literal, escaped as `serde_json` escapes it: quotes,
+/// backslashes and control characters, with lowercase `\u00XX` for those without a
+/// short form. Everything else, non-ASCII included, is hashed as is.
+fn update_json_string(hasher: &mut Sha256, value: &str) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    hasher.update(b"\"");
+    let bytes = value.as_bytes();
+    let mut unescaped = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        let mut unicode = *b"\\u0000";
+        let escape: &[u8] = match byte {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            0x08 => b"\\b",
+            0x0c => b"\\f",
+            0x00..=0x1f => {
+                unicode[4] = HEX[usize::from(byte >> 4)];
+                unicode[5] = HEX[usize::from(byte & 0xf)];
+                &unicode
+            }
+            _ => continue,
+        };
+        hasher.update(&bytes[unescaped..i]);
+        hasher.update(escape);
+        unescaped = i + 1;
+    }
+    hasher.update(&bytes[unescaped..]);
+    hasher.update(b"\"");
+}
+
+/// Commitment to a blinding salt, against which a later reveal of the salt is checked.
+pub fn compute_salt_commitment(salt: &[u8; 32]) -> [u8; 32] {
+    Sha256::digest(salt).into()
+}
+
+/// Domain separator of [`compute_requester_commitment`]
+const REQUESTER_DOMAIN: &[u8] = b"sbom-requester/v1";
+
+/// Commitment to the API key id a proof was requested under. A verifier told the key id
+/// recomputes it; the journal carries all zeros instead for unauthenticated requests.
+pub fn compute_requester_commitment(key_id: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(REQUESTER_DOMAIN);
+    hasher.update(key_id.as_bytes());
+    hasher.finalize().into()
+}
+
+/// HMAC-SHA256 (RFC 2104)
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let inner = inner.finalize();
+
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner);
+    outer.finalize().into()
+}
+
+/// Compare without an early exit, so timing does not reveal how much of a forged
+/// signature was right
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 fn parse_u64(s: &str) -> Result<u64, ()> {
     let mut result: u64 = 0;
     for b in s.bytes() {
@@ -415,6 +613,21 @@ pub const DEFAULTS: [[u8; 32]; 257] = [
     hex_to_array!("876422b7697ae7c337e2ee7727feb3db474adf7be1cf04b6b5857d82d610e88a"),
 ];
 
+/// `defaults_digest(&DEFAULTS)`, precomputed. The guest commits the digest of the table it
+/// was built with, so a journal carrying any other value comes from a guest whose empty
+/// subtrees hash differently and whose roots mean something else.
+pub const DEFAULTS_DIGEST: [u8; 32] =
+    hex_to_array!("5e40603ef945b84f1cd880f7e04f7ddb725fe53f0c4b01b112103205f682e63a");
+
+/// SHA-256 over the entries of a default hash table, concatenated from depth 0 up.
+pub fn defaults_digest(defaults: &[[u8; 32]; 257]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for default in defaults {
+        hasher.update(default);
+    }
+    hasher.finalize().into()
+}
+
 /// Extract bit at depth `d` from a 32-byte bitmap (bit-packed, 256 bits total).
 /// Formula: (bitmap[d / 8] >> (d % 8)) & 1
 /// Returns 1 if sibling at depth d should be taken from provided siblings, 0 to use DEFAULTS[d].
@@ -439,3 +652,327 @@ pub fn path_bit(leaf_index: &[u8; 32], d: usize) -> u8 {
 pub fn count_bitmap_ones(bitmap: &[u8; 32]) -> usize {
     bitmap.iter().map(|&byte| byte.count_ones() as usize).sum()
 }
+
+/// Compress a proof that lists one sibling per level (depth 0 first) into the canonical
+/// bitmap form: bit d is set, and the sibling kept, only where it differs from DEFAULTS[d].
+/// Returns `None` unless exactly 256 siblings are given.
+#[cfg(feature = "std")]
+pub fn compress_siblings(siblings: &[[u8; 32]]) -> Option<([u8; 32], Vec<[u8; 32]>)> {
+    if siblings.len() != 256 {
+        return None;
+    }
+
+    let mut bitmap = [0u8; 32];
+    let mut kept = Vec::new();
+    for (d, sibling) in siblings.iter().enumerate() {
+        if *sibling != DEFAULTS[d] {
+            bitmap[d / 8] |= 1 << (d % 8);
+            kept.push(*sibling);
+        }
+    }
+    Some((bitmap, kept))
+}
+
+/// Why a compact proof could not be folded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompactProofError {
+    /// The bitmap requires a sibling at `depth` but no more were provided
+    MissingSibling { depth: usize },
+    /// The bitmap marks a sibling at `depth` that equals `DEFAULTS[depth]`. The canonical
+    /// encoding leaves such siblings implicit, so the proof is not in canonical form
+    DefaultSibling { depth: usize },
+}
+
+/// Fold a compact proof from the leaf up to the root of the depth-256 tree.
+/// Siblings are consumed in depth order wherever the bitmap bit is set; all other
+/// levels use DEFAULTS[d]. Only the canonical encoding is accepted: a consumed sibling
+/// equal to DEFAULTS[d] is an error, as is a bitmap requiring more siblings than provided.
+pub fn compute_compact_root(
+    leaf_hash: &[u8; 32],
+    leaf_index: &[u8; 32],
+    bitmap: &[u8; 32],
+    siblings: &[[u8; 32]],
+) -> Result<[u8; 32], CompactProofError> {
+    let mut current = *leaf_hash;
+    let mut sibling_idx = 0;
+
+    for (d, default) in DEFAULTS.iter().enumerate().take(256) {
+        let sibling = if bitmap_bit(bitmap, d) == 1 {
+            let s = siblings
+                .get(sibling_idx)
+                .ok_or(CompactProofError::MissingSibling { depth: d })?;
+            if s == default {
+                return Err(CompactProofError::DefaultSibling { depth: d });
+            }
+            sibling_idx += 1;
+            s
+        } else {
+            default
+        };
+
+        current = if path_bit(leaf_index, d) == 0 {
+            hash_pair(&current, sibling)
+        } else {
+            hash_pair(sibling, &current)
+        };
+    }
+
+    Ok(current)
+}
+
+/// Check that a compact proof folds up to `root`.
+/// This is the single recomputation shared by the guest and host-side validation.
+/// Returns `Ok(false)` for a well-formed proof of a different root.
+pub fn verify_compact_proof(
+    leaf_hash: &[u8; 32],
+    leaf_index: &[u8; 32],
+    bitmap: &[u8; 32],
+    siblings: &[[u8; 32]],
+    root: &[u8; 32],
+) -> Result<bool, CompactProofError> {
+    compute_compact_root(leaf_hash, leaf_index, bitmap, siblings).map(|r| r == *root)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::smt::{CompactProof, SparseMerkleTree};
+
+    /// A tree with a few banned purls, so proofs carry siblings at several depths
+    fn tree() -> SparseMerkleTree {
+        let mut tree = SparseMerkleTree::default();
+        for purl in ["pkg:npm/a@1", "pkg:npm/b@2", "pkg:npm/c@3", "pkg:npm/d@4"] {
+            tree.insert(purl, 1);
+        }
+        tree
+    }
+
+    /// One sibling per level, depth 0 first, with the defaults the bitmap leaves implicit
+    fn expand(proof: &CompactProof) -> Vec<[u8; 32]> {
+        let mut kept = proof.siblings.iter();
+        (0..256)
+            .map(|d| match bitmap_bit(&proof.bitmap, d) {
+                1 => *kept.next().unwrap(),
+                _ => DEFAULTS[d],
+            })
+            .collect()
+    }
+
+    fn verify(proof: &CompactProof, root: &[u8; 32]) -> Result<bool, CompactProofError> {
+        verify_compact_proof(
+            &hash_leaf(proof.value),
+            &proof.leaf_index,
+            &proof.bitmap,
+            &proof.siblings,
+            root,
+        )
+    }
+
+    #[test]
+    fn hashes_are_exactly_64_hex_digits() {
+        let digits = "ab".repeat(32);
+        assert_eq!(hex_to_bytes32(&digits), Ok([0xab; 32]));
+        assert_eq!(hex_to_bytes32(&format!("0x{digits}")), Ok([0xab; 32]));
+        assert_eq!(hex_to_bytes32(&digits.to_uppercase()), Ok([0xab; 32]));
+        assert_eq!(hex_to_bytes32(&digits[1..]), Err(HexError::TooShort));
+        assert_eq!(
+            hex_to_bytes32(&format!("{digits}0")),
+            Err(HexError::TooLong)
+        );
+        // The prefix is optional, not a licence for two more digits
+        assert_eq!(
+            hex_to_bytes32(&format!("0x{digits}00")),
+            Err(HexError::TooLong)
+        );
+        assert_eq!(
+            hex_to_bytes32(&format!("0X{}", &digits[2..])),
+            Err(HexError::InvalidCharacter)
+        );
+        assert_eq!(
+            hex_to_bytes32(&format!("{}zz", &digits[2..])),
+            Err(HexError::InvalidCharacter)
+        );
+        // Multi-byte characters are refused, not split
+        assert_eq!(
+            hex_to_bytes32(&format!("{}é", &digits[2..])),
+            Err(HexError::InvalidCharacter)
+        );
+    }
+
+    #[test]
+    fn unparseable_values_are_errors_not_non_membership() {
+        assert_eq!(hash_value("0"), Ok(hash_leaf(0)));
+        assert_eq!(hash_value("42"), Ok(hash_leaf(42)));
+        for value in ["", "-1", "+1", "1.0", " 1", "0x1", "18446744073709551616"] {
+            assert_eq!(hash_value(value), Err(ValueError), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn banned_list_keys_are_hashed_as_serde_json_escapes_them() {
+        let list = [
+            "pkg:npm/\"quoted\"@1",
+            "pkg:npm/back\\slash@1",
+            "pkg:npm/line\nbreak@1",
+            "pkg:npm/bell\u{7}@1",
+            "pkg:npm/caf\u{e9}@1",
+        ];
+        let json = serde_json::to_vec(&list).unwrap();
+        assert_eq!(
+            compute_banned_list_hash(&list),
+            <[u8; 32]>::from(Sha256::digest(&json))
+        );
+    }
+
+    #[test]
+    fn the_defaults_digest_is_pinned() {
+        assert_eq!(DEFAULTS[0], hash_leaf(0));
+        for d in 1..DEFAULTS.len() {
+            assert_eq!(
+                DEFAULTS[d],
+                hash_pair(&DEFAULTS[d - 1], &DEFAULTS[d - 1]),
+                "{d}"
+            );
+        }
+        // Changing the table changes what every root means; journals must notice
+        assert_eq!(defaults_digest(&DEFAULTS), DEFAULTS_DIGEST);
+        let mut other = DEFAULTS;
+        other[256] = [0; 32];
+        assert_ne!(defaults_digest(&other), DEFAULTS_DIGEST);
+    }
+
+    #[test]
+    fn compact_proofs_fold_to_their_root() {
+        let mut tree = SparseMerkleTree::default();
+        let empty = tree.prove("pkg:npm/a@1");
+        assert_eq!(empty.bitmap, [0; 32]);
+        assert_eq!(
+            compute_compact_root(&hash_leaf(0), &empty.leaf_index, &empty.bitmap, &[]),
+            Ok(DEFAULTS[256])
+        );
+
+        for (i, purl) in ["pkg:npm/a@1", "pkg:npm/b@2", "pkg:npm/c@3"]
+            .iter()
+            .enumerate()
+        {
+            tree.insert(purl, i as u64 + 1);
+            for probe in ["pkg:npm/a@1", "pkg:npm/c@3", "pkg:npm/absent@1"] {
+                let proof = tree.prove(probe);
+                let fold = |value| {
+                    compute_compact_root(
+                        &hash_leaf(value),
+                        &proof.leaf_index,
+                        &proof.bitmap,
+                        &proof.siblings,
+                    )
+                };
+                assert_eq!(fold(proof.value), Ok(tree.root()), "{probe} after {purl}");
+                // Any other value folds to another root
+                assert_ne!(fold(proof.value + 1), Ok(tree.root()), "{probe}");
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_proofs_round_trip_through_compression() {
+        let tree = tree();
+        for purl in ["pkg:npm/a@1", "pkg:npm/absent@1"] {
+            let proof = tree.prove(purl);
+            assert!(!proof.siblings.is_empty(), "{purl}");
+
+            let (bitmap, siblings) = compress_siblings(&expand(&proof)).unwrap();
+            assert_eq!(bitmap, proof.bitmap, "{purl}");
+            assert_eq!(siblings, proof.siblings, "{purl}");
+            assert_eq!(verify(&proof, &tree.root()), Ok(true), "{purl}");
+            // Well formed, but for another tree
+            assert_eq!(verify(&proof, &[0; 32]), Ok(false), "{purl}");
+        }
+        assert_eq!(compress_siblings(&[[0; 32]; 255]), None);
+    }
+
+    #[test]
+    fn explicit_default_siblings_are_rejected() {
+        let tree = tree();
+        let mut proof = tree.prove("pkg:npm/a@1");
+        let depth = (0..256)
+            .find(|&d| bitmap_bit(&proof.bitmap, d) == 0)
+            .unwrap();
+        // Spell out the default at `depth` instead of leaving it to the bitmap
+        let position = (0..depth)
+            .filter(|&d| bitmap_bit(&proof.bitmap, d) == 1)
+            .count();
+        proof.bitmap[depth / 8] |= 1 << (depth % 8);
+        proof.siblings.insert(position, DEFAULTS[depth]);
+
+        // It folds to the same root, but only the canonical form is accepted
+        assert_eq!(
+            verify(&proof, &tree.root()),
+            Err(CompactProofError::DefaultSibling { depth })
+        );
+    }
+
+    #[test]
+    fn bitmaps_and_sibling_counts_must_agree() {
+        let tree = tree();
+        let proof = tree.prove("pkg:npm/a@1");
+        let deepest = (0..256)
+            .rev()
+            .find(|&d| bitmap_bit(&proof.bitmap, d) == 1)
+            .unwrap();
+
+        let mut short = proof.clone();
+        short.siblings.pop();
+        assert_eq!(
+            verify(&short, &tree.root()),
+            Err(CompactProofError::MissingSibling { depth: deepest })
+        );
+
+        // The fold stops at the bitmap's last sibling; a surplus is caught before it
+        let mut long = proof.clone();
+        long.siblings.push([7; 32]);
+        let siblings: Vec<String> = long.siblings.iter().map(bytes32_to_hex_string).collect();
+        let bitmap = bytes32_to_hex_string(&long.bitmap);
+        let leaf_index = bytes32_to_hex_string(&long.leaf_index);
+        let fields = proof::ProofFields {
+            key: "pkg:npm/a@1",
+            key_type: KeyType::Purl,
+            value: "1",
+            leaf_index: &leaf_index,
+            siblings: &siblings,
+            bitmap: &bitmap,
+        };
+        assert_eq!(
+            proof::check_proof_sizes([fields]),
+            Err(proof::SizeError::SiblingCount {
+                index: 0,
+                expected: proof.siblings.len(),
+                actual: proof.siblings.len() + 1,
+            })
+        );
+    }
+
+    #[test]
+    fn salts_blind_the_banned_list_commitment() {
+        let list = ["pkg:npm/a@1", "pkg:npm/b@2"];
+        let json: &[u8] = br#"["pkg:npm/a@1","pkg:npm/b@2"]"#;
+        let salt = [9u8; 32];
+
+        let plain = compute_banned_list_hash(&list);
+        let blinded = compute_banned_list_commitment(&list, Some(&salt));
+        assert_eq!(plain, <[u8; 32]>::from(Sha256::digest(json)));
+        assert_eq!(
+            blinded,
+            <[u8; 32]>::from(Sha256::digest([&salt[..], json].concat()))
+        );
+        // A candidate list cannot be confirmed without the salt
+        assert_ne!(blinded, plain);
+        assert_ne!(
+            blinded,
+            compute_banned_list_commitment(&list, Some(&[8; 32]))
+        );
+        assert_eq!(
+            compute_salt_commitment(&salt),
+            <[u8; 32]>::from(Sha256::digest(salt))
+        );
+    }
+}
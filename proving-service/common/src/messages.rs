@@ -0,0 +1,424 @@
+//! Catalog of the services' user-facing error messages, in every supported locale.
+//!
+//! Every error a service answers with has a stable `code` and a [`Message`] giving its
+//! text per [`Locale`], with `{name}` placeholders filled from the error's fields. The
+//! English text is the stable default: it is what the `error` field of a response and
+//! the error's `Display` always carry, so logs and clients matching on it are unaffected
+//! by the caller's language. The text in the locale negotiated from `Accept-Language`
+//! goes in the response's `message` field.
+//!
+//! A [`Message`] has one field per locale, so an entry cannot be missing a translation,
+//! and each service's error type maps every variant to an entry, so a variant cannot be
+//! missing one either. Free-text details that an error carries (a path, an upstream
+//! error, the reason a proof is malformed) are interpolated as they are, in English.
+
+use std::fmt::{Display, Write as _};
+
+/// A language messages are available in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::De];
+
+    /// Language tag, as sent in `Content-Language`
+    pub fn tag(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+        }
+    }
+
+    /// The supported locale the caller prefers most, by the q-values of an
+    /// `Accept-Language` header; English when none of its languages is supported. Only
+    /// the primary subtag is compared, so `de-AT` selects German.
+    pub fn negotiate(accept_language: &str) -> Locale {
+        let mut best = (Locale::En, 0.0f32);
+        for range in accept_language.split(',') {
+            let mut parts = range.split(';');
+            let tag = parts.next().unwrap_or_default().trim();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            let primary = tag.split('-').next().unwrap_or_default();
+            let Some(locale) = Locale::ALL
+                .into_iter()
+                .find(|locale| primary.eq_ignore_ascii_case(locale.tag()))
+            else {
+                continue;
+            };
+            if quality > best.1 {
+                best = (locale, quality);
+            }
+        }
+        best.0
+    }
+}
+
+/// The text of one error code in every locale
+#[derive(Debug)]
+pub struct Message {
+    /// The error code this text belongs to, as returned in a response's `code` field
+    pub code: &'static str,
+    pub en: &'static str,
+    pub de: &'static str,
+}
+
+impl Message {
+    pub fn template(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::En => self.en,
+            Locale::De => self.de,
+        }
+    }
+
+    /// The text in `locale` with every `{name}` replaced by the value of `name` in
+    /// `params`. A placeholder without a value is left as it is.
+    pub fn render(&self, locale: Locale, params: &[(&str, &dyn Display)]) -> String {
+        let mut rest = self.template(locale);
+        let mut out = String::with_capacity(rest.len());
+        while let Some(open) = rest.find('{') {
+            out.push_str(&rest[..open]);
+            let after = &rest[open + 1..];
+            let value = after.find('}').and_then(|close| {
+                let name = &after[..close];
+                params
+                    .iter()
+                    .find(|(param, _)| *param == name)
+                    .map(|(_, value)| (close, value))
+            });
+            match value {
+                Some((close, value)) => {
+                    // Writing to a String cannot fail
+                    let _ = write!(out, "{value}");
+                    rest = &after[close + 1..];
+                }
+                None => {
+                    out.push('{');
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Messages of the proving service's errors
+pub mod proving {
+    use super::Message;
+
+    /// A hex string in the request or a stored artifact does not decode
+    pub const INVALID_HEX: Message = Message {
+        code: "invalid_hex",
+        en: "Hex error: {detail}",
+        de: "Hex-Fehler: {detail}",
+    };
+    pub const IO_ERROR: Message = Message {
+        code: "io_error",
+        en: "IO error: {detail}",
+        de: "E/A-Fehler: {detail}",
+    };
+    /// The body or a stored artifact is not the JSON expected
+    pub const INVALID_JSON: Message = Message {
+        code: "invalid_json",
+        en: "JSON error: {detail}",
+        de: "JSON-Fehler: {detail}",
+    };
+    /// The zkVM failed while handling a receipt or its journal
+    pub const RISC0_ERROR: Message = Message {
+        code: "risc0_error",
+        en: "RISC0 error: {detail}",
+        de: "RISC0-Fehler: {detail}",
+    };
+    /// The request is malformed or inconsistent; nothing was proven
+    pub const INVALID_REQUEST: Message = Message {
+        code: "invalid_request",
+        en: "{detail}",
+        de: "Ungültige Anfrage: {detail}",
+    };
+    /// A compact proof in the request is malformed
+    pub const PROOF_VALIDATION: Message = Message {
+        code: "proof_validation",
+        en: "Invalid proof for key '{purl}': {reason}",
+        de: "Ungültiger Beweis für den Schlüssel '{purl}': {reason}",
+    };
+    /// A proof does not reach the requested root; found before proving
+    pub const PREVERIFY_FAILED: Message = Message {
+        code: "preverify_failed",
+        en: "Proof {index} for key '{purl}' does not reach the requested root: {reason}. Nothing was proven; set skip_preverify to prove it anyway",
+        de: "Beweis {index} für den Schlüssel '{purl}' erreicht die angeforderte Wurzel nicht: {reason}. Es wurde nichts bewiesen; mit skip_preverify wird trotzdem bewiesen",
+    };
+    /// The prover ran but did not produce a receipt
+    pub const PROVER_FAILURE: Message = Message {
+        code: "prover_failure",
+        en: "{detail}",
+        de: "Die Beweiserzeugung ist fehlgeschlagen: {detail}",
+    };
+    /// The prover's output failed the host's sanity checks and was quarantined
+    pub const INVALID_OUTPUT: Message = Message {
+        code: "invalid_output",
+        en: "{detail}",
+        de: "Die Ausgabe des Provers wurde verworfen: {detail}",
+    };
+    /// The caller's deadline passed or cannot be met
+    pub const DEADLINE_EXCEEDED: Message = Message {
+        code: "deadline_exceeded",
+        en: "{detail}",
+        de: "Frist überschritten: {detail}",
+    };
+    /// The deadline passed while the job was still waiting for a prover
+    pub const UNAVAILABLE: Message = Message {
+        code: "unavailable",
+        en: "{detail}",
+        de: "Kein Prover verfügbar: {detail}",
+    };
+    /// The proof store could not be written or read
+    pub const STORAGE_ERROR: Message = Message {
+        code: "storage_error",
+        en: "{detail}",
+        de: "Speicherfehler: {detail}",
+    };
+    /// The operation needs the prover, which this instance runs without
+    pub const PROVER_DISABLED: Message = Message {
+        code: "prover_disabled",
+        en: "{detail}",
+        de: "Der Prover ist deaktiviert: {detail}",
+    };
+    /// The operation needs a setting this instance was started without
+    pub const NOT_CONFIGURED: Message = Message {
+        code: "not_configured",
+        en: "{detail}",
+        de: "Nicht konfiguriert: {detail}",
+    };
+    /// A reloaded configuration was rejected; the previous one stays active
+    pub const INVALID_CONFIG: Message = Message {
+        code: "invalid_config",
+        en: "{detail}",
+        de: "Ungültige Konfiguration: {detail}",
+    };
+    /// An admin endpoint was called without the admin token
+    pub const UNAUTHORIZED: Message = Message {
+        code: "unauthorized",
+        en: "{detail}",
+        de: "Nicht autorisiert: {detail}",
+    };
+    /// The remote prover failed or could not be reached
+    pub const REMOTE_PROVER: Message = Message {
+        code: "remote_prover",
+        en: "{detail}",
+        de: "Fehler des entfernten Provers: {detail}",
+    };
+    /// The request exceeds a configured limit, with the amount it has
+    pub const LIMIT_EXCEEDED: Message = Message {
+        code: "limit_exceeded",
+        en: "Request has {observed} {unit}, more than the {limit} allowed by {setting}",
+        de: "Die Anfrage hat {observed} {unit}, mehr als die von {setting} erlaubten {limit}",
+    };
+    /// The request exceeds a configured limit, refused before its full amount was known
+    pub const LIMIT_EXCEEDED_UNMEASURED: Message = Message {
+        code: "limit_exceeded",
+        en: "Request has more than the {limit} {unit} allowed by {setting}",
+        de: "Die Anfrage hat mehr als die von {setting} erlaubten {limit} {unit}",
+    };
+    /// No stored proof, receipt or job matches the request
+    pub const NOT_FOUND: Message = Message {
+        code: "not_found",
+        en: "{detail}",
+        de: "Nicht gefunden: {detail}",
+    };
+    /// The request matches more than one stored proof
+    pub const CONFLICT: Message = Message {
+        code: "conflict",
+        en: "{detail}",
+        de: "Konflikt: {detail}",
+    };
+    /// Too many operations of this kind are in progress
+    pub const OVERLOADED: Message = Message {
+        code: "overloaded",
+        en: "{detail}",
+        de: "Überlastet, bitte später erneut versuchen: {detail}",
+    };
+    /// The client made more prove requests than its rate limit allows
+    pub const RATE_LIMITED: Message = Message {
+        code: "rate_limited",
+        en: "Rate limit of {limit} prove requests per minute exceeded; retry in {retry_after} s",
+        de: "Ratenlimit von {limit} Beweisanfragen pro Minute überschritten; erneut versuchen in {retry_after} s",
+    };
+    pub const INTERNAL_ERROR: Message = Message {
+        code: "internal_error",
+        en: "{detail}",
+        de: "Interner Fehler: {detail}",
+    };
+
+    /// Every entry, in the order of the service's error variants
+    pub const ALL: [&Message; 24] = [
+        &INVALID_HEX,
+        &IO_ERROR,
+        &INVALID_JSON,
+        &RISC0_ERROR,
+        &INVALID_REQUEST,
+        &PROOF_VALIDATION,
+        &PREVERIFY_FAILED,
+        &PROVER_FAILURE,
+        &INVALID_OUTPUT,
+        &DEADLINE_EXCEEDED,
+        &UNAVAILABLE,
+        &STORAGE_ERROR,
+        &PROVER_DISABLED,
+        &NOT_CONFIGURED,
+        &INVALID_CONFIG,
+        &UNAUTHORIZED,
+        &REMOTE_PROVER,
+        &LIMIT_EXCEEDED,
+        &LIMIT_EXCEEDED_UNMEASURED,
+        &NOT_FOUND,
+        &CONFLICT,
+        &OVERLOADED,
+        &RATE_LIMITED,
+        &INTERNAL_ERROR,
+    ];
+}
+
+/// Messages of the verifier service's errors
+pub mod verifier {
+    use super::Message;
+
+    /// The request or the proof in it is malformed
+    pub const INVALID_PROOF: Message = Message {
+        code: "invalid_proof",
+        en: "Invalid proof: {detail}",
+        de: "Ungültiger Beweis: {detail}",
+    };
+    /// The receipt does not verify, or its journal contradicts the request
+    pub const VERIFICATION_FAILED: Message = Message {
+        code: "verification_failed",
+        en: "Verification failed: {detail}",
+        de: "Verifizierung fehlgeschlagen: {detail}",
+    };
+    /// The proof decodes but is not a receipt in any accepted encoding
+    pub const DESERIALIZATION_FAILED: Message = Message {
+        code: "deserialization_failed",
+        en: "Deserialization failed: {detail}",
+        de: "Deserialisierung fehlgeschlagen: {detail}",
+    };
+    pub const INVALID_IMAGE_ID: Message = Message {
+        code: "invalid_image_id",
+        en: "Invalid image ID: {detail}",
+        de: "Ungültige Image-ID: {detail}",
+    };
+    pub const UNKNOWN_ROOT_ALIAS: Message = Message {
+        code: "unknown_root_alias",
+        en: "Unknown root alias: {alias}",
+        de: "Unbekannter Root-Alias: {alias}",
+    };
+    /// The requested list version is not in the catalog, or no catalog is configured
+    pub const UNKNOWN_LIST_VERSION: Message = Message {
+        code: "unknown_list_version",
+        en: "Unknown list version: {detail}",
+        de: "Unbekannte Listenversion: {detail}",
+    };
+    /// The list catalog is too old and must be re-signed
+    pub const STALE_CATALOG: Message = Message {
+        code: "stale_catalog",
+        en: "Stale list catalog: {detail}",
+        de: "Veralteter Listenkatalog: {detail}",
+    };
+    pub const INSUFFICIENT_PROOF_COUNT: Message = Message {
+        code: "insufficient_proof_count",
+        en: "Insufficient proof count: expected at least {expected_min}, receipt committed {actual}",
+        de: "Zu wenige Beweise: mindestens {expected_min} erwartet, der Receipt enthält {actual}",
+    };
+    pub const INVALID_BASE64: Message = Message {
+        code: "invalid_base64",
+        en: "Invalid base64: {detail}",
+        de: "Ungültiges Base64: {detail}",
+    };
+    pub const TRUNCATED_PROOF: Message = Message {
+        code: "truncated_proof",
+        en: "Truncated proof: {detail}",
+        de: "Abgeschnittener Beweis: {detail}",
+    };
+    pub const PADDED_PROOF: Message = Message {
+        code: "padded_proof",
+        en: "Padded proof: {detail}",
+        de: "Beweis mit überzähligen Bytes: {detail}",
+    };
+    pub const DOUBLE_ENCODED_PROOF: Message = Message {
+        code: "double_encoded_proof",
+        en: "Double-encoded proof: {detail}",
+        de: "Doppelt kodierter Beweis: {detail}",
+    };
+    /// The receipt was faked by a prover in dev mode and proves nothing
+    pub const DEV_RECEIPT: Message = Message {
+        code: "dev_receipt",
+        en: "Dev-mode receipt refused: {detail}",
+        de: "Dev-Modus-Receipt abgelehnt: {detail}",
+    };
+    /// Every verification worker is busy and the queue is full
+    pub const OVERLOADED: Message = Message {
+        code: "overloaded",
+        en: "Verifier overloaded: {detail}",
+        de: "Verifier überlastet: {detail}",
+    };
+    pub const INTERNAL_ERROR: Message = Message {
+        code: "internal_error",
+        en: "Internal error: {detail}",
+        de: "Interner Fehler: {detail}",
+    };
+
+    /// Every entry, in the order of the service's error variants
+    pub const ALL: [&Message; 15] = [
+        &INVALID_PROOF,
+        &VERIFICATION_FAILED,
+        &DESERIALIZATION_FAILED,
+        &INVALID_IMAGE_ID,
+        &UNKNOWN_ROOT_ALIAS,
+        &UNKNOWN_LIST_VERSION,
+        &STALE_CATALOG,
+        &INSUFFICIENT_PROOF_COUNT,
+        &INVALID_BASE64,
+        &TRUNCATED_PROOF,
+        &PADDED_PROOF,
+        &DOUBLE_ENCODED_PROOF,
+        &DEV_RECEIPT,
+        &OVERLOADED,
+        &INTERNAL_ERROR,
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_highest_q_value_wins() {
+        assert_eq!(Locale::negotiate("en;q=0.5, de;q=0.9"), Locale::De);
+        assert_eq!(Locale::negotiate("de;q=0.4, en"), Locale::En);
+        // A language without a q-value has q=1
+        assert_eq!(Locale::negotiate("de, en;q=0.9"), Locale::De);
+        // q=0 means not acceptable
+        assert_eq!(Locale::negotiate("de;q=0"), Locale::En);
+    }
+
+    #[test]
+    fn regions_fall_back_to_their_language() {
+        assert_eq!(Locale::negotiate("de-AT"), Locale::De);
+        assert_eq!(Locale::negotiate("DE-ch;q=0.8, fr"), Locale::De);
+        assert_eq!(Locale::negotiate("en-GB, de;q=0.5"), Locale::En);
+    }
+
+    #[test]
+    fn unknown_languages_fall_back_to_english() {
+        for header in ["fr", "fr-FR, ja;q=0.8", "", "*", "deu", ";q=1"] {
+            assert_eq!(Locale::negotiate(header), Locale::En, "{header:?}");
+        }
+        // Unknown languages are skipped, not held against the known ones
+        assert_eq!(Locale::negotiate("fr, de;q=0.1"), Locale::De);
+    }
+}
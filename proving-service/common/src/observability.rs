@@ -0,0 +1,402 @@
+//! Metric names, label keys, log field names and histogram buckets shared by both services.
+//!
+//! Every metric goes through [`Metrics`], which rejects names and label keys that are not
+//! declared here, so the services cannot drift apart (`root` vs `root_hash`,
+//! `duration_ms` vs seconds). Tracing field names cannot be taken from constants at the
+//! call site, so the macros below are the only place that spells them; [`field`] lists
+//! them for log queries.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+#[doc(hidden)]
+pub use tracing;
+
+/// Metric names, Prometheus style: `sbom_` prefix, base units in the suffix
+pub mod metric {
+    /// Counter; labels: endpoint, outcome
+    pub const REQUESTS_TOTAL: &str = "sbom_requests_total";
+    /// Histogram; labels: endpoint
+    pub const REQUEST_DURATION_SECONDS: &str = "sbom_request_duration_seconds";
+    /// Histogram; no labels
+    pub const PROOF_GENERATION_SECONDS: &str = "sbom_proof_generation_seconds";
+    /// Counter; labels: outcome
+    pub const RECEIPT_VERIFICATIONS_TOTAL: &str = "sbom_receipt_verifications_total";
+    /// Counter of prover invocations, retries included; labels: outcome
+    /// (`success`, `transient_failure` or `permanent_failure`)
+    pub const PROVE_ATTEMPTS_TOTAL: &str = "sbom_prove_attempts_total";
+    /// Counter of prove requests looked up in the proof cache; labels: outcome (`hit` or
+    /// `miss`)
+    pub const PROOF_CACHE_LOOKUPS_TOTAL: &str = "sbom_proof_cache_lookups_total";
+    /// Gauge of receipt verifications running on a verifier worker; no labels
+    pub const VERIFICATIONS_IN_FLIGHT: &str = "sbom_verifications_in_flight";
+    /// Gauge of receipt verifications waiting for a verifier worker; no labels
+    pub const VERIFICATIONS_QUEUED: &str = "sbom_verifications_queued";
+    /// Gauge of the estimated proving seconds left in a prover's backlog; no labels
+    pub const AUTOSCALE_BACKLOG_SECONDS: &str = "sbom_autoscale_backlog_seconds";
+    /// Gauge of the prover replicas needed to drain the backlog in the target time; no labels
+    pub const AUTOSCALE_RECOMMENDED_REPLICAS: &str = "sbom_autoscale_recommended_replicas";
+
+    pub const ALL: &[&str] = &[
+        REQUESTS_TOTAL,
+        REQUEST_DURATION_SECONDS,
+        PROOF_GENERATION_SECONDS,
+        RECEIPT_VERIFICATIONS_TOTAL,
+        PROVE_ATTEMPTS_TOTAL,
+        PROOF_CACHE_LOOKUPS_TOTAL,
+        VERIFICATIONS_IN_FLIGHT,
+        VERIFICATIONS_QUEUED,
+        AUTOSCALE_BACKLOG_SECONDS,
+        AUTOSCALE_RECOMMENDED_REPLICAS,
+    ];
+}
+
+/// Metric label keys
+pub mod label {
+    pub const ENDPOINT: &str = "endpoint";
+    pub const OUTCOME: &str = "outcome";
+
+    pub const ALL: &[&str] = &[ENDPOINT, OUTCOME];
+}
+
+/// Structured tracing field names emitted by the macros in this module
+pub mod field {
+    pub const ENDPOINT: &str = "endpoint";
+    pub const OUTCOME: &str = "outcome";
+    pub const STATUS: &str = "status";
+    pub const DURATION_MS: &str = "duration_ms";
+    pub const ROOT_HASH: &str = "root_hash";
+    pub const BANNED_LIST_HASH: &str = "banned_list_hash";
+    pub const PROOF_COUNT: &str = "proof_count";
+    pub const COMPLIANT: &str = "compliant";
+}
+
+/// Upper bounds (seconds) of every duration histogram, spanning fast validation
+/// requests up to multi-minute proofs.
+pub const DURATION_BUCKETS_SECONDS: &[f64] = &[
+    0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0,
+];
+
+/// Request outcome as recorded in the `outcome` label and log field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    ClientError,
+    Timeout,
+    ServerError,
+}
+
+impl Outcome {
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            408 | 504 => Outcome::Timeout,
+            400..=499 => Outcome::ClientError,
+            500..=599 => Outcome::ServerError,
+            _ => Outcome::Success,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Success => "success",
+            Outcome::ClientError => "client_error",
+            Outcome::Timeout => "timeout",
+            Outcome::ServerError => "server_error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricsError {
+    UnknownMetric(String),
+    UnknownLabel { metric: String, label: String },
+}
+
+impl fmt::Display for MetricsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricsError::UnknownMetric(name) => {
+                write!(f, "metric '{name}' is not declared in observability::metric")
+            }
+            MetricsError::UnknownLabel { metric, label } => write!(
+                f,
+                "label '{label}' on metric '{metric}' is not declared in observability::label"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MetricsError {}
+
+type SeriesKey = (&'static str, Vec<(&'static str, String)>);
+
+#[derive(Debug)]
+enum Series {
+    Counter(u64),
+    Gauge(i64),
+    Histogram {
+        buckets: Vec<u64>,
+        sum: f64,
+        count: u64,
+    },
+}
+
+/// In-process metrics registry that only accepts the names declared in this module
+#[derive(Debug, Default)]
+pub struct Metrics {
+    series: Mutex<BTreeMap<SeriesKey, Series>>,
+}
+
+impl Metrics {
+    pub fn inc_counter(
+        &self,
+        name: &str,
+        labels: &[(&str, &str)],
+    ) -> Result<(), MetricsError> {
+        let key = Self::key(name, labels)?;
+        let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        if let Series::Counter(value) = series.entry(key).or_insert(Series::Counter(0)) {
+            *value += 1;
+        }
+        Ok(())
+    }
+
+    pub fn set_gauge(
+        &self,
+        name: &str,
+        labels: &[(&str, &str)],
+        value: i64,
+    ) -> Result<(), MetricsError> {
+        let key = Self::key(name, labels)?;
+        let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        series.insert(key, Series::Gauge(value));
+        Ok(())
+    }
+
+    pub fn observe(
+        &self,
+        name: &str,
+        labels: &[(&str, &str)],
+        duration: Duration,
+    ) -> Result<(), MetricsError> {
+        let key = Self::key(name, labels)?;
+        let seconds = duration.as_secs_f64();
+        let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = series.entry(key).or_insert_with(|| Series::Histogram {
+            buckets: vec![0; DURATION_BUCKETS_SECONDS.len()],
+            sum: 0.0,
+            count: 0,
+        });
+        if let Series::Histogram {
+            buckets,
+            sum,
+            count,
+        } = entry
+        {
+            for (bucket, bound) in buckets.iter_mut().zip(DURATION_BUCKETS_SECONDS) {
+                if seconds <= *bound {
+                    *bucket += 1;
+                }
+            }
+            *sum += seconds;
+            *count += 1;
+        }
+        Ok(())
+    }
+
+    /// Render every recorded series in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+        let mut last_name = "";
+
+        for ((name, labels), value) in series.iter() {
+            if *name != last_name {
+                let kind = match value {
+                    Series::Counter(_) => "counter",
+                    Series::Gauge(_) => "gauge",
+                    Series::Histogram { .. } => "histogram",
+                };
+                let _ = writeln!(out, "# TYPE {name} {kind}");
+                last_name = name;
+            }
+            match value {
+                Series::Counter(count) => {
+                    let _ = writeln!(out, "{name}{} {count}", render_labels(labels, None));
+                }
+                Series::Gauge(value) => {
+                    let _ = writeln!(out, "{name}{} {value}", render_labels(labels, None));
+                }
+                Series::Histogram {
+                    buckets,
+                    sum,
+                    count,
+                } => {
+                    for (bucket, bound) in buckets.iter().zip(DURATION_BUCKETS_SECONDS) {
+                        let le = bound.to_string();
+                        let _ = writeln!(
+                            out,
+                            "{name}_bucket{} {bucket}",
+                            render_labels(labels, Some(&le))
+                        );
+                    }
+                    let _ = writeln!(
+                        out,
+                        "{name}_bucket{} {count}",
+                        render_labels(labels, Some("+Inf"))
+                    );
+                    let _ = writeln!(out, "{name}_sum{} {sum}", render_labels(labels, None));
+                    let _ = writeln!(out, "{name}_count{} {count}", render_labels(labels, None));
+                }
+            }
+        }
+        out
+    }
+
+    fn key(name: &str, labels: &[(&str, &str)]) -> Result<SeriesKey, MetricsError> {
+        let name = metric::ALL
+            .iter()
+            .copied()
+            .find(|known| *known == name)
+            .ok_or_else(|| MetricsError::UnknownMetric(name.to_string()))?;
+
+        let labels = labels
+            .iter()
+            .map(|(key, value)| {
+                label::ALL
+                    .iter()
+                    .copied()
+                    .find(|known| known == key)
+                    .map(|key| (key, value.to_string()))
+                    .ok_or_else(|| MetricsError::UnknownLabel {
+                        metric: name.to_string(),
+                        label: key.to_string(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((name, labels))
+    }
+}
+
+fn render_labels(labels: &[(&str, String)], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{key}=\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{le}\""));
+    }
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+/// The process-wide registry both services record into
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::default)
+}
+
+/// Record one finished HTTP request: count it by outcome, observe its duration and emit
+/// a structured log line with the shared field names.
+///
+/// `record_request!(endpoint, status, started)` where `started` is a `std::time::Instant`.
+#[macro_export]
+macro_rules! record_request {
+    ($endpoint:expr, $status:expr, $started:expr) => {{
+        let endpoint: &str = $endpoint;
+        let status: u16 = $status;
+        let elapsed = $started.elapsed();
+        let outcome = $crate::observability::Outcome::from_status(status);
+        let metrics = $crate::observability::metrics();
+        let _ = metrics.inc_counter(
+            $crate::observability::metric::REQUESTS_TOTAL,
+            &[
+                ($crate::observability::label::ENDPOINT, endpoint),
+                ($crate::observability::label::OUTCOME, outcome.as_str()),
+            ],
+        );
+        let _ = metrics.observe(
+            $crate::observability::metric::REQUEST_DURATION_SECONDS,
+            &[($crate::observability::label::ENDPOINT, endpoint)],
+            elapsed,
+        );
+        $crate::observability::tracing::info!(
+            endpoint,
+            outcome = outcome.as_str(),
+            status,
+            duration_ms = elapsed.as_millis() as u64,
+            "request finished"
+        );
+    }};
+}
+
+/// Log the public outputs of a proof with the shared field names.
+///
+/// `record_outputs!(message, root_hash, banned_list_hash, proof_count, compliant)`; the
+/// hashes are anything implementing `Display`, `proof_count` any `Debug` value.
+#[macro_export]
+macro_rules! record_outputs {
+    ($message:literal, $root_hash:expr, $banned_list_hash:expr, $proof_count:expr, $compliant:expr) => {
+        $crate::observability::tracing::info!(
+            root_hash = %$root_hash,
+            banned_list_hash = %$banned_list_hash,
+            proof_count = ?$proof_count,
+            compliant = $compliant,
+            $message
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undeclared_names_and_labels_are_rejected() {
+        let metrics = Metrics::default();
+        let endpoint = [(label::ENDPOINT, "/prove")];
+
+        assert_eq!(
+            metrics.inc_counter("sbom_requests", &endpoint),
+            Err(MetricsError::UnknownMetric("sbom_requests".to_string()))
+        );
+        let root = [("root", "ab")];
+        let err = metrics
+            .observe(metric::REQUEST_DURATION_SECONDS, &root, Duration::ZERO)
+            .unwrap_err();
+        assert!(matches!(err, MetricsError::UnknownLabel { label, .. } if label == "root"));
+        assert_eq!(metrics.render(), "");
+    }
+
+    #[test]
+    fn recorded_requests_render_with_the_shared_names() {
+        record_request!("/observability-test", 504, std::time::Instant::now());
+
+        let rendered = metrics().render();
+        let count = format!(
+            "{}{{endpoint=\"/observability-test\",outcome=\"timeout\"}} 1",
+            metric::REQUESTS_TOTAL
+        );
+        assert!(rendered.contains(&count), "{rendered}");
+        let bucket = format!(
+            "{}_bucket{{endpoint=\"/observability-test\",le=\"0.005\"}} 1",
+            metric::REQUEST_DURATION_SECONDS
+        );
+        assert!(rendered.contains(&bucket), "{rendered}");
+        // Every series rendered is one declared in `metric`
+        for line in rendered.lines().filter(|line| !line.starts_with('#')) {
+            let name = line.split(['{', ' ']).next().unwrap();
+            let name = ["_bucket", "_sum", "_count"]
+                .iter()
+                .find_map(|suffix| name.strip_suffix(suffix))
+                .unwrap_or(name);
+            assert!(metric::ALL.contains(&name), "{line}");
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Checks every compact proof passes before it is folded.
+//!
+//! The guest runs these on the proofs it commits to and the host's `/validate` runs the
+//! same functions, so the two accept and reject exactly the same proofs. Each error maps
+//! to the code the guest commits: a [`SizeError`] rejects the whole input with an
+//! `input_status`, a [`ParseError`] names the proof in `first_failure`.
+
+use crate::input::{MAX_HASH_HEX_LEN, MAX_SIBLING_BYTES, status};
+use crate::{
+    HexError, KeyType, compute_leaf_index, count_bitmap_ones, failure, hex_to_bytes32,
+    parse_leaf_value,
+};
+
+/// The fields of a compact proof, as they arrive in the request
+#[derive(Debug, Clone, Copy)]
+pub struct ProofFields<'a> {
+    pub key: &'a str,
+    pub key_type: KeyType,
+    pub value: &'a str,
+    pub leaf_index: &'a str,
+    pub siblings: &'a [String],
+    pub bitmap: &'a str,
+}
+
+/// A hex field of a proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofField {
+    Bitmap,
+    LeafIndex,
+    /// The sibling at this position of the proof's sibling list
+    Sibling(usize),
+}
+
+/// Why a list of proofs is too large to be worked on. `index` is the proof at fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeError {
+    /// The bitmap or leaf_index is longer than any hash
+    OversizedField {
+        index: usize,
+        field: ProofField,
+        len: usize,
+    },
+    /// A sibling is longer than any hash
+    OversizedSibling {
+        index: usize,
+        sibling: usize,
+        len: usize,
+    },
+    /// The proof carries a different number of siblings than its bitmap has set bits
+    SiblingCount {
+        index: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// The siblings up to and including this proof exceed `MAX_SIBLING_BYTES`
+    SiblingBudget { index: usize },
+}
+
+impl SizeError {
+    /// The `input_status` the guest rejects the input with
+    pub const fn status(self) -> u32 {
+        match self {
+            SizeError::SiblingCount { .. } => status::SIBLING_COUNT_MISMATCH,
+            _ => status::OVERSIZED_INPUT,
+        }
+    }
+}
+
+/// Bound the work a list of proofs can cause before any sibling is decoded: every proof
+/// must carry exactly as many siblings as its bitmap has set bits, every hash string must
+/// fit `MAX_HASH_HEX_LEN` and the siblings of all proofs together must fit
+/// `MAX_SIBLING_BYTES`. A bitmap that is not valid hex is left for [`parse_proof`] to fail.
+pub fn check_proof_sizes<'a>(
+    proofs: impl IntoIterator<Item = ProofFields<'a>>,
+) -> Result<(), SizeError> {
+    let mut sibling_bytes = 0usize;
+    for (index, proof) in proofs.into_iter().enumerate() {
+        for (field, hex) in [
+            (ProofField::Bitmap, proof.bitmap),
+            (ProofField::LeafIndex, proof.leaf_index),
+        ] {
+            if hex.len() > MAX_HASH_HEX_LEN {
+                return Err(SizeError::OversizedField {
+                    index,
+                    field,
+                    len: hex.len(),
+                });
+            }
+        }
+        if let Ok(bitmap) = hex_to_bytes32(proof.bitmap) {
+            let expected = count_bitmap_ones(&bitmap);
+            if expected != proof.siblings.len() {
+                return Err(SizeError::SiblingCount {
+                    index,
+                    expected,
+                    actual: proof.siblings.len(),
+                });
+            }
+        }
+        for (sibling, hex) in proof.siblings.iter().enumerate() {
+            if hex.len() > MAX_HASH_HEX_LEN {
+                return Err(SizeError::OversizedSibling {
+                    index,
+                    sibling,
+                    len: hex.len(),
+                });
+            }
+            sibling_bytes += hex.len();
+        }
+        if sibling_bytes > MAX_SIBLING_BYTES {
+            return Err(SizeError::SiblingBudget { index });
+        }
+    }
+    Ok(())
+}
+
+/// Why a single proof cannot be folded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// The value is not a decimal u64
+    BadValue,
+    BadHex {
+        field: ProofField,
+        error: HexError,
+    },
+    /// The leaf_index is not the one the key maps to; `expected` is `None` for a key that
+    /// maps to no leaf index at all
+    LeafIndexMismatch {
+        expected: Option<[u8; 32]>,
+    },
+}
+
+impl ParseError {
+    /// The `failure` code the guest commits for the proof
+    pub const fn failure(self) -> u32 {
+        match self {
+            ParseError::BadValue => failure::BAD_VALUE,
+            ParseError::BadHex { .. } => failure::BAD_HEX,
+            ParseError::LeafIndexMismatch { .. } => failure::LEAF_INDEX_MISMATCH,
+        }
+    }
+}
+
+/// A proof with its value and hex fields decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedProof {
+    pub value: u64,
+    pub bitmap: [u8; 32],
+    pub leaf_index: [u8; 32],
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// Decode the fields of a proof and check its leaf_index is the one its key maps to, which
+/// binds the proof to the claimed key: a valid proof for a different position is rejected.
+/// Fails on the first field in the order value, bitmap, leaf_index, key binding, siblings.
+pub fn parse_proof(proof: ProofFields<'_>) -> Result<ParsedProof, ParseError> {
+    let (value, bitmap, leaf_index) = decode_scalars(proof)?;
+    check_leaf_index(proof, &leaf_index)?;
+    Ok(ParsedProof {
+        value,
+        bitmap,
+        leaf_index,
+        siblings: decode_siblings(proof)?,
+    })
+}
+
+/// [`parse_proof`] without the key binding, for callers that report it separately with
+/// [`check_leaf_index`]
+pub fn decode_proof(proof: ProofFields<'_>) -> Result<ParsedProof, ParseError> {
+    let (value, bitmap, leaf_index) = decode_scalars(proof)?;
+    Ok(ParsedProof {
+        value,
+        bitmap,
+        leaf_index,
+        siblings: decode_siblings(proof)?,
+    })
+}
+
+/// Check `leaf_index` is the one the key of `proof` maps to under its key type
+pub fn check_leaf_index(proof: ProofFields<'_>, leaf_index: &[u8; 32]) -> Result<(), ParseError> {
+    match compute_leaf_index(proof.key_type, proof.key) {
+        Some(expected) if expected == *leaf_index => Ok(()),
+        expected => Err(ParseError::LeafIndexMismatch { expected }),
+    }
+}
+
+fn decode_scalars(proof: ProofFields<'_>) -> Result<(u64, [u8; 32], [u8; 32]), ParseError> {
+    // Value 0 is non-membership; nonzero values are present entries, such as reason codes
+    // of banned ones
+    let value = parse_leaf_value(proof.value).ok_or(ParseError::BadValue)?;
+    let bitmap = decode_field(ProofField::Bitmap, proof.bitmap)?;
+    let leaf_index = decode_field(ProofField::LeafIndex, proof.leaf_index)?;
+    Ok((value, bitmap, leaf_index))
+}
+
+/// Decode all sibling hashes once, so the fold does no hex parsing
+fn decode_siblings(proof: ProofFields<'_>) -> Result<Vec<[u8; 32]>, ParseError> {
+    let mut siblings = Vec::with_capacity(proof.siblings.len());
+    for (index, hex) in proof.siblings.iter().enumerate() {
+        siblings.push(decode_field(ProofField::Sibling(index), hex)?);
+    }
+    Ok(siblings)
+}
+
+fn decode_field(field: ProofField, hex: &str) -> Result<[u8; 32], ParseError> {
+    hex_to_bytes32(hex).map_err(|error| ParseError::BadHex { field, error })
+}
@@ -0,0 +1,52 @@
+//! Which statement a compact merkle proof set attests.
+//!
+//! A leaf value of 0 marks an absent key. A non-membership proof set shows that none of
+//! its keys are in the tree, as for a banned list; a membership proof set shows that all
+//! of them are, as for an allowlist. The guest verifies both the same way and only the
+//! meaning of `compliant` differs.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum ProofType {
+    /// Compliant when every leaf value is 0
+    #[default]
+    NonMembership,
+    /// Compliant when every leaf value is nonzero
+    Membership,
+}
+
+impl ProofType {
+    /// Value committed in the journal's `proof_type` field
+    pub const fn code(self) -> u32 {
+        match self {
+            ProofType::NonMembership => 0,
+            ProofType::Membership => 1,
+        }
+    }
+
+    pub const fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(ProofType::NonMembership),
+            1 => Some(ProofType::Membership),
+            _ => None,
+        }
+    }
+
+    /// Name as used in requests and responses
+    pub const fn name(self) -> &'static str {
+        match self {
+            ProofType::NonMembership => "non_membership",
+            ProofType::Membership => "membership",
+        }
+    }
+
+    /// Whether a verified leaf value satisfies the statement
+    pub const fn accepts(self, value: u64) -> bool {
+        match self {
+            ProofType::NonMembership => value == 0,
+            ProofType::Membership => value != 0,
+        }
+    }
+}
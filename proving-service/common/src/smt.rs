@@ -0,0 +1,612 @@
+//! Sparse merkle tree builder compatible with the Go merkle-proof-service.
+//!
+//! Snapshots use the Go service's JSON dump (the `smt_data` it stores per root), so a
+//! tree persisted by one implementation can be loaded by the other. Leaf values are
+//! limited to u64, which covers non-membership (0) and every reason code.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::{
+    DEFAULTS, compute_purl_hash, hash_leaf, hash_pair, hex_to_bytes32, parse_leaf_value, path_bit,
+};
+
+/// Depth of every tree this crate builds and verifies.
+pub const TREE_DEPTH: usize = 256;
+
+/// Compact proof for one leaf, in the same shape the guest consumes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactProof {
+    pub leaf_index: [u8; 32],
+    pub value: u64,
+    pub bitmap: [u8; 32],
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// Depth-256 sparse merkle tree over `sha256(purl)` leaf indices.
+///
+/// Build it from a full leaf set, or grow it one leaf at a time with [`insert`]; either
+/// way the same leaves give the same tree. Only subtrees whose hash differs from
+/// `DEFAULTS` are stored, so memory grows with the number of leaves, not the depth of
+/// the key space.
+///
+/// [`insert`]: SparseMerkleTree::insert
+#[derive(Debug, Clone)]
+pub struct SparseMerkleTree {
+    leaves: BTreeMap<[u8; 32], u64>,
+    /// Parent hash -> children, for every subtree above the leaves whose hash is not
+    /// the default of its height
+    nodes: HashMap<[u8; 32], Node>,
+    root: [u8; 32],
+}
+
+/// A stored subtree. Equal subtrees share a hash, so one entry can stand at several
+/// positions; `refs` counts them, and the entry goes once the last is replaced.
+#[derive(Debug, Clone)]
+struct Node {
+    left: [u8; 32],
+    right: [u8; 32],
+    refs: usize,
+}
+
+impl Default for SparseMerkleTree {
+    fn default() -> Self {
+        Self::from_leaves(BTreeMap::new())
+    }
+}
+
+impl SparseMerkleTree {
+    /// Build a tree from (purl, value) pairs. A later duplicate purl overrides an earlier one.
+    pub fn from_purls<'a, I>(items: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, u64)>,
+    {
+        Self::from_leaves(
+            items
+                .into_iter()
+                .map(|(purl, value)| (compute_purl_hash(purl), value))
+                .collect(),
+        )
+    }
+
+    /// Build a tree from leaf indices and their values.
+    pub fn from_leaves(leaves: BTreeMap<[u8; 32], u64>) -> Self {
+        // BTreeMap iterates in big-endian order, which is the order the split below relies on
+        let items: Vec<([u8; 32], u64)> = leaves.iter().map(|(k, v)| (*k, *v)).collect();
+        let mut nodes = HashMap::new();
+        let root = build_subtree(&mut nodes, TREE_DEPTH, &items);
+        Self {
+            leaves,
+            nodes,
+            root,
+        }
+    }
+
+    /// Set the value of a purl's leaf, replacing any earlier one
+    pub fn insert(&mut self, purl: &str, value: u64) {
+        self.insert_leaf(compute_purl_hash(purl), value);
+    }
+
+    /// Set the value at a leaf index, rehashing only the path from that leaf to the root
+    pub fn insert_leaf(&mut self, leaf_index: [u8; 32], value: u64) {
+        // Collect the siblings on the way down, releasing the path being replaced
+        let mut siblings = [[0u8; 32]; TREE_DEPTH];
+        let mut current = self.root;
+        for d in (0..TREE_DEPTH).rev() {
+            let (left, right) = self.children(d + 1, &current);
+            release_node(&mut self.nodes, d + 1, &current);
+            (siblings[d], current) = if path_bit(&leaf_index, d) == 0 {
+                (right, left)
+            } else {
+                (left, right)
+            };
+        }
+
+        self.leaves.insert(leaf_index, value);
+        let mut current = hash_leaf(value);
+        for (d, sibling) in siblings.iter().enumerate() {
+            let (left, right) = if path_bit(&leaf_index, d) == 0 {
+                (current, *sibling)
+            } else {
+                (*sibling, current)
+            };
+            current = retain_node(&mut self.nodes, d + 1, left, right);
+        }
+        self.root = current;
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.root
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Value stored at a leaf index; absent leaves are 0.
+    pub fn value(&self, leaf_index: &[u8; 32]) -> u64 {
+        self.leaves.get(leaf_index).copied().unwrap_or(0)
+    }
+
+    /// Compact proof for a purl: membership with its value, or non-membership with value 0.
+    pub fn prove(&self, purl: &str) -> CompactProof {
+        self.prove_leaf(&compute_purl_hash(purl))
+    }
+
+    /// Compact proof for a leaf index. Walks from the root exactly like the Go service's
+    /// `GenerateProof`, then drops default siblings and records them in the bitmap.
+    pub fn prove_leaf(&self, leaf_index: &[u8; 32]) -> CompactProof {
+        let mut full = [[0u8; 32]; TREE_DEPTH];
+        let mut current = self.root;
+
+        for d in (0..TREE_DEPTH).rev() {
+            let (left, right) = self.children(d + 1, &current);
+            (full[d], current) = if path_bit(leaf_index, d) == 0 {
+                (right, left)
+            } else {
+                (left, right)
+            };
+        }
+
+        let mut bitmap = [0u8; 32];
+        let mut siblings = Vec::new();
+        for (d, sibling) in full.iter().enumerate() {
+            if *sibling != DEFAULTS[d] {
+                bitmap[d / 8] |= 1 << (d % 8);
+                siblings.push(*sibling);
+            }
+        }
+
+        CompactProof {
+            leaf_index: *leaf_index,
+            value: self.value(leaf_index),
+            bitmap,
+            siblings,
+        }
+    }
+
+    /// Children of the subtree `hash` at `height`; a subtree with no stored node is the
+    /// default one, whose children are defaults too
+    fn children(&self, height: usize, hash: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
+        match self.nodes.get(hash) {
+            Some(node) => (node.left, node.right),
+            None => (DEFAULTS[height - 1], DEFAULTS[height - 1]),
+        }
+    }
+
+    /// Export in the Go service's JSON dump format.
+    pub fn export_snapshot(&self) -> SmtSnapshot {
+        SmtSnapshot {
+            depth: TREE_DEPTH,
+            default_hashes: DEFAULTS.iter().map(to_hex).collect(),
+            root: to_hex(&self.root),
+            nodes: self
+                .nodes
+                .iter()
+                .map(|(parent, node)| {
+                    (
+                        to_hex(parent),
+                        SnapshotNode {
+                            left: to_hex(&node.left),
+                            right: to_hex(&node.right),
+                        },
+                    )
+                })
+                .collect(),
+            leaves: self
+                .leaves
+                .iter()
+                .map(|(index, value)| (bytes32_to_decimal(index), value.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Rebuild a tree from a snapshot's leaves and check it reproduces the claimed root.
+    ///
+    /// The snapshot's stored nodes are never trusted for proofs; they are only used to
+    /// locate the first divergent subtree when the rebuilt root does not match.
+    pub fn import_snapshot(snapshot: &SmtSnapshot) -> Result<Self, SnapshotError> {
+        if snapshot.depth != TREE_DEPTH {
+            return Err(SnapshotError::UnsupportedDepth(snapshot.depth));
+        }
+
+        if !snapshot.default_hashes.is_empty() {
+            if snapshot.default_hashes.len() != DEFAULTS.len() {
+                return Err(SnapshotError::DefaultsMismatch {
+                    height: snapshot.default_hashes.len().min(DEFAULTS.len()),
+                });
+            }
+            for (height, hex) in snapshot.default_hashes.iter().enumerate() {
+                if parse_hash(hex)? != DEFAULTS[height] {
+                    return Err(SnapshotError::DefaultsMismatch { height });
+                }
+            }
+        }
+
+        let claimed_root = parse_hash(&snapshot.root)?;
+
+        let mut leaves = BTreeMap::new();
+        for (key, value) in &snapshot.leaves {
+            let index = decimal_to_bytes32(key)
+                .ok_or_else(|| SnapshotError::InvalidLeafKey(key.clone()))?;
+            let value = parse_leaf_value(value).ok_or_else(|| SnapshotError::InvalidLeafValue {
+                key: key.clone(),
+                value: value.clone(),
+            })?;
+            leaves.insert(index, value);
+        }
+
+        let tree = Self::from_leaves(leaves);
+        if tree.root == claimed_root {
+            return Ok(tree);
+        }
+
+        let mut snapshot_nodes = HashMap::with_capacity(snapshot.nodes.len());
+        for (parent, node) in &snapshot.nodes {
+            snapshot_nodes.insert(
+                parse_hash(parent)?,
+                (parse_hash(&node.left)?, parse_hash(&node.right)?),
+            );
+        }
+
+        Err(SnapshotError::RootMismatch {
+            claimed: claimed_root,
+            computed: tree.root,
+            divergence: Box::new(tree.first_divergence(&snapshot_nodes, claimed_root)),
+        })
+    }
+
+    /// Descend from the roots while exactly one child differs, to find the highest
+    /// subtree whose contents disagree with the snapshot.
+    fn first_divergence(
+        &self,
+        snapshot_nodes: &HashMap<[u8; 32], ([u8; 32], [u8; 32])>,
+        claimed_root: [u8; 32],
+    ) -> Divergence {
+        let mut theirs = claimed_root;
+        let mut ours = self.root;
+        let mut path = String::new();
+
+        for height in (1..=TREE_DEPTH).rev() {
+            let their_children = snapshot_nodes
+                .get(&theirs)
+                .copied()
+                .or_else(|| default_children(height, &theirs));
+            let our_children = self
+                .nodes
+                .get(&ours)
+                .map(|node| (node.left, node.right))
+                .or_else(|| default_children(height, &ours));
+            let (Some(t), Some(o)) = (their_children, our_children) else {
+                break;
+            };
+
+            match (t.0 != o.0, t.1 != o.1) {
+                (true, false) => {
+                    path.push('0');
+                    theirs = t.0;
+                    ours = o.0;
+                }
+                (false, true) => {
+                    path.push('1');
+                    theirs = t.1;
+                    ours = o.1;
+                }
+                // Both sides differ (or neither): this is as specific as it gets
+                _ => break,
+            }
+        }
+
+        Divergence {
+            height: TREE_DEPTH - path.len(),
+            path,
+            snapshot_hash: theirs,
+            computed_hash: ours,
+        }
+    }
+}
+
+fn build_subtree(
+    nodes: &mut HashMap<[u8; 32], Node>,
+    height: usize,
+    items: &[([u8; 32], u64)],
+) -> [u8; 32] {
+    if items.is_empty() {
+        return DEFAULTS[height];
+    }
+    if height == 0 {
+        return hash_leaf(items[0].1);
+    }
+
+    let split = items.partition_point(|(index, _)| path_bit(index, height - 1) == 0);
+    let left = build_subtree(nodes, height - 1, &items[..split]);
+    let right = build_subtree(nodes, height - 1, &items[split..]);
+    retain_node(nodes, height, left, right)
+}
+
+/// Hash `left` and `right` into their parent at `height` and count one more position of
+/// it, storing it unless it is the default of its height
+fn retain_node(
+    nodes: &mut HashMap<[u8; 32], Node>,
+    height: usize,
+    left: [u8; 32],
+    right: [u8; 32],
+) -> [u8; 32] {
+    let parent = hash_pair(&left, &right);
+    if parent != DEFAULTS[height] {
+        nodes
+            .entry(parent)
+            .or_insert(Node {
+                left,
+                right,
+                refs: 0,
+            })
+            .refs += 1;
+    }
+    parent
+}
+
+/// Count one position fewer of the subtree `hash` at `height`, dropping it with the last
+fn release_node(nodes: &mut HashMap<[u8; 32], Node>, height: usize, hash: &[u8; 32]) {
+    if *hash == DEFAULTS[height] {
+        return;
+    }
+    if let Entry::Occupied(mut node) = nodes.entry(*hash) {
+        node.get_mut().refs -= 1;
+        if node.get().refs == 0 {
+            node.remove();
+        }
+    }
+}
+
+fn default_children(height: usize, hash: &[u8; 32]) -> Option<([u8; 32], [u8; 32])> {
+    (*hash == DEFAULTS[height]).then(|| (DEFAULTS[height - 1], DEFAULTS[height - 1]))
+}
+
+/// The Go service's persisted tree: `{depth, defaultHashes, root, nodes, leaves}`.
+/// Node keys and hashes are hex; leaf keys are the decimal leaf index, values decimal.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SmtSnapshot {
+    pub depth: usize,
+    #[cfg_attr(feature = "serde", serde(rename = "defaultHashes", default))]
+    pub default_hashes: Vec<String>,
+    pub root: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub nodes: BTreeMap<String, SnapshotNode>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub leaves: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotNode {
+    pub left: String,
+    pub right: String,
+}
+
+/// Where a rebuilt tree first disagrees with a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Height of the divergent subtree above the leaves (256 is the root)
+    pub height: usize,
+    /// Root-first path bits leading to the subtree ('0' = left, '1' = right)
+    pub path: String,
+    pub snapshot_hash: [u8; 32],
+    pub computed_hash: [u8; 32],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    UnsupportedDepth(usize),
+    DefaultsMismatch {
+        height: usize,
+    },
+    InvalidHex(String),
+    InvalidLeafKey(String),
+    InvalidLeafValue {
+        key: String,
+        value: String,
+    },
+    RootMismatch {
+        claimed: [u8; 32],
+        computed: [u8; 32],
+        divergence: Box<Divergence>,
+    },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::UnsupportedDepth(depth) => {
+                write!(f, "unsupported tree depth {depth}, expected {TREE_DEPTH}")
+            }
+            SnapshotError::DefaultsMismatch { height } => {
+                write!(
+                    f,
+                    "default hash at height {height} does not match this implementation"
+                )
+            }
+            SnapshotError::InvalidHex(value) => write!(f, "invalid 32-byte hex value '{value}'"),
+            SnapshotError::InvalidLeafKey(key) => {
+                write!(
+                    f,
+                    "invalid leaf key '{key}': expected a decimal 256-bit leaf index"
+                )
+            }
+            SnapshotError::InvalidLeafValue { key, value } => {
+                write!(
+                    f,
+                    "invalid value '{value}' for leaf {key}: expected a decimal u64"
+                )
+            }
+            SnapshotError::RootMismatch {
+                claimed,
+                computed,
+                divergence,
+            } => write!(
+                f,
+                "snapshot claims root {} but its leaves rebuild to {}; first divergent subtree at height {} (path '{}'): snapshot has {}, leaves give {}",
+                to_hex(claimed),
+                to_hex(computed),
+                divergence.height,
+                divergence.path,
+                to_hex(&divergence.snapshot_hash),
+                to_hex(&divergence.computed_hash)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+fn parse_hash(hex: &str) -> Result<[u8; 32], SnapshotError> {
+    hex_to_bytes32(hex).map_err(|_| SnapshotError::InvalidHex(hex.to_string()))
+}
+
+fn to_hex(bytes: &[u8; 32]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(64);
+    for byte in bytes {
+        out.push(DIGITS[(byte >> 4) as usize] as char);
+        out.push(DIGITS[(byte & 0x0f) as usize] as char);
+    }
+    out
+}
+
+/// Parse a decimal integer (Go's `big.Int.String()`) into a 32-byte big-endian value.
+fn decimal_to_bytes32(decimal: &str) -> Option<[u8; 32]> {
+    if decimal.is_empty() {
+        return None;
+    }
+
+    let mut out = [0u8; 32];
+    for c in decimal.bytes() {
+        if !c.is_ascii_digit() {
+            return None;
+        }
+        // out = out * 10 + digit, failing on overflow past 256 bits
+        let mut carry = (c - b'0') as u16;
+        for byte in out.iter_mut().rev() {
+            let v = (*byte as u16) * 10 + carry;
+            *byte = v as u8;
+            carry = v >> 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    Some(out)
+}
+
+/// Render a 32-byte big-endian value as decimal, matching Go's `big.Int.String()`.
+fn bytes32_to_decimal(bytes: &[u8; 32]) -> String {
+    let mut value = *bytes;
+    let mut digits = Vec::new();
+
+    while value.iter().any(|&b| b != 0) {
+        // value, remainder = value / 10, value % 10
+        let mut remainder = 0u16;
+        for byte in value.iter_mut() {
+            let v = (remainder << 8) | *byte as u16;
+            *byte = (v / 10) as u8;
+            remainder = v % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+
+    if digits.is_empty() {
+        return "0".to_string();
+    }
+    digits.iter().rev().map(|&d| d as char).collect()
+}
+
+/// Trees and proofs checked against the Go merkle-proof-service's own output. The files
+/// in `testdata` are what the Go service stores for a five-leaf tree and its
+/// `/prove-batch` response (with `compress: true`) for three of those purls and two
+/// absent ones; `go test ./... -update` in merkle-proof-service rewrites them.
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    const GO_SNAPSHOT: &str = include_str!("../testdata/go-smt-snapshot.json");
+    const GO_PROVE_BATCH: &str = include_str!("../testdata/go-smt-prove-batch.json");
+
+    /// Import `snapshot` on a thread with room for the 256-deep rebuild, as the proving
+    /// service's loader does
+    fn import(snapshot: SmtSnapshot) -> Result<SparseMerkleTree, SnapshotError> {
+        std::thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(move || SparseMerkleTree::import_snapshot(&snapshot))
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    fn go_tree() -> SparseMerkleTree {
+        import(serde_json::from_str(GO_SNAPSHOT).unwrap()).unwrap()
+    }
+
+    /// The `/prove-batch` response body the Go service renders for `purls`
+    fn prove_batch<'a>(tree: &SparseMerkleTree, purls: impl Iterator<Item = &'a str>) -> String {
+        let proofs: Vec<String> = purls
+            .map(|purl| {
+                let proof = tree.prove(purl);
+                let siblings: Vec<String> =
+                    proof.siblings.iter().map(|s| format!("\"{}\"", to_hex(s))).collect();
+                format!(
+                    r#"{{"purl":"{purl}","value":"{}","siblings":[{}],"leaf_index":"{}","bitmap":"{}"}}"#,
+                    proof.value,
+                    siblings.join(","),
+                    to_hex(&proof.leaf_index),
+                    to_hex(&proof.bitmap)
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"depth":{TREE_DEPTH},"root":"{}","merkle_proofs":[{}]}}"#,
+            to_hex(&tree.root()),
+            proofs.join(",")
+        )
+    }
+
+    #[test]
+    fn go_snapshots_export_byte_for_byte() {
+        let tree = go_tree();
+        assert_eq!(tree.len(), 5);
+        let exported = serde_json::to_string(&tree.export_snapshot()).unwrap();
+        assert!(
+            exported == GO_SNAPSHOT,
+            "export differs from the Go snapshot"
+        );
+    }
+
+    #[test]
+    fn proofs_match_the_go_service_byte_for_byte() {
+        let response: serde_json::Value = serde_json::from_str(GO_PROVE_BATCH).unwrap();
+        let purls = response["merkle_proofs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|proof| proof["purl"].as_str().unwrap());
+        assert_eq!(prove_batch(&go_tree(), purls), GO_PROVE_BATCH);
+    }
+
+    #[test]
+    fn tampered_go_snapshots_name_the_divergent_subtree() {
+        let mut snapshot: SmtSnapshot = serde_json::from_str(GO_SNAPSHOT).unwrap();
+        let value = snapshot.leaves.values_mut().next().unwrap();
+        *value = "7".to_string();
+
+        let Err(SnapshotError::RootMismatch { divergence, .. }) = import(snapshot) else {
+            panic!("a changed leaf must not import");
+        };
+        assert_eq!(divergence.height, 0, "{divergence:?}");
+        assert_eq!(divergence.path.len(), TREE_DEPTH);
+    }
+}
@@ -0,0 +1,45 @@
+//! Prove a fixture through the library API, without the HTTP server.
+//!
+//! Usage (fake receipts, seconds instead of minutes):
+//!   RISC0_DEV_MODE=1 cargo run --example embedded --no-default-features --features prover [-- <request.json>]
+
+use proving_service::models::ProveCompactMerkleRequest;
+use proving_service::{ProverConfig, ProverService};
+
+const DEFAULT_FIXTURE: &str = "benchmark/data/merkleproofs/batch_proof_2.json";
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_FIXTURE.to_string());
+    let request: ProveCompactMerkleRequest = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to load request '{path}': {e}");
+            std::process::exit(2);
+        });
+
+    let config = ProverConfig {
+        proofs_dir: std::env::temp_dir().join("sbom-proofs"),
+        ..ProverConfig::default()
+    };
+    let service = ProverService::new(config);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+    match runtime.block_on(service.prove_compact(request)) {
+        Ok(artifact) => {
+            println!(
+                "root {} compliant={} proofs={} receipt={} bytes",
+                artifact.root_hash, artifact.compliant, artifact.proof_count, artifact.proof_size
+            );
+        }
+        Err(e) => {
+            eprintln!("Proving failed: {e}");
+            std::process::exit(1);
+        }
+    }
+}
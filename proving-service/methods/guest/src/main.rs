@@ -1,23 +1,56 @@
 use risc0_zkvm::guest::env;
-use risc0_zkvm::guest::sha::Impl as Sha256Impl;
-use risc0_zkvm::guest::sha::rust_crypto::Sha256;
+use risc0_zkvm::serde::from_slice;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use sha2::Digest;
 
-use sbom_common::{DEFAULTS, bitmap_bit, compute_purl_hash, hash_pair, hash_value, hex_to_bytes32, path_bit};
+use sbom_common::failure;
+use sbom_common::input::{InputHeader, PROTOCOL_LEGACY, PROTOCOL_SECTIONS, Section, status, tag};
+use sbom_common::json_limits::{JsonLimitError, PROOFS_JSON_LIMITS, check_json_limits};
+use sbom_common::proof::{ProofFields, check_proof_sizes, parse_proof};
+use sbom_common::{
+    CompactProofError, DEFAULTS, KeyType, ProofType, compute_banned_list_commitment, compute_banned_list_hash, compute_leaf_index,
+    compute_list_info_hash, compute_reasons_hash, compute_salt_commitment, defaults_digest, hash_leaf,
+    verify_compact_proof,
+};
 
 #[derive(Serialize, Deserialize, Clone)]
 struct CompactMerkleProof {
-    purl: String,
+    #[serde(alias = "purl")]
+    key: String,
+    #[serde(default)]
+    key_type: KeyType,
     value: String,
     leaf_index: String,
     siblings: Vec<String>,
     bitmap: String,
 }
 
+impl CompactMerkleProof {
+    fn fields(&self) -> ProofFields<'_> {
+        ProofFields {
+            key: &self.key,
+            key_type: self.key_type,
+            value: &self.value,
+            leaf_index: &self.leaf_index,
+            siblings: &self.siblings,
+            bitmap: &self.bitmap,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ListInfo {
+    source_uri: String,
+    publisher: String,
+    version: String,
+    entry_count: u64,
+}
+
 #[derive(Serialize, Deserialize)]
 struct MerklePublicInputs {
     root_hash: [u8; 32],
+    expected_banned_list_hash: Option<[u8; 32]>,
+    list_info: Option<ListInfo>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -25,123 +58,276 @@ struct MerklePublicOutputs {
     root_hash: [u8; 32],
     banned_list_hash: [u8; 32],
     compliant: bool,
+    proof_count: u32,
+    reasons_hash: [u8; 32],
+    list_info_hash: [u8; 32],
+    input_status: u32,
+    salt_commitment: [u8; 32],
+    key_type: u32,
+    proof_type: u32,
+    /// Digest of the DEFAULTS table this guest verifies against, hashed at runtime so the
+    /// journal states the table actually compiled in rather than a constant beside it
+    defaults_digest: [u8; 32],
+    /// Committed as the host sent it; all zero for an unauthenticated request
+    requester_commitment: [u8; 32],
+    first_failure: u32,
+    first_failure_index: u32,
+    first_failure_key_hash: [u8; 32],
 }
 
-fn main() {
-    let proofs_json: String = env::read();
-    let public_inputs: MerklePublicInputs = env::read();
+/// The first proof that kept a request from being compliant, and why (see `sbom_common::failure`)
+#[derive(Clone, Copy)]
+struct FirstFailure {
+    reason: u32,
+    index: u32,
+    key_hash: [u8; 32],
+}
 
-    let proofs: Vec<CompactMerkleProof> = match serde_json::from_str(&proofs_json) {
-        Ok(p) => p,
-        Err(_) => {
-            // Empty banned list for invalid JSON
-            let banned_list_hash = compute_banned_list_hash(&[]);
-            commit_result(
-                &public_inputs.root_hash,
-                &banned_list_hash,
-                false,
-            );
-            return;
+impl FirstFailure {
+    const NONE: Self = Self::unnamed(failure::NONE);
+
+    /// A failure that cannot be pinned to a single proof
+    const fn unnamed(reason: u32) -> Self {
+        Self {
+            reason,
+            index: 0,
+            key_hash: [0u8; 32],
         }
-    };
+    }
 
-    // Collect purls as string slices to avoid cloning
-    let banned_list: Vec<&str> = proofs.iter().map(|p| p.purl.as_str()).collect();
-    let banned_list_hash = compute_banned_list_hash(&banned_list);
+    fn at(reason: u32, index: usize, proof: &CompactMerkleProof) -> Self {
+        Self {
+            reason,
+            index: index as u32,
+            key_hash: compute_leaf_index(proof.key_type, &proof.key).unwrap_or([0u8; 32]),
+        }
+    }
+}
 
-    let compliant = validate_proofs(&proofs, &public_inputs.root_hash);
-    commit_result(
-        &public_inputs.root_hash,
-        &banned_list_hash,
-        compliant,
-    );
+/// Proofs JSON, public inputs, the optional blinding salt, the statement to prove and
+/// the optional requester commitment
+type GuestInputs = (String, MerklePublicInputs, Option<[u8; 32]>, ProofType, Option<[u8; 32]>);
+
+fn main() {
+    let header: InputHeader = env::read();
+    let inputs = match header.protocol_version {
+        _ if header.flags != 0 => Err(status::MALFORMED_INPUT),
+        PROTOCOL_LEGACY if header.section_count != 0 => Err(status::MALFORMED_INPUT),
+        PROTOCOL_LEGACY => Ok((env::read(), env::read(), None, ProofType::NonMembership, None)),
+        PROTOCOL_SECTIONS => read_sections(header.section_count),
+        _ => Err(status::UNSUPPORTED_PROTOCOL),
+    };
+
+    match inputs {
+        Ok((proofs_json, public_inputs, salt, proof_type, requester)) => {
+            validate(&proofs_json, &public_inputs, salt.as_ref(), proof_type, requester.unwrap_or([0u8; 32]))
+        }
+        Err(input_status) => reject(input_status),
+    }
 }
 
-fn compute_banned_list_hash(banned_list: &[&str]) -> [u8; 32] {
-    let json = serde_json::to_string(&banned_list).unwrap_or_else(|_| "[]".to_string());
-    let mut hasher = Sha256::<Sha256Impl>::new();
-    hasher.update(json.as_bytes());
-    hasher.finalize().into()
+/// Commit a rejection. Nothing about the request can be trusted, so only the status
+/// is meaningful.
+fn reject(input_status: u32) {
+    env::commit(&MerklePublicOutputs {
+        root_hash: [0u8; 32],
+        banned_list_hash: compute_banned_list_hash::<&str>(&[]),
+        compliant: false,
+        proof_count: 0,
+        reasons_hash: [0u8; 32],
+        list_info_hash: [0u8; 32],
+        input_status,
+        salt_commitment: [0u8; 32],
+        key_type: KeyType::Purl.code(),
+        proof_type: ProofType::NonMembership.code(),
+        defaults_digest: defaults_digest(&DEFAULTS),
+        requester_commitment: [0u8; 32],
+        first_failure: failure::NONE,
+        first_failure_index: 0,
+        first_failure_key_hash: [0u8; 32],
+    });
 }
 
-fn validate_proofs(proofs: &[CompactMerkleProof], root_hash: &[u8; 32]) -> bool {
-    for proof in proofs {
-        // Early exit: check value is "0" (non-membership proof)
-        if proof.value != "0" {
-            return false;
+/// Read the sections of a version 2 input. Unknown optional sections are skipped; each
+/// known section may appear at most once, and only the proofs and public inputs are required.
+fn read_sections(section_count: u32) -> Result<GuestInputs, u32> {
+    let mut proofs_json = None;
+    let mut public_inputs = None;
+    let mut salt = None;
+    let mut proof_type_code: Option<u32> = None;
+    let mut requester = None;
+
+    for _ in 0..section_count {
+        let section: Section = env::read();
+        match section.tag {
+            tag::PROOFS => decode_once(&mut proofs_json, &section.payload)?,
+            tag::PUBLIC_INPUTS => decode_once(&mut public_inputs, &section.payload)?,
+            tag::BLINDING_SALT => decode_once(&mut salt, &section.payload)?,
+            tag::PROOF_TYPE => decode_once(&mut proof_type_code, &section.payload)?,
+            tag::REQUESTER => decode_once(&mut requester, &section.payload)?,
+            _ if section.mandatory => return Err(status::UNKNOWN_MANDATORY_SECTION),
+            _ => {}
         }
+    }
 
-        // Parse bitmap and leaf_index once, outside the hot loop
-        let bitmap = match hex_to_bytes32(&proof.bitmap) {
-            Ok(b) => b,
-            Err(_) => return false,
-        };
+    let proof_type = match proof_type_code {
+        None => ProofType::NonMembership,
+        Some(code) => ProofType::from_code(code).ok_or(status::MALFORMED_INPUT)?,
+    };
 
-        let leaf_index = match hex_to_bytes32(&proof.leaf_index) {
-            Ok(li) => li,
-            Err(_) => return false,
-        };
+    match (proofs_json, public_inputs) {
+        (Some(proofs_json), Some(public_inputs)) => Ok((proofs_json, public_inputs, salt, proof_type, requester)),
+        _ => Err(status::MALFORMED_INPUT),
+    }
+}
 
-        // This binds the proof to the claimed purl, preventing attacks where
-        // an attacker provides a valid proof for a different position
-        let expected_leaf_index = compute_purl_hash(&proof.purl);
-        if leaf_index != expected_leaf_index {
-            return false;
-        }
+fn decode_once<T: DeserializeOwned>(slot: &mut Option<T>, payload: &[u32]) -> Result<(), u32> {
+    if slot.is_some() {
+        return Err(status::MALFORMED_INPUT);
+    }
+    *slot = Some(from_slice(payload).map_err(|_| status::MALFORMED_INPUT)?);
+    Ok(())
+}
 
-        // Pre-parse all sibling hashes to avoid repeated hex parsing in the loop
-        let mut siblings_parsed: Vec<[u8; 32]> = Vec::with_capacity(proof.siblings.len());
-        for hex in &proof.siblings {
-            match hex_to_bytes32(hex) {
-                Ok(h) => siblings_parsed.push(h),
-                Err(_) => return false,
-            }
-        }
+fn validate(
+    proofs_json: &str,
+    public_inputs: &MerklePublicInputs,
+    salt: Option<&[u8; 32]>,
+    proof_type: ProofType,
+    requester_commitment: [u8; 32],
+) {
+    // Provenance of the banned list is committed as given; all zero when none was supplied
+    let list_info_hash = public_inputs.list_info.as_ref().map_or([0u8; 32], |info| {
+        compute_list_info_hash(&info.source_uri, &info.publisher, &info.version, info.entry_count)
+    });
+    let salt_commitment = salt.map_or([0u8; 32], compute_salt_commitment);
 
-        // Start with leaf hash (hash of value "0")
-        let mut current = hash_value(&proof.value);
-        let mut sibling_idx = 0;
-
-        // Traverse tree from leaf to root (256 levels for depth-256 tree)
-        for d in 0..256 {
-            // Get sibling: either from provided siblings or from DEFAULTS
-            let sibling = if bitmap_bit(&bitmap, d) == 1 {
-                if sibling_idx >= siblings_parsed.len() {
-                    return false;
-                }
-                let s = siblings_parsed[sibling_idx];
-                sibling_idx += 1;
-                s
-            } else {
-                DEFAULTS[d]
-            };
-
-            // Determine if current node is left (0) or right (1) child
-            let direction = path_bit(&leaf_index, d);
-            current = if direction == 0 {
-                hash_pair(&current, &sibling)
-            } else {
-                hash_pair(&sibling, &current)
-            };
-        }
+    // serde_json recurses per nesting level; a hostile document must not exhaust the guest
+    // stack after the prover has already spent cycles on it
+    match check_json_limits(proofs_json.as_bytes(), &PROOFS_JSON_LIMITS) {
+        Ok(()) => {}
+        Err(JsonLimitError::TooDeep { .. }) => return reject(status::JSON_TOO_DEEP),
+        Err(JsonLimitError::TooManyTokens { .. }) => return reject(status::JSON_TOO_MANY_TOKENS),
+    }
 
-        // Final hash must match root
-        if current != *root_hash {
-            return false;
+    let proofs: Vec<CompactMerkleProof> = match serde_json::from_str(proofs_json) {
+        Ok(p) => p,
+        Err(_) => {
+            // Empty banned list for invalid JSON
+            env::commit(&MerklePublicOutputs {
+                root_hash: public_inputs.root_hash,
+                banned_list_hash: compute_banned_list_commitment::<&str>(&[], salt),
+                compliant: false,
+                proof_count: 0,
+                reasons_hash: [0u8; 32],
+                list_info_hash,
+                input_status: status::OK,
+                salt_commitment,
+                key_type: KeyType::Purl.code(),
+                proof_type: proof_type.code(),
+                defaults_digest: defaults_digest(&DEFAULTS),
+                requester_commitment,
+                first_failure: failure::MALFORMED_PROOFS,
+                first_failure_index: 0,
+                first_failure_key_hash: [0u8; 32],
+            });
+            return;
         }
+    };
+
+    // Bound the work before any sibling is decoded; the host's `/validate` runs the same check
+    if let Err(error) = check_proof_sizes(proofs.iter().map(CompactMerkleProof::fields)) {
+        return reject(error.status());
     }
 
-    true
-}
+    // The journal states one key type for the whole list; an empty list is a purl list
+    let key_type = proofs.first().map_or(KeyType::Purl, |p| p.key_type);
+    if proofs.iter().any(|p| p.key_type != key_type) {
+        return reject(status::MIXED_KEY_TYPES);
+    }
 
-fn commit_result(
-    root_hash: &[u8; 32],
-    banned_list_hash: &[u8; 32],
-    compliant: bool,
-) {
+    // Collect keys as string slices to avoid cloning
+    let banned_list: Vec<&str> = proofs.iter().map(|p| p.key.as_str()).collect();
+    let banned_list_hash = compute_banned_list_hash(&banned_list);
+
+    // Every proof must verify; the set is compliant only if every leaf value is one the proof
+    // type accepts: 0 (absent) for non-membership, nonzero (present) for membership.
+    // A zero reasons_hash signals that no (leaf_index, value) commitment could be made.
+    let (mut first_failure, reasons_hash) = match validate_proofs(&proofs, &public_inputs.root_hash) {
+        Ok(Ok(entries)) => {
+            let rejected = entries.iter().position(|&(_, value)| !proof_type.accepts(value));
+            let first_failure = rejected.map_or(FirstFailure::NONE, |index| {
+                FirstFailure::at(failure::VALUE_REJECTED, index, &proofs[index])
+            });
+            (first_failure, compute_reasons_hash(entries))
+        }
+        Ok(Err(first_failure)) => (first_failure, [0u8; 32]),
+        Err(input_status) => return reject(input_status),
+    };
+
+    // Strict mode: the proofs must cover exactly the list the caller committed to, in order
+    let list_matches = public_inputs
+        .expected_banned_list_hash
+        .is_none_or(|expected| expected == banned_list_hash);
+    if !list_matches && first_failure.reason == failure::NONE {
+        first_failure = FirstFailure::unnamed(failure::LIST_MISMATCH);
+    }
+    let compliant = first_failure.reason == failure::NONE;
+    // Strict mode compares the bare hash; only the committed value is blinded
+    let committed_list_hash = match salt {
+        Some(_) => compute_banned_list_commitment(&banned_list, salt),
+        None => banned_list_hash,
+    };
     env::commit(&MerklePublicOutputs {
-        root_hash: *root_hash,
-        banned_list_hash: *banned_list_hash,
+        root_hash: public_inputs.root_hash,
+        banned_list_hash: committed_list_hash,
         compliant,
+        proof_count: proofs.len() as u32,
+        reasons_hash,
+        list_info_hash,
+        input_status: status::OK,
+        salt_commitment,
+        key_type: key_type.code(),
+        proof_type: proof_type.code(),
+        defaults_digest: defaults_digest(&DEFAULTS),
+        requester_commitment,
+        first_failure: first_failure.reason,
+        first_failure_index: first_failure.index,
+        first_failure_key_hash: first_failure.key_hash,
     });
 }
+
+/// (leaf_index, value) of each verified proof, in request order
+type Entries = Vec<([u8; 32], u64)>;
+
+/// Verify every proof against the root and return its (leaf_index, value) pair, or
+/// `Ok(Err(first_failure))` naming the first proof that fails. A proof that is not in
+/// canonical compact form rejects the whole input with `Err(status)` instead.
+fn validate_proofs(
+    proofs: &[CompactMerkleProof],
+    root_hash: &[u8; 32],
+) -> Result<Result<Entries, FirstFailure>, u32> {
+    let mut entries = Vec::with_capacity(proofs.len());
+
+    for (index, proof) in proofs.iter().enumerate() {
+        let parsed = match parse_proof(proof.fields()) {
+            Ok(parsed) => parsed,
+            Err(error) => return Ok(Err(FirstFailure::at(error.failure(), index, proof))),
+        };
+
+        // Fold from leaf (hash of the value parsed above) to root using the shared recomputation
+        let leaf_hash = hash_leaf(parsed.value);
+        match verify_compact_proof(&leaf_hash, &parsed.leaf_index, &parsed.bitmap, &parsed.siblings, root_hash) {
+            Ok(true) => entries.push((parsed.leaf_index, parsed.value)),
+            Ok(false) => return Ok(Err(FirstFailure::at(failure::ROOT_MISMATCH, index, proof))),
+            Err(CompactProofError::MissingSibling { .. }) => {
+                return Ok(Err(FirstFailure::at(failure::MISSING_SIBLING, index, proof)));
+            }
+            // Accepting these would let siblings be consumed differently than in the
+            // canonical encoding of the same proof, yielding a different journal
+            Err(CompactProofError::DefaultSibling { .. }) => return Err(status::NON_CANONICAL_PROOF),
+        }
+    }
+
+    Ok(Ok(entries))
+}
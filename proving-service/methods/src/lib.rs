@@ -1,2 +1,5 @@
-
 include!(concat!(env!("OUT_DIR"), "/methods.rs"));
+
+/// Capabilities of the guest built alongside `SBOM_VALIDATOR_ELF`, kept in step with its
+/// `main.rs`. Hosts read it to pick an input protocol the guest understands.
+pub const SBOM_VALIDATOR_MANIFEST: &str = include_str!("../guest/manifest.json");
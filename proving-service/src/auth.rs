@@ -0,0 +1,79 @@
+//! API-key authentication of the proving endpoints.
+//!
+//! Proving is expensive, so once `API_KEYS` lists any keys, [`require_api_key`] admits a
+//! proving request, an import into the proof store, or a read of stored proofs and job
+//! results, only with one of their tokens in `X-Api-Key`. The matched key's id is put on
+//! the request's tracing span. Without keys every request is admitted, as before. Other
+//! endpoints, `/health` among them, are never wrapped.
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{HttpMessage, HttpRequest, Result as ActixResult, web};
+use tracing::Instrument;
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::reload::ReloadableConfig;
+
+/// Carries the API key a proving request is made with
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Id of the key an admitted request presented, in its extensions
+#[derive(Debug, Clone)]
+pub struct ApiKeyId(pub String);
+
+/// Middleware admitting a request only with a configured API key, when there are any
+pub async fn require_api_key(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> ActixResult<ServiceResponse<EitherBody<impl MessageBody>>> {
+    let admitted = match req.app_data::<web::Data<ReloadableConfig>>() {
+        Some(config) => authenticate(req.request(), &config.load()),
+        None => Err(Error::Internal(
+            "API key check started without its configuration".to_string(),
+        )),
+    };
+    match admitted {
+        Ok(Some(key_id)) => {
+            let span = tracing::info_span!("api_key", key_id = %key_id);
+            req.extensions_mut().insert(ApiKeyId(key_id));
+            Ok(next.call(req).instrument(span).await?.map_into_left_body())
+        }
+        Ok(None) => Ok(next.call(req).await?.map_into_left_body()),
+        Err(e) => Ok(req.error_response(e).map_into_right_body()),
+    }
+}
+
+/// The id of the key `http_req` presents, or `None` when no keys are configured
+fn authenticate(http_req: &HttpRequest, config: &Config) -> Result<Option<String>> {
+    if config.api_keys.is_empty() {
+        return Ok(None);
+    }
+    let presented = http_req
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .unwrap_or_default();
+    // Every key is compared, so the time taken does not tell which one came closest
+    let matched = config
+        .api_keys
+        .iter()
+        .filter(|(_, key)| key.matches(presented))
+        .map(|(key_id, _)| key_id)
+        .last();
+    match matched {
+        Some(key_id) => Ok(Some(key_id.clone())),
+        None => {
+            let err_msg = format!(
+                "{} {} needs a valid API key in {}",
+                http_req.method(),
+                http_req.path(),
+                API_KEY_HEADER
+            );
+            tracing::error!("{}", err_msg);
+            Err(Error::Unauthorized(err_msg))
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Autoscaling signal for the proving backlog.
+//!
+//! CPU is a poor scaling signal for provers: a replica proving one large batch looks as
+//! busy as one proving a small one, and requests waiting for a thread use no CPU at all.
+//! [`signal`] instead weighs every request in the [`Backlog`] by its estimated proving
+//! time ([`estimated_prove_time`]) and turns the total into the replicas needed to drain
+//! it within a target time. `GET /autoscale` serves the signal and `/metrics` exports it
+//! as gauges, so a Kubernetes HPA can scale on either.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use sbom_common::observability::{metric, metrics};
+
+use crate::service::estimated_prove_time;
+
+/// How much proving a replica takes on, and how fast the backlog should drain
+#[derive(Debug, Clone, Copy)]
+pub struct AutoscalePolicy {
+    /// Requests one replica proves at full speed at once (`PROVER_CAPACITY`)
+    pub prover_capacity: usize,
+    /// Time within which the recommended replicas drain the backlog
+    /// (`AUTOSCALE_TARGET_DRAIN_SECS`)
+    pub target_drain: Duration,
+}
+
+/// A proving request in the backlog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BacklogJob {
+    pub proof_count: usize,
+    /// How long it has been proving; `None` while it waits for a prover thread
+    pub running_for: Option<Duration>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AutoscaleSignal {
+    /// Requests waiting for a prover thread
+    pub queued: usize,
+    /// Requests being proven
+    pub running: usize,
+    /// Estimated proving time left over the whole backlog, in seconds
+    pub backlog_seconds: f64,
+    /// Replicas' worth of work in the backlog: `backlog_seconds` over the proving seconds
+    /// one replica delivers within the target drain time
+    pub backlog_score: f64,
+    /// `backlog_score` rounded up, and at least 1
+    pub recommended_replicas: u32,
+    pub prover_capacity: usize,
+    pub target_drain_seconds: u64,
+}
+
+/// The autoscaling signal for `backlog` under `policy`. More backlog, whether more
+/// requests or more proofs per request, never lowers the recommendation.
+pub fn signal(backlog: &[BacklogJob], policy: &AutoscalePolicy) -> AutoscaleSignal {
+    let running = backlog
+        .iter()
+        .filter(|job| job.running_for.is_some())
+        .count();
+    // A running request is charged what is left of its estimate; one that overran it is
+    // about to finish and adds nothing more
+    let backlog_seconds: f64 = backlog
+        .iter()
+        .map(|job| {
+            let estimate = estimated_prove_time(job.proof_count);
+            estimate
+                .saturating_sub(job.running_for.unwrap_or_default())
+                .as_secs_f64()
+        })
+        .sum();
+
+    let replica_seconds =
+        policy.prover_capacity.max(1) as f64 * policy.target_drain.as_secs_f64().max(1.0);
+    let backlog_score = backlog_seconds / replica_seconds;
+    let recommended_replicas = (backlog_score.ceil() as u32).max(1);
+
+    AutoscaleSignal {
+        queued: backlog.len() - running,
+        running,
+        backlog_seconds,
+        backlog_score,
+        recommended_replicas,
+        prover_capacity: policy.prover_capacity,
+        target_drain_seconds: policy.target_drain.as_secs(),
+    }
+}
+
+/// Export `signal` as the autoscaling gauges
+pub fn publish(signal: &AutoscaleSignal) {
+    let _ = metrics().set_gauge(
+        metric::AUTOSCALE_BACKLOG_SECONDS,
+        &[],
+        signal.backlog_seconds.ceil() as i64,
+    );
+    let _ = metrics().set_gauge(
+        metric::AUTOSCALE_RECOMMENDED_REPLICAS,
+        &[],
+        i64::from(signal.recommended_replicas),
+    );
+}
+
+/// Proving requests handed to the prover and not yet finished, shared by clones of the
+/// service
+#[derive(Debug, Default)]
+pub struct Backlog {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, Entry>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    proof_count: usize,
+    started: Option<Instant>,
+}
+
+impl Backlog {
+    /// Add a request of `proof_count` proofs; it leaves the backlog when the guard drops
+    pub fn enter(self: &Arc<Self>, proof_count: usize) -> BacklogGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.lock().insert(
+            id,
+            Entry {
+                proof_count,
+                started: None,
+            },
+        );
+        BacklogGuard {
+            backlog: self.clone(),
+            id,
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<BacklogJob> {
+        let now = Instant::now();
+        self.lock()
+            .values()
+            .map(|entry| BacklogJob {
+                proof_count: entry.proof_count,
+                running_for: entry.started.map(|started| now - started),
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Entry>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A request's place in the [`Backlog`]
+#[derive(Debug)]
+pub struct BacklogGuard {
+    backlog: Arc<Backlog>,
+    id: u64,
+}
+
+impl BacklogGuard {
+    /// Mark the request as picked up by a prover thread
+    pub fn start(&self) {
+        if let Some(entry) = self.backlog.lock().get_mut(&self.id) {
+            entry.started = Some(Instant::now());
+        }
+    }
+}
+
+impl Drop for BacklogGuard {
+    fn drop(&mut self) {
+        self.backlog.lock().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: AutoscalePolicy = AutoscalePolicy {
+        prover_capacity: 2,
+        target_drain: Duration::from_secs(60),
+    };
+
+    fn queued(proof_count: usize) -> BacklogJob {
+        BacklogJob {
+            proof_count,
+            running_for: None,
+        }
+    }
+
+    /// Synthetic backlogs: a few requests of varying size, some of them running
+    fn backlogs() -> Vec<Vec<BacklogJob>> {
+        let mut backlogs = vec![Vec::new()];
+        for jobs in 1..=12 {
+            backlogs.push(
+                (0..jobs)
+                    .map(|n| BacklogJob {
+                        proof_count: 1 + n * 37 % 50,
+                        running_for: (n % 3 == 0).then(|| Duration::from_secs(n as u64)),
+                    })
+                    .collect(),
+            );
+        }
+        backlogs
+    }
+
+    #[test]
+    fn an_idle_prover_keeps_one_replica() {
+        let idle = signal(&[], &POLICY);
+        assert_eq!((idle.queued, idle.running), (0, 0));
+        assert_eq!(idle.backlog_seconds, 0.0);
+        assert_eq!(idle.recommended_replicas, 1);
+    }
+
+    #[test]
+    fn more_backlog_never_lowers_the_recommendation() {
+        for backlog in backlogs() {
+            let before = signal(&backlog, &POLICY);
+            assert_eq!(before.queued + before.running, backlog.len());
+
+            // Another request
+            for proof_count in [1, 10, 1000] {
+                let more = [backlog.clone(), vec![queued(proof_count)]].concat();
+                let after = signal(&more, &POLICY);
+                assert!(after.backlog_seconds >= before.backlog_seconds);
+                assert!(after.recommended_replicas >= before.recommended_replicas);
+            }
+            // More proofs in each request
+            let bigger: Vec<BacklogJob> = backlog
+                .iter()
+                .map(|job| BacklogJob {
+                    proof_count: job.proof_count * 4,
+                    ..*job
+                })
+                .collect();
+            let after = signal(&bigger, &POLICY);
+            assert!(after.recommended_replicas >= before.recommended_replicas);
+        }
+    }
+
+    #[test]
+    fn running_requests_are_charged_what_is_left() {
+        let estimate = estimated_prove_time(10);
+        let half_done = BacklogJob {
+            proof_count: 10,
+            running_for: Some(estimate / 2),
+        };
+        let overran = BacklogJob {
+            proof_count: 10,
+            running_for: Some(estimate * 2),
+        };
+        let left = signal(&[half_done], &POLICY).backlog_seconds;
+        assert!((left - (estimate / 2).as_secs_f64()).abs() < 1e-6);
+        assert_eq!(signal(&[overran], &POLICY).backlog_seconds, 0.0);
+
+        // Replicas scale with the work over what one replica drains in the target time
+        let replica_seconds = 2.0 * 60.0;
+        let heavy = vec![queued(10); 40];
+        let heavy = signal(&heavy, &POLICY);
+        let expected = (heavy.backlog_seconds / replica_seconds).ceil() as u32;
+        assert_eq!(heavy.recommended_replicas, expected.max(1));
+    }
+
+    #[test]
+    fn requests_leave_the_backlog_when_their_guard_drops() {
+        let backlog = Arc::new(Backlog::default());
+        let first = backlog.enter(3);
+        let second = backlog.enter(5);
+        second.start();
+
+        let mut jobs = backlog.snapshot();
+        jobs.sort_by_key(|job| job.proof_count);
+        assert_eq!(jobs[0], queued(3));
+        assert!(jobs[1].running_for.is_some());
+
+        drop(second);
+        assert_eq!(backlog.snapshot(), [queued(3)]);
+        drop(first);
+        assert!(backlog.snapshot().is_empty());
+    }
+}
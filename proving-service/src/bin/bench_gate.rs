@@ -0,0 +1,248 @@
+//! Compare benchmark results against a stored baseline and fail on regressions.
+//!
+//! Collects criterion mean times from `cargo bench --bench throughput` and guest cycle
+//! counts from executing fixed fixtures, then compares each metric to the baseline.
+//!
+//! Usage:
+//!   bench-gate [--criterion-dir <dir>] [--baseline <path>] [--threshold <fraction>]
+//!              [--no-cycles] [--update]
+//!
+//! `--update` overwrites the baseline with the current results instead of comparing.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use methods::SBOM_VALIDATOR_ELF;
+use proving_service::models::{MerklePublicInputs, ProveCompactMerkleRequest};
+use proving_service::prover::{guest_env, negotiate_input_protocol};
+use proving_service::utils::hex_to_bytes32;
+use risc0_zkvm::default_executor;
+
+const DEFAULT_CRITERION_DIR: &str = "target/criterion";
+const DEFAULT_BASELINE: &str = "benchmark/baseline.json";
+/// Allowed relative increase before a metric counts as a regression
+const DEFAULT_THRESHOLD: f64 = 0.10;
+
+/// Fixtures whose guest cycle counts are tracked
+const CYCLE_FIXTURES: &[(&str, &str)] = &[
+    (
+        "batch_proof_2",
+        include_str!("../../benchmark/data/merkleproofs/batch_proof_2.json"),
+    ),
+    (
+        "batch_proof_10",
+        include_str!("../../benchmark/data/merkleproofs/batch_proof_10.json"),
+    ),
+    (
+        "batch_proof_50",
+        include_str!("../../benchmark/data/merkleproofs/batch_proof_50.json"),
+    ),
+];
+
+struct Options {
+    criterion_dir: PathBuf,
+    baseline: PathBuf,
+    threshold: f64,
+    cycles: bool,
+    update: bool,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        eprintln!(
+            "Usage: bench-gate [--criterion-dir <dir>] [--baseline <path>] [--threshold <fraction>] [--no-cycles] [--update]"
+        );
+        std::process::exit(2);
+    });
+
+    let current = match collect(&options) {
+        Ok(metrics) if !metrics.is_empty() => metrics,
+        Ok(_) => {
+            eprintln!(
+                "No benchmark results found in '{}'; run `cargo bench --bench throughput` first",
+                options.criterion_dir.display()
+            );
+            std::process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("Failed to collect benchmark results: {e}");
+            std::process::exit(2);
+        }
+    };
+
+    if options.update {
+        let json = serde_json::to_string_pretty(&serde_json::json!({ "metrics": current }))
+            .expect("metrics serialize");
+        if let Err(e) = std::fs::write(&options.baseline, json + "\n") {
+            eprintln!("Failed to write '{}': {e}", options.baseline.display());
+            std::process::exit(2);
+        }
+        eprintln!(
+            "Baseline '{}' updated with {} metrics",
+            options.baseline.display(),
+            current.len()
+        );
+        return;
+    }
+
+    let baseline = load_baseline(&options.baseline).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+
+    let mut regressions = 0;
+    for (name, &base) in &baseline {
+        let Some(&now) = current.get(name) else {
+            eprintln!("  missing    {name}: in baseline but not measured");
+            continue;
+        };
+        let change = if base > 0.0 { now / base - 1.0 } else { 0.0 };
+        let status = if change > options.threshold {
+            regressions += 1;
+            "REGRESSED"
+        } else {
+            "ok"
+        };
+        eprintln!(
+            "  {status:<10} {name}: {base:.1} -> {now:.1} ({:+.1}%)",
+            change * 100.0
+        );
+    }
+    for name in current.keys().filter(|name| !baseline.contains_key(*name)) {
+        eprintln!("  new        {name}: not in baseline");
+    }
+
+    if regressions > 0 {
+        eprintln!(
+            "{regressions} metric(s) regressed by more than {:.0}%. If intended, rerun with --update",
+            options.threshold * 100.0
+        );
+        std::process::exit(1);
+    }
+    eprintln!("No regressions above {:.0}%", options.threshold * 100.0);
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        criterion_dir: PathBuf::from(DEFAULT_CRITERION_DIR),
+        baseline: PathBuf::from(DEFAULT_BASELINE),
+        threshold: DEFAULT_THRESHOLD,
+        cycles: true,
+        update: false,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--criterion-dir" => options.criterion_dir = value(&mut args, &arg)?.into(),
+            "--baseline" => options.baseline = value(&mut args, &arg)?.into(),
+            "--threshold" => {
+                let raw = value(&mut args, &arg)?;
+                options.threshold =
+                    raw.parse()
+                        .ok()
+                        .filter(|t: &f64| *t >= 0.0)
+                        .ok_or_else(|| {
+                            format!("Invalid threshold '{raw}': expected a non-negative fraction")
+                        })?;
+            }
+            "--no-cycles" => options.cycles = false,
+            "--update" => options.update = true,
+            other => return Err(format!("Unknown argument '{other}'")),
+        }
+    }
+    Ok(options)
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("{flag} requires a value"))
+}
+
+fn collect(options: &Options) -> Result<BTreeMap<String, f64>, String> {
+    let mut metrics = BTreeMap::new();
+    collect_criterion(&options.criterion_dir, &options.criterion_dir, &mut metrics)?;
+    if options.cycles {
+        for (name, fixture) in CYCLE_FIXTURES {
+            metrics.insert(format!("cycles/{name}"), guest_cycles(fixture)? as f64);
+        }
+    }
+    Ok(metrics)
+}
+
+/// Record `mean.point_estimate` (ns) of every `<bench>/new/estimates.json` under `dir`
+fn collect_criterion(
+    root: &Path,
+    dir: &Path,
+    metrics: &mut BTreeMap<String, f64>,
+) -> Result<(), String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(());
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let estimates = path.join("new/estimates.json");
+        if estimates.is_file() {
+            let raw = std::fs::read_to_string(&estimates)
+                .map_err(|e| format!("Failed to read '{}': {e}", estimates.display()))?;
+            let parsed: serde_json::Value = serde_json::from_str(&raw)
+                .map_err(|e| format!("Failed to parse '{}': {e}", estimates.display()))?;
+            let mean = parsed["mean"]["point_estimate"]
+                .as_f64()
+                .ok_or_else(|| format!("'{}' has no mean.point_estimate", estimates.display()))?;
+            let name = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            metrics.insert(format!("time_ns/{name}"), mean);
+        } else if path.file_name().is_some_and(|n| n != "report") {
+            collect_criterion(root, &path, metrics)?;
+        }
+    }
+    Ok(())
+}
+
+/// Execute the guest on a fixture without proving and return its total cycle count
+fn guest_cycles(fixture: &str) -> Result<u64, String> {
+    let request: ProveCompactMerkleRequest =
+        serde_json::from_str(fixture).map_err(|e| format!("Invalid fixture: {e}"))?;
+    let proofs_json = serde_json::to_string(&request.merkle_proofs).map_err(|e| e.to_string())?;
+    let public_inputs = MerklePublicInputs {
+        root_hash: hex_to_bytes32(&request.root).map_err(|e| e.to_string())?,
+        expected_banned_list_hash: None,
+        list_info: request.list_info,
+        blinding_salt: None,
+        proof_type: Default::default(),
+        receipt_kind: Default::default(),
+        requester_commitment: None,
+        fast_path: false,
+        prover_opts: Default::default(),
+    };
+
+    let protocol = negotiate_input_protocol().map_err(|e| e.to_string())?;
+    let env = guest_env(&proofs_json, &public_inputs, protocol).map_err(|e| e.to_string())?;
+
+    let session = default_executor()
+        .execute(env, SBOM_VALIDATOR_ELF)
+        .map_err(|e| format!("Guest execution failed: {e}"))?;
+    Ok(session.cycles())
+}
+
+fn load_baseline(path: &Path) -> Result<BTreeMap<String, f64>, String> {
+    let raw = std::fs::read_to_string(path).map_err(|e| {
+        format!(
+            "Failed to read baseline '{}': {e}. Create it with --update",
+            path.display()
+        )
+    })?;
+    let parsed: serde_json::Value = serde_json::from_str(&raw)
+        .map_err(|e| format!("Invalid baseline '{}': {e}", path.display()))?;
+    serde_json::from_value(parsed["metrics"].clone())
+        .map_err(|e| format!("Invalid metrics in baseline '{}': {e}", path.display()))
+}
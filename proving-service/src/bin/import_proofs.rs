@@ -0,0 +1,86 @@
+//! Import proofs made elsewhere into the proof store, as `POST /proofs/import` does.
+//!
+//! Usage:
+//!   import-proofs [--submitter <name>] <file>...
+//!
+//! Each file holds one proof document, enveloped or bare, an array of them, or an import
+//! request body (`{"proofs": [...]}`). Receipts are verified against the embedded guest image and
+//! stored under `PROOFS_DIR`; the configuration is read as the service reads it. Exits
+//! with 1 when any document is invalid.
+
+use proving_service::config::Config;
+use proving_service::models::ImportStatus;
+use proving_service::{ProverConfig, ProverService};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (submitter, paths) = match args.as_slice() {
+        [flag, name, paths @ ..] if flag == "--submitter" && !paths.is_empty() => {
+            (Some(name.clone()), paths)
+        }
+        [first, ..] if !first.starts_with("--") => (None, args.as_slice()),
+        _ => {
+            eprintln!("Usage: import-proofs [--submitter <name>] <file>...");
+            std::process::exit(2);
+        }
+    };
+
+    match run(submitter, paths) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Import every document in `paths`; false when any of them was invalid
+fn run(submitter: Option<String>, paths: &[String]) -> Result<bool, String> {
+    let mut documents = Vec::new();
+    for path in paths {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read '{path}': {e}"))?;
+        let value: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|e| format!("'{path}' is not valid JSON: {e}"))?;
+        match value {
+            serde_json::Value::Array(items) => documents.extend(items),
+            serde_json::Value::Object(mut body) if body.contains_key("proofs") => {
+                match body.remove("proofs") {
+                    Some(serde_json::Value::Array(items)) => documents.extend(items),
+                    _ => return Err(format!("'proofs' in '{path}' is not an array")),
+                }
+            }
+            document => documents.push(document),
+        }
+    }
+
+    let config = Config::load()?;
+    let redaction = config.redaction_for(submitter.as_deref());
+    let service = ProverService::new(ProverConfig::from(&config));
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .map_err(|e| format!("Failed to start a runtime: {e}"))?;
+    let response = runtime
+        .block_on(service.import_proofs(documents, submitter, redaction))
+        .map_err(|e| format!("Import failed: {e}"))?;
+
+    for result in &response.results {
+        let status = match result.status {
+            ImportStatus::Imported => "imported",
+            ImportStatus::Duplicate => "duplicate",
+            ImportStatus::Invalid => "invalid",
+        };
+        let detail = result
+            .proof_id
+            .as_deref()
+            .or(result.error.as_deref())
+            .unwrap_or_default();
+        println!("{}\t{}\t{}", result.index, status, detail);
+    }
+    eprintln!(
+        "{} imported, {} duplicate(s), {} invalid",
+        response.imported, response.duplicates, response.invalid
+    );
+    Ok(response.invalid == 0)
+}
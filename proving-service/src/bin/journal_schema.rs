@@ -0,0 +1,140 @@
+//! Emit the machine-readable description of the compact merkle journal.
+//!
+//! Usage:
+//!   journal-schema                 print the description to stdout
+//!   journal-schema --check <path>  fail if <path> differs from the current description
+
+use proving_service::digest::encode_journal;
+use proving_service::models::{MerklePublicOutputs, ProveCompactMerkleRequest};
+use proving_service::utils::{
+    compute_banned_list_hash, compute_list_info_hash, compute_reasons_hash, hex_to_bytes32,
+    parse_leaf_value,
+};
+use risc0_zkvm::serde::to_vec;
+use sbom_common::{DEFAULTS_DIGEST, KeyType, ProofType, failure, input};
+use sbom_common::journal::MERKLE_PUBLIC_OUTPUTS;
+
+const EXAMPLE_FIXTURE_PATH: &str = "benchmark/data/merkleproofs/batch_proof_2.json";
+const EXAMPLE_FIXTURE: &str = include_str!("../../benchmark/data/merkleproofs/batch_proof_2.json");
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let description = match describe() {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("Failed to build journal description: {e}");
+            std::process::exit(2);
+        }
+    };
+
+    match args.as_slice() {
+        [] => println!("{description}"),
+        [flag, path] if flag == "--check" => {
+            let golden = std::fs::read_to_string(path).unwrap_or_else(|e| {
+                eprintln!("Failed to read '{path}': {e}");
+                std::process::exit(2);
+            });
+            let current: serde_json::Value =
+                serde_json::from_str(&description).expect("description is valid JSON");
+            let matches = serde_json::from_str::<serde_json::Value>(&golden)
+                .map(|golden| golden == current)
+                .unwrap_or(false);
+            if !matches {
+                eprintln!(
+                    "Journal description differs from '{path}'. If the journal layout changed intentionally, bump JOURNAL_SCHEMA_VERSION and regenerate it with `cargo run --bin journal-schema > {path}`"
+                );
+                std::process::exit(1);
+            }
+            eprintln!("'{path}' is up to date");
+        }
+        _ => {
+            eprintln!("Usage: journal-schema [--check <path>]");
+            std::process::exit(2);
+        }
+    }
+}
+
+fn describe() -> proving_service::Result<String> {
+    let request: ProveCompactMerkleRequest = serde_json::from_str(EXAMPLE_FIXTURE)?;
+    let banned_list: Vec<&str> = request.merkle_proofs.iter().map(|p| p.key.as_str()).collect();
+    let reasons = request
+        .merkle_proofs
+        .iter()
+        .map(|p| Ok((hex_to_bytes32(&p.leaf_index)?, parse_leaf_value(&p.value).unwrap_or(0))))
+        .collect::<proving_service::Result<Vec<_>>>()?;
+
+    let outputs = MerklePublicOutputs {
+        root_hash: hex_to_bytes32(&request.root)?,
+        banned_list_hash: compute_banned_list_hash(&banned_list),
+        compliant: true,
+        proof_count: request.merkle_proofs.len() as u32,
+        reasons_hash: compute_reasons_hash(reasons),
+        list_info_hash: request
+            .list_info
+            .as_ref()
+            .map_or([0u8; 32], compute_list_info_hash),
+        input_status: input::status::OK,
+        salt_commitment: [0u8; 32],
+        key_type: KeyType::Purl.code(),
+        proof_type: ProofType::NonMembership.code(),
+        defaults_digest: DEFAULTS_DIGEST,
+        requester_commitment: [0u8; 32],
+        first_failure: failure::NONE,
+        first_failure_index: 0,
+        first_failure_key_hash: [0u8; 32],
+    };
+
+    let words = to_vec(&outputs).map_err(|e| proving_service::Error::Risc0(e.to_string()))?;
+    let journal_bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    // Proof ids hash the journal as encoded on the host, which must match the guest's
+    if journal_bytes != encode_journal(&outputs) {
+        return Err(proving_service::Error::Risc0(
+            "digest::encode_journal disagrees with RISC Zero serde; update it with the journal layout".to_string(),
+        ));
+    }
+
+    let description = serde_json::json!({
+        "layout": MERKLE_PUBLIC_OUTPUTS,
+        "total_words": MERKLE_PUBLIC_OUTPUTS.total_words(),
+        "total_bytes": MERKLE_PUBLIC_OUTPUTS.total_bytes(),
+        "example": {
+            "fixture": EXAMPLE_FIXTURE_PATH,
+            "outputs": {
+                "root_hash": hex::encode(outputs.root_hash),
+                "banned_list_hash": hex::encode(outputs.banned_list_hash),
+                "compliant": outputs.compliant,
+                "proof_count": outputs.proof_count,
+                "reasons_hash": hex::encode(outputs.reasons_hash),
+                "list_info_hash": hex::encode(outputs.list_info_hash),
+                "input_status": outputs.input_status,
+                "salt_commitment": hex::encode(outputs.salt_commitment),
+                "defaults_digest": hex::encode(outputs.defaults_digest),
+                "requester_commitment": hex::encode(outputs.requester_commitment),
+                "first_failure": outputs.first_failure,
+                "first_failure_index": outputs.first_failure_index,
+                "first_failure_key_hash": hex::encode(outputs.first_failure_key_hash),
+            },
+            "journal_words": words.len(),
+            "journal_hex": hex::encode(&journal_bytes),
+        },
+    });
+
+    Ok(serde_json::to_string_pretty(&description)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_checked_in_description_is_current() {
+        let golden: serde_json::Value =
+            serde_json::from_str(include_str!("../../../docs/journal-schema.json")).unwrap();
+        let current: serde_json::Value = serde_json::from_str(&describe().unwrap()).unwrap();
+        assert!(
+            golden == current,
+            "docs/journal-schema.json is stale; regenerate it with `make journal-schema`"
+        );
+    }
+}
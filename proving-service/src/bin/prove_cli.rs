@@ -0,0 +1,130 @@
+//! Prove a request without running the server, e.g. in an air-gapped environment.
+//!
+//! Usage:
+//!   prove-cli [--out <path>] [--dev-mode] [--allow-noncompliant] <request.json>
+//!
+//! The file holds a `POST /prove-merkle-compact` body. It is validated and proven by the
+//! same pipeline the endpoint runs, with the configuration read as the service reads it,
+//! and the proof is recorded under `PROOFS_DIR`. The proof document is written to stdout,
+//! or to `--out`, with the receipt always inline. `--dev-mode` sets `RISC0_DEV_MODE` for
+//! fast, fake receipts. Exits with 1 when the proof attests `compliant=false`, unless
+//! `--allow-noncompliant` is passed, and with 2 when no proof was made.
+
+use std::path::PathBuf;
+
+use proving_service::config::Config;
+use proving_service::models::ProveCompactMerkleRequest;
+use proving_service::storage::RecordFormat;
+use proving_service::{ProverConfig, ProverService};
+
+const USAGE: &str =
+    "Usage: prove-cli [--out <path>] [--dev-mode] [--allow-noncompliant] <request.json>";
+
+struct Options {
+    request: PathBuf,
+    out: Option<PathBuf>,
+    dev_mode: bool,
+    allow_noncompliant: bool,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    });
+
+    if options.dev_mode {
+        // SAFETY: no other thread has been started yet
+        unsafe { std::env::set_var("RISC0_DEV_MODE", "1") };
+        eprintln!("Dev mode: the receipt is fake and verifies nowhere");
+    }
+
+    match run(&options) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Prove the request and write its proof; false when it attests non-compliance and that
+/// is not allowed
+fn run(options: &Options) -> Result<bool, String> {
+    let path = options.request.display();
+    let contents = std::fs::read_to_string(&options.request)
+        .map_err(|e| format!("Failed to read '{path}': {e}"))?;
+    let mut req: ProveCompactMerkleRequest = serde_json::from_str(&contents)
+        .map_err(|e| format!("'{path}' is not a valid prove request: {e}"))?;
+
+    let config = Config::load()?;
+    req.redaction = config.redaction_for(None);
+    // Nothing serves /proofs/{id}/raw here, so the receipt cannot be linked
+    let prover_config = ProverConfig {
+        inline_proof_limit: usize::MAX,
+        ..ProverConfig::from(&config)
+    };
+    let service = ProverService::new(prover_config);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .map_err(|e| format!("Failed to start a runtime: {e}"))?;
+    let artifact = runtime
+        .block_on(service.prove_compact(req))
+        .map_err(|e| format!("Proving failed: {e}"))?;
+
+    let document = if config.legacy_proofs.envelope_responses() {
+        serde_json::to_string_pretty(&RecordFormat::from(&config).envelope(&artifact))
+    } else {
+        serde_json::to_string_pretty(&artifact)
+    }
+    .map_err(|e| format!("Failed to serialize the proof: {e}"))?;
+    match &options.out {
+        Some(out) => std::fs::write(out, document + "\n")
+            .map_err(|e| format!("Failed to write '{}': {e}", out.display()))?,
+        None => println!("{document}"),
+    }
+
+    eprintln!(
+        "Proof {} made in {} ms: compliant={}",
+        artifact.proof_id, artifact.generation_duration_ms, artifact.compliant
+    );
+    if !artifact.compliant && !options.allow_noncompliant {
+        eprintln!("The proof attests non-compliance; pass --allow-noncompliant to accept it");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut request = None;
+    let mut out = None;
+    let mut dev_mode = false;
+    let mut allow_noncompliant = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => {
+                out = Some(
+                    args.next()
+                        .ok_or_else(|| format!("{arg} requires a value"))?
+                        .into(),
+                )
+            }
+            "--dev-mode" => dev_mode = true,
+            "--allow-noncompliant" => allow_noncompliant = true,
+            _ if arg.starts_with("--") => return Err(format!("Unknown option '{arg}'")),
+            _ if request.is_none() => request = Some(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument '{arg}'")),
+        }
+    }
+
+    Ok(Options {
+        request: request.ok_or("Missing the request file")?,
+        out,
+        dev_mode,
+        allow_noncompliant,
+    })
+}
@@ -0,0 +1,114 @@
+//! Load, build and prove from sparse merkle tree snapshots in the Go service's format.
+//!
+//! Usage:
+//!   smt-snapshot verify <snapshot.json>          rebuild the tree and check the claimed root
+//!   smt-snapshot build <purls.txt>               print a snapshot for `purl [value]` lines
+//!   smt-snapshot prove <snapshot.json> <purl>... print a /prove-merkle-compact request body
+
+use proving_service::models::CompactMerkleProof;
+use sbom_common::KeyType;
+use sbom_common::smt::{SmtSnapshot, SparseMerkleTree, TREE_DEPTH};
+
+/// Value given to purls listed without one in a `build` input file.
+const DEFAULT_BANNED_VALUE: u64 = 1;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.as_slice() {
+        [cmd, path] if cmd == "verify" => verify(path),
+        [cmd, path] if cmd == "build" => build(path),
+        [cmd, path, purls @ ..] if cmd == "prove" && !purls.is_empty() => prove(path, purls),
+        _ => {
+            eprintln!("Usage: smt-snapshot verify <snapshot.json>");
+            eprintln!("       smt-snapshot build <purls.txt>");
+            eprintln!("       smt-snapshot prove <snapshot.json> <purl>...");
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+fn verify(path: &str) -> Result<(), String> {
+    let tree = load(path)?;
+    eprintln!(
+        "'{}' is consistent: root {} with {} leaves",
+        path,
+        hex::encode(tree.root()),
+        tree.len()
+    );
+    Ok(())
+}
+
+fn build(path: &str) -> Result<(), String> {
+    let input =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read '{path}': {e}"))?;
+
+    let mut items = Vec::new();
+    for (line_no, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let purl = parts.next().unwrap_or_default();
+        let value = match parts.next() {
+            Some(v) => sbom_common::parse_leaf_value(v).ok_or_else(|| {
+                format!(
+                    "Invalid value '{}' on line {}: expected a decimal u64",
+                    v,
+                    line_no + 1
+                )
+            })?,
+            None => DEFAULT_BANNED_VALUE,
+        };
+        items.push((purl, value));
+    }
+
+    let snapshot = SparseMerkleTree::from_purls(items).export_snapshot();
+    let json = serde_json::to_string(&snapshot)
+        .map_err(|e| format!("Failed to serialize snapshot: {e}"))?;
+    println!("{json}");
+    Ok(())
+}
+
+fn prove(path: &str, purls: &[String]) -> Result<(), String> {
+    let tree = load(path)?;
+
+    let merkle_proofs: Vec<CompactMerkleProof> = purls
+        .iter()
+        .map(|purl| {
+            let proof = tree.prove(purl);
+            CompactMerkleProof {
+                key: purl.clone(),
+                key_type: KeyType::Purl,
+                value: proof.value.to_string(),
+                leaf_index: hex::encode(proof.leaf_index),
+                siblings: proof.siblings.iter().map(hex::encode).collect(),
+                bitmap: hex::encode(proof.bitmap),
+            }
+        })
+        .collect();
+
+    let request = serde_json::json!({
+        "depth": TREE_DEPTH,
+        "root": hex::encode(tree.root()),
+        "merkle_proofs": merkle_proofs,
+    });
+    let json = serde_json::to_string_pretty(&request)
+        .map_err(|e| format!("Failed to serialize request: {e}"))?;
+    println!("{json}");
+    Ok(())
+}
+
+fn load(path: &str) -> Result<SparseMerkleTree, String> {
+    let raw = std::fs::read_to_string(path).map_err(|e| format!("Failed to read '{path}': {e}"))?;
+    let snapshot: SmtSnapshot = serde_json::from_str(&raw)
+        .map_err(|e| format!("Failed to parse snapshot '{path}': {e}"))?;
+    SparseMerkleTree::import_snapshot(&snapshot)
+        .map_err(|e| format!("Snapshot '{path}' rejected: {e}"))
+}
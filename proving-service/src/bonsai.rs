@@ -0,0 +1,197 @@
+//! Remote proving on Bonsai.
+//!
+//! [`BonsaiBackend`] sends the guest image and the same input words the local executor
+//! would get to the Bonsai API, polls the session until it ends, and downloads the
+//! receipt; a Groth16 receipt takes a further SNARK conversion of the session. Bonsai
+//! always compresses, so a composite request comes back succinct. The receipt then goes
+//! through the same journal checks and verification as a local one, so a remote prover
+//! is trusted for nothing but its availability.
+
+use std::time::{Duration, Instant};
+
+use bonsai_sdk::SdkErr;
+use bonsai_sdk::blocking::Client;
+use methods::{SBOM_VALIDATOR_ELF, SBOM_VALIDATOR_ID};
+use risc0_zkvm::{Receipt, sha::Digest};
+use sbom_common::observability::{metric, metrics};
+
+use crate::config::BonsaiConfig;
+use crate::error::{Error, Result};
+use crate::models::{CompactMerkleProof, MerklePublicInputs, ReceiptKind};
+use crate::prover::{
+    GeneratedProof, ProvingBackend, VerifiedReceipt, finish_receipt, prepare_input, verify_receipt,
+};
+
+/// How often a running session or conversion is polled
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct BonsaiBackend {
+    config: BonsaiConfig,
+}
+
+impl BonsaiBackend {
+    pub fn new(config: BonsaiConfig) -> Self {
+        Self { config }
+    }
+
+    fn client(&self) -> Result<Client> {
+        Client::from_parts(
+            self.config.api_url.clone(),
+            self.config.api_key.clone(),
+            risc0_zkvm::VERSION,
+        )
+        .map_err(|e| {
+            Error::RemoteProver(format!(
+                "Cannot create a Bonsai client for {}: {}",
+                self.config.api_url, e
+            ))
+        })
+    }
+}
+
+impl ProvingBackend for BonsaiBackend {
+    fn image_id(&self) -> [u32; 8] {
+        SBOM_VALIDATOR_ID
+    }
+
+    fn name(&self) -> &'static str {
+        "bonsai"
+    }
+
+    fn prove(
+        &self,
+        proofs: &[CompactMerkleProof],
+        public_inputs: &MerklePublicInputs,
+    ) -> Result<GeneratedProof> {
+        let (input, protocol) = prepare_input(proofs, public_inputs)?;
+        let input_bytes: Vec<u8> = input.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let client = self.client()?;
+
+        let image_id = Digest::from(SBOM_VALIDATOR_ID).to_string();
+        client
+            .upload_img(&image_id, SBOM_VALIDATOR_ELF.to_vec())
+            .map_err(|e| {
+                Error::RemoteProver(format!("Failed to upload guest image {image_id}: {e}"))
+            })?;
+        let input_id = client.upload_input(input_bytes).map_err(|e| {
+            Error::RemoteProver(format!(
+                "Failed to upload {} input words: {}",
+                input.len(),
+                e
+            ))
+        })?;
+        let session = client
+            .create_session(image_id, input_id, Vec::new(), false)
+            .map_err(|e| Error::RemoteProver(format!("Failed to start a Bonsai session: {e}")))?;
+        tracing::info!(
+            "Bonsai session {} started with input protocol {}",
+            session.uuid,
+            protocol
+        );
+
+        let started = Instant::now();
+        let mut receipt_url = poll("session", &session.uuid, || {
+            session
+                .status(&client)
+                .map(|status| (status.status, status.receipt_url, status.error_msg))
+        });
+        // Bonsai sessions end in a succinct receipt; Groth16 is a further conversion
+        if receipt_url.is_ok() && public_inputs.receipt_kind == ReceiptKind::Groth16 {
+            receipt_url = client
+                .create_snark(session.uuid.clone())
+                .map_err(|e| {
+                    Error::RemoteProver(format!(
+                        "Failed to start the Groth16 conversion of Bonsai session {}: {}",
+                        session.uuid, e
+                    ))
+                })
+                .and_then(|snark| {
+                    poll("snark", &snark.uuid, || {
+                        snark
+                            .status(&client)
+                            .map(|status| (status.status, status.output, status.error_msg))
+                    })
+                });
+        }
+        let _ = metrics().observe(metric::PROOF_GENERATION_SECONDS, &[], started.elapsed());
+        let receipt_url = receipt_url?;
+        tracing::info!(
+            "Bonsai session {} succeeded after {}s",
+            session.uuid,
+            started.elapsed().as_secs()
+        );
+
+        let receipt_bytes = client.download(&receipt_url).map_err(|e| {
+            Error::RemoteProver(format!(
+                "Failed to download the receipt of Bonsai session {}: {}",
+                session.uuid, e
+            ))
+        })?;
+        let receipt: Receipt = bincode::deserialize(&receipt_bytes).map_err(|e| {
+            Error::RemoteProver(format!(
+                "Bonsai session {} returned a receipt that cannot be decoded: {}",
+                session.uuid, e
+            ))
+        })?;
+
+        let mut generated =
+            finish_receipt(receipt, proofs, public_inputs, protocol).inspect_err(|_| {
+                tracing::error!("Rejected the receipt of Bonsai session {}", session.uuid);
+            })?;
+        generated.session_id = Some(session.uuid);
+        Ok(generated)
+    }
+
+    /// Only builds a client: probing the remote service on every health check would
+    /// spend its quota
+    fn self_check(&self) -> Result<()> {
+        self.client().map(|_| ())
+    }
+
+    /// Verifies locally; a receipt is checked the same way wherever it was proven
+    fn verify(&self, receipt_bytes: &[u8]) -> Result<VerifiedReceipt> {
+        verify_receipt(receipt_bytes)
+    }
+}
+
+/// Poll a Bonsai job until it ends; returns the URL of its receipt. `status` reports the
+/// job's status, receipt URL and error message.
+fn poll(
+    job: &str,
+    id: &str,
+    mut status: impl FnMut() -> std::result::Result<(String, Option<String>, Option<String>), SdkErr>,
+) -> Result<String> {
+    let started = Instant::now();
+    loop {
+        let (state, url, error_msg) = status()
+            .map_err(|e| Error::RemoteProver(format!("Failed to poll Bonsai {job} {id}: {e}")))?;
+        match state.as_str() {
+            "RUNNING" => {
+                tracing::debug!(
+                    "Bonsai {} {} running after {}s",
+                    job,
+                    id,
+                    started.elapsed().as_secs()
+                );
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            "SUCCEEDED" => {
+                return url.ok_or_else(|| {
+                    Error::RemoteProver(format!(
+                        "Bonsai {job} {id} succeeded without a receipt URL"
+                    ))
+                });
+            }
+            other => {
+                return Err(Error::RemoteProver(format!(
+                    "Bonsai {} {} ended with status {}: {}",
+                    job,
+                    id,
+                    other,
+                    error_msg.as_deref().unwrap_or("no error message")
+                )));
+            }
+        }
+    }
+}
@@ -1,26 +1,797 @@
+use std::collections::HashMap;
 use std::env;
+use std::fmt;
+use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
+
+use sbom_common::json_limits::JsonLimits;
+use sha2::{Digest, Sha256};
+
+use crate::digest::normalize_hex;
+use crate::models::{HashFn, ProverOptions};
+use crate::prover::{MAX_SEGMENT_PO2, MIN_SEGMENT_PO2};
+use crate::redact::RedactionKey;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub port: u16,
     pub proofs_dir: PathBuf,
+    /// SQLite database proof records are kept in instead of files in `proofs_dir`; set
+    /// when `PROOF_STORE=sqlite`, from `DATABASE_PATH`. Receipts stay in `proofs_dir`
+    pub database_path: Option<PathBuf>,
+    /// Named roots (e.g. "security-banlist@2024-06") mapped to normalized hex roots
+    pub root_aliases: HashMap<String, String>,
+    /// Receipts larger than this many bytes are offered as a download link instead of inline base64
+    pub inline_proof_limit: usize,
+    /// Responses whose JSON body would exceed this many bytes carry a download link
+    /// instead of the inline proof; `None` leaves response size unbounded
+    pub max_response_bytes: Option<usize>,
+    /// Serve archived proofs only: proving endpoints answer 501. Always set when the
+    /// crate is built without the `prover` feature
+    pub read_only: bool,
+    /// Base URL of a verifier-service that `/verify` redirects to in read-only mode
+    pub verifier_url: Option<String>,
+    /// Retries of a transient prover failure before the request fails
+    pub prove_max_retries: u32,
+    /// Delay before the first retry; doubled for each further one
+    pub prove_retry_backoff: Duration,
+    /// Case-insensitive substrings of prover errors that are worth retrying
+    pub prove_transient_patterns: Vec<String>,
+    /// Settings the local zkVM proves with unless a request overrides them
+    /// (`PROVER_SEGMENT_PO2`, `PROVER_HASHFN`); RISC Zero's defaults where unset
+    pub prover: ProverOptions,
+    /// Prove with RISC Zero's fake prover (`RISC0_DEV_MODE`), for integration tests: fast,
+    /// but the receipts prove nothing and are marked `dev_mode` wherever they appear
+    pub dev_mode: bool,
+    /// SMT snapshot (Go service JSON dump) that `/prove-purl` synthesizes proofs from
+    pub smt_snapshot: Option<PathBuf>,
+    /// Spot checks proving at once; further ones are turned away with 429
+    pub spot_check_concurrency: usize,
+    /// Prove on Bonsai instead of locally; set when both `BONSAI_API_URL` and
+    /// `BONSAI_API_KEY` are
+    pub bonsai: Option<BonsaiConfig>,
+    /// Requests one replica proves at full speed at once; only weighs the autoscaling signal
+    pub prover_capacity: usize,
+    /// Time within which the recommended replica count should drain the proving backlog
+    pub autoscale_target_drain: Duration,
+    /// Request bodies larger than this many bytes are refused with 413 before they are
+    /// read in full
+    pub max_body_bytes: usize,
+    /// Proving and validation requests with more compact proofs than this are refused
+    /// with 413 before any of them is checked
+    pub max_proofs_per_request: usize,
+    /// Nesting depth and token count a JSON request body may reach; checked before the
+    /// body is parsed
+    pub request_json_limits: JsonLimits,
+    /// Proofs whose journal is longer than this many bytes are quarantined without the
+    /// journal being read further
+    pub max_journal_bytes: usize,
+    /// Requests with at most this many proofs take the fast path; 0 turns it off
+    pub fast_path_max_proofs: usize,
+    /// How long the result of the prover self-check behind `/health?deep=true` is reused
+    pub health_check_ttl: Duration,
+    /// Proofs of recent requests kept to answer identical requests without proving; 0
+    /// turns the cache off
+    pub proof_cache_entries: usize,
+    /// How long a cached proof answers identical requests
+    pub proof_cache_ttl: Duration,
+    /// Prove requests a client may make per minute, counted by API key or, without
+    /// `API_KEYS`, by IP address; 0 turns rate limiting off
+    pub rate_limit_per_minute: u32,
+    /// Prove requests a client may make back to back before it is held to the rate
+    pub rate_limit_burst: u32,
+    /// Bearer token admin endpoints such as `/whatif` require; without one they answer 501
+    pub admin_token: Option<AdminToken>,
+    /// `host:port` of a separate admin listener. When set, admin endpoints are served
+    /// only there, all of them behind `admin_token`, and answer 404 on `port`
+    pub admin_bind_addr: Option<String>,
+    /// API keys by key id. When there are any, proving endpoints need a key's token in
+    /// `X-Api-Key`; a request may also be signed with a key's signing secret, and its
+    /// proof then commits to the key id
+    pub api_keys: HashMap<String, ApiKey>,
+    /// Tenants whose keys are kept out of logs, error messages and stored records, by
+    /// API key id or `*` for every other request, each with the key its records are sealed under
+    pub redaction_keys: HashMap<String, RedactionKey>,
+    /// How proof documents without the `sbom-proof` envelope are treated
+    pub legacy_proofs: LegacyProofs,
+    /// Base URL clients reach this service at, which makes the `schema_url` of proof
+    /// envelopes absolute; without it they name the path `/schema`
+    pub public_url: Option<String>,
+    /// Origins browsers may call the API from, or `*` for any; empty sends no CORS
+    /// headers at all. Checked when the server starts
+    pub cors_allowed_origins: Vec<String>,
+    /// Methods a cross-origin request may use
+    pub cors_allowed_methods: Vec<String>,
+    /// Request headers a cross-origin request may send
+    pub cors_allowed_headers: Vec<String>,
+    /// How long browsers may cache a preflight answer
+    pub cors_max_age: Duration,
+}
+
+/// Stage of the move from bare proof documents to the `sbom-proof` envelope (see
+/// `sbom_common::envelope`). Records are always written enveloped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LegacyProofs {
+    /// Bare records and imports are read, and a bare record read by id is rewritten in
+    /// the envelope; responses are enveloped
+    #[default]
+    Accept,
+    /// As `Accept`, but proof responses stay bare for clients that cannot read the
+    /// envelope yet
+    Serve,
+    /// Only enveloped documents are read; bare records are skipped and bare imports refused
+    Reject,
+}
+
+impl LegacyProofs {
+    /// Whether bare documents are read
+    pub fn accepted(self) -> bool {
+        self != LegacyProofs::Reject
+    }
+
+    /// Whether proof responses are enveloped
+    pub fn envelope_responses(self) -> bool {
+        self != LegacyProofs::Serve
+    }
+}
+
+/// Where and as whom to reach the Bonsai proving service
+#[derive(Clone, PartialEq, Eq)]
+pub struct BonsaiConfig {
+    pub api_url: String,
+    pub api_key: String,
+}
+
+impl fmt::Debug for BonsaiConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BonsaiConfig")
+            .field("api_url", &self.api_url)
+            .field("api_key", &"***")
+            .finish()
+    }
+}
+
+/// Secret callers of admin endpoints present as `Authorization: Bearer <token>`
+#[derive(Clone, PartialEq, Eq)]
+pub struct AdminToken(String);
+
+impl AdminToken {
+    /// Whether `presented` is the token. Compares digests, so the time taken does not
+    /// reveal how much of the token a guess got right.
+    pub fn matches(&self, presented: &str) -> bool {
+        Sha256::digest(self.0.as_bytes()) == Sha256::digest(presented.as_bytes())
+    }
+}
+
+impl fmt::Debug for AdminToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AdminToken(***)")
+    }
+}
+
+/// The two secrets of an API key: the bearer token a client presents in `X-Api-Key`, and
+/// the signing secret it signs proving requests with, which never goes on the wire:
+/// `X-Request-Signature` carries the hex HMAC-SHA256 of the request's canonical digest
+/// under it. Kept apart so that whoever relays a request cannot sign under its key id.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKey {
+    token: String,
+    signing_secret: String,
+}
+
+impl ApiKey {
+    /// A key with bearer `token` and `signing_secret`, which must both be non-empty and
+    /// differ from each other
+    pub fn new(token: &str, signing_secret: &str) -> Option<Self> {
+        (!token.is_empty() && !signing_secret.is_empty() && token != signing_secret).then(|| Self {
+            token: token.to_string(),
+            signing_secret: signing_secret.to_string(),
+        })
+    }
+
+    /// A key that admits and signs nothing, standing in for an entry in the old
+    /// `key_id=secret` form so that the proving endpoints stay closed
+    fn disabled() -> Self {
+        Self {
+            token: String::new(),
+            signing_secret: String::new(),
+        }
+    }
+
+    /// Whether `presented` is this key's bearer token. Compares digests in constant time,
+    /// so the time taken reveals neither the token's length nor how much of a guess was right.
+    pub fn matches(&self, presented: &str) -> bool {
+        !self.token.is_empty()
+            && sbom_common::constant_time_eq(
+                &Sha256::digest(self.token.as_bytes()),
+                &Sha256::digest(presented.as_bytes()),
+            )
+    }
+
+    /// Whether `signature`, in hex, is this key's signature of `digest`. Compared in
+    /// constant time, so the time taken does not reveal how much of a guess was right.
+    pub fn signed(&self, digest: &[u8; 32], signature: &str) -> bool {
+        if self.signing_secret.is_empty() {
+            return false;
+        }
+        let expected = sbom_common::hmac_sha256(self.signing_secret.as_bytes(), digest);
+        hex::decode(normalize_hex(signature.trim()))
+            .is_ok_and(|presented| sbom_common::constant_time_eq(&expected, &presented))
+    }
 }
 
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ApiKey(***)")
+    }
+}
+
+/// Tenant of `REDACTION_KEYS` that stands for every request not signed with a listed key
+pub const ANY_TENANT: &str = "*";
+
+/// Default for `INLINE_PROOF_LIMIT_BYTES`: 16 MiB of raw receipt.
+const DEFAULT_INLINE_PROOF_LIMIT: usize = 16 * 1024 * 1024;
+/// Default for `PROVE_MAX_RETRIES`.
+const DEFAULT_PROVE_MAX_RETRIES: u32 = 2;
+/// Default for `PROVE_RETRY_BACKOFF_MS`.
+const DEFAULT_PROVE_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+/// Default for `DATABASE_PATH`, relative to `PROOFS_DIR`.
+const DEFAULT_DATABASE_FILE: &str = "proofs.sqlite3";
+/// Default for `SPOT_CHECK_CONCURRENCY`.
+const DEFAULT_SPOT_CHECK_CONCURRENCY: usize = 1;
+/// Default for `PROVER_CAPACITY`: the zkVM prover uses every core for one request.
+const DEFAULT_PROVER_CAPACITY: usize = 1;
+/// Default for `AUTOSCALE_TARGET_DRAIN_SECS`.
+const DEFAULT_AUTOSCALE_TARGET_DRAIN: Duration = Duration::from_secs(300);
+/// Default for `MAX_BODY_BYTES`: the JSON body limit the service had before it was
+/// configurable.
+const DEFAULT_MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+/// Default for `MAX_PROOFS_PER_REQUEST`: about 25 minutes of conservatively estimated
+/// proving.
+const DEFAULT_MAX_PROOFS_PER_REQUEST: usize = 1000;
+/// Default for `MAX_JOURNAL_BYTES`: far above the 916 bytes of the current journal
+/// layout, so only a runaway guest reaches it.
+const DEFAULT_MAX_JOURNAL_BYTES: usize = 16 * 1024;
+/// Default for `FAST_PATH_MAX_PROOFS`: single-proof requests, which spot checks are.
+const DEFAULT_FAST_PATH_MAX_PROOFS: usize = 1;
+/// Default for `HEALTH_CHECK_TTL_SECS`: a probe every few seconds runs the guest at most
+/// twice a minute.
+const DEFAULT_HEALTH_CHECK_TTL: Duration = Duration::from_secs(30);
+/// Default for `PROOF_CACHE_ENTRIES`: far more distinct requests than a day of retried
+/// builds submits, each a record without its receipt.
+const DEFAULT_PROOF_CACHE_ENTRIES: usize = 1024;
+/// Default for `PROOF_CACHE_TTL_SECS`: a day, long enough for retries of a failed build.
+const DEFAULT_PROOF_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Default for `RATE_LIMIT_PER_MINUTE`: off, every client proves as often as it likes.
+const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 0;
+/// Default for `CORS_ALLOWED_METHODS`: every method the public API routes.
+const DEFAULT_CORS_ALLOWED_METHODS: &[&str] = &["GET", "POST"];
+/// Default for `CORS_ALLOWED_HEADERS`: the headers of JSON requests and every header the
+/// API reads.
+const DEFAULT_CORS_ALLOWED_HEADERS: &[&str] = &[
+    "Accept",
+    "Accept-Language",
+    "Authorization",
+    "Content-Type",
+    "X-Api-Key",
+    "X-Api-Key-Id",
+    "X-Redaction-Key",
+    "X-Request-Deadline",
+    "X-Request-Signature",
+];
+/// Default for `CORS_MAX_AGE_SECS`: an hour of cached preflights.
+const DEFAULT_CORS_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+/// Defaults for `MAX_JSON_DEPTH` and `MAX_JSON_TOKENS`. Requests nest at most 3 deep; a
+/// request of worst-case proofs at `MAX_BODY_BYTES` has about 35,000 tokens.
+const DEFAULT_REQUEST_JSON_LIMITS: JsonLimits = JsonLimits {
+    max_depth: 16,
+    max_tokens: 200_000,
+};
+/// Default for `PROVE_TRANSIENT_PATTERNS`: GPU driver and resource hiccups that
+/// typically succeed on a second attempt.
+const DEFAULT_PROVE_TRANSIENT_PATTERNS: &[&str] = &[
+    "cuda",
+    "driver",
+    "out of memory",
+    "device busy",
+    "resource temporarily unavailable",
+];
+
+/// Environment variable naming an optional `KEY=VALUE` file whose entries override the
+/// environment. It is re-read on every reload, so it is how settings change at runtime.
+pub const CONFIG_FILE_VAR: &str = "CONFIG_FILE";
+
+/// Every variable the configuration reads; a config file naming anything else is rejected
+const VARIABLES: &[&str] = &[
+    "PORT",
+    "PROOFS_DIR",
+    "PROOF_STORE",
+    "DATABASE_PATH",
+    "ROOT_ALIASES",
+    "INLINE_PROOF_LIMIT_BYTES",
+    "MAX_RESPONSE_BYTES",
+    "READ_ONLY",
+    "VERIFIER_URL",
+    "PROVE_MAX_RETRIES",
+    "PROVE_RETRY_BACKOFF_MS",
+    "PROVE_TRANSIENT_PATTERNS",
+    "PROVER_SEGMENT_PO2",
+    "PROVER_HASHFN",
+    "RISC0_DEV_MODE",
+    "SMT_SNAPSHOT",
+    "SPOT_CHECK_CONCURRENCY",
+    "BONSAI_API_URL",
+    "BONSAI_API_KEY",
+    "PROVER_CAPACITY",
+    "AUTOSCALE_TARGET_DRAIN_SECS",
+    "MAX_BODY_BYTES",
+    "MAX_PROOFS_PER_REQUEST",
+    "MAX_JSON_DEPTH",
+    "MAX_JSON_TOKENS",
+    "MAX_JOURNAL_BYTES",
+    "FAST_PATH_MAX_PROOFS",
+    "HEALTH_CHECK_TTL_SECS",
+    "PROOF_CACHE_ENTRIES",
+    "PROOF_CACHE_TTL_SECS",
+    "RATE_LIMIT_PER_MINUTE",
+    "RATE_LIMIT_BURST",
+    "ADMIN_TOKEN",
+    "ADMIN_BIND_ADDR",
+    "API_KEYS",
+    "REDACTION_KEYS",
+    "LEGACY_PROOFS",
+    "PUBLIC_URL",
+    "CORS_ALLOWED_ORIGINS",
+    "CORS_ALLOWED_METHODS",
+    "CORS_ALLOWED_HEADERS",
+    "CORS_MAX_AGE_SECS",
+];
+
 impl Config {
     pub fn from_env() -> Self {
-        let port = env::var("PORT")
-            .ok()
-            .and_then(|p| p.parse().ok())
-            .unwrap_or(8080);
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    /// The environment overlaid with the file named by `CONFIG_FILE`, if set. Unlike
+    /// [`Config::from_env`] this fails when the file cannot be read or parsed.
+    pub fn load() -> Result<Self, String> {
+        let Ok(path) = env::var(CONFIG_FILE_VAR) else {
+            return Ok(Self::from_env());
+        };
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read {CONFIG_FILE_VAR} '{path}': {e}"))?;
+        let file = parse_config_file(&contents)
+            .map_err(|e| format!("Invalid config file '{path}': {e}"))?;
+        Ok(Self::from_vars(|name| {
+            file.get(name).cloned().or_else(|| env::var(name).ok())
+        }))
+    }
+
+    /// Build the configuration from `var`, which looks a variable up by name
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let port = var("PORT").and_then(|p| p.parse().ok()).unwrap_or(8080);
 
-        let proofs_dir = env::var("PROOFS_DIR")
-            .ok()
+        let proofs_dir = var("PROOFS_DIR")
             .map(PathBuf::from)
             .unwrap_or_else(|| PathBuf::from("/app/proofs"));
 
-        Self { port, proofs_dir }
+        let database_path = match var("PROOF_STORE").map(|v| v.trim().to_ascii_lowercase()) {
+            Some(store) if store == "sqlite" => Some(
+                var("DATABASE_PATH")
+                    .filter(|path| !path.trim().is_empty())
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| proofs_dir.join(DEFAULT_DATABASE_FILE)),
+            ),
+            None => None,
+            Some(store) if store.is_empty() || store == "fs" => None,
+            Some(store) => {
+                tracing::warn!(
+                    "Ignoring unknown PROOF_STORE '{}': expected fs or sqlite; keeping records in PROOFS_DIR",
+                    store
+                );
+                None
+            }
+        };
+
+        let root_aliases = var("ROOT_ALIASES")
+            .map(|raw| parse_root_aliases(&raw))
+            .unwrap_or_default();
+
+        let inline_proof_limit = var("INLINE_PROOF_LIMIT_BYTES")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_INLINE_PROOF_LIMIT);
+
+        let max_response_bytes = var("MAX_RESPONSE_BYTES").and_then(|v| v.parse().ok());
+
+        let read_only = !cfg!(feature = "prover")
+            || var("READ_ONLY")
+                .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false);
+
+        let verifier_url = var("VERIFIER_URL")
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty());
+
+        let prove_max_retries = var("PROVE_MAX_RETRIES")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_PROVE_MAX_RETRIES);
+
+        let prove_retry_backoff = var("PROVE_RETRY_BACKOFF_MS")
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_PROVE_RETRY_BACKOFF);
+
+        let prove_transient_patterns = var("PROVE_TRANSIENT_PATTERNS")
+            .map(|raw| parse_list(&raw))
+            .unwrap_or_else(default_transient_patterns);
+
+        let segment_po2 = var("PROVER_SEGMENT_PO2").and_then(|raw| match raw.trim().parse() {
+            Ok(po2) if (MIN_SEGMENT_PO2..=MAX_SEGMENT_PO2).contains(&po2) => Some(po2),
+            _ => {
+                tracing::warn!(
+                    "Ignoring PROVER_SEGMENT_PO2 '{}': expected {} to {}",
+                    raw,
+                    MIN_SEGMENT_PO2,
+                    MAX_SEGMENT_PO2
+                );
+                None
+            }
+        });
+        let hashfn = var("PROVER_HASHFN")
+            .map(|raw| raw.trim().to_ascii_lowercase())
+            .filter(|raw| !raw.is_empty())
+            .and_then(|raw| {
+                let hashfn = HashFn::from_name(&raw);
+                if hashfn.is_none() {
+                    tracing::warn!(
+                        "Ignoring PROVER_HASHFN '{}': expected poseidon2, sha-256 or blake2b",
+                        raw
+                    );
+                }
+                hashfn
+            });
+        let prover = ProverOptions {
+            segment_po2,
+            hashfn,
+        };
+        let dev_mode = var("RISC0_DEV_MODE")
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let smt_snapshot = var("SMT_SNAPSHOT")
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from);
+
+        let spot_check_concurrency = var("SPOT_CHECK_CONCURRENCY")
+            .and_then(|v| v.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_SPOT_CHECK_CONCURRENCY);
+
+        let nonempty = |name: &str| var(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let bonsai = match (nonempty("BONSAI_API_URL"), nonempty("BONSAI_API_KEY")) {
+            (Some(api_url), Some(api_key)) => Some(BonsaiConfig {
+                api_url: api_url.trim_end_matches('/').to_string(),
+                api_key,
+            }),
+            (None, None) => None,
+            (url, _) => {
+                tracing::warn!(
+                    "Ignoring Bonsai settings: {} is set but {} is not; proving locally",
+                    if url.is_some() { "BONSAI_API_URL" } else { "BONSAI_API_KEY" },
+                    if url.is_some() { "BONSAI_API_KEY" } else { "BONSAI_API_URL" }
+                );
+                None
+            }
+        };
+
+        let prover_capacity = var("PROVER_CAPACITY")
+            .and_then(|v| v.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_PROVER_CAPACITY);
+
+        let autoscale_target_drain = var("AUTOSCALE_TARGET_DRAIN_SECS")
+            .and_then(|v| v.parse().ok())
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_AUTOSCALE_TARGET_DRAIN);
+
+        let max_body_bytes = var("MAX_BODY_BYTES")
+            .and_then(|v| v.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_MAX_BODY_BYTES);
+
+        let max_proofs_per_request = var("MAX_PROOFS_PER_REQUEST")
+            .and_then(|v| v.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_MAX_PROOFS_PER_REQUEST);
+
+        let request_json_limits = JsonLimits {
+            max_depth: var("MAX_JSON_DEPTH")
+                .and_then(|v| v.parse().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(DEFAULT_REQUEST_JSON_LIMITS.max_depth),
+            max_tokens: var("MAX_JSON_TOKENS")
+                .and_then(|v| v.parse().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(DEFAULT_REQUEST_JSON_LIMITS.max_tokens),
+        };
+
+        let max_journal_bytes = var("MAX_JOURNAL_BYTES")
+            .and_then(|v| v.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_MAX_JOURNAL_BYTES);
+
+        let fast_path_max_proofs = var("FAST_PATH_MAX_PROOFS")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_FAST_PATH_MAX_PROOFS);
+
+        let health_check_ttl = var("HEALTH_CHECK_TTL_SECS")
+            .and_then(|v| v.parse().ok())
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_HEALTH_CHECK_TTL);
+
+        let proof_cache_entries = var("PROOF_CACHE_ENTRIES")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_PROOF_CACHE_ENTRIES);
+
+        let proof_cache_ttl = var("PROOF_CACHE_TTL_SECS")
+            .and_then(|v| v.parse().ok())
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_PROOF_CACHE_TTL);
+
+        let rate_limit_per_minute = var("RATE_LIMIT_PER_MINUTE")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RATE_LIMIT_PER_MINUTE);
+        // A minute's worth unless set; a bucket holds at least one request
+        let rate_limit_burst = var("RATE_LIMIT_BURST")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(rate_limit_per_minute)
+            .max(1);
+
+        let admin_token = var("ADMIN_TOKEN")
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty())
+            .map(AdminToken);
+
+        let admin_bind_addr = var("ADMIN_BIND_ADDR")
+            .map(|addr| addr.trim().to_string())
+            .filter(|addr| !addr.is_empty());
+
+        let api_keys = var("API_KEYS")
+            .map(|raw| parse_api_keys(&raw))
+            .unwrap_or_default();
+
+        let redaction_keys = var("REDACTION_KEYS")
+            .map(|raw| parse_redaction_keys(&raw))
+            .unwrap_or_default();
+
+        let legacy_proofs = match var("LEGACY_PROOFS").map(|v| v.trim().to_ascii_lowercase()) {
+            None => LegacyProofs::default(),
+            Some(stage) => match stage.as_str() {
+                "" | "accept" => LegacyProofs::Accept,
+                "serve" => LegacyProofs::Serve,
+                "reject" => LegacyProofs::Reject,
+                _ => {
+                    tracing::warn!(
+                        "Ignoring unknown LEGACY_PROOFS '{}': expected accept, serve or reject",
+                        stage
+                    );
+                    LegacyProofs::default()
+                }
+            },
+        };
+
+        let public_url = var("PUBLIC_URL")
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty());
+
+        let cors_allowed_origins = var("CORS_ALLOWED_ORIGINS")
+            .map(|raw| parse_list(&raw))
+            .unwrap_or_default();
+        let cors_allowed_methods = var("CORS_ALLOWED_METHODS")
+            .map(|raw| parse_list(&raw))
+            .filter(|methods| !methods.is_empty())
+            .unwrap_or_else(|| to_strings(DEFAULT_CORS_ALLOWED_METHODS));
+        let cors_allowed_headers = var("CORS_ALLOWED_HEADERS")
+            .map(|raw| parse_list(&raw))
+            .filter(|headers| !headers.is_empty())
+            .unwrap_or_else(|| to_strings(DEFAULT_CORS_ALLOWED_HEADERS));
+        let cors_max_age = var("CORS_MAX_AGE_SECS")
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_CORS_MAX_AGE);
+
+        Self {
+            port,
+            proofs_dir,
+            database_path,
+            root_aliases,
+            inline_proof_limit,
+            max_response_bytes,
+            read_only,
+            verifier_url,
+            prove_max_retries,
+            prove_retry_backoff,
+            prove_transient_patterns,
+            prover,
+            dev_mode,
+            smt_snapshot,
+            spot_check_concurrency,
+            bonsai,
+            prover_capacity,
+            autoscale_target_drain,
+            max_body_bytes,
+            max_proofs_per_request,
+            request_json_limits,
+            max_journal_bytes,
+            fast_path_max_proofs,
+            health_check_ttl,
+            proof_cache_entries,
+            proof_cache_ttl,
+            rate_limit_per_minute,
+            rate_limit_burst,
+            admin_token,
+            admin_bind_addr,
+            api_keys,
+            redaction_keys,
+            legacy_proofs,
+            public_url,
+            cors_allowed_origins,
+            cors_allowed_methods,
+            cors_allowed_headers,
+            cors_max_age,
+        }
+    }
+
+    /// Where proof envelopes point for the schema of their payload: `GET /schema` of
+    /// this service
+    pub fn schema_url(&self) -> String {
+        format!("{}/schema", self.public_url.as_deref().unwrap_or_default())
+    }
+
+    /// Redaction key of the tenant a request signed with `requester` belongs to, or
+    /// `None` when its keys may be logged and stored as they are
+    pub fn redaction_for(&self, requester: Option<&str>) -> Option<RedactionKey> {
+        requester
+            .and_then(|id| self.redaction_keys.get(id))
+            .or_else(|| self.redaction_keys.get(ANY_TENANT))
+            .cloned()
+    }
+}
+
+fn default_transient_patterns() -> Vec<String> {
+    to_strings(DEFAULT_PROVE_TRANSIENT_PATTERNS)
+}
+
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+/// Comma-separated values, trimmed, without empty ones
+fn parse_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// Parse a config file: one `KEY=VALUE` per line; blank lines and lines starting with `#`
+/// are skipped. Values are taken verbatim after trimming.
+fn parse_config_file(contents: &str) -> Result<HashMap<String, String>, String> {
+    let mut vars = HashMap::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!(
+                "line {}: expected KEY=VALUE, got '{}'",
+                number + 1,
+                line
+            ));
+        };
+        let key = key.trim();
+        if !VARIABLES.contains(&key) {
+            return Err(format!(
+                "line {}: unknown setting '{}'. Known settings: {}",
+                number + 1,
+                key,
+                VARIABLES.join(", ")
+            ));
+        }
+        vars.insert(key.to_string(), value.trim().to_string());
     }
+    Ok(vars)
+}
+
+/// Parse `alias=root` pairs separated by commas. Malformed entries are skipped with a warning.
+fn parse_root_aliases(raw: &str) -> HashMap<String, String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let Some((alias, root)) = entry.split_once('=') else {
+                tracing::warn!(
+                    "Ignoring malformed ROOT_ALIASES entry '{}': expected alias=root",
+                    entry
+                );
+                return None;
+            };
+            let root = normalize_hex(root.trim());
+            if root.len() != 64 || !root.bytes().all(|b| b.is_ascii_hexdigit()) {
+                tracing::warn!(
+                    "Ignoring ROOT_ALIASES entry '{}': root must be a 64-character hex string",
+                    entry
+                );
+                return None;
+            }
+            Some((alias.trim().to_string(), root))
+        })
+        .collect()
+}
+
+/// Parse `key_id=token:signing_secret` entries separated by commas. Malformed entries are
+/// skipped with a warning that names the key id but never a secret. An entry in the old
+/// `key_id=secret` form, whose one secret was both sent and signed with, or with an empty
+/// or reused secret, is kept as a disabled key: it admits nothing, but still keeps the
+/// proving endpoints closed.
+fn parse_api_keys(raw: &str) -> HashMap<String, ApiKey> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let Some((id, secrets)) = entry.split_once('=') else {
+                tracing::warn!(
+                    "Ignoring malformed API_KEYS entry: expected key_id=token:signing_secret"
+                );
+                return None;
+            };
+            let id = id.trim();
+            if id.is_empty() {
+                tracing::warn!("Ignoring API_KEYS entry: the key id must not be empty");
+                return None;
+            }
+            let key = secrets
+                .split_once(':')
+                .and_then(|(token, signing_secret)| ApiKey::new(token.trim(), signing_secret.trim()));
+            let key = key.unwrap_or_else(|| {
+                tracing::warn!(
+                    "Disabling API_KEYS entry for key '{}': expected key_id=token:signing_secret, with a token other than the signing secret",
+                    id
+                );
+                ApiKey::disabled()
+            });
+            Some((id.to_string(), key))
+        })
+        .collect()
+}
+
+/// Parse `tenant=key` pairs separated by commas, where a key is 64 hex characters.
+/// Malformed entries are skipped with a warning that names the tenant but never the key.
+fn parse_redaction_keys(raw: &str) -> HashMap<String, RedactionKey> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let Some((tenant, key)) = entry.split_once('=') else {
+                tracing::warn!("Ignoring malformed REDACTION_KEYS entry: expected tenant=key");
+                return None;
+            };
+            let tenant = tenant.trim();
+            let Some(key) = RedactionKey::from_hex(key.trim()).filter(|_| !tenant.is_empty()) else {
+                tracing::warn!(
+                    "Ignoring REDACTION_KEYS entry for tenant '{}': key must be a 64-character hex string",
+                    tenant
+                );
+                return None;
+            };
+            Some((tenant.to_string(), key))
+        })
+        .collect()
 }
 
 impl Default for Config {
@@ -28,6 +799,87 @@ impl Default for Config {
         Self {
             port: 8080,
             proofs_dir: PathBuf::from("/app/proofs"),
+            database_path: None,
+            root_aliases: HashMap::new(),
+            inline_proof_limit: DEFAULT_INLINE_PROOF_LIMIT,
+            max_response_bytes: None,
+            read_only: !cfg!(feature = "prover"),
+            verifier_url: None,
+            prove_max_retries: DEFAULT_PROVE_MAX_RETRIES,
+            prove_retry_backoff: DEFAULT_PROVE_RETRY_BACKOFF,
+            prove_transient_patterns: default_transient_patterns(),
+            prover: ProverOptions::default(),
+            dev_mode: false,
+            smt_snapshot: None,
+            spot_check_concurrency: DEFAULT_SPOT_CHECK_CONCURRENCY,
+            bonsai: None,
+            prover_capacity: DEFAULT_PROVER_CAPACITY,
+            autoscale_target_drain: DEFAULT_AUTOSCALE_TARGET_DRAIN,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_proofs_per_request: DEFAULT_MAX_PROOFS_PER_REQUEST,
+            request_json_limits: DEFAULT_REQUEST_JSON_LIMITS,
+            max_journal_bytes: DEFAULT_MAX_JOURNAL_BYTES,
+            fast_path_max_proofs: DEFAULT_FAST_PATH_MAX_PROOFS,
+            health_check_ttl: DEFAULT_HEALTH_CHECK_TTL,
+            proof_cache_entries: DEFAULT_PROOF_CACHE_ENTRIES,
+            proof_cache_ttl: DEFAULT_PROOF_CACHE_TTL,
+            rate_limit_per_minute: DEFAULT_RATE_LIMIT_PER_MINUTE,
+            rate_limit_burst: 1,
+            admin_token: None,
+            admin_bind_addr: None,
+            api_keys: HashMap::new(),
+            redaction_keys: HashMap::new(),
+            legacy_proofs: LegacyProofs::default(),
+            public_url: None,
+            cors_allowed_origins: Vec::new(),
+            cors_allowed_methods: to_strings(DEFAULT_CORS_ALLOWED_METHODS),
+            cors_allowed_headers: to_strings(DEFAULT_CORS_ALLOWED_HEADERS),
+            cors_max_age: DEFAULT_CORS_MAX_AGE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, digest: &[u8; 32]) -> String {
+        hex::encode(sbom_common::hmac_sha256(secret.as_bytes(), digest))
+    }
+
+    #[test]
+    fn api_key_token_and_signing_secret_are_distinct() {
+        let keys = parse_api_keys("ci=bearer:signing");
+        let key = &keys["ci"];
+        let digest = [7u8; 32];
+
+        assert!(key.matches("bearer"));
+        assert!(!key.matches("signing"));
+        assert!(key.signed(&digest, &sign("signing", &digest)));
+        // The token travels in X-Api-Key, so it must not sign anything
+        assert!(!key.signed(&digest, &sign("bearer", &digest)));
+    }
+
+    #[test]
+    fn api_key_entries_in_the_old_form_are_disabled() {
+        let keys = parse_api_keys("old=secret, same=x:x, empty=:s, ok=t:s, =t:s, junk");
+        assert_eq!(keys.len(), 4);
+        let digest = [1u8; 32];
+        for id in ["old", "same", "empty"] {
+            let key = &keys[id];
+            for presented in ["", "secret", "x", ":s", "s"] {
+                assert!(!key.matches(presented), "{id} admitted {presented:?}");
+            }
+            assert!(!key.signed(&digest, &sign("", &digest)));
+            assert!(!key.signed(&digest, &sign("secret", &digest)));
         }
+        assert!(keys["ok"].matches("t"));
+    }
+
+    #[test]
+    fn api_key_debug_hides_both_secrets() {
+        let key = ApiKey::new("bearer", "signing").unwrap();
+        let shown = format!("{key:?}");
+        assert!(!shown.contains("bearer") && !shown.contains("signing"));
     }
 }
@@ -0,0 +1,286 @@
+use sbom_common::journal::MERKLE_PUBLIC_OUTPUTS;
+use sha2::{Digest, Sha256};
+
+use crate::models::{
+    CompactMerkleProof, HashFn, MerklePublicOutputs, ProveCompactMerkleRequest, ReceiptKind,
+};
+use crate::utils::{KeyType, ProofType, compute_list_info_hash, compute_salt_commitment, hex_to_bytes32};
+
+/// Domain separator so the digest can never collide with other SHA-256 uses in the system.
+const DOMAIN: &[u8] = b"sbom-prove-request/v1";
+/// Domain separator of [`proof_id`].
+const PROOF_ID_DOMAIN: &[u8] = b"sbom-proof-id/v1";
+
+/// Canonical SHA-256 digest of a prove request.
+///
+/// This is the one definition of "the hash of this request" used for cache keys,
+/// idempotency and audit records. Requests that differ only in proof order, hex case
+/// or `0x` prefixes hash identically; any change to depth, root, the key type, the mode,
+/// a proof's key, value, leaf_index, bitmap or siblings, the list_info, the blinding salt,
+/// the receipt kind or a requested hash function changes the digest. Fields that affect neither the statement being
+/// proven nor the receipt returned are deliberately excluded.
+pub fn canonical_request_digest(request: &ProveCompactMerkleRequest) -> [u8; 32] {
+    let mut proofs: Vec<CanonicalProof<'_>> =
+        request.merkle_proofs.iter().map(CanonicalProof::from).collect();
+    proofs.sort_by(|a, b| {
+        a.leaf_index
+            .cmp(&b.leaf_index)
+            .then_with(|| a.key.cmp(b.key))
+    });
+
+    let mut hasher = Sha256::new();
+    hasher.update(DOMAIN);
+    hasher.update((request.depth as u64).to_be_bytes());
+    update_field(&mut hasher, normalize_hex(&request.root).as_bytes());
+    hasher.update((proofs.len() as u64).to_be_bytes());
+
+    for proof in &proofs {
+        update_field(&mut hasher, proof.key.as_bytes());
+        update_field(&mut hasher, proof.value.as_bytes());
+        update_field(&mut hasher, proof.leaf_index.as_bytes());
+        update_field(&mut hasher, proof.bitmap.as_bytes());
+        hasher.update((proof.siblings.len() as u64).to_be_bytes());
+        for sibling in &proof.siblings {
+            update_field(&mut hasher, sibling.as_bytes());
+        }
+    }
+
+    // Appended only for non-purl lists so digests of purl requests are unchanged; mixed
+    // key types are rejected by validation before any digest is taken
+    let key_type = crate::validation::list_key_type(&request.merkle_proofs);
+    if key_type != KeyType::Purl {
+        hasher.update(key_type.code().to_be_bytes());
+    }
+    // Appended only for membership proofs so digests of earlier requests are unchanged.
+    // Labelled, since a bare code would read the same as a key type's
+    if request.mode != ProofType::NonMembership {
+        update_field(&mut hasher, b"mode");
+        hasher.update(request.mode.code().to_be_bytes());
+    }
+    // Appended only when present so digests of requests without list_info are unchanged
+    if let Some(info) = &request.list_info {
+        hasher.update(compute_list_info_hash(info));
+    }
+    // The salt is secret, so only its commitment enters the digest; an unparsable salt
+    // is rejected by validation before any digest is taken
+    if let Some(salt) = request
+        .banned_list_salt
+        .as_deref()
+        .and_then(|salt| hex_to_bytes32(salt).ok())
+    {
+        hasher.update(compute_salt_commitment(&salt));
+    }
+    // Appended only for compressed receipts so digests of earlier requests are unchanged.
+    // The statement is the same, but a cached composite receipt cannot answer for a
+    // Groth16 one
+    if request.receipt_kind != ReceiptKind::Composite {
+        update_field(&mut hasher, b"receipt_kind");
+        update_field(&mut hasher, request.receipt_kind.name().as_bytes());
+    }
+    // Likewise a seal hashed with poseidon2 cannot answer for one asked to use another
+    // hash function; segment sizes leave the receipt's statement and seal kind alone
+    if let Some(hashfn) = request.prover_opts.and_then(|opts| opts.hashfn)
+        && hashfn != HashFn::Poseidon2
+    {
+        update_field(&mut hasher, b"hashfn");
+        update_field(&mut hasher, hashfn.name().as_bytes());
+    }
+
+    hasher.finalize().into()
+}
+
+/// Identifier of a generated proof: SHA-256 over the canonical request digest, the image
+/// ID of the guest that proved it and the digest of its journal.
+///
+/// Re-proving an identical request with the same guest yields the same id, whenever it
+/// runs; a different request, guest or journal yields a different one. The timestamp is
+/// not part of the journal, so it never affects the id.
+pub fn proof_id(request_digest: &[u8; 32], image_id: &[u32; 8], journal_digest: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(PROOF_ID_DOMAIN);
+    hasher.update(request_digest);
+    for word in image_id {
+        hasher.update(word.to_le_bytes());
+    }
+    hasher.update(journal_digest);
+    hasher.finalize().into()
+}
+
+/// SHA-256 of the journal the guest commits for `outputs`.
+pub fn journal_digest(outputs: &MerklePublicOutputs) -> [u8; 32] {
+    Sha256::digest(encode_journal(outputs)).into()
+}
+
+/// The journal bytes the guest commits for `outputs`, encoded as described by
+/// `sbom_common::journal::MERKLE_PUBLIC_OUTPUTS`: every byte, bool and u32 is one
+/// little-endian word. The journal-schema generator checks this against RISC Zero's serde.
+pub fn encode_journal(outputs: &MerklePublicOutputs) -> Vec<u8> {
+    let mut words: Vec<u32> = Vec::with_capacity(MERKLE_PUBLIC_OUTPUTS.total_words());
+    words.extend(outputs.root_hash.map(u32::from));
+    words.extend(outputs.banned_list_hash.map(u32::from));
+    words.push(u32::from(outputs.compliant));
+    words.push(outputs.proof_count);
+    words.extend(outputs.reasons_hash.map(u32::from));
+    words.extend(outputs.list_info_hash.map(u32::from));
+    words.push(outputs.input_status);
+    words.extend(outputs.salt_commitment.map(u32::from));
+    words.push(outputs.key_type);
+    words.push(outputs.proof_type);
+    words.extend(outputs.defaults_digest.map(u32::from));
+    words.extend(outputs.requester_commitment.map(u32::from));
+    words.push(outputs.first_failure);
+    words.push(outputs.first_failure_index);
+    words.extend(outputs.first_failure_key_hash.map(u32::from));
+    words.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
+
+/// Lower-case a hex string and drop an optional `0x` prefix.
+pub fn normalize_hex(hex_str: &str) -> String {
+    hex_str
+        .strip_prefix("0x")
+        .or_else(|| hex_str.strip_prefix("0X"))
+        .unwrap_or(hex_str)
+        .to_ascii_lowercase()
+}
+
+struct CanonicalProof<'a> {
+    key: &'a str,
+    value: &'a str,
+    leaf_index: String,
+    bitmap: String,
+    siblings: Vec<String>,
+}
+
+impl<'a> From<&'a CompactMerkleProof> for CanonicalProof<'a> {
+    fn from(proof: &'a CompactMerkleProof) -> Self {
+        Self {
+            key: &proof.key,
+            value: &proof.value,
+            leaf_index: normalize_hex(&proof.leaf_index),
+            bitmap: normalize_hex(&proof.bitmap),
+            siblings: proof.siblings.iter().map(|s| normalize_hex(s)).collect(),
+        }
+    }
+}
+
+/// Length-prefix every variable-size field so adjacent fields cannot be re-split.
+fn update_field(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_be_bytes());
+    hasher.update(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    fn hash(n: u8) -> String {
+        hex::encode([n; 32])
+    }
+
+    /// A request with two proofs, as a client might send it
+    fn request() -> Value {
+        json!({
+            "depth": 256,
+            "root": hash(0xab),
+            "merkle_proofs": [
+                {
+                    "key": "pkg:npm/b@2.0.0",
+                    "value": "0",
+                    "leaf_index": hash(0x02),
+                    "bitmap": hash(0x0c),
+                    "siblings": [hash(0xc1), hash(0xc2)],
+                },
+                {
+                    "key": "pkg:npm/a@1.0.0",
+                    "value": "7",
+                    "leaf_index": hash(0x01),
+                    "bitmap": hash(0x0d),
+                    "siblings": [hash(0xd1)],
+                },
+            ],
+        })
+    }
+
+    fn digest(request: &Value) -> String {
+        let request: ProveCompactMerkleRequest = serde_json::from_value(request.clone()).unwrap();
+        hex::encode(canonical_request_digest(&request))
+    }
+
+    /// `request()` with `edit` applied
+    fn edited(edit: impl FnOnce(&mut Value)) -> String {
+        let mut request = request();
+        edit(&mut request);
+        digest(&request)
+    }
+
+    /// Cache keys, idempotency keys and proof ids are derived from this value, so a
+    /// change to the encoding must be deliberate and bump `DOMAIN`. Recomputed by hand
+    /// from the encoding documented on [`canonical_request_digest`].
+    #[test]
+    fn digest_is_pinned() {
+        assert_eq!(
+            digest(&request()),
+            "866682e9e2385d83fe842c445837894ee0014b019bb9fe85873328446ea8ae52"
+        );
+    }
+
+    #[test]
+    fn spelling_does_not_change_the_digest() {
+        let expected = digest(&request());
+        let same = [
+            edited(|r| r["merkle_proofs"].as_array_mut().unwrap().reverse()),
+            edited(|r| r["root"] = format!("0x{}", hash(0xab).to_uppercase()).into()),
+            edited(|r| r["merkle_proofs"][0]["leaf_index"] = format!("0x{}", hash(0x02)).into()),
+            edited(|r| r["merkle_proofs"][0]["bitmap"] = hash(0x0c).to_uppercase().into()),
+            edited(|r| r["merkle_proofs"][0]["siblings"][1] = format!("0X{}", hash(0xc2)).into()),
+            // Explicit defaults
+            edited(|r| r["mode"] = "non_membership".into()),
+            edited(|r| r["receipt_kind"] = "composite".into()),
+            // Fields that change neither the statement nor the receipt
+            edited(|r| r["timestamp"] = 1_700_000_000.into()),
+            edited(|r| r["reproducible"] = true.into()),
+            edited(|r| r["force"] = true.into()),
+            edited(|r| r["skip_preverify"] = true.into()),
+            edited(|r| r["max_response_bytes"] = 4096.into()),
+        ];
+        for (case, digest) in same.iter().enumerate() {
+            assert_eq!(*digest, expected, "case {case}");
+        }
+    }
+
+    #[test]
+    fn every_bound_field_changes_the_digest() {
+        let changed = [
+            digest(&request()),
+            edited(|r| r["root"] = hash(0xac).into()),
+            edited(|r| r["merkle_proofs"][0]["key"] = "pkg:npm/b@2.0.1".into()),
+            edited(|r| r["merkle_proofs"][0]["value"] = "1".into()),
+            edited(|r| r["merkle_proofs"][0]["leaf_index"] = hash(0x03).into()),
+            edited(|r| r["merkle_proofs"][0]["bitmap"] = hash(0x0e).into()),
+            edited(|r| r["merkle_proofs"][0]["siblings"][0] = hash(0xc3).into()),
+            edited(|r| {
+                r["merkle_proofs"][0]["siblings"]
+                    .as_array_mut()
+                    .unwrap()
+                    .pop();
+            }),
+            edited(|r| r["merkle_proofs"].as_array_mut().unwrap().truncate(1)),
+            edited(|r| {
+                for proof in r["merkle_proofs"].as_array_mut().unwrap() {
+                    proof["key_type"] = "raw_hash".into();
+                }
+            }),
+            edited(|r| r["mode"] = "membership".into()),
+            edited(|r| r["receipt_kind"] = "succinct".into()),
+            edited(|r| r["receipt_kind"] = "groth16".into()),
+            edited(|r| r["banned_list_salt"] = hash(0x5a).into()),
+            edited(|r| r["banned_list_salt"] = hash(0x5b).into()),
+        ];
+        let distinct: HashSet<_> = changed.iter().collect();
+        assert_eq!(distinct.len(), changed.len());
+    }
+}
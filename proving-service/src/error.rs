@@ -1,3 +1,8 @@
+#[cfg(feature = "server")]
+use actix_web::http::{StatusCode, header};
+#[cfg(feature = "server")]
+use actix_web::{HttpResponse, ResponseError};
+use sbom_common::messages::{Locale, Message, proving};
 use std::fmt;
 
 #[derive(Debug)]
@@ -6,21 +11,251 @@ pub enum Error {
     Io(std::io::Error),
     Json(serde_json::Error),
     Risc0(String),
+    /// The request is malformed or inconsistent; nothing was proven
+    InvalidRequest(String),
+    /// A compact proof in the request is malformed; `purl` is the key it proves
+    ProofValidation {
+        purl: String,
+        reason: String,
+    },
+    /// Proof `index` of the request does not fold up to the requested root, found on the
+    /// host before proving; `computed_root` is `None` when it does not fold at all
+    Preverify {
+        index: usize,
+        purl: String,
+        computed_root: Option<String>,
+        reason: String,
+    },
+    /// The prover ran but did not produce a receipt
+    ProverFailure(String),
+    /// The prover's output failed the host's sanity checks; it was quarantined rather
+    /// than stored or returned
+    InvalidOutput(String),
+    /// The caller's deadline passed or cannot be met
+    DeadlineExceeded(String),
+    /// The deadline passed while the job was still waiting for a prover
+    Unavailable(String),
+    /// A proof artifact could not be written to or read from the proof store
+    Storage(String),
+    /// The operation needs the prover, which this instance runs without
+    ProverDisabled(String),
+    /// The operation needs a setting this instance was started without
+    NotConfigured(String),
+    /// A reloaded configuration was rejected; the previous one stays active
+    InvalidConfig(String),
+    /// A request lacked valid credentials: the admin token, an API key or a signature
+    Unauthorized(String),
+    /// The remote prover failed or could not be reached
+    RemoteProver(String),
+    /// The request exceeds the configured limit `setting` on some quantity counted in
+    /// `unit`; `observed` is `None` when it was refused before the full amount was known
+    LimitExceeded {
+        setting: &'static str,
+        unit: &'static str,
+        limit: usize,
+        observed: Option<usize>,
+    },
+    /// No stored proof, receipt or job matches the request
+    NotFound(String),
+    /// The request matches more than one stored proof
+    Conflict(String),
+    /// Too many operations of this kind are in progress; retry shortly
+    Overloaded(String),
+    /// The client exceeded its `limit` of prove requests per minute; it may try again
+    /// after `retry_after` seconds
+    RateLimited {
+        limit: u32,
+        retry_after: u64,
+    },
+    Internal(String),
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Error {
+    /// Stable machine-readable code, returned alongside the message
+    pub fn code(&self) -> &'static str {
+        self.message().code
+    }
+
+    /// The message in `locale`; `Display` gives the English one
+    pub fn localized(&self, locale: Locale) -> String {
+        self.message().render(locale, &self.params())
+    }
+
+    fn message(&self) -> &'static Message {
         match self {
-            Error::Hex(msg) => write!(f, "Hex error: {msg}"),
-            Error::Io(e) => write!(f, "IO error: {e}"),
-            Error::Json(e) => write!(f, "JSON error: {e}"),
-            Error::Risc0(msg) => write!(f, "RISC0 error: {msg}"),
+            Error::Hex(_) => &proving::INVALID_HEX,
+            Error::Io(_) => &proving::IO_ERROR,
+            Error::Json(_) => &proving::INVALID_JSON,
+            Error::Risc0(_) => &proving::RISC0_ERROR,
+            Error::InvalidRequest(_) => &proving::INVALID_REQUEST,
+            Error::ProofValidation { .. } => &proving::PROOF_VALIDATION,
+            Error::Preverify { .. } => &proving::PREVERIFY_FAILED,
+            Error::ProverFailure(_) => &proving::PROVER_FAILURE,
+            Error::InvalidOutput(_) => &proving::INVALID_OUTPUT,
+            Error::DeadlineExceeded(_) => &proving::DEADLINE_EXCEEDED,
+            Error::Unavailable(_) => &proving::UNAVAILABLE,
+            Error::Storage(_) => &proving::STORAGE_ERROR,
+            Error::ProverDisabled(_) => &proving::PROVER_DISABLED,
+            Error::NotConfigured(_) => &proving::NOT_CONFIGURED,
+            Error::InvalidConfig(_) => &proving::INVALID_CONFIG,
+            Error::Unauthorized(_) => &proving::UNAUTHORIZED,
+            Error::RemoteProver(_) => &proving::REMOTE_PROVER,
+            Error::LimitExceeded {
+                observed: Some(_), ..
+            } => &proving::LIMIT_EXCEEDED,
+            Error::LimitExceeded { observed: None, .. } => &proving::LIMIT_EXCEEDED_UNMEASURED,
+            Error::NotFound(_) => &proving::NOT_FOUND,
+            Error::Conflict(_) => &proving::CONFLICT,
+            Error::Overloaded(_) => &proving::OVERLOADED,
+            Error::RateLimited { .. } => &proving::RATE_LIMITED,
+            Error::Internal(_) => &proving::INTERNAL_ERROR,
+        }
+    }
+
+    /// Values of the placeholders in the error's message
+    fn params(&self) -> Vec<(&'static str, &dyn fmt::Display)> {
+        match self {
+            Error::Io(e) => vec![("detail", e)],
+            Error::Json(e) => vec![("detail", e)],
+            Error::ProofValidation { purl, reason } => vec![("purl", purl), ("reason", reason)],
+            Error::Preverify {
+                index,
+                purl,
+                reason,
+                ..
+            } => vec![("index", index), ("purl", purl), ("reason", reason)],
+            Error::LimitExceeded {
+                setting,
+                unit,
+                limit,
+                observed,
+            } => {
+                let mut params: Vec<(&'static str, &dyn fmt::Display)> =
+                    vec![("setting", setting), ("unit", unit), ("limit", limit)];
+                if let Some(observed) = observed {
+                    params.push(("observed", observed));
+                }
+                params
+            }
+            Error::RateLimited { limit, retry_after } => {
+                vec![("limit", limit), ("retry_after", retry_after)]
+            }
+            Error::Hex(msg)
+            | Error::Risc0(msg)
+            | Error::InvalidRequest(msg)
+            | Error::ProverFailure(msg)
+            | Error::InvalidOutput(msg)
+            | Error::DeadlineExceeded(msg)
+            | Error::Unavailable(msg)
+            | Error::Storage(msg)
+            | Error::ProverDisabled(msg)
+            | Error::NotConfigured(msg)
+            | Error::InvalidConfig(msg)
+            | Error::Unauthorized(msg)
+            | Error::RemoteProver(msg)
+            | Error::NotFound(msg)
+            | Error::Conflict(msg)
+            | Error::Overloaded(msg)
+            | Error::Internal(msg) => vec![("detail", msg)],
+        }
+    }
+
+    /// The error's response with its message in `locale`. The `error` field always
+    /// carries the English message, so clients matching on it see the same text
+    /// whatever language they asked for.
+    #[cfg(feature = "server")]
+    pub fn localized_response(&self, locale: Locale) -> HttpResponse {
+        let mut body = serde_json::json!({
+            "error": self.to_string(),
+            "code": self.code(),
+            "message": self.localized(locale),
+        });
+        if let Error::ProofValidation { purl, .. } = self {
+            body["purl"] = purl.as_str().into();
+        }
+        if let Error::Preverify {
+            index,
+            purl,
+            computed_root,
+            ..
+        } = self
+        {
+            body["index"] = (*index).into();
+            body["purl"] = purl.as_str().into();
+            body["computed_root"] = computed_root.as_deref().into();
+        }
+        if let Error::LimitExceeded {
+            limit, observed, ..
+        } = self
+        {
+            body["limit"] = (*limit).into();
+            body["observed"] = (*observed).into();
         }
+        if let Error::RateLimited { limit, retry_after } = self {
+            body["limit"] = (*limit).into();
+            body["retry_after"] = (*retry_after).into();
+        }
+        let mut response = HttpResponse::build(self.status_code());
+        if let Error::Unauthorized(_) = self {
+            response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+        }
+        if let Error::RateLimited { retry_after, .. } = self {
+            response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+        }
+        response
+            .insert_header((header::CONTENT_LANGUAGE, locale.tag()))
+            .json(body)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.localized(Locale::En))
     }
 }
 
 impl std::error::Error for Error {}
 
+/// Errors answer with `{"error": <message>, "code": <code>, "message": <localized>}`,
+/// plus the offending `purl` for a proof that failed validation, also its `index` and
+/// `computed_root` for one that failed pre-verification, the `limit` and `observed`
+/// amount for an exceeded limit, and the `limit` and `retry_after` seconds, also sent as
+/// `Retry-After`, for an exceeded rate limit. The message is English here; the
+/// `localize_errors` middleware re-renders it in the caller's language. The library has
+/// already logged errors where they arose.
+#[cfg(feature = "server")]
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::InvalidRequest(_)
+            | Error::ProofValidation { .. }
+            | Error::Preverify { .. }
+            | Error::Json(_) => StatusCode::BAD_REQUEST,
+            Error::DeadlineExceeded(_) => StatusCode::GATEWAY_TIMEOUT,
+            Error::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::ProverDisabled(_) | Error::NotConfigured(_) => StatusCode::NOT_IMPLEMENTED,
+            Error::InvalidConfig(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Error::RemoteProver(_) => StatusCode::BAD_GATEWAY,
+            Error::LimitExceeded { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::Conflict(_) => StatusCode::CONFLICT,
+            Error::Overloaded(_) | Error::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Error::Hex(_)
+            | Error::Io(_)
+            | Error::Risc0(_)
+            | Error::ProverFailure(_)
+            | Error::InvalidOutput(_)
+            | Error::Storage(_)
+            | Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        self.localized_response(Locale::En)
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
         Error::Io(err)
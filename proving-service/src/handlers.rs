@@ -1,342 +1,2305 @@
-use actix_web::{HttpResponse, Result as ActixResult, web};
-use base64::{Engine as _, engine::general_purpose};
-use methods::{SBOM_VALIDATOR_ELF, SBOM_VALIDATOR_ID};
-use risc0_zkvm::{ExecutorEnv, default_prover, serde::to_vec};
+use actix_files::NamedFile;
+use actix_web::http::header::{self, ContentType};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{
+    HttpMessage, HttpRequest, HttpResponse, Result as ActixResult, guard, middleware, web,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
 
+use crate::auth;
+use crate::autoscale::{self, AutoscalePolicy, AutoscaleSignal};
 use crate::config::Config;
-use crate::models::{MerklePublicInputs, MerklePublicOutputs, ProveCompactMerkleRequest};
-use crate::utils::{DEFAULTS, bitmap_bit, count_bitmap_ones, hex_to_bytes32};
+use crate::cors;
+use crate::digest::canonical_request_digest;
+use crate::error::{Error, Result};
+use crate::models::{
+    API_SCHEMA_VERSION, GenerateProofsRequest, ImportProofsRequest, ProofArtifact,
+    ProveCompactMerkleRequest, TemplateDefinition, TemplateRun, WhatIfRequest, api_schema,
+};
+use crate::proof_gen;
+use crate::rate_limit::{self, RateLimiter};
+use crate::redact::{self, RecordKeys, RedactionKey, SealedKeys};
+use crate::reload::ReloadableConfig;
+use crate::report::ComplianceReport;
+use crate::service::ProverService;
+use crate::spot_check::SpotCheck;
+use crate::storage::{RecordFilter, RecordFormat, verify_payload};
+use crate::supervisor::TaskSupervisor;
+use crate::templates::TemplateStore;
+use crate::utils::{KeyType, bytes_to_hex, hex_to_bytes32};
+use crate::validation::validate_proof_count;
+use crate::whatif;
+use sbom_common::envelope;
+use sbom_common::json_limits::{JsonLimitError, check_json_limits};
+use sbom_common::messages::Locale;
+use sbom_common::smt::SparseMerkleTree;
 
-pub async fn health() -> ActixResult<HttpResponse> {
-    Ok(HttpResponse::Ok().json(serde_json::json!({"status": "healthy"})))
+/// Everything the application shares between workers
+#[derive(Clone)]
+pub struct AppState {
+    pub config: web::Data<ReloadableConfig>,
+    pub service: web::Data<ProverService>,
+    pub jobs: web::Data<ProveJobs>,
+    pub spot_check: web::Data<SpotCheck>,
+    /// Background tasks, stopped together once the server exits
+    pub tasks: web::Data<TaskSupervisor>,
+    pub templates: web::Data<TemplateStore>,
+    /// Prove request quotas by client
+    pub rate_limiter: web::Data<RateLimiter>,
 }
 
-pub async fn prove_merkle_compact(
-    req: web::Json<ProveCompactMerkleRequest>,
-    config: web::Data<Config>,
-) -> ActixResult<HttpResponse> {
-    tracing::info!(
-        "Received compact merkle prove request with depth={}, root={}, proof_count={}",
-        req.depth,
-        req.root,
-        req.merkle_proofs.len()
+impl AppState {
+    pub fn new(config: Config, service: ProverService) -> Self {
+        let service = web::Data::new(service);
+        let spot_check = SpotCheck::new(None, config.spot_check_concurrency);
+        let templates = TemplateStore::new(config.proofs_dir.join("templates"));
+        Self {
+            config: web::Data::new(ReloadableConfig::new(config, service.clone())),
+            service,
+            jobs: web::Data::new(ProveJobs::default()),
+            spot_check: web::Data::new(spot_check),
+            tasks: web::Data::new(TaskSupervisor::new()),
+            templates: web::Data::new(templates),
+            rate_limiter: web::Data::new(RateLimiter::default()),
+        }
+    }
+
+    /// Serve spot checks against `tree`, loaded from `SMT_SNAPSHOT`
+    pub fn with_snapshot(mut self, tree: SparseMerkleTree) -> Self {
+        let concurrency = self.config.load().spot_check_concurrency;
+        self.spot_check = web::Data::new(SpotCheck::new(Some(tree), concurrency));
+        self
+    }
+}
+
+/// Progress of a job submitted to `POST /prove-jobs`
+#[derive(Serialize, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ProveJob {
+    Pending,
+    Running,
+    /// `proof` is the body `/prove-merkle-compact` would have answered with
+    Completed {
+        proof: serde_json::Value,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// How long a finished prove job can still be polled
+const FINISHED_JOB_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Most finished prove jobs kept at once; the oldest are dropped first
+const MAX_FINISHED_JOBS: usize = 10_000;
+
+/// Asynchronous proving jobs by id. Kept in memory only: jobs are lost on restart, while
+/// the proofs they completed stay in the proof store. Pending and running jobs are kept
+/// until they finish; finished ones for `FINISHED_JOB_TTL`, and only the newest
+/// `MAX_FINISHED_JOBS` of them.
+pub struct ProveJobs {
+    jobs: Mutex<HashMap<String, (ProveJob, Option<Instant>)>>,
+    ttl: Duration,
+    max_finished: usize,
+}
+
+impl Default for ProveJobs {
+    fn default() -> Self {
+        Self::new(FINISHED_JOB_TTL, MAX_FINISHED_JOBS)
+    }
+}
+
+impl ProveJobs {
+    /// Jobs that stay pollable for `ttl` after they finish, at most `max_finished` of them
+    pub fn new(ttl: Duration, max_finished: usize) -> Self {
+        Self {
+            jobs: Mutex::default(),
+            ttl,
+            max_finished,
+        }
+    }
+
+    /// The job, unless it was never submitted or finished too long ago
+    pub fn get(&self, job_id: &str) -> Option<ProveJob> {
+        let mut jobs = self.lock();
+        self.evict(&mut jobs);
+        jobs.get(job_id).map(|(job, _)| job.clone())
+    }
+
+    fn set(&self, job_id: &str, job: ProveJob) {
+        let finished =
+            matches!(job, ProveJob::Completed { .. } | ProveJob::Failed { .. }).then(Instant::now);
+        let mut jobs = self.lock();
+        jobs.insert(job_id.to_string(), (job, finished));
+        self.evict(&mut jobs);
+    }
+
+    /// Drop finished jobs past their TTL, then the oldest beyond the cap
+    fn evict(&self, jobs: &mut HashMap<String, (ProveJob, Option<Instant>)>) {
+        jobs.retain(|_, (_, finished)| finished.is_none_or(|at| at.elapsed() < self.ttl));
+        let mut finished: Vec<(Instant, String)> = jobs
+            .iter()
+            .filter_map(|(id, (_, finished))| finished.map(|at| (at, id.clone())))
+            .collect();
+        if finished.len() > self.max_finished {
+            finished.sort_unstable();
+            for (_, id) in &finished[..finished.len() - self.max_finished] {
+                jobs.remove(id);
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, (ProveJob, Option<Instant>)>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Register the complete application: shared state, middleware and every route. `main`
+/// and tests build the same app from this, so tests exercise the real middleware stack.
+/// With `ADMIN_BIND_ADDR` set the admin endpoints are left out; see [`configure_admin_app`].
+pub fn configure_app(cfg: &mut web::ServiceConfig, state: AppState) {
+    let config = state.config.load();
+    let read_only = config.read_only;
+    let admin_routes = config.admin_bind_addr.is_none();
+    // Checked at startup; the middleware is not Send, so every worker builds its own
+    let cors = cors::policy(&config).unwrap_or_else(|err_msg| {
+        tracing::error!("{}", err_msg);
+        None
+    });
+    share_state(cfg, state).service(
+        web::scope("")
+            .wrap(middleware::Condition::new(
+                cors.is_some(),
+                cors.unwrap_or_default(),
+            ))
+            .wrap(middleware::from_fn(localize_errors))
+            .wrap(middleware::Logger::default())
+            .wrap(middleware::from_fn(record_metrics))
+            .configure(|cfg| {
+                configure_routes(cfg, read_only);
+                if admin_routes {
+                    configure_admin_routes(cfg);
+                }
+            }),
     );
+}
+
+/// Register the application of the `ADMIN_BIND_ADDR` listener: the admin endpoints only,
+/// every one of them behind `ADMIN_TOKEN`
+pub fn configure_admin_app(cfg: &mut web::ServiceConfig, state: AppState) {
+    share_state(cfg, state).service(
+        web::scope("")
+            .wrap(middleware::from_fn(admin_only))
+            .wrap(middleware::from_fn(localize_errors))
+            .wrap(middleware::Logger::default())
+            .wrap(middleware::from_fn(record_metrics))
+            .configure(configure_admin_routes),
+    );
+}
+
+fn share_state(cfg: &mut web::ServiceConfig, state: AppState) -> &mut web::ServiceConfig {
+    cfg.app_data(state.config)
+        .app_data(state.service)
+        .app_data(state.jobs)
+        .app_data(state.spot_check)
+        .app_data(state.tasks)
+        .app_data(state.templates)
+        .app_data(state.rate_limiter)
+}
+
+/// Register the HTTP API. In read-only mode only retrieval, host-only validation and the
+/// verifier passthrough are served; the proving endpoints, template runs and the import
+/// answer 501. Otherwise they need an API key once `API_KEYS` lists any, and are rate
+/// limited by `RATE_LIMIT_PER_MINUTE`.
+pub fn configure_routes(cfg: &mut web::ServiceConfig, read_only: bool) {
+    cfg.route("/health", web::get().to(health))
+        .route("/info", web::get().to(info))
+        .route("/schema", web::get().to(schema))
+        .route("/metrics", web::get().to(metrics))
+        .route("/autoscale", web::get().to(autoscale_signal))
+        .route("/validate", web::post().to(validate_merkle_compact))
+        .route("/generate-proofs", web::post().to(generate_proofs));
+
+    // Stored proofs and job results name the keys they were made for, so reading them
+    // needs an API key just like proving does. The method guard lets other methods on
+    // the same paths, such as POST /proofs/import, fall through to their own resource.
+    let reading = |path| {
+        web::resource(path)
+            .guard(guard::Get())
+            .wrap(middleware::from_fn(auth::require_api_key))
+    };
+    cfg.service(reading("/proofs").route(web::get().to(list_proofs)))
+        .service(reading("/proofs/{id}").route(web::get().to(get_proof)))
+        .service(reading("/proofs/{proof_id}/raw").route(web::get().to(download_raw_proof)))
+        .service(reading("/proofs/{proof_id}/report").route(web::get().to(proof_report)))
+        .service(reading("/prove-jobs/{job_id}").route(web::get().to(prove_job_status)));
+
+    if read_only {
+        cfg.route("/prove-merkle-compact", web::post().to(prover_disabled))
+            .route("/prove-jobs", web::post().to(prover_disabled))
+            .route("/prove-purl", web::post().to(prover_disabled))
+            .route("/proofs/import", web::post().to(prover_disabled))
+            .route("/templates/{name}/run", web::post().to(prover_disabled))
+            .route("/verify", web::post().to(verify_passthrough))
+            .route("/verify/raw", web::post().to(verify_passthrough));
+    } else {
+        // Everything that proves or writes into the proof store sits behind API_KEYS and
+        // the rate limit
+        let proving = |path| {
+            web::resource(path)
+                .wrap(middleware::from_fn(rate_limit::limit_prove_requests))
+                .wrap(middleware::from_fn(auth::require_api_key))
+        };
+        cfg.service(proving("/prove-merkle-compact").route(web::post().to(prove_merkle_compact)))
+            .service(proving("/prove-jobs").route(web::post().to(submit_prove_job)))
+            .service(proving("/prove-purl").route(web::post().to(prove_purl)))
+            .service(proving("/proofs/import").route(web::post().to(import_proofs)))
+            .service(proving("/templates/{name}/run").route(web::post().to(run_template)));
+    }
+}
+
+/// Register the admin endpoints, served in read-only mode too. They share the public
+/// listener unless `ADMIN_BIND_ADDR` moves them to their own; either way each needs
+/// `ADMIN_TOKEN`. Templates are stored and read here; running one is a public route.
+pub fn configure_admin_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/reload", web::post().to(reload_config))
+        .route("/whatif", web::post().to(what_if))
+        .route("/templates", web::post().to(create_template))
+        .route("/templates/{name}", web::get().to(get_template));
+}
+
+#[derive(serde::Deserialize)]
+pub struct HealthQuery {
+    /// Also run the prover's self-check; its result is reused for `HEALTH_CHECK_TTL_SECS`
+    #[serde(default)]
+    deep: bool,
+}
+
+/// Liveness, or with `?deep=true` whether the prover can still prove: 503 when its
+/// self-check fails. A read-only instance has no prover and reports `prover_ok: null`.
+pub async fn health(
+    query: web::Query<HealthQuery>,
+    service: web::Data<ProverService>,
+) -> Result<HttpResponse> {
+    if !query.deep {
+        return Ok(HttpResponse::Ok().json(serde_json::json!({"status": "healthy"})));
+    }
+
+    let Some(check) = service.check_prover().await else {
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "status": "healthy",
+            "prover_ok": null,
+        })));
+    };
+    let prover_ok = check.error.is_none();
+    let body = serde_json::json!({
+        "status": if prover_ok { "healthy" } else { "unhealthy" },
+        "prover_ok": prover_ok,
+        "image_id": check.image_id.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+        "error": check.error,
+        "checked_at": check.checked_at,
+    });
+    Ok(if prover_ok {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    })
+}
 
-    if req.depth != 256 {
+pub async fn info(
+    config: web::Data<ReloadableConfig>,
+    service: web::Data<ProverService>,
+) -> Result<HttpResponse> {
+    let config = config.load();
+    let image_id = service
+        .image_id()
+        .map(|id| id.iter().map(|x| x.to_string()).collect::<Vec<_>>());
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "service": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "read_only": config.read_only,
+        "image_id": image_id,
+        "verifier_url": config.verifier_url,
+        "api_schema_version": API_SCHEMA_VERSION,
+    })))
+}
+
+/// JSON Schema of the request and response models, for validating payloads at runtime
+pub async fn schema() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(api_schema().json_schema()))
+}
+
+/// Middleware answering errors with their message in the language `Accept-Language`
+/// prefers; see `sbom_common::messages`
+pub async fn localize_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> ActixResult<ServiceResponse<EitherBody<impl MessageBody>>> {
+    let locale = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(Locale::En, Locale::negotiate);
+    let res = next.call(req).await?;
+    let localized = res
+        .response()
+        .error()
+        .and_then(|e| e.as_error::<Error>())
+        .map(|e| e.localized_response(locale));
+    Ok(match localized {
+        Some(response) => res.into_response(response).map_into_right_body(),
+        None => res.map_into_left_body(),
+    })
+}
+
+/// Middleware turning away requests without `ADMIN_TOKEN`; guards the admin listener
+pub async fn admin_only(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> ActixResult<ServiceResponse<EitherBody<impl MessageBody>>> {
+    let admitted = match req.app_data::<web::Data<ReloadableConfig>>() {
+        Some(config) => require_admin(req.request(), &config.load()),
+        None => Err(Error::Internal(
+            "Admin listener started without its configuration".to_string(),
+        )),
+    };
+    match admitted {
+        Ok(()) => Ok(next.call(req).await?.map_into_left_body()),
+        Err(e) => Ok(req.error_response(e).map_into_right_body()),
+    }
+}
+
+/// Middleware recording the outcome and duration of every request under its route pattern
+pub async fn record_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> ActixResult<ServiceResponse<impl MessageBody>> {
+    let started = Instant::now();
+    let endpoint = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let response = next.call(req).await;
+    let status = match &response {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    sbom_common::record_request!(&endpoint, status.as_u16(), started);
+    response
+}
+
+/// Metrics in the Prometheus text exposition format. The autoscaling gauges depend on how
+/// long running requests have been proving, so they are refreshed on every scrape.
+pub async fn metrics(
+    config: web::Data<ReloadableConfig>,
+    service: web::Data<ProverService>,
+) -> Result<HttpResponse> {
+    autoscale::publish(&current_autoscale_signal(&config, &service));
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(sbom_common::observability::metrics().render()))
+}
+
+/// Backlog weighted by estimated proving time and the replica count that drains it in
+/// `AUTOSCALE_TARGET_DRAIN_SECS`, for a custom-metric autoscaler
+pub async fn autoscale_signal(
+    config: web::Data<ReloadableConfig>,
+    service: web::Data<ProverService>,
+) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(current_autoscale_signal(&config, &service)))
+}
+
+fn current_autoscale_signal(config: &ReloadableConfig, service: &ProverService) -> AutoscaleSignal {
+    let config = config.load();
+    let policy = AutoscalePolicy {
+        prover_capacity: config.prover_capacity,
+        target_drain: config.autoscale_target_drain,
+    };
+    autoscale::signal(&service.backlog(), &policy)
+}
+
+/// Client deadline: absolute Unix epoch milliseconds, or seconds relative to arrival
+const DEADLINE_HEADER: &str = "X-Request-Deadline";
+/// Header values at or above this are epoch milliseconds (2001-09-09); below it, relative seconds
+const EPOCH_MILLIS_THRESHOLD: u64 = 1_000_000_000_000;
+/// Media type of the binary encoding `/prove-merkle-compact` accepts and answers in
+const CBOR: &str = "application/cbor";
+
+/// Prove a request sent as JSON or, with `Content-Type: application/cbor`, as CBOR; the
+/// proof comes back as CBOR when `Accept` asks for it. Errors are always JSON.
+pub async fn prove_merkle_compact(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    config: web::Data<ReloadableConfig>,
+    service: web::Data<ProverService>,
+) -> Result<HttpResponse> {
+    let deadline = parse_deadline(&http_req)?;
+    let mut req: ProveCompactMerkleRequest =
+        json_or_cbor_body(&http_req, payload, &config.load()).await?;
+    req.requester =
+        authenticate_requester(&http_req, &canonical_request_digest(&req), &config.load())?;
+    req.redaction = config.load().redaction_for(req.requester.as_deref());
+    let max_response_bytes = req.max_response_bytes;
+
+    let artifact = service.prove_compact_by(req, deadline).await?;
+
+    let response = cap_response_size(&config.load(), &service, max_response_bytes, &artifact)?;
+
+    tracing::info!("Request completed successfully. Returning proof response");
+    negotiated_response(&http_req, &response)
+}
+
+/// How long a prove job has to stop once shutdown cancels it. It stops at its next await,
+/// so this only bounds a runtime that is slow to poll it.
+const PROVE_JOB_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Asynchronous form of `prove_merkle_compact`: answers 202 with a job id right away and
+/// proves in a background task. Poll `GET /prove-jobs/{id}` for the outcome; the proof is
+/// persisted to the proof store as for a synchronous request.
+pub async fn submit_prove_job(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    config: web::Data<ReloadableConfig>,
+    service: web::Data<ProverService>,
+    jobs: web::Data<ProveJobs>,
+    tasks: web::Data<TaskSupervisor>,
+) -> Result<HttpResponse> {
+    let deadline = parse_deadline(&http_req)?;
+    let mut req: ProveCompactMerkleRequest = json_body(&http_req, payload, &config.load()).await?;
+    req.requester =
+        authenticate_requester(&http_req, &canonical_request_digest(&req), &config.load())?;
+    req.redaction = config.load().redaction_for(req.requester.as_deref());
+    validate_proof_count(&req, service.config().max_proofs_per_request)?;
+    let job_id = Uuid::now_v7().to_string();
+    jobs.set(&job_id, ProveJob::Pending);
+    tracing::info!("Accepted prove job {}", job_id);
+
+    let id = job_id.clone();
+    tasks.spawn(format!("prove-job {job_id}"), PROVE_JOB_STOP_TIMEOUT, |task| async move {
+        jobs.set(&id, ProveJob::Running);
+        let max_response_bytes = req.max_response_bytes;
+        // Proving writes the proof store only after its last await, so a job cancelled
+        // at any await leaves nothing half-written; it is reported as failed
+        let proved = tokio::select! {
+            proved = service.prove_compact_by(req, deadline) => proved,
+            _ = task.cancelled() => {
+                let error = "The service shut down before the job completed; submit it again".to_string();
+                tracing::warn!("Prove job {} cancelled by shutdown", id);
+                jobs.set(&id, ProveJob::Failed { error });
+                return;
+            }
+        };
+        let outcome = match proved {
+            Ok(artifact) => {
+                cap_response_size(&config.load(), &service, max_response_bytes, &artifact)
+                    .map_err(|e| e.to_string())
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        match outcome {
+            Ok(proof) => {
+                tracing::info!("Prove job {} completed", id);
+                jobs.set(&id, ProveJob::Completed { proof });
+            }
+            Err(error) => {
+                tracing::error!("Prove job {} failed: {}", id, error);
+                jobs.set(&id, ProveJob::Failed { error });
+            }
+        }
+    });
+
+    Ok(HttpResponse::Accepted()
+        .insert_header((header::LOCATION, format!("/prove-jobs/{job_id}")))
+        .json(serde_json::json!({
+            "job_id": job_id,
+            "status": "pending",
+        })))
+}
+
+/// Status of a job submitted to `POST /prove-jobs`, with the proof once it completed or
+/// the error once it failed. A finished job can be polled for an hour, and only the
+/// newest 10,000 finished jobs are kept; after that, and after a restart, its id answers
+/// 404 and the proof is found in `GET /proofs`.
+pub async fn prove_job_status(
+    job_id: web::Path<String>,
+    jobs: web::Data<ProveJobs>,
+) -> Result<HttpResponse> {
+    let job_id = job_id.into_inner();
+    let job = jobs.get(&job_id).ok_or_else(|| {
         let err_msg = format!(
-            "Invalid depth: expected 256, got {}. Depth must be exactly 256 for this merkle tree configuration",
-            req.depth
+            "No prove job with id '{}'; finished jobs are kept for {} minutes",
+            job_id,
+            FINISHED_JOB_TTL.as_secs() / 60
         );
         tracing::error!("{}", err_msg);
-        return Err(actix_web::error::ErrorBadRequest(err_msg));
-    }
+        Error::NotFound(err_msg)
+    })?;
 
-    if req.merkle_proofs.is_empty() {
-        let err_msg = "Request validation failed: at least one merkle proof is required. Cannot generate proof without any proofs to verify";
+    let mut response = serde_json::to_value(job).map_err(|e| {
+        let err_msg = format!("Failed to serialize prove job: {e}");
         tracing::error!("{}", err_msg);
-        return Err(actix_web::error::ErrorBadRequest(err_msg));
-    }
+        Error::Internal(err_msg)
+    })?;
+    response["job_id"] = job_id.into();
+    Ok(HttpResponse::Ok().json(response))
+}
 
-    let root_hash = hex_to_bytes32(&req.root)
+/// Build the SMT of a banned list on the host and return non-membership proofs of the
+/// queried purls, ready for `POST /prove-merkle-compact`. Banned purls are listed
+/// separately; see [`proof_gen`].
+pub async fn generate_proofs(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    config: web::Data<ReloadableConfig>,
+) -> Result<HttpResponse> {
+    let config = config.load();
+    let req: GenerateProofsRequest = json_body(&http_req, payload, &config).await?;
+
+    // Hashes every banned purl into the tree; keep it off the async workers
+    let max_proofs = config.max_proofs_per_request;
+    let response = web::block(move || proof_gen::generate(&req, max_proofs))
+        .await
         .map_err(|e| {
-            let err_msg = format!("Invalid root hash format: '{}'. Error details: {}. Root hash must be a valid 64-character hex string (optionally prefixed with '0x')", req.root, e);
+            let err_msg = format!("Proof generation failed to run: {e}");
             tracing::error!("{}", err_msg);
-            actix_web::error::ErrorBadRequest(err_msg)
-        })?;
+            Error::Internal(err_msg)
+        })??;
+    Ok(HttpResponse::Ok().json(response))
+}
 
-    tracing::info!(
-        "Validating {} compact merkle proof(s)",
-        req.merkle_proofs.len()
-    );
-    for (idx, proof) in req.merkle_proofs.iter().enumerate() {
-        validate_compact_proof(proof).map_err(|e| {
-            tracing::error!(
-                "Proof validation failed at index {} (purl: {}): {}",
-                idx,
-                proof.purl,
-                e
-            );
-            e
-        })?;
-    }
-    tracing::info!(
-        "All {} proof(s) validated successfully",
-        req.merkle_proofs.len()
-    );
+#[derive(serde::Deserialize)]
+pub struct ProvePurlRequest {
+    pub purl: String,
+    /// Hex root, or a name from ROOT_ALIASES
+    pub root_or_alias: String,
+}
 
-    let public_inputs = MerklePublicInputs { root_hash };
+/// Spot check: prove one purl against the loaded SMT snapshot without the caller
+/// assembling a proof; see [`SpotCheck`]. Answers with the outcome and the compact proof
+/// that was used; the proof is persisted like any other. A signed spot check is bound to
+/// its requester; the signature covers the SHA-256 of the body.
+pub async fn prove_purl(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    config: web::Data<ReloadableConfig>,
+    service: web::Data<ProverService>,
+    spot_check: web::Data<SpotCheck>,
+) -> Result<HttpResponse> {
+    let deadline = parse_deadline(&http_req)?;
+    let body = json_body_bytes(&http_req, payload, &config.load()).await?;
+    let requester =
+        authenticate_requester(&http_req, &Sha256::digest(&body).into(), &config.load())?;
+    let req: ProvePurlRequest = parse_json_body(&body)?;
 
-    let proofs_json = serde_json::to_string(&req.merkle_proofs)
-        .map_err(|e| {
-            let err_msg = format!("Failed to serialize merkle proofs to JSON: {}. This may indicate invalid proof structure or serialization issue", e);
-            tracing::error!("{}", err_msg);
-            actix_web::error::ErrorBadRequest(err_msg)
-        })?;
+    let (root, root_alias) = match hex_to_bytes32(&req.root_or_alias) {
+        Ok(root) => (root, None),
+        Err(_) => {
+            let root = config
+                .load()
+                .root_aliases
+                .get(&req.root_or_alias)
+                .and_then(|root| hex_to_bytes32(root).ok())
+                .ok_or_else(|| {
+                    let err_msg = format!(
+                        "'{}' is neither a 64-character hex root nor a configured root alias",
+                        req.root_or_alias
+                    );
+                    tracing::error!("{}", err_msg);
+                    Error::InvalidRequest(err_msg)
+                })?;
+            (root, Some(req.root_or_alias))
+        }
+    };
+    let mut request = spot_check.request_for(&req.purl, root, root_alias)?;
+    request.redaction = config.load().redaction_for(requester.as_deref());
+    request.requester = requester;
+    let key_display = request.key_display();
+    let merkle_proof = request.merkle_proofs[0].clone();
 
-    tracing::info!(
-        "Preparing executor environment: processing {} compact non-membership proofs for root: {}",
-        req.merkle_proofs.len(),
-        req.root
-    );
+    let Some(_permit) = spot_check.try_acquire() else {
+        let err_msg = format!(
+            "Too many spot checks in progress (limit {}); retry shortly",
+            spot_check.concurrency()
+        );
+        tracing::warn!("{}", err_msg);
+        return Err(Error::Overloaded(err_msg));
+    };
 
-    let env = ExecutorEnv::builder()
-        .write(&proofs_json)
-        .map_err(|e| {
-            let err_msg = format!("Failed to write proofs JSON to executor environment: {}. Proofs JSON length: {} bytes", e, proofs_json.len());
-            tracing::error!("{}", err_msg);
-            actix_web::error::ErrorInternalServerError(err_msg)
-        })?
-        .write(&public_inputs)
-        .map_err(|e| {
-            let err_msg = format!("Failed to write public inputs to executor environment: {}. Root hash: {}", e, hex::encode(root_hash));
-            tracing::error!("{}", err_msg);
-            actix_web::error::ErrorInternalServerError(err_msg)
-        })?
-        .build()
-        .map_err(|e| {
-            let err_msg = format!("Failed to build executor environment: {}. This may indicate memory or configuration issues", e);
-            tracing::error!("{}", err_msg);
-            actix_web::error::ErrorInternalServerError(err_msg)
-        })?;
+    let artifact = service.prove_compact_by(request, deadline).await?;
 
     tracing::info!(
-        "Executor environment built successfully. Starting proof generation for compact merkle tree root: {}",
-        req.root
+        "Spot check of '{}' completed: compliant={}",
+        key_display.key(KeyType::Purl, &req.purl),
+        artifact.compliant
     );
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "proof_id": artifact.proof_id,
+        "request_id": artifact.request_id,
+        "purl": req.purl,
+        "compliant": artifact.compliant,
+        "root_hash": artifact.root_hash,
+        "root_alias": artifact.root_alias,
+        "image_id": artifact.image_id,
+        "timestamp": artifact.timestamp,
+        "merkle_proof": merkle_proof,
+        "proof": artifact.proof,
+        "proof_url": artifact.proof_url,
+    })))
+}
 
-    let prover = default_prover();
-    let prove_info = prover
-        .prove(env, SBOM_VALIDATOR_ELF)
-        .map_err(|e| {
-            let err_msg = format!("Proof generation failed during RISC0 execution: {}. This may indicate an issue with the proof computation or executor environment", e);
-            tracing::error!("{}", err_msg);
-            actix_web::error::ErrorInternalServerError(err_msg)
-        })?;
+/// Keep a small request from producing a huge response: when the body would exceed the
+/// tighter of MAX_RESPONSE_BYTES and the request's `max_response_bytes`, the inline proof
+/// is replaced by a download link and `oversize` is set. The persisted record keeps the
+/// inline proof either way. The response is enveloped unless LEGACY_PROOFS=serve.
+fn cap_response_size(
+    config: &Config,
+    service: &ProverService,
+    max_response_bytes: Option<usize>,
+    artifact: &ProofArtifact,
+) -> Result<serde_json::Value> {
+    let format = RecordFormat::from(config);
+    let enveloped = config.legacy_proofs.envelope_responses();
+    let respond = |response| {
+        if enveloped {
+            proof_response(format.envelope(response))
+        } else {
+            Ok(response)
+        }
+    };
+    let mut response = proof_response(artifact)?;
+    response["oversize"] = false.into();
 
-    let receipt = prove_info.receipt;
+    let limit = [config.max_response_bytes, max_response_bytes]
+        .into_iter()
+        .flatten()
+        .min();
+    // No limit applies, or the receipt already exceeded the inline limit and is linked
+    let Some(limit) = limit.filter(|_| artifact.proof.is_some()) else {
+        return respond(response);
+    };
 
-    let output: MerklePublicOutputs = receipt
-        .journal
-        .decode()
+    let size = serde_json::to_vec(&respond(response.clone())?)
+        .map(|body| body.len())
+        .unwrap_or(0);
+    if size <= limit {
+        return respond(response);
+    }
+
+    service
+        .store()
+        .store_receipt(&artifact.proof_id, &artifact.receipt_bytes)
         .map_err(|e| {
-            let err_msg = format!("Failed to decode receipt journal output: {}. Journal size: {} bytes. This may indicate a serialization mismatch or corrupted receipt", e, receipt.journal.bytes.len());
+            let err_msg = format!(
+                "Response of {size} bytes exceeds the limit of {limit} bytes and the receipt could not be stored: {e}"
+            );
             tracing::error!("{}", err_msg);
-            actix_web::error::ErrorInternalServerError(err_msg)
+            Error::Internal(err_msg)
         })?;
-
     tracing::info!(
-        "Compact proof generated successfully. Compliant: {}, Root hash: {}, Banned list hash: {}",
-        output.compliant,
-        hex::encode(output.root_hash),
-        hex::encode(output.banned_list_hash)
+        "Response of {} bytes exceeds the limit of {} bytes; returning a download link for proof {}",
+        size,
+        limit,
+        artifact.proof_id
     );
 
-    receipt
-        .verify(SBOM_VALIDATOR_ID)
-        .map_err(|e| {
-            let err_msg = format!("Receipt verification failed: {}. This indicates the generated proof is invalid or corrupted. Image ID: {:?}", e, SBOM_VALIDATOR_ID);
-            tracing::error!("{}", err_msg);
-            actix_web::error::ErrorInternalServerError(err_msg)
-        })?;
+    response["proof"] = serde_json::Value::Null;
+    response["proof_url"] = format!("/proofs/{}/raw", artifact.proof_id).into();
+    response["oversize"] = true.into();
+    respond(response)
+}
 
-    tracing::info!("Receipt verification successful");
+fn proof_response(response: impl Serialize) -> Result<serde_json::Value> {
+    serde_json::to_value(response).map_err(|e| {
+        let err_msg = format!("Failed to serialize proof response: {e}");
+        tracing::error!("{}", err_msg);
+        Error::Internal(err_msg)
+    })
+}
 
-    let receipt_bytes: Vec<u8> = to_vec(&receipt)
-        .map_err(|e| {
-            let err_msg = format!("Failed to serialize receipt to bytes: {}. This may indicate a serialization format issue", e);
-            tracing::error!("{}", err_msg);
-            actix_web::error::ErrorInternalServerError(err_msg)
-        })?
-        .iter()
-        .flat_map(|&x| x.to_le_bytes())
-        .collect();
+#[derive(serde::Deserialize)]
+pub struct ListProofsQuery {
+    /// Return at most this many records
+    #[serde(default)]
+    limit: Option<usize>,
+    /// Only records with a timestamp at or after this Unix time
+    #[serde(default)]
+    since: Option<u64>,
+}
+
+/// Import proofs made elsewhere; see [`ProverService::import_proofs`]. Answers 200 with a
+/// result per document, invalid ones included. A signed import is recorded with its key
+/// id as `imported_by`; the signature covers the SHA-256 of the body. Like proving, needs
+/// an API key once `API_KEYS` lists any, and is rate limited.
+pub async fn import_proofs(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    config: web::Data<ReloadableConfig>,
+    service: web::Data<ProverService>,
+) -> Result<HttpResponse> {
+    let config = config.load();
+    let body = json_body_bytes(&http_req, payload, &config).await?;
+    let submitter = authenticate_requester(&http_req, &Sha256::digest(&body).into(), &config)?;
+    let req: ImportProofsRequest = parse_json_body(&body)?;
+    tracing::info!("Importing {} proof document(s)", req.proofs.len());
 
+    let redaction = config.redaction_for(submitter.as_deref());
+    let response = service
+        .import_proofs(req.proofs, submitter, redaction)
+        .await?;
     tracing::info!(
-        "Proof generation completed: receipt_size={} bytes",
-        receipt_bytes.len()
+        "Import finished: {} imported, {} duplicate(s), {} invalid",
+        response.imported,
+        response.duplicates,
+        response.invalid
     );
+    Ok(HttpResponse::Ok().json(response))
+}
 
-    let proof_base64 = general_purpose::STANDARD.encode(&receipt_bytes);
+/// Summaries of the stored proofs, newest first
+pub async fn list_proofs(
+    query: web::Query<ListProofsQuery>,
+    service: web::Data<ProverService>,
+) -> Result<HttpResponse> {
+    let filter = RecordFilter {
+        since: query.since,
+        limit: query.limit,
+        ..RecordFilter::default()
+    };
+    let proofs = service.store().list_records(&filter).map_err(|e| {
+        let err_msg = format!("Failed to list proofs: {e}");
+        tracing::error!("{}", err_msg);
+        Error::Internal(err_msg)
+    })?;
 
-    let proof_data = serde_json::json!({
-        "banned_list_hash": hex::encode(output.banned_list_hash),
-        "compliant": output.compliant,
-        "image_id": SBOM_VALIDATOR_ID.iter().map(|&x| x.to_string()).collect::<Vec<_>>(),
-        "proof": proof_base64,
-        "root_hash": hex::encode(output.root_hash),
-    });
+    Ok(HttpResponse::Ok().json(proofs))
+}
 
-    tracing::info!(
-        "Attempting to save proof to directory: {}",
-        config.proofs_dir.display()
-    );
-    if let Err(e) = std::fs::create_dir_all(&config.proofs_dir) {
+/// Serve a receipt that was too large to inline, as raw bytes streamed from disk.
+pub async fn download_raw_proof(
+    proof_id: web::Path<String>,
+    service: web::Data<ProverService>,
+) -> Result<NamedFile> {
+    let proof_id = parse_proof_id(proof_id.into_inner())?;
+
+    let filepath = service.store().find_receipt(&proof_id)?.ok_or_else(|| {
+        let err_msg = format!("No stored receipt for proof id '{proof_id}'");
+        tracing::error!("{}", err_msg);
+        Error::NotFound(err_msg)
+    })?;
+
+    // Payloads are shared between proofs, so a corrupted one must not be served to any
+    let receipt_sha256 = filepath
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    if !verify_payload(&filepath)? {
         let err_msg = format!(
-            "Failed to create proofs directory '{}': {}. Proof will not be persisted to disk",
-            config.proofs_dir.display(),
-            e
+            "Stored receipt for proof id '{proof_id}' does not match its digest {receipt_sha256}"
         );
-        tracing::warn!("{}", err_msg);
+        tracing::error!("{}", err_msg);
+        return Err(Error::Internal(err_msg));
     }
 
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-    let filename = format!("proof_{}.json", timestamp);
-    let filepath = config.proofs_dir.join(&filename);
+    let file = NamedFile::open_async(&filepath).await.map_err(|e| {
+        let err_msg = format!("No stored receipt for proof id '{proof_id}'");
+        tracing::error!("{} ({}: {})", err_msg, filepath.display(), e);
+        Error::NotFound(err_msg)
+    })?;
+
+    Ok(file.set_content_type(ContentType::octet_stream().0))
+}
 
-    match serde_json::to_string_pretty(&proof_data) {
-        Ok(json) => {
-            if let Err(e) = std::fs::write(&filepath, json) {
+/// The stored record of a proof, verbatim, by proof id or by the Unix timestamp it was
+/// generated at. A receipt that was not inlined is linked by `proof_url`. Timestamps have
+/// second granularity, so one shared by several proofs answers 409 naming their ids. The
+/// ETag is the SHA-256 of the record, and a matching `If-None-Match` answers 304. With
+/// LEGACY_PROOFS=serve the record is served bare, without its envelope.
+pub async fn get_proof(
+    http_req: HttpRequest,
+    id: web::Path<String>,
+    config: web::Data<ReloadableConfig>,
+    service: web::Data<ProverService>,
+) -> Result<HttpResponse> {
+    let id = id.into_inner();
+    let store = service.store();
+
+    let proof_id = match parse_timestamp(&id) {
+        Some(timestamp) => {
+            let mut matches = store.list_records(&RecordFilter::at(timestamp))?;
+            if matches.len() > 1 {
+                // Candidates in the order they were generated
+                matches.sort_by(|a, b| (a.sequence, &a.proof_id).cmp(&(b.sequence, &b.proof_id)));
+                let matches: Vec<String> =
+                    matches.into_iter().map(|proof| proof.proof_id).collect();
                 let err_msg = format!(
-                    "Failed to write proof file to '{}': {}. Proof data will still be returned in response",
-                    filepath.display(),
-                    e
-                );
-                tracing::warn!("{}", err_msg);
-            } else {
-                tracing::info!(
-                    "Proof successfully saved to: {} (size: {} bytes)",
-                    filepath.display(),
-                    std::fs::metadata(&filepath).map(|m| m.len()).unwrap_or(0)
+                    "{} proofs were generated at timestamp {}; fetch one by proof id: {}",
+                    matches.len(),
+                    timestamp,
+                    matches.join(", ")
                 );
+                tracing::error!("{}", err_msg);
+                return Err(Error::Conflict(err_msg));
             }
+            matches.pop().map(|proof| proof.proof_id).ok_or_else(|| {
+                let err_msg = format!("No stored proof record for timestamp {timestamp}");
+                tracing::error!("{}", err_msg);
+                Error::NotFound(err_msg)
+            })?
         }
-        Err(e) => {
-            let err_msg = format!(
-                "Failed to serialize proof data to JSON for file storage: {}. Proof data will still be returned in response",
-                e
-            );
-            tracing::warn!("{}", err_msg);
+        None => parse_proof_id(id)?,
+    };
+
+    let record = store.find_record(&proof_id)?.ok_or_else(|| {
+        let err_msg = format!("No stored proof record for proof id '{proof_id}'");
+        tracing::error!("{}", err_msg);
+        Error::NotFound(err_msg)
+    })?;
+    let record = match http_req.headers().get(REDACTION_KEY_HEADER) {
+        Some(key) => unseal_record(record, key.to_str().unwrap_or_default())?,
+        None => record,
+    };
+    let record = if config.load().legacy_proofs.envelope_responses() {
+        record
+    } else {
+        let opened = envelope::open(serde_json::from_str(&record)?, true)
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        serde_json::to_string_pretty(&opened.data)?
+    };
+
+    let etag = format!("\"{}\"", bytes_to_hex(&Sha256::digest(record.as_bytes())));
+    let cached = http_req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| {
+            tags.split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == etag || tag == "*")
+        });
+    if cached {
+        return Ok(HttpResponse::NotModified()
+            .insert_header((header::ETAG, etag))
+            .finish());
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .insert_header((header::ETAG, etag))
+        .body(record))
+}
+
+/// Hex redaction key of the tenant whose sealed record is fetched
+const REDACTION_KEY_HEADER: &str = "X-Redaction-Key";
+
+/// A stored record with the keys sealed in it restored, which needs the key it was
+/// sealed under. A record without sealed keys is returned as it is.
+fn unseal_record(record: String, hex_key: &str) -> Result<String> {
+    let mut opened = envelope::open(serde_json::from_str(&record)?, true)
+        .map_err(|e| Error::Storage(e.to_string()))?;
+    let value = &mut opened.data;
+    let Some(sealed) = value.get("sealed_purls").filter(|sealed| !sealed.is_null()) else {
+        return Ok(record);
+    };
+    let sealed: SealedKeys = serde_json::from_value(sealed.clone())?;
+    let key = RedactionKey::from_hex(hex_key.trim()).ok_or_else(|| {
+        let err_msg = format!("{REDACTION_KEY_HEADER} must be a 64-character hex string");
+        tracing::error!("{}", err_msg);
+        Error::Unauthorized(err_msg)
+    })?;
+    let keys: RecordKeys = serde_json::from_slice(&redact::open(&sealed, &key)?)?;
+
+    value["purls"] = keys.purls.into();
+    if let Some(reasons) = value["reasons"].as_array_mut() {
+        for (reason, purl) in reasons.iter_mut().zip(keys.reasons) {
+            reason["purl"] = purl.into();
         }
     }
+    value["leaf_indexes"] = serde_json::Value::Null;
+    value["sealed_purls"] = serde_json::Value::Null;
+    Ok(serde_json::to_string_pretty(&opened.into_document())?)
+}
 
-    let response = serde_json::json!({
-        "banned_list_hash": hex::encode(output.banned_list_hash),
-        "compliant": output.compliant,
-        "image_id": SBOM_VALIDATOR_ID.iter().map(|&x| x.to_string()).collect::<Vec<_>>(),
-        "proof": proof_base64,
-        "root_hash": hex::encode(output.root_hash),
-    });
+/// A path segment of at most 20 digits is a Unix timestamp; a proof id is 64 characters,
+/// so the two cannot be confused. Anything else is left to `parse_proof_id`.
+fn parse_timestamp(id: &str) -> Option<u64> {
+    if id.is_empty() || id.len() > 20 || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    id.parse().ok()
+}
 
-    tracing::info!("Request completed successfully. Returning proof response");
+#[derive(serde::Deserialize)]
+pub struct ReportQuery {
+    /// `json` (default) or `html`
+    #[serde(default)]
+    format: Option<String>,
+}
+
+/// Compliance report of a stored proof, as JSON or as an HTML page. Built from the
+/// persisted record only; receipt bytes are never included.
+pub async fn proof_report(
+    proof_id: web::Path<String>,
+    query: web::Query<ReportQuery>,
+    service: web::Data<ProverService>,
+) -> Result<HttpResponse> {
+    let proof_id = parse_proof_id(proof_id.into_inner())?;
+    let html = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("html") => true,
+        Some(other) => {
+            let err_msg = format!("Unknown report format '{other}': expected json or html");
+            tracing::error!("{}", err_msg);
+            return Err(Error::InvalidRequest(err_msg));
+        }
+    };
+
+    let record = service.store().find_record(&proof_id)?.ok_or_else(|| {
+        let err_msg = format!("No stored proof record for proof id '{proof_id}'");
+        tracing::error!("{}", err_msg);
+        Error::NotFound(err_msg)
+    })?;
+    let report = ComplianceReport::from_record(&record).inspect_err(|e| {
+        tracing::error!("{}", e);
+    })?;
+
+    if html {
+        Ok(HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(report.to_html()))
+    } else {
+        Ok(HttpResponse::Ok().json(report))
+    }
+}
+
+/// The id becomes part of a file path, so accept nothing but a lowercase SHA-256 hex digest
+fn parse_proof_id(proof_id: String) -> Result<String> {
+    if proof_id.len() != 64
+        || !proof_id
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    {
+        let err_msg =
+            format!("Invalid proof id '{proof_id}': expected a 64-character lowercase hex string");
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg));
+    }
+    Ok(proof_id)
+}
+
+/// Host-only preflight: runs the same validation as `prove_merkle_compact` plus the
+/// guest's recomputation of every proof against the root, without invoking the zkVM.
+/// The result is not cryptographic evidence; no receipt is produced.
+pub async fn validate_merkle_compact(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    config: web::Data<ReloadableConfig>,
+    service: web::Data<ProverService>,
+) -> Result<HttpResponse> {
+    let mut req: ProveCompactMerkleRequest = json_body(&http_req, payload, &config.load()).await?;
+    req.redaction = config.load().redaction_for(None);
+    let response = service.validate(req)?;
     Ok(HttpResponse::Ok().json(response))
 }
 
-fn validate_compact_proof(proof: &crate::models::CompactMerkleProof) -> actix_web::Result<()> {
-    tracing::debug!("Validating compact proof for purl: {}", proof.purl);
+/// Store a new version of a proof request template; see [`crate::templates`]. Admin only:
+/// needs `ADMIN_TOKEN` as a bearer token.
+pub async fn create_template(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    config: web::Data<ReloadableConfig>,
+    templates: web::Data<TemplateStore>,
+) -> Result<HttpResponse> {
+    let config = config.load();
+    require_admin(&http_req, &config)?;
+    let definition: TemplateDefinition = json_body(&http_req, payload, &config).await?;
+    let template = templates.save(definition, &config)?;
+    Ok(HttpResponse::Created().json(template))
+}
+
+/// The latest version of a template. Admin only: needs `ADMIN_TOKEN` as a bearer token.
+pub async fn get_template(
+    http_req: HttpRequest,
+    name: web::Path<String>,
+    config: web::Data<ReloadableConfig>,
+    templates: web::Data<TemplateStore>,
+) -> Result<HttpResponse> {
+    require_admin(&http_req, &config.load())?;
+    Ok(HttpResponse::Ok().json(templates.latest(&name)?))
+}
+
+/// Prove the latest version of a template, completed with the per-run parts in the body.
+/// Answers like `/prove-merkle-compact`; the proof records the template's name and version.
+/// A signed run is bound to its requester; the signature covers the SHA-256 of the body.
+pub async fn run_template(
+    http_req: HttpRequest,
+    name: web::Path<String>,
+    payload: web::Payload,
+    config: web::Data<ReloadableConfig>,
+    service: web::Data<ProverService>,
+    spot_check: web::Data<SpotCheck>,
+    templates: web::Data<TemplateStore>,
+) -> Result<HttpResponse> {
+    let deadline = parse_deadline(&http_req)?;
+    let config = config.load();
+    let body = json_body_bytes(&http_req, payload, &config).await?;
+    let requester = authenticate_requester(&http_req, &Sha256::digest(&body).into(), &config)?;
+    let run: TemplateRun = parse_json_body(&body)?;
+    let template = templates.latest(&name)?;
+    let max_response_bytes = run.max_response_bytes;
+    let mut req = template.request_for(run, &config.root_aliases, &spot_check)?;
+    req.redaction = config.redaction_for(requester.as_deref());
+    req.requester = requester;
+
+    tracing::info!(
+        "Running template '{}' version {}",
+        template.definition.name,
+        template.version
+    );
+    let artifact = service.prove_compact_by(req, deadline).await?;
+
+    let response = cap_response_size(&config, &service, max_response_bytes, &artifact)?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Stand-in for the proving endpoint on a read-only instance
+pub async fn prover_disabled() -> Result<HttpResponse> {
+    let err_msg = "Proving is disabled: this instance only serves archived proofs";
+    tracing::warn!("{}", err_msg);
+    Err(Error::ProverDisabled(err_msg.to_string()))
+}
+
+/// Send verification requests on to the configured verifier-service. A 307 keeps the
+/// method and body, so clients retry the same request there without this instance
+/// needing an HTTP client.
+pub async fn verify_passthrough(
+    http_req: HttpRequest,
+    config: web::Data<ReloadableConfig>,
+) -> Result<HttpResponse> {
+    let config = config.load();
+    let Some(verifier_url) = config.verifier_url.as_deref() else {
+        let err_msg = "Verification is not available: VERIFIER_URL is not configured";
+        tracing::warn!("{}", err_msg);
+        return Err(Error::NotConfigured(err_msg.to_string()));
+    };
+
+    let location = match http_req.uri().query() {
+        Some(query) => format!("{}{}?{}", verifier_url, http_req.path(), query),
+        None => format!("{}{}", verifier_url, http_req.path()),
+    };
+    Ok(HttpResponse::TemporaryRedirect()
+        .insert_header((header::LOCATION, location))
+        .finish())
+}
+
+/// Re-read the configuration and swap it in without a restart; see [`ReloadableConfig`].
+/// Answers with the changed fields, or 422 with the old configuration still active.
+/// Admin only: needs `ADMIN_TOKEN` as a bearer token.
+pub async fn reload_config(
+    http_req: HttpRequest,
+    config: web::Data<ReloadableConfig>,
+) -> Result<HttpResponse> {
+    require_admin(&http_req, &config.load())?;
+    let changes = config.reload()?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "reloaded": true,
+        "changes": changes,
+    })))
+}
+
+/// Replay stored proofs against a candidate banned list and report how many would no
+/// longer be compliant. Admin only: needs `ADMIN_TOKEN` as a bearer token.
+pub async fn what_if(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    config: web::Data<ReloadableConfig>,
+    service: web::Data<ProverService>,
+) -> Result<HttpResponse> {
+    let config = config.load();
+    require_admin(&http_req, &config)?;
+    let req: WhatIfRequest = json_body(&http_req, payload, &config).await?;
+
+    // A scan reads every record in the store; keep it off the async workers
+    let store = service.store().clone();
+    let report = web::block(move || whatif::evaluate(store.as_ref(), &req))
+        .await
+        .map_err(|e| {
+            let err_msg = format!("What-if replay failed to run: {e}");
+            tracing::error!("{}", err_msg);
+            Error::Internal(err_msg)
+        })??;
+    Ok(HttpResponse::Ok().json(report))
+}
 
-    let bitmap_hex = proof.bitmap.strip_prefix("0x").unwrap_or(&proof.bitmap);
-    if bitmap_hex.len() != 64 {
+/// Admit the request only if it carries `ADMIN_TOKEN` as `Authorization: Bearer <token>`.
+/// Without a configured token admin endpoints are off rather than open.
+fn require_admin(http_req: &HttpRequest, config: &Config) -> Result<()> {
+    let Some(token) = &config.admin_token else {
+        let err_msg = "Admin endpoints are not available: ADMIN_TOKEN is not configured";
+        tracing::error!("{}", err_msg);
+        return Err(Error::NotConfigured(err_msg.to_string()));
+    };
+    let presented = http_req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !presented.is_some_and(|presented| token.matches(presented.trim())) {
         let err_msg = format!(
-            "Invalid bitmap length for purl '{}': expected 64-character hex string, got {} characters (value: '{}')",
-            proof.purl,
-            bitmap_hex.len(),
-            proof.bitmap
+            "{} {} needs the admin token as a bearer token",
+            http_req.method(),
+            http_req.path()
         );
         tracing::error!("{}", err_msg);
-        return Err(actix_web::error::ErrorBadRequest(err_msg));
+        return Err(Error::Unauthorized(err_msg));
     }
+    Ok(())
+}
 
-    let bitmap = hex_to_bytes32(&proof.bitmap)
-        .map_err(|e| {
+/// Names the `API_KEYS` entry a proving request is signed with
+const API_KEY_ID_HEADER: &str = "X-Api-Key-Id";
+/// Hex HMAC-SHA256 of the request's digest under that key
+const REQUEST_SIGNATURE_HEADER: &str = "X-Request-Signature";
+
+/// The key id a request with `digest` is signed with, or `None` for an unsigned request.
+/// For a proving request the digest is `digest::canonical_request_digest` of the request
+/// as sent, before aliases are resolved or encodings normalized; for a spot check, a
+/// template run or an import, the SHA-256 of the body. A request with only one of the
+/// two headers, an unknown key id or a wrong signature is refused rather than proven
+/// unbound.
+fn authenticate_requester(
+    http_req: &HttpRequest,
+    digest: &[u8; 32],
+    config: &Config,
+) -> Result<Option<String>> {
+    let header_value = |name: &str| {
+        http_req
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+    let (key_id, signature) = match (
+        header_value(API_KEY_ID_HEADER),
+        header_value(REQUEST_SIGNATURE_HEADER),
+    ) {
+        (None, None) => return Ok(None),
+        (Some(key_id), Some(signature)) => (key_id, signature),
+        _ => {
             let err_msg = format!(
-                "Invalid bitmap hex format for purl '{}': {}. Bitmap value: '{}'. Bitmap must be a valid 64-character hex string",
-                proof.purl, e, proof.bitmap
+                "A signed request needs both {API_KEY_ID_HEADER} and {REQUEST_SIGNATURE_HEADER}"
             );
             tracing::error!("{}", err_msg);
-            actix_web::error::ErrorBadRequest(err_msg)
+            return Err(Error::Unauthorized(err_msg));
+        }
+    };
+    let signed = config
+        .api_keys
+        .get(key_id)
+        .is_some_and(|key| key.signed(digest, signature));
+    if !signed {
+        // Unknown keys and wrong signatures read the same, so key ids cannot be probed
+        let err_msg = format!("The request signature does not verify under API key '{key_id}'");
+        tracing::error!("{}", err_msg);
+        return Err(Error::Unauthorized(err_msg));
+    }
+    tracing::info!("Request signed with API key '{}'", key_id);
+    Ok(Some(key_id.to_string()))
+}
+
+/// Read a JSON request body of at most `MAX_BODY_BYTES` and parse it once it is within
+/// the configured depth and token limits. A declared `Content-Length` over the limit is
+/// refused before anything is read; an undeclared one once the limit is reached. The
+/// depth and token limits are checked before parsing, so a deeply nested or token-packed
+/// document is refused without serde_json recursing into it: too deep is a malformed
+/// request (400), too many tokens too large a one (413).
+async fn json_body<T: DeserializeOwned>(
+    http_req: &HttpRequest,
+    payload: web::Payload,
+    config: &Config,
+) -> Result<T> {
+    parse_json_body(&json_body_bytes(http_req, payload, config).await?)
+}
+
+fn parse_json_body<T: DeserializeOwned>(body: &[u8]) -> Result<T> {
+    serde_json::from_slice(body).map_err(|e| {
+        tracing::error!("Invalid request JSON: {}", e);
+        Error::Json(e)
+    })
+}
+
+/// The body as [`json_body`] reads and limits it, before it is parsed
+async fn json_body_bytes(
+    http_req: &HttpRequest,
+    payload: web::Payload,
+    config: &Config,
+) -> Result<web::Bytes> {
+    let body = body_bytes(http_req, payload, config).await?;
+    let limits = &config.request_json_limits;
+    check_json_limits(&body, limits).map_err(|e| {
+        let error = match e {
+            JsonLimitError::TooDeep { offset } => Error::InvalidRequest(format!(
+                "Request JSON nests deeper than the limit of {} at byte {}",
+                limits.max_depth, offset
+            )),
+            JsonLimitError::TooManyTokens { .. } => Error::LimitExceeded {
+                setting: "MAX_JSON_TOKENS",
+                unit: "JSON tokens",
+                limit: limits.max_tokens,
+                observed: None,
+            },
+        };
+        tracing::error!("{}", error);
+        error
+    })?;
+    Ok(body)
+}
+
+/// A request body of at most `MAX_BODY_BYTES`, whatever its encoding
+async fn body_bytes(
+    http_req: &HttpRequest,
+    payload: web::Payload,
+    config: &Config,
+) -> Result<web::Bytes> {
+    let declared = http_req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    let too_large = |observed| {
+        let error = Error::LimitExceeded {
+            setting: "MAX_BODY_BYTES",
+            unit: "bytes",
+            limit: config.max_body_bytes,
+            observed,
+        };
+        tracing::error!("{}", error);
+        error
+    };
+    if let Some(len) = declared.filter(|&len| len > config.max_body_bytes) {
+        return Err(too_large(Some(len)));
+    }
+    let body = payload
+        .to_bytes_limited(config.max_body_bytes)
+        .await
+        .map_err(|_| too_large(declared))?
+        .map_err(|e| {
+            let err_msg = format!("Failed to read request body: {e}");
+            tracing::error!("{}", err_msg);
+            Error::InvalidRequest(err_msg)
         })?;
+    Ok(body)
+}
 
-    let expected_sibling_count = count_bitmap_ones(&bitmap);
-    if proof.siblings.len() != expected_sibling_count {
-        let err_msg = format!(
-            "Sibling count mismatch for purl '{}': bitmap indicates {} sibling(s) should be present (bitmap: '{}'), but {} sibling(s) provided",
-            proof.purl,
-            expected_sibling_count,
-            proof.bitmap,
-            proof.siblings.len()
-        );
+/// Parse a request body as [`json_body`] does, or as CBOR when it is sent with
+/// `Content-Type: application/cbor`. In CBOR the hex fields of compact proofs may be raw
+/// byte strings; the body limit applies, the JSON depth and token limits do not.
+async fn json_or_cbor_body<T: DeserializeOwned>(
+    http_req: &HttpRequest,
+    payload: web::Payload,
+    config: &Config,
+) -> Result<T> {
+    if http_req.content_type() != CBOR {
+        return json_body(http_req, payload, config).await;
+    }
+    let body = body_bytes(http_req, payload, config).await?;
+    ciborium::from_reader(body.as_ref()).map_err(|e| {
+        let err_msg = format!("Invalid request CBOR: {e}");
         tracing::error!("{}", err_msg);
-        return Err(actix_web::error::ErrorBadRequest(err_msg));
+        Error::InvalidRequest(err_msg)
+    })
+}
+
+/// Answer 200 with `body` as CBOR when the request's `Accept` names
+/// `application/cbor`, as JSON otherwise
+fn negotiated_response<T: Serialize>(http_req: &HttpRequest, body: &T) -> Result<HttpResponse> {
+    let accepts_cbor = http_req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value.split(',').any(|range| {
+                let media_type = range.split(';').next().unwrap_or_default();
+                media_type.trim().eq_ignore_ascii_case(CBOR)
+            })
+        });
+    if !accepts_cbor {
+        return Ok(HttpResponse::Ok().json(body));
     }
+    let mut encoded = Vec::new();
+    ciborium::into_writer(body, &mut encoded).map_err(|e| {
+        let err_msg = format!("Failed to encode response as CBOR: {e}");
+        tracing::error!("{}", err_msg);
+        Error::Internal(err_msg)
+    })?;
+    Ok(HttpResponse::Ok().content_type(CBOR).body(encoded))
+}
 
-    let leaf_index_hex = proof
-        .leaf_index
-        .strip_prefix("0x")
-        .unwrap_or(&proof.leaf_index);
-    if leaf_index_hex.len() != 64 {
+/// Parse `X-Request-Deadline` into the instant by which the response must be sent.
+fn parse_deadline(http_req: &HttpRequest) -> Result<Option<Instant>> {
+    let Some(value) = http_req.headers().get(DEADLINE_HEADER) else {
+        return Ok(None);
+    };
+
+    let raw = value.to_str().unwrap_or_default().trim();
+    let parsed: u64 = raw.parse().map_err(|_| {
         let err_msg = format!(
-            "Invalid leaf_index length for purl '{}': expected 64-character hex string, got {} characters (value: '{}')",
-            proof.purl,
-            leaf_index_hex.len(),
-            proof.leaf_index
+            "Invalid {DEADLINE_HEADER} header '{raw}': expected epoch milliseconds or relative seconds as an unsigned integer"
         );
         tracing::error!("{}", err_msg);
-        return Err(actix_web::error::ErrorBadRequest(err_msg));
+        Error::InvalidRequest(err_msg)
+    })?;
+
+    let remaining = if parsed >= EPOCH_MILLIS_THRESHOLD {
+        let now_millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        Duration::from_millis(parsed.saturating_sub(now_millis))
+    } else {
+        Duration::from_secs(parsed)
+    };
+
+    if remaining.is_zero() {
+        let err_msg = format!("{DEADLINE_HEADER} '{raw}' has already passed");
+        tracing::error!("{}", err_msg);
+        return Err(Error::DeadlineExceeded(err_msg));
     }
 
-    hex_to_bytes32(&proof.leaf_index)
-        .map_err(|e| {
-            let err_msg = format!(
-                "Invalid leaf_index hex format for purl '{}': {}. Leaf index value: '{}'. Leaf index must be a valid 64-character hex string",
-                proof.purl, e, proof.leaf_index
+    Ok(Some(Instant::now() + remaining))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::Method;
+    use actix_web::{App, test};
+    use base64::{Engine as _, engine::general_purpose};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ProverConfig;
+    use crate::models::{CompactMerkleProof, MerklePublicInputs, MerklePublicOutputs};
+    use crate::prover::{GeneratedProof, ProvingBackend, VerifiedReceipt};
+    use crate::utils::{compute_reasons_hash, hex_to_bytes32};
+
+    /// Configuration from `vars`, storing under a fresh directory named after `test`
+    fn config(test: &str, vars: &[(&str, &str)]) -> Config {
+        let proofs_dir = std::env::temp_dir().join(format!("proving-service-{test}"));
+        let _ = std::fs::remove_dir_all(&proofs_dir);
+        let proofs_dir = proofs_dir.display().to_string();
+        Config::from_vars(|name| match name {
+            "PROOFS_DIR" => Some(proofs_dir.clone()),
+            _ => vars
+                .iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string()),
+        })
+    }
+
+    /// App state with `vars` as configuration and no prover
+    fn state(test: &str, vars: &[(&str, &str)]) -> AppState {
+        let config = config(test, vars);
+        let service = ProverService::without_prover(ProverConfig::from(&config));
+        AppState::new(config, service)
+    }
+
+    fn import(api_key: Option<&str>) -> test::TestRequest {
+        let req = test::TestRequest::post()
+            .uri("/proofs/import")
+            .peer_addr("10.0.0.1:5000".parse().unwrap())
+            .set_json(serde_json::json!({"proofs": []}));
+        match api_key {
+            Some(key) => req.insert_header((auth::API_KEY_HEADER, key)),
+            None => req,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn import_needs_an_api_key() {
+        let state = state("import-key", &[("API_KEYS", "ci=token:secret")]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        let resp = test::call_service(&app, import(None).to_request()).await;
+        assert_eq!(resp.status(), 401);
+        let resp = test::call_service(&app, import(Some("secret")).to_request()).await;
+        assert_eq!(resp.status(), 401);
+        // Admitted; without a prover the import itself then answers 501
+        let resp = test::call_service(&app, import(Some("token")).to_request()).await;
+        assert_eq!(resp.status(), 501);
+    }
+
+    #[actix_rt::test]
+    async fn stored_proofs_need_an_api_key() {
+        let keyed = state("read-key", &[("API_KEYS", "ci=token:secret")]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, keyed))).await;
+
+        for uri in [
+            "/proofs",
+            "/proofs/proof_1",
+            "/proofs/proof_1/raw",
+            "/proofs/proof_1/report",
+            "/prove-jobs/job_1",
+        ] {
+            let req = test::TestRequest::get().uri(uri);
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), 401, "{uri}");
+            let req = test::TestRequest::get()
+                .uri(uri)
+                .insert_header((auth::API_KEY_HEADER, "token"));
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_ne!(resp.status(), 401, "{uri}");
+        }
+        // Without any keys configured, reading stays open
+        let open = state("read-open", &[]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, open))).await;
+        let req = test::TestRequest::get().uri("/proofs");
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[actix_rt::test]
+    async fn import_is_rate_limited() {
+        let state = state(
+            "import-rate",
+            &[("RATE_LIMIT_PER_MINUTE", "1"), ("RATE_LIMIT_BURST", "1")],
+        );
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        let resp = test::call_service(&app, import(None).to_request()).await;
+        assert_eq!(resp.status(), 501);
+        let resp = test::call_service(&app, import(None).to_request()).await;
+        assert_eq!(resp.status(), 429);
+    }
+
+    const ADMIN_ROUTES: [(Method, &str); 4] = [
+        (Method::POST, "/admin/reload"),
+        (Method::POST, "/whatif"),
+        (Method::POST, "/templates"),
+        (Method::GET, "/templates/weekly"),
+    ];
+    const PUBLIC_ROUTES: [(Method, &str); 4] = [
+        (Method::GET, "/health"),
+        (Method::GET, "/proofs"),
+        (Method::POST, "/validate"),
+        (Method::POST, "/prove-merkle-compact"),
+    ];
+
+    fn request((method, uri): &(Method, &str), admin_token: Option<&str>) -> test::TestRequest {
+        let req = test::TestRequest::default()
+            .method(method.clone())
+            .uri(uri)
+            .set_json(serde_json::json!({}));
+        match admin_token {
+            Some(token) => req.insert_header((header::AUTHORIZATION, format!("Bearer {token}"))),
+            None => req,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn one_listener_serves_every_route() {
+        let state = state("one-listener", &[("ADMIN_TOKEN", "admin")]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        for route in &ADMIN_ROUTES {
+            let resp = test::call_service(&app, request(route, None).to_request()).await;
+            assert_eq!(resp.status(), 401, "{}", route.1);
+        }
+        for route in &PUBLIC_ROUTES {
+            let resp = test::call_service(&app, request(route, None).to_request()).await;
+            assert_ne!(resp.status(), 404, "{}", route.1);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn admin_listener_takes_the_admin_routes() {
+        let state = state(
+            "two-listeners",
+            &[
+                ("ADMIN_BIND_ADDR", "127.0.0.1:9090"),
+                ("ADMIN_TOKEN", "admin"),
+            ],
+        );
+        let public = state.clone();
+        let public =
+            test::init_service(App::new().configure(|cfg| configure_app(cfg, public))).await;
+        let admin =
+            test::init_service(App::new().configure(|cfg| configure_admin_app(cfg, state))).await;
+
+        for route in &ADMIN_ROUTES {
+            let resp =
+                test::call_service(&public, request(route, Some("admin")).to_request()).await;
+            assert_eq!(resp.status(), 404, "{}", route.1);
+            let resp = test::call_service(&admin, request(route, None).to_request()).await;
+            assert_eq!(resp.status(), 401, "{}", route.1);
+        }
+        for route in &PUBLIC_ROUTES {
+            let resp = test::call_service(&public, request(route, None).to_request()).await;
+            assert_ne!(resp.status(), 404, "{}", route.1);
+            let resp = test::call_service(&admin, request(route, Some("admin")).to_request()).await;
+            assert_eq!(resp.status(), 404, "{}", route.1);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn reload_needs_the_admin_token() {
+        let reload = |token: &str| {
+            test::TestRequest::post()
+                .uri("/admin/reload")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+                .to_request()
+        };
+
+        let off = state("reload-off", &[]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, off))).await;
+        let resp = test::call_service(&app, reload("anything")).await;
+        assert_eq!(resp.status(), 501);
+
+        let gated = state("reload-token", &[("ADMIN_TOKEN", "admin")]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, gated))).await;
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::post().uri("/admin/reload").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 401);
+        let resp = test::call_service(&app, reload("wrong")).await;
+        assert_eq!(resp.status(), 401);
+    }
+
+    #[actix_rt::test]
+    async fn body_limit_applies_to_every_route() {
+        let state = state("body-limit", &[("MAX_BODY_BYTES", "64")]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        let body = serde_json::json!({"merkle_proofs": ["a".repeat(500)]});
+        for uri in ["/validate", "/prove-merkle-compact", "/proofs/import"] {
+            let req = test::TestRequest::post().uri(uri).set_json(&body);
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), 413, "{uri}");
+        }
+    }
+
+    #[actix_rt::test]
+    async fn read_only_errors_are_localized() {
+        let state = state("read-only", &[("READ_ONLY", "true")]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        for (language, content_language) in [(None, "en"), (Some("de-DE"), "de")] {
+            let mut req = test::TestRequest::post()
+                .uri("/prove-merkle-compact")
+                .set_json(serde_json::json!({}));
+            if let Some(language) = language {
+                req = req.insert_header((header::ACCEPT_LANGUAGE, language));
+            }
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), 501);
+            assert_eq!(
+                resp.headers().get(header::CONTENT_LANGUAGE).unwrap(),
+                content_language
             );
-            tracing::error!("{}", err_msg);
-            actix_web::error::ErrorBadRequest(err_msg)
-        })?;
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["code"], "prover_disabled", "{body}");
+            assert!(body["error"].is_string(), "{}", body);
+            assert!(body["message"].is_string(), "{}", body);
+        }
+    }
 
-    tracing::debug!(
-        "Validating {} sibling(s) for purl '{}'",
-        proof.siblings.len(),
-        proof.purl
-    );
-    let mut sibling_idx = 0;
-    #[allow(clippy::needless_range_loop)]
-    for d in 0..256 {
-        if bitmap_bit(&bitmap, d) == 1 {
-            if sibling_idx >= proof.siblings.len() {
-                let err_msg = format!(
-                    "Insufficient siblings for purl '{}': bitmap indicates sibling needed at depth {}, but only {} sibling(s) available (expected at least {})",
-                    proof.purl,
-                    d,
-                    proof.siblings.len(),
-                    sibling_idx + 1
-                );
-                tracing::error!("{}", err_msg);
-                return Err(actix_web::error::ErrorBadRequest(err_msg));
+    /// What [`InstantBackend`] commits for `proof_count` proofs against `root_hash`
+    fn instant_outputs(
+        root_hash: [u8; 32],
+        compliant: bool,
+        proof_count: u32,
+    ) -> MerklePublicOutputs {
+        MerklePublicOutputs {
+            root_hash,
+            banned_list_hash: [1; 32],
+            compliant,
+            proof_count,
+            reasons_hash: [2; 32],
+            list_info_hash: [0; 32],
+            input_status: 0,
+            salt_commitment: [0; 32],
+            key_type: 0,
+            proof_type: 0,
+            defaults_digest: sbom_common::DEFAULTS_DIGEST,
+            requester_commitment: [0; 32],
+            first_failure: if compliant { 0 } else { 6 },
+            first_failure_index: 0,
+            first_failure_key_hash: [0; 32],
+        }
+    }
+
+    /// Proves instantly: compliant when every proven value is zero, bound to the requester
+    /// asked for. Its receipts are the committed outputs as JSON, so it can verify them
+    /// again for imports
+    struct InstantBackend;
+
+    impl ProvingBackend for InstantBackend {
+        fn image_id(&self) -> [u32; 8] {
+            [7; 8]
+        }
+
+        fn prove(
+            &self,
+            proofs: &[CompactMerkleProof],
+            public_inputs: &MerklePublicInputs,
+        ) -> Result<GeneratedProof> {
+            let compliant = proofs.iter().all(|proof| proof.value == "0");
+            let mut outputs =
+                instant_outputs(public_inputs.root_hash, compliant, proofs.len() as u32);
+            outputs.requester_commitment = public_inputs.requester_commitment.unwrap_or_default();
+            Ok(GeneratedProof {
+                journal: crate::digest::encode_journal(&outputs),
+                receipt_bytes: serde_json::to_vec(&outputs)?,
+                outputs,
+                session_id: None,
+                receipt_kind: "instant",
+                seal: None,
+            })
+        }
+
+        fn verify(&self, receipt_bytes: &[u8]) -> Result<VerifiedReceipt> {
+            let outputs: MerklePublicOutputs = serde_json::from_slice(receipt_bytes)?;
+            Ok(VerifiedReceipt {
+                journal: crate::digest::encode_journal(&outputs),
+                outputs,
+                receipt_kind: "instant",
+                seal: None,
+            })
+        }
+    }
+
+    /// Like [`InstantBackend`], but binds every proof to another requester
+    struct MisboundBackend;
+
+    impl ProvingBackend for MisboundBackend {
+        fn image_id(&self) -> [u32; 8] {
+            InstantBackend.image_id()
+        }
+
+        fn prove(
+            &self,
+            proofs: &[CompactMerkleProof],
+            public_inputs: &MerklePublicInputs,
+        ) -> Result<GeneratedProof> {
+            let mut generated = InstantBackend.prove(proofs, public_inputs)?;
+            generated.outputs.requester_commitment =
+                sbom_common::compute_requester_commitment("someone-else");
+            generated.journal = crate::digest::encode_journal(&generated.outputs);
+            Ok(generated)
+        }
+    }
+
+    /// App state for spot checks against a snapshot, with `ci` as the only API key, and
+    /// a spot check of a purl missing from that snapshot
+    fn spot_checking(
+        test: &str,
+        backend: Arc<dyn ProvingBackend>,
+    ) -> (AppState, serde_json::Value) {
+        let config = config(test, &[("API_KEYS", "ci=token:secret")]);
+        let service =
+            ProverService::without_prover(ProverConfig::from(&config)).with_backend(backend);
+        let mut tree = SparseMerkleTree::default();
+        tree.insert("pkg:npm/evil@1.0.0", 1);
+        let spot_check = serde_json::json!({
+            "purl": "pkg:npm/a@1",
+            "root_or_alias": hex::encode(tree.root()),
+        });
+        (
+            AppState::new(config, service).with_snapshot(tree),
+            spot_check,
+        )
+    }
+
+    /// A request for `uri` carrying `body`, signed with `signature` under key `ci`, or
+    /// unsigned for `None`
+    fn signed(uri: &str, body: &serde_json::Value, signature: Option<&str>) -> test::TestRequest {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .insert_header((auth::API_KEY_HEADER, "token"))
+            .set_json(body);
+        match signature {
+            Some(signature) => req
+                .insert_header((API_KEY_ID_HEADER, "ci"))
+                .insert_header((REQUEST_SIGNATURE_HEADER, signature)),
+            None => req,
+        }
+    }
+
+    /// The signature of a request whose digest is the SHA-256 of `body`
+    fn body_signature(body: &serde_json::Value) -> String {
+        let digest = Sha256::digest(serde_json::to_vec(body).unwrap());
+        hex::encode(sbom_common::hmac_sha256(b"secret", &digest))
+    }
+
+    #[actix_rt::test]
+    async fn spot_checks_prove_one_purl_from_the_snapshot() {
+        let mut tree = SparseMerkleTree::default();
+        tree.insert("pkg:npm/evil@1.0.0", 1);
+        let root = hex::encode(tree.root());
+        let aliases = format!("nightly={root}");
+        let config = config("spot-check", &[("ROOT_ALIASES", &aliases)]);
+        let service = ProverService::without_prover(ProverConfig::from(&config))
+            .with_backend(Arc::new(InstantBackend));
+        let state = AppState::new(config, service).with_snapshot(tree);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+        let spot_check = |purl: &str, root_or_alias: &str| {
+            test::TestRequest::post()
+                .uri("/prove-purl")
+                .set_json(serde_json::json!({"purl": purl, "root_or_alias": root_or_alias}))
+                .to_request()
+        };
+
+        let req = spot_check("pkg:npm/a@1", "nightly");
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["compliant"], true, "{resp}");
+        assert_eq!(resp["root_hash"], root);
+        assert_eq!(resp["root_alias"], "nightly");
+        let merkle_proof = &resp["merkle_proof"];
+        assert_eq!(merkle_proof["key"], "pkg:npm/a@1", "{resp}");
+        assert_eq!(merkle_proof["value"], "0");
+        assert!(resp["proof"].is_string());
+
+        let req = spot_check("pkg:npm/evil@1.0.0", &root);
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["compliant"], false, "{resp}");
+        assert_eq!(resp["merkle_proof"]["value"], "1");
+
+        // Only the snapshot's root can be proven against
+        for root_or_alias in ["weekly", &"00".repeat(32)] {
+            let resp = test::call_service(&app, spot_check("pkg:npm/a@1", root_or_alias)).await;
+            assert_eq!(resp.status(), 400, "{root_or_alias}");
+        }
+    }
+
+    #[actix_rt::test]
+    async fn every_proving_route_refuses_a_bad_signature() {
+        let (state, spot_check) = spot_checking("bad-signature", Arc::new(InstantBackend));
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        let root = "00".repeat(32);
+        let request = serde_json::json!({"depth": 256, "root": root, "merkle_proofs": []});
+        for (uri, body) in [
+            ("/prove-merkle-compact", request.clone()),
+            ("/prove-jobs", request),
+            ("/prove-purl", spot_check),
+            ("/templates/weekly/run", serde_json::json!({"root": root})),
+            ("/proofs/import", serde_json::json!({"proofs": []})),
+        ] {
+            let forged = "ab".repeat(32);
+            let resp =
+                test::call_service(&app, signed(uri, &body, Some(&forged)).to_request()).await;
+            assert_eq!(resp.status(), 401, "{uri}");
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["code"], "unauthorized", "{uri}: {body}");
+        }
+    }
+
+    #[actix_rt::test]
+    async fn spot_checks_are_bound_to_their_signer() {
+        let (state, body) = spot_checking("spot-check-signer", Arc::new(InstantBackend));
+        let stored = state.service.clone();
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+        let commitment_of = |resp: serde_json::Value| {
+            let proof_id = resp["proof_id"].as_str().unwrap();
+            let record = stored.store().find_record(proof_id).unwrap().unwrap();
+            let record: serde_json::Value = serde_json::from_str(&record).unwrap();
+            record["data"]["requester_commitment"].clone()
+        };
+
+        let req = signed("/prove-purl", &body, Some(&body_signature(&body)));
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        assert_eq!(
+            commitment_of(resp),
+            hex::encode(sbom_common::compute_requester_commitment("ci"))
+        );
+
+        // Unsigned, the spot check is still proven, bound to no one
+        let req = signed("/prove-purl", &body, None);
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        assert!(commitment_of(resp).is_null());
+    }
+
+    #[actix_rt::test]
+    async fn a_proof_bound_to_another_requester_is_refused() {
+        let (state, body) = spot_checking("requester-mismatch", Arc::new(MisboundBackend));
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        let req = signed("/prove-purl", &body, Some(&body_signature(&body)));
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), 500);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "invalid_output", "{body}");
+        let error = body["error"].as_str().unwrap();
+        assert!(error.contains("requester commitment"), "{}", error);
+    }
+
+    #[actix_rt::test]
+    async fn templates_run_with_overrides_within_their_constraints() {
+        let mut tree = SparseMerkleTree::default();
+        tree.insert("pkg:npm/evil@1.0.0", 1);
+        let root = hex::encode(tree.root());
+        let aliases = format!("bl@1={},other={}", root, "00".repeat(32));
+        let config = config(
+            "templates",
+            &[("ADMIN_TOKEN", "admin"), ("ROOT_ALIASES", &aliases)],
+        );
+        let service = ProverService::without_prover(ProverConfig::from(&config))
+            .with_backend(Arc::new(InstantBackend));
+        let state = AppState::new(config, service).with_snapshot(tree);
+        let stored = state.service.clone();
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+        let post = |uri: &str, body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri(uri)
+                .insert_header((header::AUTHORIZATION, "Bearer admin"))
+                .set_json(body)
+                .to_request()
+        };
+
+        let definition = serde_json::json!({
+            "name": "weekly",
+            "source": {"kind": "snapshot", "purls": ["pkg:npm/a@1", "pkg:npm/b@2"]},
+            "root_aliases": ["bl@1"],
+        });
+        for version in [1, 2] {
+            let resp = test::call_service(&app, post("/templates", definition.clone())).await;
+            assert_eq!(resp.status(), 201);
+            let template: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(template["version"], version);
+        }
+
+        // The override picks the root; the purls come from the template
+        let run = post(
+            "/templates/weekly/run",
+            serde_json::json!({"root_alias": "bl@1"}),
+        );
+        let resp = test::call_service(&app, run).await;
+        assert_eq!(resp.status(), 200);
+        let run: serde_json::Value = test::read_body_json(resp).await;
+        let run = &run["data"];
+        assert_eq!(run["compliant"], true, "{run}");
+        assert_eq!(
+            run["template"],
+            serde_json::json!({"name": "weekly", "version": 2})
+        );
+        assert_eq!(
+            run["purls"],
+            serde_json::json!(["pkg:npm/a@1", "pkg:npm/b@2"])
+        );
+        let proof_id = run["proof_id"].as_str().unwrap();
+        let record = stored.store().find_record(proof_id).unwrap().unwrap();
+        let record: serde_json::Value = serde_json::from_str(&record).unwrap();
+        assert_eq!(record["data"]["template"]["version"], 2);
+
+        for (overrides, violation) in [
+            (serde_json::json!({"root": root}), "root aliases [bl@1]"),
+            (serde_json::json!({"root_alias": "other"}), "root aliases"),
+            (
+                serde_json::json!({"root_alias": "bl@1", "merkle_proofs": []}),
+                "cannot supply merkle_proofs",
+            ),
+        ] {
+            let resp = test::call_service(&app, post("/templates/weekly/run", overrides)).await;
+            assert_eq!(resp.status(), 400);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            let error = body["error"].as_str().unwrap();
+            assert!(
+                error.contains("violates template 'weekly' version 2"),
+                "{}",
+                error
+            );
+            assert!(error.contains(violation), "{}", error);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn import_reports_every_document() {
+        let config = config("import-batch", &[]);
+        let service = ProverService::without_prover(ProverConfig::from(&config))
+            .with_backend(Arc::new(InstantBackend));
+        let app = test::init_service(
+            App::new().configure(|cfg| configure_app(cfg, AppState::new(config, service))),
+        )
+        .await;
+        let document = |receipt: &[u8]| {
+            serde_json::json!({
+                "proof": general_purpose::STANDARD.encode(receipt),
+                "image_id": ["7", "7", "7", "7", "7", "7", "7", "7"],
+                "request_digest": "ab".repeat(32),
+                "purls": ["pkg:npm/a@1"],
+            })
+        };
+        let receipt = serde_json::to_vec(&instant_outputs([3; 32], true, 1)).unwrap();
+        let good = document(&receipt);
+        let corrupt = document(&receipt[..receipt.len() / 2]);
+
+        let import = |proofs: Vec<serde_json::Value>| {
+            test::TestRequest::post()
+                .uri("/proofs/import")
+                .set_json(serde_json::json!({"proofs": proofs}))
+                .to_request()
+        };
+        let resp =
+            test::call_service(&app, import(vec![good.clone(), corrupt, good.clone()])).await;
+        assert_eq!(resp.status(), 200);
+        let report: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(report["imported"], 1, "{report}");
+        assert_eq!(report["invalid"], 1, "{report}");
+        assert_eq!(report["duplicates"], 1, "{report}");
+        let results = &report["results"];
+        assert_eq!(results[0]["status"], "imported");
+        assert_eq!(results[1]["status"], "invalid");
+        assert!(results[1]["error"].is_string());
+        assert!(results[1].get("proof_id").is_none());
+        assert_eq!(results[2]["status"], "duplicate");
+        assert_eq!(results[2]["proof_id"], results[0]["proof_id"]);
+
+        let proof_id = results[0]["proof_id"].as_str().unwrap();
+        let req = test::TestRequest::get().uri(&format!("/proofs/{proof_id}"));
+        let record: serde_json::Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        assert_eq!(record["data"]["imported"], true, "{record}");
+
+        // The stored proof stays a duplicate on later imports
+        let report: serde_json::Value =
+            test::call_and_read_body_json(&app, import(vec![good])).await;
+        assert_eq!(report["duplicates"], 1, "{report}");
+    }
+
+    /// Compact proofs of `purls` against `tree`, as clients send them
+    fn compact_proofs(tree: &SparseMerkleTree, purls: &[&str]) -> Vec<serde_json::Value> {
+        purls
+            .iter()
+            .map(|purl| {
+                let proof = tree.prove(purl);
+                serde_json::json!({
+                    "purl": purl,
+                    "value": proof.value.to_string(),
+                    "leaf_index": hex::encode(proof.leaf_index),
+                    "siblings": proof.siblings.iter().map(hex::encode).collect::<Vec<_>>(),
+                    "bitmap": hex::encode(proof.bitmap),
+                })
+            })
+            .collect()
+    }
+
+    #[actix_rt::test]
+    async fn reason_codes_of_banned_purls_are_decoded() {
+        let mut tree = SparseMerkleTree::default();
+        tree.insert("pkg:npm/evil@1.0.0", 2);
+        let state = state("reasons", &[]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+        let validate = |proofs: &[serde_json::Value]| {
+            test::TestRequest::post()
+                .uri("/validate")
+                .set_json(serde_json::json!({
+                    "depth": 256,
+                    "root": hex::encode(tree.root()),
+                    "merkle_proofs": proofs,
+                }))
+                .to_request()
+        };
+
+        let mut proofs = compact_proofs(&tree, &["pkg:npm/a@1", "pkg:npm/evil@1.0.0"]);
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, validate(&proofs)).await;
+        assert_eq!(resp["valid"], true, "{resp}");
+        assert_eq!(resp["compliant"], false, "{resp}");
+        assert_eq!(
+            resp["reasons"],
+            serde_json::json!([{
+                "purl": "pkg:npm/evil@1.0.0",
+                "leaf_index": proofs[1]["leaf_index"],
+                "value": 2,
+                "reason": "legal",
+            }])
+        );
+        let leaf_index = |proof: &serde_json::Value| {
+            hex_to_bytes32(proof["leaf_index"].as_str().unwrap()).unwrap()
+        };
+        let reasons_hash =
+            compute_reasons_hash([(leaf_index(&proofs[0]), 0), (leaf_index(&proofs[1]), 2)]);
+        assert_eq!(resp["reasons_hash"], hex::encode(reasons_hash));
+
+        // One past u64::MAX
+        proofs[1]["value"] = "18446744073709551616".into();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, validate(&proofs)).await;
+        assert_eq!(resp["valid"], false, "{resp}");
+        let error = resp["results"][1]["error"].as_str().unwrap();
+        assert!(error.contains("expected a decimal u64"), "{}", error);
+    }
+
+    /// App state with `vars` as configuration, proving with [`InstantBackend`]
+    fn instant(test: &str, vars: &[(&str, &str)]) -> AppState {
+        let config = config(test, vars);
+        let service = ProverService::without_prover(ProverConfig::from(&config))
+            .with_backend(Arc::new(InstantBackend));
+        AppState::new(config, service)
+    }
+
+    /// A request proving `purls` against a tree that bans one other purl
+    fn prove_request(purls: &[&str]) -> serde_json::Value {
+        let mut tree = SparseMerkleTree::default();
+        tree.insert("pkg:npm/evil@1.0.0", 1);
+        serde_json::json!({
+            "depth": 256,
+            "root": hex::encode(tree.root()),
+            "merkle_proofs": compact_proofs(&tree, purls),
+        })
+    }
+
+    #[actix_rt::test]
+    async fn large_receipts_are_linked_and_downloaded_raw() {
+        let state = instant("download-link", &[("INLINE_PROOF_LIMIT_BYTES", "16")]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        let req = test::TestRequest::post()
+            .uri("/prove-merkle-compact")
+            .set_json(prove_request(&["pkg:npm/a@1"]));
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        let proof = &resp["data"];
+        assert!(proof["proof"].is_null(), "{resp}");
+        let url = proof["proof_url"].as_str().unwrap();
+        assert_eq!(
+            url,
+            format!("/proofs/{}/raw", proof["proof_id"].as_str().unwrap())
+        );
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri(url).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/octet-stream"
+        );
+        let receipt = test::read_body(resp).await;
+        assert_eq!(Some(receipt.len() as u64), proof["proof_size"].as_u64());
+        let verified = InstantBackend.verify(&receipt).unwrap();
+        assert_eq!(hex::encode(verified.journal), proof["journal"]);
+    }
+
+    #[actix_rt::test]
+    async fn oversized_responses_link_the_proof_instead() {
+        let state = instant("response-cap", &[]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+        let prove = |max_response_bytes: Option<usize>| {
+            let mut body = prove_request(&["pkg:npm/a@1"]);
+            if let Some(limit) = max_response_bytes {
+                body["max_response_bytes"] = limit.into();
             }
+            test::TestRequest::post()
+                .uri("/prove-merkle-compact")
+                .set_json(body)
+                .to_request()
+        };
 
-            let sibling_hash = hex_to_bytes32(&proof.siblings[sibling_idx])
-                .map_err(|e| {
-                    let err_msg = format!(
-                        "Invalid sibling hex format for purl '{}' at depth {} (sibling index {}): {}. Sibling value: '{}'",
-                        proof.purl, d, sibling_idx, e, proof.siblings[sibling_idx]
-                    );
-                    tracing::error!("{}", err_msg);
-                    actix_web::error::ErrorBadRequest(err_msg)
-                })?;
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, prove(None)).await;
+        let inline = &resp["data"];
+        assert_eq!(inline["oversize"], false, "{resp}");
+        let receipt = general_purpose::STANDARD
+            .decode(inline["proof"].as_str().unwrap())
+            .unwrap();
 
-            if sibling_hash == DEFAULTS[d] {
-                let err_msg = format!(
-                    "Invalid sibling for purl '{}' at depth {}: sibling matches DEFAULTS[{}] (value: {}). When sibling equals default value, bitmap bit should be 0, not 1",
-                    proof.purl,
-                    d,
-                    d,
-                    hex::encode(DEFAULTS[d])
-                );
-                tracing::error!("{}", err_msg);
-                return Err(actix_web::error::ErrorBadRequest(err_msg));
+        // The same proof, asked for with a hint smaller than its response
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, prove(Some(64))).await;
+        let linked = &resp["data"];
+        assert_eq!(linked["oversize"], true, "{resp}");
+        assert!(linked["proof"].is_null());
+        assert_eq!(linked["proof_id"], inline["proof_id"]);
+        let url = linked["proof_url"].as_str().unwrap();
+        let req = test::TestRequest::get().uri(url).to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, receipt);
+
+        // A hint above the response size changes nothing
+        let resp: serde_json::Value =
+            test::call_and_read_body_json(&app, prove(Some(1 << 20))).await;
+        assert_eq!(resp["data"]["oversize"], false, "{resp}");
+        assert_eq!(resp["data"]["proof"], inline["proof"]);
+    }
+
+    #[actix_rt::test]
+    async fn read_only_replicas_serve_the_archive_and_refuse_to_prove() {
+        // Receipts above the inline limit are archived beside their records
+        let state = instant("archive", &[("INLINE_PROOF_LIMIT_BYTES", "16")]);
+        let proofs_dir = state.config.load().proofs_dir.clone();
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+        let req = test::TestRequest::post()
+            .uri("/prove-merkle-compact")
+            .set_json(prove_request(&["pkg:npm/a@1"]));
+        let proved: serde_json::Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        let proof_id = proved["data"]["proof_id"].as_str().unwrap();
+
+        // A replica over the same directory, as after a restore
+        let config = Config::from_vars(|name| match name {
+            "PROOFS_DIR" => Some(proofs_dir.display().to_string()),
+            "READ_ONLY" => Some("true".to_string()),
+            _ => None,
+        });
+        let service = ProverService::without_prover(ProverConfig::from(&config));
+        let state = AppState::new(config, service);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        let uri = format!("/proofs/{proof_id}");
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let stored: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let journal = &proved["data"]["journal"];
+        assert_eq!(&stored["data"]["journal"], journal, "{stored}");
+        let raw = format!("{uri}/raw");
+        let req = test::TestRequest::get().uri(&raw).to_request();
+        let receipt = test::call_and_read_body(&app, req).await;
+        let verified = InstantBackend.verify(&receipt).unwrap();
+        assert_eq!(hex::encode(verified.journal), *journal);
+
+        for uri in [
+            "/prove-merkle-compact",
+            "/prove-jobs",
+            "/prove-purl",
+            "/proofs/import",
+            "/templates/nightly/run",
+        ] {
+            let req = test::TestRequest::post()
+                .uri(uri)
+                .set_json(prove_request(&["pkg:npm/a@1"]));
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), 501, "{uri}");
+        }
+    }
+
+    #[actix_rt::test]
+    async fn proof_ids_follow_the_request_not_the_moment() {
+        let state = instant("proof-ids", &[]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+        let prove = |purls: &[&str]| {
+            let mut body = prove_request(purls);
+            // Prove again instead of answering from the proof cache
+            body["force"] = true.into();
+            test::TestRequest::post()
+                .uri("/prove-merkle-compact")
+                .set_json(body)
+                .to_request()
+        };
+
+        let mut proof_ids = Vec::new();
+        let mut request_ids = std::collections::HashSet::new();
+        for purls in [&["pkg:npm/a@1"][..], &["pkg:npm/a@1"], &["pkg:npm/b@1"]] {
+            let resp: serde_json::Value = test::call_and_read_body_json(&app, prove(purls)).await;
+            assert_eq!(resp["data"]["cached"], false, "{resp}");
+            proof_ids.push(resp["data"]["proof_id"].as_str().unwrap().to_string());
+            request_ids.insert(resp["data"]["request_id"].as_str().unwrap().to_string());
+        }
+        assert_eq!(proof_ids[0], proof_ids[1]);
+        assert_ne!(proof_ids[0], proof_ids[2]);
+        assert_eq!(request_ids.len(), 3);
+    }
+
+    #[actix_rt::test]
+    async fn hostile_json_is_refused_before_it_is_parsed() {
+        let app = test::init_service(
+            App::new().configure(|cfg| configure_app(cfg, instant("json-bombs", &[]))),
+        )
+        .await;
+
+        // 10,000 nested arrays, and a flat object of 100,001 keys: 200,002 tokens
+        let bomb = format!("{}{}", "[".repeat(10_000), "]".repeat(10_000));
+        let keys: Vec<String> = (0..=100_000).map(|n| format!("\"k{n}\":0")).collect();
+        let many_keys = format!("{{{}}}", keys.join(","));
+        for uri in [
+            "/prove-merkle-compact",
+            "/prove-jobs",
+            "/prove-purl",
+            "/validate",
+        ] {
+            for (body, status, code) in [
+                (&bomb, 400, "invalid_request"),
+                (&many_keys, 413, "limit_exceeded"),
+            ] {
+                let req = test::TestRequest::post()
+                    .uri(uri)
+                    .insert_header(header::ContentType::json())
+                    .set_payload(body.clone())
+                    .to_request();
+                let resp = test::call_service(&app, req).await;
+                assert_eq!(resp.status(), status, "{uri}");
+                let body: serde_json::Value = test::read_body_json(resp).await;
+                assert_eq!(body["code"], code, "{uri}: {body}");
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn deadlines_the_estimate_cannot_meet_fail_fast() {
+        let state = instant("deadline", &[]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        // One proof is estimated at 21.5 s. Each request proves another purl, so none is
+        // answered from the proof cache
+        for (deadline, status, purl) in [
+            ("3600", 200, "pkg:npm/a@1"),
+            ("10", 504, "pkg:npm/b@1"),
+            // An absolute deadline, long past
+            ("1000000000000", 504, "pkg:npm/c@1"),
+            ("soon", 400, "pkg:npm/d@1"),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/prove-merkle-compact")
+                .insert_header((DEADLINE_HEADER, deadline))
+                .set_json(prove_request(&[purl]));
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), status, "{deadline}");
+            if status == 504 {
+                let body: serde_json::Value = test::read_body_json(resp).await;
+                assert_eq!(body["code"], "deadline_exceeded", "{deadline}: {body}");
             }
+        }
+    }
 
-            sibling_idx += 1;
+    #[actix_rt::test]
+    async fn finished_jobs_expire() {
+        let jobs = ProveJobs::new(Duration::ZERO, 10);
+        jobs.set("running", ProveJob::Running);
+        jobs.set(
+            "failed",
+            ProveJob::Failed {
+                error: "no".to_string(),
+            },
+        );
+        assert!(matches!(jobs.get("running"), Some(ProveJob::Running)));
+        assert!(jobs.get("failed").is_none());
+    }
+
+    #[actix_rt::test]
+    async fn only_the_newest_finished_jobs_are_kept() {
+        let jobs = ProveJobs::new(FINISHED_JOB_TTL, 2);
+        jobs.set("pending", ProveJob::Pending);
+        for id in ["first", "second", "third"] {
+            jobs.set(
+                id,
+                ProveJob::Failed {
+                    error: "no".to_string(),
+                },
+            );
         }
+        assert!(jobs.get("first").is_none());
+        assert!(jobs.get("second").is_some());
+        assert!(jobs.get("third").is_some());
+        assert!(jobs.get("pending").is_some());
     }
 
-    tracing::debug!(
-        "Compact proof validation successful for purl: {}",
-        proof.purl
-    );
-    Ok(())
+    #[actix_rt::test]
+    async fn unknown_jobs_name_the_retention() {
+        let state = state("prove-jobs", &[]);
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        let req = test::TestRequest::get().uri("/prove-jobs/evicted");
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), 404);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let error = body["error"].as_str().unwrap();
+        assert!(error.contains("kept for 60 minutes"), "{}", error);
+    }
 }
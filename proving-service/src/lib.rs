@@ -1,7 +1,41 @@
+#[cfg(feature = "server")]
+pub mod auth;
+pub mod autoscale;
+#[cfg(feature = "bonsai")]
+pub mod bonsai;
 pub mod config;
+#[cfg(feature = "server")]
+pub mod cors;
+pub mod digest;
 pub mod error;
+#[cfg(feature = "server")]
 pub mod handlers;
+pub mod migrations;
 pub mod models;
+mod output_checks;
+pub mod proof_cache;
+pub mod proof_gen;
+pub mod prover;
+#[cfg(feature = "server")]
+pub mod rate_limit;
+pub mod redact;
+#[cfg(feature = "server")]
+pub mod reload;
+pub mod report;
+pub mod sequence;
+pub mod service;
+#[cfg(feature = "server")]
+pub mod spot_check;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod storage;
+#[cfg(feature = "server")]
+pub mod supervisor;
+#[cfg(feature = "server")]
+pub mod templates;
 pub mod utils;
+mod validation;
+pub mod whatif;
 
 pub use error::{Error, Result};
+pub use service::{ProverConfig, ProverService};
@@ -1,34 +1,163 @@
-use actix_web::{App, HttpServer, middleware, web};
-use proving_service::{config::Config, handlers};
+use actix_web::{App, HttpServer};
+#[cfg(unix)]
+use proving_service::reload::reload_on_sighup;
+use proving_service::spot_check::load_snapshot;
+use proving_service::{ProverConfig, ProverService, config::Config, cors, handlers, migrations};
 use tracing_subscriber::filter::EnvFilter;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let (mut migrate_only, mut dry_run) = (false, false);
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--migrate-only" => migrate_only = true,
+            "--dry-run" => dry_run = true,
+            _ => usage(),
+        }
+    }
+    if dry_run && !migrate_only {
+        usage();
+    }
+
     tracing_subscriber::fmt()
         .with_env_filter(
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
         )
         .init();
 
-    let config = Config::from_env();
+    let config = Config::load().map_err(std::io::Error::other)?;
+
+    if migrate_only {
+        let service = ProverService::without_prover(ProverConfig::from(&config));
+        let run = migrations::run(service.store().as_ref(), dry_run).map_err(|e| {
+            tracing::error!("Failed to migrate the proof store: {}", e);
+            std::io::Error::other(e)
+        })?;
+        if run.is_empty() {
+            tracing::info!("The proof store is up to date");
+        }
+        return Ok(());
+    }
 
     tracing::info!("Starting proving-service on port {}", config.port);
     tracing::info!("Proofs directory: {}", config.proofs_dir.display());
+    if let Some(path) = &config.database_path {
+        tracing::info!("Proof records database: {}", path.display());
+    }
+    tracing::info!(
+        "Receipts above {} bytes are served via download link",
+        config.inline_proof_limit
+    );
+    if let Some(limit) = config.max_response_bytes {
+        tracing::info!(
+            "Responses above {} bytes carry a download link instead of the proof",
+            limit
+        );
+    }
+
+    cors::policy(&config).map_err(|err_msg| {
+        tracing::error!("{}", err_msg);
+        std::io::Error::other(err_msg)
+    })?;
+    if !config.cors_allowed_origins.is_empty() {
+        tracing::info!(
+            "Cross-origin requests are allowed from {}",
+            config.cors_allowed_origins.join(", ")
+        );
+    }
+
+    if !config.api_keys.is_empty() {
+        tracing::info!(
+            "Proving endpoints need one of {} API key(s)",
+            config.api_keys.len()
+        );
+    }
+
+    if config.rate_limit_per_minute > 0 {
+        tracing::info!(
+            "Prove requests are limited to {} per minute per client, in bursts of up to {}",
+            config.rate_limit_per_minute,
+            config.rate_limit_burst
+        );
+    }
+
+    if config.read_only {
+        tracing::info!("Read-only mode: serving archived proofs, proving is disabled");
+        match &config.verifier_url {
+            Some(url) => tracing::info!("Verification requests are redirected to {}", url),
+            None => tracing::warn!("VERIFIER_URL is not set; /verify answers 501"),
+        }
+    }
+
+    // RISC Zero reads the variable itself, so a value from CONFIG_FILE has to reach it
+    if config.dev_mode && !config.read_only {
+        // SAFETY: no worker thread has been started yet
+        unsafe { std::env::set_var("RISC0_DEV_MODE", "1") };
+        tracing::warn!(
+            "Dev mode: receipts are fake, prove nothing and are marked dev_mode; never use them as evidence"
+        );
+    } else {
+        // SAFETY: as above
+        unsafe { std::env::remove_var("RISC0_DEV_MODE") };
+    }
 
     let port = config.port;
+    let prover_config = ProverConfig::from(&config);
+    let service = if config.read_only {
+        ProverService::without_prover(prover_config)
+    } else {
+        ProverService::new(prover_config)
+    };
 
-    HttpServer::new(move || {
-        let config = config.clone();
-        App::new()
-            .wrap(middleware::Logger::default())
-            .app_data(web::Data::new(config))
-            .route("/health", web::get().to(handlers::health))
-            .route(
-                "/prove-merkle-compact",
-                web::post().to(handlers::prove_merkle_compact),
-            )
+    if let Err(e) = migrations::run(service.store().as_ref(), false) {
+        tracing::warn!("Failed to migrate the proof store: {}", e);
+    }
+
+    let snapshot = match &config.smt_snapshot {
+        Some(path) => Some(load_snapshot(path).map_err(std::io::Error::other)?),
+        None => None,
+    };
+
+    let mut state = handlers::AppState::new(config, service);
+    if let Some(tree) = snapshot {
+        state = state.with_snapshot(tree);
+    }
+    #[cfg(unix)]
+    reload_on_sighup(state.config.clone(), &state.tasks)?;
+
+    let tasks = state.tasks.clone();
+    let admin_bind_addr = state.config.load().admin_bind_addr.clone();
+    let public_state = state.clone();
+    let public = HttpServer::new(move || {
+        let state = public_state.clone();
+        App::new().configure(|cfg| handlers::configure_app(cfg, state))
     })
     .bind(("0.0.0.0", port))?
-    .run()
-    .await
+    .run();
+
+    let served = match admin_bind_addr {
+        Some(addr) => {
+            tracing::info!("Admin endpoints are served on {} only", addr);
+            if state.config.load().admin_token.is_none() {
+                tracing::warn!("ADMIN_TOKEN is not set; every admin endpoint answers 501");
+            }
+            let admin = HttpServer::new(move || {
+                let state = state.clone();
+                App::new().configure(|cfg| handlers::configure_admin_app(cfg, state))
+            })
+            .bind(addr.as_str())?
+            .run();
+            tokio::try_join!(public, admin).map(|_| ())
+        }
+        None => public.await,
+    };
+
+    // The server has drained its connections; stop what runs besides them
+    tasks.shutdown().await;
+    served
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: proving-service [--migrate-only [--dry-run]]");
+    std::process::exit(2);
 }
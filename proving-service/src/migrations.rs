@@ -0,0 +1,150 @@
+//! Numbered one-off migrations of the proof store, run on startup.
+//!
+//! Each [`Migration`] brings what older builds stored to the current layout. The store
+//! records the ids it has applied ([`ProofStore::applied_migrations`]), in
+//! `migrations/applied.json` under `PROOFS_DIR` or in the database, so a start runs only
+//! the migrations not yet applied, in order, recording each as it completes. A migration
+//! interrupted part way is not recorded and runs again in full on the next start, so
+//! every migration converts only what it finds unconverted. A failed migration stops the
+//! run, as later ones may rely on it.
+//!
+//! `proving-service --migrate-only` runs them and exits; with `--dry-run` it lists the
+//! migrations a start would run and changes nothing.
+
+use std::collections::BTreeSet;
+
+use crate::error::Result;
+use crate::storage::ProofStore;
+
+/// One step of [`MIGRATIONS`]
+#[derive(Debug)]
+pub struct Migration {
+    /// Recorded once applied; never reused
+    pub id: u32,
+    pub name: &'static str,
+    /// Convert what is left unconverted, returning how many items were
+    apply: fn(&dyn ProofStore) -> Result<usize>,
+}
+
+/// Every migration, in the order they run
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        id: 1,
+        name: "proof-id-records",
+        apply: |store| store.migrate_legacy_records(),
+    },
+    Migration {
+        id: 2,
+        name: "content-addressed-receipts",
+        apply: |store| store.migrate_legacy_receipts(),
+    },
+    Migration {
+        id: 3,
+        name: "enveloped-records",
+        apply: |store| store.upgrade_bare_records(),
+    },
+];
+
+/// Run the migrations `store` has not applied, in order, recording each once it
+/// completes. With `dry_run`, run and record nothing. Returns the migrations run, or
+/// that would run, each with the number of items it converted (0 in a dry run).
+pub fn run(store: &dyn ProofStore, dry_run: bool) -> Result<Vec<(&'static Migration, usize)>> {
+    let applied: BTreeSet<u32> = store.applied_migrations()?;
+    let mut run = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| !applied.contains(&m.id)) {
+        if dry_run {
+            tracing::info!("Migration {} ({}) is pending", migration.id, migration.name);
+            run.push((migration, 0));
+            continue;
+        }
+        let converted = (migration.apply)(store)?;
+        store.record_migration(migration.id, migration.name)?;
+        tracing::info!(
+            "Applied migration {} ({}), converting {} items",
+            migration.id,
+            migration.name,
+            converted
+        );
+        run.push((migration, converted));
+    }
+    Ok(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::storage::{FsProofStore, RecordFormat};
+
+    const LEGACY_ID: &str = "abababababababababababababababababababababababababababababababab";
+    const BARE_ID: &str = "cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd";
+
+    /// A store in a fresh directory named after `test`, holding what an old build left:
+    /// a record named by its timestamp with its receipt beside it, and a bare record
+    fn old_store(test: &str) -> FsProofStore {
+        let dir = std::env::temp_dir().join(format!("proving-service-migrations-{test}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = FsProofStore::new(dir);
+
+        for (path, proof_id) in [
+            (store.legacy_record_path(1_700_000_000), LEGACY_ID),
+            (store.record_path(BARE_ID), BARE_ID),
+        ] {
+            let record = serde_json::json!({"proof_id": proof_id, "timestamp": 1_700_000_000});
+            std::fs::write(path, record.to_string()).unwrap();
+        }
+        std::fs::write(store.legacy_receipt_path(LEGACY_ID), b"receipt").unwrap();
+        store
+    }
+
+    fn ids(run: &[(&Migration, usize)]) -> Vec<u32> {
+        run.iter().map(|(migration, _)| migration.id).collect()
+    }
+
+    #[test]
+    fn dry_runs_change_nothing() {
+        let store = old_store("dry-run");
+
+        let pending = run(&store, true).unwrap();
+        assert_eq!(ids(&pending), [1, 2, 3]);
+        assert!(store.legacy_record_path(1_700_000_000).exists());
+        assert!(store.legacy_receipt_path(LEGACY_ID).exists());
+        assert!(store.applied_migrations().unwrap().is_empty());
+    }
+
+    #[test]
+    fn interrupted_runs_resume_and_reruns_change_nothing() {
+        let store = old_store("resume");
+        // A start that stopped after converting the records but before recording it
+        assert_eq!(store.migrate_legacy_records().unwrap(), 1);
+
+        let first = run(&store, false).unwrap();
+        let converted: Vec<usize> = first.iter().map(|&(_, converted)| converted).collect();
+        assert_eq!((ids(&first), converted), (vec![1, 2, 3], vec![0, 1, 1]));
+        assert_eq!(
+            store.applied_migrations().unwrap(),
+            BTreeSet::from([1, 2, 3])
+        );
+
+        assert!(!store.legacy_record_path(1_700_000_000).exists());
+        let mut records = 0;
+        for entry in std::fs::read_dir(store.dir()).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let contents = std::fs::read(&path).unwrap();
+                assert!(!RecordFormat::default().open(&contents).unwrap().is_bare());
+                records += 1;
+            }
+        }
+        assert_eq!(records, 2);
+        let receipt_sha256 = hex::encode(Sha256::digest(b"receipt"));
+        assert!(store.payload_path(&receipt_sha256).exists());
+
+        assert!(run(&store, false).unwrap().is_empty());
+        for migration in MIGRATIONS {
+            assert_eq!((migration.apply)(&store).unwrap(), 0, "{}", migration.name);
+        }
+    }
+}
@@ -1,24 +1,559 @@
+use sbom_common::api_schema::ApiSchema;
+use sbom_common::envelope::ProofEnvelope;
+use sbom_common::proof::ProofFields;
+use sbom_common::{KeyType, ProofType, failure};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone)]
+use crate::redact::{KeyDisplay, RedactionKey, SealedKeys};
+
+/// Bump whenever a request or response model below changes shape, then regenerate the
+/// definitions in `docs/api` (see the `api-schema` binary).
+pub const API_SCHEMA_VERSION: u32 = 18;
+
+/// The HTTP request and response models, as served at `GET /schema`
+pub fn api_schema() -> ApiSchema {
+    ApiSchema::new("proving-service", API_SCHEMA_VERSION)
+        .with::<ProveCompactMerkleRequest>()
+        .with::<ProofArtifact>()
+        .with::<ProofEnvelope<ProofArtifact>>()
+        .with::<ProofSummary>()
+        .with::<ValidateCompactMerkleResponse>()
+        .with::<TemplateDefinition>()
+        .with::<ProofTemplate>()
+        .with::<TemplateRun>()
+        .with::<WhatIfRequest>()
+        .with::<WhatIfReport>()
+        .with::<GenerateProofsRequest>()
+        .with::<GenerateProofsResponse>()
+        .with::<ImportProofsRequest>()
+        .with::<ImportProofsResponse>()
+}
+
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 pub struct CompactMerkleProof {
-    pub purl: String,
+    /// Identifier the proof is about; older clients send it as `purl`
+    #[serde(alias = "purl")]
+    pub key: String,
+    /// How `key` maps to its leaf index; a purl when absent
+    #[serde(default)]
+    pub key_type: KeyType,
     pub value: String,
+    /// Hex, like `siblings` and `bitmap`; a CBOR request may send all three as byte strings
+    #[serde(deserialize_with = "hex_or_bytes::string")]
     pub leaf_index: String,
+    #[serde(deserialize_with = "hex_or_bytes::strings")]
     pub siblings: Vec<String>,
+    /// Hex bitmap of the levels carrying a sibling; absent from uncompressed proofs
+    #[serde(default, deserialize_with = "hex_or_bytes::string")]
     pub bitmap: String,
 }
 
-#[derive(Deserialize)]
+impl CompactMerkleProof {
+    /// The fields the guest checks, for the checks it shares with the host
+    pub fn fields(&self) -> ProofFields<'_> {
+        ProofFields {
+            key: &self.key,
+            key_type: self.key_type,
+            value: &self.value,
+            leaf_index: &self.leaf_index,
+            siblings: &self.siblings,
+            bitmap: &self.bitmap,
+        }
+    }
+}
+
+/// Hex fields of compact proofs, which a CBOR request sends as raw byte strings. Text is
+/// taken as it is and bytes are hex-encoded, so the model holds hex either way; a JSON
+/// request is read exactly as a plain `String` field would be.
+mod hex_or_bytes {
+    use serde::de::{self, Deserialize, Deserializer, Visitor};
+    use std::fmt;
+
+    struct HexOrBytes;
+
+    impl Visitor<'_> for HexOrBytes {
+        type Value = String;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a string")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<String, E> {
+            Ok(value.to_string())
+        }
+
+        fn visit_string<E: de::Error>(self, value: String) -> Result<String, E> {
+            Ok(value)
+        }
+
+        fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<String, E> {
+            Ok(hex::encode(value))
+        }
+    }
+
+    struct Item(String);
+
+    impl<'de> Deserialize<'de> for Item {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            string(deserializer).map(Item)
+        }
+    }
+
+    pub fn string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        deserializer.deserialize_any(HexOrBytes)
+    }
+
+    pub fn strings<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+        let items = Vec::<Item>::deserialize(deserializer)?;
+        Ok(items.into_iter().map(|Item(value)| value).collect())
+    }
+}
+
+/// How the proofs in a request are encoded
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofEncoding {
+    /// A bitmap plus only the siblings that differ from DEFAULTS; the canonical form
+    #[default]
+    Compact,
+    /// No bitmap and one sibling for every level, depth 0 first, as the Go
+    /// merkle-proof-service returns them with `compress: false`. Compressed on arrival
+    Uncompressed,
+}
+
+/// Which receipt the prover produces
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptKind {
+    /// One STARK per segment; the fastest to produce and the largest
+    #[default]
+    Composite,
+    /// The segments recursively compressed into a single STARK of constant size
+    Succinct,
+    /// A succinct receipt wrapped in a Groth16 SNARK, small enough for EVM calldata
+    Groth16,
+}
+
+impl ReceiptKind {
+    /// Name as used in requests and responses
+    pub const fn name(self) -> &'static str {
+        match self {
+            ReceiptKind::Composite => "composite",
+            ReceiptKind::Succinct => "succinct",
+            ReceiptKind::Groth16 => "groth16",
+        }
+    }
+}
+
+/// Hash function the prover builds its STARK seals with
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug, JsonSchema)]
+pub enum HashFn {
+    /// The only one the recursion circuit supports, so the only one for `succinct` and
+    /// `groth16` receipts and the fast path
+    #[default]
+    #[serde(rename = "poseidon2")]
+    Poseidon2,
+    #[serde(rename = "sha-256")]
+    Sha256,
+    #[serde(rename = "blake2b")]
+    Blake2b,
+}
+
+impl HashFn {
+    /// Name as used in requests, responses, `PROVER_HASHFN` and by RISC Zero
+    pub const fn name(self) -> &'static str {
+        match self {
+            HashFn::Poseidon2 => "poseidon2",
+            HashFn::Sha256 => "sha-256",
+            HashFn::Blake2b => "blake2b",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [HashFn::Poseidon2, HashFn::Sha256, HashFn::Blake2b]
+            .into_iter()
+            .find(|hashfn| hashfn.name() == name)
+    }
+}
+
+/// RISC Zero prover settings. Unset ones fall back to the server's `PROVER_*` settings,
+/// and those to RISC Zero's defaults
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProverOptions {
+    /// log2 of the cycles per segment, 14 to 22. Smaller segments prove in less memory,
+    /// but there are more of them
+    #[serde(default)]
+    pub segment_po2: Option<u32>,
+    #[serde(default)]
+    pub hashfn: Option<HashFn>,
+}
+
+/// The prover settings a proof was made with, recorded so it can be made again alike
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, JsonSchema)]
+pub struct EffectiveProverOptions {
+    pub segment_po2: u32,
+    pub hashfn: HashFn,
+}
+
+impl Default for EffectiveProverOptions {
+    fn default() -> Self {
+        Self {
+            segment_po2: crate::prover::DEFAULT_SEGMENT_PO2,
+            hashfn: HashFn::default(),
+        }
+    }
+}
+
+/// Provenance of the banned list the proofs were drawn from
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ListInfo {
+    pub source_uri: String,
+    pub publisher: String,
+    pub version: String,
+    /// Number of keys checked against the list; must equal the number of proofs
+    pub entry_count: u64,
+}
+
+#[derive(Deserialize, JsonSchema)]
 pub struct ProveCompactMerkleRequest {
     pub depth: usize,
+    /// Hex root; may be omitted when `root_alias` is given
+    #[serde(default)]
     pub root: String,
+    /// Named root resolved through the configured alias registry
+    #[serde(default)]
+    pub root_alias: Option<String>,
     pub merkle_proofs: Vec<CompactMerkleProof>,
+    /// Encoding of `merkle_proofs`; never guessed from their shape
+    #[serde(default)]
+    pub proof_encoding: ProofEncoding,
+    /// Pin every nondeterministic input so repeated runs produce identical journals
+    #[serde(default)]
+    pub reproducible: bool,
+    /// Unix timestamp (seconds) used instead of the system clock; required when `reproducible`
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+    /// Strict mode: the proofs must cover exactly these purls; they are proven in this order
+    #[serde(default)]
+    pub expected_purls: Option<Vec<String>>,
+    /// Strict mode: the banned_list_hash of the proven purls must equal this hex hash
+    #[serde(default)]
+    pub expected_banned_list_hash: Option<String>,
+    /// Where the banned list came from; its hash is committed in the journal
+    #[serde(default)]
+    pub list_info: Option<ListInfo>,
+    /// Largest response body the client wants; a larger one links to the proof instead.
+    /// Can only tighten the server's MAX_RESPONSE_BYTES
+    #[serde(default)]
+    pub max_response_bytes: Option<usize>,
+    /// Hex 32-byte secret that blinds the committed banned_list_hash. Only its hash is
+    /// committed and returned; the salt itself is never stored
+    #[serde(default)]
+    pub banned_list_salt: Option<String>,
+    /// Statement to prove: that none of the keys are in the tree (`non_membership`, value 0
+    /// in every proof) or that all of them are (`membership`, nonzero values)
+    #[serde(default)]
+    pub mode: ProofType,
+    /// Receipt to produce; `succinct` and `groth16` also return the seal separately for
+    /// on-chain verification
+    #[serde(default)]
+    pub receipt_kind: ReceiptKind,
+    /// Prove even when a proof does not fold up to `root` on the host, which yields a
+    /// non-compliant attestation; such requests are refused before proving by default
+    #[serde(default)]
+    pub skip_preverify: bool,
+    /// Prove even when an identical request was proven recently, instead of answering
+    /// with that proof from the cache
+    #[serde(default)]
+    pub force: bool,
+    /// Prover settings for this request only, e.g. smaller segments for a large batch.
+    /// Refused by backends other than the local zkVM
+    #[serde(default)]
+    pub prover_opts: Option<ProverOptions>,
+    /// Template the request was built from; set only by `POST /templates/{name}/run`
+    #[serde(skip)]
+    pub template: Option<TemplateRef>,
+    /// Id of the API key that signed the request; set only by the server once the
+    /// signature checked out
+    #[serde(skip)]
+    pub requester: Option<String>,
+    /// Key the request's tenant seals its stored keys under; set by the server when the
+    /// tenant's keys are redacted
+    #[serde(skip)]
+    pub redaction: Option<RedactionKey>,
 }
 
-#[derive(Serialize, Deserialize)]
+impl ProveCompactMerkleRequest {
+    /// How this request's keys may appear in logs and error messages
+    pub fn key_display(&self) -> KeyDisplay {
+        if self.redaction.is_some() {
+            KeyDisplay::Redacted
+        } else {
+            KeyDisplay::Plain
+        }
+    }
+}
+
+/// A template version, as recorded in the proofs run from it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TemplateRef {
+    pub name: String,
+    pub version: u32,
+}
+
+/// Where the proofs of a template run come from
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProofSource {
+    /// Proofs of these purls, drawn from the loaded SMT snapshot and proven in this order
+    Snapshot { purls: Vec<String> },
+    /// Every run supplies its own `merkle_proofs`
+    Supplied,
+}
+
+/// Body of `POST /templates`: a named, partial prove request for a recurring job. Runs
+/// add the root and, for `supplied` proofs, the proofs themselves
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct TemplateDefinition {
+    /// 1 to 64 ASCII letters, digits, `-` and `_`
+    pub name: String,
+    pub source: ProofSource,
+    #[serde(default)]
+    pub mode: ProofType,
+    #[serde(default)]
+    pub receipt_kind: ReceiptKind,
+    /// Root aliases runs may prove against; when not empty, every run must name one of
+    /// them and cannot give a hex root
+    #[serde(default)]
+    pub root_aliases: Vec<String>,
+    /// Strict mode for `supplied` proofs: every run must cover exactly these purls. A
+    /// `snapshot` source implies its purls
+    #[serde(default)]
+    pub expected_purls: Option<Vec<String>>,
+    /// Strict mode: the banned_list_hash of every run must equal this hex hash
+    #[serde(default)]
+    pub expected_banned_list_hash: Option<String>,
+    /// Provenance committed by runs that do not bring their own
+    #[serde(default)]
+    pub list_info: Option<ListInfo>,
+}
+
+/// A stored template version, as returned by `POST /templates` and `GET /templates/{name}`
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ProofTemplate {
+    /// 1 for a new name, one more than the latest version on every update
+    pub version: u32,
+    /// Unix timestamp (seconds) the version was stored
+    pub created_at: u64,
+    #[serde(flatten)]
+    pub definition: TemplateDefinition,
+}
+
+/// Body of `POST /templates/{name}/run`: the parts of the request that change per run
+#[derive(Deserialize, JsonSchema)]
+pub struct TemplateRun {
+    /// Hex root; may be omitted when `root_alias` is given
+    #[serde(default)]
+    pub root: String,
+    #[serde(default)]
+    pub root_alias: Option<String>,
+    /// Required by templates with `supplied` proofs and refused by the others
+    #[serde(default)]
+    pub merkle_proofs: Option<Vec<CompactMerkleProof>>,
+    /// Encoding of `merkle_proofs`
+    #[serde(default)]
+    pub proof_encoding: ProofEncoding,
+    /// Replaces the template's `list_info` for this run, e.g. with this week's version
+    #[serde(default)]
+    pub list_info: Option<ListInfo>,
+    #[serde(default)]
+    pub max_response_bytes: Option<usize>,
+    /// Prove even when an identical run was proven recently
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Body of `POST /whatif`: a candidate banned list to replay stored proofs against
+#[derive(Deserialize, JsonSchema)]
+pub struct WhatIfRequest {
+    /// Purls the candidate list would ban
+    pub purls: Vec<String>,
+    /// Only proofs with a timestamp at or after this Unix time
+    #[serde(default)]
+    pub since: Option<u64>,
+    /// Only proofs with a timestamp at or before this Unix time
+    #[serde(default)]
+    pub until: Option<u64>,
+}
+
+/// How stored proofs would fare under a candidate banned list
+#[derive(Serialize, JsonSchema)]
+pub struct WhatIfReport {
+    /// Distinct purls in the candidate list
+    pub candidate_purls: usize,
+    /// Stored proofs in the time window
+    pub scanned: usize,
+    /// Compliant non-membership proofs among them, the only ones a banned list can flip
+    pub evaluated: usize,
+    /// Evaluated proofs with a key the candidate list bans
+    pub flipped: usize,
+    /// Per proven root, most flipped first
+    pub roots: Vec<WhatIfRoot>,
+    /// Distinct keys that caused a flip, sorted, at most `WHATIF_SAMPLE_SIZE` of them
+    pub affected_purls: Vec<String>,
+    /// More keys caused a flip than `affected_purls` lists
+    pub affected_purls_truncated: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct WhatIfRoot {
+    pub root_hash: String,
+    /// Alias an evaluated proof of this root was requested by, if any was
+    pub root_alias: Option<String>,
+    pub evaluated: usize,
+    pub flipped: usize,
+}
+
+/// Body of `POST /generate-proofs`: a banned list to build the tree from and the SBOM
+/// purls to prove against it
+#[derive(Deserialize, JsonSchema)]
+pub struct GenerateProofsRequest {
+    /// Every entry of the banned list; a later duplicate purl overrides an earlier one
+    pub banned: Vec<BannedPurl>,
+    /// SBOM purls to produce non-membership proofs for
+    pub purls: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct BannedPurl {
+    pub purl: String,
+    /// Reason code stored in the purl's leaf; must be nonzero. `1` (security) when absent
+    #[serde(default = "default_banned_value")]
+    pub value: u64,
+}
+
+fn default_banned_value() -> u64 {
+    1
+}
+
+/// The tree built from a banned list and proofs of the queried purls, ready to send to
+/// `POST /prove-merkle-compact` as `depth`, `root` and `merkle_proofs`
+#[derive(Serialize, JsonSchema)]
+pub struct GenerateProofsResponse {
+    pub depth: usize,
+    /// Hex root of the tree built from the banned list
+    pub root: String,
+    /// Distinct purls in the banned list
+    pub banned_count: usize,
+    /// Non-membership proofs of the queried purls the list does not ban, in request order
+    pub merkle_proofs: Vec<CompactMerkleProof>,
+    /// Queried purls the list bans; no proof is produced for them
+    pub banned: Vec<BannedMatch>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct BannedMatch {
+    pub purl: String,
+    pub value: u64,
+    /// Name of the reason code, e.g. `security`
+    pub reason: String,
+}
+
+/// Body of `POST /proofs/import`: proof documents made elsewhere, each imported or
+/// rejected on its own
+#[derive(Deserialize, JsonSchema)]
+pub struct ImportProofsRequest {
+    /// Parsed one at a time, so a malformed document is reported without failing the rest
+    #[schemars(with = "Vec<ImportedProof>")]
+    pub proofs: Vec<serde_json::Value>,
+}
+
+/// A proof to import, bare or in the `sbom-proof` envelope. A proof record as
+/// `GET /proofs/{id}` returns it is one, provided its receipt is inline. What the receipt
+/// commits to is read from its journal; the other fields are taken as given once they
+/// agree with it.
+#[derive(Deserialize, JsonSchema)]
+pub struct ImportedProof {
+    /// Base64 receipt, as in `ProofArtifact::proof`
+    pub proof: String,
+    /// Must be the image ID this instance proves with
+    pub image_id: Vec<String>,
+    /// Hex canonical digest of the request the receipt was proven for; with the image ID
+    /// and the journal it determines the proof id
+    pub request_digest: String,
+    /// Keys checked, in proving order; as many as the journal's proof count when given
+    #[serde(default)]
+    pub purls: Vec<String>,
+    #[serde(default)]
+    pub reasons: Vec<ImportedReason>,
+    /// Checked against the journal's list info hash when given
+    pub list_info: Option<ListInfo>,
+    /// When the proof was made; the time of the import when absent
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ImportedReason {
+    pub purl: String,
+    pub leaf_index: String,
+    pub value: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ImportProofsResponse {
+    pub imported: usize,
+    pub duplicates: usize,
+    pub invalid: usize,
+    /// One per submitted document, in request order
+    pub results: Vec<ImportResult>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ImportResult {
+    pub index: usize,
+    pub status: ImportStatus,
+    /// Id the proof is stored under; absent when the document is invalid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Imported,
+    /// A proof with the same id is already stored; it is left as it is
+    Duplicate,
+    Invalid,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct MerklePublicInputs {
     pub root_hash: [u8; 32],
+    /// Checked in-circuit against the banned_list_hash of the proofs when set
+    pub expected_banned_list_hash: Option<[u8; 32]>,
+    pub list_info: Option<ListInfo>,
+    /// Sent to the guest in its own section rather than as part of this value
+    #[serde(skip)]
+    pub blinding_salt: Option<[u8; 32]>,
+    /// Sent to the guest in its own section, and only for membership proofs
+    #[serde(skip)]
+    pub proof_type: ProofType,
+    /// Never sent to the guest; selects the receipt the prover compresses to
+    #[serde(skip)]
+    pub receipt_kind: ReceiptKind,
+    /// Sent to the guest in its own section, and only for authenticated requests
+    #[serde(skip)]
+    pub requester_commitment: Option<[u8; 32]>,
+    /// Never sent to the guest; proves with the options tuned for small executions
+    #[serde(skip)]
+    pub fast_path: bool,
+    /// Never sent to the guest; the settings the local zkVM proves with
+    #[serde(skip)]
+    pub prover_opts: EffectiveProverOptions,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -26,4 +561,248 @@ pub struct MerklePublicOutputs {
     pub root_hash: [u8; 32],
     pub banned_list_hash: [u8; 32],
     pub compliant: bool,
+    pub proof_count: u32,
+    pub reasons_hash: [u8; 32],
+    pub list_info_hash: [u8; 32],
+    /// Nonzero when the guest rejected the input framing; see `sbom_common::input::status`
+    pub input_status: u32,
+    /// Hash of the blinding salt; all zero when banned_list_hash is not blinded
+    pub salt_commitment: [u8; 32],
+    /// `KeyType::code` of the keys every proof was bound to
+    pub key_type: u32,
+    /// `ProofType::code` of the statement `compliant` attests
+    pub proof_type: u32,
+    /// Digest of the guest's DEFAULTS table; `sbom_common::DEFAULTS_DIGEST` for this build
+    pub defaults_digest: [u8; 32],
+    /// Commitment to the API key id the request was authenticated under; all zero when it
+    /// was not
+    pub requester_commitment: [u8; 32],
+    /// `sbom_common::failure` code of the first proof that kept the request from being
+    /// compliant; 0 when it is compliant
+    pub first_failure: u32,
+    pub first_failure_index: u32,
+    pub first_failure_key_hash: [u8; 32],
+}
+
+/// A generated proof as returned to callers and persisted by the proof store.
+/// Fields are serialized in this order, which is the persisted record format.
+#[derive(Serialize, Clone, JsonSchema)]
+pub struct ProofArtifact {
+    pub banned_list_hash: String,
+    /// Answered from the proof cache: an identical request was proven recently and this
+    /// is that proof, so `generation_duration_ms` is 0. Always false in stored records
+    pub cached: bool,
+    pub compliant: bool,
+    /// Digest of the default hash table the guest verified against, as committed in the journal
+    pub defaults_digest: String,
+    /// The receipt is a fake from a prover in dev mode and proves nothing; set from the
+    /// receipt itself, so also for fakes from other provers or imports
+    pub dev_mode: bool,
+    /// The request had no more proofs than `FAST_PATH_MAX_PROOFS` and took the fast path:
+    /// validated on one thread and proven in small segments, compressed to a succinct
+    /// receipt unless a Groth16 one was requested
+    pub fast_path: bool,
+    /// Why the request is not compliant, as committed in the journal; `None` when it is
+    pub first_failure: Option<FirstFailure>,
+    /// Wall-clock time spent proving, retries included; excludes waiting for a prover
+    pub generation_duration_ms: u64,
+    pub image_id: Vec<String>,
+    /// The receipt was proven elsewhere and imported with `POST /proofs/import`; the
+    /// proving fields then describe the import, and `purls` and `reasons` are as submitted
+    pub imported: bool,
+    /// API key id the import was signed with; `None` for proofs made here and for
+    /// unsigned imports
+    pub imported_by: Option<String>,
+    /// Hex journal bytes, as in `receipt.journal.bytes`; lets consumers read the outputs
+    /// without decoding `proof`
+    pub journal: String,
+    /// Kind of identifier in `purls` and `reasons`
+    pub key_type: KeyType,
+    /// Leaf index of each of `purls`; present only in records whose keys are sealed
+    pub leaf_indexes: Option<Vec<String>>,
+    pub list_info: Option<ListInfo>,
+    /// Present only when the request carried `list_info`
+    pub list_info_hash: Option<String>,
+    /// Base64 receipt; `None` when it exceeded the inline limit and `proof_url` links to it
+    pub proof: Option<String>,
+    pub proof_count: u32,
+    /// Key of the proof in the store and the retrieval endpoints: derived from the request
+    /// digest, the image ID and the journal (see `digest::proof_id`), so re-proving an
+    /// identical request with the same guest image yields the same id
+    pub proof_id: String,
+    pub proof_size: usize,
+    /// Statement `compliant` attests, as requested by `mode`
+    pub proof_type: ProofType,
+    pub proof_url: Option<String>,
+    /// How many times the prover ran; more than 1 when transient failures were retried
+    pub prove_attempts: u32,
+    /// Backend that produced the receipt: "local", "bonsai" or "custom", or "imported"
+    pub prover_backend: &'static str,
+    /// Settings the local zkVM proved with; `None` for other backends and imports
+    pub prover_opts: Option<EffectiveProverOptions>,
+    /// Remote session that produced the receipt; `None` for local proving
+    pub prover_session_id: Option<String>,
+    /// Every key checked, in proving order: the list `banned_list_hash` commits to, which a
+    /// verifier recomputes when it is passed as `banned_list`. Named for the default key
+    /// type; see `key_type`. Only previews in records whose keys are sealed
+    pub purls: Vec<String>,
+    pub reasons: Vec<BannedReason>,
+    pub reasons_hash: String,
+    /// Receipt actually produced: "composite", "succinct", "groth16", or "fake" in dev mode
+    pub receipt_kind: &'static str,
+    /// SHA-256 (hex) of the receipt; names the stored payload when it is linked
+    pub receipt_sha256: String,
+    pub reproducible: bool,
+    /// Present when an identical request was proven before by another guest image: the
+    /// latest such proof, which stays stored under its own id and image ID
+    pub reproves: Option<EarlierProof>,
+    /// Canonical digest of the request (hex); see `digest::canonical_request_digest`
+    pub request_digest: String,
+    /// UUIDv7 assigned when the request arrived; unique per proving run
+    pub request_id: String,
+    /// Present only when the request was signed with an API key; the journal commits all
+    /// zeros otherwise
+    pub requester_commitment: Option<String>,
+    pub root_alias: Option<String>,
+    pub root_hash: String,
+    /// Present only when the request carried `banned_list_salt`
+    pub salt_commitment: Option<String>,
+    /// Hex seal of a succinct or Groth16 receipt; `None` for a composite one, which has a
+    /// seal per segment. A Groth16 seal is prefixed with the 4-byte verifier selector, as the RISC Zero
+    /// verifier contracts expect
+    pub seal: Option<String>,
+    /// `purls` and the keys of `reasons` sealed under the tenant's redaction key; present
+    /// only in records of tenants whose keys are redacted
+    pub sealed_purls: Option<SealedKeys>,
+    /// Host-side sequence number, increasing across restarts; orders proofs that share a
+    /// timestamp or were stamped by a clock that stepped back
+    pub sequence: u64,
+    /// Template version the request was run from, if any
+    pub template: Option<TemplateRef>,
+    pub timestamp: u64,
+    /// The receipt as RISC Zero serde words flattened to little-endian bytes
+    #[serde(skip)]
+    pub receipt_bytes: Vec<u8>,
+}
+
+/// A stored proof of the same request made by a different guest image
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct EarlierProof {
+    pub proof_id: String,
+    pub image_id: Vec<String>,
+}
+
+/// The first proof the guest found keeping a request from being compliant
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct FirstFailure {
+    /// `sbom_common::failure::name` of the committed code, e.g. "bad_hex" or "root_mismatch"
+    pub reason: String,
+    /// Position of the failing proof in the request; absent when no single proof failed,
+    /// as for unparsable proofs or a strict-mode list mismatch
+    pub index: Option<u32>,
+    /// Leaf index of the failing proof's key (`compute_purl_hash` for a purl); absent
+    /// with `index` or when the key maps to no leaf
+    pub key_hash: Option<String>,
+}
+
+impl FirstFailure {
+    pub fn from_outputs(outputs: &MerklePublicOutputs) -> Option<Self> {
+        if outputs.first_failure == failure::NONE {
+            return None;
+        }
+        let names_proof = failure::names_proof(outputs.first_failure);
+        Some(Self {
+            reason: failure::name(outputs.first_failure).to_string(),
+            index: names_proof.then_some(outputs.first_failure_index),
+            key_hash: (names_proof && outputs.first_failure_key_hash != [0u8; 32])
+                .then(|| hex::encode(outputs.first_failure_key_hash)),
+        })
+    }
+}
+
+/// The fields of a persisted [`ProofArtifact`] that `GET /proofs` lists
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ProofSummary {
+    pub proof_id: String,
+    pub timestamp: u64,
+    /// 0 for records written before sequence numbers were assigned
+    #[serde(default)]
+    pub sequence: u64,
+    pub root_hash: String,
+    pub compliant: bool,
+    /// Absent from records written before the duration was recorded
+    pub generation_duration_ms: Option<u64>,
+    /// Guest image the proof was made with, which verifying it needs
+    #[serde(default)]
+    pub image_id: Vec<String>,
+    /// The proof was imported rather than made here
+    #[serde(default)]
+    pub imported: bool,
+}
+
+/// Reason code carried by a banned (value-carrying) leaf
+#[derive(Serialize, Clone, JsonSchema)]
+pub struct BannedReason {
+    pub purl: String,
+    pub leaf_index: String,
+    pub value: u64,
+    pub reason: &'static str,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ProofValidationResult {
+    pub index: usize,
+    pub purl: String,
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub computed_root: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ValidateCompactMerkleResponse {
+    pub valid: bool,
+    /// Always false: this result comes from host-side recomputation, not from a receipt
+    pub cryptographic: bool,
+    pub root_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root_alias: Option<String>,
+    pub banned_list_hash: String,
+    /// Kind of identifier the proofs are keyed by
+    pub key_type: KeyType,
+    pub proof_count: usize,
+    /// Statement `compliant` refers to, as requested by `mode`
+    pub proof_type: ProofType,
+    /// True only if every proof is valid and its value is one `proof_type` accepts
+    pub compliant: bool,
+    /// Commitment the guest would produce over (leaf_index, value); present only when `valid`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasons_hash: Option<String>,
+    pub reasons: Vec<BannedReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_info: Option<ListInfo>,
+    /// Commitment the guest would produce over `list_info`; present only when it was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_info_hash: Option<String>,
+    /// Hash of the salt blinding `banned_list_hash`; present only when one was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salt_commitment: Option<String>,
+    pub results: Vec<ProofValidationResult>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA_GOLDEN: &str = include_str!("../../docs/api/proving-service.schema.json");
+    const TYPESCRIPT_GOLDEN: &str = include_str!("../../docs/api/proving-service.d.ts");
+
+    #[test]
+    fn the_checked_in_definitions_match_the_models() {
+        let schema = api_schema();
+        let golden: serde_json::Value = serde_json::from_str(SCHEMA_GOLDEN).unwrap();
+        assert_eq!(serde_json::to_value(schema.json_schema()).unwrap(), golden);
+        assert_eq!(schema.typescript(), TYPESCRIPT_GOLDEN);
+    }
 }
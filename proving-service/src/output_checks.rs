@@ -0,0 +1,188 @@
+//! Sanity checks on a prover's output before the proof is persisted or served.
+//!
+//! `prover::finish_receipt` matches a receipt's journal against the request, but a custom
+//! backend need not, and a guest bug could commit a journal that still decodes into
+//! `MerklePublicOutputs` without being one this host understands, for instance one that
+//! echoes its input after the outputs. [`check_output`] therefore holds the raw journal
+//! to `sbom_common::journal::MERKLE_PUBLIC_OUTPUTS` and the decoded outputs to the
+//! request, whichever backend proved them. Output that fails is quarantined by the
+//! caller, never stored or returned.
+
+use sbom_common::journal::{
+    JournalLayout, MERKLE_PUBLIC_OUTPUTS, MERKLE_PUBLIC_OUTPUTS_V1, MERKLE_PUBLIC_OUTPUTS_V2,
+    MERKLE_PUBLIC_OUTPUTS_V3, MERKLE_PUBLIC_OUTPUTS_V4, MERKLE_PUBLIC_OUTPUTS_V5,
+    MERKLE_PUBLIC_OUTPUTS_V6, MERKLE_PUBLIC_OUTPUTS_V7, MERKLE_PUBLIC_OUTPUTS_V8,
+    MERKLE_PUBLIC_OUTPUTS_V9, MERKLE_PUBLIC_OUTPUTS_V10,
+};
+use sbom_common::{failure, input};
+
+use crate::digest::encode_journal;
+use crate::models::{CompactMerkleProof, MerklePublicInputs};
+use crate::prover::GeneratedProof;
+use crate::validation::list_key_type;
+
+/// Layouts committed by older guests, newest first; recognized only to name them when
+/// one turns up
+const PREVIOUS_LAYOUTS: [JournalLayout; 10] = [
+    MERKLE_PUBLIC_OUTPUTS_V10,
+    MERKLE_PUBLIC_OUTPUTS_V9,
+    MERKLE_PUBLIC_OUTPUTS_V8,
+    MERKLE_PUBLIC_OUTPUTS_V7,
+    MERKLE_PUBLIC_OUTPUTS_V6,
+    MERKLE_PUBLIC_OUTPUTS_V5,
+    MERKLE_PUBLIC_OUTPUTS_V4,
+    MERKLE_PUBLIC_OUTPUTS_V3,
+    MERKLE_PUBLIC_OUTPUTS_V2,
+    MERKLE_PUBLIC_OUTPUTS_V1,
+];
+
+/// Check `generated`, proven for `proofs` and `public_inputs`, and say why it must not
+/// be served if it fails. The journal length is checked first, so an oversized journal
+/// is refused without being read further.
+pub(crate) fn check_output(
+    generated: &GeneratedProof,
+    proofs: &[CompactMerkleProof],
+    public_inputs: &MerklePublicInputs,
+    max_journal_bytes: usize,
+) -> Result<(), String> {
+    let journal = &generated.journal;
+    if journal.len() > max_journal_bytes {
+        return Err(format!(
+            "journal of {} bytes exceeds MAX_JOURNAL_BYTES ({})",
+            journal.len(),
+            max_journal_bytes
+        ));
+    }
+
+    let layout = &MERKLE_PUBLIC_OUTPUTS;
+    if journal.len() != layout.total_bytes() {
+        return Err(
+            match PREVIOUS_LAYOUTS
+                .iter()
+                .find(|previous| previous.total_bytes() == journal.len())
+            {
+                Some(previous) => format!(
+                    "journal of {} bytes has the layout of journal schema version {}; this host reads version {}",
+                    journal.len(),
+                    previous.schema_version,
+                    layout.schema_version
+                ),
+                None => format!(
+                    "journal of {} bytes matches no known layout; journal schema version {} has {} bytes",
+                    journal.len(),
+                    layout.schema_version,
+                    layout.total_bytes()
+                ),
+            },
+        );
+    }
+
+    // Every field is a run of little-endian words; bytes and bools must fit theirs
+    let mut words = journal
+        .chunks_exact(4)
+        .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]));
+    for field in layout.fields {
+        for word in words.by_ref().take(field.words) {
+            let in_range = match field.rust_type {
+                "bool" => word <= 1,
+                "u32" => true,
+                "[u8; 32]" => word <= u32::from(u8::MAX),
+                other => {
+                    return Err(format!(
+                        "journal field {} has type {}, which this host cannot check",
+                        field.name, other
+                    ));
+                }
+            };
+            if !in_range {
+                return Err(format!(
+                    "journal field {} holds word {:#x}, out of range for {}",
+                    field.name, word, field.rust_type
+                ));
+            }
+        }
+    }
+
+    let outputs = &generated.outputs;
+    if encode_journal(outputs) != *journal {
+        return Err("the decoded outputs do not encode back to the committed journal".to_string());
+    }
+    if outputs.input_status != input::status::OK {
+        return Err(format!(
+            "the guest rejected the input: {} (status {})",
+            input::status::name(outputs.input_status),
+            outputs.input_status
+        ));
+    }
+    if outputs.root_hash != public_inputs.root_hash {
+        return Err(format!(
+            "root hash {} was committed but {} was requested",
+            hex::encode(outputs.root_hash),
+            hex::encode(public_inputs.root_hash)
+        ));
+    }
+    if outputs.proof_count as usize != proofs.len() {
+        return Err(format!(
+            "proof count {} was committed but {} proofs were sent",
+            outputs.proof_count,
+            proofs.len()
+        ));
+    }
+    if outputs.compliant != (outputs.first_failure == failure::NONE) {
+        return Err(format!(
+            "compliant is {} but first failure {} ({}) was committed",
+            outputs.compliant,
+            failure::name(outputs.first_failure),
+            outputs.first_failure
+        ));
+    }
+    if failure::names_proof(outputs.first_failure)
+        && outputs.first_failure_index >= outputs.proof_count
+    {
+        return Err(format!(
+            "first failure names proof {} but only {} proofs were committed",
+            outputs.first_failure_index, outputs.proof_count
+        ));
+    }
+    if outputs.key_type != list_key_type(proofs).code() {
+        return Err(format!(
+            "key type {} was committed but the proofs are keyed by {}",
+            outputs.key_type,
+            list_key_type(proofs).name()
+        ));
+    }
+    if outputs.proof_type != public_inputs.proof_type.code() {
+        return Err(format!(
+            "proof type {} was committed but {} was requested",
+            outputs.proof_type,
+            public_inputs.proof_type.name()
+        ));
+    }
+    let expected_salt_commitment = public_inputs
+        .blinding_salt
+        .as_ref()
+        .map_or([0u8; 32], sbom_common::compute_salt_commitment);
+    if outputs.salt_commitment != expected_salt_commitment {
+        return Err(format!(
+            "salt commitment {} was committed but {} was expected",
+            hex::encode(outputs.salt_commitment),
+            hex::encode(expected_salt_commitment)
+        ));
+    }
+    let expected_requester_commitment = public_inputs.requester_commitment.unwrap_or([0u8; 32]);
+    if outputs.requester_commitment != expected_requester_commitment {
+        return Err(format!(
+            "requester commitment {} was committed but {} was expected",
+            hex::encode(outputs.requester_commitment),
+            hex::encode(expected_requester_commitment)
+        ));
+    }
+    if outputs.defaults_digest != sbom_common::DEFAULTS_DIGEST {
+        return Err(format!(
+            "defaults digest {} was committed but this build's is {}",
+            hex::encode(outputs.defaults_digest),
+            hex::encode(sbom_common::DEFAULTS_DIGEST)
+        ));
+    }
+    Ok(())
+}
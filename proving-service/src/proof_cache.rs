@@ -0,0 +1,119 @@
+//! Deduplication of identical prove requests.
+//!
+//! A retried build submits the same request again, and proving it again takes as long as
+//! the first time. The [`ProofCache`] remembers for a while which proof each request
+//! produced, keyed by [`cache_key`], so the service can answer a repeat with that proof,
+//! its receipt read back from the proof store. Entries live in memory only: a restart
+//! empties the cache, while the proofs stay in the store.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+
+use crate::models::ProofArtifact;
+use crate::utils::compute_requester_commitment;
+
+/// Domain separator of [`cache_key`] for signed requests
+const DOMAIN: &[u8] = b"sbom-proof-cache/v1";
+
+/// Cache key of a request with canonical digest `request_digest`, signed with the API key
+/// `requester` if any. The proof commits to the key id, so the same request signed by
+/// another key, or not at all, is a different entry.
+pub fn cache_key(request_digest: &[u8; 32], requester: Option<&str>) -> [u8; 32] {
+    let Some(requester) = requester else {
+        return *request_digest;
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(DOMAIN);
+    hasher.update(request_digest);
+    hasher.update(compute_requester_commitment(requester));
+    hasher.finalize().into()
+}
+
+/// Proofs of recent requests by [`cache_key`], at most a configured number of them for a
+/// configured time. Both limits are passed on each call, so a reload applies at once.
+#[derive(Default)]
+pub struct ProofCache {
+    entries: Mutex<HashMap<[u8; 32], Entry>>,
+}
+
+struct Entry {
+    /// Without `receipt_bytes`, which the proof store holds
+    artifact: ProofArtifact,
+    stored_at: Instant,
+}
+
+impl ProofCache {
+    /// The proof cached for `key` if it is younger than `ttl`. Its `receipt_bytes` are
+    /// empty; the caller reads the receipt from the proof store.
+    pub fn get(&self, key: &[u8; 32], ttl: Duration) -> Option<ProofArtifact> {
+        let mut entries = self.lock();
+        match entries.get(key) {
+            Some(entry) if entry.stored_at.elapsed() < ttl => Some(entry.artifact.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Remember `artifact` as the proof of `key`. Expired entries are dropped first, then
+    /// the oldest ones while the cache is full; with `max_entries` 0 nothing is kept.
+    pub fn insert(
+        &self,
+        key: [u8; 32],
+        artifact: &ProofArtifact,
+        max_entries: usize,
+        ttl: Duration,
+    ) {
+        let mut entries = self.lock();
+        entries.retain(|_, entry| entry.stored_at.elapsed() < ttl);
+        if max_entries == 0 {
+            entries.clear();
+            return;
+        }
+        entries.remove(&key);
+        while entries.len() >= max_entries {
+            let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            entries.remove(&oldest);
+        }
+
+        let artifact = ProofArtifact {
+            receipt_bytes: Vec::new(),
+            ..artifact.clone()
+        };
+        entries.insert(
+            key,
+            Entry {
+                artifact,
+                stored_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Forget the proof of `key`, e.g. once its record is gone from the proof store
+    pub fn remove(&self, key: &[u8; 32]) {
+        self.lock().remove(key);
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<[u8; 32], Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
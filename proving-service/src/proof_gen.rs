@@ -0,0 +1,95 @@
+//! Proofs generated on the host from a banned list.
+//!
+//! `POST /generate-proofs` spares a client the merkle-proof-service round trip: it builds
+//! the sparse Merkle tree of a banned list, keyed by `compute_purl_hash` like the Go
+//! service's, and proves the queried SBOM purls against it. A queried purl the list bans
+//! is reported instead of being proven, since its proof would be a membership proof that
+//! fails a non-membership prove request anyway.
+
+use std::collections::HashSet;
+
+use sbom_common::KeyType;
+use sbom_common::smt::{SparseMerkleTree, TREE_DEPTH};
+
+use crate::error::{Error, Result};
+use crate::models::{
+    BannedMatch, CompactMerkleProof, GenerateProofsRequest, GenerateProofsResponse,
+};
+use crate::utils::reason_name;
+
+/// Build the tree of the request's banned list and prove each distinct queried purl
+/// against it, in request order. At most `max_proofs` purls may be queried.
+pub fn generate(
+    request: &GenerateProofsRequest,
+    max_proofs: usize,
+) -> Result<GenerateProofsResponse> {
+    if request.purls.is_empty() {
+        let err_msg = "The request must name at least one purl to prove";
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg.to_string()));
+    }
+    if request.purls.len() > max_proofs {
+        let err = Error::LimitExceeded {
+            setting: "MAX_PROOFS_PER_REQUEST",
+            unit: "proofs",
+            limit: max_proofs,
+            observed: Some(request.purls.len()),
+        };
+        tracing::error!("{}", err);
+        return Err(err);
+    }
+    if let Some(entry) = request.banned.iter().find(|entry| entry.value == 0) {
+        let err_msg = format!(
+            "Banned purl '{}' has value 0, which marks a purl as not banned",
+            entry.purl
+        );
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg));
+    }
+
+    // Inserting leaf by leaf stays iterative, so large lists need no loader-sized stack
+    let mut tree = SparseMerkleTree::default();
+    for entry in &request.banned {
+        tree.insert(&entry.purl, entry.value);
+    }
+
+    let mut seen = HashSet::new();
+    let mut merkle_proofs = Vec::new();
+    let mut banned = Vec::new();
+    for purl in &request.purls {
+        if !seen.insert(purl.as_str()) {
+            continue;
+        }
+        let proof = tree.prove(purl);
+        if proof.value != 0 {
+            banned.push(BannedMatch {
+                purl: purl.clone(),
+                value: proof.value,
+                reason: reason_name(proof.value).to_string(),
+            });
+            continue;
+        }
+        merkle_proofs.push(CompactMerkleProof {
+            key: purl.clone(),
+            key_type: KeyType::Purl,
+            value: proof.value.to_string(),
+            leaf_index: hex::encode(proof.leaf_index),
+            siblings: proof.siblings.iter().map(hex::encode).collect(),
+            bitmap: hex::encode(proof.bitmap),
+        });
+    }
+
+    tracing::info!(
+        "Generated {} proofs against a {}-entry banned list; {} queried purls are banned",
+        merkle_proofs.len(),
+        tree.len(),
+        banned.len()
+    );
+    Ok(GenerateProofsResponse {
+        depth: TREE_DEPTH,
+        root: hex::encode(tree.root()),
+        banned_count: tree.len(),
+        merkle_proofs,
+        banned,
+    })
+}
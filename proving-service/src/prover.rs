@@ -0,0 +1,726 @@
+//! Proof generation for the compact merkle guest, independent of the HTTP layer.
+//!
+//! The zkVM itself, [`ZkvmBackend`] and the guest helpers are only built with the
+//! `prover` feature, the remote [`crate::bonsai::BonsaiBackend`] with `bonsai`; the
+//! [`ProvingBackend`] trait is always available.
+
+#[cfg(feature = "prover")]
+use methods::{SBOM_VALIDATOR_ELF, SBOM_VALIDATOR_ID, SBOM_VALIDATOR_MANIFEST};
+#[cfg(feature = "prover")]
+use risc0_zkvm::{
+    ExecutorEnv, InnerReceipt, ProverOpts, Receipt, default_executor, default_prover,
+    serde::{from_slice, to_vec},
+};
+#[cfg(feature = "prover")]
+use sbom_common::input::{self, InputHeader, PROTOCOL_LEGACY, PROTOCOL_SECTIONS, Section, tag};
+#[cfg(feature = "prover")]
+use sbom_common::observability::{metric, metrics};
+#[cfg(feature = "prover")]
+use sbom_common::smt::TREE_DEPTH;
+#[cfg(feature = "prover")]
+use sbom_common::{DEFAULTS, DEFAULTS_DIGEST, KeyType, ProofType, compute_purl_hash};
+#[cfg(feature = "prover")]
+use serde::Deserialize;
+#[cfg(feature = "prover")]
+use std::fmt::Display;
+#[cfg(feature = "prover")]
+use std::time::Instant;
+
+use crate::error::Result;
+#[cfg(feature = "prover")]
+use crate::error::Error;
+#[cfg(feature = "prover")]
+use crate::models::ReceiptKind;
+use crate::models::{CompactMerkleProof, MerklePublicInputs, MerklePublicOutputs};
+
+/// Smallest segment size, as a power of two, the prover is asked for
+pub const MIN_SEGMENT_PO2: u32 = 14;
+/// Largest segment size the prover is asked for; RISC Zero's limit
+pub const MAX_SEGMENT_PO2: u32 = 22;
+/// Segment size when none is configured; RISC Zero's default
+pub const DEFAULT_SEGMENT_PO2: u32 = 20;
+/// Segment size of fast-path executions unless one is configured: a request of a few
+/// proofs runs in a handful of these, each proven and lifted faster and in less memory
+/// than a default-sized segment
+pub const FAST_PATH_SEGMENT_PO2: u32 = 18;
+
+/// The journal of a verified receipt, with the receipt in serialized form
+pub struct GeneratedProof {
+    pub outputs: MerklePublicOutputs,
+    /// The journal exactly as committed; `outputs` is its decoding
+    pub journal: Vec<u8>,
+    /// The receipt as RISC Zero serde words flattened to little-endian bytes
+    pub receipt_bytes: Vec<u8>,
+    /// Remote proving session that produced the receipt, for debugging
+    pub session_id: Option<String>,
+    /// Kind of the receipt, as named in `ProofArtifact::receipt_kind`
+    pub receipt_kind: &'static str,
+    /// Seal of a succinct or Groth16 receipt, encoded for on-chain verification
+    pub seal: Option<Vec<u8>>,
+}
+
+/// A receipt made elsewhere, verified against a backend's image ID
+pub struct VerifiedReceipt {
+    pub outputs: MerklePublicOutputs,
+    /// The journal exactly as committed; `outputs` is its decoding
+    pub journal: Vec<u8>,
+    /// Kind of the receipt, as named in `ProofArtifact::receipt_kind`
+    pub receipt_kind: &'static str,
+    /// Seal of a succinct or Groth16 receipt, encoded for on-chain verification
+    pub seal: Option<Vec<u8>>,
+}
+
+/// Turns validated compact proofs into a verified receipt.
+///
+/// [`ZkvmBackend`] proves in-process; embedders can substitute a remote prover or, in
+/// tests, a fixed result.
+pub trait ProvingBackend: Send + Sync {
+    /// Image ID of the guest whose receipts this backend produces
+    fn image_id(&self) -> [u32; 8];
+
+    /// Short name recorded with every proof this backend generates
+    fn name(&self) -> &'static str {
+        "custom"
+    }
+
+    /// Whether the backend proves with `MerklePublicInputs::prover_opts`. Requests that
+    /// set `prover_opts` are refused by backends that do not, rather than proven otherwise
+    fn takes_prover_opts(&self) -> bool {
+        false
+    }
+
+    fn prove(
+        &self,
+        proofs: &[CompactMerkleProof],
+        public_inputs: &MerklePublicInputs,
+    ) -> Result<GeneratedProof>;
+
+    /// Quick check that the backend can prove at all, for deep health checks; should
+    /// finish well within a second. Passes unless overridden
+    fn self_check(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Verify serialized receipt bytes, as in `GeneratedProof::receipt_bytes`, against
+    /// this backend's image ID and decode their journal, for importing receipts proven
+    /// elsewhere. Fails unless overridden
+    fn verify(&self, _receipt_bytes: &[u8]) -> Result<VerifiedReceipt> {
+        Err(crate::error::Error::NotConfigured(format!(
+            "The {} prover cannot verify receipts",
+            self.name()
+        )))
+    }
+}
+
+/// Proves with the default RISC Zero prover, which honours `RISC0_DEV_MODE`
+#[cfg(feature = "prover")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZkvmBackend;
+
+#[cfg(feature = "prover")]
+impl ProvingBackend for ZkvmBackend {
+    fn image_id(&self) -> [u32; 8] {
+        SBOM_VALIDATOR_ID
+    }
+
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn takes_prover_opts(&self) -> bool {
+        true
+    }
+
+    fn prove(
+        &self,
+        proofs: &[CompactMerkleProof],
+        public_inputs: &MerklePublicInputs,
+    ) -> Result<GeneratedProof> {
+        prove_compact(proofs, public_inputs)
+    }
+
+    fn self_check(&self) -> Result<()> {
+        execute_self_check()
+    }
+
+    fn verify(&self, receipt_bytes: &[u8]) -> Result<VerifiedReceipt> {
+        verify_receipt(receipt_bytes)
+    }
+}
+
+/// Execute the guest, without proving, on a single non-membership proof against the
+/// empty tree, and check that it accepts the input and commits this build's DEFAULTS.
+/// Exercises the ELF, input framing and the executor without the cost of a receipt.
+#[cfg(feature = "prover")]
+pub fn execute_self_check() -> Result<()> {
+    let purl = "pkg:generic/sbom-self-check@0";
+    let proofs = [CompactMerkleProof {
+        key: purl.to_string(),
+        key_type: KeyType::Purl,
+        value: "0".to_string(),
+        leaf_index: hex::encode(compute_purl_hash(purl)),
+        siblings: Vec::new(),
+        bitmap: hex::encode([0u8; 32]),
+    }];
+    let public_inputs = MerklePublicInputs {
+        root_hash: DEFAULTS[TREE_DEPTH],
+        expected_banned_list_hash: None,
+        list_info: None,
+        blinding_salt: None,
+        proof_type: ProofType::NonMembership,
+        receipt_kind: ReceiptKind::Composite,
+        requester_commitment: None,
+        fast_path: false,
+        prover_opts: Default::default(),
+    };
+
+    let (guest_input, _) = prepare_input(&proofs, &public_inputs)?;
+    let outputs = execute_guest(&guest_input)
+        .map_err(|e| Error::ProverFailure(format!("Guest self-check failed: {e}")))?;
+
+    if outputs.input_status != input::status::OK
+        || !outputs.compliant
+        || outputs.defaults_digest != DEFAULTS_DIGEST
+    {
+        return Err(Error::ProverFailure(format!(
+            "Guest self-check produced unexpected outputs: input_status={} ({}), compliant={}, defaults_digest={}",
+            outputs.input_status,
+            input::status::name(outputs.input_status),
+            outputs.compliant,
+            hex::encode(outputs.defaults_digest)
+        )));
+    }
+    Ok(())
+}
+
+/// Execute the guest on `input`, its input words as [`guest_input`] frames them, without
+/// proving, and decode its journal. In `RISC0_DEV_MODE` this is how the guest's verdict
+/// on an input is learned in milliseconds.
+#[cfg(feature = "prover")]
+pub fn execute_guest(input: &[u32]) -> Result<MerklePublicOutputs> {
+    let session = default_executor()
+        .execute(executor_env(input)?, SBOM_VALIDATOR_ELF)
+        .map_err(|e| Error::ProverFailure(format!("Guest execution failed: {e}")))?;
+    session
+        .journal
+        .decode()
+        .map_err(|e| Error::ProverFailure(format!("Guest journal does not decode: {e}")))
+}
+
+/// Prove a set of compact merkle proofs in the zkVM and verify the resulting receipt.
+///
+/// The proofs are passed to the guest as given; callers are expected to have run the
+/// request validation first, since the guest reports malformed input only as non-compliance.
+#[cfg(feature = "prover")]
+pub fn prove_compact(
+    proofs: &[CompactMerkleProof],
+    public_inputs: &MerklePublicInputs,
+) -> Result<GeneratedProof> {
+    let (input, protocol) = prepare_input(proofs, public_inputs)?;
+    let opts = public_inputs.prover_opts;
+    let env = segmented_env(&input, opts.segment_po2)?;
+
+    tracing::info!(
+        "Executor environment built successfully with input protocol {}. Starting proof generation with {} segments of 2^{} cycles",
+        protocol,
+        opts.hashfn.name(),
+        opts.segment_po2
+    );
+
+    let opts = match public_inputs.receipt_kind {
+        ReceiptKind::Composite if public_inputs.fast_path => ProverOpts::succinct(),
+        ReceiptKind::Composite => ProverOpts::composite(),
+        ReceiptKind::Succinct => ProverOpts::succinct(),
+        ReceiptKind::Groth16 => ProverOpts::groth16(),
+    }
+    .with_hashfn(opts.hashfn.name());
+    let started = Instant::now();
+    let prove_info = default_prover().prove_with_opts(env, SBOM_VALIDATOR_ELF, &opts);
+    let _ = metrics().observe(metric::PROOF_GENERATION_SECONDS, &[], started.elapsed());
+    let prove_info = prove_info.map_err(|e| {
+        Error::ProverFailure(format!(
+            "Proof generation failed during RISC0 execution: {e}. This may indicate an issue with the proof computation or executor environment"
+        ))
+    })?;
+
+    finish_receipt(prove_info.receipt, proofs, public_inputs, protocol)
+}
+
+/// Frame the proofs and public inputs for the guest with the negotiated input protocol.
+/// Returns the guest's input words and the protocol.
+#[cfg(feature = "prover")]
+pub(crate) fn prepare_input(
+    proofs: &[CompactMerkleProof],
+    public_inputs: &MerklePublicInputs,
+) -> Result<(Vec<u32>, u32)> {
+    let proofs_json = serde_json::to_string(proofs)?;
+
+    tracing::info!(
+        "Preparing guest input: processing {} compact proofs for root: {}",
+        proofs.len(),
+        hex::encode(public_inputs.root_hash)
+    );
+
+    let protocol = negotiate_input_protocol()?;
+    let input = guest_input(&proofs_json, public_inputs, protocol)?;
+    Ok((input, protocol))
+}
+
+/// Check a receipt's journal against what was asked of the guest, verify it, and
+/// serialize it. The same checks apply wherever the receipt was proven.
+#[cfg(feature = "prover")]
+pub(crate) fn finish_receipt(
+    receipt: Receipt,
+    proofs: &[CompactMerkleProof],
+    public_inputs: &MerklePublicInputs,
+    protocol: u32,
+) -> Result<GeneratedProof> {
+    let outputs: MerklePublicOutputs = receipt.journal.decode().map_err(|e| {
+        Error::Risc0(format!(
+            "Failed to decode receipt journal output: {}. Journal size: {} bytes. This may indicate a serialization mismatch or corrupted receipt",
+            e,
+            receipt.journal.bytes.len()
+        ))
+    })?;
+
+    sbom_common::record_outputs!(
+        "Compact proof generated",
+        hex::encode(outputs.root_hash),
+        hex::encode(outputs.banned_list_hash),
+        outputs.proof_count,
+        outputs.compliant
+    );
+    tracing::info!(
+        "Reasons hash: {}, List info hash: {}",
+        hex::encode(outputs.reasons_hash),
+        hex::encode(outputs.list_info_hash)
+    );
+
+    if outputs.input_status != input::status::OK {
+        return Err(Error::Risc0(format!(
+            "Guest rejected the input: {} (status {}, input protocol {})",
+            input::status::name(outputs.input_status),
+            outputs.input_status,
+            protocol
+        )));
+    }
+
+    let expected_salt_commitment = public_inputs
+        .blinding_salt
+        .as_ref()
+        .map_or([0u8; 32], sbom_common::compute_salt_commitment);
+    if outputs.salt_commitment != expected_salt_commitment {
+        return Err(Error::Risc0(format!(
+            "Guest committed salt commitment {} but {} was expected; the banned list commitment is not blinded as requested",
+            hex::encode(outputs.salt_commitment),
+            hex::encode(expected_salt_commitment)
+        )));
+    }
+
+    let expected_key_type = crate::validation::list_key_type(proofs);
+    if outputs.key_type != expected_key_type.code() {
+        return Err(Error::Risc0(format!(
+            "Guest committed key type {} but the proofs are keyed by {} ({})",
+            outputs.key_type,
+            expected_key_type.name(),
+            expected_key_type.code()
+        )));
+    }
+
+    if outputs.proof_type != public_inputs.proof_type.code() {
+        return Err(Error::Risc0(format!(
+            "Guest committed proof type {} but {} ({}) was requested",
+            outputs.proof_type,
+            public_inputs.proof_type.name(),
+            public_inputs.proof_type.code()
+        )));
+    }
+
+    // Roots are only meaningful under the table the host hashes its trees with
+    if outputs.defaults_digest != sbom_common::DEFAULTS_DIGEST {
+        return Err(Error::Risc0(format!(
+            "Guest committed defaults digest {} but this build's DEFAULTS hash to {}; the guest was built with a different default hash table",
+            hex::encode(outputs.defaults_digest),
+            hex::encode(sbom_common::DEFAULTS_DIGEST)
+        )));
+    }
+
+    let expected_requester_commitment = public_inputs.requester_commitment.unwrap_or([0u8; 32]);
+    if outputs.requester_commitment != expected_requester_commitment {
+        return Err(Error::Risc0(format!(
+            "Guest committed requester commitment {} but {} was expected; the proof is not bound to the requester",
+            hex::encode(outputs.requester_commitment),
+            hex::encode(expected_requester_commitment)
+        )));
+    }
+
+    receipt.verify(SBOM_VALIDATOR_ID).map_err(|e| {
+        Error::Risc0(format!(
+            "Receipt verification failed: {e}. This indicates the generated proof is invalid or corrupted. Image ID: {SBOM_VALIDATOR_ID:?}"
+        ))
+    })?;
+
+    tracing::info!("Receipt verification successful");
+
+    let receipt_bytes: Vec<u8> = to_vec(&receipt)
+        .map_err(|e| {
+            Error::Risc0(format!(
+                "Failed to serialize receipt to bytes: {e}. This may indicate a serialization format issue"
+            ))
+        })?
+        .iter()
+        .flat_map(|&x| x.to_le_bytes())
+        .collect();
+
+    Ok(GeneratedProof {
+        outputs,
+        journal: receipt.journal.bytes.clone(),
+        receipt_bytes,
+        session_id: None,
+        receipt_kind: receipt_kind(&receipt),
+        seal: seal(&receipt),
+    })
+}
+
+/// Parse serialized receipt bytes, verify them against the embedded guest's image ID and
+/// decode the journal. Checks nothing the journal claims; that is up to the caller.
+#[cfg(feature = "prover")]
+pub fn verify_receipt(receipt_bytes: &[u8]) -> Result<VerifiedReceipt> {
+    if !receipt_bytes.len().is_multiple_of(4) {
+        return Err(Error::Risc0(format!(
+            "Receipt of {} bytes is not a whole number of 4-byte words",
+            receipt_bytes.len()
+        )));
+    }
+    let words: Vec<u32> = receipt_bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect();
+    let receipt: Receipt = from_slice(&words)
+        .map_err(|e| Error::Risc0(format!("Failed to deserialize receipt: {e}")))?;
+
+    receipt.verify(SBOM_VALIDATOR_ID).map_err(|e| {
+        Error::Risc0(format!(
+            "Receipt verification failed: {e}. Image ID: {SBOM_VALIDATOR_ID:?}"
+        ))
+    })?;
+
+    let outputs: MerklePublicOutputs = receipt.journal.decode().map_err(|e| {
+        Error::Risc0(format!(
+            "Failed to decode receipt journal output: {}. Journal size: {} bytes",
+            e,
+            receipt.journal.bytes.len()
+        ))
+    })?;
+
+    Ok(VerifiedReceipt {
+        outputs,
+        receipt_kind: receipt_kind(&receipt),
+        seal: seal(&receipt),
+        journal: receipt.journal.bytes,
+    })
+}
+
+/// Name of the kind of `receipt`
+#[cfg(feature = "prover")]
+pub fn receipt_kind(receipt: &Receipt) -> &'static str {
+    match &receipt.inner {
+        InnerReceipt::Composite(_) => "composite",
+        InnerReceipt::Succinct(_) => "succinct",
+        InnerReceipt::Groth16(_) => "groth16",
+        InnerReceipt::Fake(_) => "fake",
+        _ => "unknown",
+    }
+}
+
+/// The seal of a compressed receipt: the STARK words of a succinct one as little-endian
+/// bytes, or a Groth16 proof prefixed with the first 4 bytes of its verifier parameters
+/// digest, which the RISC Zero verifier router uses to select the verifier
+#[cfg(feature = "prover")]
+fn seal(receipt: &Receipt) -> Option<Vec<u8>> {
+    match &receipt.inner {
+        InnerReceipt::Succinct(succinct) => Some(
+            succinct
+                .seal
+                .iter()
+                .flat_map(|word| word.to_le_bytes())
+                .collect(),
+        ),
+        InnerReceipt::Groth16(groth16) => Some(
+            [
+                &groth16.verifier_parameters.as_bytes()[..4],
+                groth16.seal.as_slice(),
+            ]
+            .concat(),
+        ),
+        _ => None,
+    }
+}
+
+/// The parts of the guest manifest the host acts on
+#[cfg(feature = "prover")]
+#[derive(Deserialize)]
+struct GuestManifest {
+    input_protocols: Vec<u32>,
+}
+
+/// Input protocol versions the embedded guest declares in its manifest
+#[cfg(feature = "prover")]
+pub fn guest_input_protocols() -> Result<Vec<u32>> {
+    serde_json::from_str::<GuestManifest>(SBOM_VALIDATOR_MANIFEST)
+        .map(|manifest| manifest.input_protocols)
+        .map_err(|e| Error::Risc0(format!("Invalid guest manifest: {e}")))
+}
+
+/// The highest input protocol version both this host and the embedded guest support
+#[cfg(feature = "prover")]
+pub fn negotiate_input_protocol() -> Result<u32> {
+    let guest_protocols = guest_input_protocols()?;
+    input::negotiate(&guest_protocols).ok_or_else(|| {
+        Error::Risc0(format!(
+            "No common input protocol: host supports {:?}, guest manifest declares {:?}",
+            input::HOST_PROTOCOLS,
+            guest_protocols
+        ))
+    })
+}
+
+/// Build the guest's executor environment, framing the inputs with the given protocol
+#[cfg(feature = "prover")]
+pub fn guest_env(
+    proofs_json: &str,
+    public_inputs: &MerklePublicInputs,
+    protocol: u32,
+) -> Result<ExecutorEnv<'static>> {
+    executor_env(&guest_input(proofs_json, public_inputs, protocol)?)
+}
+
+#[cfg(feature = "prover")]
+fn executor_env(input: &[u32]) -> Result<ExecutorEnv<'static>> {
+    ExecutorEnv::builder().write_slice(input).build().map_err(|e| {
+        Error::Risc0(format!(
+            "Failed to build executor environment: {e}. This may indicate memory or configuration issues"
+        ))
+    })
+}
+
+/// Like [`executor_env`], split into segments of `2^segment_po2` cycles
+#[cfg(feature = "prover")]
+fn segmented_env(input: &[u32], segment_po2: u32) -> Result<ExecutorEnv<'static>> {
+    ExecutorEnv::builder()
+        .write_slice(input)
+        .segment_limit_po2(segment_po2)
+        .build()
+        .map_err(|e| {
+            Error::Risc0(format!(
+                "Failed to build executor environment with segments of 2^{segment_po2} cycles: {e}"
+            ))
+        })
+}
+
+/// The guest's input words, framed with the given protocol: exactly what the executor
+/// environment feeds the guest, and what a remote prover is sent
+#[cfg(feature = "prover")]
+pub fn guest_input(
+    proofs_json: &str,
+    public_inputs: &MerklePublicInputs,
+    protocol: u32,
+) -> Result<Vec<u32>> {
+    let write_err = |what: &str, e: &dyn Display| {
+        Error::Risc0(format!(
+            "Failed to write {what} to guest input: {e}. Input protocol: {protocol}"
+        ))
+    };
+
+    let mut input = Vec::new();
+    match protocol {
+        PROTOCOL_LEGACY if public_inputs.blinding_salt.is_some() => {
+            return Err(Error::Risc0(format!(
+                "Blinding the banned list needs input protocol {PROTOCOL_SECTIONS}, but the guest only supports {protocol}"
+            )));
+        }
+        PROTOCOL_LEGACY if public_inputs.requester_commitment.is_some() => {
+            return Err(Error::Risc0(format!(
+                "Binding a proof to its requester needs input protocol {PROTOCOL_SECTIONS}, but the guest only supports {protocol}"
+            )));
+        }
+        PROTOCOL_LEGACY if public_inputs.proof_type != ProofType::NonMembership => {
+            return Err(Error::Risc0(format!(
+                "Membership proofs need input protocol {PROTOCOL_SECTIONS}, but the guest only supports {protocol}"
+            )));
+        }
+        PROTOCOL_LEGACY => {
+            let header = InputHeader {
+                protocol_version: PROTOCOL_LEGACY,
+                flags: 0,
+                section_count: 0,
+            };
+            input.extend(to_vec(&header).map_err(|e| write_err("input header", &e))?);
+            input.extend(to_vec(&proofs_json).map_err(|e| write_err("proofs JSON", &e))?);
+            input.extend(to_vec(public_inputs).map_err(|e| write_err("public inputs", &e))?);
+        }
+        PROTOCOL_SECTIONS => {
+            let mut sections = vec![
+                Section {
+                    tag: tag::PROOFS,
+                    mandatory: true,
+                    payload: to_vec(proofs_json).map_err(|e| write_err("proofs section", &e))?,
+                },
+                Section {
+                    tag: tag::PUBLIC_INPUTS,
+                    mandatory: true,
+                    payload: to_vec(public_inputs)
+                        .map_err(|e| write_err("public inputs section", &e))?,
+                },
+            ];
+            if let Some(salt) = &public_inputs.blinding_salt {
+                sections.push(Section {
+                    tag: tag::BLINDING_SALT,
+                    mandatory: true,
+                    payload: to_vec(salt).map_err(|e| write_err("blinding salt section", &e))?,
+                });
+            }
+            if public_inputs.proof_type != ProofType::NonMembership {
+                sections.push(Section {
+                    tag: tag::PROOF_TYPE,
+                    mandatory: true,
+                    payload: to_vec(&public_inputs.proof_type.code())
+                        .map_err(|e| write_err("proof type section", &e))?,
+                });
+            }
+            if let Some(commitment) = &public_inputs.requester_commitment {
+                sections.push(Section {
+                    tag: tag::REQUESTER,
+                    mandatory: true,
+                    payload: to_vec(commitment).map_err(|e| write_err("requester section", &e))?,
+                });
+            }
+            let header = InputHeader {
+                protocol_version: PROTOCOL_SECTIONS,
+                flags: 0,
+                section_count: sections.len() as u32,
+            };
+            input.extend(to_vec(&header).map_err(|e| write_err("input header", &e))?);
+            for section in &sections {
+                input.extend(to_vec(section).map_err(|e| write_err("input section", &e))?);
+            }
+        }
+        other => {
+            return Err(Error::Risc0(format!(
+                "Input protocol {other} is not supported by this host"
+            )));
+        }
+    }
+
+    Ok(input)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    fn public_inputs() -> MerklePublicInputs {
+        MerklePublicInputs {
+            root_hash: [7u8; 32],
+            expected_banned_list_hash: None,
+            list_info: None,
+            blinding_salt: None,
+            proof_type: ProofType::NonMembership,
+            receipt_kind: ReceiptKind::Composite,
+            requester_commitment: None,
+            fast_path: false,
+            prover_opts: Default::default(),
+        }
+    }
+
+    /// Split input words the way a sections guest reads them: the header, then each
+    /// section as its tag, mandatory flag, payload length and payload
+    fn read_sections(input: &[u32]) -> (InputHeader, Vec<Section>) {
+        let header: InputHeader = from_slice(input).unwrap();
+        let mut rest = &input[3..];
+        let mut sections = Vec::new();
+        while let [tag, mandatory, len, tail @ ..] = rest {
+            let (payload, tail) = tail.split_at(*len as usize);
+            sections.push(Section {
+                tag: *tag,
+                mandatory: *mandatory != 0,
+                payload: payload.to_vec(),
+            });
+            rest = tail;
+        }
+        (header, sections)
+    }
+
+    #[test]
+    fn legacy_framing_emulates_the_unframed_input() {
+        let input = guest_input("[]", &public_inputs(), PROTOCOL_LEGACY).unwrap();
+
+        let header: InputHeader = from_slice(&input).unwrap();
+        let legacy = InputHeader {
+            protocol_version: PROTOCOL_LEGACY,
+            flags: 0,
+            section_count: 0,
+        };
+        assert_eq!(header, legacy);
+        let proofs_json: String = from_slice(&input[3..]).unwrap();
+        assert_eq!(proofs_json, "[]");
+
+        // What a legacy guest cannot be told is refused rather than dropped
+        for inputs in [
+            MerklePublicInputs {
+                blinding_salt: Some([1u8; 32]),
+                ..public_inputs()
+            },
+            MerklePublicInputs {
+                proof_type: ProofType::Membership,
+                ..public_inputs()
+            },
+            MerklePublicInputs {
+                requester_commitment: Some([2u8; 32]),
+                ..public_inputs()
+            },
+        ] {
+            assert!(guest_input("[]", &inputs, PROTOCOL_LEGACY).is_err());
+            assert!(guest_input("[]", &inputs, PROTOCOL_SECTIONS).is_ok());
+        }
+    }
+
+    #[test]
+    fn sections_carry_only_what_the_request_needs() {
+        let input = guest_input("[]", &public_inputs(), PROTOCOL_SECTIONS).unwrap();
+        let (header, sections) = read_sections(&input);
+        assert_eq!((header.protocol_version, header.section_count), (2, 2));
+        let tags: Vec<u32> = sections.iter().map(|section| section.tag).collect();
+        assert_eq!(tags, [tag::PROOFS, tag::PUBLIC_INPUTS]);
+        let proofs_json: String = from_slice(&sections[0].payload).unwrap();
+        assert_eq!(proofs_json, "[]");
+
+        let inputs = MerklePublicInputs {
+            blinding_salt: Some([1u8; 32]),
+            proof_type: ProofType::Membership,
+            requester_commitment: Some([2u8; 32]),
+            ..public_inputs()
+        };
+        let input = guest_input("[]", &inputs, PROTOCOL_SECTIONS).unwrap();
+        let (header, sections) = read_sections(&input);
+        assert_eq!(header.section_count, 5);
+        assert!(sections.iter().all(|section| section.mandatory));
+        let tags: Vec<u32> = sections.iter().map(|section| section.tag).collect();
+        let expected = [
+            tag::PROOFS,
+            tag::PUBLIC_INPUTS,
+            tag::BLINDING_SALT,
+            tag::PROOF_TYPE,
+            tag::REQUESTER,
+        ];
+        assert_eq!(tags, expected);
+
+        assert!(guest_input("[]", &inputs, 3).is_err());
+    }
+
+    #[test]
+    fn the_shipped_guest_negotiates_sections() {
+        assert_eq!(guest_input_protocols().unwrap(), [1, 2]);
+        assert_eq!(negotiate_input_protocol().unwrap(), PROTOCOL_SECTIONS);
+    }
+}
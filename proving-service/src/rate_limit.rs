@@ -0,0 +1,146 @@
+//! Per-client rate limiting of the proving endpoints.
+//!
+//! One client queueing dozens of prove requests would starve every other one, so with
+//! `RATE_LIMIT_PER_MINUTE` set each client gets a token bucket: it holds up to
+//! `RATE_LIMIT_BURST` requests and refills at the per-minute rate. Clients are told
+//! apart by the API key [`crate::auth`] matched, or by IP address when no keys are
+//! configured. A request finding its bucket empty is answered 429 with `Retry-After`;
+//! an admitted one learns its remaining quota from `X-RateLimit-Remaining`. The limits
+//! are read from the active configuration on every request, so a reload applies them to
+//! the buckets already filled; the buckets live in memory and a restart refills them.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{HttpMessage, Result as ActixResult, web};
+
+use crate::auth::ApiKeyId;
+use crate::error::Error;
+use crate::reload::ReloadableConfig;
+
+/// Carries the requests an admitted client may still make right away
+pub const REMAINING_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+
+/// Buckets kept before the full ones, which have nothing to remember, are dropped
+const PRUNE_THRESHOLD: usize = 4096;
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Token buckets by client, shared by every worker
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Take one request from `client`'s bucket, refilled at `per_minute` up to `burst`.
+    /// Returns the requests left, or how long until the next one is admitted.
+    pub fn acquire(
+        &self,
+        client: &str,
+        per_minute: u32,
+        burst: u32,
+        now: Instant,
+    ) -> Result<u32, Duration> {
+        let rate = f64::from(per_minute) / 60.0;
+        let capacity = f64::from(burst.max(1));
+        let refill = |bucket: &Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+            (bucket.tokens + elapsed * rate).min(capacity)
+        };
+
+        let mut buckets = self.lock();
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| refill(bucket) < capacity);
+        }
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            refilled: now,
+        });
+        bucket.tokens = refill(bucket);
+        bucket.refilled = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(bucket.tokens as u32)
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+
+    /// Clients with a bucket
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Bucket>> {
+        self.buckets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Middleware holding each client to `RATE_LIMIT_PER_MINUTE`. Runs inside
+/// [`crate::auth::require_api_key`], so the client is known by its key and requests
+/// without a valid one do not use up its quota.
+pub async fn limit_prove_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> ActixResult<ServiceResponse<EitherBody<impl MessageBody>>> {
+    let (Some(config), Some(limiter)) = (
+        req.app_data::<web::Data<ReloadableConfig>>(),
+        req.app_data::<web::Data<RateLimiter>>(),
+    ) else {
+        let e = Error::Internal("Rate limiter started without its state".to_string());
+        return Ok(req.error_response(e).map_into_right_body());
+    };
+    let config = config.load();
+    if config.rate_limit_per_minute == 0 {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let client = match req.extensions().get::<ApiKeyId>() {
+        Some(ApiKeyId(key_id)) => format!("key:{key_id}"),
+        None => format!(
+            "ip:{}",
+            req.peer_addr()
+                .map_or_else(|| "unknown".to_string(), |addr| addr.ip().to_string())
+        ),
+    };
+    let acquired = limiter.acquire(
+        &client,
+        config.rate_limit_per_minute,
+        config.rate_limit_burst,
+        Instant::now(),
+    );
+    match acquired {
+        Ok(remaining) => {
+            let mut res = next.call(req).await?;
+            res.headers_mut()
+                .insert(REMAINING_HEADER, HeaderValue::from(remaining));
+            Ok(res.map_into_left_body())
+        }
+        Err(wait) => {
+            tracing::warn!(
+                "Rate limit of {} prove requests per minute exceeded by {}",
+                config.rate_limit_per_minute,
+                client
+            );
+            let e = Error::RateLimited {
+                limit: config.rate_limit_per_minute,
+                // Whole seconds, rounded up so a client waiting this long is admitted
+                retry_after: wait.as_secs() + u64::from(wait.subsec_nanos() > 0),
+            };
+            Ok(req.error_response(e).map_into_right_body())
+        }
+    }
+}
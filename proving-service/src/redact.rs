@@ -0,0 +1,191 @@
+//! Redaction of the keys a tenant proves.
+//!
+//! Some tenants consider the purls they query confidential. A request whose tenant (the
+//! API key id it is signed with, or `*` for any other request) has an entry in
+//! `REDACTION_KEYS` is logged and refused with a [`KeyDisplay::Redacted`] label of each
+//! key instead of the key: the leaf index it hashes to and a short preview. The record
+//! stored for it lists only those previews and leaf indexes, and keeps the keys
+//! themselves sealed under the tenant's key, so reading them back needs that key.
+//!
+//! No AEAD is among the service's dependencies, so keys are sealed with HMAC-SHA256 as a
+//! PRF in counter mode, authenticated by an HMAC-SHA256 tag over nonce and ciphertext
+//! (encrypt-then-MAC). Each of the two uses its own key derived from the tenant key.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use base64::{Engine as _, engine::general_purpose};
+use sbom_common::{KeyType, compute_leaf_index, constant_time_eq, hmac_sha256};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::{CompactMerkleProof, ProofArtifact};
+
+/// Characters of a key a redacted label keeps
+pub const PREVIEW_CHARS: usize = 12;
+
+/// Name of the sealing scheme, recorded with every sealed list
+pub const SEAL_ALGORITHM: &str = "hmac-sha256-ctr+hmac-sha256";
+
+const ENCRYPTION_DOMAIN: &[u8] = b"sbom-seal/v1/encryption";
+const AUTHENTICATION_DOMAIN: &[u8] = b"sbom-seal/v1/authentication";
+
+/// How a request's keys appear in logs and error messages
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum KeyDisplay {
+    #[default]
+    Plain,
+    /// Leaf index and a preview of at most [`PREVIEW_CHARS`] characters
+    Redacted,
+}
+
+impl KeyDisplay {
+    /// `key`, of `key_type`, as it may be logged
+    pub fn key<'a>(self, key_type: KeyType, key: &'a str) -> Cow<'a, str> {
+        match self {
+            KeyDisplay::Plain => Cow::Borrowed(key),
+            KeyDisplay::Redacted => Cow::Owned(match compute_leaf_index(key_type, key) {
+                Some(leaf_index) => format!("leaf {} ({})", hex::encode(leaf_index), preview(key)),
+                None => format!("unhashable {} key ({})", key_type.name(), preview(key)),
+            }),
+        }
+    }
+
+    /// The key of `proof` as it may be logged
+    pub fn proof<'a>(self, proof: &'a CompactMerkleProof) -> Cow<'a, str> {
+        self.key(proof.key_type, &proof.key)
+    }
+}
+
+/// The first [`PREVIEW_CHARS`] characters of `key`, marked as truncated when it is longer
+pub fn preview(key: &str) -> String {
+    match key.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", &key[..end]),
+        None => key.to_string(),
+    }
+}
+
+/// A tenant's 32-byte secret that its stored keys are sealed under
+#[derive(Clone, PartialEq, Eq)]
+pub struct RedactionKey([u8; 32]);
+
+impl RedactionKey {
+    /// Parse a 64-character hex key, optionally prefixed with `0x`
+    pub fn from_hex(hex_key: &str) -> Option<Self> {
+        sbom_common::hex_to_bytes32(hex_key).ok().map(Self)
+    }
+
+    fn derive(&self, domain: &[u8]) -> [u8; 32] {
+        hmac_sha256(&self.0, domain)
+    }
+}
+
+impl fmt::Debug for RedactionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RedactionKey(***)")
+    }
+}
+
+/// Keys of a stored record, readable only with the tenant's [`RedactionKey`]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct SealedKeys {
+    /// Always [`SEAL_ALGORITHM`]
+    pub algorithm: String,
+    /// Hex 16-byte nonce, unique per sealed record
+    pub nonce: String,
+    /// Base64 ciphertext of the JSON-encoded [`RecordKeys`]
+    pub ciphertext: String,
+    /// Hex HMAC-SHA256 over nonce and ciphertext
+    pub tag: String,
+}
+
+/// What a sealed record hides: its `purls` and the `purl` of each of its `reasons`, in
+/// record order
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RecordKeys {
+    pub purls: Vec<String>,
+    pub reasons: Vec<String>,
+}
+
+/// The record to store for `artifact` when its tenant redacts: keys are replaced by
+/// their previews, their leaf indexes are listed, and the keys themselves sealed under `key`
+pub fn seal_record(artifact: &ProofArtifact, key: &RedactionKey) -> Result<ProofArtifact> {
+    let keys = RecordKeys {
+        purls: artifact.purls.clone(),
+        reasons: artifact.reasons.iter().map(|r| r.purl.clone()).collect(),
+    };
+    let mut record = artifact.clone();
+    record.leaf_indexes = Some(
+        artifact
+            .purls
+            .iter()
+            .map(|purl| {
+                compute_leaf_index(artifact.key_type, purl).map_or_else(String::new, hex::encode)
+            })
+            .collect(),
+    );
+    record.purls = artifact.purls.iter().map(|purl| preview(purl)).collect();
+    for reason in &mut record.reasons {
+        reason.purl = preview(&reason.purl);
+    }
+    record.sealed_purls = Some(seal(&serde_json::to_vec(&keys)?, key));
+    Ok(record)
+}
+
+/// Seal `plaintext` under `key` with a fresh nonce
+pub fn seal(plaintext: &[u8], key: &RedactionKey) -> SealedKeys {
+    // A UUIDv7 is unique per call; uniqueness is all counter mode needs of a nonce
+    let nonce = Uuid::now_v7().into_bytes();
+    let mut ciphertext = plaintext.to_vec();
+    apply_keystream(&key.derive(ENCRYPTION_DOMAIN), &nonce, &mut ciphertext);
+    let tag = authenticate(&key.derive(AUTHENTICATION_DOMAIN), &nonce, &ciphertext);
+    SealedKeys {
+        algorithm: SEAL_ALGORITHM.to_string(),
+        nonce: hex::encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+        tag: hex::encode(tag),
+    }
+}
+
+/// The plaintext `sealed` was sealed from, provided it was sealed under `key` and not
+/// altered since. A wrong key and a tampered record are refused alike.
+pub fn open(sealed: &SealedKeys, key: &RedactionKey) -> Result<Vec<u8>> {
+    let refused = |reason: &str| {
+        let err_msg = format!("Cannot open the sealed keys of this record: {reason}");
+        tracing::error!("{}", err_msg);
+        Error::Unauthorized(err_msg)
+    };
+    if sealed.algorithm != SEAL_ALGORITHM {
+        return Err(refused(&format!(
+            "unknown algorithm '{}'",
+            sealed.algorithm
+        )));
+    }
+    let nonce = hex::decode(&sealed.nonce).map_err(|_| refused("malformed nonce"))?;
+    let tag = hex::decode(&sealed.tag).map_err(|_| refused("malformed tag"))?;
+    let mut plaintext = general_purpose::STANDARD
+        .decode(&sealed.ciphertext)
+        .map_err(|_| refused("malformed ciphertext"))?;
+    let expected = authenticate(&key.derive(AUTHENTICATION_DOMAIN), &nonce, &plaintext);
+    if !constant_time_eq(&expected, &tag) {
+        return Err(refused("the key does not match or the record was altered"));
+    }
+    apply_keystream(&key.derive(ENCRYPTION_DOMAIN), &nonce, &mut plaintext);
+    Ok(plaintext)
+}
+
+/// XOR `data` with HMAC-SHA256(key, nonce || counter) blocks
+fn apply_keystream(key: &[u8; 32], nonce: &[u8], data: &mut [u8]) {
+    for (counter, chunk) in data.chunks_mut(32).enumerate() {
+        let block = hmac_sha256(key, &[nonce, &(counter as u64).to_be_bytes()].concat());
+        for (byte, pad) in chunk.iter_mut().zip(block) {
+            *byte ^= pad;
+        }
+    }
+}
+
+fn authenticate(key: &[u8; 32], nonce: &[u8], ciphertext: &[u8]) -> [u8; 32] {
+    hmac_sha256(key, &[nonce, ciphertext].concat())
+}
@@ -0,0 +1,321 @@
+//! Compliance reports assembled from persisted proof records.
+//!
+//! A report restates what a proof established (which list, which root, when, which
+//! keys, and the outcome) for people and for machines. The JSON form follows
+//! `docs/compliance-report.schema.json` and carries [`REPORT_VERSION`]; the HTML form is
+//! rendered from the same [`ComplianceReport`]. Reports never contain receipt bytes,
+//! only a link when the receipt is stored for download.
+
+use sbom_common::envelope;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::models::ListInfo;
+use crate::utils::{KeyType, ProofType};
+
+/// Bump whenever a field of [`ComplianceReport`] is added, removed or changes meaning.
+pub const REPORT_VERSION: u32 = 3;
+
+/// The subset of a persisted proof record a report is built from
+#[derive(Deserialize)]
+struct ProofRecord {
+    banned_list_hash: String,
+    compliant: bool,
+    image_id: Vec<String>,
+    /// Absent in records written before non-purl keys, which are purl lists
+    #[serde(default)]
+    key_type: KeyType,
+    list_info: Option<ListInfo>,
+    list_info_hash: Option<String>,
+    proof_count: u32,
+    proof_id: String,
+    proof_size: usize,
+    /// Absent in records written before membership proofs, which are non-membership proofs
+    #[serde(default)]
+    proof_type: ProofType,
+    proof_url: Option<String>,
+    #[serde(default)]
+    purls: Vec<String>,
+    reasons: Vec<RecordReason>,
+    reasons_hash: String,
+    root_alias: Option<String>,
+    root_hash: String,
+    #[serde(default)]
+    salt_commitment: Option<String>,
+    timestamp: u64,
+}
+
+#[derive(Deserialize)]
+struct RecordReason {
+    purl: String,
+    value: u64,
+    reason: String,
+}
+
+#[derive(Serialize)]
+pub struct ComplianceReport {
+    pub report_version: u32,
+    pub proof_id: String,
+    /// Unix timestamp (seconds) at which the proof was generated
+    pub proven_at: u64,
+    pub compliant: bool,
+    /// What `compliant` means: no component is in the tree, or every one is
+    pub proof_type: ProofType,
+    pub root_hash: String,
+    pub root_alias: Option<String>,
+    pub banned_list: ReportedList,
+    pub components: Vec<CheckedComponent>,
+    pub proof_count: u32,
+    pub verification: ReportedVerification,
+    pub artifacts: ReportedArtifacts,
+}
+
+#[derive(Serialize)]
+pub struct ReportedList {
+    pub banned_list_hash: String,
+    /// Kind of identifier the list is keyed by, and so of every component's `purl`
+    pub key_type: KeyType,
+    /// True when `banned_list_hash` is blinded with a salt and cannot be matched to a list
+    pub blinded: bool,
+    pub salt_commitment: Option<String>,
+    pub list_info: Option<ListInfo>,
+    pub list_info_hash: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CheckedComponent {
+    pub purl: String,
+    pub banned: bool,
+    /// Reason code of a banned purl; 0 for the others
+    pub value: u64,
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+pub struct ReportedVerification {
+    /// The prover verified the receipt against `image_id` before recording it
+    pub receipt_verified_by_prover: bool,
+    pub image_id: Vec<String>,
+    pub reasons_hash: String,
+    /// False for records that predate the purl list; only banned purls are then known
+    pub components_complete: bool,
+}
+
+#[derive(Serialize)]
+pub struct ReportedArtifacts {
+    /// Download link of the raw receipt; absent when the receipt was returned inline
+    pub receipt_url: Option<String>,
+    pub receipt_size: usize,
+}
+
+impl ComplianceReport {
+    /// Build a report from a record as stored by [`crate::storage::FsProofStore`]
+    pub fn from_record(record_json: &str) -> Result<Self> {
+        let record: ProofRecord = serde_json::from_str(record_json)
+            .map_err(|e| e.to_string())
+            .and_then(|document| envelope::open(document, true).map_err(|e| e.to_string()))
+            .and_then(|opened| serde_json::from_value(opened.data).map_err(|e| e.to_string()))
+            .map_err(|e| {
+                Error::Storage(format!("Stored proof record cannot be reported on: {e}"))
+            })?;
+
+        let components_complete = !record.purls.is_empty() || record.proof_count == 0;
+        let components = if components_complete {
+            record
+                .purls
+                .iter()
+                .map(|purl| {
+                    let banned = record.reasons.iter().find(|r| r.purl == *purl);
+                    CheckedComponent {
+                        purl: purl.clone(),
+                        banned: banned.is_some(),
+                        value: banned.map_or(0, |r| r.value),
+                        reason: banned.map_or_else(|| "none".to_string(), |r| r.reason.clone()),
+                    }
+                })
+                .collect()
+        } else {
+            record
+                .reasons
+                .iter()
+                .map(|r| CheckedComponent {
+                    purl: r.purl.clone(),
+                    banned: true,
+                    value: r.value,
+                    reason: r.reason.clone(),
+                })
+                .collect()
+        };
+
+        Ok(Self {
+            report_version: REPORT_VERSION,
+            proof_id: record.proof_id,
+            proven_at: record.timestamp,
+            compliant: record.compliant,
+            proof_type: record.proof_type,
+            root_hash: record.root_hash,
+            root_alias: record.root_alias,
+            banned_list: ReportedList {
+                banned_list_hash: record.banned_list_hash,
+                key_type: record.key_type,
+                blinded: record.salt_commitment.is_some(),
+                salt_commitment: record.salt_commitment,
+                list_info: record.list_info,
+                list_info_hash: record.list_info_hash,
+            },
+            components,
+            proof_count: record.proof_count,
+            verification: ReportedVerification {
+                receipt_verified_by_prover: true,
+                image_id: record.image_id,
+                reasons_hash: record.reasons_hash,
+                components_complete,
+            },
+            artifacts: ReportedArtifacts {
+                receipt_url: record.proof_url,
+                receipt_size: record.proof_size,
+            },
+        })
+    }
+
+    /// Render the report as a standalone HTML page
+    pub fn to_html(&self) -> String {
+        let mut rows = String::new();
+        for component in &self.components {
+            rows.push_str(&format!(
+                "      <tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape(&component.purl),
+                match (component.banned, self.proof_type) {
+                    (true, ProofType::NonMembership) => "banned",
+                    (true, ProofType::Membership) => "listed",
+                    (false, _) => "not listed",
+                },
+                escape(&component.reason)
+            ));
+        }
+
+        let list_info = self.banned_list.list_info.as_ref().map_or_else(
+            || "not recorded".to_string(),
+            |info| {
+                format!(
+                    "{} ({} {}, {} entries)",
+                    escape(&info.source_uri),
+                    escape(&info.publisher),
+                    escape(&info.version),
+                    info.entry_count
+                )
+            },
+        );
+        let receipt = self.artifacts.receipt_url.as_deref().map_or_else(
+            || "returned inline to the requester".to_string(),
+            |url| format!("<a href=\"{0}\">{0}</a>", escape(url)),
+        );
+        let incomplete = if self.verification.components_complete {
+            ""
+        } else {
+            "    <p>This record predates the purl list; only banned components are shown.</p>\n"
+        };
+
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <title>Compliance report {proof_id}</title>
+  </head>
+  <body>
+    <h1>Compliance report</h1>
+    <p><strong>{verdict}</strong>: {proof_count} component(s) checked against the {checked_against}.</p>
+    <table>
+      <tr><th>Proof ID</th><td>{proof_id}</td></tr>
+      <tr><th>Proven at (Unix time)</th><td>{proven_at}</td></tr>
+      <tr><th>Root</th><td>{root_hash}{root_alias}</td></tr>
+      <tr><th>Banned list hash</th><td>{banned_list_hash}{blinded}</td></tr>
+      <tr><th>Banned list</th><td>{list_info}</td></tr>
+      <tr><th>Keyed by</th><td>{key_type}</td></tr>
+      <tr><th>Image ID</th><td>{image_id}</td></tr>
+      <tr><th>Verification</th><td>receipt verified by the prover</td></tr>
+      <tr><th>Receipt</th><td>{receipt} ({receipt_size} bytes)</td></tr>
+    </table>
+    <h2>Components</h2>
+{incomplete}    <table>
+      <tr><th>Key</th><th>Status</th><th>Reason</th></tr>
+{rows}    </table>
+    <p>Report version {report_version}</p>
+  </body>
+</html>
+"#,
+            proof_id = escape(&self.proof_id),
+            verdict = if self.compliant { "Compliant" } else { "Not compliant" },
+            proof_count = self.proof_count,
+            checked_against = match self.proof_type {
+                ProofType::NonMembership => "banned list",
+                ProofType::Membership => "list for membership",
+            },
+            proven_at = self.proven_at,
+            root_hash = escape(&self.root_hash),
+            root_alias = self
+                .root_alias
+                .as_deref()
+                .map_or_else(String::new, |alias| format!(" ({})", escape(alias))),
+            banned_list_hash = escape(&self.banned_list.banned_list_hash),
+            blinded = if self.banned_list.blinded { " (blinded)" } else { "" },
+            list_info = list_info,
+            key_type = self.banned_list.key_type.name(),
+            image_id = escape(&self.verification.image_id.join(",")),
+            receipt = receipt,
+            receipt_size = self.artifacts.receipt_size,
+            incomplete = incomplete,
+            rows = rows,
+            report_version = self.report_version,
+        )
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stored record of a non-compliant proof whose receipt was linked. The golden
+    /// reports beside it are what it must render to
+    const RECORD: &str = include_str!("../testdata/reports/record.json");
+
+    #[test]
+    fn reports_match_the_golden_files() {
+        let report = ComplianceReport::from_record(RECORD).unwrap();
+
+        let json = serde_json::to_string_pretty(&report).unwrap() + "\n";
+        assert_eq!(json, include_str!("../testdata/reports/report.json"));
+        let html = report.to_html();
+        assert_eq!(html, include_str!("../testdata/reports/report.html"));
+    }
+
+    #[test]
+    fn reports_never_carry_the_receipt() {
+        let mut record: serde_json::Value = serde_json::from_str(RECORD).unwrap();
+        record["proof"] = "cmVjZWlwdCBieXRlcw==".into();
+        record["proof_url"] = serde_json::Value::Null;
+
+        let report = ComplianceReport::from_record(&record.to_string()).unwrap();
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("cmVjZWlwdCBieXRlcw"), "{json}");
+        let html = report.to_html();
+        assert!(!html.contains("cmVjZWlwdCBieXRlcw"), "{html}");
+        assert!(html.contains("returned inline to the requester"));
+    }
+}
@@ -0,0 +1,163 @@
+//! Host-side ordering of proofs.
+//!
+//! Record timestamps come from the wall clock, which NTP occasionally steps backwards,
+//! and two proofs can share a second. Every proof therefore also gets a sequence number
+//! from the [`SequenceCounter`], which only ever increases and survives restarts: it is
+//! persisted in the proofs directory, and on startup it also resumes past the highest
+//! number found in the stored records, so losing the counter file cannot reuse numbers.
+//! Proofs are ordered by `(timestamp, sequence)`.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::storage::{ProofStore, RecordFilter};
+
+/// File in the proofs directory holding the next sequence number
+const SEQUENCE_FILE: &str = "sequence";
+
+pub struct SequenceCounter {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+struct State {
+    next: u64,
+    /// Previous wall-clock timestamp read, to notice the clock stepping back
+    latest_clock: Option<u64>,
+}
+
+impl SequenceCounter {
+    /// Counter persisted in `proofs_dir`, resuming after every number already handed out,
+    /// including those of the records in `store`
+    pub fn open(proofs_dir: &Path, store: &dyn ProofStore) -> Self {
+        let path = proofs_dir.join(SEQUENCE_FILE);
+        let persisted = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents.trim().parse::<u64>().unwrap_or_else(|e| {
+                tracing::warn!(
+                    "Ignoring unreadable sequence file {}: {}",
+                    path.display(),
+                    e
+                );
+                0
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => {
+                tracing::warn!("Failed to read sequence file {}: {}", path.display(), e);
+                0
+            }
+        };
+        let recorded = store
+            .list_records(&RecordFilter::default())
+            .unwrap_or_default()
+            .iter()
+            .map(|summary| summary.sequence + 1)
+            .max()
+            .unwrap_or(0);
+        if recorded > persisted {
+            tracing::warn!(
+                "Sequence file {} is behind the stored records; resuming at {}",
+                path.display(),
+                recorded
+            );
+        }
+
+        Self {
+            path,
+            state: Mutex::new(State {
+                next: persisted.max(recorded).max(1),
+                latest_clock: None,
+            }),
+        }
+    }
+
+    /// Hand out the next sequence number. `clock` is the timestamp just read from the wall
+    /// clock, if it was; a caller-supplied timestamp says nothing about the clock.
+    pub fn next(&self, clock: Option<u64>) -> u64 {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(now) = clock {
+            if let Some(latest) = state.latest_clock.filter(|&latest| now < latest) {
+                tracing::warn!(
+                    "Clock stepped back {}s (read {} after {}); proofs are ordered by sequence within the overlap",
+                    latest - now,
+                    now,
+                    latest
+                );
+            }
+            state.latest_clock = Some(now);
+        }
+
+        let sequence = state.next;
+        state.next += 1;
+        // A failed write only matters after a restart, and then the records still carry
+        // the numbers handed out
+        if let Err(e) = self.persist(state.next) {
+            tracing::error!(
+                "Failed to persist sequence {} to {}: {}",
+                state.next,
+                self.path.display(),
+                e
+            );
+        }
+        sequence
+    }
+
+    /// Replace the counter file in one rename, so a crash cannot leave it half-written
+    fn persist(&self, next: u64) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let staging = self.path.with_extension("tmp");
+        std::fs::write(&staging, next.to_string())?;
+        std::fs::rename(&staging, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FsProofStore;
+
+    /// A store in a fresh directory named after `test`
+    fn store(test: &str) -> FsProofStore {
+        let dir = std::env::temp_dir().join(format!("proving-service-sequence-{test}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        FsProofStore::new(dir)
+    }
+
+    #[test]
+    fn proofs_stay_ordered_when_the_clock_steps_back() {
+        let store = store("backwards");
+        let counter = SequenceCounter::open(store.dir(), &store);
+
+        // The clock reads 100 twice, then steps back to 90 before recovering to 95
+        let mut stamped = Vec::new();
+        for (n, clock) in [100, 100, 90, 95].into_iter().enumerate() {
+            let sequence = counter.next(Some(clock));
+            let proof_id = format!("{n:064x}");
+            let record = serde_json::json!({
+                "proof_id": proof_id,
+                "timestamp": clock,
+                "sequence": sequence,
+                "root_hash": "00",
+                "compliant": true,
+            });
+            std::fs::write(store.record_path(&proof_id), record.to_string()).unwrap();
+            stamped.push((clock, sequence));
+        }
+        assert_eq!(stamped, [(100, 1), (100, 2), (90, 3), (95, 4)]);
+
+        let listed = || -> Vec<(u64, u64)> {
+            let proofs = store.list_records(&RecordFilter::default()).unwrap();
+            proofs.iter().map(|p| (p.timestamp, p.sequence)).collect()
+        };
+        assert_eq!(listed(), [(100, 2), (100, 1), (95, 4), (90, 3)]);
+        assert_eq!(listed(), listed());
+
+        // A restart resumes after the last number, with or without the counter file
+        assert_eq!(SequenceCounter::open(store.dir(), &store).next(None), 5);
+        std::fs::remove_file(store.dir().join(SEQUENCE_FILE)).unwrap();
+        assert_eq!(SequenceCounter::open(store.dir(), &store).next(Some(80)), 5);
+    }
+}
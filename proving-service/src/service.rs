@@ -0,0 +1,1505 @@
+//! Library entry point for proving compact merkle requests.
+//!
+//! [`ProverService`] runs the full pipeline behind `POST /prove-merkle-compact`: request
+//! validation, proving, persistence and response assembly. The HTTP handlers are thin
+//! adapters over it, and other services can embed it directly without the `server`
+//! feature:
+//!
+//! ```no_run
+//! use proving_service::{ProverConfig, ProverService};
+//! # async fn run(request: proving_service::models::ProveCompactMerkleRequest) -> proving_service::Result<()> {
+//! let service = ProverService::new(ProverConfig::default());
+//! let artifact = service.prove_compact(request).await?;
+//! println!("compliant: {}", artifact.compliant);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Proving runs on tokio's blocking pool, so `prove_compact` must be awaited inside a
+//! tokio runtime. Set `RISC0_DEV_MODE=1` to get fake receipts quickly while integrating;
+//! artifacts holding one are marked `dev_mode`.
+//!
+//! Without the `prover` feature, or when built with [`ProverService::without_prover`],
+//! the service still validates requests and stores artifacts, but proving fails with
+//! [`Error::ProverDisabled`] unless a backend is supplied.
+
+use arc_swap::ArcSwap;
+use base64::{Engine as _, engine::general_purpose};
+use rayon::prelude::*;
+use sbom_common::{envelope, input};
+use sbom_common::observability::{label, metric, metrics};
+use sbom_common::{DEFAULTS_DIGEST, KeyType, ProofType};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::autoscale::{Backlog, BacklogJob};
+#[cfg(feature = "bonsai")]
+use crate::bonsai::BonsaiBackend;
+use crate::config::{BonsaiConfig, Config};
+use crate::digest::{self, canonical_request_digest, journal_digest, normalize_hex};
+use crate::error::{Error, Result};
+use crate::models::{
+    BannedReason, CompactMerkleProof, FirstFailure, ImportProofsResponse, ImportResult, ImportStatus, ImportedProof,
+    MerklePublicInputs, ProofArtifact, ProofValidationResult, ProveCompactMerkleRequest,
+    ProverOptions, ValidateCompactMerkleResponse,
+};
+use crate::output_checks::check_output;
+use crate::proof_cache::{self, ProofCache};
+use crate::prover::{GeneratedProof, ProvingBackend};
+#[cfg(feature = "prover")]
+use crate::prover::ZkvmBackend;
+use crate::redact::{RedactionKey, seal_record};
+use crate::sequence::SequenceCounter;
+#[cfg(feature = "sqlite")]
+use crate::sqlite::SqliteProofStore;
+use crate::storage::{FsProofStore, ProofStore, RecordFormat};
+use crate::utils::{
+    bytes_to_hex, bytes32_to_hex_string, compute_banned_list_commitment, compute_list_info_hash, compute_reasons_hash,
+    compute_requester_commitment, compute_salt_commitment, hex_to_bytes32, parse_leaf_value, reason_name,
+};
+use crate::validation::{
+    apply_strict_mode, banned_reasons, list_key_type, normalize_proof_encoding, preverify_proofs, recompute_compact_proof,
+    resolve_blinding_salt, resolve_prover_opts, resolve_root_alias, resolve_timestamp, validate_compact_proof, validate_compact_proofs,
+    validate_list_info, validate_proof_count, validate_proof_sizes, validate_request,
+};
+
+/// Conservative proving time used to decide up front whether a deadline can be met, and
+/// to weigh the backlog for autoscaling
+const ESTIMATED_PROVE_BASE: Duration = Duration::from_secs(20);
+const ESTIMATED_PROVE_PER_PROOF: Duration = Duration::from_millis(1500);
+
+/// Settings of the proving pipeline that do not depend on how it is exposed
+#[derive(Debug, Clone)]
+pub struct ProverConfig {
+    /// Directory of the default file system proof store, and of receipts whichever store
+    /// holds the records
+    pub proofs_dir: PathBuf,
+    /// SQLite database to keep records in instead; read once by [`ProverService::new`]
+    pub database_path: Option<PathBuf>,
+    /// Envelope records are written in, and whether bare legacy ones are still read
+    pub record_format: RecordFormat,
+    /// Named roots (e.g. "security-banlist@2024-06") mapped to normalized hex roots
+    pub root_aliases: HashMap<String, String>,
+    /// Receipts larger than this many bytes are stored and linked instead of inlined
+    pub inline_proof_limit: usize,
+    /// Requests with more compact proofs than this are refused before any work is done
+    pub max_proofs_per_request: usize,
+    /// Proofs whose journal is longer than this many bytes are quarantined
+    pub max_journal_bytes: usize,
+    /// Requests with at most this many proofs take the fast path; 0 turns it off
+    pub fast_path_max_proofs: usize,
+    /// Settings the local zkVM proves with unless a request overrides them
+    pub prover_opts: ProverOptions,
+    /// How long [`ProverService::check_prover`] reuses its last result
+    pub health_check_ttl: Duration,
+    /// Most proofs the proof cache holds; 0 turns it off
+    pub proof_cache_entries: usize,
+    /// How long an identical request is answered from the proof cache
+    pub proof_cache_ttl: Duration,
+    pub retry: RetryPolicy,
+    /// Remote prover to use instead of the local zkVM; read once by [`ProverService::new`]
+    pub bonsai: Option<BonsaiConfig>,
+}
+
+impl From<&Config> for ProverConfig {
+    fn from(config: &Config) -> Self {
+        Self {
+            proofs_dir: config.proofs_dir.clone(),
+            database_path: config.database_path.clone(),
+            record_format: RecordFormat::from(config),
+            root_aliases: config.root_aliases.clone(),
+            inline_proof_limit: config.inline_proof_limit,
+            max_proofs_per_request: config.max_proofs_per_request,
+            max_journal_bytes: config.max_journal_bytes,
+            fast_path_max_proofs: config.fast_path_max_proofs,
+            prover_opts: config.prover,
+            health_check_ttl: config.health_check_ttl,
+            proof_cache_entries: config.proof_cache_entries,
+            proof_cache_ttl: config.proof_cache_ttl,
+            retry: RetryPolicy {
+                max_retries: config.prove_max_retries,
+                initial_backoff: config.prove_retry_backoff,
+                transient_patterns: config.prove_transient_patterns.clone(),
+            },
+            bonsai: config.bonsai.clone(),
+        }
+    }
+}
+
+impl Default for ProverConfig {
+    fn default() -> Self {
+        Self::from(&Config::default())
+    }
+}
+
+/// Which prover failures are retried, and how often.
+///
+/// Only backend errors whose message contains one of `transient_patterns` (ignoring
+/// case) are retried; invalid requests and everything else fail on the first attempt.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for each further one, up to [`MAX_RETRY_BACKOFF`]
+    pub initial_backoff: Duration,
+    pub transient_patterns: Vec<String>,
+}
+
+/// Upper bound of the delay between two attempts
+pub const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
+impl RetryPolicy {
+    pub fn is_transient(&self, error: &Error) -> bool {
+        let message = match error {
+            Error::Risc0(msg) | Error::ProverFailure(msg) | Error::RemoteProver(msg) => {
+                msg.to_ascii_lowercase()
+            }
+            Error::Io(e) => e.to_string().to_ascii_lowercase(),
+            _ => return false,
+        };
+        self.transient_patterns
+            .iter()
+            .any(|pattern| message.contains(&pattern.to_ascii_lowercase()))
+    }
+
+    /// Delay before retry number `retry` (1-based)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(MAX_RETRY_BACKOFF)
+    }
+}
+
+#[derive(Clone)]
+pub struct ProverService {
+    /// Shared by clones, so [`ProverService::set_config`] reaches every copy
+    config: Arc<ArcSwap<ProverConfig>>,
+    store: Arc<dyn ProofStore>,
+    backend: Option<Arc<dyn ProvingBackend>>,
+    sequence: Arc<SequenceCounter>,
+    backlog: Arc<Backlog>,
+    cache: Arc<ProofCache>,
+    /// Last self-check of the backend and when it ran
+    prover_check: Arc<tokio::sync::Mutex<Option<(Instant, ProverCheck)>>>,
+}
+
+/// Outcome of [`ProverService::check_prover`]
+#[derive(Debug, Clone)]
+pub struct ProverCheck {
+    /// Why the backend failed its self-check; `None` when it passed
+    pub error: Option<String>,
+    pub image_id: [u32; 8],
+    /// Unix timestamp (seconds) the check ran at; older than now when it was reused
+    pub checked_at: u64,
+}
+
+impl ProverService {
+    /// A service proving with the local zkVM, or on Bonsai when `config.bonsai` is set and
+    /// the `bonsai` feature is enabled, and storing proofs under `config.proofs_dir`, or
+    /// their records in `config.database_path` when it is set and the `sqlite` feature is
+    /// enabled. Without the `prover` feature this is [`ProverService::without_prover`].
+    pub fn new(config: ProverConfig) -> Self {
+        Self {
+            backend: default_backend(&config),
+            ..Self::without_prover(config)
+        }
+    }
+
+    /// A service that validates and serves stored proofs but cannot prove
+    pub fn without_prover(config: ProverConfig) -> Self {
+        let store = default_store(&config);
+        Self {
+            sequence: Arc::new(SequenceCounter::open(&config.proofs_dir, store.as_ref())),
+            store,
+            backend: None,
+            backlog: Arc::default(),
+            cache: Arc::default(),
+            prover_check: Arc::default(),
+            config: Arc::new(ArcSwap::from_pointee(config)),
+        }
+    }
+
+    /// Keep proofs in `store`; sequence numbers resume after the ones its records hold
+    pub fn with_store(mut self, store: Arc<dyn ProofStore>) -> Self {
+        self.sequence = Arc::new(SequenceCounter::open(
+            &self.config().proofs_dir,
+            store.as_ref(),
+        ));
+        self.store = store;
+        self
+    }
+
+    pub fn with_backend(mut self, backend: Arc<dyn ProvingBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// The settings requests starting now run with
+    pub fn config(&self) -> Arc<ProverConfig> {
+        self.config.load_full()
+    }
+
+    /// Replace the settings for requests that start after this call; requests already
+    /// running finish with the settings they started with. The proof store is fixed at
+    /// construction, so a changed `proofs_dir` or `database_path` is ignored.
+    pub fn set_config(&self, config: ProverConfig) {
+        self.config.store(Arc::new(config));
+    }
+
+    pub fn store(&self) -> &Arc<dyn ProofStore> {
+        &self.store
+    }
+
+    /// Requests handed to the prover and not yet finished, for autoscaling
+    pub fn backlog(&self) -> Vec<BacklogJob> {
+        self.backlog.snapshot()
+    }
+
+    /// Image ID of the guest this service proves with; `None` when it cannot prove
+    pub fn image_id(&self) -> Option<[u32; 8]> {
+        self.backend.as_ref().map(|backend| backend.image_id())
+    }
+
+    /// Run the backend's self-check, or reuse the last result while it is younger than
+    /// `health_check_ttl`; `None` when the service cannot prove. Concurrent callers wait
+    /// for a single check rather than each running one.
+    pub async fn check_prover(&self) -> Option<ProverCheck> {
+        let backend = self.backend.clone()?;
+        let ttl = self.config().health_check_ttl;
+        let mut last = self.prover_check.lock().await;
+        if let Some((_, check)) = last.as_ref().filter(|(ran, _)| ran.elapsed() < ttl) {
+            return Some(check.clone());
+        }
+
+        let image_id = backend.image_id();
+        let error = match tokio::task::spawn_blocking(move || backend.self_check()).await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(e) => Some(format!("Prover self-check failed to run: {e}")),
+        };
+        match &error {
+            Some(error) => tracing::error!("Prover self-check failed: {}", error),
+            None => tracing::info!("Prover self-check passed"),
+        }
+
+        let check = ProverCheck {
+            error,
+            image_id,
+            checked_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+        *last = Some((Instant::now(), check.clone()));
+        Some(check)
+    }
+
+    /// Validate, prove and persist a request.
+    pub async fn prove_compact(&self, req: ProveCompactMerkleRequest) -> Result<ProofArtifact> {
+        self.prove_compact_by(req, None).await
+    }
+
+    /// Like [`ProverService::prove_compact`], giving up once `deadline` passes.
+    ///
+    /// Fails with [`Error::DeadlineExceeded`] when the proving estimate does not fit or
+    /// the deadline passes while proving, and with [`Error::Unavailable`] when it passes
+    /// before a blocking thread picked the job up; in that case nothing was proven.
+    pub async fn prove_compact_by(
+        &self,
+        mut req: ProveCompactMerkleRequest,
+        deadline: Option<Instant>,
+    ) -> Result<ProofArtifact> {
+        let config = self.config();
+        validate_proof_count(&req, config.max_proofs_per_request)?;
+
+        let Some(backend) = self.backend.clone() else {
+            let err_msg = "Proving is disabled: this instance runs without a prover";
+            tracing::error!("{}", err_msg);
+            return Err(Error::ProverDisabled(err_msg.to_string()));
+        };
+
+        let request_id = Uuid::now_v7().to_string();
+        resolve_root_alias(&mut req, &config.root_aliases)?;
+
+        tracing::info!(
+            "Received compact merkle prove request {} with depth={}, root={}, proof_count={}",
+            request_id,
+            req.depth,
+            req.root,
+            req.merkle_proofs.len()
+        );
+
+        let root_hash = validate_request(&req)?;
+        normalize_proof_encoding(&mut req)?;
+        validate_proof_sizes(&req)?;
+        let timestamp = resolve_timestamp(&req)?;
+        // Drawn right after the timestamp, so sequence order is the order timestamps were read
+        let sequence = self
+            .sequence
+            .next((!req.reproducible).then_some(timestamp));
+        let blinding_salt = resolve_blinding_salt(&req)?;
+        let expected_banned_list_hash = apply_strict_mode(&mut req)?;
+        validate_list_info(&req)?;
+        let (prover_opts, fast_path) = resolve_prover_opts(
+            &req,
+            config.prover_opts,
+            config.fast_path_max_proofs,
+            backend.as_ref(),
+        )?;
+
+        // A retried request is answered with the proof of the first one, unless forced
+        let cache_key =
+            proof_cache::cache_key(&canonical_request_digest(&req), req.requester.as_deref());
+        if !req.force {
+            match self.cached_proof(&cache_key, &req, backend.image_id(), config.proof_cache_ttl) {
+                Some(mut artifact) => {
+                    tracing::info!(
+                        "Request {} is identical to the one proven as {}; answering from the proof cache",
+                        request_id,
+                        artifact.proof_id
+                    );
+                    record_cache_lookup("hit");
+                    artifact.cached = true;
+                    artifact.generation_duration_ms = 0;
+                    artifact.request_id = request_id;
+                    artifact.template = req.template.clone();
+                    return Ok(artifact);
+                }
+                None => record_cache_lookup("miss"),
+            }
+        }
+
+        // Small requests are dominated by setup rather than work: they skip the thread
+        // pool for validation and prove with options tuned for short executions
+        tracing::info!(
+            "Validating {} compact merkle proof(s){}",
+            req.merkle_proofs.len(),
+            if fast_path { " on the fast path" } else { "" }
+        );
+        let key_display = req.key_display();
+        let decoded = validate_compact_proofs(&req.merkle_proofs, key_display, !fast_path)
+            .map_err(|(idx, e)| {
+                tracing::error!(
+                    "Proof validation failed at index {} (key: {}): {}",
+                    idx,
+                    key_display.proof(&req.merkle_proofs[idx]),
+                    e
+                );
+                e
+            })?;
+        tracing::info!(
+            "All {} proof(s) validated successfully",
+            req.merkle_proofs.len()
+        );
+        if req.skip_preverify {
+            tracing::warn!(
+                "Request {} skips pre-verification; proofs that miss the root prove non-compliant",
+                request_id
+            );
+        } else {
+            preverify_proofs(&req.merkle_proofs, &decoded, &root_hash, key_display)?;
+        }
+
+        if let Some(deadline) = deadline {
+            check_deadline_fits(deadline, req.merkle_proofs.len())?;
+        }
+
+        let public_inputs = MerklePublicInputs {
+            root_hash,
+            expected_banned_list_hash,
+            list_info: req.list_info.clone(),
+            blinding_salt,
+            proof_type: req.mode,
+            receipt_kind: req.receipt_kind,
+            requester_commitment: req.requester.as_deref().map(compute_requester_commitment),
+            fast_path,
+            prover_opts,
+        };
+
+        // Proving blocks for a long time, so it runs on the blocking pool. A deadline
+        // bounds how long this request waits for it; a job still waiting for a thread
+        // when the deadline passes is dropped without proving.
+        let proofs = req.merkle_proofs.clone();
+        let expected = public_inputs.clone();
+        let image_id = backend.image_id();
+        let prover_backend = backend.name();
+        let prover_opts = backend.takes_prover_opts().then_some(prover_opts);
+        let retry = config.retry.clone();
+        // Leaves the backlog when the proving task ends, even if this request stopped
+        // waiting for it
+        let in_backlog = self.backlog.enter(proofs.len());
+        let proving = tokio::task::spawn_blocking(move || {
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                return None;
+            }
+            in_backlog.start();
+            let started = Instant::now();
+            let result = prove_with_retries(
+                backend.as_ref(),
+                &proofs,
+                &public_inputs,
+                &retry,
+                deadline,
+            );
+            Some((result, started.elapsed()))
+        });
+        let outcome = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                tokio::time::timeout(remaining, proving).await.map_err(|_| {
+                    let err_msg = format!(
+                        "Request deadline passed while proving {} proof(s); the result is discarded",
+                        req.merkle_proofs.len()
+                    );
+                    tracing::error!("{}", err_msg);
+                    Error::DeadlineExceeded(err_msg)
+                })?
+            }
+            None => proving.await,
+        };
+
+        let (generated, generation_duration) = outcome
+            .map_err(|e| {
+                let err_msg = format!("Proving task failed to run: {e}");
+                tracing::error!("{}", err_msg);
+                Error::ProverFailure(err_msg)
+            })?
+            .ok_or_else(|| {
+                let err_msg = "Request deadline passed while waiting for a prover; the job was dropped without proving";
+                tracing::error!("{}", err_msg);
+                Error::Unavailable(err_msg.to_string())
+            })?;
+        let (generated, prove_attempts) = generated.map_err(|e| {
+            tracing::error!("{}", e);
+            e
+        })?;
+        if let Err(reason) = check_output(
+            &generated,
+            &req.merkle_proofs,
+            &expected,
+            config.max_journal_bytes,
+        ) {
+            let err_msg = format!(
+                "Prover output for request {request_id} failed the sanity checks and was quarantined: {reason}"
+            );
+            tracing::error!("{}", err_msg);
+            if let Err(e) = self.store.quarantine(
+                &request_id,
+                &reason,
+                &generated.journal,
+                &generated.receipt_bytes,
+            ) {
+                tracing::error!(
+                    "Failed to quarantine the output of request {}: {}",
+                    request_id,
+                    e
+                );
+            }
+            return Err(Error::InvalidOutput(err_msg));
+        }
+        let output = generated.outputs;
+        let receipt_bytes = generated.receipt_bytes;
+        let prover_session_id = generated.session_id;
+        let receipt_kind = generated.receipt_kind;
+        // External verifiers take the seal and journal apart from the receipt
+        let seal = generated.seal.as_deref().map(bytes_to_hex);
+        let journal = bytes_to_hex(&generated.journal);
+        let dev_mode = receipt_kind == "fake";
+        if dev_mode {
+            tracing::warn!(
+                "Request {} was proven in dev mode; its receipt is fake and proves nothing",
+                request_id
+            );
+        } else if receipt_kind != req.receipt_kind.name() {
+            tracing::info!(
+                "Requested a {} receipt, the prover produced a {} one",
+                req.receipt_kind.name(),
+                receipt_kind
+            );
+        }
+
+        tracing::info!(
+            "Proof generation completed: receipt_size={} bytes, attempts={}, duration={:?}",
+            receipt_bytes.len(),
+            prove_attempts,
+            generation_duration
+        );
+
+        let request_digest = canonical_request_digest(&req);
+        let proof_id = bytes32_to_hex_string(&digest::proof_id(
+            &request_digest,
+            &image_id,
+            &journal_digest(&output),
+        ));
+
+        let image_id: Vec<String> = image_id.iter().map(|&x| x.to_string()).collect();
+        let request_digest = bytes32_to_hex_string(&request_digest);
+        // After a guest upgrade the same request proves under a new id; the earlier proof
+        // keeps its record and image ID, and this one points back to it
+        let reproves = self
+            .store
+            .find_other_image(&request_digest, &image_id)
+            .unwrap_or_else(|e| {
+                tracing::warn!("Could not look up earlier proofs of this request: {}", e);
+                None
+            });
+        if let Some(earlier) = &reproves {
+            tracing::info!(
+                "Request was proven before as {} by image {}; this proof is made by image {}",
+                earlier.proof_id,
+                earlier.image_id.join(","),
+                image_id.join(",")
+            );
+        }
+
+        let (proof_base64, proof_url) =
+            self.place_receipt(&proof_id, &receipt_bytes, config.inline_proof_limit)?;
+
+        let artifact = ProofArtifact {
+            banned_list_hash: bytes32_to_hex_string(&output.banned_list_hash),
+            cached: false,
+            compliant: output.compliant,
+            defaults_digest: bytes32_to_hex_string(&output.defaults_digest),
+            dev_mode,
+            fast_path,
+            first_failure: FirstFailure::from_outputs(&output),
+            generation_duration_ms: generation_duration.as_millis() as u64,
+            image_id,
+            imported: false,
+            imported_by: None,
+            journal,
+            key_type: list_key_type(&req.merkle_proofs),
+            leaf_indexes: None,
+            list_info_hash: req
+                .list_info
+                .as_ref()
+                .map(|_| bytes32_to_hex_string(&output.list_info_hash)),
+            list_info: req.list_info.clone(),
+            proof: proof_base64,
+            proof_count: output.proof_count,
+            proof_id,
+            proof_size: receipt_bytes.len(),
+            proof_type: req.mode,
+            proof_url,
+            prove_attempts,
+            prover_backend,
+            prover_opts,
+            prover_session_id,
+            purls: req.merkle_proofs.iter().map(|p| p.key.clone()).collect(),
+            reasons: banned_reasons(&req.merkle_proofs),
+            reasons_hash: bytes32_to_hex_string(&output.reasons_hash),
+            receipt_kind,
+            receipt_sha256: bytes_to_hex(&Sha256::digest(&receipt_bytes)),
+            reproducible: req.reproducible,
+            reproves,
+            request_digest,
+            request_id,
+            requester_commitment: req
+                .requester
+                .as_ref()
+                .map(|_| bytes32_to_hex_string(&output.requester_commitment)),
+            root_alias: req.root_alias.clone(),
+            root_hash: bytes32_to_hex_string(&output.root_hash),
+            salt_commitment: blinding_salt.map(|_| bytes32_to_hex_string(&output.salt_commitment)),
+            seal,
+            sealed_purls: None,
+            sequence,
+            template: req.template.clone(),
+            timestamp,
+            receipt_bytes,
+        };
+
+        // The record is best-effort: the caller still gets the proof if it cannot be saved.
+        // A tenant that redacts its keys gets a record only its key reveals them from
+        let record = match &req.redaction {
+            Some(key) => seal_record(&artifact, key).map(Cow::Owned),
+            None => Ok(Cow::Borrowed(&artifact)),
+        };
+        // Only a stored proof can be served again, so only a stored one is cached
+        match record.and_then(|record| self.store.save_record(&record)) {
+            Ok(()) => self.cache.insert(
+                cache_key,
+                &artifact,
+                config.proof_cache_entries,
+                config.proof_cache_ttl,
+            ),
+            Err(e) => tracing::warn!(
+                "Failed to persist proof record: {}. Proof data will still be returned",
+                e
+            ),
+        }
+
+        Ok(artifact)
+    }
+
+    /// Verify proofs made elsewhere against this instance's guest image and store them, as
+    /// `POST /proofs/import` does. Each document is checked on its own: one that does not
+    /// parse, names another image, fails verification or contradicts its journal is
+    /// reported invalid, and one whose proof id is already stored is left as it is. Only
+    /// a failure of the proof store fails the batch; documents before it stay imported.
+    pub async fn import_proofs(
+        &self,
+        documents: Vec<serde_json::Value>,
+        submitter: Option<String>,
+        redaction: Option<RedactionKey>,
+    ) -> Result<ImportProofsResponse> {
+        let Some(backend) = self.backend.clone() else {
+            let err_msg = "Importing is disabled: this instance runs without a prover to verify receipts with";
+            tracing::error!("{}", err_msg);
+            return Err(Error::ProverDisabled(err_msg.to_string()));
+        };
+
+        // Verifying receipts blocks, like proving them
+        let service = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let config = service.config();
+            let mut results = Vec::with_capacity(documents.len());
+            for (index, document) in documents.into_iter().enumerate() {
+                let mut artifact = match service.imported_artifact(
+                    backend.as_ref(),
+                    document,
+                    submitter.as_deref(),
+                ) {
+                    Ok(artifact) => artifact,
+                    Err(e) => {
+                        tracing::warn!("Rejected imported proof {}: {}", index, e);
+                        results.push(ImportResult {
+                            index,
+                            status: ImportStatus::Invalid,
+                            proof_id: None,
+                            error: Some(e.to_string()),
+                        });
+                        continue;
+                    }
+                };
+                if service.store.contains_record(&artifact.proof_id)? {
+                    tracing::info!(
+                        "Imported proof {} is stored already as {}",
+                        index,
+                        artifact.proof_id
+                    );
+                    results.push(ImportResult {
+                        index,
+                        status: ImportStatus::Duplicate,
+                        proof_id: Some(artifact.proof_id),
+                        error: None,
+                    });
+                    continue;
+                }
+
+                (artifact.proof, artifact.proof_url) = service.place_receipt(
+                    &artifact.proof_id,
+                    &artifact.receipt_bytes,
+                    config.inline_proof_limit,
+                )?;
+                match &redaction {
+                    Some(key) => service.store.save_record(&seal_record(&artifact, key)?)?,
+                    None => service.store.save_record(&artifact)?,
+                }
+                tracing::info!("Imported proof {} as {}", index, artifact.proof_id);
+                results.push(ImportResult {
+                    index,
+                    status: ImportStatus::Imported,
+                    proof_id: Some(artifact.proof_id),
+                    error: None,
+                });
+            }
+
+            let count = |status| results.iter().filter(|r| r.status == status).count();
+            Ok(ImportProofsResponse {
+                imported: count(ImportStatus::Imported),
+                duplicates: count(ImportStatus::Duplicate),
+                invalid: count(ImportStatus::Invalid),
+                results,
+            })
+        })
+        .await
+        .map_err(|e| {
+            let err_msg = format!("Import task failed to run: {e}");
+            tracing::error!("{}", err_msg);
+            Error::Internal(err_msg)
+        })?
+    }
+
+    /// The record an imported proof document is stored as, once its receipt verifies and
+    /// the document agrees with the journal. The receipt is not placed yet: `proof` and
+    /// `proof_url` are `None`.
+    fn imported_artifact(
+        &self,
+        backend: &dyn ProvingBackend,
+        document: serde_json::Value,
+        submitter: Option<&str>,
+    ) -> Result<ProofArtifact> {
+        let accept_bare = self.config().record_format.accept_bare;
+        let document = envelope::open(document, accept_bare)
+            .map_err(|e| Error::InvalidRequest(format!("Cannot import the document: {e}")))?;
+        let doc: ImportedProof = serde_json::from_value(document.data)?;
+        let image_id = backend.image_id();
+        let trusted: Vec<String> = image_id.iter().map(|&x| x.to_string()).collect();
+        if doc.image_id != trusted {
+            return Err(Error::InvalidRequest(format!(
+                "Image ID {} is not the one this instance verifies against ({})",
+                doc.image_id.join(","),
+                trusted.join(",")
+            )));
+        }
+        let request_digest = hex_to_bytes32(&doc.request_digest)?;
+        let receipt_bytes = general_purpose::STANDARD
+            .decode(&doc.proof)
+            .map_err(|e| Error::InvalidRequest(format!("Receipt is not valid base64: {e}")))?;
+
+        let verified = backend.verify(&receipt_bytes)?;
+        let output = verified.outputs;
+        if output.input_status != input::status::OK {
+            return Err(Error::InvalidRequest(format!(
+                "The receipt proves that the guest rejected its input: {} (status {})",
+                input::status::name(output.input_status),
+                output.input_status
+            )));
+        }
+        if output.defaults_digest != DEFAULTS_DIGEST {
+            return Err(Error::InvalidRequest(format!(
+                "The receipt commits defaults digest {} but this build's is {}",
+                hex::encode(output.defaults_digest),
+                hex::encode(DEFAULTS_DIGEST)
+            )));
+        }
+        let key_type = KeyType::from_code(output.key_type).ok_or_else(|| {
+            Error::InvalidRequest(format!(
+                "The receipt commits unknown key type {}",
+                output.key_type
+            ))
+        })?;
+        let proof_type = ProofType::from_code(output.proof_type).ok_or_else(|| {
+            Error::InvalidRequest(format!(
+                "The receipt commits unknown proof type {}",
+                output.proof_type
+            ))
+        })?;
+        let proof_count = output.proof_count as usize;
+        if !doc.purls.is_empty() && doc.purls.len() != proof_count {
+            return Err(Error::InvalidRequest(format!(
+                "{} purls were given but the receipt commits {} proofs",
+                doc.purls.len(),
+                proof_count
+            )));
+        }
+        if doc.reasons.len() > proof_count {
+            return Err(Error::InvalidRequest(format!(
+                "{} reasons were given but the receipt commits {} proofs",
+                doc.reasons.len(),
+                proof_count
+            )));
+        }
+        if let Some(info) = &doc.list_info
+            && compute_list_info_hash(info) != output.list_info_hash
+        {
+            return Err(Error::InvalidRequest(
+                "list_info does not hash to the list info hash the receipt commits".to_string(),
+            ));
+        }
+
+        let proof_id = bytes32_to_hex_string(&digest::proof_id(
+            &request_digest,
+            &image_id,
+            &journal_digest(&output),
+        ));
+        let request_digest = bytes32_to_hex_string(&request_digest);
+        let reproves = self
+            .store
+            .find_other_image(&request_digest, &trusted)
+            .unwrap_or_else(|e| {
+                tracing::warn!("Could not look up earlier proofs of this request: {}", e);
+                None
+            });
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let (timestamp, clock) = match doc.timestamp {
+            Some(timestamp) => (timestamp, None),
+            None => (now, Some(now)),
+        };
+        let nonzero = |commitment: &[u8; 32]| {
+            (*commitment != [0u8; 32]).then(|| bytes32_to_hex_string(commitment))
+        };
+
+        Ok(ProofArtifact {
+            banned_list_hash: bytes32_to_hex_string(&output.banned_list_hash),
+            cached: false,
+            compliant: output.compliant,
+            defaults_digest: bytes32_to_hex_string(&output.defaults_digest),
+            dev_mode: verified.receipt_kind == "fake",
+            fast_path: false,
+            first_failure: FirstFailure::from_outputs(&output),
+            generation_duration_ms: 0,
+            image_id: trusted,
+            imported: true,
+            imported_by: submitter.map(str::to_string),
+            journal: bytes_to_hex(&verified.journal),
+            key_type,
+            leaf_indexes: None,
+            list_info_hash: doc
+                .list_info
+                .as_ref()
+                .map(|_| bytes32_to_hex_string(&output.list_info_hash)),
+            list_info: doc.list_info,
+            proof: None,
+            proof_count: output.proof_count,
+            proof_id,
+            proof_size: receipt_bytes.len(),
+            proof_type,
+            proof_url: None,
+            prove_attempts: 0,
+            prover_backend: "imported",
+            prover_opts: None,
+            prover_session_id: None,
+            purls: doc.purls,
+            reasons: doc
+                .reasons
+                .into_iter()
+                .map(|reason| BannedReason {
+                    purl: reason.purl,
+                    leaf_index: normalize_hex(&reason.leaf_index),
+                    value: reason.value,
+                    reason: reason_name(reason.value),
+                })
+                .collect(),
+            reasons_hash: bytes32_to_hex_string(&output.reasons_hash),
+            receipt_kind: verified.receipt_kind,
+            receipt_sha256: bytes_to_hex(&Sha256::digest(&receipt_bytes)),
+            reproducible: false,
+            reproves,
+            request_digest,
+            request_id: Uuid::now_v7().to_string(),
+            requester_commitment: nonzero(&output.requester_commitment),
+            root_alias: None,
+            root_hash: bytes32_to_hex_string(&output.root_hash),
+            salt_commitment: nonzero(&output.salt_commitment),
+            seal: verified.seal.as_deref().map(bytes_to_hex),
+            sealed_purls: None,
+            sequence: self.sequence.next(clock),
+            template: None,
+            timestamp,
+            receipt_bytes,
+        })
+    }
+
+    /// Host-only preflight: the same validation as [`ProverService::prove_compact`] plus
+    /// the guest's recomputation of every proof against the root, without invoking the
+    /// zkVM. The result is not cryptographic evidence; no receipt is produced.
+    pub fn validate(
+        &self,
+        mut req: ProveCompactMerkleRequest,
+    ) -> Result<ValidateCompactMerkleResponse> {
+        let config = self.config();
+        validate_proof_count(&req, config.max_proofs_per_request)?;
+        resolve_root_alias(&mut req, &config.root_aliases)?;
+
+        tracing::info!(
+            "Received compact merkle validate request with depth={}, root={}, proof_count={}",
+            req.depth,
+            req.root,
+            req.merkle_proofs.len()
+        );
+
+        let root_hash = validate_request(&req)?;
+        normalize_proof_encoding(&mut req)?;
+        validate_proof_sizes(&req)?;
+        let blinding_salt = resolve_blinding_salt(&req)?;
+        apply_strict_mode(&mut req)?;
+        validate_list_info(&req)?;
+
+        let key_display = req.key_display();
+        let results: Vec<ProofValidationResult> = req
+            .merkle_proofs
+            .par_iter()
+            .enumerate()
+            .map(|(index, proof)| {
+                let outcome = validate_compact_proof(proof, key_display)
+                    .map_err(|e| e.to_string())
+                    .and_then(|decoded| recompute_compact_proof(proof, &decoded, key_display));
+
+                match outcome {
+                    Ok(computed) if computed == root_hash => ProofValidationResult {
+                        index,
+                        purl: proof.key.clone(),
+                        valid: true,
+                        computed_root: Some(bytes32_to_hex_string(&computed)),
+                        error: None,
+                    },
+                    Ok(computed) => ProofValidationResult {
+                        index,
+                        purl: proof.key.clone(),
+                        valid: false,
+                        computed_root: Some(bytes32_to_hex_string(&computed)),
+                        error: Some(format!(
+                            "Computed root {} does not match requested root {}",
+                            bytes32_to_hex_string(&computed),
+                            bytes32_to_hex_string(&root_hash)
+                        )),
+                    },
+                    Err(e) => ProofValidationResult {
+                        index,
+                        purl: proof.key.clone(),
+                        valid: false,
+                        computed_root: None,
+                        error: Some(e),
+                    },
+                }
+            })
+            .collect();
+
+        let banned_list: Vec<&str> = req.merkle_proofs.iter().map(|p| p.key.as_str()).collect();
+        let valid = results.iter().all(|r| r.valid);
+        let reasons = banned_reasons(&req.merkle_proofs);
+        let statement_holds = req.merkle_proofs.iter().all(|p| {
+            parse_leaf_value(&p.value).is_some_and(|value| req.mode.accepts(value))
+        });
+
+        // Same commitment the guest makes; only meaningful once every proof verified
+        let reasons_hash = valid.then(|| {
+            bytes32_to_hex_string(&compute_reasons_hash(req.merkle_proofs.iter().filter_map(|p| {
+                Some((hex_to_bytes32(&p.leaf_index).ok()?, parse_leaf_value(&p.value)?))
+            })))
+        });
+
+        tracing::info!(
+            "Validation completed: valid={}, failing proofs={}",
+            valid,
+            results.iter().filter(|r| !r.valid).count()
+        );
+
+        Ok(ValidateCompactMerkleResponse {
+            valid,
+            cryptographic: false,
+            root_hash: bytes32_to_hex_string(&root_hash),
+            root_alias: req.root_alias.clone(),
+            banned_list_hash: bytes32_to_hex_string(&compute_banned_list_commitment(
+                &banned_list,
+                blinding_salt.as_ref(),
+            )),
+            key_type: list_key_type(&req.merkle_proofs),
+            proof_count: req.merkle_proofs.len(),
+            proof_type: req.mode,
+            compliant: valid && statement_holds,
+            reasons_hash,
+            reasons,
+            list_info_hash: req
+                .list_info
+                .as_ref()
+                .map(|info| bytes32_to_hex_string(&compute_list_info_hash(info))),
+            list_info: req.list_info,
+            salt_commitment: blinding_salt.as_ref().map(|salt| bytes32_to_hex_string(&compute_salt_commitment(salt))),
+            results,
+        })
+    }
+
+    /// The proof cached for `key`, with its receipt read back, if it still answers `req`:
+    /// made by the guest `image_id`, at the timestamp `req` pins if any, and with its
+    /// record and receipt still in the proof store. An entry failing any of this is dropped.
+    fn cached_proof(
+        &self,
+        key: &[u8; 32],
+        req: &ProveCompactMerkleRequest,
+        image_id: [u32; 8],
+        ttl: Duration,
+    ) -> Option<ProofArtifact> {
+        let mut artifact = self.cache.get(key, ttl)?;
+        let image_id: Vec<String> = image_id.iter().map(|&x| x.to_string()).collect();
+        let usable = artifact.image_id == image_id
+            && req.timestamp.is_none_or(|t| t == artifact.timestamp)
+            && self
+                .store
+                .contains_record(&artifact.proof_id)
+                .unwrap_or(false);
+        let receipt = if !usable {
+            None
+        } else if let Some(proof) = &artifact.proof {
+            general_purpose::STANDARD.decode(proof).ok()
+        } else {
+            self.store
+                .find_receipt(&artifact.proof_id)
+                .ok()
+                .flatten()
+                .and_then(|path| std::fs::read(path).ok())
+        };
+        let Some(receipt_bytes) = receipt else {
+            tracing::info!(
+                "Proof {} no longer answers its request; dropped from the proof cache",
+                artifact.proof_id
+            );
+            self.cache.remove(key);
+            return None;
+        };
+        artifact.receipt_bytes = receipt_bytes;
+        Some(artifact)
+    }
+
+    /// Inline a receipt as base64, or, above `inline_limit` bytes, store it raw to be
+    /// served from /proofs/{id}/raw. Returns the artifact's `proof` and `proof_url`.
+    fn place_receipt(
+        &self,
+        proof_id: &str,
+        receipt_bytes: &[u8],
+        inline_limit: usize,
+    ) -> Result<(Option<String>, Option<String>)> {
+        if receipt_bytes.len() <= inline_limit {
+            return Ok((Some(general_purpose::STANDARD.encode(receipt_bytes)), None));
+        }
+        self.store.store_receipt(proof_id, receipt_bytes).map_err(|e| {
+            let err_msg = format!(
+                "Receipt of {} bytes exceeds the inline limit of {} bytes and could not be stored: {}",
+                receipt_bytes.len(),
+                inline_limit,
+                e
+            );
+            tracing::error!("{}", err_msg);
+            Error::Storage(err_msg)
+        })?;
+        Ok((None, Some(format!("/proofs/{proof_id}/raw"))))
+    }
+}
+
+/// Run the backend, retrying transient failures with exponential backoff on the same
+/// blocking thread. Returns the proof and the number of attempts it took; once retries
+/// are exhausted, or the next one could not finish before `deadline`, the error lists
+/// every attempt.
+fn prove_with_retries(
+    backend: &dyn ProvingBackend,
+    proofs: &[CompactMerkleProof],
+    public_inputs: &MerklePublicInputs,
+    retry: &RetryPolicy,
+    deadline: Option<Instant>,
+) -> Result<(GeneratedProof, u32)> {
+    let mut failures = Vec::new();
+    let mut remote = false;
+
+    for attempt in 1..=retry.max_retries.saturating_add(1) {
+        let error = match backend.prove(proofs, public_inputs) {
+            Ok(generated) => {
+                record_attempt("success");
+                return Ok((generated, attempt));
+            }
+            Err(e) => e,
+        };
+        remote = matches!(error, Error::RemoteProver(_));
+
+        if !retry.is_transient(&error) {
+            record_attempt("permanent_failure");
+            if failures.is_empty() {
+                return Err(error);
+            }
+            failures.push(format!("attempt {attempt}: {error}"));
+            break;
+        }
+        record_attempt("transient_failure");
+        failures.push(format!("attempt {attempt}: {error}"));
+
+        if attempt > retry.max_retries {
+            break;
+        }
+        let backoff = retry.backoff(attempt);
+        if deadline.is_some_and(|d| Instant::now() + backoff >= d) {
+            tracing::warn!("Not retrying transient prover failure: the request deadline would pass first");
+            break;
+        }
+        tracing::warn!(
+            "Transient prover failure on attempt {} of {}, retrying in {}ms: {}",
+            attempt,
+            retry.max_retries + 1,
+            backoff.as_millis(),
+            error
+        );
+        std::thread::sleep(backoff);
+    }
+
+    let err_msg = format!(
+        "Proving failed after {} attempt(s): {}",
+        failures.len(),
+        failures.join("; ")
+    );
+    // Keep a remote failure distinguishable, so it is reported as a bad gateway
+    Err(if remote {
+        Error::RemoteProver(err_msg)
+    } else {
+        Error::ProverFailure(err_msg)
+    })
+}
+
+fn record_attempt(outcome: &str) {
+    let _ = metrics().inc_counter(metric::PROVE_ATTEMPTS_TOTAL, &[(label::OUTCOME, outcome)]);
+}
+
+fn record_cache_lookup(outcome: &str) {
+    let _ = metrics().inc_counter(
+        metric::PROOF_CACHE_LOOKUPS_TOTAL,
+        &[(label::OUTCOME, outcome)],
+    );
+}
+
+#[cfg(feature = "prover")]
+fn default_backend(config: &ProverConfig) -> Option<Arc<dyn ProvingBackend>> {
+    match &config.bonsai {
+        #[cfg(feature = "bonsai")]
+        Some(bonsai) => {
+            tracing::info!("Proving remotely on Bonsai at {}", bonsai.api_url);
+            Some(Arc::new(BonsaiBackend::new(bonsai.clone())))
+        }
+        #[cfg(not(feature = "bonsai"))]
+        Some(_) => {
+            tracing::warn!(
+                "BONSAI_API_URL is set but this build lacks the bonsai feature; proving locally"
+            );
+            Some(Arc::new(ZkvmBackend))
+        }
+        None => Some(Arc::new(ZkvmBackend)),
+    }
+}
+
+#[cfg(not(feature = "prover"))]
+fn default_backend(_config: &ProverConfig) -> Option<Arc<dyn ProvingBackend>> {
+    None
+}
+
+fn default_store(config: &ProverConfig) -> Arc<dyn ProofStore> {
+    match &config.database_path {
+        #[cfg(feature = "sqlite")]
+        Some(path) => Arc::new(
+            SqliteProofStore::new(path, config.proofs_dir.clone())
+                .with_format(config.record_format.clone()),
+        ),
+        #[cfg(not(feature = "sqlite"))]
+        Some(_) => {
+            tracing::warn!(
+                "PROOF_STORE=sqlite is set but this build lacks the sqlite feature; keeping records in PROOFS_DIR"
+            );
+            Arc::new(
+                FsProofStore::new(config.proofs_dir.clone())
+                    .with_format(config.record_format.clone()),
+            )
+        }
+        None => Arc::new(
+            FsProofStore::new(config.proofs_dir.clone()).with_format(config.record_format.clone()),
+        ),
+    }
+}
+
+/// Conservative estimate of the time proving `proof_count` proofs takes
+pub fn estimated_prove_time(proof_count: usize) -> Duration {
+    ESTIMATED_PROVE_BASE + ESTIMATED_PROVE_PER_PROOF * proof_count as u32
+}
+
+/// Fail fast when the conservative proving estimate does not fit the time left.
+fn check_deadline_fits(deadline: Instant, proof_count: usize) -> Result<()> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    let estimate = estimated_prove_time(proof_count);
+
+    if estimate > remaining {
+        let err_msg = format!(
+            "Request deadline cannot be met: proving {} proof(s) is estimated at {}s, but only {}s remain",
+            proof_count,
+            estimate.as_secs(),
+            remaining.as_secs()
+        );
+        tracing::error!("{}", err_msg);
+        return Err(Error::DeadlineExceeded(err_msg));
+    }
+
+    tracing::info!(
+        "Deadline in {}s; estimated proving time {}s",
+        remaining.as_secs(),
+        estimate.as_secs()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sbom_common::smt::SparseMerkleTree;
+
+    use super::*;
+    use crate::models::{
+        CompactMerkleProof, EarlierProof, MerklePublicInputs, MerklePublicOutputs,
+    };
+    use crate::storage::RecordFilter;
+
+    /// Proves instantly, committing a compliant journal for whatever it is given
+    struct EchoBackend;
+
+    impl ProvingBackend for EchoBackend {
+        fn image_id(&self) -> [u32; 8] {
+            [3; 8]
+        }
+
+        fn prove(
+            &self,
+            proofs: &[CompactMerkleProof],
+            public_inputs: &MerklePublicInputs,
+        ) -> Result<GeneratedProof> {
+            let outputs = MerklePublicOutputs {
+                root_hash: public_inputs.root_hash,
+                banned_list_hash: [1; 32],
+                compliant: true,
+                proof_count: proofs.len() as u32,
+                reasons_hash: [2; 32],
+                list_info_hash: [0; 32],
+                input_status: 0,
+                salt_commitment: [0; 32],
+                key_type: 0,
+                proof_type: 0,
+                defaults_digest: DEFAULTS_DIGEST,
+                requester_commitment: [0; 32],
+                first_failure: 0,
+                first_failure_index: 0,
+                first_failure_key_hash: [0; 32],
+            };
+            Ok(GeneratedProof {
+                journal: digest::encode_journal(&outputs),
+                receipt_bytes: serde_json::to_vec(&outputs)?,
+                outputs,
+                session_id: None,
+                receipt_kind: "echo",
+                seal: None,
+            })
+        }
+    }
+
+    /// [`EchoBackend`] after a guest upgrade changed its image ID
+    struct RotatedBackend;
+
+    impl ProvingBackend for RotatedBackend {
+        fn image_id(&self) -> [u32; 8] {
+            [4; 8]
+        }
+
+        fn prove(
+            &self,
+            proofs: &[CompactMerkleProof],
+            public_inputs: &MerklePublicInputs,
+        ) -> Result<GeneratedProof> {
+            EchoBackend.prove(proofs, public_inputs)
+        }
+    }
+
+    type Corruption = fn(&mut GeneratedProof);
+
+    /// Proves like [`EchoBackend`], then damages the output
+    struct CorruptingBackend(Corruption);
+
+    impl ProvingBackend for CorruptingBackend {
+        fn image_id(&self) -> [u32; 8] {
+            EchoBackend.image_id()
+        }
+
+        fn prove(
+            &self,
+            proofs: &[CompactMerkleProof],
+            public_inputs: &MerklePublicInputs,
+        ) -> Result<GeneratedProof> {
+            let mut generated = EchoBackend.prove(proofs, public_inputs)?;
+            (self.0)(&mut generated);
+            Ok(generated)
+        }
+    }
+
+    /// Byte offset of the journal field `name`
+    fn offset(name: &str) -> usize {
+        let fields = sbom_common::journal::MERKLE_PUBLIC_OUTPUTS.fields;
+        let before = fields.iter().take_while(|field| field.name != name);
+        4 * before.map(|field| field.words).sum::<usize>()
+    }
+
+    fn request() -> ProveCompactMerkleRequest {
+        let mut tree = SparseMerkleTree::default();
+        tree.insert("pkg:npm/evil@1.0.0", 1);
+        let proof = tree.prove("pkg:npm/a@1");
+        serde_json::from_value(serde_json::json!({
+            "depth": 256,
+            "root": hex::encode(tree.root()),
+            "merkle_proofs": [{
+                "purl": "pkg:npm/a@1",
+                "value": proof.value.to_string(),
+                "leaf_index": hex::encode(proof.leaf_index),
+                "siblings": proof.siblings.iter().map(hex::encode).collect::<Vec<_>>(),
+                "bitmap": hex::encode(proof.bitmap),
+            }],
+        }))
+        .unwrap()
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn embedders_prove_with_their_own_backend_and_store() {
+        let dir = std::env::temp_dir().join("proving-service-embedded");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = ProverConfig {
+            proofs_dir: dir.join("unused"),
+            ..ProverConfig::default()
+        };
+        let store: Arc<dyn ProofStore> = Arc::new(FsProofStore::new(dir));
+        let service = ProverService::without_prover(config)
+            .with_store(store.clone())
+            .with_backend(Arc::new(EchoBackend));
+
+        let artifact = block_on(service.prove_compact(request())).unwrap();
+        assert!(artifact.compliant);
+        assert_eq!(artifact.proof_count, 1);
+        assert_eq!(artifact.prover_backend, "custom");
+        assert!(store.find_record(&artifact.proof_id).unwrap().is_some());
+    }
+
+    #[test]
+    fn a_service_without_a_backend_refuses_to_prove() {
+        let config = ProverConfig {
+            proofs_dir: std::env::temp_dir().join("proving-service-no-backend"),
+            ..ProverConfig::default()
+        };
+        let service = ProverService::without_prover(config);
+        let result = block_on(service.prove_compact(request()));
+        assert!(matches!(result, Err(Error::ProverDisabled(_))));
+    }
+
+    #[test]
+    fn corrupted_output_is_quarantined_not_served() {
+        let flavors: [(Corruption, &str); 7] = [
+            (
+                |g| g.journal.resize(20 * 1024, 0),
+                "exceeds MAX_JOURNAL_BYTES",
+            ),
+            (
+                |g| g.journal.extend_from_slice(b"echo"),
+                "matches no known layout",
+            ),
+            (
+                |g| g.journal.truncate(g.journal.len() - 32 * 4),
+                "journal schema version",
+            ),
+            (
+                |g| g.journal[offset("compliant")] = 2,
+                "out of range for bool",
+            ),
+            (
+                |g| g.journal[offset("root_hash") + 1] = 1,
+                "out of range for [u8; 32]",
+            ),
+            (|g| g.outputs.proof_count = 2, "do not encode back"),
+            (
+                |g| {
+                    g.outputs.root_hash = [9; 32];
+                    g.journal = digest::encode_journal(&g.outputs);
+                },
+                "was requested",
+            ),
+        ];
+
+        for (n, (corrupt, reason)) in flavors.into_iter().enumerate() {
+            let dir = std::env::temp_dir().join(format!("proving-service-corrupt-{n}"));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            let store = FsProofStore::new(&dir);
+            let config = ProverConfig {
+                proofs_dir: dir.clone(),
+                ..ProverConfig::default()
+            };
+            let service = ProverService::without_prover(config)
+                .with_store(Arc::new(store.clone()))
+                .with_backend(Arc::new(CorruptingBackend(corrupt)));
+
+            let result = block_on(service.prove_compact(request()));
+            let Err(Error::InvalidOutput(msg)) = result else {
+                panic!("{reason}: the corrupted output was accepted");
+            };
+            assert!(msg.contains(reason), "{msg}");
+            let quarantined = std::fs::read_dir(dir.join("quarantine")).unwrap().count();
+            assert_eq!(quarantined, 2, "{reason}");
+            let stored = store.list_records(&RecordFilter::default()).unwrap();
+            assert!(stored.is_empty(), "{reason}");
+        }
+    }
+
+    #[test]
+    fn proofs_keep_the_image_they_were_made_with() {
+        let dir = std::env::temp_dir().join("proving-service-rotated-image");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = FsProofStore::new(&dir);
+        let service = |backend: Arc<dyn ProvingBackend>| {
+            let config = ProverConfig {
+                proofs_dir: dir.clone(),
+                ..ProverConfig::default()
+            };
+            ProverService::without_prover(config)
+                .with_store(Arc::new(store.clone()))
+                .with_backend(backend)
+        };
+        let image = |word: u32| vec![word.to_string(); 8];
+
+        let before = block_on(service(Arc::new(EchoBackend)).prove_compact(request())).unwrap();
+        assert_eq!(before.image_id, image(3));
+        assert_eq!(before.reproves, None);
+
+        // The same request after the upgrade proves anew and links the earlier proof
+        let after = block_on(service(Arc::new(RotatedBackend)).prove_compact(request())).unwrap();
+        assert_eq!(after.image_id, image(4));
+        assert_ne!(after.proof_id, before.proof_id);
+        let earlier = EarlierProof {
+            proof_id: before.proof_id.clone(),
+            image_id: image(3),
+        };
+        assert_eq!(after.reproves, Some(earlier));
+
+        // Both records are kept, each with its own image ID
+        let mut stored: Vec<(String, Vec<String>)> = store
+            .list_records(&RecordFilter::default())
+            .unwrap()
+            .into_iter()
+            .map(|summary| (summary.proof_id, summary.image_id))
+            .collect();
+        stored.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            stored,
+            [(before.proof_id, image(3)), (after.proof_id, image(4))]
+        );
+    }
+
+    #[test]
+    fn single_proofs_take_the_fast_path_to_the_same_journal() {
+        let mut runs = Vec::new();
+        for fast_path_max_proofs in [1, 0] {
+            let dir = std::env::temp_dir()
+                .join(format!("proving-service-fast-path-{fast_path_max_proofs}"));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            let config = ProverConfig {
+                proofs_dir: dir.clone(),
+                fast_path_max_proofs,
+                ..ProverConfig::default()
+            };
+            let service = ProverService::without_prover(config)
+                .with_store(Arc::new(FsProofStore::new(dir)))
+                .with_backend(Arc::new(EchoBackend));
+            runs.push(block_on(service.prove_compact(request())).unwrap());
+        }
+
+        let (fast, batch) = (&runs[0], &runs[1]);
+        assert!(fast.fast_path);
+        assert!(!batch.fast_path);
+        assert_eq!(fast.journal, batch.journal);
+        assert_eq!(fast.proof_id, batch.proof_id);
+    }
+}
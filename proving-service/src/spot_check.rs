@@ -0,0 +1,156 @@
+//! Single-purl "spot check" proving.
+//!
+//! `POST /prove-purl` proves one purl against the root of the SMT snapshot loaded from
+//! `SMT_SNAPSHOT`, synthesizing the compact proof itself instead of requiring the caller
+//! to assemble one. Spot checks have their own concurrency limit,
+//! `SPOT_CHECK_CONCURRENCY`: a spot check arriving while all slots are taken is turned
+//! away with 429 rather than queueing for the prover behind batch requests. Templates
+//! with a `snapshot` source draw their proofs from the same snapshot.
+
+use std::path::Path;
+
+use sbom_common::smt::{SmtSnapshot, SparseMerkleTree, TREE_DEPTH};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::error::{Error, Result};
+use crate::models::{CompactMerkleProof, ProveCompactMerkleRequest};
+use crate::utils::KeyType;
+
+/// Building a tree recurses once per level, more than a default thread stack holds
+const SNAPSHOT_LOADER_STACK: usize = 64 * 1024 * 1024;
+
+pub struct SpotCheck {
+    tree: Option<SparseMerkleTree>,
+    permits: Semaphore,
+    concurrency: usize,
+}
+
+impl SpotCheck {
+    /// Spot checks against `tree`; without one, `/prove-purl` answers 501
+    pub fn new(tree: Option<SparseMerkleTree>, concurrency: usize) -> Self {
+        Self {
+            tree,
+            permits: Semaphore::new(concurrency),
+            concurrency,
+        }
+    }
+
+    /// Root of the loaded snapshot, if any
+    pub fn root(&self) -> Option<[u8; 32]> {
+        self.tree.as_ref().map(SparseMerkleTree::root)
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// A slot to prove in, or `None` when the limit is reached. Held until dropped
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.permits.try_acquire().ok()
+    }
+
+    /// A prove request for `purl` alone, with its proof drawn from the snapshot. Fails
+    /// unless `root` is the snapshot's root, since no other tree can be proven against.
+    pub fn request_for(
+        &self,
+        purl: &str,
+        root: [u8; 32],
+        root_alias: Option<String>,
+    ) -> Result<ProveCompactMerkleRequest> {
+        Ok(ProveCompactMerkleRequest {
+            depth: TREE_DEPTH,
+            root: hex::encode(root),
+            root_alias,
+            merkle_proofs: self.proofs_for(&[purl.to_string()], root)?,
+            proof_encoding: Default::default(),
+            reproducible: false,
+            timestamp: None,
+            expected_purls: None,
+            expected_banned_list_hash: None,
+            list_info: None,
+            max_response_bytes: None,
+            banned_list_salt: None,
+            mode: Default::default(),
+            receipt_kind: Default::default(),
+            skip_preverify: false,
+            force: false,
+            prover_opts: None,
+            template: None,
+            requester: None,
+            redaction: None,
+        })
+    }
+
+    /// Compact proofs of `purls` from the snapshot, in order; fails unless `root` is the
+    /// snapshot's root
+    pub fn proofs_for(&self, purls: &[String], root: [u8; 32]) -> Result<Vec<CompactMerkleProof>> {
+        let Some(tree) = &self.tree else {
+            let err_msg =
+                "Proving from the SMT snapshot is not available: SMT_SNAPSHOT is not configured";
+            tracing::error!("{}", err_msg);
+            return Err(Error::ProverDisabled(err_msg.to_string()));
+        };
+        if root != tree.root() {
+            let err_msg = format!(
+                "No snapshot for root {}: proofs are drawn from the loaded snapshot, whose root is {}",
+                hex::encode(root),
+                hex::encode(tree.root())
+            );
+            tracing::error!("{}", err_msg);
+            return Err(Error::InvalidRequest(err_msg));
+        }
+
+        Ok(purls
+            .iter()
+            .map(|purl| {
+                let proof = tree.prove(purl);
+                CompactMerkleProof {
+                    key: purl.clone(),
+                    key_type: KeyType::Purl,
+                    value: proof.value.to_string(),
+                    leaf_index: hex::encode(proof.leaf_index),
+                    siblings: proof.siblings.iter().map(hex::encode).collect(),
+                    bitmap: hex::encode(proof.bitmap),
+                }
+            })
+            .collect())
+    }
+}
+
+/// Read a snapshot and rebuild its tree, checking the claimed root
+pub fn load_snapshot(path: &Path) -> Result<SparseMerkleTree> {
+    let raw = std::fs::read_to_string(path).map_err(|e| {
+        Error::InvalidConfig(format!(
+            "Cannot read SMT_SNAPSHOT '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let snapshot: SmtSnapshot = serde_json::from_str(&raw).map_err(|e| {
+        Error::InvalidConfig(format!("Invalid SMT_SNAPSHOT '{}': {}", path.display(), e))
+    })?;
+
+    let tree = std::thread::Builder::new()
+        .stack_size(SNAPSHOT_LOADER_STACK)
+        .spawn(move || SparseMerkleTree::import_snapshot(&snapshot))
+        .and_then(|loader| {
+            loader
+                .join()
+                .map_err(|_| std::io::Error::other("snapshot loader panicked"))
+        })?
+        .map_err(|e| {
+            Error::InvalidConfig(format!(
+                "SMT_SNAPSHOT '{}' does not rebuild: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+    tracing::info!(
+        "Loaded SMT snapshot '{}' with {} leaves, root {}",
+        path.display(),
+        tree.len(),
+        hex::encode(tree.root())
+    );
+    Ok(tree)
+}
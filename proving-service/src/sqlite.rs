@@ -0,0 +1,594 @@
+//! Proof records kept in SQLite.
+//!
+//! [`SqliteProofStore`] keeps one row per proof in the database at `DATABASE_PATH`, the
+//! enveloped record JSON verbatim alongside the columns listings and lookups filter on,
+//! so a record reads back byte for byte as [`FsProofStore`] would serve it. Receipts and
+//! quarantined output stay files under `PROOFS_DIR`, in the layout [`FsProofStore`] uses,
+//! so downloads are streamed from disk whichever store holds the records. Record files
+//! found under `PROOFS_DIR` are moved into the database by the first of the
+//! [`crate::migrations`], on the first start against it, so a store switched to SQLite
+//! keeps the proofs it held. Applied migrations are listed in the `migrations` table.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::models::{EarlierProof, ProofArtifact, ProofSummary};
+use crate::storage::{FsProofStore, ProofStore, RecordFilter, RecordFormat};
+
+/// How long a write waits for another connection to release the database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS proofs (
+        proof_id TEXT PRIMARY KEY,
+        request_digest TEXT NOT NULL,
+        image_id TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        sequence INTEGER NOT NULL,
+        receipt_sha256 TEXT NOT NULL,
+        record TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS proofs_by_time ON proofs (timestamp, sequence);
+    CREATE INDEX IF NOT EXISTS proofs_by_request ON proofs (request_digest);
+    CREATE TABLE IF NOT EXISTS migrations (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        applied_at INTEGER NOT NULL
+    );
+";
+
+/// The columns of a row, read from the record stored in it
+#[derive(Deserialize)]
+struct RecordColumns {
+    proof_id: String,
+    /// Absent from records older than request digests
+    #[serde(default)]
+    request_digest: String,
+    image_id: Vec<String>,
+    timestamp: u64,
+    /// Absent from records older than sequence numbers
+    #[serde(default)]
+    sequence: u64,
+    /// Absent from records older than content-addressed receipts
+    #[serde(default)]
+    receipt_sha256: String,
+}
+
+/// Stores records in the SQLite database at `path`, created with its table on first use,
+/// and receipts under `receipts_dir` as [`FsProofStore`] does. Re-proving an identical
+/// request with the same guest image replaces its row.
+#[derive(Debug, Clone)]
+pub struct SqliteProofStore {
+    path: PathBuf,
+    /// Opened on first use, so a database that cannot be opened fails requests rather
+    /// than the service's start
+    connection: Arc<Mutex<Option<Connection>>>,
+    format: RecordFormat,
+    receipts: FsProofStore,
+}
+
+impl SqliteProofStore {
+    pub fn new(path: impl Into<PathBuf>, receipts_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            connection: Arc::new(Mutex::new(None)),
+            format: RecordFormat::default(),
+            receipts: FsProofStore::new(receipts_dir),
+        }
+    }
+
+    pub fn with_format(mut self, format: RecordFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Run `f` on the connection, opening the database first if no call has yet
+    fn with_connection<T>(&self, f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        if connection.is_none() {
+            *connection = Some(self.open()?);
+        }
+        let connection = connection.as_ref().expect("connection was just opened");
+        f(connection).map_err(|e| self.error(e))
+    }
+
+    fn open(&self) -> Result<Connection> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                Error::Storage(format!(
+                    "Failed to create the directory of database '{}': {}",
+                    self.path.display(),
+                    e
+                ))
+            })?;
+        }
+        let connection = Connection::open(&self.path).map_err(|e| self.error(e))?;
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .and_then(|_| connection.execute_batch(SCHEMA))
+            .map_err(|e| self.error(e))?;
+        tracing::info!(
+            "Proof records are kept in SQLite database {}",
+            self.path.display()
+        );
+        Ok(connection)
+    }
+
+    fn error(&self, e: rusqlite::Error) -> Error {
+        Error::Storage(format!("Database '{}': {}", self.path.display(), e))
+    }
+
+    /// Insert the record `json`, whose payload is `data`, unless its proof already has a
+    /// row. Returns whether it was inserted.
+    fn insert_record(&self, data: serde_json::Value, json: &str) -> Result<bool> {
+        let columns: RecordColumns = serde_json::from_value(data)?;
+        let image_id = serde_json::to_string(&columns.image_id)?;
+        let inserted = self.with_connection(|db| {
+            db.execute(
+                "INSERT OR IGNORE INTO proofs
+                     (proof_id, request_digest, image_id, timestamp, sequence, receipt_sha256, record)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    columns.proof_id,
+                    columns.request_digest,
+                    image_id,
+                    columns.timestamp,
+                    columns.sequence,
+                    columns.receipt_sha256,
+                    json,
+                ],
+            )
+        })?;
+        Ok(inserted > 0)
+    }
+
+    /// Move the record files under `PROOFS_DIR` into the database, keeping the row of a
+    /// proof that already has one. Files that are not records are left where they are.
+    fn import_record_files(&self) -> Result<usize> {
+        let dir = self.receipts.dir();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut imported = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if !path.is_file() || path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let contents = std::fs::read(&path)?;
+            let opened = match self.format.open(&contents) {
+                Ok(opened) => opened,
+                Err(e) => {
+                    tracing::warn!("Leaving unreadable record file {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let json = if opened.is_bare() {
+                self.format.write(&opened.data)?
+            } else {
+                String::from_utf8_lossy(&contents).into_owned()
+            };
+            match self.insert_record(opened.data, &json) {
+                Ok(true) => imported += 1,
+                Ok(false) => tracing::info!(
+                    "Record file {} duplicates a row; keeping the row",
+                    path.display()
+                ),
+                Err(e) => {
+                    tracing::warn!("Leaving record file {}: {}", path.display(), e);
+                    continue;
+                }
+            }
+            std::fs::remove_file(&path)?;
+        }
+        if imported > 0 {
+            tracing::info!(
+                "Moved {} record files from {} into {}",
+                imported,
+                dir.display(),
+                self.path.display()
+            );
+        }
+        Ok(imported)
+    }
+
+    /// Name `receipt_sha256` as the receipt of `proof_id`, in its row and its record
+    fn name_receipt(&self, proof_id: &str, receipt_sha256: &str) -> Result<()> {
+        let record: Option<String> = self.with_connection(|db| {
+            db.query_row(
+                "SELECT record FROM proofs WHERE proof_id = ?1",
+                params![proof_id],
+                |row| row.get(0),
+            )
+            .optional()
+        })?;
+        let Some(record) = record else {
+            return Ok(());
+        };
+        let mut data = self.format.open(record.as_bytes())?.data;
+        data["receipt_sha256"] = receipt_sha256.into();
+        let json = self.format.write(&data)?;
+        self.with_connection(|db| {
+            db.execute(
+                "UPDATE proofs SET receipt_sha256 = ?2, record = ?3 WHERE proof_id = ?1",
+                params![proof_id, receipt_sha256, json],
+            )
+        })?;
+        Ok(())
+    }
+
+    /// `receipt_sha256` of the stored record of `proof_id`
+    fn record_receipt(&self, proof_id: &str) -> Result<Option<String>> {
+        self.with_connection(|db| {
+            db.query_row(
+                "SELECT receipt_sha256 FROM proofs WHERE proof_id = ?1",
+                params![proof_id],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+    }
+}
+
+impl ProofStore for SqliteProofStore {
+    fn save_record(&self, artifact: &ProofArtifact) -> Result<()> {
+        let previous_receipt = self.record_receipt(&artifact.proof_id)?;
+        let json = self.format.write(artifact)?;
+        let image_id = serde_json::to_string(&artifact.image_id)?;
+        self.with_connection(|db| {
+            db.execute(
+                "INSERT OR REPLACE INTO proofs
+                     (proof_id, request_digest, image_id, timestamp, sequence, receipt_sha256, record)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    artifact.proof_id,
+                    artifact.request_digest,
+                    image_id,
+                    artifact.timestamp,
+                    artifact.sequence,
+                    artifact.receipt_sha256,
+                    json,
+                ],
+            )
+        })?;
+        // A re-proven request replaces its record; a receipt that differs from the old
+        // one leaves the old payload with one reference less
+        if let Some(previous) = previous_receipt.filter(|p| *p != artifact.receipt_sha256) {
+            self.receipts.release(&previous, &artifact.proof_id)?;
+        }
+
+        tracing::info!(
+            "Proof {} successfully saved to {} (size: {} bytes)",
+            artifact.proof_id,
+            self.path.display(),
+            json.len()
+        );
+        Ok(())
+    }
+
+    fn store_receipt(&self, proof_id: &str, receipt_bytes: &[u8]) -> Result<()> {
+        self.receipts.store_receipt(proof_id, receipt_bytes)
+    }
+
+    fn quarantine(
+        &self,
+        request_id: &str,
+        reason: &str,
+        journal: &[u8],
+        receipt_bytes: &[u8],
+    ) -> Result<()> {
+        self.receipts
+            .quarantine(request_id, reason, journal, receipt_bytes)
+    }
+
+    fn find_other_image(
+        &self,
+        request_digest: &str,
+        image_id: &[String],
+    ) -> Result<Option<EarlierProof>> {
+        let image_id = serde_json::to_string(image_id)?;
+        let latest: Option<(String, String)> = self.with_connection(|db| {
+            db.query_row(
+                "SELECT proof_id, image_id FROM proofs
+                 WHERE request_digest = ?1 AND image_id != ?2
+                 ORDER BY sequence DESC, timestamp DESC LIMIT 1",
+                params![request_digest, image_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+        })?;
+        latest
+            .map(|(proof_id, image_id)| {
+                Ok(EarlierProof {
+                    proof_id,
+                    image_id: serde_json::from_str(&image_id)?,
+                })
+            })
+            .transpose()
+    }
+
+    fn contains_record(&self, proof_id: &str) -> Result<bool> {
+        Ok(self.record_receipt(proof_id)?.is_some())
+    }
+
+    /// A bare record, as written before the envelope, is returned in the envelope and
+    /// rewritten in it
+    fn find_record(&self, proof_id: &str) -> Result<Option<String>> {
+        let record: Option<String> = self.with_connection(|db| {
+            db.query_row(
+                "SELECT record FROM proofs WHERE proof_id = ?1",
+                params![proof_id],
+                |row| row.get(0),
+            )
+            .optional()
+        })?;
+        let Some(record) = record else {
+            return Ok(None);
+        };
+        let opened = self.format.open(record.as_bytes()).map_err(|e| {
+            Error::Storage(format!("Cannot read the record of proof {proof_id}: {e}"))
+        })?;
+        if !opened.is_bare() {
+            return Ok(Some(record));
+        }
+
+        let json = self.format.write(&opened.data)?;
+        let upgraded = self.with_connection(|db| {
+            db.execute(
+                "UPDATE proofs SET record = ?2 WHERE proof_id = ?1",
+                params![proof_id, json],
+            )
+        });
+        match upgraded {
+            Ok(_) => tracing::info!(
+                "Upgraded legacy record of proof {} to the envelope",
+                proof_id
+            ),
+            Err(e) => tracing::warn!(
+                "Serving legacy record of proof {} without upgrading it: {}",
+                proof_id,
+                e
+            ),
+        }
+        Ok(Some(json))
+    }
+
+    /// Records that cannot be parsed are logged and skipped rather than failing the
+    /// listing
+    fn list_records(&self, filter: &RecordFilter) -> Result<Vec<ProofSummary>> {
+        // SQLite integers are signed; no stored timestamp exceeds i64::MAX, so saturating
+        // keeps every comparison's answer. A negative limit reads as none.
+        let saturate = |n: u64| i64::try_from(n).unwrap_or(i64::MAX);
+        let since = filter.since.map(saturate);
+        let timestamp = filter.timestamp.map(saturate);
+        let limit = filter.limit.map_or(-1, |limit| saturate(limit as u64));
+        let records: Vec<(String, String)> = self.with_connection(|db| {
+            db.prepare_cached(
+                "SELECT proof_id, record FROM proofs
+                 WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp = ?2)
+                 ORDER BY timestamp DESC, sequence DESC, proof_id ASC LIMIT ?3",
+            )?
+            .query_map(params![since, timestamp, limit], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect()
+        })?;
+
+        Ok(records
+            .into_iter()
+            .filter_map(|(proof_id, record)| {
+                self.format
+                    .open(record.as_bytes())
+                    .and_then(|opened| Ok(serde_json::from_value(opened.data)?))
+                    .inspect_err(|e| {
+                        tracing::warn!("Skipping unreadable proof record {}: {}", proof_id, e)
+                    })
+                    .ok()
+            })
+            .collect())
+    }
+
+    fn find_receipt(&self, proof_id: &str) -> Result<Option<PathBuf>> {
+        let Some(receipt_sha256) = self.record_receipt(proof_id)? else {
+            return Ok(None);
+        };
+        let path = self.receipts.payload_path(&receipt_sha256);
+        Ok(path.exists().then_some(path))
+    }
+
+    /// Records that cannot be parsed are logged and skipped. The database stays locked
+    /// for the whole scan.
+    fn for_each_record(&self, visit: &mut dyn FnMut(serde_json::Value)) -> Result<()> {
+        self.with_connection(|db| {
+            let mut statement = db.prepare_cached("SELECT proof_id, record FROM proofs")?;
+            let mut rows = statement.query([])?;
+            while let Some(row) = rows.next()? {
+                let proof_id: String = row.get(0)?;
+                let record: String = row.get(1)?;
+                match self.format.open(record.as_bytes()) {
+                    Ok(opened) => visit(opened.data),
+                    Err(e) => {
+                        tracing::warn!("Skipping unreadable proof record {}: {}", proof_id, e)
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Legacy record files are migrated in `PROOFS_DIR` as [`FsProofStore`] migrates
+    /// them, then moved into the database along with every other record file there
+    fn migrate_legacy_records(&self) -> Result<usize> {
+        let migrated = self.receipts.migrate_legacy_records()?;
+        self.import_record_files()?;
+        Ok(migrated)
+    }
+
+    fn migrate_legacy_receipts(&self) -> Result<usize> {
+        self.receipts
+            .move_legacy_receipts(|proof_id, receipt_sha256| {
+                self.name_receipt(proof_id, receipt_sha256)
+            })
+    }
+
+    /// Bare rows are collected while the table is read and rewritten after
+    fn upgrade_bare_records(&self) -> Result<usize> {
+        let mut bare = Vec::new();
+        self.with_connection(|db| {
+            let mut statement = db.prepare_cached("SELECT proof_id, record FROM proofs")?;
+            let mut rows = statement.query([])?;
+            while let Some(row) = rows.next()? {
+                let proof_id: String = row.get(0)?;
+                let record: String = row.get(1)?;
+                match self.format.open(record.as_bytes()) {
+                    Ok(opened) if opened.is_bare() => bare.push((proof_id, opened.data)),
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::warn!("Leaving unreadable proof record {}: {}", proof_id, e)
+                    }
+                }
+            }
+            Ok(())
+        })?;
+
+        for (proof_id, data) in &bare {
+            let json = self.format.write(data)?;
+            self.with_connection(|db| {
+                db.execute(
+                    "UPDATE proofs SET record = ?2 WHERE proof_id = ?1",
+                    params![proof_id, json],
+                )
+            })?;
+        }
+        Ok(bare.len())
+    }
+
+    fn applied_migrations(&self) -> Result<BTreeSet<u32>> {
+        self.with_connection(|db| {
+            db.prepare_cached("SELECT id FROM migrations")?
+                .query_map([], |row| row.get(0))?
+                .collect()
+        })
+    }
+
+    fn record_migration(&self, id: u32, name: &str) -> Result<()> {
+        self.with_connection(|db| {
+            db.execute(
+                "INSERT OR REPLACE INTO migrations (id, name, applied_at)
+                 VALUES (?1, ?2, strftime('%s', 'now'))",
+                params![id, name],
+            )
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::migrations;
+    use crate::models::WhatIfRequest;
+    use crate::sequence::SequenceCounter;
+    use crate::whatif;
+
+    const PURL: &str = "pkg:npm/left-pad@1.0.0";
+
+    /// A store with a fresh database and proofs directory named after `test`
+    fn store(test: &str) -> SqliteProofStore {
+        let dir = std::env::temp_dir().join(format!("proving-service-sqlite-{test}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("proofs")).unwrap();
+        SqliteProofStore::new(dir.join("proofs.db"), dir.join("proofs"))
+    }
+
+    /// Write the record of a compliant proof of [`PURL`] as [`FsProofStore`] does
+    fn write_record_file(store: &SqliteProofStore, proof_id: &str, sequence: u64) {
+        let record = serde_json::json!({
+            "proof_id": proof_id,
+            "timestamp": 1_700_000_000,
+            "sequence": sequence,
+            "root_hash": "00".repeat(32),
+            "compliant": true,
+            "generation_duration_ms": null,
+            "image_id": ["1", "2"],
+            "purls": [PURL],
+            "receipt_sha256": "",
+        });
+        let json = store.format.write(&record).unwrap();
+        std::fs::write(store.receipts.record_path(proof_id), json).unwrap();
+    }
+
+    #[test]
+    fn record_files_move_into_the_database() {
+        let store = store("migrate");
+        write_record_file(&store, "aa", 1);
+        std::fs::write(store.receipts.legacy_receipt_path("aa"), b"receipt").unwrap();
+
+        store.migrate_legacy_records().unwrap();
+        assert!(!store.receipts.record_path("aa").exists());
+        assert!(store.contains_record("aa").unwrap());
+
+        assert_eq!(store.migrate_legacy_receipts().unwrap(), 1);
+        let receipt_sha256 = hex::encode(Sha256::digest(b"receipt"));
+        assert_eq!(
+            store.find_receipt("aa").unwrap(),
+            Some(store.receipts.payload_path(&receipt_sha256))
+        );
+        let record = store.find_record("aa").unwrap().unwrap();
+        let record = store.format.open(record.as_bytes()).unwrap().data;
+        assert_eq!(record["receipt_sha256"], receipt_sha256.as_str());
+    }
+
+    #[test]
+    fn scans_and_sequence_numbers_read_the_database() {
+        let store = store("scan");
+        write_record_file(&store, "bb", 41);
+        store.migrate_legacy_records().unwrap();
+
+        let request: WhatIfRequest =
+            serde_json::from_value(serde_json::json!({"purls": [PURL]})).unwrap();
+        let report = whatif::evaluate(&store, &request).unwrap();
+        assert_eq!((report.scanned, report.flipped), (1, 1));
+
+        let sequence = SequenceCounter::open(store.receipts.dir(), &store);
+        assert_eq!(sequence.next(None), 42);
+    }
+
+    #[test]
+    fn migrations_are_recorded_in_the_database() {
+        let store = store("migrations");
+        write_record_file(&store, "cc", 1);
+        // A row written before the envelope
+        let bare = serde_json::json!({"proof_id": "dd", "image_id": [], "timestamp": 1});
+        let json = bare.to_string();
+        assert!(store.insert_record(bare, &json).unwrap());
+
+        let run = migrations::run(&store, false).unwrap();
+        let converted: Vec<usize> = run.iter().map(|&(_, converted)| converted).collect();
+        assert_eq!(converted, [0, 0, 1]);
+        assert!(store.contains_record("cc").unwrap());
+        assert_eq!(store.upgrade_bare_records().unwrap(), 0);
+
+        let reopened = SqliteProofStore::new(store.path(), store.receipts.dir());
+        assert_eq!(
+            reopened.applied_migrations().unwrap(),
+            BTreeSet::from([1, 2, 3])
+        );
+        assert!(migrations::run(&reopened, false).unwrap().is_empty());
+    }
+}
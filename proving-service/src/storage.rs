@@ -0,0 +1,849 @@
+//! Where generated proofs are kept.
+//!
+//! The prover persists a JSON record of every proof and, for receipts that are not
+//! returned inline, the raw receipt bytes. [`FsProofStore`] is the layout the HTTP
+//! service serves downloads from; with the `sqlite` feature, `PROOF_STORE=sqlite` keeps
+//! records in a database instead (see [`crate::sqlite`]). Embedders can supply their own
+//! [`ProofStore`].
+//!
+//! Records are written in the `sbom-proof` envelope of [`sbom_common::envelope`]. Bare
+//! records written before it are read while [`RecordFormat::accept_bare`] allows, and
+//! one read by id is rewritten in the envelope.
+//!
+//! Raw receipts are content-addressed: one payload per distinct receipt, named by its
+//! SHA-256, shared by every proof whose record names that digest in `receipt_sha256`.
+//! Each payload has a reference file listing those proofs, and the payload is deleted
+//! with its last reference.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use sbom_common::envelope::{self, Opened, ProofEnvelope};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+use crate::digest::{journal_digest, proof_id};
+use crate::error::{Error, Result};
+use crate::models::{EarlierProof, MerklePublicOutputs, ProofArtifact, ProofSummary};
+use crate::utils::{KeyType, hex_to_bytes32};
+
+/// Domain separator of the stand-in request digest of legacy records that predate
+/// `proof_id`
+const LEGACY_RECORD_DOMAIN: &[u8] = b"sbom-legacy-record/v1";
+
+pub trait ProofStore: Send + Sync {
+    /// Persist the record of a generated proof
+    fn save_record(&self, artifact: &ProofArtifact) -> Result<()>;
+
+    /// Persist raw receipt bytes so they can be fetched later by `proof_id`. Called for
+    /// the receipt named by the record's `receipt_sha256`.
+    fn store_receipt(&self, proof_id: &str, receipt_bytes: &[u8]) -> Result<()>;
+
+    /// Set aside the output of proving run `request_id`, which failed the host's sanity
+    /// checks for `reason`, so it can be inspected without ever being served. Stores
+    /// nothing unless overridden.
+    fn quarantine(
+        &self,
+        _request_id: &str,
+        _reason: &str,
+        _journal: &[u8],
+        _receipt_bytes: &[u8],
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// The latest stored proof of the request with `request_digest` made by a guest image
+    /// other than `image_id`. Finds nothing unless overridden.
+    fn find_other_image(
+        &self,
+        _request_digest: &str,
+        _image_id: &[String],
+    ) -> Result<Option<EarlierProof>> {
+        Ok(None)
+    }
+
+    /// Whether a record is stored under `proof_id`, so an import does not replace it.
+    /// Finds nothing unless overridden.
+    fn contains_record(&self, _proof_id: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// The record of `proof_id`, as stored JSON in its envelope. Finds nothing unless
+    /// overridden.
+    fn find_record(&self, _proof_id: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Summaries of the stored records that pass `filter`, newest first. Lists nothing
+    /// unless overridden.
+    fn list_records(&self, _filter: &RecordFilter) -> Result<Vec<ProofSummary>> {
+        Ok(Vec::new())
+    }
+
+    /// The stored receipt of `proof_id`, if its record names one and the payload exists.
+    /// Finds nothing unless overridden.
+    fn find_receipt(&self, _proof_id: &str) -> Result<Option<PathBuf>> {
+        Ok(None)
+    }
+
+    /// Hand the payload of every stored record to `visit`, one at a time and in no
+    /// particular order, so a scan holds a single record in memory. Records that cannot be
+    /// read are logged and skipped. `visit` must not call back into the store. Visits
+    /// nothing unless overridden.
+    fn for_each_record(&self, _visit: &mut dyn FnMut(serde_json::Value)) -> Result<()> {
+        Ok(())
+    }
+
+    /// Bring records stored as `proof_<timestamp>.json`, before they were keyed by
+    /// `proof_id`, into the store. Returns the number of records migrated. Migrates
+    /// nothing unless overridden.
+    fn migrate_legacy_records(&self) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Move receipts stored as `<proof_id>.receipt` into the content-addressed layout.
+    /// Returns the number of receipts moved. Moves nothing unless overridden.
+    fn migrate_legacy_receipts(&self) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Rewrite records stored bare, before the envelope, in it. Records that cannot be
+    /// read, bare ones included when they are not accepted, are logged and left. Returns
+    /// the number of records rewritten. Rewrites nothing unless overridden.
+    fn upgrade_bare_records(&self) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Ids of the [`crate::migrations`] applied to this store. None unless overridden,
+    /// so every migration runs on each start, finding nothing left to convert.
+    fn applied_migrations(&self) -> Result<BTreeSet<u32>> {
+        Ok(BTreeSet::new())
+    }
+
+    /// Record that migration `id`, named `name`, has been applied. Records nothing
+    /// unless overridden.
+    fn record_migration(&self, _id: u32, _name: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// `created_by` of the envelopes this build writes
+pub const CREATED_BY: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// How stores write records and which stored records they read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordFormat {
+    /// `schema_url` of the envelope records are written in
+    pub schema_url: String,
+    /// Read bare records written before the envelope
+    pub accept_bare: bool,
+}
+
+impl Default for RecordFormat {
+    fn default() -> Self {
+        Self::from(&Config::default())
+    }
+}
+
+impl From<&Config> for RecordFormat {
+    fn from(config: &Config) -> Self {
+        Self {
+            schema_url: config.schema_url(),
+            accept_bare: config.legacy_proofs.accepted(),
+        }
+    }
+}
+
+impl RecordFormat {
+    /// `data` in the envelope, as records are stored and proofs returned
+    pub fn envelope<T: Serialize>(&self, data: T) -> ProofEnvelope<T> {
+        ProofEnvelope::new(data, self.schema_url.as_str(), CREATED_BY)
+    }
+
+    /// `data` in the envelope, serialized as a stored record
+    pub fn write<T: Serialize>(&self, data: T) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.envelope(data))?)
+    }
+
+    /// The payload of the stored record `contents`, and its envelope unless it is bare
+    pub fn open(&self, contents: &[u8]) -> Result<Opened> {
+        let document = serde_json::from_slice(contents)?;
+        envelope::open(document, self.accept_bare).map_err(|e| Error::Storage(e.to_string()))
+    }
+}
+
+/// Which stored records [`ProofStore::list_records`] returns
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordFilter {
+    /// Only proofs stamped at or after this Unix timestamp
+    pub since: Option<u64>,
+    /// Only proofs stamped at exactly this Unix timestamp
+    pub timestamp: Option<u64>,
+    /// At most this many, the newest
+    pub limit: Option<usize>,
+}
+
+impl RecordFilter {
+    /// The proofs stamped at `timestamp`, which may be several
+    pub fn at(timestamp: u64) -> Self {
+        Self {
+            timestamp: Some(timestamp),
+            ..Self::default()
+        }
+    }
+
+    pub fn matches(&self, summary: &ProofSummary) -> bool {
+        self.since.is_none_or(|since| summary.timestamp >= since)
+            && self
+                .timestamp
+                .is_none_or(|timestamp| summary.timestamp == timestamp)
+    }
+}
+
+/// Order summaries newest first. The sequence breaks ties between proofs stamped in the
+/// same second, and the proof id between records that predate sequence numbers.
+pub fn sort_newest_first(proofs: &mut [ProofSummary]) {
+    proofs.sort_by(|a, b| {
+        (b.timestamp, b.sequence)
+            .cmp(&(a.timestamp, a.sequence))
+            .then_with(|| a.proof_id.cmp(&b.proof_id))
+    });
+}
+
+/// Check that the receipt payload at `path` still hashes to its name
+pub fn verify_payload(path: &Path) -> Result<bool> {
+    let payload = std::fs::read(path)?;
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    Ok(hex::encode(Sha256::digest(&payload)) == name)
+}
+
+/// The fields of a stored record that identify the request and image it proves
+#[derive(serde::Deserialize)]
+struct ImageRecord {
+    proof_id: String,
+    #[serde(default)]
+    request_digest: String,
+    image_id: Vec<String>,
+    #[serde(default)]
+    sequence: u64,
+    timestamp: u64,
+}
+
+/// Stores records as `<proof_id>.json` and receipts as `receipts/<sha256>.receipt` with
+/// their references in `receipts/<sha256>.refs`, created on first write. Re-proving an
+/// identical request with the same guest image replaces its record; with another image
+/// it gets a record of its own. Quarantined output goes to
+/// `quarantine/<request_id>.json` and `.receipt`, which nothing serves. Applied
+/// migrations are listed in `migrations/applied.json`.
+#[derive(Debug, Clone)]
+pub struct FsProofStore {
+    dir: PathBuf,
+    format: RecordFormat,
+    /// Serializes updates of reference files
+    refs_lock: Arc<Mutex<()>>,
+}
+
+impl FsProofStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            format: RecordFormat::default(),
+            refs_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn with_format(mut self, format: RecordFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn record_path(&self, proof_id: &str) -> PathBuf {
+        self.dir.join(format!("{proof_id}.json"))
+    }
+
+    /// Where records were kept before they were keyed by `proof_id`
+    pub fn legacy_record_path(&self, timestamp: u64) -> PathBuf {
+        self.dir.join(format!("proof_{timestamp}.json"))
+    }
+
+    /// The receipt with SHA-256 `receipt_sha256` (hex)
+    pub fn payload_path(&self, receipt_sha256: &str) -> PathBuf {
+        self.dir
+            .join("receipts")
+            .join(format!("{receipt_sha256}.receipt"))
+    }
+
+    fn refs_path(&self, receipt_sha256: &str) -> PathBuf {
+        self.dir
+            .join("receipts")
+            .join(format!("{receipt_sha256}.refs"))
+    }
+
+    /// The [`crate::migrations`] applied to the store, by id with their names
+    fn migrations_path(&self) -> PathBuf {
+        self.dir.join("migrations").join("applied.json")
+    }
+
+    fn read_migrations(&self) -> Result<BTreeMap<u32, String>> {
+        match std::fs::read(self.migrations_path()) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Where receipts were kept before they were content-addressed
+    pub fn legacy_receipt_path(&self, proof_id: &str) -> PathBuf {
+        self.dir.join(format!("{proof_id}.receipt"))
+    }
+
+    /// Delete the record of `proof_id` and drop its reference to its receipt, deleting the
+    /// receipt if no other proof shares it. Returns whether there was a record.
+    pub fn delete_proof(&self, proof_id: &str) -> Result<bool> {
+        let receipt_sha256 = self.record_receipt(proof_id)?;
+        match std::fs::remove_file(self.record_path(proof_id)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        if let Some(receipt_sha256) = receipt_sha256 {
+            self.release(&receipt_sha256, proof_id)?;
+        }
+        tracing::info!("Deleted proof {}", proof_id);
+        Ok(true)
+    }
+
+    /// `receipt_sha256` of the stored record of `proof_id`
+    fn record_receipt(&self, proof_id: &str) -> Result<Option<String>> {
+        let Some(record) = self.find_record(proof_id)? else {
+            return Ok(None);
+        };
+        let record = self.format.open(record.as_bytes())?.data;
+        Ok(record["receipt_sha256"].as_str().map(str::to_string))
+    }
+
+    /// Add `proof_id` to the references of `receipt_sha256`
+    fn retain(&self, receipt_sha256: &str, proof_id: &str) -> Result<()> {
+        let _refs = self.refs_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut refs = self.read_refs(receipt_sha256)?;
+        if refs.insert(proof_id.to_string()) {
+            self.write(&self.refs_path(receipt_sha256), &serde_json::to_vec(&refs)?)?;
+        }
+        Ok(())
+    }
+
+    /// Remove `proof_id` from the references of `receipt_sha256`, deleting the receipt
+    /// with its last reference
+    pub(crate) fn release(&self, receipt_sha256: &str, proof_id: &str) -> Result<()> {
+        let _refs = self.refs_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut refs = self.read_refs(receipt_sha256)?;
+        if !refs.remove(proof_id) {
+            return Ok(());
+        }
+        if !refs.is_empty() {
+            return self.write(&self.refs_path(receipt_sha256), &serde_json::to_vec(&refs)?);
+        }
+
+        for path in [
+            self.payload_path(receipt_sha256),
+            self.refs_path(receipt_sha256),
+        ] {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        tracing::info!("Deleted receipt {} with its last reference", receipt_sha256);
+        Ok(())
+    }
+
+    fn read_refs(&self, receipt_sha256: &str) -> Result<BTreeSet<String>> {
+        match std::fs::read(self.refs_path(receipt_sha256)) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeSet::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Move receipts stored as `<proof_id>.receipt` into the content-addressed layout,
+    /// handing each proof id and its receipt's digest to `name_receipt` to record in the
+    /// proof's record, wherever the records are kept. Identical receipts end up as one
+    /// payload. Returns the number of receipts moved.
+    pub(crate) fn move_legacy_receipts(
+        &self,
+        name_receipt: impl Fn(&str, &str) -> Result<()>,
+    ) -> Result<usize> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut proof_ids = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            if let Some(proof_id) = name.to_str().and_then(|name| name.strip_suffix(".receipt")) {
+                proof_ids.push(proof_id.to_string());
+            }
+        }
+
+        for proof_id in &proof_ids {
+            let legacy_path = self.legacy_receipt_path(proof_id);
+            let receipt_bytes = std::fs::read(&legacy_path)?;
+            let receipt_sha256 = hex::encode(Sha256::digest(&receipt_bytes));
+
+            self.store_payload(&receipt_sha256, &receipt_bytes)?;
+            name_receipt(proof_id, &receipt_sha256)?;
+            self.retain(&receipt_sha256, proof_id)?;
+            std::fs::remove_file(&legacy_path)?;
+            tracing::info!(
+                "Migrated receipt {} to {}",
+                legacy_path.display(),
+                self.payload_path(&receipt_sha256).display()
+            );
+        }
+
+        Ok(proof_ids.len())
+    }
+
+    /// Write a payload unless an identical one is already stored
+    fn store_payload(&self, receipt_sha256: &str, receipt_bytes: &[u8]) -> Result<()> {
+        let path = self.payload_path(receipt_sha256);
+        if path.exists() {
+            tracing::info!(
+                "Receipt of {} bytes already stored at: {}",
+                receipt_bytes.len(),
+                path.display()
+            );
+            return Ok(());
+        }
+
+        // Write under a temporary name so a crash never leaves a truncated payload under
+        // the name of its digest
+        let partial = path.with_extension("partial");
+        self.write(&partial, receipt_bytes)?;
+        std::fs::rename(&partial, &path)?;
+        tracing::info!(
+            "Receipt of {} bytes stored for download at: {}",
+            receipt_bytes.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    /// Every stored record parsed as `T`, one at a time and in no particular order, so a
+    /// scan holds a single record in memory. Records that cannot be read or parsed as `T`
+    /// are logged and skipped; only failing to list the directory is an error.
+    pub fn records<T: DeserializeOwned>(&self) -> Result<impl Iterator<Item = Result<T>>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => Some(entries),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(entries.into_iter().flatten().filter_map(|entry| {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => return Some(Err(e.into())),
+            };
+            if path.extension().is_none_or(|ext| ext != "json") {
+                return None;
+            }
+
+            let record = std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| self.format.open(&contents).map_err(|e| e.to_string()))
+                .and_then(|opened| {
+                    serde_json::from_value::<T>(opened.data).map_err(|e| e.to_string())
+                });
+            match record {
+                Ok(record) => Some(Ok(record)),
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable proof record {}: {}", path.display(), e);
+                    None
+                }
+            }
+        }))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        std::fs::create_dir_all(path.parent().unwrap_or(&self.dir))
+            .and_then(|_| std::fs::write(path, contents))
+            .map_err(|e| {
+                Error::Storage(format!(
+                    "Failed to write {} bytes to '{}': {}",
+                    contents.len(),
+                    path.display(),
+                    e
+                ))
+            })
+    }
+}
+
+impl ProofStore for FsProofStore {
+    fn save_record(&self, artifact: &ProofArtifact) -> Result<()> {
+        let previous_receipt = self.record_receipt(&artifact.proof_id)?;
+        let json = self.format.write(artifact)?;
+        let filepath = self.record_path(&artifact.proof_id);
+        self.write(&filepath, json.as_bytes())?;
+        // A re-proven request replaces its record; a receipt that differs from the old
+        // one leaves the old payload with one reference less
+        if let Some(previous) = previous_receipt.filter(|p| *p != artifact.receipt_sha256) {
+            self.release(&previous, &artifact.proof_id)?;
+        }
+
+        tracing::info!(
+            "Proof successfully saved to: {} (size: {} bytes)",
+            filepath.display(),
+            json.len()
+        );
+        Ok(())
+    }
+
+    fn store_receipt(&self, proof_id: &str, receipt_bytes: &[u8]) -> Result<()> {
+        let receipt_sha256 = hex::encode(Sha256::digest(receipt_bytes));
+        self.store_payload(&receipt_sha256, receipt_bytes)?;
+        self.retain(&receipt_sha256, proof_id)
+    }
+
+    fn find_other_image(
+        &self,
+        request_digest: &str,
+        image_id: &[String],
+    ) -> Result<Option<EarlierProof>> {
+        let mut latest: Option<ImageRecord> = None;
+        for record in self.records::<ImageRecord>()? {
+            let record = record?;
+            if record.request_digest != request_digest || record.image_id == image_id {
+                continue;
+            }
+            if latest
+                .as_ref()
+                .is_none_or(|l| (record.sequence, record.timestamp) > (l.sequence, l.timestamp))
+            {
+                latest = Some(record);
+            }
+        }
+        Ok(latest.map(|record| EarlierProof {
+            proof_id: record.proof_id,
+            image_id: record.image_id,
+        }))
+    }
+
+    fn contains_record(&self, proof_id: &str) -> Result<bool> {
+        Ok(self.record_path(proof_id).exists())
+    }
+
+    /// A bare record is returned in the envelope and rewritten in it. The rewrite is
+    /// best effort: a store on a read-only volume still serves the record.
+    fn find_record(&self, proof_id: &str) -> Result<Option<String>> {
+        let path = self.record_path(proof_id);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let opened = self.format.open(contents.as_bytes()).map_err(|e| {
+            Error::Storage(format!("Cannot read record '{}': {}", path.display(), e))
+        })?;
+        if !opened.is_bare() {
+            return Ok(Some(contents));
+        }
+
+        let json = self.format.write(&opened.data)?;
+        match self.write(&path, json.as_bytes()) {
+            Ok(()) => tracing::info!("Upgraded legacy record {} to the envelope", path.display()),
+            Err(e) => tracing::warn!(
+                "Serving legacy record {} without upgrading it: {}",
+                path.display(),
+                e
+            ),
+        }
+        Ok(Some(json))
+    }
+
+    /// Records that cannot be read or parsed are logged and skipped rather than failing
+    /// the listing
+    fn list_records(&self, filter: &RecordFilter) -> Result<Vec<ProofSummary>> {
+        let mut proofs = Vec::new();
+        for summary in self.records::<ProofSummary>()? {
+            let summary = summary?;
+            if filter.matches(&summary) {
+                proofs.push(summary);
+            }
+        }
+        sort_newest_first(&mut proofs);
+        if let Some(limit) = filter.limit {
+            proofs.truncate(limit);
+        }
+        Ok(proofs)
+    }
+
+    fn find_receipt(&self, proof_id: &str) -> Result<Option<PathBuf>> {
+        let Some(receipt_sha256) = self.record_receipt(proof_id)? else {
+            return Ok(None);
+        };
+        let path = self.payload_path(&receipt_sha256);
+        Ok(path.exists().then_some(path))
+    }
+
+    fn quarantine(
+        &self,
+        request_id: &str,
+        reason: &str,
+        journal: &[u8],
+        receipt_bytes: &[u8],
+    ) -> Result<()> {
+        let dir = self.dir.join("quarantine");
+        let record = serde_json::json!({
+            "request_id": request_id,
+            "reason": reason,
+            "journal": hex::encode(journal),
+            "receipt_sha256": hex::encode(Sha256::digest(receipt_bytes)),
+            "receipt_size": receipt_bytes.len(),
+        });
+        self.write(&dir.join(format!("{request_id}.receipt")), receipt_bytes)?;
+        let filepath = dir.join(format!("{request_id}.json"));
+        self.write(&filepath, serde_json::to_string_pretty(&record)?.as_bytes())?;
+        tracing::warn!("Quarantined proving output at: {}", filepath.display());
+        Ok(())
+    }
+
+    /// Records that cannot be read are logged and skipped
+    fn for_each_record(&self, visit: &mut dyn FnMut(serde_json::Value)) -> Result<()> {
+        for record in self.records::<serde_json::Value>()? {
+            visit(record?);
+        }
+        Ok(())
+    }
+
+    /// Rename records stored as `proof_<timestamp>.json` to the `proof_id` derived from
+    /// their contents, rewriting the ids inside and moving linked receipts along. Legacy
+    /// records are migrated oldest first, so when two of them map to the same id the
+    /// newer one is kept. Returns the number of records migrated.
+    fn migrate_legacy_records(&self) -> Result<usize> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut timestamps = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            if let Some(timestamp) = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("proof_")?.strip_suffix(".json"))
+                .and_then(|ts| ts.parse::<u64>().ok())
+            {
+                timestamps.push(timestamp);
+            }
+        }
+        timestamps.sort_unstable();
+
+        for &timestamp in &timestamps {
+            let legacy_path = self.legacy_record_path(timestamp);
+            let contents = std::fs::read(&legacy_path)?;
+            let mut record: serde_json::Value = serde_json::from_slice(&contents).map_err(|e| {
+                Error::Storage(format!(
+                    "Failed to migrate legacy record '{}': {}",
+                    legacy_path.display(),
+                    e
+                ))
+            })?;
+
+            let old_id = record["proof_id"].as_str().map(str::to_string);
+            let request_digest = old_id
+                .as_deref()
+                .and_then(|id| hex_to_bytes32(id).ok())
+                .unwrap_or_else(|| {
+                    Sha256::new()
+                        .chain_update(LEGACY_RECORD_DOMAIN)
+                        .chain_update(&contents)
+                        .finalize()
+                        .into()
+                });
+            // A record too old to carry every journal field falls back to a digest of its
+            // raw contents
+            let new_id = legacy_proof_id(&record, &request_digest).unwrap_or_else(|| {
+                Sha256::new()
+                    .chain_update(LEGACY_RECORD_DOMAIN)
+                    .chain_update(request_digest)
+                    .chain_update(&contents)
+                    .finalize()
+                    .into()
+            });
+            let new_id = hex::encode(new_id);
+
+            record["proof_id"] = new_id.clone().into();
+            record["request_digest"] = hex::encode(request_digest).into();
+            if let Some(old_id) = &old_id {
+                if let Some(url) = record["proof_url"].as_str() {
+                    record["proof_url"] = url.replace(old_id.as_str(), &new_id).into();
+                }
+                let old_receipt = self.legacy_receipt_path(old_id);
+                if old_receipt.exists() {
+                    std::fs::rename(&old_receipt, self.legacy_receipt_path(&new_id))?;
+                }
+            }
+
+            let json = self.format.write(&record)?;
+            self.write(&self.record_path(&new_id), json.as_bytes())?;
+            std::fs::remove_file(&legacy_path)?;
+            tracing::info!(
+                "Migrated legacy record {} to {}",
+                legacy_path.display(),
+                self.record_path(&new_id).display()
+            );
+        }
+
+        Ok(timestamps.len())
+    }
+
+    fn migrate_legacy_receipts(&self) -> Result<usize> {
+        self.move_legacy_receipts(|proof_id, receipt_sha256| {
+            if let Some(record) = self.find_record(proof_id)? {
+                // The record is enveloped by now; only its payload changes
+                let mut record: serde_json::Value = serde_json::from_str(&record)?;
+                record["data"]["receipt_sha256"] = receipt_sha256.into();
+                let json = serde_json::to_string_pretty(&record)?;
+                self.write(&self.record_path(proof_id), json.as_bytes())?;
+            }
+            Ok(())
+        })
+    }
+
+    fn upgrade_bare_records(&self) -> Result<usize> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut upgraded = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let contents = std::fs::read(&path)?;
+            match self.format.open(&contents) {
+                Ok(opened) if opened.is_bare() => {
+                    let json = self.format.write(&opened.data)?;
+                    self.write(&path, json.as_bytes())?;
+                    upgraded += 1;
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Leaving unreadable record {}: {}", path.display(), e),
+            }
+        }
+        Ok(upgraded)
+    }
+
+    fn applied_migrations(&self) -> Result<BTreeSet<u32>> {
+        Ok(self.read_migrations()?.into_keys().collect())
+    }
+
+    fn record_migration(&self, id: u32, name: &str) -> Result<()> {
+        let mut applied = self.read_migrations()?;
+        applied.insert(id, name.to_string());
+        let json = serde_json::to_vec_pretty(&applied)?;
+        self.write(&self.migrations_path(), &json)
+    }
+}
+
+/// `proof_id` of a legacy record, with the journal rebuilt from the record's fields
+fn legacy_proof_id(record: &serde_json::Value, request_digest: &[u8; 32]) -> Option<[u8; 32]> {
+    let hash = |field: &str| record[field].as_str().and_then(|h| hex_to_bytes32(h).ok());
+    let image_id: Vec<u32> = record["image_id"]
+        .as_array()?
+        .iter()
+        .map(|word| word.as_str()?.parse().ok())
+        .collect::<Option<_>>()?;
+    let key_type = match record.get("key_type") {
+        None => KeyType::Purl,
+        Some(key_type) => serde_json::from_value::<KeyType>(key_type.clone()).ok()?,
+    };
+
+    let outputs = MerklePublicOutputs {
+        root_hash: hash("root_hash")?,
+        banned_list_hash: hash("banned_list_hash")?,
+        compliant: record["compliant"].as_bool()?,
+        proof_count: record["proof_count"].as_u64()?.try_into().ok()?,
+        reasons_hash: hash("reasons_hash")?,
+        list_info_hash: hash("list_info_hash").unwrap_or_default(),
+        // Only accepted input was ever recorded
+        input_status: 0,
+        salt_commitment: hash("salt_commitment").unwrap_or_default(),
+        key_type: key_type.code(),
+        // Legacy records predate membership proofs
+        proof_type: 0,
+        // and the defaults commitment
+        defaults_digest: [0u8; 32],
+        // and requester binding
+        requester_commitment: [0u8; 32],
+        // and the first failure
+        first_failure: 0,
+        first_failure_index: 0,
+        first_failure_key_hash: [0u8; 32],
+    };
+    Some(proof_id(
+        request_digest,
+        &image_id.try_into().ok()?,
+        &journal_digest(&outputs),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A store in a fresh directory named after `test`
+    fn store(test: &str) -> FsProofStore {
+        let dir = std::env::temp_dir().join(format!("proving-service-storage-{test}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        FsProofStore::new(dir)
+    }
+
+    /// Store `receipt_bytes` for `proof_id` with a record naming them
+    fn save(store: &FsProofStore, proof_id: &str, receipt_bytes: &[u8]) -> String {
+        let receipt_sha256 = hex::encode(Sha256::digest(receipt_bytes));
+        let record = serde_json::json!({
+            "proof_id": proof_id,
+            "receipt_sha256": receipt_sha256,
+        });
+        let json = store.format.write(&record).unwrap();
+        std::fs::write(store.record_path(proof_id), json).unwrap();
+        store.store_receipt(proof_id, receipt_bytes).unwrap();
+        receipt_sha256
+    }
+
+    #[test]
+    fn shared_receipts_are_deleted_with_their_last_proof() {
+        let store = store("refcount");
+        let receipt_sha256 = save(&store, "aa", b"receipt");
+        assert_eq!(save(&store, "bb", b"receipt"), receipt_sha256);
+        let payload = store.payload_path(&receipt_sha256);
+
+        assert!(store.delete_proof("aa").unwrap());
+        assert!(payload.exists());
+        assert_eq!(store.find_receipt("bb").unwrap(), Some(payload.clone()));
+
+        assert!(store.delete_proof("bb").unwrap());
+        assert!(!payload.exists());
+        assert!(!store.refs_path(&receipt_sha256).exists());
+        assert!(!store.delete_proof("bb").unwrap());
+    }
+}
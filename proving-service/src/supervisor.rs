@@ -0,0 +1,230 @@
+//! Coordinated shutdown of background tasks.
+//!
+//! Every task the service runs besides request handlers is spawned through the
+//! [`TaskSupervisor`], which hands it a [`TaskHandle`]. A task selects on
+//! [`TaskHandle::cancelled`] at points where stopping leaves nothing half-written; file
+//! writes happen between those points and are never interrupted. On shutdown the
+//! supervisor cancels the tasks one at a time, newest first, and waits for each up to its
+//! own timeout before aborting it.
+
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::rt::task::JoinHandle;
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+/// Given to a supervised task so it can notice that it should stop
+#[derive(Clone)]
+pub struct TaskHandle {
+    name: String,
+    token: CancellationToken,
+}
+
+impl TaskHandle {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Resolves once the task should stop
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+/// How a supervised task ended during shutdown
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskExit {
+    /// Returned after being cancelled, or had already finished
+    Clean,
+    Panicked,
+    /// Did not return within its timeout and was aborted
+    TimedOut,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskReport {
+    pub name: String,
+    pub exit: TaskExit,
+}
+
+struct Supervised {
+    name: String,
+    timeout: Duration,
+    token: CancellationToken,
+    handle: JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct TaskSupervisor {
+    /// Cancelled once shutdown starts; tasks spawned after that start out cancelled
+    closing: CancellationToken,
+    tasks: Mutex<Vec<Supervised>>,
+}
+
+impl TaskSupervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn `task` on the current runtime. After cancellation it has `timeout` to return
+    /// before it is aborted.
+    pub fn spawn<F, Fut>(&self, name: impl Into<String>, timeout: Duration, task: F)
+    where
+        F: FnOnce(TaskHandle) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let name = name.into();
+        let token = CancellationToken::new();
+        if self.closing.is_cancelled() {
+            tracing::warn!(
+                "Task '{}' started during shutdown; it is cancelled right away",
+                name
+            );
+            token.cancel();
+        }
+        let handle = actix_web::rt::spawn(task(TaskHandle {
+            name: name.clone(),
+            token: token.clone(),
+        }));
+
+        let mut tasks = self.lock();
+        // Finished tasks need no shutdown; dropping them keeps short-lived ones from piling up
+        tasks.retain(|task| !task.handle.is_finished());
+        tasks.push(Supervised {
+            name,
+            timeout,
+            token,
+            handle,
+        });
+    }
+
+    /// Number of tasks that have not finished yet
+    pub fn running(&self) -> usize {
+        self.lock()
+            .iter()
+            .filter(|task| !task.handle.is_finished())
+            .count()
+    }
+
+    /// Cancel every task, newest first, and wait for each to return within its timeout.
+    /// Returns how each one ended, in the order they were stopped.
+    pub async fn shutdown(&self) -> Vec<TaskReport> {
+        self.closing.cancel();
+        tracing::info!("Shutting down {} background task(s)", self.lock().len());
+
+        let mut reports = Vec::new();
+        // Popping one at a time also catches tasks spawned while shutdown is under way
+        loop {
+            let Some(mut task) = self.lock().pop() else {
+                break;
+            };
+            task.token.cancel();
+            let exit = match tokio::time::timeout(task.timeout, &mut task.handle).await {
+                Ok(Ok(())) => TaskExit::Clean,
+                Ok(Err(e)) => {
+                    tracing::error!("Task '{}' panicked: {}", task.name, e);
+                    TaskExit::Panicked
+                }
+                Err(_) => {
+                    task.handle.abort();
+                    tracing::error!(
+                        "Task '{}' did not stop within {:?} and was aborted",
+                        task.name,
+                        task.timeout
+                    );
+                    TaskExit::TimedOut
+                }
+            };
+            if exit == TaskExit::Clean {
+                tracing::info!("Task '{}' stopped cleanly", task.name);
+            }
+            reports.push(TaskReport {
+                name: task.name,
+                exit,
+            });
+        }
+
+        let clean = reports.iter().filter(|r| r.exit == TaskExit::Clean).count();
+        tracing::info!(
+            "Background tasks stopped: {} clean, {} not",
+            clean,
+            reports.len() - clean
+        );
+        reports
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Supervised>> {
+        self.tasks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn tasks_stop_newest_first_within_their_timeouts() {
+        let supervisor = TaskSupervisor::new();
+        let stopped = Arc::new(Mutex::new(Vec::new()));
+        for name in ["retention", "outbox"] {
+            let stopped = stopped.clone();
+            supervisor.spawn(name, Duration::from_secs(5), move |handle| async move {
+                handle.cancelled().await;
+                stopped.lock().unwrap().push(handle.name().to_string());
+            });
+        }
+        supervisor.spawn("stuck", Duration::from_millis(50), |_| async {
+            std::future::pending::<()>().await
+        });
+        supervisor.spawn("crashing", Duration::from_secs(5), |handle| async move {
+            handle.cancelled().await;
+            panic!("failed while stopping");
+        });
+        assert_eq!(supervisor.running(), 4);
+
+        let started = Instant::now();
+        let reports = supervisor.shutdown().await;
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let exits: Vec<(&str, TaskExit)> = reports
+            .iter()
+            .map(|report| (report.name.as_str(), report.exit))
+            .collect();
+        assert_eq!(
+            exits,
+            [
+                ("crashing", TaskExit::Panicked),
+                ("stuck", TaskExit::TimedOut),
+                ("outbox", TaskExit::Clean),
+                ("retention", TaskExit::Clean),
+            ]
+        );
+        assert_eq!(*stopped.lock().unwrap(), ["outbox", "retention"]);
+        assert_eq!(supervisor.running(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn tasks_spawned_during_shutdown_start_cancelled() {
+        let supervisor = TaskSupervisor::new();
+        supervisor.spawn("warm-up", Duration::from_secs(5), |handle| async move {
+            handle.cancelled().await
+        });
+        assert_eq!(supervisor.shutdown().await[0].exit, TaskExit::Clean);
+
+        supervisor.spawn("late", Duration::from_secs(5), |handle| async move {
+            assert!(handle.is_cancelled());
+        });
+        let reports = supervisor.shutdown().await;
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].exit, TaskExit::Clean);
+    }
+}
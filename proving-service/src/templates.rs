@@ -0,0 +1,256 @@
+//! Proof request templates for recurring compliance jobs.
+//!
+//! `POST /templates`, an admin endpoint, stores a [`TemplateDefinition`]: a named,
+//! validated partial prove request. `POST /templates/{name}/run` completes the latest
+//! version with the parts that change per run, a [`TemplateRun`], and proves the result
+//! like any other request; the template's name and version are recorded in the proof.
+//! Storing a name again adds a version instead of replacing it, so every proof can be
+//! traced to the exact template it was run from.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sbom_common::smt::TREE_DEPTH;
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::models::{
+    ProofSource, ProofTemplate, ProveCompactMerkleRequest, TemplateDefinition, TemplateRef,
+    TemplateRun,
+};
+use crate::spot_check::SpotCheck;
+use crate::utils::hex_to_bytes32;
+use crate::validation::resolve_root_alias;
+
+const MAX_TEMPLATE_NAME_LEN: usize = 64;
+
+/// Keeps every version of a template as `<dir>/<name>/<version>.json`
+pub struct TemplateStore {
+    dir: PathBuf,
+    /// Serializes the choice of the next version number
+    lock: Mutex<()>,
+}
+
+impl TemplateStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Validate `definition` and store it as the next version of its name
+    pub fn save(&self, definition: TemplateDefinition, config: &Config) -> Result<ProofTemplate> {
+        validate_definition(&definition, config)?;
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let version = self.latest_version(&definition.name)?.map_or(1, |v| v + 1);
+        let template = ProofTemplate {
+            version,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            definition,
+        };
+
+        let dir = self.dir.join(&template.definition.name);
+        std::fs::create_dir_all(&dir)?;
+        let json = serde_json::to_string_pretty(&template)?;
+        std::fs::write(dir.join(format!("{version}.json")), json)?;
+        tracing::info!(
+            "Stored template '{}' version {}",
+            template.definition.name,
+            version
+        );
+        Ok(template)
+    }
+
+    /// The latest version of the template `name`
+    pub fn latest(&self, name: &str) -> Result<ProofTemplate> {
+        validate_name(name)?;
+        let Some(version) = self.latest_version(name)? else {
+            let err_msg = format!("No template named '{name}'");
+            tracing::error!("{}", err_msg);
+            return Err(Error::NotFound(err_msg));
+        };
+
+        let path = self.dir.join(name).join(format!("{version}.json"));
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    fn latest_version(&self, name: &str) -> Result<Option<u32>> {
+        let entries = match std::fs::read_dir(self.dir.join(name)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut latest = None;
+        for entry in entries {
+            let name = entry?.file_name();
+            if let Some(version) = name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|version| version.parse::<u32>().ok())
+            {
+                latest = latest.max(Some(version));
+            }
+        }
+        Ok(latest)
+    }
+}
+
+impl ProofTemplate {
+    /// The prove request for `run`. Fails if the run breaks one of the template's
+    /// constraints: an allowed root alias, or where its proofs come from.
+    pub fn request_for(
+        &self,
+        run: TemplateRun,
+        root_aliases: &HashMap<String, String>,
+        spot_check: &SpotCheck,
+    ) -> Result<ProveCompactMerkleRequest> {
+        let definition = &self.definition;
+        if !definition.root_aliases.is_empty() {
+            let allowed = run.root.is_empty()
+                && run
+                    .root_alias
+                    .as_ref()
+                    .is_some_and(|alias| definition.root_aliases.contains(alias));
+            if !allowed {
+                return Err(self.violation(format!(
+                    "runs must name one of the root aliases [{}] and no hex root",
+                    definition.root_aliases.join(", ")
+                )));
+            }
+        }
+
+        let mut req = ProveCompactMerkleRequest {
+            depth: TREE_DEPTH,
+            root: run.root,
+            root_alias: run.root_alias,
+            merkle_proofs: Vec::new(),
+            proof_encoding: run.proof_encoding,
+            reproducible: false,
+            timestamp: None,
+            expected_purls: definition.expected_purls.clone(),
+            expected_banned_list_hash: definition.expected_banned_list_hash.clone(),
+            list_info: run.list_info.or_else(|| definition.list_info.clone()),
+            max_response_bytes: run.max_response_bytes,
+            banned_list_salt: None,
+            mode: definition.mode,
+            receipt_kind: definition.receipt_kind,
+            skip_preverify: false,
+            force: run.force,
+            prover_opts: None,
+            template: Some(TemplateRef {
+                name: definition.name.clone(),
+                version: self.version,
+            }),
+            requester: None,
+            redaction: None,
+        };
+
+        req.merkle_proofs = match (&definition.source, run.merkle_proofs) {
+            (ProofSource::Supplied, Some(proofs)) => proofs,
+            (ProofSource::Supplied, None) => {
+                return Err(self.violation("runs must supply merkle_proofs".to_string()));
+            }
+            (ProofSource::Snapshot { .. }, Some(_)) => {
+                return Err(self.violation(
+                    "its proofs are drawn from the SMT snapshot; runs cannot supply merkle_proofs"
+                        .to_string(),
+                ));
+            }
+            (ProofSource::Snapshot { purls }, None) => {
+                resolve_root_alias(&mut req, root_aliases)?;
+                let root = hex_to_bytes32(&req.root).map_err(|e| {
+                    let err_msg = format!("Invalid root '{}': {}", req.root, e);
+                    tracing::error!("{}", err_msg);
+                    Error::InvalidRequest(err_msg)
+                })?;
+                req.expected_purls = Some(purls.clone());
+                spot_check.proofs_for(purls, root)?
+            }
+        };
+        Ok(req)
+    }
+
+    fn violation(&self, reason: String) -> Error {
+        let err_msg = format!(
+            "Run violates template '{}' version {}: {}",
+            self.definition.name, self.version, reason
+        );
+        tracing::error!("{}", err_msg);
+        Error::InvalidRequest(err_msg)
+    }
+}
+
+/// Checks a template can pass before any run: its name, purls and hash are well formed
+/// and its root aliases are configured. Everything else is checked per run, with the
+/// root known.
+fn validate_definition(definition: &TemplateDefinition, config: &Config) -> Result<()> {
+    validate_name(&definition.name)?;
+
+    let invalid = |reason: String| {
+        let err_msg = format!("Invalid template '{}': {}", definition.name, reason);
+        tracing::error!("{}", err_msg);
+        Error::InvalidRequest(err_msg)
+    };
+
+    if let ProofSource::Snapshot { purls } = &definition.source {
+        if purls.is_empty() {
+            return Err(invalid(
+                "a snapshot source needs at least one purl".to_string(),
+            ));
+        }
+        if purls.len() > config.max_proofs_per_request {
+            return Err(invalid(format!(
+                "{} purls, more than the {} allowed by MAX_PROOFS_PER_REQUEST",
+                purls.len(),
+                config.max_proofs_per_request
+            )));
+        }
+        let mut seen = HashSet::new();
+        if let Some(duplicate) = purls.iter().find(|purl| !seen.insert(*purl)) {
+            return Err(invalid(format!("purl '{duplicate}' is listed twice")));
+        }
+        if definition.expected_purls.is_some() {
+            return Err(invalid(
+                "expected_purls is implied by a snapshot source and cannot be given".to_string(),
+            ));
+        }
+    }
+
+    if let Some(alias) = definition
+        .root_aliases
+        .iter()
+        .find(|alias| !config.root_aliases.contains_key(*alias))
+    {
+        return Err(invalid(format!("unknown root alias '{alias}'")));
+    }
+
+    if let Some(hash) = &definition.expected_banned_list_hash {
+        hex_to_bytes32(hash)
+            .map_err(|e| invalid(format!("invalid expected_banned_list_hash: {e}")))?;
+    }
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_TEMPLATE_NAME_LEN
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if !valid {
+        let err_msg = format!(
+            "Invalid template name '{name}': expected 1 to {MAX_TEMPLATE_NAME_LEN} ASCII letters, digits, '-' or '_'"
+        );
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg));
+    }
+    Ok(())
+}
@@ -1,10 +1,43 @@
 use crate::error::{Error, Result};
+use crate::models::ListInfo;
 
-pub use sbom_common::{DEFAULTS, bitmap_bit, count_bitmap_ones};
+pub use sbom_common::{
+    CompactProofError, DEFAULTS, KeyType, ProofType, bitmap_bit, bytes_to_hex, bytes32_to_hex_string,
+    compress_siblings, compute_banned_list_commitment, compute_banned_list_hash, compute_compact_root,
+    compute_cpe_hash, compute_leaf_index, compute_purl_hash, compute_reasons_hash, compute_requester_commitment, compute_salt_commitment, count_bitmap_ones, hash_leaf, hash_value,
+    parse_leaf_value,
+};
 
 pub fn hex_to_bytes32(hex_str: &str) -> Result<[u8; 32]> {
-    sbom_common::hex_to_bytes32(hex_str).map_err(|e| match e {
+    sbom_common::hex_to_bytes32(hex_str).map_err(hex_error)
+}
+
+/// The error of a hex string `sbom_common::hex_to_bytes32` rejected
+pub fn hex_error(e: sbom_common::HexError) -> Error {
+    match e {
         sbom_common::HexError::TooShort => Error::Hex("Hex string too short".to_string()),
+        sbom_common::HexError::TooLong => Error::Hex("Hex string too long".to_string()),
         sbom_common::HexError::InvalidCharacter => Error::Hex("Invalid hex character".to_string()),
-    })
+    }
+}
+
+/// Hash list provenance exactly as the guest does.
+pub fn compute_list_info_hash(info: &ListInfo) -> [u8; 32] {
+    sbom_common::compute_list_info_hash(
+        &info.source_uri,
+        &info.publisher,
+        &info.version,
+        info.entry_count,
+    )
+}
+
+/// Human-readable name of a banned-list reason code; 0 means the purl is not banned.
+pub fn reason_name(value: u64) -> &'static str {
+    match value {
+        0 => "none",
+        1 => "security",
+        2 => "legal",
+        3 => "export",
+        _ => "unknown",
+    }
 }
@@ -0,0 +1,1052 @@
+//! Request validation shared by proving and host-only preflight.
+//!
+//! Every check here runs before the zkVM is invoked, so malformed input is rejected
+//! with a specific message instead of surfacing as a non-compliant journal.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rayon::prelude::*;
+use sbom_common::input::{MAX_HASH_HEX_LEN, MAX_SIBLING_BYTES};
+use sbom_common::proof::{
+    ParseError, ParsedProof, ProofField, SizeError, check_leaf_index, check_proof_sizes,
+    decode_proof,
+};
+
+use crate::digest::normalize_hex;
+use crate::error::{Error, Result};
+use crate::models::{
+    BannedReason, CompactMerkleProof, EffectiveProverOptions, HashFn, ProofEncoding,
+    ProveCompactMerkleRequest, ProverOptions, ReceiptKind,
+};
+use crate::prover::{
+    DEFAULT_SEGMENT_PO2, FAST_PATH_SEGMENT_PO2, MAX_SEGMENT_PO2, MIN_SEGMENT_PO2, ProvingBackend,
+};
+use crate::redact::KeyDisplay;
+use crate::utils::{
+    CompactProofError, DEFAULTS, KeyType, ProofType, bitmap_bit, compress_siblings, compute_banned_list_hash,
+    compute_compact_root, hash_leaf, hex_error, hex_to_bytes32, parse_leaf_value, reason_name,
+};
+
+/// Resolve `root_alias` through the configured registry into `root`.
+/// If both are given they must agree, so a stale alias can't silently change the root.
+pub(crate) fn resolve_root_alias(
+    req: &mut ProveCompactMerkleRequest,
+    root_aliases: &HashMap<String, String>,
+) -> Result<()> {
+    let Some(alias) = req.root_alias.as_deref() else {
+        return Ok(());
+    };
+
+    let resolved = root_aliases.get(alias).ok_or_else(|| {
+        let err_msg = format!(
+            "Unknown root alias '{}'. Known aliases: [{}]",
+            alias,
+            root_aliases
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        tracing::error!("{}", err_msg);
+        Error::InvalidRequest(err_msg)
+    })?;
+
+    if !req.root.is_empty() && normalize_hex(&req.root) != *resolved {
+        let err_msg = format!(
+            "Root alias '{}' resolves to {}, but the request also specifies root {}",
+            alias, resolved, req.root
+        );
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg));
+    }
+
+    tracing::info!("Resolved root alias '{}' to {}", alias, resolved);
+    req.root = resolved.clone();
+    Ok(())
+}
+
+/// Strict mode: check the proof set against the caller's expected list before proving.
+///
+/// With `expected_purls`, the proofs must cover exactly those purls (surrounding whitespace
+/// in the list is ignored) and are reordered to match it, so the guest hashes the list in
+/// the caller's order. With `expected_banned_list_hash`, the proven purls must hash to it.
+/// Returns the hash the guest must check in-circuit, or `None` outside strict mode.
+pub(crate) fn apply_strict_mode(req: &mut ProveCompactMerkleRequest) -> Result<Option<[u8; 32]>> {
+    if req.expected_purls.is_none() && req.expected_banned_list_hash.is_none() {
+        return Ok(None);
+    }
+
+    let key_display = req.key_display();
+    let key_type = list_key_type(&req.merkle_proofs);
+    if let Some(expected_purls) = &req.expected_purls {
+        let expected: Vec<&str> = expected_purls.iter().map(|p| p.trim()).collect();
+
+        let mut seen = HashSet::new();
+        let duplicates: Vec<&str> = expected.iter().copied().filter(|p| !seen.insert(*p)).collect();
+        if !duplicates.is_empty() {
+            let err_msg = format!(
+                "Strict mode: expected_purls contains duplicate entries: {}",
+                display_keys(key_display, key_type, &duplicates)
+            );
+            tracing::error!("{}", err_msg);
+            return Err(Error::InvalidRequest(err_msg));
+        }
+
+        let mut by_purl: HashMap<&str, &CompactMerkleProof> = HashMap::new();
+        let mut extra: Vec<&str> = Vec::new();
+        for proof in &req.merkle_proofs {
+            if !seen.contains(proof.key.as_str()) || by_purl.insert(&proof.key, proof).is_some() {
+                extra.push(&proof.key);
+            }
+        }
+        let missing: Vec<&str> = expected
+            .iter()
+            .copied()
+            .filter(|p| !by_purl.contains_key(p))
+            .collect();
+
+        if !missing.is_empty() || !extra.is_empty() {
+            let err_msg = format!(
+                "Strict mode: proofs do not cover exactly the expected purls. Missing: [{}]. Extra or duplicate: [{}]",
+                display_keys(key_display, key_type, &missing),
+                display_keys(key_display, key_type, &extra)
+            );
+            tracing::error!("{}", err_msg);
+            return Err(Error::InvalidRequest(err_msg));
+        }
+
+        let reordered: Vec<CompactMerkleProof> =
+            expected.iter().map(|p| by_purl[p].clone()).collect();
+        req.merkle_proofs = reordered;
+    }
+
+    let banned_list: Vec<&str> = req.merkle_proofs.iter().map(|p| p.key.as_str()).collect();
+    let actual = compute_banned_list_hash(&banned_list);
+
+    if let Some(expected_hex) = &req.expected_banned_list_hash {
+        let expected = hex_to_bytes32(expected_hex).map_err(|e| {
+            let err_msg = format!(
+                "Invalid expected_banned_list_hash '{expected_hex}': {e}. Must be a 64-character hex string"
+            );
+            tracing::error!("{}", err_msg);
+            Error::InvalidRequest(err_msg)
+        })?;
+        if expected != actual {
+            let err_msg = format!(
+                "Strict mode: banned list hash mismatch: expected {}, the proven purls hash to {}",
+                hex::encode(expected),
+                hex::encode(actual)
+            );
+            tracing::error!("{}", err_msg);
+            return Err(Error::InvalidRequest(err_msg));
+        }
+    }
+
+    tracing::info!(
+        "Strict mode: proof set matches the expected list (banned_list_hash={})",
+        hex::encode(actual)
+    );
+    Ok(Some(actual))
+}
+
+/// The list provenance, when given, must describe the proof set actually submitted.
+pub(crate) fn validate_list_info(req: &ProveCompactMerkleRequest) -> Result<()> {
+    let Some(info) = &req.list_info else {
+        return Ok(());
+    };
+
+    if info.entry_count != req.merkle_proofs.len() as u64 {
+        let err_msg = format!(
+            "list_info entry_count mismatch: list '{}' version '{}' declares {} entries, but {} proof(s) were provided",
+            info.source_uri,
+            info.version,
+            info.entry_count,
+            req.merkle_proofs.len()
+        );
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg));
+    }
+
+    Ok(())
+}
+
+/// The prover settings `req` is proven with on `backend`, and whether it takes the fast
+/// path. Each setting is the request's own, else the configured one, else RISC Zero's
+/// default; the fast path proves in smaller segments unless a size is set. Only poseidon2
+/// seals can be compressed, so another hash function is refused for `succinct` and
+/// `groth16` receipts and keeps the request off the fast path, which always compresses.
+pub(crate) fn resolve_prover_opts(
+    req: &ProveCompactMerkleRequest,
+    configured: ProverOptions,
+    fast_path_max_proofs: usize,
+    backend: &dyn ProvingBackend,
+) -> Result<(EffectiveProverOptions, bool)> {
+    let invalid = |err_msg: String| {
+        tracing::error!("{}", err_msg);
+        Error::InvalidRequest(err_msg)
+    };
+    let requested = req.prover_opts.unwrap_or_default();
+    if req.prover_opts.is_some() && !backend.takes_prover_opts() {
+        return Err(invalid(format!(
+            "The {} prover does not take prover_opts",
+            backend.name()
+        )));
+    }
+    if let Some(po2) = requested.segment_po2
+        && !(MIN_SEGMENT_PO2..=MAX_SEGMENT_PO2).contains(&po2)
+    {
+        return Err(invalid(format!(
+            "prover_opts.segment_po2 must be {MIN_SEGMENT_PO2} to {MAX_SEGMENT_PO2}, got {po2}"
+        )));
+    }
+
+    let hashfn = requested.hashfn.or(configured.hashfn).unwrap_or_default();
+    if hashfn != HashFn::Poseidon2 && req.receipt_kind != ReceiptKind::Composite {
+        return Err(invalid(format!(
+            "A {} receipt can only be made with the poseidon2 hash function, not {}",
+            req.receipt_kind.name(),
+            hashfn.name()
+        )));
+    }
+    let fast_path = req.merkle_proofs.len() <= fast_path_max_proofs && hashfn == HashFn::Poseidon2;
+    let segment_po2 = requested
+        .segment_po2
+        .or(configured.segment_po2)
+        .unwrap_or(if fast_path {
+            FAST_PATH_SEGMENT_PO2
+        } else {
+            DEFAULT_SEGMENT_PO2
+        });
+    Ok((
+        EffectiveProverOptions {
+            segment_po2,
+            hashfn,
+        },
+        fast_path,
+    ))
+}
+
+/// Refuse a request with more proofs than `MAX_PROOFS_PER_REQUEST` before any of them is
+/// looked at.
+pub(crate) fn validate_proof_count(
+    req: &ProveCompactMerkleRequest,
+    max_proofs: usize,
+) -> Result<()> {
+    let count = req.merkle_proofs.len();
+    if count > max_proofs {
+        let err = Error::LimitExceeded {
+            setting: "MAX_PROOFS_PER_REQUEST",
+            unit: "proofs",
+            limit: max_proofs,
+            observed: Some(count),
+        };
+        tracing::error!("{}", err);
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Request-level checks shared by every endpoint accepting a `ProveCompactMerkleRequest`.
+/// Returns the parsed root hash.
+pub(crate) fn validate_request(req: &ProveCompactMerkleRequest) -> Result<[u8; 32]> {
+    if req.depth != 256 {
+        let err_msg = format!(
+            "Invalid depth: expected 256, got {}. Depth must be exactly 256 for this merkle tree configuration",
+            req.depth
+        );
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg));
+    }
+
+    if req.merkle_proofs.is_empty() {
+        let err_msg = "Request validation failed: at least one merkle proof is required. Cannot generate proof without any proofs to verify";
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg.to_string()));
+    }
+
+    if req.root.is_empty() {
+        let err_msg = "Request validation failed: either root or root_alias is required";
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg.to_string()));
+    }
+
+    let root_hash = hex_to_bytes32(&req.root)
+        .map_err(|e| {
+            let err_msg = format!("Invalid root hash format: '{}'. Error details: {}. Root hash must be a valid 64-character hex string (optionally prefixed with '0x')", req.root, e);
+            tracing::error!("{}", err_msg);
+            Error::InvalidRequest(err_msg)
+        })?;
+
+    // The journal commits a single key type for the whole list
+    let key_type = list_key_type(&req.merkle_proofs);
+    let key_display = req.key_display();
+    if let Some(other) = req.merkle_proofs.iter().find(|p| p.key_type != key_type) {
+        let err_msg = format!(
+            "Request validation failed: all proofs must use the same key_type, but '{}' is {} while the first proof is {}",
+            key_display.proof(other),
+            other.key_type.name(),
+            key_type.name()
+        );
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg));
+    }
+
+    // A membership proof of an absent key can only yield a non-compliant journal, so it is
+    // rejected up front; malformed values are left to `validate_compact_proof`
+    if req.mode == ProofType::Membership
+        && let Some(absent) = req
+            .merkle_proofs
+            .iter()
+            .find(|p| parse_leaf_value(&p.value) == Some(0))
+    {
+        let err_msg = format!(
+            "Request validation failed: mode \"membership\" needs a nonzero value in every proof, but '{}' has value {} (not in the tree)",
+            key_display.proof(absent),
+            absent.value
+        );
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg));
+    }
+
+    Ok(root_hash)
+}
+
+/// Rewrite uncompressed proofs into the canonical compact form, so everything after this
+/// (validation, the request digest and the guest) sees one encoding. The encoding comes
+/// from `proof_encoding` alone: a compact request with a missing bitmap is an error, not
+/// a hint to compress.
+pub(crate) fn normalize_proof_encoding(req: &mut ProveCompactMerkleRequest) -> Result<()> {
+    if req.proof_encoding == ProofEncoding::Compact {
+        return Ok(());
+    }
+
+    let key_display = req.key_display();
+    for proof in &mut req.merkle_proofs {
+        if !proof.bitmap.is_empty() {
+            return Err(invalid_proof(
+                proof,
+                key_display,
+                format!(
+                    "uncompressed proof carries a bitmap ('{}'), but proof_encoding \"uncompressed\" expects one sibling per level and no bitmap",
+                    proof.bitmap
+                ),
+            ));
+        }
+
+        let siblings = proof
+            .siblings
+            .iter()
+            .enumerate()
+            .map(|(d, s)| {
+                hex_to_bytes32(s).map_err(|e| {
+                    invalid_proof(
+                        proof,
+                        key_display,
+                        format!(
+                            "invalid sibling hex format at depth {d}: {e}. Sibling value: '{s}'"
+                        ),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (bitmap, kept) = compress_siblings(&siblings).ok_or_else(|| {
+            invalid_proof(
+                proof,
+                key_display,
+                format!(
+                    "uncompressed proof needs one sibling for each of the 256 levels, got {}",
+                    siblings.len()
+                ),
+            )
+        })?;
+
+        proof.bitmap = hex::encode(bitmap);
+        proof.siblings = kept.iter().map(hex::encode).collect();
+    }
+
+    tracing::info!(
+        "Compressed {} uncompressed proof(s) into compact form",
+        req.merkle_proofs.len()
+    );
+    req.proof_encoding = ProofEncoding::Compact;
+    Ok(())
+}
+
+/// The guest's size guard, run on the host: every proof must carry as many siblings as its
+/// bitmap has set bits, every hash must fit `MAX_HASH_HEX_LEN` and all siblings together
+/// `MAX_SIBLING_BYTES`. Runs on the compact form, which is what the guest sees; the guest
+/// rejects the whole input on any of these, so the request is refused as a whole too.
+pub(crate) fn validate_proof_sizes(req: &ProveCompactMerkleRequest) -> Result<()> {
+    check_proof_sizes(req.merkle_proofs.iter().map(CompactMerkleProof::fields))
+        .map_err(|error| size_error(&req.merkle_proofs, req.key_display(), error))
+}
+
+/// The error of a size check of `proofs` that failed
+fn size_error(proofs: &[CompactMerkleProof], key_display: KeyDisplay, error: SizeError) -> Error {
+    let reason = match error {
+        SizeError::OversizedField { field, len, .. } => format!(
+            "invalid {}: {len} characters long, at most {MAX_HASH_HEX_LEN} allowed",
+            field_name(field)
+        ),
+        SizeError::OversizedSibling { sibling, len, .. } => format!(
+            "invalid sibling (sibling index {sibling}): {len} characters long, at most {MAX_HASH_HEX_LEN} allowed"
+        ),
+        SizeError::SiblingCount {
+            index,
+            expected,
+            actual,
+        } => format!(
+            "sibling count mismatch: bitmap indicates {expected} sibling(s) should be present (bitmap: '{}'), but {actual} sibling(s) provided",
+            proofs[index].bitmap
+        ),
+        SizeError::SiblingBudget { .. } => {
+            let sibling_bytes: usize = proofs
+                .iter()
+                .flat_map(|p| &p.siblings)
+                .map(String::len)
+                .sum();
+            let err_msg = format!(
+                "Request too large: the siblings of all proofs total {sibling_bytes} bytes, the guest accepts at most {MAX_SIBLING_BYTES}. Split the proofs over several requests"
+            );
+            tracing::error!("{}", err_msg);
+            return Error::InvalidRequest(err_msg);
+        }
+    };
+    let index = match error {
+        SizeError::OversizedField { index, .. }
+        | SizeError::OversizedSibling { index, .. }
+        | SizeError::SiblingCount { index, .. }
+        | SizeError::SiblingBudget { index } => index,
+    };
+    invalid_proof(&proofs[index], key_display, reason)
+}
+
+/// Key type of a proof list as the guest commits it: that of the first proof, or purl
+/// for an empty list. `validate_request` ensures the others agree.
+pub(crate) fn list_key_type(proofs: &[CompactMerkleProof]) -> KeyType {
+    proofs.first().map_or(KeyType::Purl, |p| p.key_type)
+}
+
+/// Earliest caller-supplied timestamp accepted in reproducible mode (2020-01-01T00:00:00Z).
+const MIN_REPRODUCIBLE_TIMESTAMP: u64 = 1_577_836_800;
+/// How far ahead of this host's clock a caller-supplied timestamp may be.
+const MAX_TIMESTAMP_SKEW_SECS: u64 = 300;
+
+/// Pick the timestamp recorded with the proof. Reproducible requests must supply one
+/// within a sane window; all others use the system clock.
+pub(crate) fn resolve_timestamp(req: &ProveCompactMerkleRequest) -> Result<u64> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    if !req.reproducible {
+        if req.timestamp.is_some() {
+            let err_msg = "Request validation failed: timestamp is only accepted together with reproducible: true";
+            tracing::error!("{}", err_msg);
+            return Err(Error::InvalidRequest(err_msg.to_string()));
+        }
+        return Ok(now);
+    }
+
+    let Some(timestamp) = req.timestamp else {
+        let err_msg = "Request validation failed: reproducible mode requires a caller-supplied timestamp";
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg.to_string()));
+    };
+
+    if timestamp < MIN_REPRODUCIBLE_TIMESTAMP || timestamp > now + MAX_TIMESTAMP_SKEW_SECS {
+        let err_msg = format!(
+            "Invalid timestamp {timestamp}: must be between {MIN_REPRODUCIBLE_TIMESTAMP} and {MAX_TIMESTAMP_SKEW_SECS} seconds ahead of the current time ({now})"
+        );
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg));
+    }
+
+    tracing::info!("Reproducible mode: using caller-supplied timestamp {}", timestamp);
+    Ok(timestamp)
+}
+
+/// Parse the optional blinding salt. It is a secret, so errors never echo it.
+pub(crate) fn resolve_blinding_salt(req: &ProveCompactMerkleRequest) -> Result<Option<[u8; 32]>> {
+    let Some(salt) = req.banned_list_salt.as_deref() else {
+        return Ok(None);
+    };
+
+    let salt = hex_to_bytes32(salt).map_err(|e| {
+        let err_msg = format!("Invalid banned_list_salt: {e}. Must be a 64-character hex string");
+        tracing::error!("{}", err_msg);
+        Error::InvalidRequest(err_msg)
+    })?;
+
+    tracing::info!("Blinding the banned list commitment with a caller-supplied salt");
+    Ok(Some(salt))
+}
+
+/// Validate every proof, spread across all cores when `parallel`; a handful of proofs is
+/// validated sooner on the calling thread. A request with several bad proofs always fails
+/// on the lowest index of them, with that index, so its error is stable.
+pub(crate) fn validate_compact_proofs(
+    proofs: &[CompactMerkleProof],
+    key_display: KeyDisplay,
+    parallel: bool,
+) -> std::result::Result<Vec<ParsedProof>, (usize, Error)> {
+    let validate = |proof| validate_compact_proof(proof, key_display);
+    let outcomes: Vec<_> = if parallel {
+        proofs.par_iter().map(validate).collect()
+    } else {
+        proofs.iter().map(validate).collect()
+    };
+    outcomes
+        .into_iter()
+        .enumerate()
+        .map(|(index, outcome)| outcome.map_err(|e| (index, e)))
+        .collect()
+}
+
+/// Run the guest's checks of a single proof: its sizes, then its value and hex fields. A
+/// sibling equal to the default of its level is refused here as well, as the guest rejects
+/// such a non-canonical proof while folding it. The key binding is left to
+/// [`recompute_compact_proof`].
+pub(crate) fn validate_compact_proof(
+    proof: &CompactMerkleProof,
+    key_display: KeyDisplay,
+) -> Result<ParsedProof> {
+    tracing::debug!(
+        "Validating compact proof for key: {}",
+        key_display.proof(proof)
+    );
+
+    check_proof_sizes([proof.fields()])
+        .map_err(|error| size_error(std::slice::from_ref(proof), key_display, error))?;
+    let parsed = decode_proof(proof.fields())
+        .map_err(|error| invalid_proof(proof, key_display, parse_reason(proof, error)))?;
+
+    for (d, sibling) in sibling_depths(&parsed.bitmap).zip(&parsed.siblings) {
+        if *sibling == DEFAULTS[d] {
+            return Err(invalid_proof(
+                proof,
+                key_display,
+                format!(
+                    "invalid sibling at depth {d}: sibling matches DEFAULTS[{d}] (value: {}). When sibling equals default value, bitmap bit should be 0, not 1",
+                    hex::encode(DEFAULTS[d])
+                ),
+            ));
+        }
+    }
+
+    tracing::debug!(
+        "Compact proof validation successful for key: {}",
+        key_display.proof(proof)
+    );
+    Ok(parsed)
+}
+
+/// Levels of the tree that carry a sibling, from the leaf up
+fn sibling_depths(bitmap: &[u8; 32]) -> impl Iterator<Item = usize> + '_ {
+    (0..256).filter(move |&d| bitmap_bit(bitmap, d) == 1)
+}
+
+fn field_name(field: ProofField) -> String {
+    match field {
+        ProofField::Bitmap => "bitmap".to_string(),
+        ProofField::LeafIndex => "leaf_index".to_string(),
+        ProofField::Sibling(index) => format!("sibling (sibling index {index})"),
+    }
+}
+
+/// Why the guest would fail `proof` with `error`, in the words of `/validate`
+fn parse_reason(proof: &CompactMerkleProof, error: ParseError) -> String {
+    match error {
+        ParseError::BadValue => format!(
+            "invalid value: expected a decimal u64 (0 for non-membership, otherwise a reason code), got '{}'",
+            proof.value
+        ),
+        ParseError::BadHex {
+            field: ProofField::Bitmap,
+            ..
+        } if proof.bitmap.is_empty() => {
+            "missing bitmap: compact proofs require a 64-character hex bitmap. Proofs listing a sibling for every level need proof_encoding \"uncompressed\"".to_string()
+        }
+        ParseError::BadHex {
+            field: field @ (ProofField::Bitmap | ProofField::LeafIndex),
+            error,
+        } => {
+            let hex = match field {
+                ProofField::Bitmap => &proof.bitmap,
+                _ => &proof.leaf_index,
+            };
+            let digits = hex.strip_prefix("0x").unwrap_or(hex).len();
+            if digits != 64 {
+                format!(
+                    "invalid {} length: expected 64-character hex string, got {digits} characters (value: '{hex}')",
+                    field_name(field)
+                )
+            } else {
+                format!(
+                    "invalid {} hex format: {}. Value: '{hex}'. It must be a valid 64-character hex string",
+                    field_name(field),
+                    hex_error(error)
+                )
+            }
+        }
+        ParseError::BadHex {
+            field: ProofField::Sibling(index),
+            error,
+        } => {
+            // Siblings are decoded after the bitmap, so it is valid here
+            let depth = hex_to_bytes32(&proof.bitmap)
+                .ok()
+                .and_then(|bitmap| sibling_depths(&bitmap).nth(index));
+            format!(
+                "invalid sibling hex format at depth {} (sibling index {index}): {}. Sibling value: '{}'",
+                depth.map_or_else(|| "?".to_string(), |d| d.to_string()),
+                hex_error(error),
+                proof.siblings[index]
+            )
+        }
+        ParseError::LeafIndexMismatch {
+            expected: Some(expected),
+        } => format!(
+            "leaf_index for {} key does not match its hash: expected {}, got '{}'",
+            proof.key_type.name(),
+            hex::encode(expected),
+            proof.leaf_index
+        ),
+        ParseError::LeafIndexMismatch { expected: None } => format!(
+            "{} key is not a 64-character hex string",
+            proof.key_type.name()
+        ),
+    }
+}
+
+/// A validation failure of `proof`, logged
+fn invalid_proof(proof: &CompactMerkleProof, key_display: KeyDisplay, reason: String) -> Error {
+    let error = Error::ProofValidation {
+        purl: key_display.proof(proof).into_owned(),
+        reason,
+    };
+    tracing::error!("{}", error);
+    error
+}
+
+/// Replay the guest's per-proof checks on the host and return the computed root.
+/// Mirrors `validate_proofs` in the guest: leaf_index must be the one the key maps to
+/// under its key_type, and the fold must consume siblings according to the bitmap.
+/// `decoded` is what validating `proof` decoded of it.
+pub(crate) fn recompute_compact_proof(
+    proof: &CompactMerkleProof,
+    decoded: &ParsedProof,
+    key_display: KeyDisplay,
+) -> std::result::Result<[u8; 32], String> {
+    let key = key_display.proof(proof);
+    check_leaf_index(proof.fields(), &decoded.leaf_index)
+        .map_err(|error| format!("Key '{key}': {}", parse_reason(proof, error)))?;
+
+    let leaf_hash = hash_leaf(decoded.value);
+    compute_compact_root(&leaf_hash, &decoded.leaf_index, &decoded.bitmap, &decoded.siblings).map_err(|e| {
+        match e {
+            CompactProofError::MissingSibling { depth } => format!(
+                "Insufficient siblings for key '{}': bitmap requires a sibling at depth {} but only {} were provided",
+                key,
+                depth,
+                decoded.siblings.len()
+            ),
+            CompactProofError::DefaultSibling { depth } => format!(
+                "Non-canonical proof for key '{key}': sibling at depth {depth} equals DEFAULTS[{depth}], so its bitmap bit must be 0"
+            ),
+        }
+    })
+}
+
+/// Fold every proof on the host exactly as the guest will and fail on the first that does
+/// not reach `root`, so a request bound to come out non-compliant is refused in
+/// milliseconds instead of after a full zkVM run. Proofs are folded in parallel; the
+/// failure reported is always that of the lowest index.
+pub(crate) fn preverify_proofs(
+    proofs: &[CompactMerkleProof],
+    decoded: &[ParsedProof],
+    root: &[u8; 32],
+    key_display: KeyDisplay,
+) -> Result<()> {
+    let failure =
+        proofs
+            .par_iter()
+            .zip(decoded)
+            .enumerate()
+            .find_map_first(|(index, (proof, decoded))| {
+                preverify_failure(index, proof, decoded, root, key_display)
+            });
+    match failure {
+        Some(error) => {
+            tracing::error!("{}", error);
+            Err(error)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Why `proof`, at `index` of the request, does not fold up to `root`, if it does not
+fn preverify_failure(
+    index: usize,
+    proof: &CompactMerkleProof,
+    decoded: &ParsedProof,
+    root: &[u8; 32],
+    key_display: KeyDisplay,
+) -> Option<Error> {
+    let (computed_root, reason) = match recompute_compact_proof(proof, decoded, key_display) {
+        Ok(computed) if computed == *root => return None,
+        Ok(computed) => (
+            Some(hex::encode(computed)),
+            format!(
+                "it folds up to {}, not {}",
+                hex::encode(computed),
+                hex::encode(root)
+            ),
+        ),
+        Err(reason) => (None, reason),
+    };
+    Some(Error::Preverify {
+        index,
+        purl: key_display.proof(proof).into_owned(),
+        computed_root,
+        reason,
+    })
+}
+
+/// `keys`, of `key_type`, comma-separated as they may be logged
+fn display_keys(key_display: KeyDisplay, key_type: KeyType, keys: &[&str]) -> String {
+    keys.iter()
+        .map(|key| key_display.key(key_type, key))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Decode the reason codes of every banned (nonzero-value) proof, in request order.
+pub(crate) fn banned_reasons(proofs: &[CompactMerkleProof]) -> Vec<BannedReason> {
+    proofs
+        .iter()
+        .filter_map(|proof| {
+            let value = parse_leaf_value(&proof.value).filter(|&v| v != 0)?;
+            Some(BannedReason {
+                purl: proof.key.clone(),
+                leaf_index: normalize_hex(&proof.leaf_index),
+                value,
+                reason: reason_name(value),
+            })
+        })
+        .collect()
+}
+
+/// Host validation of the proofs the Go merkle-proof-service returns, in both encodings.
+/// The guest's verdict on the same proofs is checked against `/validate` in `tests/guest.rs`.
+#[cfg(test)]
+mod tests {
+    use sbom_common::smt::SparseMerkleTree;
+
+    use super::*;
+    use crate::models::ListInfo;
+    use crate::utils::compute_leaf_index;
+
+    /// Whether the host's validation and preverification pass `proof` for `root`
+    fn host_accepts(proof: &CompactMerkleProof, root: &[u8; 32]) -> bool {
+        validate_compact_proof(proof, KeyDisplay::Plain).is_ok_and(|decoded| {
+            recompute_compact_proof(proof, &decoded, KeyDisplay::Plain) == Ok(*root)
+        })
+    }
+
+    /// Batch proofs the Go merkle-proof-service returned for the benchmark requests
+    const GO_FIXTURES: [&str; 3] = [
+        include_str!("../benchmark/data/merkleproofs/batch_proof_2.json"),
+        include_str!("../benchmark/data/merkleproofs/batch_proof_5.json"),
+        include_str!("../benchmark/data/merkleproofs/batch_proof_10.json"),
+    ];
+
+    /// `proof` with one sibling per level and no bitmap, as the Go service returns it
+    /// with `compress: false`
+    fn uncompressed(proof: &CompactMerkleProof) -> CompactMerkleProof {
+        let bitmap = hex_to_bytes32(&proof.bitmap).unwrap();
+        let mut kept = proof.siblings.iter();
+        let siblings = (0..256)
+            .map(|d| match bitmap_bit(&bitmap, d) {
+                1 => kept.next().unwrap().clone(),
+                _ => hex::encode(DEFAULTS[d]),
+            })
+            .collect();
+        CompactMerkleProof {
+            siblings,
+            bitmap: String::new(),
+            ..proof.clone()
+        }
+    }
+
+    /// The first fixture's request, naming its root only through `alias`
+    fn aliased(alias: &str) -> ProveCompactMerkleRequest {
+        let mut req: ProveCompactMerkleRequest = serde_json::from_str(GO_FIXTURES[0]).unwrap();
+        req.root = String::new();
+        req.root_alias = Some(alias.to_string());
+        req
+    }
+
+    fn registry(root: &str) -> HashMap<String, String> {
+        HashMap::from([("bl@1".to_string(), normalize_hex(root))])
+    }
+
+    #[test]
+    fn list_info_must_count_the_proofs() {
+        let mut req: ProveCompactMerkleRequest = serde_json::from_str(GO_FIXTURES[0]).unwrap();
+        assert!(validate_list_info(&req).is_ok());
+
+        let proofs = req.merkle_proofs.len() as u64;
+        for (entry_count, accepted) in [(proofs, true), (proofs + 1, false)] {
+            req.list_info = Some(ListInfo {
+                source_uri: "https://lists.example.org/banned.json".to_string(),
+                publisher: "example".to_string(),
+                version: "2024-06".to_string(),
+                entry_count,
+            });
+            assert_eq!(validate_list_info(&req).is_ok(), accepted, "{entry_count}");
+        }
+    }
+
+    #[test]
+    fn known_aliases_resolve_to_their_root() {
+        let fixture: ProveCompactMerkleRequest = serde_json::from_str(GO_FIXTURES[0]).unwrap();
+        let aliases = registry(&fixture.root);
+
+        let mut req = aliased("bl@1");
+        resolve_root_alias(&mut req, &aliases).unwrap();
+        assert_eq!(req.root, normalize_hex(&fixture.root));
+
+        // Restating the same root, in any spelling, is fine
+        let mut req = aliased("bl@1");
+        req.root = format!("0x{}", fixture.root.to_uppercase());
+        resolve_root_alias(&mut req, &aliases).unwrap();
+        assert_eq!(req.root, normalize_hex(&fixture.root));
+    }
+
+    #[test]
+    fn unknown_aliases_are_refused() {
+        let mut req = aliased("bl@2");
+        let err = resolve_root_alias(&mut req, &registry(&"11".repeat(32))).unwrap_err();
+        assert!(matches!(err, Error::InvalidRequest(_)), "{err}");
+        assert!(err.to_string().contains("'bl@2'"), "{err}");
+        // The known aliases are listed
+        assert!(err.to_string().contains("bl@1"), "{err}");
+        assert!(req.root.is_empty());
+    }
+
+    #[test]
+    fn stale_aliases_cannot_move_an_explicit_root() {
+        let fixture: ProveCompactMerkleRequest = serde_json::from_str(GO_FIXTURES[0]).unwrap();
+        // The registry has moved on to a newer list; the request still pins the old root
+        let mut req = aliased("bl@1");
+        req.root = fixture.root.clone();
+        let err = resolve_root_alias(&mut req, &registry(&"11".repeat(32))).unwrap_err();
+        assert!(matches!(err, Error::InvalidRequest(_)), "{err}");
+        assert!(err.to_string().contains("also specifies root"), "{err}");
+        assert_eq!(req.root, fixture.root);
+    }
+
+    #[test]
+    fn every_key_type_is_bound_to_its_leaf() {
+        let keys = [
+            // Mixed case, as a lowercase purl hashes the same as a CPE name would
+            (KeyType::Purl, "pkg:maven/org.Example/Lib@1".to_string()),
+            (KeyType::Cpe, "cpe:2.3:a:vendor:product".to_string()),
+            (KeyType::RawHash, "ab".repeat(32)),
+        ];
+        let mut tree = SparseMerkleTree::default();
+        tree.insert("pkg:npm/evil@1.0.0", 1);
+        let root = tree.root();
+
+        for (key_type, key) in &keys {
+            let leaf_index = compute_leaf_index(*key_type, key).unwrap();
+            let compact = tree.prove_leaf(&leaf_index);
+            let proof = CompactMerkleProof {
+                key: key.clone(),
+                key_type: *key_type,
+                value: compact.value.to_string(),
+                leaf_index: hex::encode(compact.leaf_index),
+                siblings: compact.siblings.iter().map(hex::encode).collect(),
+                bitmap: hex::encode(compact.bitmap),
+            };
+            assert!(host_accepts(&proof, &root), "{key}");
+
+            // The same leaf claimed under another key type does not bind to the key
+            for (other, _) in keys.iter().filter(|(other, _)| other != key_type) {
+                let rebound = CompactMerkleProof {
+                    key_type: *other,
+                    ..proof.clone()
+                };
+                let decoded = validate_compact_proof(&rebound, KeyDisplay::Plain).unwrap();
+                let recomputed = recompute_compact_proof(&rebound, &decoded, KeyDisplay::Plain);
+                assert!(recomputed.is_err(), "{key} as {}", other.name());
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_validation_matches_sequential_and_is_faster() {
+        let mut tree = SparseMerkleTree::default();
+        for n in 0..64 {
+            tree.insert(&format!("pkg:npm/banned-{n}@1"), 1);
+        }
+        let mut proofs: Vec<CompactMerkleProof> = (0..5_000)
+            .map(|n| {
+                let key = format!("pkg:npm/package-{n}@1.0.0");
+                let compact = tree.prove(&key);
+                CompactMerkleProof {
+                    key,
+                    key_type: KeyType::Purl,
+                    value: compact.value.to_string(),
+                    leaf_index: hex::encode(compact.leaf_index),
+                    siblings: compact.siblings.iter().map(hex::encode).collect(),
+                    bitmap: hex::encode(compact.bitmap),
+                }
+            })
+            .collect();
+
+        let timed = |parallel| {
+            let started = std::time::Instant::now();
+            let validated = validate_compact_proofs(&proofs, KeyDisplay::Plain, parallel);
+            (validated.unwrap(), started.elapsed())
+        };
+        let (sequential, sequential_time) = timed(false);
+        let (parallel, parallel_time) = timed(true);
+        assert_eq!(parallel, sequential);
+        eprintln!("5,000 proofs: sequential {sequential_time:?}, parallel {parallel_time:?}");
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        if cores >= 4 {
+            assert!(parallel_time < sequential_time);
+        }
+
+        // Whichever proof a worker reaches first, the lowest bad index is reported
+        for index in [4_321, 1_234] {
+            proofs[index].value = "x".to_string();
+        }
+        for parallel in [false, true] {
+            let failed = validate_compact_proofs(&proofs, KeyDisplay::Plain, parallel);
+            assert!(matches!(failed, Err((1_234, _))), "parallel: {parallel}");
+        }
+    }
+
+    /// The second fixture's request in strict mode, expecting `expected` in that order
+    fn strict(expected: &[&str]) -> ProveCompactMerkleRequest {
+        let mut req: ProveCompactMerkleRequest = serde_json::from_str(GO_FIXTURES[1]).unwrap();
+        req.expected_purls = Some(expected.iter().map(|key| key.to_string()).collect());
+        req
+    }
+
+    fn keys(req: &ProveCompactMerkleRequest) -> Vec<String> {
+        req.merkle_proofs.iter().map(|p| p.key.clone()).collect()
+    }
+
+    #[test]
+    fn strict_mode_reorders_proofs_to_the_expected_list() {
+        let fixture: ProveCompactMerkleRequest = serde_json::from_str(GO_FIXTURES[1]).unwrap();
+        let mut expected = keys(&fixture);
+        expected.reverse();
+        let expected: Vec<&str> = expected.iter().map(String::as_str).collect();
+
+        let mut req = strict(&expected);
+        let hash = apply_strict_mode(&mut req).unwrap();
+        assert_eq!(keys(&req), expected);
+        assert_eq!(hash, Some(compute_banned_list_hash(&expected)));
+
+        // Only expected_purls reorders; against a bare hash, order is part of the list
+        let mut req = strict(&expected);
+        req.expected_purls = None;
+        req.expected_banned_list_hash = Some(hex::encode(compute_banned_list_hash(&expected)));
+        let err = apply_strict_mode(&mut req).unwrap_err();
+        assert!(err.to_string().contains("hash mismatch"), "{err}");
+    }
+
+    #[test]
+    fn strict_mode_names_missing_and_extra_purls() {
+        let fixture: ProveCompactMerkleRequest = serde_json::from_str(GO_FIXTURES[1]).unwrap();
+        let all = keys(&fixture);
+        let all: Vec<&str> = all.iter().map(String::as_str).collect();
+
+        // A proof the list does not expect
+        let mut req = strict(&all[1..]);
+        let err = apply_strict_mode(&mut req).unwrap_err().to_string();
+        assert!(err.contains("Missing: []"), "{err}");
+        let extra = format!("Extra or duplicate: [{}]", all[0]);
+        assert!(err.contains(&extra), "{err}");
+
+        // An expected purl nobody proved
+        let mut expected = all.clone();
+        expected.push("pkg:npm/unproven@1.0.0");
+        let mut req = strict(&expected);
+        let err = apply_strict_mode(&mut req).unwrap_err().to_string();
+        assert!(err.contains("Missing: [pkg:npm/unproven@1.0.0]"), "{err}");
+        assert!(err.contains("Extra or duplicate: []"), "{err}");
+
+        // The same purl expected twice
+        let mut expected = all.clone();
+        expected.push(all[0]);
+        let mut req = strict(&expected);
+        let err = apply_strict_mode(&mut req).unwrap_err().to_string();
+        assert!(err.contains("duplicate entries"), "{err}");
+    }
+
+    #[test]
+    fn go_service_proofs_validate() {
+        for fixture in GO_FIXTURES {
+            let req: ProveCompactMerkleRequest = serde_json::from_str(fixture).unwrap();
+            let root = hex_to_bytes32(&req.root).unwrap();
+            assert!(!req.merkle_proofs.is_empty());
+            for proof in &req.merkle_proofs {
+                assert!(host_accepts(proof, &root), "host rejects {}", proof.key);
+            }
+        }
+    }
+
+    #[test]
+    fn uncompressed_go_proofs_become_compact() {
+        for fixture in GO_FIXTURES {
+            let compact: ProveCompactMerkleRequest = serde_json::from_str(fixture).unwrap();
+            let mut req: ProveCompactMerkleRequest = serde_json::from_str(fixture).unwrap();
+            req.proof_encoding = ProofEncoding::Uncompressed;
+            req.merkle_proofs = compact.merkle_proofs.iter().map(uncompressed).collect();
+
+            normalize_proof_encoding(&mut req).unwrap();
+            assert_eq!(req.proof_encoding, ProofEncoding::Compact);
+            for (normalized, original) in req.merkle_proofs.iter().zip(&compact.merkle_proofs) {
+                assert_eq!(normalized.bitmap, original.bitmap, "{}", original.key);
+                assert_eq!(normalized.siblings, original.siblings, "{}", original.key);
+            }
+        }
+    }
+
+    #[test]
+    fn proof_encoding_is_never_guessed() {
+        let compact: ProveCompactMerkleRequest = serde_json::from_str(GO_FIXTURES[0]).unwrap();
+        let proof = &compact.merkle_proofs[0];
+
+        // A compact request whose proof lacks its bitmap points at proof_encoding
+        let err = validate_compact_proof(&uncompressed(proof), KeyDisplay::Plain)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("proof_encoding"), "{}", err);
+
+        let normalize = |proof: CompactMerkleProof| {
+            let mut req: ProveCompactMerkleRequest = serde_json::from_str(GO_FIXTURES[0]).unwrap();
+            req.proof_encoding = ProofEncoding::Uncompressed;
+            req.merkle_proofs = vec![proof];
+            normalize_proof_encoding(&mut req)
+        };
+        // An uncompressed proof that kept its bitmap, or lost a level, is refused
+        let with_bitmap = CompactMerkleProof {
+            bitmap: proof.bitmap.clone(),
+            ..uncompressed(proof)
+        };
+        assert!(normalize(with_bitmap).is_err());
+        let mut short = uncompressed(proof);
+        short.siblings.pop();
+        assert!(normalize(short).is_err());
+    }
+}
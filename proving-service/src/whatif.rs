@@ -0,0 +1,255 @@
+//! Dry runs of a candidate banned list against stored proofs.
+//!
+//! `POST /whatif` replays the key sets of stored proofs against a list of purls the
+//! security team is considering banning, and reports how many proofs that were compliant
+//! would no longer be. Nothing is proven: a key is banned when its leaf index, computed
+//! with the same `sbom_common::compute_leaf_index` the guest binds proofs with, is one a
+//! candidate purl maps to. Records are read one at a time, so a scan's memory does not
+//! grow with the size of the store. Records whose keys are sealed are matched by the leaf
+//! indexes they list, and report their keys as previews.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use sbom_common::{KeyType, ProofType, compute_leaf_index, compute_purl_hash};
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::models::{WhatIfReport, WhatIfRequest, WhatIfRoot};
+use crate::storage::ProofStore;
+use crate::utils::hex_to_bytes32;
+
+/// Most affected keys a report lists
+pub const WHATIF_SAMPLE_SIZE: usize = 100;
+
+/// The fields of a stored [`crate::models::ProofArtifact`] a replay reads
+#[derive(Deserialize)]
+struct StoredProof {
+    timestamp: u64,
+    root_hash: String,
+    #[serde(default)]
+    root_alias: Option<String>,
+    compliant: bool,
+    /// Absent from records written before other key types were supported
+    #[serde(default)]
+    key_type: KeyType,
+    /// Absent from records written before membership proofs were supported
+    #[serde(default)]
+    proof_type: ProofType,
+    purls: Vec<String>,
+    /// Present only in records whose keys are sealed, where `purls` are previews
+    #[serde(default)]
+    leaf_indexes: Option<Vec<String>>,
+}
+
+/// Replay the proofs in `store` stamped within the request's window against its
+/// candidate list
+pub fn evaluate(store: &dyn ProofStore, request: &WhatIfRequest) -> Result<WhatIfReport> {
+    if request.purls.is_empty() {
+        let err_msg = "The candidate list must name at least one purl";
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg.to_string()));
+    }
+    let since = request.since.unwrap_or(0);
+    let until = request.until.unwrap_or(u64::MAX);
+    if since > until {
+        let err_msg = format!("Empty time window: since {since} is after until {until}");
+        tracing::error!("{}", err_msg);
+        return Err(Error::InvalidRequest(err_msg));
+    }
+
+    let banned: HashSet<[u8; 32]> = request
+        .purls
+        .iter()
+        .map(|purl| compute_purl_hash(purl))
+        .collect();
+    let mut report = WhatIfReport {
+        candidate_purls: banned.len(),
+        scanned: 0,
+        evaluated: 0,
+        flipped: 0,
+        roots: Vec::new(),
+        affected_purls: Vec::new(),
+        affected_purls_truncated: false,
+    };
+    let mut roots: HashMap<String, WhatIfRoot> = HashMap::new();
+    let mut affected = BTreeSet::new();
+
+    store.for_each_record(&mut |record| {
+        let record: StoredProof = match serde_json::from_value(record) {
+            Ok(record) => record,
+            Err(e) => {
+                tracing::warn!("Skipping unreadable proof record: {}", e);
+                return;
+            }
+        };
+        if record.timestamp < since || record.timestamp > until {
+            return;
+        }
+        report.scanned += 1;
+        // A banned list can only make a compliant non-membership proof fail
+        if !record.compliant || record.proof_type != ProofType::NonMembership {
+            return;
+        }
+        report.evaluated += 1;
+
+        let root = roots
+            .entry(record.root_hash.clone())
+            .or_insert_with(|| WhatIfRoot {
+                root_hash: record.root_hash.clone(),
+                root_alias: None,
+                evaluated: 0,
+                flipped: 0,
+            });
+        root.evaluated += 1;
+        if root.root_alias.is_none() {
+            root.root_alias = record.root_alias.clone();
+        }
+
+        let mut flipped = false;
+        for (position, key) in record.purls.iter().enumerate() {
+            // A sealed record lists the leaf indexes its keys hash to instead of the keys
+            let leaf_index = match &record.leaf_indexes {
+                Some(leaf_indexes) => leaf_indexes
+                    .get(position)
+                    .and_then(|leaf_index| hex_to_bytes32(leaf_index).ok()),
+                None => compute_leaf_index(record.key_type, key),
+            };
+            let banned_key = leaf_index.is_some_and(|leaf_index| banned.contains(&leaf_index));
+            if !banned_key {
+                continue;
+            }
+            flipped = true;
+            if affected.len() < WHATIF_SAMPLE_SIZE || affected.contains(key) {
+                affected.insert(key.clone());
+            } else {
+                report.affected_purls_truncated = true;
+            }
+        }
+        if flipped {
+            report.flipped += 1;
+            root.flipped += 1;
+        }
+    })?;
+
+    report.roots = roots.into_values().collect();
+    report.roots.sort_by(|a, b| {
+        b.flipped
+            .cmp(&a.flipped)
+            .then_with(|| a.root_hash.cmp(&b.root_hash))
+    });
+    report.affected_purls = affected.into_iter().collect();
+    tracing::info!(
+        "What-if replay of {} candidate purls: {} of {} evaluated proofs would flip",
+        report.candidate_purls,
+        report.flipped,
+        report.evaluated
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FsProofStore;
+
+    /// A store in a fresh directory named after `test`, holding `records`
+    fn seeded(test: &str, records: &[serde_json::Value]) -> FsProofStore {
+        let dir = std::env::temp_dir().join(format!("proving-service-whatif-{test}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = FsProofStore::new(dir);
+        for (n, record) in records.iter().enumerate() {
+            let path = store.record_path(&format!("{n:064x}"));
+            std::fs::write(path, record.to_string()).unwrap();
+        }
+        store
+    }
+
+    fn proof(timestamp: u64, root: &str, compliant: bool, purls: &[&str]) -> serde_json::Value {
+        serde_json::json!({
+            "timestamp": timestamp,
+            "root_hash": root,
+            "compliant": compliant,
+            "purls": purls,
+        })
+    }
+
+    fn request(purls: &[&str], until: Option<u64>) -> WhatIfRequest {
+        WhatIfRequest {
+            purls: purls.iter().map(|purl| purl.to_string()).collect(),
+            since: None,
+            until,
+        }
+    }
+
+    #[test]
+    fn replays_count_the_compliant_proofs_that_would_flip() {
+        let mut nightly = proof(300, "bb", true, &["pkg:npm/b@1", "pkg:npm/a@1"]);
+        nightly["root_alias"] = "nightly".into();
+        let mut membership = proof(300, "bb", true, &["pkg:npm/a@1"]);
+        membership["proof_type"] = "membership".into();
+        // Its key is only known by the leaf index it hashes to
+        let mut sealed = proof(400, "aa", true, &["pkg:npm/d…"]);
+        let leaf_index = compute_leaf_index(KeyType::Purl, "pkg:npm/d@1").unwrap();
+        sealed["leaf_indexes"] = serde_json::json!([hex::encode(leaf_index)]);
+        let store = seeded(
+            "flips",
+            &[
+                proof(100, "aa", true, &["pkg:npm/a@1", "pkg:npm/b@1"]),
+                proof(200, "aa", true, &["pkg:npm/c@1"]),
+                nightly,
+                proof(300, "bb", false, &["pkg:npm/a@1"]),
+                membership,
+                sealed,
+                proof(5000, "aa", true, &["pkg:npm/a@1"]),
+            ],
+        );
+
+        let candidates = ["pkg:npm/a@1", "pkg:npm/d@1", "pkg:npm/unused@1"];
+        let report = evaluate(&store, &request(&candidates, Some(1000))).unwrap();
+        assert_eq!(report.candidate_purls, 3);
+        assert_eq!(
+            (report.scanned, report.evaluated, report.flipped),
+            (6, 4, 3)
+        );
+        let roots: Vec<_> = report
+            .roots
+            .iter()
+            .map(|r| (r.root_hash.as_str(), r.evaluated, r.flipped))
+            .collect();
+        assert_eq!(roots, [("aa", 3, 2), ("bb", 1, 1)]);
+        assert_eq!(report.roots[1].root_alias.as_deref(), Some("nightly"));
+        assert_eq!(report.affected_purls, ["pkg:npm/a@1", "pkg:npm/d…"]);
+        assert!(!report.affected_purls_truncated);
+
+        // Without the window the late proof flips too
+        let report = evaluate(&store, &request(&candidates, None)).unwrap();
+        assert_eq!((report.scanned, report.flipped), (7, 4));
+    }
+
+    #[test]
+    fn samples_of_affected_purls_are_capped() {
+        let purls: Vec<String> = (0..150).map(|n| format!("pkg:npm/p{n:03}@1")).collect();
+        let keys: Vec<&str> = purls.iter().map(String::as_str).collect();
+        let store = seeded("sample", &[proof(1, "aa", true, &keys)]);
+
+        let report = evaluate(&store, &request(&keys, None)).unwrap();
+        assert_eq!(report.flipped, 1);
+        assert_eq!(report.affected_purls.len(), WHATIF_SAMPLE_SIZE);
+        assert_eq!(report.affected_purls[..], keys[..WHATIF_SAMPLE_SIZE]);
+        assert!(report.affected_purls_truncated);
+    }
+
+    #[test]
+    fn empty_lists_and_windows_are_refused() {
+        let store = seeded("refused", &[]);
+        let empty = evaluate(&store, &request(&[], None));
+        assert!(matches!(empty, Err(Error::InvalidRequest(_))));
+        let backwards = WhatIfRequest {
+            since: Some(10),
+            ..request(&["pkg:npm/a@1"], Some(9))
+        };
+        let backwards = evaluate(&store, &backwards);
+        assert!(matches!(backwards, Err(Error::InvalidRequest(_))));
+    }
+}
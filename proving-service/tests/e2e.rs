@@ -0,0 +1,263 @@
+//! End to end: the proving-service and verifier-service apps, built by their
+//! `configure_app` factories in one process. A proof made by the prover in
+//! `RISC0_DEV_MODE` is proven as a job, fetched from the proof store and verified,
+//! checking every field the two services must agree on along the way.
+
+use actix_web::{App, test};
+use proving_service::config::Config;
+use proving_service::handlers::{AppState, configure_app};
+use proving_service::{ProverConfig, ProverService};
+use sbom_common::smt::SparseMerkleTree;
+use serde_json::{Value, json};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use verifier_service::handlers as verifier;
+
+/// How long the dev-mode prover gets for the job; it still executes the guest
+const PROVE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Fields of a stored proof that the verifier reads back from the journal
+const JOURNAL_FIELDS: [&str; 8] = [
+    "root_hash",
+    "banned_list_hash",
+    "compliant",
+    "proof_count",
+    "reasons_hash",
+    "defaults_digest",
+    "key_type",
+    "proof_type",
+];
+
+/// Configuration of a prover in `RISC0_DEV_MODE`, storing under a fresh directory named
+/// after `test`
+fn dev_mode_config(test: &str) -> (Config, PathBuf) {
+    // The zkVM reads dev mode from the process environment, not from `Config`
+    unsafe { std::env::set_var("RISC0_DEV_MODE", "1") };
+    let proofs_dir =
+        std::env::temp_dir().join(format!("proving-service-{test}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&proofs_dir);
+    let config = Config::from_vars(|name| match name {
+        "PROOFS_DIR" => Some(proofs_dir.display().to_string()),
+        "RISC0_DEV_MODE" => Some("1".to_string()),
+        _ => None,
+    });
+    (config, proofs_dir)
+}
+
+/// Non-membership proofs of `purls` in `tree`, as a client sends them
+fn proofs_of(tree: &SparseMerkleTree, purls: &[&str]) -> Vec<Value> {
+    purls
+        .iter()
+        .map(|purl| {
+            let proof = tree.prove(purl);
+            json!({
+                "purl": purl,
+                "value": proof.value.to_string(),
+                "leaf_index": hex::encode(proof.leaf_index),
+                "siblings": proof.siblings.iter().map(hex::encode).collect::<Vec<_>>(),
+                "bitmap": hex::encode(proof.bitmap),
+            })
+        })
+        .collect()
+}
+
+#[actix_rt::test]
+async fn a_proof_travels_from_prover_to_verifier() {
+    let (config, proofs_dir) = dev_mode_config("e2e");
+    let service = ProverService::new(ProverConfig::from(&config));
+    let prover = test::init_service(
+        App::new().configure(|cfg| configure_app(cfg, AppState::new(config, service))),
+    )
+    .await;
+    let verifier_config = verifier_service::config::Config {
+        allow_dev_receipts: true,
+        ..Default::default()
+    };
+    let verifier =
+        test::init_service(App::new().configure(|cfg| {
+            verifier::configure_app(cfg, verifier::AppState::new(verifier_config))
+        }))
+        .await;
+
+    // A banned list of one purl, and non-membership proofs of two others
+    let mut tree = SparseMerkleTree::default();
+    tree.insert("pkg:npm/evil@1.0.0", 1);
+    let root = hex::encode(tree.root());
+    let purls = ["pkg:npm/a@1.0.0", "pkg:npm/b@2.0.0"];
+    let merkle_proofs = proofs_of(&tree, &purls);
+
+    // Prove as a job and poll it
+    let req = test::TestRequest::post()
+        .uri("/prove-jobs")
+        .set_json(json!({"depth": 256, "root": root, "merkle_proofs": merkle_proofs}));
+    let resp = test::call_service(&prover, req.to_request()).await;
+    assert_eq!(resp.status(), 202);
+    let job: Value = test::read_body_json(resp).await;
+    let job_uri = format!("/prove-jobs/{}", job["job_id"].as_str().unwrap());
+    let started = Instant::now();
+    let job = loop {
+        let req = test::TestRequest::get().uri(&job_uri);
+        let job: Value = test::call_and_read_body_json(&prover, req.to_request()).await;
+        match job["status"].as_str().unwrap() {
+            "completed" => break job,
+            "failed" => panic!("prove job failed: {}", job["error"]),
+            _ => {
+                assert!(
+                    started.elapsed() < PROVE_TIMEOUT,
+                    "prove job still {}",
+                    job["status"]
+                );
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    };
+    let proved = &job["proof"]["data"];
+    assert_eq!(proved["root_hash"], root);
+    assert_eq!(proved["compliant"], true);
+    assert_eq!(proved["proof_count"], 2);
+    assert_eq!(proved["purls"], json!(purls));
+    assert_eq!(proved["dev_mode"], true);
+    assert_eq!(proved["receipt_kind"], "fake");
+
+    // The proof is persisted and served by id
+    let proof_id = proved["proof_id"].as_str().unwrap();
+    let req = test::TestRequest::get().uri(&format!("/proofs/{proof_id}"));
+    let record: Value = test::call_and_read_body_json(&prover, req.to_request()).await;
+    let stored = &record["data"];
+    for field in JOURNAL_FIELDS
+        .iter()
+        .chain(&["proof_id", "image_id", "proof"])
+    {
+        assert_eq!(stored[field], proved[field], "{field}");
+    }
+
+    // The verifier reads the same statement from the receipt's journal
+    let claims = json!({
+        "root_hash": stored["root_hash"],
+        "banned_list_hash": stored["banned_list_hash"],
+        "compliant": stored["compliant"],
+        "image_id": stored["image_id"],
+        "proof": stored["proof"],
+        "expected_min_proof_count": 2,
+        "expected_reasons_hash": stored["reasons_hash"],
+        "key_type": stored["key_type"],
+        "proof_type": stored["proof_type"],
+    });
+    let req = test::TestRequest::post().uri("/verify").set_json(&claims);
+    let resp = test::call_service(&verifier, req.to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let verified: Value = test::read_body_json(resp).await;
+    assert_eq!(verified["proof_verified"], true);
+    assert_eq!(verified["receipt_kind"], "fake");
+    for field in JOURNAL_FIELDS {
+        assert_eq!(verified[field], stored[field], "{field}");
+    }
+
+    // The stored proof file verifies as it is, and its metadata matches its journal
+    let req = test::TestRequest::post()
+        .uri("/verify-file")
+        .set_json(&record);
+    let resp = test::call_service(&verifier, req.to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let verified: Value = test::read_body_json(resp).await;
+    assert_eq!(verified["proof_verified"], true);
+    assert_eq!(verified["tampered"], false);
+    assert_eq!(verified["file_consistency"], json!([]));
+
+    // A verifier that does not allow dev receipts refuses it
+    let strict = test::init_service(App::new().configure(|cfg| {
+        verifier::configure_app(cfg, verifier::AppState::new(Default::default()))
+    }))
+    .await;
+    let req = test::TestRequest::post().uri("/verify").set_json(&claims);
+    let resp = test::call_service(&strict, req.to_request()).await;
+    assert_eq!(resp.status(), 400);
+    let refused: Value = test::read_body_json(resp).await;
+    assert_eq!(refused["code"], "dev_receipt");
+
+    let _ = std::fs::remove_dir_all(&proofs_dir);
+}
+
+#[actix_rt::test]
+async fn reproducible_runs_commit_identical_journals() {
+    let (config, proofs_dir) = dev_mode_config("e2e-reproducible");
+    let service = ProverService::new(ProverConfig::from(&config));
+    let prover = test::init_service(
+        App::new().configure(|cfg| configure_app(cfg, AppState::new(config, service))),
+    )
+    .await;
+
+    let mut tree = SparseMerkleTree::default();
+    tree.insert("pkg:npm/evil@1.0.0", 1);
+    let request = json!({
+        "depth": 256,
+        "root": hex::encode(tree.root()),
+        "merkle_proofs": proofs_of(&tree, &["pkg:npm/a@1.0.0", "pkg:npm/b@2.0.0"]),
+        "reproducible": true,
+        "timestamp": 1_717_200_000u64,
+        // Prove twice rather than answer the second run from the proof cache
+        "force": true,
+    });
+    let mut runs = Vec::new();
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/prove-merkle-compact")
+            .set_json(&request);
+        let resp = test::call_service(&prover, req.to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let proved: Value = test::read_body_json(resp).await;
+        assert_eq!(proved["data"]["cached"], false, "{proved}");
+        runs.push(proved["data"].clone());
+    }
+
+    let journal = runs[0]["journal"].as_str().unwrap();
+    assert!(!journal.is_empty());
+    assert_eq!(runs[1]["journal"], journal);
+    for field in JOURNAL_FIELDS.iter().chain(&["timestamp", "image_id"]) {
+        assert_eq!(runs[0][field], runs[1][field], "{field}");
+    }
+
+    let _ = std::fs::remove_dir_all(&proofs_dir);
+}
+
+#[actix_rt::test]
+async fn fast_and_batch_paths_commit_identical_journals() {
+    let mut tree = SparseMerkleTree::default();
+    tree.insert("pkg:npm/evil@1.0.0", 1);
+    let request = json!({
+        "depth": 256,
+        "root": hex::encode(tree.root()),
+        "merkle_proofs": proofs_of(&tree, &["pkg:npm/a@1.0.0"]),
+    });
+
+    // The same single-proof request, once under the default threshold and once with the
+    // fast path turned off
+    let mut runs = Vec::new();
+    for (test, fast_path_max_proofs) in [("e2e-fast-path", 1), ("e2e-batch-path", 0)] {
+        let (mut config, proofs_dir) = dev_mode_config(test);
+        config.fast_path_max_proofs = fast_path_max_proofs;
+        let service = ProverService::new(ProverConfig::from(&config));
+        let prover = test::init_service(
+            App::new().configure(|cfg| configure_app(cfg, AppState::new(config, service))),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/prove-merkle-compact")
+            .set_json(&request);
+        let resp = test::call_service(&prover, req.to_request()).await;
+        assert_eq!(resp.status(), 200, "{test}");
+        let proved: Value = test::read_body_json(resp).await;
+        runs.push(proved["data"].clone());
+        let _ = std::fs::remove_dir_all(&proofs_dir);
+    }
+
+    let (fast, batch) = (&runs[0], &runs[1]);
+    assert_eq!(fast["fast_path"], true);
+    assert_eq!(batch["fast_path"], false);
+    let journal = fast["journal"].as_str().unwrap();
+    assert!(!journal.is_empty());
+    assert_eq!(batch["journal"], journal);
+    for field in JOURNAL_FIELDS.iter().chain(&["proof_id", "image_id"]) {
+        assert_eq!(fast[field], batch[field], "{field}");
+    }
+}
@@ -0,0 +1,678 @@
+//! The guest itself, run by the executor in `RISC0_DEV_MODE`, against the host's
+//! `/validate`: both must come to the same verdict on the same proofs, since they run the
+//! same `sbom_common::proof` checks.
+//!
+//! Besides fixed cases, a differential run feeds both generated proofs, well-formed and
+//! subtly malformed, and shrinks any disagreement to a minimal request. `PARITY_CASES`
+//! sets the case count and `PARITY_SEED` replays a reported failure; the nightly profile
+//! (`cargo test --test guest -- --ignored`) runs many more cases from a fresh seed.
+//!
+//! The guest's own guards against oversized input are exercised directly: they must
+//! reject such input with an `input_status`, not run out of memory on it. So is its
+//! reading of the sectioned input: missing, repeated or reordered sections.
+
+use proving_service::models::{
+    CompactMerkleProof, MerklePublicInputs, MerklePublicOutputs, ProveCompactMerkleRequest,
+    ReceiptKind,
+};
+use proving_service::prover::{execute_guest, guest_input};
+use proving_service::{ProverConfig, ProverService};
+use risc0_zkvm::serde::to_vec;
+use sbom_common::input::{InputHeader, MAX_SIBLING_BYTES, PROTOCOL_SECTIONS, Section, status, tag};
+use sbom_common::json_limits::PROOFS_JSON_LIMITS;
+use sbom_common::smt::SparseMerkleTree;
+use sbom_common::{DEFAULTS, KeyType, ProofType, bitmap_bit, failure, hex_to_bytes32};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Cases a plain `cargo test` runs
+const QUICK_CASES: usize = 200;
+/// Cases of the nightly profile
+const NIGHTLY_CASES: usize = 20_000;
+const TREE_SIZE: usize = 64;
+
+/// xorshift64*, so a failure is reproducible from its seed alone
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn env_or<T: std::str::FromStr>(var: &str, default: T) -> T {
+    std::env::var(var)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn purl(i: usize) -> String {
+    format!("pkg:npm/guest-{i}@1.0.0")
+}
+
+/// A tree of the first `size` purls, with values cycling through `0..values`
+fn tree(size: usize, values: u64) -> SparseMerkleTree {
+    let mut tree = SparseMerkleTree::default();
+    for i in 0..size {
+        tree.insert(&purl(i), i as u64 % values);
+    }
+    tree
+}
+
+/// A valid proof of `key` in `tree`, as a client sends it
+fn proof_of(tree: &SparseMerkleTree, key: String) -> CompactMerkleProof {
+    let proof = tree.prove(&key);
+    CompactMerkleProof {
+        key,
+        key_type: KeyType::Purl,
+        value: proof.value.to_string(),
+        leaf_index: hex::encode(proof.leaf_index),
+        siblings: proof.siblings.iter().map(hex::encode).collect(),
+        bitmap: hex::encode(proof.bitmap),
+    }
+}
+
+/// Execute the guest on `proofs`, framed as the prover frames them
+fn run_guest(proofs: &[CompactMerkleProof], root: &[u8; 32]) -> MerklePublicOutputs {
+    run_guest_json(&serde_json::to_string(proofs).unwrap(), root)
+}
+
+/// [`run_guest`] on proofs JSON as given, which need not be a list of proofs at all
+fn run_guest_json(proofs_json: &str, root: &[u8; 32]) -> MerklePublicOutputs {
+    execute(&guest_input(proofs_json, &public_inputs(root), PROTOCOL_SECTIONS).unwrap())
+}
+
+/// Execute the guest on input words as given
+fn execute(input: &[u32]) -> MerklePublicOutputs {
+    // The zkVM reads dev mode from the process environment
+    unsafe { std::env::set_var("RISC0_DEV_MODE", "1") };
+    execute_guest(input).unwrap()
+}
+
+/// Public inputs of a plain non-membership proof against `root`
+fn public_inputs(root: &[u8; 32]) -> MerklePublicInputs {
+    MerklePublicInputs {
+        root_hash: *root,
+        expected_banned_list_hash: None,
+        list_info: None,
+        blinding_salt: None,
+        proof_type: ProofType::NonMembership,
+        receipt_kind: ReceiptKind::Composite,
+        requester_commitment: None,
+        fast_path: false,
+        prover_opts: Default::default(),
+    }
+}
+
+/// How `/validate` disagrees with the guest on `proofs`, if it does. It must refuse the
+/// request the guest rejects as input, and otherwise agree on compliance and on the first
+/// failing proof.
+fn disagreement(
+    service: &ProverService,
+    proofs: &[CompactMerkleProof],
+    root: &[u8; 32],
+) -> Option<String> {
+    let guest = run_guest(proofs, root);
+    let req: ProveCompactMerkleRequest = serde_json::from_value(serde_json::json!({
+        "depth": 256,
+        "root": hex::encode(root),
+        "merkle_proofs": proofs,
+    }))
+    .unwrap();
+
+    match (guest.input_status, service.validate(req)) {
+        (status::OK, Ok(response)) => {
+            let first_invalid = response.results.iter().position(|r| !r.valid);
+            let guest_failed = (failure::names_proof(guest.first_failure)
+                && guest.first_failure != failure::VALUE_REJECTED)
+                .then_some(guest.first_failure_index as usize);
+            if response.compliant != guest.compliant {
+                Some(format!(
+                    "/validate says compliant={}, the guest {} ({})",
+                    response.compliant,
+                    guest.compliant,
+                    failure::name(guest.first_failure)
+                ))
+            } else if first_invalid != guest_failed {
+                Some(format!(
+                    "/validate fails proof {first_invalid:?}, the guest {guest_failed:?} ({})",
+                    failure::name(guest.first_failure)
+                ))
+            } else if response.valid
+                && response.reasons_hash != Some(hex::encode(guest.reasons_hash))
+            {
+                Some("/validate and the guest compute different reasons hashes".to_string())
+            } else if response.banned_list_hash != hex::encode(guest.banned_list_hash) {
+                Some("/validate and the guest compute different banned list hashes".to_string())
+            } else {
+                None
+            }
+        }
+        // The guest rejects a non-canonical proof only once it folds it; `/validate` names it
+        (status::NON_CANONICAL_PROOF, Ok(response)) => {
+            let invalid = response.results.iter().find(|r| !r.valid);
+            let error = invalid.and_then(|r| r.error.as_deref()).unwrap_or_default();
+            (!error.contains("DEFAULTS")).then(|| {
+                format!("the guest rejects a non-canonical proof, /validate says: {error}")
+            })
+        }
+        (status::OK, Err(e)) => Some(format!("/validate refuses what the guest accepts: {e}")),
+        (input_status, Ok(_)) => Some(format!(
+            "/validate accepts what the guest rejects with {}",
+            status::name(input_status)
+        )),
+        (_, Err(_)) => None,
+    }
+}
+
+fn assert_agrees(
+    service: &ProverService,
+    proofs: &[CompactMerkleProof],
+    root: &[u8; 32],
+    case: &str,
+) {
+    if let Some(reason) = disagreement(service, proofs, root) {
+        panic!(
+            "{case}: {reason}: {}",
+            serde_json::to_string(proofs).unwrap()
+        );
+    }
+}
+
+#[test]
+fn validate_endpoint_agrees_with_the_guest() {
+    let tree = tree(8, 2);
+    let root = tree.root();
+    let service = ProverService::without_prover(ProverConfig::default());
+    let absent = proof_of(&tree, purl(100));
+    let present = proof_of(&tree, purl(1));
+    assert_eq!(present.value, "1");
+
+    let mut cases: Vec<(&str, Vec<CompactMerkleProof>)> = vec![
+        ("absent", vec![absent.clone()]),
+        ("present", vec![absent.clone(), present.clone()]),
+    ];
+    let mut case = |name, edit: &dyn Fn(&mut CompactMerkleProof)| {
+        let mut proof = absent.clone();
+        edit(&mut proof);
+        cases.push((name, vec![present.clone(), proof]));
+    };
+    case("bad value", &|p| p.value = "+1".to_string());
+    case("0x prefixes", &|p| {
+        p.bitmap.insert_str(0, "0x");
+        p.leaf_index.insert_str(0, "0x");
+    });
+    case("upper-case hex", &|p| p.bitmap = p.bitmap.to_uppercase());
+    case("other key", &|p| p.key = purl(101));
+    case("bad sibling hex", &|p| {
+        p.siblings[0].replace_range(0..2, "zz")
+    });
+    case("changed sibling", &|p| {
+        p.siblings[0].replace_range(0..1, "f")
+    });
+    case("sibling dropped", &|p| {
+        p.siblings.pop();
+    });
+    case("oversized bitmap", &|p| p.bitmap.push_str("0000"));
+    case("oversized sibling", &|p| p.siblings[0].push_str("0000"));
+    case("missing bitmap", &|p| p.bitmap.clear());
+    case("default sibling spelled out", &|p| {
+        let mut bitmap = hex_to_bytes32(&p.bitmap).unwrap();
+        let d = (0..256).find(|&d| bitmap_bit(&bitmap, d) == 0).unwrap();
+        bitmap[d / 8] |= 1 << (d % 8);
+        let at = (0..d).filter(|&i| bitmap_bit(&bitmap, i) == 1).count();
+        p.siblings.insert(at, hex::encode(DEFAULTS[d]));
+        p.bitmap = hex::encode(bitmap);
+    });
+
+    for (name, proofs) in &cases {
+        assert_agrees(&service, proofs, &root, name);
+    }
+}
+
+/// One hex field of `proof`, picked by `rng`. `None` when a sibling is picked from a
+/// proof without any, or the field is too mangled already to edit by byte offset.
+fn hex_field<'a>(proof: &'a mut CompactMerkleProof, rng: &mut Rng) -> Option<&'a mut String> {
+    let field = match rng.below(3) {
+        0 => &mut proof.bitmap,
+        1 => &mut proof.leaf_index,
+        _ if proof.siblings.is_empty() => return None,
+        _ => {
+            let index = rng.below(proof.siblings.len());
+            &mut proof.siblings[index]
+        }
+    };
+    (field.is_ascii() && field.len() >= 2).then_some(field)
+}
+
+/// Apply one subtle corruption to `proof` and name it
+fn mutate(proof: &mut CompactMerkleProof, rng: &mut Rng) -> &'static str {
+    match rng.below(14) {
+        0 => {
+            let Ok(mut bitmap) = hex_to_bytes32(&proof.bitmap) else {
+                return "unchanged";
+            };
+            let d = rng.below(256);
+            bitmap[d / 8] ^= 1 << (d % 8);
+            proof.bitmap = hex::encode(bitmap);
+            "bitmap bit flipped"
+        }
+        1 => {
+            // Set a bit the proof leaves default and hand over that default, so the
+            // sibling count still matches
+            let Ok(mut bitmap) = hex_to_bytes32(&proof.bitmap) else {
+                return "unchanged";
+            };
+            let d = rng.below(256);
+            if bitmap_bit(&bitmap, d) == 1 {
+                return "unchanged";
+            }
+            bitmap[d / 8] |= 1 << (d % 8);
+            let at = (0..d).filter(|&i| bitmap_bit(&bitmap, i) == 1).count();
+            proof.siblings.insert(at, hex::encode(DEFAULTS[d]));
+            proof.bitmap = hex::encode(bitmap);
+            "default sibling spelled out"
+        }
+        2 if !proof.siblings.is_empty() => {
+            let index = rng.below(proof.siblings.len());
+            proof.siblings.remove(index);
+            "sibling dropped"
+        }
+        3 if !proof.siblings.is_empty() => {
+            let index = rng.below(proof.siblings.len());
+            proof.siblings.swap(0, index);
+            proof.siblings.rotate_left(1);
+            "siblings reordered"
+        }
+        4 => {
+            if let Some(field) = hex_field(proof, rng) {
+                let at = rng.below(field.len());
+                let flipped = if field.as_bytes()[at] == b'0' {
+                    "1"
+                } else {
+                    "0"
+                };
+                field.replace_range(at..at + 1, flipped);
+            }
+            "hex digit changed"
+        }
+        5 => {
+            if let Some(field) = hex_field(proof, rng) {
+                *field = field.to_uppercase();
+            }
+            "hex upper-cased"
+        }
+        6 => {
+            if let Some(field) = hex_field(proof, rng) {
+                field.insert_str(0, "0x");
+            }
+            "0x prefix"
+        }
+        7 => {
+            if let Some(field) = hex_field(proof, rng) {
+                field.insert_str(0, ["0X", "x", "0x0x", " "][rng.below(4)]);
+            }
+            "bad prefix"
+        }
+        8 => {
+            if let Some(field) = hex_field(proof, rng) {
+                field.push_str(["0", "00", "g", " "][rng.below(4)]);
+            }
+            "hex lengthened"
+        }
+        9 => {
+            if let Some(field) = hex_field(proof, rng) {
+                field.pop();
+            }
+            "hex shortened"
+        }
+        10 => {
+            if let Some(field) = hex_field(proof, rng) {
+                let at = rng.below(field.len() / 2) * 2;
+                field.replace_range(at..at + 2, ["é", "zz", "+1", "\u{0}"][rng.below(4)]);
+            }
+            "non-hex character"
+        }
+        11 => {
+            proof.value = [
+                "0",
+                "1",
+                "7",
+                "01",
+                "+1",
+                "-1",
+                " 0",
+                "0 ",
+                "",
+                "1e3",
+                "0x1",
+                "18446744073709551615",
+                "18446744073709551616",
+            ][rng.below(13)]
+            .to_string();
+            "value replaced"
+        }
+        12 => {
+            proof.key = purl(rng.below(TREE_SIZE * 2));
+            "key replaced"
+        }
+        _ => {
+            // A raw_hash key is the leaf index itself, in any spelling hex allows
+            proof.key_type = KeyType::RawHash;
+            proof.key = match rng.below(3) {
+                0 => proof.leaf_index.clone(),
+                1 => proof.leaf_index.to_uppercase(),
+                _ => format!("0x{}", proof.leaf_index),
+            };
+            "raw_hash key"
+        }
+    }
+}
+
+/// Shrink `proofs`, a request `fails`, to a smaller one that still fails: drop whole proofs,
+/// then undo one field at a time of each mutated proof back to its `original`, until no
+/// step keeps the failure. `originals` are the proofs as generated, before any mutation.
+fn shrink(
+    mut originals: Vec<CompactMerkleProof>,
+    mut proofs: Vec<CompactMerkleProof>,
+    fails: impl Fn(&[CompactMerkleProof]) -> bool,
+) -> Vec<CompactMerkleProof> {
+    type Undo = fn(&mut CompactMerkleProof, &CompactMerkleProof);
+    const UNDO: [Undo; 6] = [
+        |p, o| p.key = o.key.clone(),
+        |p, o| p.key_type = o.key_type,
+        |p, o| p.value = o.value.clone(),
+        |p, o| p.leaf_index = o.leaf_index.clone(),
+        |p, o| p.bitmap = o.bitmap.clone(),
+        |p, o| p.siblings = o.siblings.clone(),
+    ];
+
+    loop {
+        let mut shrunk = false;
+        for index in (0..proofs.len()).rev() {
+            if proofs.len() > 1 {
+                let mut fewer = proofs.clone();
+                fewer.remove(index);
+                if fails(&fewer) {
+                    proofs = fewer;
+                    originals.remove(index);
+                    shrunk = true;
+                    continue;
+                }
+            }
+            for undo in UNDO {
+                let mut simpler = proofs.clone();
+                undo(&mut simpler[index], &originals[index]);
+                let unchanged = serde_json::to_value(&simpler[index]).ok()
+                    == serde_json::to_value(&proofs[index]).ok();
+                if !unchanged && fails(&simpler) {
+                    proofs = simpler;
+                    shrunk = true;
+                }
+            }
+        }
+        if !shrunk {
+            return proofs;
+        }
+    }
+}
+
+/// Run `cases` generated requests of one to three proofs through the guest and `/validate`
+/// and panic with the shrunk request of the first they disagree on
+fn differential_run(cases: usize, seed: u64) {
+    let mut rng = Rng(seed);
+    // Half the generated keys are in the tree, with a value of 0 to 3
+    let tree = tree(TREE_SIZE, 4);
+    let root = tree.root();
+    let service = ProverService::without_prover(ProverConfig::default());
+
+    let (mut compliant, mut failed, mut rejected) = (0, 0, 0);
+    for case in 0..cases {
+        let originals: Vec<_> = (0..1 + rng.below(3))
+            .map(|_| proof_of(&tree, purl(rng.below(TREE_SIZE * 2))))
+            .collect();
+        let mut proofs = originals.clone();
+        let mut mutations = Vec::new();
+        for proof in &mut proofs {
+            for _ in 0..rng.below(3) {
+                mutations.push(mutate(proof, &mut rng));
+            }
+        }
+
+        if let Some(reason) = disagreement(&service, &proofs, &root) {
+            let minimal = shrink(originals, proofs, |proofs| {
+                disagreement(&service, proofs, &root).is_some()
+            });
+            panic!(
+                "case {case} of seed {seed:#x}, mutations {mutations:?}: {reason}. Shrunk to {}: {}",
+                serde_json::to_string(&minimal).unwrap(),
+                disagreement(&service, &minimal, &root).unwrap_or_default()
+            );
+        }
+        let guest = run_guest(&proofs, &root);
+        match guest.input_status {
+            status::OK if guest.compliant => compliant += 1,
+            status::OK => failed += 1,
+            _ => rejected += 1,
+        }
+    }
+    // The generator must reach every kind of verdict to mean anything
+    assert!(
+        compliant > cases / 20 && failed > cases / 20 && rejected > 0,
+        "{compliant} compliant, {failed} failed, {rejected} rejected"
+    );
+}
+
+#[test]
+fn guest_and_validate_agree_on_generated_proofs() {
+    differential_run(
+        env_or("PARITY_CASES", QUICK_CASES),
+        env_or("PARITY_SEED", 0x5b0b_u64),
+    );
+}
+
+/// The nightly profile: many more cases, from a seed of the day unless one is given
+#[test]
+#[ignore]
+fn guest_and_validate_agree_nightly() {
+    let day = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        / 86_400;
+    differential_run(
+        env_or("PARITY_CASES", NIGHTLY_CASES),
+        env_or("PARITY_SEED", day),
+    );
+}
+
+#[test]
+fn shrinking_keeps_only_what_fails() {
+    let tree = tree(8, 2);
+    let originals: Vec<_> = (0..3).map(|i| proof_of(&tree, purl(i))).collect();
+    let mut proofs = originals.clone();
+    for proof in &mut proofs {
+        proof.bitmap = proof.bitmap.to_uppercase();
+        proof.siblings.reverse();
+    }
+    proofs[1].value = "+1".to_string();
+
+    let minimal = shrink(originals.clone(), proofs, |proofs| {
+        proofs.iter().any(|p| p.value == "+1")
+    });
+    assert_eq!(minimal.len(), 1);
+    assert_eq!(minimal[0].value, "+1");
+    assert_eq!(minimal[0].bitmap, originals[1].bitmap);
+    assert_eq!(minimal[0].siblings, originals[1].siblings);
+}
+
+/// Batch proofs the Go merkle-proof-service returned for the benchmark requests
+#[test]
+fn go_service_proofs_satisfy_the_guest() {
+    let service = ProverService::without_prover(ProverConfig::default());
+    for fixture in [
+        include_str!("../benchmark/data/merkleproofs/batch_proof_2.json"),
+        include_str!("../benchmark/data/merkleproofs/batch_proof_5.json"),
+        include_str!("../benchmark/data/merkleproofs/batch_proof_10.json"),
+    ] {
+        let req: ProveCompactMerkleRequest = serde_json::from_str(fixture).unwrap();
+        let root = hex_to_bytes32(&req.root).unwrap();
+        let guest = run_guest(&req.merkle_proofs, &root);
+        assert_eq!(guest.input_status, status::OK);
+        assert_eq!(guest.first_failure, failure::NONE, "{}", req.root);
+        assert_agrees(&service, &req.merkle_proofs, &root, &req.root);
+    }
+}
+
+/// A proof with a sibling on every level, each `sibling_len` hex digits long
+fn full_proof(i: usize, sibling_len: usize) -> CompactMerkleProof {
+    CompactMerkleProof {
+        key: purl(i),
+        key_type: KeyType::Purl,
+        value: "0".to_string(),
+        leaf_index: hex::encode(sbom_common::compute_purl_hash(&purl(i))),
+        siblings: vec!["1".repeat(sibling_len); 256],
+        bitmap: "f".repeat(64),
+    }
+}
+
+#[test]
+fn oversized_sibling_totals_are_rejected_as_input() {
+    // Full proofs of 256 * 64 hex digits each; the budget fits exactly this many
+    let fitting = MAX_SIBLING_BYTES / (256 * 64);
+    let proofs: Vec<_> = (0..=fitting).map(|i| full_proof(i, 64)).collect();
+
+    let guest = run_guest(&proofs[..fitting], &[0; 32]);
+    assert_eq!(guest.input_status, status::OK);
+    assert!(!guest.compliant);
+    let guest = run_guest(&proofs, &[0; 32]);
+    assert_eq!(guest.input_status, status::OVERSIZED_INPUT);
+    assert_eq!(guest.proof_count, 0);
+
+    // One sibling far longer than a hash is refused before it is decoded
+    let mut long = full_proof(0, 64);
+    long.siblings[0] = "1".repeat(MAX_SIBLING_BYTES / 2);
+    let guest = run_guest(&[long], &[0; 32]);
+    assert_eq!(guest.input_status, status::OVERSIZED_INPUT);
+}
+
+#[test]
+fn sibling_counts_must_match_their_bitmap() {
+    let tree = tree(TREE_SIZE, 2);
+    for extra in [false, true] {
+        let mut proof = proof_of(&tree, purl(1));
+        if extra {
+            proof.siblings.push("11".repeat(32));
+        } else {
+            proof.siblings.pop();
+        }
+        let guest = run_guest(&[proof], &tree.root());
+        assert_eq!(
+            guest.input_status,
+            status::SIBLING_COUNT_MISMATCH,
+            "extra: {extra}"
+        );
+    }
+}
+
+#[test]
+fn oversized_proof_counts_are_rejected_before_parsing() {
+    // More (empty) proofs than the JSON token budget admits
+    let count = PROOFS_JSON_LIMITS.max_tokens + 1;
+    let proofs_json = format!("[{}{{}}]", "{},".repeat(count - 1));
+    let guest = run_guest_json(&proofs_json, &[0; 32]);
+    assert_eq!(guest.input_status, status::JSON_TOO_MANY_TOKENS);
+    assert_eq!(guest.proof_count, 0);
+}
+
+/// Version 2 input words carrying `sections` in the order given
+fn framed(sections: &[Section]) -> Vec<u32> {
+    let header = InputHeader {
+        protocol_version: PROTOCOL_SECTIONS,
+        flags: 0,
+        section_count: sections.len() as u32,
+    };
+    let mut input = to_vec(&header).unwrap();
+    for section in sections {
+        input.extend(to_vec(section).unwrap());
+    }
+    input
+}
+
+fn section<T: serde::Serialize + ?Sized>(tag: u32, mandatory: bool, value: &T) -> Section {
+    Section {
+        tag,
+        mandatory,
+        payload: to_vec(value).unwrap(),
+    }
+}
+
+#[test]
+fn sections_are_read_by_tag_not_position() {
+    let tree = tree(8, 1);
+    let proofs = serde_json::to_string(&[proof_of(&tree, purl(1))]).unwrap();
+    let proofs = section(tag::PROOFS, true, proofs.as_str());
+    let inputs = section(tag::PUBLIC_INPUTS, true, &public_inputs(&tree.root()));
+    let in_order = execute(&framed(&[proofs.clone(), inputs.clone()]));
+    assert_eq!(in_order.input_status, status::OK);
+    assert!(in_order.compliant);
+
+    // Reordered
+    let reordered = execute(&framed(&[inputs.clone(), proofs.clone()]));
+    assert_eq!(reordered.input_status, status::OK);
+    assert_eq!(reordered.root_hash, in_order.root_hash);
+    assert_eq!(reordered.reasons_hash, in_order.reasons_hash);
+    assert!(reordered.compliant);
+
+    // An extra optional section the guest does not know is skipped
+    let unknown = section(99, false, &7u32);
+    let extra = execute(&framed(&[proofs.clone(), unknown.clone(), inputs.clone()]));
+    assert_eq!(extra.input_status, status::OK);
+    assert!(extra.compliant);
+}
+
+#[test]
+fn missing_repeated_and_unknown_mandatory_sections_are_rejected() {
+    let tree = tree(8, 1);
+    let proofs = serde_json::to_string(&[proof_of(&tree, purl(1))]).unwrap();
+    let proofs = section(tag::PROOFS, true, proofs.as_str());
+    let inputs = section(tag::PUBLIC_INPUTS, true, &public_inputs(&tree.root()));
+
+    let cases = [
+        ("no proofs", vec![inputs.clone()], status::MALFORMED_INPUT),
+        (
+            "no public inputs",
+            vec![proofs.clone()],
+            status::MALFORMED_INPUT,
+        ),
+        ("no sections", vec![], status::MALFORMED_INPUT),
+        (
+            "proofs twice",
+            vec![proofs.clone(), inputs.clone(), proofs.clone()],
+            status::MALFORMED_INPUT,
+        ),
+        (
+            "undecodable public inputs",
+            vec![proofs.clone(), section(tag::PUBLIC_INPUTS, true, &1u32)],
+            status::MALFORMED_INPUT,
+        ),
+        (
+            "unknown mandatory section",
+            vec![proofs.clone(), inputs.clone(), section(99, true, &7u32)],
+            status::UNKNOWN_MANDATORY_SECTION,
+        ),
+    ];
+    for (case, sections, expected) in cases {
+        let guest = execute(&framed(&sections));
+        assert_eq!(guest.input_status, expected, "{case}");
+        assert!(!guest.compliant, "{case}");
+        assert_eq!(guest.proof_count, 0, "{case}");
+    }
+}
@@ -4,6 +4,9 @@
 # 2. Sends proof to verifier-service
 # 3. Displays verification results
 # Usage: ./verify-proof.sh [proof-file.json] (defaults to latest proof)
+# Proofs too large to inline carry a proof_url instead of proof; download it from the
+# proving-service and pass the file as RECEIPT_FILE=/path/to/receipt to verify it raw.
+# Both enveloped ("format": "sbom-proof") and older bare proof files are accepted.
 
 set -euo pipefail
 
@@ -27,10 +30,20 @@ echo "Proof: $PROOF_FILE"
 echo ""
 
 echo "Step 1: Validate proof structure..."
-REQUIRED_FIELDS=("proof" "image_id" "root_hash" "banned_list_hash" "compliant")
+PROOF_DATA=$(jq 'if .format == "sbom-proof" then .data else . end' "$PROOF_FILE") \
+    || { echo "ERROR: Proof file is not valid JSON"; exit 1; }
+REQUIRED_FIELDS=("image_id" "root_hash" "banned_list_hash" "compliant")
 for field in "${REQUIRED_FIELDS[@]}"; do
-    jq -e "has(\"$field\")" "$PROOF_FILE" > /dev/null 2>&1 || { echo "ERROR: Missing field: $field"; exit 1; }
+    jq -e "has(\"$field\")" <<< "$PROOF_DATA" > /dev/null 2>&1 || { echo "ERROR: Missing field: $field"; exit 1; }
 done
+RAW_MODE=false
+if ! jq -e '.proof | type == "string"' <<< "$PROOF_DATA" > /dev/null 2>&1; then
+    PROOF_URL=$(jq -r '.proof_url // empty' <<< "$PROOF_DATA")
+    [ -z "$PROOF_URL" ] && { echo "ERROR: Missing field: proof"; exit 1; }
+    [ -z "${RECEIPT_FILE:-}" ] || [ ! -f "$RECEIPT_FILE" ] && {
+        echo "ERROR: Proof is stored separately at $PROOF_URL; download it and set RECEIPT_FILE"; exit 1; }
+    RAW_MODE=true
+fi
 echo "Valid"
 echo ""
 
@@ -48,11 +61,21 @@ for i in {1..30}; do
     sleep 2
 done
 
-RESPONSE=$(curl -X POST "http://localhost:8082/verify" \
-    -H "Content-Type: application/json" \
-    --data-binary @"$PROOF_FILE" \
-    --max-time 300 \
-    --silent)
+if [ "$RAW_MODE" = true ]; then
+    QUERY=$(jq -r '{root_hash, banned_list_hash, compliant, proof_size, image_id: (.image_id | join(","))}
+        | with_entries(select(.value != null)) | to_entries | map("\(.key)=\(.value | tostring | @uri)") | join("&")' <<< "$PROOF_DATA")
+    RESPONSE=$(curl -X POST "http://localhost:8082/verify/raw?$QUERY" \
+        -H "Content-Type: application/octet-stream" \
+        --data-binary @"$RECEIPT_FILE" \
+        --max-time 300 \
+        --silent)
+else
+    RESPONSE=$(curl -X POST "http://localhost:8082/verify" \
+        -H "Content-Type: application/json" \
+        --data-binary @- <<< "$PROOF_DATA" \
+        --max-time 300 \
+        --silent)
+fi
 
 VERIFIED=$(echo "$RESPONSE" | jq -r '.proof_verified // false')
 [ "$VERIFIED" != "true" ] && { echo "ERROR: Verification failed"; echo "$RESPONSE" | jq .; kill $VERIFIER_PID 2>/dev/null; exit 1; }
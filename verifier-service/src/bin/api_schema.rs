@@ -0,0 +1,10 @@
+//! Emit the JSON Schema and TypeScript definitions of the verifier-service HTTP models.
+//!
+//! Usage:
+//!   api-schema                                   print the JSON Schema to stdout
+//!   api-schema --typescript                      print the TypeScript definitions to stdout
+//!   api-schema --check <schema.json> <types.d.ts>  fail if either differs from the models
+
+fn main() {
+    sbom_common::api_schema::cli(&verifier_service::models::api_schema());
+}
@@ -0,0 +1,41 @@
+//! Sign a list catalog for distribution to verifiers.
+//!
+//! Usage:
+//!   LIST_CATALOG_KEY=<hex> sign-catalog <catalog.json>
+//!
+//! `<catalog.json>` holds `{"publisher", "issued_at", "lists": [{"version", "banned_list_hash", "key_type"}]}`;
+//! the signed catalog, ready for `LIST_CATALOG`, is printed to stdout.
+
+use verifier_service::catalog::{sign, CatalogBody};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [path] = args.as_slice() else {
+        eprintln!("Usage: LIST_CATALOG_KEY=<hex> sign-catalog <catalog.json>");
+        std::process::exit(2);
+    };
+
+    let key = std::env::var("LIST_CATALOG_KEY").unwrap_or_default();
+    let key = match hex::decode(key.trim()) {
+        Ok(key) if !key.is_empty() => key,
+        _ => {
+            eprintln!("LIST_CATALOG_KEY must be set to a hex key");
+            std::process::exit(2);
+        }
+    };
+
+    let raw = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read '{path}': {e}");
+        std::process::exit(2);
+    });
+    let catalog: CatalogBody = serde_json::from_str(&raw).unwrap_or_else(|e| {
+        eprintln!("'{path}' is not a list catalog: {e}");
+        std::process::exit(1);
+    });
+
+    let signed = sign(&catalog, &key);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&signed).expect("signed catalog serializes")
+    );
+}
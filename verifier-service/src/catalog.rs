@@ -0,0 +1,232 @@
+//! Signed catalog of published banned list versions.
+//!
+//! A list publisher maps each released list version to its `banned_list_hash` and signs
+//! the mapping. With a catalog configured, a request may name the list by
+//! `expected_list_version` instead of by hash, and the verifier accepts the proof only if
+//! its journal commits to the hash the catalog records for that version.
+//!
+//! The catalog file is `{"catalog": "<base64 catalog JSON>", "signature": "<hex>"}`. The
+//! signature is HMAC-SHA256 over the decoded catalog bytes, keyed with the secret the
+//! publisher shares with the verifier (`LIST_CATALOG_KEY`). Signing the bytes rather than
+//! the parsed JSON means no canonical encoding is needed.
+
+use base64::{engine::general_purpose, Engine as _};
+use sbom_common::{constant_time_eq, hmac_sha256, KeyType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+
+/// The file a publisher distributes: the catalog and its signature
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignedCatalog {
+    /// Base64 of the catalog JSON, exactly as signed
+    pub catalog: String,
+    /// Hex HMAC-SHA256 of the decoded catalog bytes
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CatalogEntry {
+    pub version: String,
+    /// Hex hash of the list as committed by an unblinded proof
+    pub banned_list_hash: String,
+    /// Kind of identifier the list is keyed by
+    #[serde(default)]
+    pub key_type: KeyType,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CatalogBody {
+    pub publisher: String,
+    /// Unix seconds at which the publisher issued this catalog
+    pub issued_at: u64,
+    pub lists: Vec<CatalogEntry>,
+}
+
+/// A catalog whose signature has been checked
+#[derive(Debug, Clone)]
+pub struct ListCatalog {
+    publisher: String,
+    issued_at: u64,
+    /// A catalog older than this no longer vouches for any version
+    max_age: Duration,
+    lists: HashMap<String, CatalogEntry>,
+}
+
+impl ListCatalog {
+    /// Read and verify the signed catalog at `path`
+    pub fn load(path: &Path, key: &[u8], max_age: Duration) -> std::result::Result<Self, String> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read list catalog {}: {}", path.display(), e))?;
+        let signed: SignedCatalog = serde_json::from_str(&raw).map_err(|e| {
+            format!(
+                "List catalog {} is not a signed catalog: {}",
+                path.display(),
+                e
+            )
+        })?;
+        Self::verify(&signed, key, max_age)
+    }
+
+    /// Check the signature of `signed` and parse the catalog it carries
+    pub fn verify(
+        signed: &SignedCatalog,
+        key: &[u8],
+        max_age: Duration,
+    ) -> std::result::Result<Self, String> {
+        let bytes = general_purpose::STANDARD
+            .decode(signed.catalog.trim())
+            .map_err(|e| format!("List catalog is not valid base64: {e}"))?;
+        let signature = hex::decode(signed.signature.trim())
+            .map_err(|_| "List catalog signature is not hex".to_string())?;
+        if !constant_time_eq(&hmac_sha256(key, &bytes), &signature) {
+            return Err(
+                "List catalog signature does not match; it was altered or signed with another key"
+                    .to_string(),
+            );
+        }
+
+        let body: CatalogBody = serde_json::from_slice(&bytes)
+            .map_err(|e| format!("Signed list catalog is malformed: {e}"))?;
+        let mut lists = HashMap::new();
+        for mut entry in body.lists {
+            let hash = entry.banned_list_hash.trim();
+            let hash = hash.strip_prefix("0x").unwrap_or(hash).to_ascii_lowercase();
+            if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(format!(
+                    "List catalog version '{}' has an invalid banned_list_hash: must be a 64-character hex string",
+                    entry.version
+                ));
+            }
+            entry.banned_list_hash = hash;
+            if lists.contains_key(&entry.version) {
+                return Err(format!(
+                    "List catalog lists version '{}' twice",
+                    entry.version
+                ));
+            }
+            lists.insert(entry.version.clone(), entry);
+        }
+
+        Ok(Self {
+            publisher: body.publisher,
+            issued_at: body.issued_at,
+            max_age,
+            lists,
+        })
+    }
+
+    pub fn publisher(&self) -> &str {
+        &self.publisher
+    }
+
+    pub fn issued_at(&self) -> u64 {
+        self.issued_at
+    }
+
+    pub fn len(&self) -> usize {
+        self.lists.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lists.is_empty()
+    }
+
+    /// Seconds since the catalog was issued, as of `now`
+    pub fn age(&self, now: SystemTime) -> Duration {
+        let now = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        Duration::from_secs(now.saturating_sub(self.issued_at))
+    }
+
+    pub fn is_stale(&self, now: SystemTime) -> bool {
+        self.age(now) > self.max_age
+    }
+
+    /// The entry for `version`, provided the catalog is still fresh as of `now`
+    pub fn resolve(&self, version: &str, now: SystemTime) -> Result<&CatalogEntry> {
+        if self.is_stale(now) {
+            return Err(Error::StaleCatalog(format!(
+                "the list catalog from {} was issued {}s ago, more than the accepted {}s; it cannot vouch for version '{}' until it is refreshed",
+                self.publisher,
+                self.age(now).as_secs(),
+                self.max_age.as_secs(),
+                version
+            )));
+        }
+        self.lists.get(version).ok_or_else(|| {
+            Error::UnknownListVersion(format!(
+                "'{}' is not in the catalog from {}",
+                version, self.publisher
+            ))
+        })
+    }
+}
+
+/// Sign `catalog` for distribution with `key`
+pub fn sign(catalog: &CatalogBody, key: &[u8]) -> SignedCatalog {
+    let bytes = serde_json::to_vec(catalog).expect("catalog serializes to JSON");
+    SignedCatalog {
+        catalog: general_purpose::STANDARD.encode(&bytes),
+        signature: hex::encode(hmac_sha256(key, &bytes)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Signed with [`FIXTURE_KEY`]: versions 2024-06 (`22..`) and 2024-05 (`21..`) of a
+    /// CPE list, issued at 1718000000
+    const FIXTURE: &str = include_str!("../testdata/catalog.json");
+    const FIXTURE_KEY: &[u8] = b"fixture-catalog-key";
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn fixture(key: &[u8]) -> std::result::Result<ListCatalog, String> {
+        let signed: SignedCatalog = serde_json::from_str(FIXTURE).unwrap();
+        ListCatalog::verify(&signed, key, DAY)
+    }
+
+    fn issued_plus(elapsed: Duration) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_718_000_000) + elapsed
+    }
+
+    #[test]
+    fn fresh_catalogs_resolve_their_versions() {
+        let catalog = fixture(FIXTURE_KEY).unwrap();
+        assert_eq!(catalog.publisher(), "Example Security");
+        assert_eq!(catalog.len(), 2);
+
+        let entry = catalog.resolve("2024-06", issued_plus(DAY / 2)).unwrap();
+        assert_eq!(entry.banned_list_hash, "22".repeat(32));
+        assert_eq!(entry.key_type, KeyType::Cpe);
+    }
+
+    #[test]
+    fn unknown_versions_and_stale_catalogs_are_distinct_errors() {
+        let catalog = fixture(FIXTURE_KEY).unwrap();
+
+        let unknown = catalog.resolve("2024-07", issued_plus(DAY / 2));
+        assert!(matches!(unknown, Err(Error::UnknownListVersion(_))));
+        // Staleness is reported even for versions the catalog lists
+        let stale = catalog.resolve("2024-06", issued_plus(2 * DAY));
+        assert!(matches!(stale, Err(Error::StaleCatalog(_))));
+    }
+
+    #[test]
+    fn catalogs_signed_with_another_key_are_refused() {
+        let err = fixture(b"another-key").unwrap_err();
+        assert!(err.contains("signature does not match"), "{err}");
+
+        let mut signed: SignedCatalog = serde_json::from_str(FIXTURE).unwrap();
+        let body = CatalogBody {
+            publisher: "Example Security".to_string(),
+            issued_at: 1_718_000_000,
+            lists: Vec::new(),
+        };
+        signed.catalog = sign(&body, b"another-key").catalog;
+        assert!(ListCatalog::verify(&signed, FIXTURE_KEY, DAY).is_err());
+    }
+}
@@ -1,8 +1,80 @@
+use std::collections::HashMap;
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub port: u16,
+    /// Named roots (e.g. "security-banlist@2024-06") mapped to normalized hex roots
+    pub root_aliases: HashMap<String, String>,
+    /// Largest request body accepted by `/verify` (JSON) and `/verify/raw` (raw receipt), in bytes
+    pub max_proof_bytes: usize,
+    /// Receipt verifications that may run at once (`VERIFY_WORKERS`)
+    pub verify_workers: usize,
+    /// Verifications that may wait for a worker before requests are refused with 429
+    /// (`VERIFY_QUEUE`)
+    pub verify_queue: usize,
+    /// Signed catalog of published banned list versions (`LIST_CATALOG`)
+    pub list_catalog: Option<PathBuf>,
+    /// Hex key the catalog is signed with (`LIST_CATALOG_KEY`)
+    pub list_catalog_key: Option<String>,
+    /// Oldest catalog that still resolves list versions (`LIST_CATALOG_MAX_AGE`, seconds)
+    pub list_catalog_max_age: Duration,
+    /// Whether `/verify-file` still takes proof files without the `sbom-proof` envelope
+    /// (`LEGACY_PROOFS`, false only when `reject`)
+    pub accept_bare_proofs: bool,
+    /// How long fetching a `proof_url` may take, redirects included
+    /// (`PROOF_FETCH_TIMEOUT`, seconds)
+    pub proof_fetch_timeout: Duration,
+    /// Largest proof file fetched from a `proof_url`, in bytes (`PROOF_FETCH_MAX_BYTES`)
+    pub proof_fetch_max_bytes: usize,
+    /// Whether a `proof_url` may use plain HTTP, e.g. a registry inside the cluster
+    /// (`PROOF_FETCH_ALLOW_HTTP`); HTTPS only by default
+    pub proof_fetch_allow_http: bool,
+    /// Hosts a `proof_url` may name, on every hop of a redirect (`PROOF_FETCH_ALLOWED_HOSTS`,
+    /// comma-separated). A listed host is trusted wherever it resolves to; with none listed,
+    /// any host is fetched whose addresses are all public
+    pub proof_fetch_allowed_hosts: Vec<String>,
+    /// Whether receipts faked by a prover in `RISC0_DEV_MODE` are accepted, for
+    /// integration tests (`ALLOW_DEV_RECEIPTS`); they prove nothing and are refused by default
+    pub allow_dev_receipts: bool,
+    /// Origins browsers may call the API from (`CORS_ALLOWED_ORIGINS`, comma-separated, `*`
+    /// for any); none sends no CORS headers
+    pub cors_allowed_origins: Vec<String>,
+    /// Methods allowed in cross-origin requests (`CORS_ALLOWED_METHODS`)
+    pub cors_allowed_methods: Vec<String>,
+    /// Request headers allowed in cross-origin requests (`CORS_ALLOWED_HEADERS`)
+    pub cors_allowed_headers: Vec<String>,
+    /// How long browsers may cache a preflight answer (`CORS_MAX_AGE_SECS`)
+    pub cors_max_age: Duration,
+}
+
+/// Default for `MAX_PROOF_BYTES`: large enough for a base64 composite receipt of a big batch.
+const DEFAULT_MAX_PROOF_BYTES: usize = 256 * 1024 * 1024;
+
+/// Default for `VERIFY_QUEUE`: enough to absorb a burst without letting latency run away.
+const DEFAULT_VERIFY_QUEUE: usize = 16;
+
+/// Default for `LIST_CATALOG_MAX_AGE`: a week, so a publisher that re-signs daily can miss a few days.
+const DEFAULT_LIST_CATALOG_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Default for `PROOF_FETCH_TIMEOUT`: long enough to download a large receipt from a
+/// registry on the same network.
+const DEFAULT_PROOF_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default for `CORS_ALLOWED_METHODS`: the methods the API routes use.
+const DEFAULT_CORS_ALLOWED_METHODS: &[&str] = &["GET", "POST"];
+
+/// Default for `CORS_ALLOWED_HEADERS`: the request headers the API reads.
+const DEFAULT_CORS_ALLOWED_HEADERS: &[&str] = &["Accept", "Accept-Language", "Content-Type"];
+
+/// Default for `CORS_MAX_AGE_SECS`: an hour of cached preflights.
+const DEFAULT_CORS_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Default for `VERIFY_WORKERS`: one verification per available CPU.
+fn default_verify_workers() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
 }
 
 impl Config {
@@ -12,7 +84,113 @@ impl Config {
             .and_then(|p| p.parse().ok())
             .unwrap_or(8082);
 
-        Self { port }
+        let root_aliases = env::var("ROOT_ALIASES")
+            .map(|raw| parse_root_aliases(&raw))
+            .unwrap_or_default();
+
+        let max_proof_bytes = env::var("MAX_PROOF_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_PROOF_BYTES);
+
+        let verify_workers = env::var("VERIFY_WORKERS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&workers| workers > 0)
+            .unwrap_or_else(default_verify_workers);
+
+        let verify_queue = env::var("VERIFY_QUEUE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_VERIFY_QUEUE);
+
+        let list_catalog = env::var("LIST_CATALOG")
+            .ok()
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from);
+        let list_catalog_key = env::var("LIST_CATALOG_KEY").ok().filter(|k| !k.is_empty());
+
+        let list_catalog_max_age = env::var("LIST_CATALOG_MAX_AGE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_LIST_CATALOG_MAX_AGE);
+
+        let accept_bare_proofs = match env::var("LEGACY_PROOFS").ok().as_deref() {
+            None | Some("" | "accept" | "serve") => true,
+            Some("reject") => false,
+            Some(other) => {
+                tracing::warn!(
+                    "Ignoring LEGACY_PROOFS '{}': expected accept, serve or reject",
+                    other
+                );
+                true
+            }
+        };
+
+        let proof_fetch_timeout = env::var("PROOF_FETCH_TIMEOUT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_PROOF_FETCH_TIMEOUT);
+
+        let proof_fetch_max_bytes = env::var("PROOF_FETCH_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(max_proof_bytes);
+
+        let proof_fetch_allow_http = env::var("PROOF_FETCH_ALLOW_HTTP")
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let proof_fetch_allowed_hosts = env::var("PROOF_FETCH_ALLOWED_HOSTS")
+            .map(|raw| parse_list(&raw.to_ascii_lowercase()))
+            .unwrap_or_default();
+
+        let allow_dev_receipts = env::var("ALLOW_DEV_RECEIPTS")
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let cors_allowed_origins = env::var("CORS_ALLOWED_ORIGINS")
+            .map(|raw| parse_list(&raw))
+            .unwrap_or_default();
+        let cors_allowed_methods = env::var("CORS_ALLOWED_METHODS")
+            .map(|raw| parse_list(&raw))
+            .ok()
+            .filter(|methods| !methods.is_empty())
+            .unwrap_or_else(|| to_strings(DEFAULT_CORS_ALLOWED_METHODS));
+        let cors_allowed_headers = env::var("CORS_ALLOWED_HEADERS")
+            .map(|raw| parse_list(&raw))
+            .ok()
+            .filter(|headers| !headers.is_empty())
+            .unwrap_or_else(|| to_strings(DEFAULT_CORS_ALLOWED_HEADERS));
+        let cors_max_age = env::var("CORS_MAX_AGE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_CORS_MAX_AGE);
+
+        Self {
+            port,
+            root_aliases,
+            max_proof_bytes,
+            verify_workers,
+            verify_queue,
+            list_catalog,
+            list_catalog_key,
+            list_catalog_max_age,
+            accept_bare_proofs,
+            proof_fetch_timeout,
+            proof_fetch_max_bytes,
+            proof_fetch_allow_http,
+            proof_fetch_allowed_hosts,
+            allow_dev_receipts,
+            cors_allowed_origins,
+            cors_allowed_methods,
+            cors_allowed_headers,
+            cors_max_age,
+        }
     }
 }
 
@@ -20,7 +198,57 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             port: 8082,
+            root_aliases: HashMap::new(),
+            max_proof_bytes: DEFAULT_MAX_PROOF_BYTES,
+            verify_workers: default_verify_workers(),
+            verify_queue: DEFAULT_VERIFY_QUEUE,
+            list_catalog: None,
+            list_catalog_key: None,
+            list_catalog_max_age: DEFAULT_LIST_CATALOG_MAX_AGE,
+            accept_bare_proofs: true,
+            proof_fetch_timeout: DEFAULT_PROOF_FETCH_TIMEOUT,
+            proof_fetch_max_bytes: DEFAULT_MAX_PROOF_BYTES,
+            proof_fetch_allow_http: false,
+            proof_fetch_allowed_hosts: Vec::new(),
+            allow_dev_receipts: false,
+            cors_allowed_origins: Vec::new(),
+            cors_allowed_methods: to_strings(DEFAULT_CORS_ALLOWED_METHODS),
+            cors_allowed_headers: to_strings(DEFAULT_CORS_ALLOWED_HEADERS),
+            cors_max_age: DEFAULT_CORS_MAX_AGE,
         }
     }
 }
 
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+/// Comma-separated values, trimmed, without empty ones
+fn parse_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// Parse `alias=root` pairs separated by commas, using the same format as the proving-service.
+/// Malformed entries are skipped with a warning.
+fn parse_root_aliases(raw: &str) -> HashMap<String, String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let Some((alias, root)) = entry.split_once('=') else {
+                tracing::warn!("Ignoring malformed ROOT_ALIASES entry '{}': expected alias=root", entry);
+                return None;
+            };
+            let root = root.trim();
+            let root = root.strip_prefix("0x").unwrap_or(root).to_ascii_lowercase();
+            if root.len() != 64 || !root.bytes().all(|b| b.is_ascii_hexdigit()) {
+                tracing::warn!("Ignoring ROOT_ALIASES entry '{}': root must be a 64-character hex string", entry);
+                return None;
+            }
+            Some((alias.trim().to_string(), root))
+        })
+        .collect()
+}
@@ -0,0 +1,89 @@
+//! Cross-origin access to the HTTP API, for browser dashboards on another origin.
+//!
+//! The policy comes from `CORS_ALLOWED_ORIGINS` and its companions. Without origins no
+//! CORS headers are sent at all, so browsers keep refusing cross-origin calls as before.
+//! The configuration is checked by [`policy`] at startup, so a typo in an origin stops the
+//! service instead of opening or closing the API unnoticed.
+
+use actix_cors::Cors;
+use actix_web::http::header::HeaderName;
+use actix_web::http::{Method, Uri};
+
+use crate::config::Config;
+
+/// Origin value that allows every origin
+pub const ANY_ORIGIN: &str = "*";
+
+/// The CORS middleware for `config`, or `None` when no origins are configured. Fails with
+/// a message naming the offending variable when an origin, method or header is invalid.
+pub fn policy(config: &Config) -> Result<Option<Cors>, String> {
+    let origins = &config.cors_allowed_origins;
+    if origins.is_empty() {
+        return Ok(None);
+    }
+    let any_origin = origins.iter().any(|origin| origin == ANY_ORIGIN);
+    if any_origin && origins.len() > 1 {
+        return Err(format!(
+            "Invalid CORS_ALLOWED_ORIGINS: '{ANY_ORIGIN}' cannot be combined with other origins"
+        ));
+    }
+    if !any_origin {
+        for origin in origins {
+            validate_origin(origin).map_err(|reason| {
+                format!("Invalid CORS_ALLOWED_ORIGINS entry '{origin}': {reason}")
+            })?;
+        }
+    }
+
+    let methods = config
+        .cors_allowed_methods
+        .iter()
+        .map(|method| {
+            Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                .map_err(|_| format!("Invalid CORS_ALLOWED_METHODS entry '{method}'"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let headers = config
+        .cors_allowed_headers
+        .iter()
+        .map(|name| {
+            HeaderName::try_from(name.as_str())
+                .map_err(|_| format!("Invalid CORS_ALLOWED_HEADERS entry '{name}'"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut cors = Cors::default()
+        .allowed_methods(methods)
+        .allowed_headers(headers)
+        .max_age(config.cors_max_age.as_secs() as usize);
+    if any_origin {
+        cors = cors.allow_any_origin();
+    } else {
+        for origin in origins {
+            cors = cors.allowed_origin(origin);
+        }
+    }
+    Ok(Some(cors))
+}
+
+/// An origin is `scheme://host[:port]` with an http(s) scheme, as browsers send it
+fn validate_origin(origin: &str) -> Result<(), &'static str> {
+    const EXPECTED: &str = "expected http(s)://host[:port]";
+    let uri: Uri = origin.parse().map_err(|_| EXPECTED)?;
+    match uri.scheme_str() {
+        Some("http" | "https") => {}
+        _ => return Err(EXPECTED),
+    }
+    if uri.host().is_none_or(str::is_empty) {
+        return Err(EXPECTED);
+    }
+    // Uri reports "/" for a bare authority too, so look at the text itself
+    let rest = &origin[origin.find("://").map_or(0, |at| at + 3)..];
+    if rest.contains(['/', '?', '#']) {
+        return Err("an origin has no path, query or fragment");
+    }
+    if rest.contains('@') {
+        return Err("an origin has no user info");
+    }
+    Ok(())
+}
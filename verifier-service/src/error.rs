@@ -1,4 +1,6 @@
+use actix_web::http::{header, StatusCode};
 use actix_web::{HttpResponse, ResponseError};
+use sbom_common::messages::{verifier, Locale, Message};
 use std::fmt;
 
 #[derive(Debug)]
@@ -7,37 +9,123 @@ pub enum Error {
     VerificationFailed(String),
     DeserializationFailed(String),
     InvalidImageId(String),
+    UnknownRootAlias(String),
+    /// The requested list version is not in the catalog, or no catalog is configured
+    UnknownListVersion(String),
+    /// The list catalog is older than `LIST_CATALOG_MAX_AGE`; the publisher must re-sign it
+    StaleCatalog(String),
+    InsufficientProofCount { expected_min: u32, actual: u32 },
+    InvalidBase64(String),
+    TruncatedProof(String),
+    PaddedProof(String),
+    DoubleEncodedProof(String),
+    /// The receipt was faked in dev mode and `ALLOW_DEV_RECEIPTS` is off
+    DevReceipt(String),
+    /// Every verification worker is busy and the queue is full
+    Overloaded(String),
     InternalError(String),
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Error {
+    /// Stable machine-readable code, returned alongside the message
+    pub fn code(&self) -> &'static str {
+        self.message().code
+    }
+
+    /// The message in `locale`; `Display` gives the English one
+    pub fn localized(&self, locale: Locale) -> String {
+        self.message().render(locale, &self.params())
+    }
+
+    fn message(&self) -> &'static Message {
         match self {
-            Error::InvalidProof(msg) => write!(f, "Invalid proof: {}", msg),
-            Error::VerificationFailed(msg) => write!(f, "Verification failed: {}", msg),
-            Error::DeserializationFailed(msg) => write!(f, "Deserialization failed: {}", msg),
-            Error::InvalidImageId(msg) => write!(f, "Invalid image ID: {}", msg),
-            Error::InternalError(msg) => write!(f, "Internal error: {}", msg),
+            Error::InvalidProof(_) => &verifier::INVALID_PROOF,
+            Error::VerificationFailed(_) => &verifier::VERIFICATION_FAILED,
+            Error::DeserializationFailed(_) => &verifier::DESERIALIZATION_FAILED,
+            Error::InvalidImageId(_) => &verifier::INVALID_IMAGE_ID,
+            Error::UnknownRootAlias(_) => &verifier::UNKNOWN_ROOT_ALIAS,
+            Error::UnknownListVersion(_) => &verifier::UNKNOWN_LIST_VERSION,
+            Error::StaleCatalog(_) => &verifier::STALE_CATALOG,
+            Error::InsufficientProofCount { .. } => &verifier::INSUFFICIENT_PROOF_COUNT,
+            Error::InvalidBase64(_) => &verifier::INVALID_BASE64,
+            Error::TruncatedProof(_) => &verifier::TRUNCATED_PROOF,
+            Error::PaddedProof(_) => &verifier::PADDED_PROOF,
+            Error::DoubleEncodedProof(_) => &verifier::DOUBLE_ENCODED_PROOF,
+            Error::DevReceipt(_) => &verifier::DEV_RECEIPT,
+            Error::Overloaded(_) => &verifier::OVERLOADED,
+            Error::InternalError(_) => &verifier::INTERNAL_ERROR,
+        }
+    }
+
+    /// Values of the placeholders in the error's message
+    fn params(&self) -> Vec<(&'static str, &dyn fmt::Display)> {
+        match self {
+            Error::UnknownRootAlias(alias) => vec![("alias", alias)],
+            Error::InsufficientProofCount {
+                expected_min,
+                actual,
+            } => vec![("expected_min", expected_min), ("actual", actual)],
+            Error::InvalidProof(msg)
+            | Error::VerificationFailed(msg)
+            | Error::DeserializationFailed(msg)
+            | Error::InvalidImageId(msg)
+            | Error::UnknownListVersion(msg)
+            | Error::StaleCatalog(msg)
+            | Error::InvalidBase64(msg)
+            | Error::TruncatedProof(msg)
+            | Error::PaddedProof(msg)
+            | Error::DoubleEncodedProof(msg)
+            | Error::DevReceipt(msg)
+            | Error::Overloaded(msg)
+            | Error::InternalError(msg) => vec![("detail", msg)],
+        }
+    }
+
+    /// The error's response with its message in `locale`. The `error` field always
+    /// carries the English message, so clients matching on it see the same text
+    /// whatever language they asked for.
+    pub fn localized_response(&self, locale: Locale) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let Error::Overloaded(_) = self {
+            response.insert_header((
+                header::RETRY_AFTER,
+                crate::pool::RETRY_AFTER_SECS.to_string(),
+            ));
         }
+        response
+            .insert_header((header::CONTENT_LANGUAGE, locale.tag()))
+            .json(serde_json::json!({
+                "error": self.to_string(),
+                "code": self.code(),
+                "message": self.localized(locale),
+            }))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.localized(Locale::En))
     }
 }
 
 impl std::error::Error for Error {}
 
+/// Errors answer with `{"error": <message>, "code": <code>, "message": <localized>}`. The
+/// message is English here; the `localize_errors` middleware re-renders it in the
+/// caller's language.
 impl ResponseError for Error {
-    fn error_response(&self) -> HttpResponse {
-        let status = match self {
-            Error::InvalidProof(_) | Error::VerificationFailed(_) | Error::DeserializationFailed(_) | Error::InvalidImageId(_) => {
-                actix_web::http::StatusCode::BAD_REQUEST
-            }
-            Error::InternalError(_) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
-        };
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::Overloaded(_) => StatusCode::TOO_MANY_REQUESTS,
+            Error::StaleCatalog(_) => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
 
-        HttpResponse::build(status).json(serde_json::json!({
-            "error": self.to_string()
-        }))
+    fn error_response(&self) -> HttpResponse {
+        self.localized_response(Locale::En)
     }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
-
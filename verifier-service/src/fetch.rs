@@ -0,0 +1,450 @@
+//! Fetching proof files by reference, for `proof_url` in a verification request.
+//!
+//! A registry that keeps the proving-service's proof files can hand out a URL instead of
+//! the file, so clients need not send megabytes of base64 themselves. Every failure to
+//! get a usable file from the URL is the caller's problem and reported as
+//! [`Error::InvalidProof`].
+//!
+//! The URL comes from the caller, so the fetch must not become a way into the network the
+//! verifier runs in. Redirects are followed by hand: every hop is held to the scheme and
+//! to `PROOF_FETCH_ALLOWED_HOSTS`, or, with no hosts listed, its host must resolve to
+//! public addresses only, and the connection is pinned to the addresses checked. The
+//! caller learns only which rule refused the URL; what the fetch ran into is logged.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use reqwest::header::LOCATION;
+use reqwest::redirect::Policy;
+use reqwest::{Response, StatusCode, Url};
+use sbom_common::envelope;
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::models::ProofFile;
+
+/// Redirects followed before a fetch is given up
+pub const MAX_PROOF_FETCH_REDIRECTS: usize = 5;
+
+/// Why a `proof_url` gave no proof file: `public` is what the caller is told, `detail`
+/// what is logged
+#[derive(Debug)]
+struct FetchError {
+    public: &'static str,
+    detail: String,
+}
+
+impl FetchError {
+    fn new(public: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            public,
+            detail: detail.into(),
+        }
+    }
+}
+
+type Fetched<T> = std::result::Result<T, FetchError>;
+
+const NOT_A_URL: &str = "proof_url is not a valid URL";
+const NOT_HTTPS: &str = "proof_url must use https";
+const HOST_REFUSED: &str = "proof_url names a host this verifier does not fetch from";
+const FETCH_FAILED: &str = "proof_url could not be fetched";
+const TOO_LARGE: &str = "the file at proof_url exceeds the size limit";
+const UNREADABLE: &str = "proof_url does not serve a readable proof file";
+
+/// Download the proof file at `url` and read it as `/verify-file` reads an upload: in its
+/// envelope, or bare unless LEGACY_PROOFS=reject. Only HTTPS is fetched unless
+/// PROOF_FETCH_ALLOW_HTTP is set, on every hop of a redirect as well.
+pub async fn fetch_proof_file(url: &str, config: &Config) -> Result<ProofFile> {
+    let fetched = async {
+        let body = download(url, config).await?;
+        read_proof_file(&body, config)
+    };
+    let fetched = tokio::time::timeout(config.proof_fetch_timeout, fetched)
+        .await
+        .unwrap_or_else(|_| {
+            Err(FetchError::new(
+                FETCH_FAILED,
+                format!("timed out after {:?}", config.proof_fetch_timeout),
+            ))
+        });
+    fetched.map_err(|e| {
+        tracing::warn!("Fetching proof_url {} failed: {}", url, e.detail);
+        Error::InvalidProof(e.public.to_string())
+    })
+}
+
+fn read_proof_file(body: &[u8], config: &Config) -> Fetched<ProofFile> {
+    let document: serde_json::Value = serde_json::from_slice(body)
+        .map_err(|e| FetchError::new(UNREADABLE, format!("not a JSON proof file: {e}")))?;
+    let opened = envelope::open(document, config.accept_bare_proofs)
+        .map_err(|e| FetchError::new(UNREADABLE, format!("unreadable proof file: {e}")))?;
+    serde_json::from_value(opened.data)
+        .map_err(|e| FetchError::new(UNREADABLE, format!("malformed proof file: {e}")))
+}
+
+/// The body at `url`, following at most `MAX_PROOF_FETCH_REDIRECTS` redirects
+async fn download(url: &str, config: &Config) -> Fetched<Vec<u8>> {
+    let mut target =
+        Url::parse(url).map_err(|e| FetchError::new(NOT_A_URL, format!("not a valid URL: {e}")))?;
+    for _ in 0..=MAX_PROOF_FETCH_REDIRECTS {
+        let response = request(&target, config).await?;
+        if !response.status().is_redirection() {
+            return read_body(response, config).await;
+        }
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or_else(|| {
+                FetchError::new(
+                    FETCH_FAILED,
+                    format!("{target} answered {} without a Location", response.status()),
+                )
+            })?;
+        target = target.join(location).map_err(|e| {
+            FetchError::new(
+                FETCH_FAILED,
+                format!("{target} redirects to '{location}': {e}"),
+            )
+        })?;
+    }
+    Err(FetchError::new(
+        FETCH_FAILED,
+        format!("more than {MAX_PROOF_FETCH_REDIRECTS} redirects"),
+    ))
+}
+
+/// Send one GET to `target` once it passes [`check_target`], connecting only to the
+/// addresses checked, so a second lookup cannot point the connection elsewhere
+async fn request(target: &Url, config: &Config) -> Fetched<Response> {
+    let addresses = check_target(target, config).await?;
+    let mut client = reqwest::Client::builder()
+        .redirect(Policy::none())
+        .https_only(!config.proof_fetch_allow_http);
+    if let Some(domain) = target.domain() {
+        client = client.resolve_to_addrs(domain, &addresses);
+    }
+    let client = client.build().map_err(|e| {
+        FetchError::new(
+            FETCH_FAILED,
+            format!("failed to build the HTTP client: {e}"),
+        )
+    })?;
+
+    tracing::debug!("Fetching proof file from {}", target);
+    client
+        .get(target.clone())
+        .send()
+        .await
+        .map_err(|e| FetchError::new(FETCH_FAILED, format!("fetching {target} failed: {e}")))
+}
+
+/// The addresses `target` may be fetched from: its scheme must be https, or http with
+/// PROOF_FETCH_ALLOW_HTTP, and its host listed in PROOF_FETCH_ALLOWED_HOSTS or, with
+/// none listed, resolve to public addresses only
+async fn check_target(target: &Url, config: &Config) -> Fetched<Vec<SocketAddr>> {
+    match target.scheme() {
+        "https" => {}
+        "http" if config.proof_fetch_allow_http => {}
+        scheme => {
+            return Err(FetchError::new(
+                NOT_HTTPS,
+                format!("the {scheme} scheme is not fetched"),
+            ))
+        }
+    }
+    let host = target
+        .host_str()
+        .ok_or_else(|| FetchError::new(NOT_A_URL, format!("{target} names no host")))?;
+    let port = target
+        .port_or_known_default()
+        .ok_or_else(|| FetchError::new(NOT_A_URL, format!("{target} names no port")))?;
+    let listed = config
+        .proof_fetch_allowed_hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(host));
+    if !config.proof_fetch_allowed_hosts.is_empty() && !listed {
+        return Err(FetchError::new(
+            HOST_REFUSED,
+            format!("{host} is not in PROOF_FETCH_ALLOWED_HOSTS"),
+        ));
+    }
+
+    // The url crate keeps the brackets of an IPv6 host, which lookup_host does not take
+    let bare_host = host.trim_start_matches('[').trim_end_matches(']');
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((bare_host, port))
+        .await
+        .map_err(|e| FetchError::new(FETCH_FAILED, format!("resolving {host} failed: {e}")))?
+        .collect();
+    if addresses.is_empty() {
+        return Err(FetchError::new(
+            FETCH_FAILED,
+            format!("{host} resolves to no address"),
+        ));
+    }
+    if !listed {
+        if let Some(internal) = addresses.iter().find(|address| !is_public(address.ip())) {
+            return Err(FetchError::new(
+                HOST_REFUSED,
+                format!(
+                    "{host} resolves to the non-public address {}",
+                    internal.ip()
+                ),
+            ));
+        }
+    }
+    Ok(addresses)
+}
+
+/// Whether `ip` is reachable on the public internet, as opposed to loopback, private,
+/// link-local (cloud metadata services among them), shared, reserved or multicast
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_v4(mapped),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Shared address space of carrier-grade NAT, 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments, 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking, 198.18.0.0/15
+        || (a == 198 && (b == 18 || b == 19))
+        // Reserved, 240.0.0.0/4
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link-local, fe80::/10
+        || (first & 0xffc0) == 0xfe80
+        // Documentation, 2001:db8::/32
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+/// Read a 200 answer of at most PROOF_FETCH_MAX_BYTES
+async fn read_body(mut response: Response, config: &Config) -> Fetched<Vec<u8>> {
+    if response.status() != StatusCode::OK {
+        return Err(FetchError::new(
+            FETCH_FAILED,
+            format!("{} answered {}", response.url(), response.status()),
+        ));
+    }
+
+    let limit = config.proof_fetch_max_bytes;
+    let too_large = || FetchError::new(TOO_LARGE, format!("the file exceeds {limit} bytes"));
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| FetchError::new(FETCH_FAILED, format!("download failed: {e}")))?
+    {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Answer every request on a loopback port with `respond(path)`, a raw HTTP response;
+    /// returns the server's base URL and how many requests it has taken
+    async fn serve(
+        respond: impl Fn(&str) -> String + Send + Sync + 'static,
+    ) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 4096];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]);
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                counted.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.write_all(respond(&path).as_bytes()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+        (base, requests)
+    }
+
+    /// Plain HTTP to the loopback test server, which only an allowlist admits
+    fn loopback_config() -> Config {
+        Config {
+            proof_fetch_allow_http: true,
+            proof_fetch_allowed_hosts: vec!["127.0.0.1".to_string()],
+            ..Config::default()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn redirects_are_capped() {
+        let (base, requests) = serve(|path| {
+            let hop: usize = path.trim_start_matches("/hop/").parse().unwrap_or(0);
+            format!(
+                "HTTP/1.1 302 Found\r\nLocation: /hop/{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                hop + 1
+            )
+        })
+        .await;
+
+        let error = download(&format!("{base}/hop/0"), &loopback_config())
+            .await
+            .unwrap_err();
+        assert_eq!(error.public, FETCH_FAILED);
+        assert!(error.detail.contains("redirects"), "{}", error.detail);
+        assert_eq!(
+            requests.load(Ordering::SeqCst),
+            MAX_PROOF_FETCH_REDIRECTS + 1
+        );
+    }
+
+    #[actix_rt::test]
+    async fn oversized_files_are_refused() {
+        let config = Config {
+            proof_fetch_max_bytes: 64,
+            ..loopback_config()
+        };
+        let body = "x".repeat(65);
+        let (base, _) = serve(move |path| match path {
+            // Declared too large
+            "/declared" => format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            ),
+            // Found too large while reading
+            _ => format!("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n{body}"),
+        })
+        .await;
+
+        for path in ["/declared", "/undeclared"] {
+            let error = download(&format!("{base}{path}"), &config)
+                .await
+                .unwrap_err();
+            assert_eq!(error.public, TOO_LARGE, "{path}: {}", error.detail);
+        }
+        let within = Config {
+            proof_fetch_max_bytes: 65,
+            ..config
+        };
+        let body = download(&format!("{base}/declared"), &within)
+            .await
+            .unwrap();
+        assert_eq!(body.len(), 65);
+    }
+
+    #[actix_rt::test]
+    async fn internal_targets_are_refused() {
+        let (base, requests) = serve(|_| {
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}".to_string()
+        })
+        .await;
+        let port = base.rsplit(':').next().unwrap().to_string();
+        let config = Config {
+            proof_fetch_allow_http: true,
+            ..Config::default()
+        };
+
+        for url in [
+            base.clone(),
+            format!("http://localhost:{port}/"),
+            format!("http://[::1]:{port}/"),
+            format!("http://[::ffff:127.0.0.1]:{port}/"),
+            "http://169.254.169.254/latest/meta-data/".to_string(),
+            "http://10.0.0.1/".to_string(),
+            "http://192.168.1.1/".to_string(),
+        ] {
+            let error = download(&url, &config).await.unwrap_err();
+            assert_eq!(error.public, HOST_REFUSED, "{url}: {}", error.detail);
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+        // A listed host that redirects to one not listed is refused on that hop
+        let (redirecting, _) = serve(move |_| {
+            format!(
+                "HTTP/1.1 302 Found\r\nLocation: http://localhost:{port}/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            )
+        })
+        .await;
+        let error = download(&redirecting, &loopback_config())
+            .await
+            .unwrap_err();
+        assert_eq!(error.public, HOST_REFUSED, "{}", error.detail);
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[actix_rt::test]
+    async fn callers_are_not_told_the_url_or_the_cause() {
+        let (base, _) = serve(|_| {
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        })
+        .await;
+        let url = format!("{base}/secret-path");
+        let Err(Error::InvalidProof(message)) = fetch_proof_file(&url, &loopback_config()).await
+        else {
+            panic!("a 404 must fail as an invalid proof");
+        };
+        assert_eq!(message, FETCH_FAILED);
+    }
+
+    #[test]
+    fn only_public_addresses_are_public() {
+        for internal in [
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(!is_public(internal.parse().unwrap()), "{internal}");
+        }
+        for public in ["1.1.1.1", "93.184.216.34", "2606:4700:4700::1111"] {
+            assert!(is_public(public.parse().unwrap()), "{public}");
+        }
+    }
+}
@@ -1,95 +1,1241 @@
-use actix_web::{web, HttpResponse, Result as ActixResult};
-use base64::{engine::general_purpose, Engine as _};
-use risc0_zkvm::{serde::from_slice, Receipt};
+use actix_multipart::Multipart;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::PayloadError;
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::middleware::Next;
+use actix_web::{middleware, web, HttpRequest, HttpResponse, Result as ActixResult};
+use base64::{engine::general_purpose, DecodeError, Engine as _};
+use futures_util::{stream, StreamExt};
+use risc0_zkvm::{serde::from_slice, InnerReceipt, Receipt};
+use sbom_common::envelope;
+use sbom_common::input::status;
+use sbom_common::observability::{label, metric, metrics as registry};
+use sbom_common::messages::Locale;
+use sbom_common::journal::{
+    JournalLayout, MERKLE_PUBLIC_OUTPUTS, MERKLE_PUBLIC_OUTPUTS_V1, MERKLE_PUBLIC_OUTPUTS_V2,
+    MERKLE_PUBLIC_OUTPUTS_V3, MERKLE_PUBLIC_OUTPUTS_V4, MERKLE_PUBLIC_OUTPUTS_V5,
+    MERKLE_PUBLIC_OUTPUTS_V6, MERKLE_PUBLIC_OUTPUTS_V7, MERKLE_PUBLIC_OUTPUTS_V8,
+    MERKLE_PUBLIC_OUTPUTS_V9, MERKLE_PUBLIC_OUTPUTS_V10,
+};
+use sbom_common::{
+    compute_banned_list_commitment, compute_banned_list_hash, compute_list_info_hash, compute_salt_commitment,
+    hex_to_bytes32, KeyType, ProofType, DEFAULTS_DIGEST,
+};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 use tracing;
 
+use crate::catalog::{CatalogEntry, ListCatalog};
+use crate::config::Config;
+use crate::cors;
 use crate::error::{Error, Result};
-use crate::models::{MerklePublicOutputs, VerifyProofRequest, VerifyProofResponse};
+use crate::fetch::fetch_proof_file;
+use crate::pool::VerifyPool;
+use crate::models::{
+    api_schema, DecodedOutputs, FileInconsistency, MerklePublicOutputs, MerklePublicOutputsV1,
+    MerklePublicOutputsV2, MerklePublicOutputsV3, MerklePublicOutputsV4, MerklePublicOutputsV5,
+    MerklePublicOutputsV6, MerklePublicOutputsV7, MerklePublicOutputsV8, MerklePublicOutputsV9,
+    MerklePublicOutputsV10,
+    ProofFile, VerifyFileResponse, VerifyProofRequest, VerifyProofResponse, VerifyRawQuery,
+    VerifyReceiptRequest, VerifyReceiptResponse,
+    API_SCHEMA_VERSION,
+};
+
+/// Everything the application shares between workers
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Config,
+    /// Shared by every endpoint that verifies a receipt
+    pub pool: VerifyPool,
+    /// Signed catalog resolving `expected_list_version`, when one is configured
+    pub catalog: Option<Arc<ListCatalog>>,
+}
+
+impl AppState {
+    /// State with a verification pool sized by `config`
+    pub fn new(config: Config) -> Self {
+        let pool = VerifyPool::new(config.verify_workers, config.verify_queue);
+        Self {
+            config,
+            pool,
+            catalog: None,
+        }
+    }
+
+    /// Resolve list versions through `catalog`
+    pub fn with_catalog(mut self, catalog: ListCatalog) -> Self {
+        self.catalog = Some(Arc::new(catalog));
+        self
+    }
+}
+
+/// Register the complete application: shared state, body limits, middleware and every
+/// route. `main` and tests build the same app from this, so tests exercise the real
+/// middleware stack.
+pub fn configure_app(cfg: &mut web::ServiceConfig, state: AppState) {
+    let max_proof_bytes = state.config.max_proof_bytes;
+    // Checked at startup; the middleware is not Send, so every worker builds its own
+    let cors = cors::policy(&state.config).unwrap_or_else(|err_msg| {
+        tracing::error!("{}", err_msg);
+        None
+    });
+    cfg.app_data(web::Data::new(state.config))
+        .app_data(web::Data::new(state.pool))
+        .app_data(web::Data::new(state.catalog))
+        .app_data(web::JsonConfig::default().limit(max_proof_bytes))
+        .app_data(web::PayloadConfig::new(max_proof_bytes))
+        .service(
+            web::scope("")
+                .wrap(middleware::Condition::new(
+                    cors.is_some(),
+                    cors.unwrap_or_default(),
+                ))
+                .wrap(middleware::from_fn(localize_errors))
+                .wrap(middleware::Logger::default())
+                .wrap(middleware::from_fn(record_metrics))
+                .configure(configure_routes),
+        );
+}
+
+/// Register the HTTP API
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/health", web::get().to(health))
+        .route("/info", web::get().to(info))
+        .route("/schema", web::get().to(schema))
+        .route("/metrics", web::get().to(metrics))
+        .route("/verify", web::post().to(verify))
+        .route("/verify/raw", web::post().to(verify_raw))
+        .route("/verify-file", web::post().to(verify_file))
+        .route("/verify-receipt", web::post().to(verify_receipt))
+        .route("/verify-upload", web::post().to(verify_upload));
+}
 
 /// Health check endpoint
 pub async fn health() -> ActixResult<HttpResponse> {
     Ok(HttpResponse::Ok().json(serde_json::json!({"status": "healthy"})))
 }
 
-/// Verify a Risc0 zero-knowledge proof
-pub async fn verify(req: web::Json<VerifyProofRequest>) -> ActixResult<HttpResponse> {
+/// Service information, including the journal formats this verifier can decode
+pub async fn info() -> ActixResult<HttpResponse> {
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "service": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "journal_formats": [
+            describe_layout(&MERKLE_PUBLIC_OUTPUTS),
+            describe_layout(&MERKLE_PUBLIC_OUTPUTS_V10),
+            describe_layout(&MERKLE_PUBLIC_OUTPUTS_V9),
+            describe_layout(&MERKLE_PUBLIC_OUTPUTS_V8),
+            describe_layout(&MERKLE_PUBLIC_OUTPUTS_V7),
+            describe_layout(&MERKLE_PUBLIC_OUTPUTS_V6),
+            describe_layout(&MERKLE_PUBLIC_OUTPUTS_V5),
+            describe_layout(&MERKLE_PUBLIC_OUTPUTS_V4),
+            describe_layout(&MERKLE_PUBLIC_OUTPUTS_V3),
+            describe_layout(&MERKLE_PUBLIC_OUTPUTS_V2),
+            describe_layout(&MERKLE_PUBLIC_OUTPUTS_V1),
+        ],
+        "defaults_digest": hex::encode(DEFAULTS_DIGEST),
+        "api_schema_version": API_SCHEMA_VERSION,
+    })))
+}
+
+/// JSON Schema of the request and response models, for validating payloads at runtime
+pub async fn schema() -> ActixResult<HttpResponse> {
+    Ok(HttpResponse::Ok().json(api_schema().json_schema()))
+}
+
+/// Middleware answering errors with their message in the language `Accept-Language`
+/// prefers; see `sbom_common::messages`
+pub async fn localize_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> ActixResult<ServiceResponse<EitherBody<impl MessageBody>>> {
+    let locale = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(Locale::En, Locale::negotiate);
+    let res = next.call(req).await?;
+    let localized = res
+        .response()
+        .error()
+        .and_then(|e| e.as_error::<Error>())
+        .map(|e| e.localized_response(locale));
+    Ok(match localized {
+        Some(response) => res.into_response(response).map_into_right_body(),
+        None => res.map_into_left_body(),
+    })
+}
+
+/// Middleware recording the outcome and duration of every request under its route pattern
+pub async fn record_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> ActixResult<ServiceResponse<impl MessageBody>> {
+    let started = Instant::now();
+    let endpoint = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let response = next.call(req).await;
+    let status = match &response {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    sbom_common::record_request!(&endpoint, status.as_u16(), started);
+    response
+}
+
+/// Metrics in the Prometheus text exposition format
+pub async fn metrics() -> ActixResult<HttpResponse> {
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(registry().render()))
+}
+
+fn describe_layout(layout: &JournalLayout) -> serde_json::Value {
+    serde_json::json!({
+        "layout": layout,
+        "total_words": layout.total_words(),
+        "total_bytes": layout.total_bytes(),
+    })
+}
+
+/// Verify a Risc0 zero-knowledge proof, sent as base64 or, with `proof_url`, fetched
+/// from a proof file
+pub async fn verify(
+    req: web::Json<VerifyProofRequest>,
+    config: web::Data<Config>,
+    pool: web::Data<VerifyPool>,
+    catalog: web::Data<Option<Arc<ListCatalog>>>,
+) -> ActixResult<HttpResponse> {
     tracing::debug!("Received verification request");
 
-    req.validate().map_err(|e| Error::InvalidProof(e))?;
+    let mut req = req.into_inner();
+    if let Some(url) = req.proof_url.clone() {
+        let file = fetch_proof_file(&url, &config).await?;
+        req.fill_from_file(file)
+            .map_err(|e| Error::InvalidProof(format!("proof_url: {e}")))?;
+    }
+    req.validate().map_err(Error::InvalidProof)?;
+    let response = pool
+        .run(move || {
+            let receipt = deserialize_receipt(&req.proof, req.proof_size)?;
+            check_receipt(&req, &receipt, &config, catalog.as_deref())
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Verify a receipt sent as raw bytes, with the claims as query parameters.
+/// Used for receipts too large to embed as base64 in a JSON body.
+pub async fn verify_raw(
+    query: web::Query<VerifyRawQuery>,
+    body: web::Bytes,
+    config: web::Data<Config>,
+    pool: web::Data<VerifyPool>,
+    catalog: web::Data<Option<Arc<ListCatalog>>>,
+) -> ActixResult<HttpResponse> {
+    tracing::debug!("Received raw verification request ({} bytes)", body.len());
+
+    let req = VerifyProofRequest::from(query.into_inner());
+    req.validate_claims().map_err(Error::InvalidProof)?;
+    if body.is_empty() {
+        return Err(Error::InvalidProof("Proof body cannot be empty".to_string()).into());
+    }
+    if looks_like_base64(&body) {
+        return Err(Error::DoubleEncodedProof(
+            "the body is base64 text; send the raw receipt bytes here, or the base64 string to /verify".to_string(),
+        )
+        .into());
+    }
+    let response = pool
+        .run(move || {
+            let receipt = receipt_from_bytes(strip_trailing_whitespace(&body), req.proof_size)?;
+            check_receipt(&req, &receipt, &config, catalog.as_deref())
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Verify a proof file as persisted by the proving-service. Besides verifying the
+/// receipt, every metadata field of the file is cross-checked against the receipt's
+/// journal, without any caller expectations: a file whose fields were edited after
+/// proving is reported as tampered even though its receipt verifies. The file may be in
+/// the `sbom-proof` envelope or, unless LEGACY_PROOFS=reject, a bare legacy file.
+pub async fn verify_file(
+    document: web::Json<serde_json::Value>,
+    config: web::Data<Config>,
+    pool: web::Data<VerifyPool>,
+) -> ActixResult<HttpResponse> {
+    tracing::debug!("Received proof file verification request");
+
+    let opened = envelope::open(document.into_inner(), config.accept_bare_proofs)
+        .map_err(|e| Error::InvalidProof(format!("unreadable proof file: {e}")))?;
+    let file: ProofFile = serde_json::from_value(opened.data)
+        .map_err(|e| Error::InvalidProof(format!("malformed proof file: {e}")))?;
+
+    let Some(proof) = file.proof.as_deref() else {
+        return Err(Error::InvalidProof(
+            "the file links its receipt instead of embedding it; download the receipt and use /verify/raw".to_string(),
+        )
+        .into());
+    };
+    let image_id = parse_image_id(&file.image_id)?;
+
+    let proof = proof.to_string();
+    let (outputs, receipt_size, receipt_kind) = pool
+        .run(move || {
+            let receipt = deserialize_receipt(&proof, None)?;
+            let outputs = open_receipt(&receipt, image_id, &config)?;
+            Ok((outputs, decoded_len(&proof), receipt_kind_name(&receipt)))
+        })
+        .await?;
+
+    let file_consistency = file_consistency(&file, &outputs, Some(receipt_size));
+    for inconsistency in &file_consistency {
+        tracing::warn!(
+            "Proof file field {} is {} but the journal has {}",
+            inconsistency.field,
+            inconsistency.file_value,
+            inconsistency.journal_value
+        );
+    }
+
+    Ok(HttpResponse::Ok().json(VerifyFileResponse {
+        proof_verified: true,
+        tampered: !file_consistency.is_empty(),
+        file_consistency,
+        root_hash: hex::encode(outputs.root_hash),
+        banned_list_hash: hex::encode(outputs.banned_list_hash),
+        compliant: outputs.compliant,
+        proof_count: outputs.proof_count,
+        receipt_kind,
+        image_id: file.image_id.clone(),
+    }))
+}
+
+/// Verify a receipt knowing nothing but its image ID, and return everything its journal
+/// states. For auditors handed only a receipt; `/verify` is the variant that holds the
+/// journal to the caller's expectations.
+pub async fn verify_receipt(
+    req: web::Json<VerifyReceiptRequest>,
+    config: web::Data<Config>,
+    pool: web::Data<VerifyPool>,
+) -> ActixResult<HttpResponse> {
+    tracing::debug!("Received stand-alone receipt verification request");
+
+    let req = req.into_inner();
+    if req.proof.is_empty() {
+        return Err(Error::InvalidProof("Proof cannot be empty".to_string()).into());
+    }
+    let image_id = parse_image_id(&req.image_id)?;
+    let response = pool
+        .run(move || {
+            let receipt = deserialize_receipt(&req.proof, None)?;
+            let outputs = open_receipt(&receipt, image_id, &config)?;
+            sbom_common::record_outputs!(
+                "Receipt verified",
+                hex::encode(outputs.root_hash),
+                hex::encode(outputs.banned_list_hash),
+                outputs.proof_count,
+                outputs.compliant
+            );
+            // All-zero commitments mean the request had no salt, list_info or signature
+            let nonzero =
+                |hash: Option<[u8; 32]>| hash.filter(|hash| *hash != [0u8; 32]).map(hex::encode);
+            Ok(VerifyReceiptResponse {
+                proof_verified: true,
+                root_hash: hex::encode(outputs.root_hash),
+                banned_list_hash: hex::encode(outputs.banned_list_hash),
+                compliant: outputs.compliant,
+                proof_count: outputs.proof_count,
+                reasons_hash: outputs.reasons_hash.map(hex::encode),
+                list_info_hash: nonzero(outputs.list_info_hash),
+                salt_commitment: nonzero(outputs.salt_commitment),
+                key_type: key_type_name(outputs.key_type),
+                proof_type: proof_type_name(outputs.proof_type),
+                defaults_digest: outputs.defaults_digest.map(hex::encode),
+                requester_commitment: nonzero(outputs.requester_commitment),
+                first_failure: outputs.first_failure,
+                receipt_kind: receipt_kind_name(&receipt),
+                image_id: req.image_id,
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Verify a receipt uploaded as `multipart/form-data`, for receipts whose base64 makes a
+/// JSON body too large for a gateway. The `proof` part holds either the raw receipt bytes
+/// or a proof file as persisted by the proving-service, enveloped or bare. Optional text
+/// parts give the expected values: `root_hash`, `banned_list_hash`, `compliant` and
+/// `image_id` (comma-separated words). A proof file supplies any of them left out; with
+/// raw bytes all four are required. Answers like /verify.
+pub async fn verify_upload(
+    http_req: HttpRequest,
+    body: web::Bytes,
+    config: web::Data<Config>,
+    pool: web::Data<VerifyPool>,
+    catalog: web::Data<Option<Arc<ListCatalog>>>,
+) -> ActixResult<HttpResponse> {
+    tracing::debug!("Received proof upload ({} bytes)", body.len());
+
+    let upload = read_upload(http_req.headers(), body).await?;
+    let (req, raw_receipt) = upload_request(upload, config.accept_bare_proofs)?;
+    req.validate_claims().map_err(Error::InvalidProof)?;
+    let response = pool
+        .run(move || {
+            let receipt = match &raw_receipt {
+                Some(bytes) => receipt_from_bytes(strip_trailing_whitespace(bytes), None)?,
+                None => deserialize_receipt(&req.proof, req.proof_size)?,
+            };
+            check_receipt(&req, &receipt, &config, catalog.as_deref())
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Parts of a `/verify-upload` form
+#[derive(Default)]
+struct Upload {
+    proof: Option<web::Bytes>,
+    root_hash: Option<String>,
+    banned_list_hash: Option<String>,
+    compliant: Option<bool>,
+    image_id: Option<Vec<String>>,
+}
+
+/// Split a `multipart/form-data` body, already bounded by MAX_PROOF_BYTES, into its parts.
+/// Unknown parts are ignored.
+async fn read_upload(headers: &HeaderMap, body: web::Bytes) -> Result<Upload> {
+    let limit = body.len();
+    let mut form = Multipart::new(
+        headers,
+        stream::once(async move { Ok::<_, PayloadError>(body) }),
+    );
+    let mut upload = Upload::default();
+    while let Some(field) = form.next().await {
+        let mut field =
+            field.map_err(|e| Error::InvalidProof(format!("malformed multipart body: {e}")))?;
+        let name = field.name().unwrap_or_default().to_string();
+        let value = match field.bytes(limit).await {
+            Ok(Ok(value)) => value,
+            Ok(Err(e)) => {
+                return Err(Error::InvalidProof(format!(
+                    "malformed multipart body: {e}"
+                )))
+            }
+            Err(e) => return Err(Error::InvalidProof(format!("part '{name}': {e}"))),
+        };
+        let text = || {
+            std::str::from_utf8(&value)
+                .map(|text| text.trim().to_string())
+                .map_err(|_| Error::InvalidProof(format!("part '{name}' is not UTF-8 text")))
+        };
+        match name.as_str() {
+            "proof" => upload.proof = Some(value.clone()),
+            "root_hash" => upload.root_hash = Some(text()?),
+            "banned_list_hash" => upload.banned_list_hash = Some(text()?),
+            "compliant" => {
+                upload.compliant = Some(text()?.parse().map_err(|_| {
+                    Error::InvalidProof("part 'compliant' must be true or false".to_string())
+                })?)
+            }
+            "image_id" => {
+                upload.image_id = Some(
+                    text()?
+                        .split(',')
+                        .map(|word| word.trim().to_string())
+                        .filter(|word| !word.is_empty())
+                        .collect(),
+                )
+            }
+            _ => tracing::debug!("Ignoring unknown upload part '{}'", name),
+        }
+    }
+    Ok(upload)
+}
+
+/// The claims to check an upload against, and its receipt bytes when they were uploaded
+/// raw; a proof file's receipt is the request's `proof`
+fn upload_request(
+    upload: Upload,
+    accept_bare: bool,
+) -> Result<(VerifyProofRequest, Option<web::Bytes>)> {
+    let Some(proof) = upload.proof else {
+        return Err(Error::InvalidProof(
+            "the form has no 'proof' part".to_string(),
+        ));
+    };
+    // A serialized receipt starts with a small enum tag, never with a JSON object
+    let is_file = proof.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+    let (file, raw_receipt) = if is_file {
+        let document: serde_json::Value = serde_json::from_slice(&proof)
+            .map_err(|e| Error::InvalidProof(format!("unreadable proof file: {e}")))?;
+        let opened = envelope::open(document, accept_bare)
+            .map_err(|e| Error::InvalidProof(format!("unreadable proof file: {e}")))?;
+        let file: ProofFile = serde_json::from_value(opened.data)
+            .map_err(|e| Error::InvalidProof(format!("malformed proof file: {e}")))?;
+        (Some(file), None)
+    } else if proof.is_empty() {
+        return Err(Error::InvalidProof("Proof cannot be empty".to_string()));
+    } else if looks_like_base64(&proof) {
+        return Err(Error::DoubleEncodedProof(
+            "the 'proof' part is base64 text; upload the raw receipt bytes or the proof file"
+                .to_string(),
+        ));
+    } else {
+        (None, Some(proof))
+    };
+
+    let proof = match &file {
+        Some(file) => file.proof.clone().ok_or_else(|| {
+            Error::InvalidProof(
+                "the file links its receipt instead of embedding it; upload the receipt itself"
+                    .to_string(),
+            )
+        })?,
+        None => String::new(),
+    };
+    let compliant = upload
+        .compliant
+        .or(file.as_ref().map(|file| file.compliant))
+        .ok_or_else(|| Error::InvalidProof("a raw receipt needs a 'compliant' part".to_string()))?;
+    let req = VerifyProofRequest {
+        root_hash: upload
+            .root_hash
+            .or(file.as_ref().map(|file| file.root_hash.clone()))
+            .unwrap_or_default(),
+        root_alias: None,
+        banned_list_hash: upload
+            .banned_list_hash
+            .or(file.as_ref().map(|file| file.banned_list_hash.clone()))
+            .unwrap_or_default(),
+        expected_list_version: None,
+        compliant,
+        image_id: upload
+            .image_id
+            .or(file.as_ref().map(|file| file.image_id.clone()))
+            .unwrap_or_default(),
+        proof,
+        proof_url: None,
+        generation_duration_ms: None,
+        expected_min_proof_count: None,
+        expected_reasons_hash: None,
+        expected_list_info_hash: None,
+        proof_size: file.as_ref().and_then(|file| file.proof_size),
+        banned_list: None,
+        banned_list_salt: None,
+        key_type: file.as_ref().map(|file| file.key_type).unwrap_or_default(),
+        proof_type: file
+            .as_ref()
+            .map(|file| file.proof_type)
+            .unwrap_or_default(),
+        expected_requester_commitment: None,
+    };
+    Ok((req, raw_receipt))
+}
+
+/// Compare a proof file's metadata with the journal of its receipt. Fields the journal
+/// predates are skipped; hex values are compared case-insensitively without `0x`.
+/// `receipt_size` is the decoded size of the embedded receipt, if it could be determined.
+pub fn file_consistency(
+    file: &ProofFile,
+    outputs: &DecodedOutputs,
+    receipt_size: Option<usize>,
+) -> Vec<FileInconsistency> {
+    let mut found = Vec::new();
+    let mut check = |field: &'static str, file_value: String, journal_value: String| {
+        if file_value != journal_value {
+            found.push(FileInconsistency {
+                field,
+                file_value,
+                journal_value,
+            });
+        }
+    };
+    let normalize = |hex: &str| hex.strip_prefix("0x").unwrap_or(hex).to_ascii_lowercase();
+    // Optional hashes are absent from the file exactly when the journal holds all zeros
+    let optional_hash = |hash: [u8; 32]| (hash != [0u8; 32]).then(|| hex::encode(hash));
+    let or_absent = |value: Option<String>| value.unwrap_or_else(|| "absent".to_string());
+
+    check("root_hash", normalize(&file.root_hash), hex::encode(outputs.root_hash));
+    check(
+        "banned_list_hash",
+        normalize(&file.banned_list_hash),
+        hex::encode(outputs.banned_list_hash),
+    );
+    check("compliant", file.compliant.to_string(), outputs.compliant.to_string());
+
+    if let (Some(file_count), Some(count)) = (file.proof_count, outputs.proof_count) {
+        check("proof_count", file_count.to_string(), count.to_string());
+    }
+    if let (Some(file_hash), Some(hash)) = (file.reasons_hash.as_deref(), outputs.reasons_hash) {
+        check("reasons_hash", normalize(file_hash), hex::encode(hash));
+    }
+    if let Some(hash) = outputs.list_info_hash {
+        check(
+            "list_info_hash",
+            or_absent(file.list_info_hash.as_deref().map(normalize)),
+            or_absent(optional_hash(hash)),
+        );
+        let recomputed = file.list_info.as_ref().map(|info| {
+            hex::encode(compute_list_info_hash(
+                &info.source_uri,
+                &info.publisher,
+                &info.version,
+                info.entry_count,
+            ))
+        });
+        check("list_info", or_absent(recomputed), or_absent(optional_hash(hash)));
+    }
+    if let Some(hash) = outputs.salt_commitment {
+        check(
+            "salt_commitment",
+            or_absent(file.salt_commitment.as_deref().map(normalize)),
+            or_absent(optional_hash(hash)),
+        );
+    }
+    check(
+        "key_type",
+        file.key_type.name().to_string(),
+        key_type_name(outputs.key_type).to_string(),
+    );
+    check(
+        "proof_type",
+        file.proof_type.name().to_string(),
+        proof_type_name(outputs.proof_type).to_string(),
+    );
+    if let Some(digest) = outputs.defaults_digest {
+        check(
+            "defaults_digest",
+            or_absent(file.defaults_digest.as_deref().map(normalize)),
+            hex::encode(digest),
+        );
+    }
+    if let Some(commitment) = outputs.requester_commitment {
+        check(
+            "requester_commitment",
+            or_absent(file.requester_commitment.as_deref().map(normalize)),
+            or_absent(optional_hash(commitment)),
+        );
+    }
+    if let (Some(file_size), Some(size)) = (file.proof_size, receipt_size) {
+        check("proof_size", file_size.to_string(), size.to_string());
+    }
+
+    found
+}
+
+/// Decoded size of base64 text that is already known to be valid, ignoring whitespace
+fn decoded_len(base64: &str) -> usize {
+    let symbols = base64.bytes().filter(|b| !b.is_ascii_whitespace()).count();
+    let padding = base64.trim_end().bytes().rev().take_while(|&b| b == b'=').count();
+    symbols / 4 * 3 - padding
+}
+
+/// Verify the receipt against the image ID and check its journal against the request's claims
+fn check_receipt(
+    req: &VerifyProofRequest,
+    receipt: &Receipt,
+    config: &Config,
+    catalog: Option<&ListCatalog>,
+) -> Result<VerifyProofResponse> {
+    let result = verify_claims(req, receipt, config, catalog);
+    let outcome = if result.is_ok() { "verified" } else { "rejected" };
+    let _ = registry().inc_counter(
+        metric::RECEIPT_VERIFICATIONS_TOTAL,
+        &[(label::OUTCOME, outcome)],
+    );
+    result
+}
 
-    let receipt = deserialize_receipt(&req.proof)?;
+fn verify_claims(
+    req: &VerifyProofRequest,
+    receipt: &Receipt,
+    config: &Config,
+    catalog: Option<&ListCatalog>,
+) -> Result<VerifyProofResponse> {
+    let expected_root_hash = resolve_expected_root(req, config)?;
+    let list_version = resolve_list_version(req, catalog)?;
     let image_id = parse_image_id(&req.image_id)?;
 
+    check_dev_receipt(receipt, config)?;
     receipt
         .verify(image_id)
         .map_err(|e| Error::VerificationFailed(e.to_string()))?;
 
-    let outputs: MerklePublicOutputs = receipt
-        .journal
-        .decode()
-        .map_err(|e| Error::DeserializationFailed(e.to_string()))?;
+    let outputs = decode_outputs(receipt)?;
+
+    // A rejected input leaves nothing to compare; the other fields are placeholders
+    if outputs.input_status != status::OK {
+        return Err(Error::VerificationFailed(format!(
+            "The guest rejected its input ({}, status {}); the proof makes no compliance claim",
+            status::name(outputs.input_status),
+            outputs.input_status
+        )));
+    }
+    check_defaults_digest(&outputs)?;
 
     // Validate all fields match the proof's journal outputs
     let decoded_root_hash = hex::encode(outputs.root_hash);
-    if req.root_hash != decoded_root_hash {
+    if expected_root_hash != decoded_root_hash {
         return Err(Error::VerificationFailed(format!(
-            "Root hash mismatch: request has {}, proof contains {}",
-            req.root_hash, decoded_root_hash
-        ))
-        .into());
+            "Root hash mismatch: request has {expected_root_hash}, proof contains {decoded_root_hash}"
+        )));
     }
 
     let decoded_banned_hash = hex::encode(outputs.banned_list_hash);
-    if req.banned_list_hash != decoded_banned_hash {
+    if !req.banned_list_hash.is_empty() && req.banned_list_hash != decoded_banned_hash {
         return Err(Error::VerificationFailed(format!(
             "Banned list hash mismatch: request has {}, proof contains {}",
             req.banned_list_hash, decoded_banned_hash
-        ))
-        .into());
+        )));
+    }
+
+    if outputs.key_type != req.key_type.code() {
+        return Err(Error::VerificationFailed(format!(
+            "Key type mismatch: request expects a {} list, proof contains a {} list",
+            req.key_type.name(),
+            key_type_name(outputs.key_type)
+        )));
+    }
+
+    // The same flag means opposite things for the two proof types
+    if outputs.proof_type != req.proof_type.code() {
+        return Err(Error::VerificationFailed(format!(
+            "Proof type mismatch: request expects a {} proof, proof contains a {} proof",
+            req.proof_type.name(),
+            proof_type_name(outputs.proof_type)
+        )));
+    }
+
+    // An all-zero commitment means the list hash is not blinded
+    let salt_commitment = outputs.salt_commitment.filter(|hash| *hash != [0u8; 32]);
+    if let Some(banned_list) = &req.banned_list {
+        check_revealed_list(banned_list, req.banned_list_salt.as_deref(), &outputs, salt_commitment)?;
+    }
+
+    if let Some((entry, publisher)) = list_version {
+        check_list_version(entry, publisher, req, &outputs, salt_commitment)?;
     }
 
     if req.compliant != outputs.compliant {
         return Err(Error::VerificationFailed(format!(
             "Compliant flag mismatch: request has {}, proof contains {}",
             req.compliant, outputs.compliant
-        ))
-        .into());
+        )));
+    }
+
+    if let Some(expected_min) = req.expected_min_proof_count {
+        match outputs.proof_count {
+            Some(actual) if actual < expected_min => {
+                return Err(Error::InsufficientProofCount {
+                    expected_min,
+                    actual,
+                });
+            }
+            Some(_) => {}
+            None => tracing::warn!(
+                "Expected at least {} proofs, but the journal predates proof_count; count is unverified",
+                expected_min
+            ),
+        }
     }
 
-    tracing::info!("Proof verified: compliant={}", outputs.compliant);
+    if let Some(expected) = req.expected_reasons_hash.as_deref() {
+        let expected = expected.strip_prefix("0x").unwrap_or(expected).to_ascii_lowercase();
+        match outputs.reasons_hash {
+            Some(actual) if hex::encode(actual) == expected => {}
+            Some(actual) => {
+                return Err(Error::VerificationFailed(format!(
+                    "Reasons hash mismatch: request expects {}, proof contains {}",
+                    expected,
+                    hex::encode(actual)
+                )));
+            }
+            None => {
+                return Err(Error::VerificationFailed(
+                    "Request expects a reasons hash, but the journal predates reasons_hash".to_string(),
+                ));
+            }
+        }
+    }
 
-    let response = VerifyProofResponse {
+    // An all-zero hash means the prover was given no list_info
+    let list_info_hash = outputs.list_info_hash.filter(|hash| *hash != [0u8; 32]);
+    if let Some(expected) = req.expected_list_info_hash.as_deref() {
+        let expected = expected.strip_prefix("0x").unwrap_or(expected).to_ascii_lowercase();
+        match (outputs.list_info_hash, list_info_hash) {
+            (_, Some(actual)) if hex::encode(actual) == expected => {}
+            (_, Some(actual)) => {
+                return Err(Error::VerificationFailed(format!(
+                    "List info hash mismatch: request expects {}, proof contains {}",
+                    expected,
+                    hex::encode(actual)
+                )));
+            }
+            (Some(_), None) => {
+                return Err(Error::VerificationFailed(
+                    "Request expects a list info hash, but the proof was generated without list_info".to_string(),
+                ));
+            }
+            (None, _) => {
+                return Err(Error::VerificationFailed(
+                    "Request expects a list info hash, but the journal predates list_info_hash".to_string(),
+                ));
+            }
+        }
+    }
+
+    // An all-zero commitment means the request was not signed
+    let requester_commitment = outputs.requester_commitment.filter(|hash| *hash != [0u8; 32]);
+    if let Some(expected) = req.expected_requester_commitment.as_deref() {
+        let expected = expected.strip_prefix("0x").unwrap_or(expected).to_ascii_lowercase();
+        match (outputs.requester_commitment, requester_commitment) {
+            (_, Some(actual)) if hex::encode(actual) == expected => {}
+            (_, Some(actual)) => {
+                return Err(Error::VerificationFailed(format!(
+                    "Requester commitment mismatch: request expects {}, proof contains {}",
+                    expected,
+                    hex::encode(actual)
+                )));
+            }
+            (Some(_), None) => {
+                return Err(Error::VerificationFailed(
+                    "Request expects a requester commitment, but the proof was generated for an unsigned request".to_string(),
+                ));
+            }
+            (None, _) => {
+                return Err(Error::VerificationFailed(
+                    "Request expects a requester commitment, but the journal predates requester_commitment".to_string(),
+                ));
+            }
+        }
+    }
+
+    sbom_common::record_outputs!(
+        "Proof verified",
+        decoded_root_hash,
+        decoded_banned_hash,
+        outputs.proof_count,
+        outputs.compliant
+    );
+
+    Ok(VerifyProofResponse {
         proof_verified: true,
         root_hash: decoded_root_hash,
+        root_alias: req.root_alias.clone(),
         banned_list_hash: decoded_banned_hash,
+        list_version: req.expected_list_version.clone(),
         compliant: outputs.compliant,
+        proof_count: outputs.proof_count,
+        count_unverified: outputs.proof_count.is_none(),
+        reasons_hash: outputs.reasons_hash.map(hex::encode),
+        list_info_hash: list_info_hash.map(hex::encode),
+        salt_commitment: salt_commitment.map(hex::encode),
+        banned_list_revealed: req.banned_list.is_some(),
+        key_type: key_type_name(outputs.key_type),
+        proof_type: proof_type_name(outputs.proof_type),
+        defaults_digest: outputs.defaults_digest.map(hex::encode),
+        requester_commitment: requester_commitment.map(hex::encode),
+        first_failure: outputs.first_failure,
+        receipt_kind: receipt_kind_name(receipt),
         image_id: req.image_id.clone(),
         generation_duration_ms: req.generation_duration_ms,
+    })
+}
+
+/// Verify `receipt` against `image_id` with no expectations of its journal, and decode the
+/// outputs. Refuses dev receipts, rejected input and a foreign defaults table, as
+/// `verify_claims` does.
+fn open_receipt(receipt: &Receipt, image_id: [u32; 8], config: &Config) -> Result<DecodedOutputs> {
+    let result = check_dev_receipt(receipt, config)
+        .and_then(|_| {
+            receipt
+                .verify(image_id)
+                .map_err(|e| Error::VerificationFailed(e.to_string()))
+        })
+        .and_then(|_| decode_outputs(receipt));
+    let outcome = if result.is_ok() { "verified" } else { "rejected" };
+    let _ = registry().inc_counter(
+        metric::RECEIPT_VERIFICATIONS_TOTAL,
+        &[(label::OUTCOME, outcome)],
+    );
+    let outputs = result?;
+
+    if outputs.input_status != status::OK {
+        return Err(Error::VerificationFailed(format!(
+            "The guest rejected its input ({}, status {}); the proof makes no compliance claim",
+            status::name(outputs.input_status),
+            outputs.input_status
+        )));
+    }
+    check_defaults_digest(&outputs)?;
+    Ok(outputs)
+}
+
+/// Name of the kind of `receipt`; every kind verifies the same way
+fn receipt_kind_name(receipt: &Receipt) -> &'static str {
+    match &receipt.inner {
+        InnerReceipt::Composite(_) => "composite",
+        InnerReceipt::Succinct(_) => "succinct",
+        InnerReceipt::Groth16(_) => "groth16",
+        InnerReceipt::Fake(_) => "fake",
+        _ => "unknown",
+    }
+}
+
+/// Refuse a receipt faked in dev mode unless `ALLOW_DEV_RECEIPTS` is set. Decided by the
+/// receipt's inner type, never by what a request or proof file says about it.
+fn check_dev_receipt(receipt: &Receipt, config: &Config) -> Result<()> {
+    if !matches!(receipt.inner, InnerReceipt::Fake(_)) || config.allow_dev_receipts {
+        return Ok(());
+    }
+    let err_msg = "the receipt was faked by a prover in RISC0_DEV_MODE and proves nothing; set ALLOW_DEV_RECEIPTS=true to accept it in tests".to_string();
+    tracing::error!("{}", err_msg);
+    Err(Error::DevReceipt(err_msg))
+}
+
+/// A root only means what the host computed if the guest folded empty subtrees with the
+/// same DEFAULTS table; a guest built with any other table proves statements about
+/// different trees. Journals that predate defaults_digest cannot be checked.
+fn check_defaults_digest(outputs: &DecodedOutputs) -> Result<()> {
+    match outputs.defaults_digest {
+        Some(digest) if digest != DEFAULTS_DIGEST => Err(Error::VerificationFailed(format!(
+            "Defaults digest mismatch: the guest verified against a default hash table with digest {}, this verifier knows {}",
+            hex::encode(digest),
+            hex::encode(DEFAULTS_DIGEST)
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Name of a journal's key type code
+fn key_type_name(code: u32) -> &'static str {
+    KeyType::from_code(code).map_or("unknown", KeyType::name)
+}
+
+/// Name of a journal's proof type code
+fn proof_type_name(code: u32) -> &'static str {
+    ProofType::from_code(code).map_or("unknown", ProofType::name)
+}
+
+/// Reveal flow for the banned list commitment: recompute it from the revealed keys and,
+/// for a blinded commitment, the revealed salt, which must also match the committed salt hash.
+fn check_revealed_list(
+    banned_list: &[String],
+    salt: Option<&str>,
+    outputs: &DecodedOutputs,
+    salt_commitment: Option<[u8; 32]>,
+) -> Result<()> {
+    let salt = salt
+        .map(|salt| {
+            hex_to_bytes32(salt).map_err(|_| {
+                Error::InvalidProof(
+                    "Invalid banned_list_salt: must be a 64-character hex string".to_string(),
+                )
+            })
+        })
+        .transpose()?;
+
+    match (salt.as_ref(), salt_commitment) {
+        (Some(salt), Some(committed)) if compute_salt_commitment(salt) == committed => {}
+        (Some(_), Some(committed)) => {
+            return Err(Error::VerificationFailed(format!(
+                "Revealed salt does not match the salt commitment {} in the proof",
+                hex::encode(committed)
+            )));
+        }
+        (Some(_), None) => {
+            return Err(Error::VerificationFailed(
+                "A salt was revealed, but the proof's banned list commitment is not blinded".to_string(),
+            ));
+        }
+        (None, Some(_)) => {
+            return Err(Error::VerificationFailed(
+                "The proof's banned list commitment is blinded; banned_list_salt is required to check the revealed list".to_string(),
+            ));
+        }
+        (None, None) => {}
+    }
+
+    let expected = compute_banned_list_commitment(banned_list, salt.as_ref());
+    if expected != outputs.banned_list_hash {
+        return Err(Error::VerificationFailed(format!(
+            "Revealed banned list of {} purl(s) hashes to {}, but the proof commits to {}",
+            banned_list.len(),
+            hex::encode(expected),
+            hex::encode(outputs.banned_list_hash)
+        )));
+    }
+
+    tracing::info!(
+        "Revealed banned list of {} purl(s) matches the proof (blinded: {})",
+        banned_list.len(),
+        salt.is_some()
+    );
+    Ok(())
+}
+
+/// Look up `expected_list_version` in the signed list catalog, returning its entry and the
+/// catalog's publisher. Runs before the receipt is verified, so an unknown version or a
+/// stale catalog is reported without the cost of verification.
+fn resolve_list_version<'a>(
+    req: &VerifyProofRequest,
+    catalog: Option<&'a ListCatalog>,
+) -> Result<Option<(&'a CatalogEntry, &'a str)>> {
+    let Some(version) = req.expected_list_version.as_deref() else {
+        return Ok(None);
     };
+    let catalog = catalog.ok_or_else(|| {
+        Error::UnknownListVersion(format!(
+            "'{version}' cannot be resolved: this verifier has no list catalog (LIST_CATALOG)"
+        ))
+    })?;
+    let entry = catalog.resolve(version, SystemTime::now())?;
 
-    Ok(HttpResponse::Ok().json(response))
+    if !req.banned_list_hash.is_empty() {
+        let requested = req.banned_list_hash.strip_prefix("0x").unwrap_or(&req.banned_list_hash).to_ascii_lowercase();
+        if requested != entry.banned_list_hash {
+            return Err(Error::InvalidProof(format!(
+                "banned_list_hash {} contradicts list version '{}', which the catalog records as {}",
+                req.banned_list_hash, version, entry.banned_list_hash
+            )));
+        }
+    }
+
+    Ok(Some((entry, catalog.publisher())))
+}
+
+/// Check the journal commits to the list of a catalog entry. A blinded commitment hides the
+/// list hash, so it is matched through the revealed list instead, which
+/// `check_revealed_list` has already tied to the journal.
+fn check_list_version(
+    entry: &CatalogEntry,
+    publisher: &str,
+    req: &VerifyProofRequest,
+    outputs: &DecodedOutputs,
+    salt_commitment: Option<[u8; 32]>,
+) -> Result<()> {
+    let version = &entry.version;
+    if entry.key_type.code() != outputs.key_type {
+        return Err(Error::VerificationFailed(format!(
+            "List version '{}' is a {} list, but the proof contains a {} list",
+            version,
+            entry.key_type.name(),
+            key_type_name(outputs.key_type)
+        )));
+    }
+
+    let committed = match (salt_commitment, &req.banned_list) {
+        (None, _) => outputs.banned_list_hash,
+        (Some(_), Some(banned_list)) => compute_banned_list_hash(banned_list),
+        (Some(_), None) => {
+            return Err(Error::VerificationFailed(format!(
+                "The proof's banned list commitment is blinded; it can only be matched to list version '{version}' when banned_list and banned_list_salt are revealed"
+            )));
+        }
+    };
+    if hex::encode(committed) != entry.banned_list_hash {
+        return Err(Error::VerificationFailed(format!(
+            "The proof was generated against banned list {}, but list version '{}' is {} in the catalog from {}",
+            hex::encode(committed),
+            version,
+            entry.banned_list_hash,
+            publisher
+        )));
+    }
+
+    tracing::info!(
+        "Banned list matches version '{}' of the catalog from {}",
+        version,
+        publisher
+    );
+    Ok(())
 }
 
-/// Deserialize a Risc0 receipt from base64-encoded proof
-fn deserialize_receipt(proof_base64: &str) -> Result<Receipt> {
+/// Determine the root the journal must contain, resolving `root_alias` through this
+/// verifier's registry. When the request carries both, they must agree: a mismatch means
+/// the prover and verifier registries map the alias to different roots.
+fn resolve_expected_root(req: &VerifyProofRequest, config: &Config) -> Result<String> {
+    let Some(alias) = req.root_alias.as_deref() else {
+        return Ok(req.root_hash.clone());
+    };
+
+    let resolved = config
+        .root_aliases
+        .get(alias)
+        .ok_or_else(|| Error::UnknownRootAlias(alias.to_string()))?;
+
+    if !req.root_hash.is_empty() {
+        let requested = req.root_hash.strip_prefix("0x").unwrap_or(&req.root_hash).to_ascii_lowercase();
+        if requested != *resolved {
+            return Err(Error::VerificationFailed(format!(
+                "Root alias '{}' resolves to {} in this verifier's registry, but the request root_hash is {}. The prover and verifier alias registries disagree",
+                alias, resolved, req.root_hash
+            )));
+        }
+    }
+
+    Ok(resolved.clone())
+}
+
+/// Decode the journal, falling back to older layouts from newest to oldest.
+/// Newer layouts only append fields, so an older journal is too short for a newer struct.
+fn decode_outputs(receipt: &Receipt) -> Result<DecodedOutputs> {
+    let current_err = match receipt.journal.decode::<MerklePublicOutputs>() {
+        Ok(outputs) => return Ok(outputs.into()),
+        Err(e) => e,
+    };
+    if let Ok(outputs) = receipt.journal.decode::<MerklePublicOutputsV10>() {
+        return Ok(outputs.into());
+    }
+    if let Ok(outputs) = receipt.journal.decode::<MerklePublicOutputsV9>() {
+        return Ok(outputs.into());
+    }
+    if let Ok(outputs) = receipt.journal.decode::<MerklePublicOutputsV8>() {
+        return Ok(outputs.into());
+    }
+    if let Ok(outputs) = receipt.journal.decode::<MerklePublicOutputsV7>() {
+        return Ok(outputs.into());
+    }
+    if let Ok(outputs) = receipt.journal.decode::<MerklePublicOutputsV6>() {
+        return Ok(outputs.into());
+    }
+    if let Ok(outputs) = receipt.journal.decode::<MerklePublicOutputsV5>() {
+        return Ok(outputs.into());
+    }
+    if let Ok(outputs) = receipt.journal.decode::<MerklePublicOutputsV4>() {
+        return Ok(outputs.into());
+    }
+    if let Ok(outputs) = receipt.journal.decode::<MerklePublicOutputsV3>() {
+        return Ok(outputs.into());
+    }
+    if let Ok(outputs) = receipt.journal.decode::<MerklePublicOutputsV2>() {
+        return Ok(outputs.into());
+    }
+    receipt
+        .journal
+        .decode::<MerklePublicOutputsV1>()
+        .map(DecodedOutputs::from)
+        .map_err(|_| Error::DeserializationFailed(current_err.to_string()))
+}
+
+/// Deserialize a Risc0 receipt from base64-encoded proof.
+/// Whitespace (trailing newlines, line wrapping) is ignored; common transport damage
+/// is reported with a specific error instead of a generic decode failure.
+fn deserialize_receipt(proof_base64: &str, expected_size: Option<usize>) -> Result<Receipt> {
+    let cleaned: String;
+    let proof_base64 = if proof_base64.bytes().any(|b| b.is_ascii_whitespace()) {
+        cleaned = proof_base64.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+        tracing::debug!(
+            "Stripped {} whitespace characters from proof",
+            proof_base64.len() - cleaned.len()
+        );
+        cleaned.as_str()
+    } else {
+        proof_base64
+    };
+
     let proof_bytes = general_purpose::STANDARD
         .decode(proof_base64)
-        .map_err(|e| Error::InvalidProof(format!("Invalid base64: {}", e)))?;
+        .map_err(|e| match e {
+            DecodeError::InvalidLength | DecodeError::InvalidLastSymbol(..) => {
+                Error::TruncatedProof(format!(
+                    "base64 length {} does not end on a complete quantum ({}); the proof was probably cut off in transit",
+                    proof_base64.len(),
+                    e
+                ))
+            }
+            DecodeError::InvalidByte(offset, byte) => Error::InvalidBase64(format!(
+                "unexpected character {:?} at offset {} of {}",
+                byte as char,
+                offset,
+                proof_base64.len()
+            )),
+            e => Error::InvalidBase64(e.to_string()),
+        })?;
 
-    if proof_bytes.len() % 4 != 0 {
-        return Err(Error::InvalidProof(format!(
-            "Proof length {} is not a multiple of 4",
+    if looks_like_base64(&proof_bytes) {
+        return Err(Error::DoubleEncodedProof(format!(
+            "the decoded proof ({} bytes) is itself base64 text; decode it once before sending",
             proof_bytes.len()
         )));
     }
 
+    receipt_from_bytes(&proof_bytes, expected_size)
+}
+
+/// Deserialize a Risc0 receipt from its little-endian word encoding
+fn receipt_from_bytes(proof_bytes: &[u8], expected_size: Option<usize>) -> Result<Receipt> {
+    match expected_size {
+        Some(expected) if proof_bytes.len() < expected => {
+            return Err(Error::TruncatedProof(format!(
+                "received {} bytes but the prover reported {} ({} missing)",
+                proof_bytes.len(),
+                expected,
+                expected - proof_bytes.len()
+            )));
+        }
+        Some(expected) if proof_bytes.len() > expected => {
+            return Err(Error::PaddedProof(format!(
+                "received {} bytes but the prover reported {} ({} extra)",
+                proof_bytes.len(),
+                expected,
+                proof_bytes.len() - expected
+            )));
+        }
+        _ => {}
+    }
+
+    if !proof_bytes.len().is_multiple_of(4) {
+        return Err(Error::TruncatedProof(format!(
+            "proof length {} is not a multiple of 4; the last word is {} byte(s) short",
+            proof_bytes.len(),
+            4 - proof_bytes.len() % 4
+        )));
+    }
+
     let proof_u32: Vec<u32> = proof_bytes
         .chunks_exact(4)
         .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
         .collect();
 
-    from_slice(&proof_u32)
-        .map_err(|e| Error::DeserializationFailed(format!("Invalid receipt: {}", e)))
+    from_slice(&proof_u32).map_err(|e| match e {
+        risc0_zkvm::serde::Error::DeserializeUnexpectedEnd => Error::TruncatedProof(format!(
+            "receipt data ended after {} bytes while more was expected",
+            proof_bytes.len()
+        )),
+        e => Error::DeserializationFailed(format!("Invalid receipt: {e}")),
+    })
+}
+
+/// Heuristic for double encoding: a receipt starts with small little-endian words
+/// (mostly zero bytes), so plausible-length pure base64 text is never a real receipt.
+fn looks_like_base64(bytes: &[u8]) -> bool {
+    bytes.len() >= 64
+        && bytes.len().is_multiple_of(4)
+        && bytes
+            .iter()
+            .all(|&b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'='))
+        && general_purpose::STANDARD.decode(bytes).is_ok()
+}
+
+/// Drop trailing whitespace that would leave a raw body off a word boundary,
+/// e.g. the newline a shell pipeline appends. Word-aligned bodies are left untouched.
+fn strip_trailing_whitespace(bytes: &[u8]) -> &[u8] {
+    let excess = bytes.len() % 4;
+    if excess != 0 && bytes[bytes.len() - excess..].iter().all(|b| b.is_ascii_whitespace()) {
+        tracing::debug!("Stripped {} trailing whitespace bytes from raw proof", excess);
+        &bytes[..bytes.len() - excess]
+    } else {
+        bytes
+    }
 }
 
 /// Parse image ID from string array to u32 array
@@ -105,8 +1251,587 @@ fn parse_image_id(image_id_vec: &[String]) -> Result<[u32; 8]> {
     for (i, val_str) in image_id_vec.iter().enumerate() {
         image_id[i] = val_str
             .parse::<u32>()
-            .map_err(|e| Error::InvalidImageId(format!("Invalid value at index {}: {}", i, e)))?;
+            .map_err(|e| Error::InvalidImageId(format!("Invalid value at index {i}: {e}")))?;
     }
 
     Ok(image_id)
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use risc0_zkvm::{FakeReceipt, ReceiptClaim};
+
+    use super::*;
+
+    fn state(config: Config) -> AppState {
+        AppState::new(config)
+    }
+
+    #[actix_rt::test]
+    async fn body_limit_applies_to_every_route() {
+        let config = Config {
+            max_proof_bytes: 64,
+            ..Config::default()
+        };
+        let app =
+            test::init_service(App::new().configure(|cfg| configure_app(cfg, state(config)))).await;
+
+        let body = serde_json::json!({"proof": "A".repeat(500)});
+        for uri in ["/verify", "/verify-file", "/verify-receipt"] {
+            let req = test::TestRequest::post()
+                .uri(uri)
+                .set_json(&body)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 413, "{uri}");
+        }
+    }
+
+    #[actix_rt::test]
+    async fn errors_have_one_shape() {
+        let app = test::init_service(
+            App::new().configure(|cfg| configure_app(cfg, state(Config::default()))),
+        )
+        .await;
+
+        for (language, content_language) in [(None, "en"), (Some("de-DE"), "de")] {
+            let mut req = test::TestRequest::post()
+                .uri("/verify-file")
+                .set_json(serde_json::json!({"proof": "!!"}));
+            if let Some(language) = language {
+                req = req.insert_header((header::ACCEPT_LANGUAGE, language));
+            }
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), 400);
+            assert_eq!(
+                resp.headers().get(header::CONTENT_LANGUAGE).unwrap(),
+                content_language
+            );
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert!(body["code"].is_string(), "{}", body);
+            assert!(body["error"].is_string(), "{}", body);
+            assert!(body["message"].is_string(), "{}", body);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn every_request_is_recorded() {
+        let app = test::init_service(
+            App::new().configure(|cfg| configure_app(cfg, state(Config::default()))),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/no-such-route").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let metrics = test::call_and_read_body(&app, req).await;
+        let metrics = String::from_utf8(metrics.to_vec()).unwrap();
+        assert!(metrics.contains("endpoint=\"unmatched\""), "{}", metrics);
+    }
+
+    #[actix_rt::test]
+    async fn a_full_pool_answers_429() {
+        let config = Config {
+            verify_workers: 1,
+            verify_queue: 0,
+            ..Config::default()
+        };
+        let state = state(config);
+        let pool = state.pool.clone();
+        let app = test::init_service(App::new().configure(|cfg| configure_app(cfg, state))).await;
+
+        // Occupy the only worker until the request has been refused
+        let (release, blocked) = std::sync::mpsc::channel::<()>();
+        let running = {
+            let pool = pool.clone();
+            actix_rt::spawn(async move {
+                pool.run(move || {
+                    blocked.recv().unwrap();
+                    Ok(())
+                })
+                .await
+            })
+        };
+        while pool.stats().in_flight == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+
+        let req = test::TestRequest::post()
+            .uri("/verify/raw?root_hash=00&banned_list_hash=00&compliant=true&image_id=1,2,3,4,5,6,7,8")
+            .set_payload(vec![0xff; 8]);
+        let resp = test::call_service(&app, req.to_request()).await;
+        release.send(()).unwrap();
+        running.await.unwrap().unwrap();
+
+        assert_eq!(resp.status(), 429);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "1");
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "overloaded", "{body}");
+    }
+
+    /// A receipt for `journal` as a prover in `RISC0_DEV_MODE` fakes it
+    fn dev_receipt(journal: Vec<u8>) -> Receipt {
+        let claim = ReceiptClaim::ok([7u32; 8], journal.clone());
+        Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal)
+    }
+
+    /// A dev receipt of `journal` as the little-endian bytes of its words, as sent
+    fn receipt_bytes(journal: Vec<u8>) -> Vec<u8> {
+        let words = risc0_zkvm::serde::to_vec(&dev_receipt(journal)).unwrap();
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    #[actix_rt::test]
+    async fn dev_receipts_are_refused_unless_allowed() {
+        let receipt = dev_receipt(vec![0; 4]);
+        assert_eq!(receipt_kind_name(&receipt), "fake");
+
+        let refused = check_dev_receipt(&receipt, &Config::default());
+        assert!(matches!(refused, Err(Error::DevReceipt(_))));
+        let allowed = Config {
+            allow_dev_receipts: true,
+            ..Config::default()
+        };
+        assert!(check_dev_receipt(&receipt, &allowed).is_ok());
+    }
+
+    /// One journal per layout, as the guests of schema versions 1 to 11 committed it.
+    /// Every hash field is a repeated byte (root 0x11, banned list 0x22, reasons 0x33, list
+    /// info 0x44, salt 0x55, requester 0x66), with 3 proofs of a CPE membership list and
+    /// the real defaults digest. Only the current layout is non-compliant, failing with a
+    /// root mismatch at proof 1 whose key hashes to 0x77 bytes.
+    const JOURNALS: [&str; 11] = [
+        include_str!("../testdata/journals/v1.hex"),
+        include_str!("../testdata/journals/v2.hex"),
+        include_str!("../testdata/journals/v3.hex"),
+        include_str!("../testdata/journals/v4.hex"),
+        include_str!("../testdata/journals/v5.hex"),
+        include_str!("../testdata/journals/v6.hex"),
+        include_str!("../testdata/journals/v7.hex"),
+        include_str!("../testdata/journals/v8.hex"),
+        include_str!("../testdata/journals/v9.hex"),
+        include_str!("../testdata/journals/v10.hex"),
+        include_str!("../testdata/journals/v11.hex"),
+    ];
+
+    fn journal(version: usize) -> Vec<u8> {
+        hex::decode(JOURNALS[version - 1].trim()).unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn every_journal_layout_decodes() {
+        for version in 1..=JOURNALS.len() {
+            let outputs = decode_outputs(&dev_receipt(journal(version))).unwrap();
+            let since = |first: usize| version >= first;
+
+            assert_eq!(outputs.root_hash, [0x11; 32], "v{version}");
+            assert_eq!(outputs.banned_list_hash, [0x22; 32], "v{version}");
+            assert_eq!(outputs.compliant, version < 11, "v{version}");
+            assert_eq!(outputs.proof_count, since(2).then_some(3), "v{version}");
+            assert_eq!(
+                outputs.reasons_hash,
+                since(3).then_some([0x33; 32]),
+                "v{version}"
+            );
+            assert_eq!(
+                outputs.list_info_hash,
+                since(4).then_some([0x44; 32]),
+                "v{version}"
+            );
+            assert_eq!(outputs.input_status, status::OK, "v{version}");
+            assert_eq!(
+                outputs.salt_commitment,
+                since(6).then_some([0x55; 32]),
+                "v{version}"
+            );
+            let key_type = if since(7) {
+                KeyType::Cpe
+            } else {
+                KeyType::Purl
+            };
+            assert_eq!(outputs.key_type, key_type.code(), "v{version}");
+            let proof_type = if since(8) {
+                ProofType::Membership
+            } else {
+                ProofType::NonMembership
+            };
+            assert_eq!(outputs.proof_type, proof_type.code(), "v{version}");
+            assert_eq!(
+                outputs.defaults_digest,
+                since(9).then_some(DEFAULTS_DIGEST),
+                "v{version}"
+            );
+            assert_eq!(
+                outputs.requester_commitment,
+                since(10).then_some([0x66; 32]),
+                "v{version}"
+            );
+            let first_failure = since(11).then(|| crate::models::FirstFailure {
+                reason: sbom_common::failure::name(sbom_common::failure::ROOT_MISMATCH),
+                index: Some(1),
+                key_hash: Some("77".repeat(32)),
+            });
+            assert_eq!(outputs.first_failure, first_failure, "v{version}");
+        }
+
+        // A journal cut short of even the oldest layout decodes as nothing
+        let mut truncated = journal(1);
+        truncated.truncate(truncated.len() - 4);
+        let err = decode_outputs(&dev_receipt(truncated)).unwrap_err();
+        assert!(matches!(err, Error::DeserializationFailed(_)), "{err}");
+    }
+
+    #[actix_rt::test]
+    async fn damaged_proofs_are_diagnosed() {
+        let bytes = receipt_bytes(journal(11));
+        let encode = |bytes: &[u8]| general_purpose::STANDARD.encode(bytes);
+        let intact = encode(&bytes);
+        let wrapped: Vec<&str> = intact
+            .as_bytes()
+            .chunks(76)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect();
+        let size = Some(bytes.len());
+
+        let corpus = [
+            ("intact", intact.clone(), size, None),
+            ("trailing newline", format!("{intact}\n"), size, None),
+            ("line wrapped", wrapped.join("\r\n"), size, None),
+            (
+                "cut mid-quantum",
+                intact[..intact.len() - 3].to_string(),
+                None,
+                Some("truncated_proof"),
+            ),
+            (
+                "cut mid-word",
+                encode(&bytes[..bytes.len() - 1]),
+                None,
+                Some("truncated_proof"),
+            ),
+            (
+                "missing words",
+                encode(&bytes[..bytes.len() - 8]),
+                None,
+                Some("truncated_proof"),
+            ),
+            (
+                "shorter than reported",
+                intact.clone(),
+                Some(bytes.len() + 4),
+                Some("truncated_proof"),
+            ),
+            (
+                "padded",
+                encode(&[bytes.as_slice(), &[0; 4]].concat()),
+                size,
+                Some("padded_proof"),
+            ),
+            (
+                "double encoded",
+                encode(intact.as_bytes()),
+                None,
+                Some("double_encoded_proof"),
+            ),
+            (
+                "stray character",
+                format!("!{}", &intact[1..]),
+                None,
+                Some("invalid_base64"),
+            ),
+        ];
+        for (damage, proof, expected_size, code) in corpus {
+            let result = deserialize_receipt(&proof, expected_size);
+            assert_eq!(result.err().map(|e| e.code()), code, "{damage}");
+        }
+    }
+
+    #[actix_rt::test]
+    async fn expected_list_info_must_match_the_journal() {
+        // RISC Zero only verifies fake receipts in dev mode
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let config = Config {
+            allow_dev_receipts: true,
+            ..Config::default()
+        };
+        let verify = |expected: &str| {
+            let req: VerifyProofRequest = serde_json::from_value(serde_json::json!({
+                "root_hash": "11".repeat(32),
+                "compliant": false,
+                "image_id": ["7", "7", "7", "7", "7", "7", "7", "7"],
+                "generation_duration_ms": null,
+                "expected_list_info_hash": expected,
+                "key_type": "cpe",
+                "proof_type": "membership",
+            }))
+            .unwrap();
+            verify_claims(&req, &dev_receipt(journal(11)), &config, None)
+        };
+
+        let committed = "44".repeat(32);
+        let verified = verify(&committed).unwrap();
+        assert_eq!(verified.list_info_hash, Some(committed));
+
+        let refused = verify(&"45".repeat(32));
+        assert!(
+            matches!(&refused, Err(Error::VerificationFailed(msg)) if msg.contains("List info hash mismatch")),
+            "{:?}",
+            refused.err()
+        );
+    }
+
+    #[actix_rt::test]
+    async fn list_versions_resolve_through_the_catalog() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let config = Config {
+            allow_dev_receipts: true,
+            ..Config::default()
+        };
+        let signed = serde_json::from_str(include_str!("../testdata/catalog.json")).unwrap();
+        let catalog = |max_age| ListCatalog::verify(&signed, b"fixture-catalog-key", max_age);
+        let fresh = catalog(std::time::Duration::MAX).unwrap();
+        // An unblinded commitment to the banned list hash of 2024-06
+        let committed = MerklePublicOutputs {
+            key_type: KeyType::Cpe.code(),
+            ..public_outputs([0x22; 32], [0; 32])
+        };
+        let words = risc0_zkvm::serde::to_vec(&committed).unwrap();
+        let receipt = dev_receipt(words.iter().flat_map(|word| word.to_le_bytes()).collect());
+        let verify = |version: &str, catalog: &ListCatalog| {
+            let req: VerifyProofRequest = serde_json::from_value(serde_json::json!({
+                "root_hash": "01".repeat(32),
+                "compliant": true,
+                "image_id": ["7", "7", "7", "7", "7", "7", "7", "7"],
+                "generation_duration_ms": null,
+                "expected_list_version": version,
+                "key_type": "cpe",
+            }))
+            .unwrap();
+            verify_claims(&req, &receipt, &config, Some(catalog))
+        };
+
+        let verified = verify("2024-06", &fresh).unwrap();
+        assert_eq!(verified.list_version.as_deref(), Some("2024-06"));
+        let refused = verify("2024-05", &fresh);
+        assert!(matches!(refused, Err(Error::VerificationFailed(_))));
+        let refused = verify("2024-07", &fresh);
+        assert!(matches!(refused, Err(Error::UnknownListVersion(_))));
+
+        let stale = catalog(std::time::Duration::ZERO).unwrap();
+        let refused = verify("2024-06", &stale);
+        assert!(matches!(refused, Err(Error::StaleCatalog(_))));
+    }
+
+    #[actix_rt::test]
+    async fn journals_of_a_guest_with_other_defaults_are_refused() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let config = Config {
+            allow_dev_receipts: true,
+            ..Config::default()
+        };
+        let req: VerifyProofRequest = serde_json::from_value(serde_json::json!({
+            "root_hash": "01".repeat(32),
+            "banned_list_hash": "22".repeat(32),
+            "compliant": true,
+            "image_id": ["7", "7", "7", "7", "7", "7", "7", "7"],
+            "generation_duration_ms": null,
+        }))
+        .unwrap();
+        let verify = |defaults_digest| {
+            let committed = MerklePublicOutputs {
+                defaults_digest,
+                ..public_outputs([0x22; 32], [0; 32])
+            };
+            let words = risc0_zkvm::serde::to_vec(&committed).unwrap();
+            let journal = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+            verify_claims(&req, &dev_receipt(journal), &config, None)
+        };
+        assert!(verify(DEFAULTS_DIGEST).is_ok());
+
+        // What a guest rebuilt with one default hash changed commits
+        let mut perturbed = sbom_common::DEFAULTS;
+        perturbed[0][31] ^= 1;
+        let refused = verify(sbom_common::defaults_digest(&perturbed));
+        assert!(
+            matches!(&refused, Err(Error::VerificationFailed(msg)) if msg.contains("Defaults digest"))
+        );
+    }
+
+    #[actix_rt::test]
+    async fn bare_legacy_files_verify_by_default() {
+        // RISC Zero only verifies fake receipts in dev mode; nothing here relies on it off
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let config = Config {
+            allow_dev_receipts: true,
+            ..Config::default()
+        };
+        // LEGACY_PROOFS defaults to accept
+        assert!(config.accept_bare_proofs);
+        let app =
+            test::init_service(App::new().configure(|cfg| configure_app(cfg, state(config)))).await;
+
+        // Files from before the envelope carry only what these two layouts commit
+        for version in [1, 2] {
+            // A file as the proving-service wrote it before the envelope
+            let file = serde_json::json!({
+                "root_hash": "11".repeat(32),
+                "banned_list_hash": "22".repeat(32),
+                "compliant": true,
+                "image_id": ["7", "7", "7", "7", "7", "7", "7", "7"],
+                "proof": general_purpose::STANDARD.encode(receipt_bytes(journal(version))),
+            });
+            let req = test::TestRequest::post()
+                .uri("/verify-file")
+                .set_json(&file)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 200, "v{version}");
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["proof_verified"], true, "v{version}: {body}");
+            assert_eq!(body["tampered"], false, "v{version}: {body}");
+            assert_eq!(body["receipt_kind"], "fake", "v{version}");
+        }
+    }
+
+    /// A current journal committing `banned_list_hash` and `salt_commitment`
+    fn public_outputs(
+        banned_list_hash: [u8; 32],
+        salt_commitment: [u8; 32],
+    ) -> MerklePublicOutputs {
+        MerklePublicOutputs {
+            root_hash: [1; 32],
+            banned_list_hash,
+            compliant: true,
+            proof_count: 2,
+            reasons_hash: [0; 32],
+            list_info_hash: [0; 32],
+            input_status: status::OK,
+            salt_commitment,
+            key_type: KeyType::Purl.code(),
+            proof_type: ProofType::NonMembership.code(),
+            defaults_digest: sbom_common::DEFAULTS_DIGEST,
+            requester_commitment: [0; 32],
+            first_failure: sbom_common::failure::NONE,
+            first_failure_index: 0,
+            first_failure_key_hash: [0; 32],
+        }
+    }
+
+    fn outputs(banned_list_hash: [u8; 32], salt_commitment: [u8; 32]) -> DecodedOutputs {
+        DecodedOutputs::from(public_outputs(banned_list_hash, salt_commitment))
+    }
+
+    #[actix_rt::test]
+    async fn every_edited_file_field_is_reported() {
+        let outputs = outputs([0x22; 32], [0; 32]);
+        let consistent = serde_json::json!({
+            "root_hash": format!("0x{}", "01".repeat(32)),
+            "banned_list_hash": "22".repeat(32).to_uppercase(),
+            "compliant": true,
+            "image_id": ["7", "7", "7", "7", "7", "7", "7", "7"],
+            "proof_count": 2,
+            "proof_size": 100,
+            "reasons_hash": "00".repeat(32),
+            "key_type": "purl",
+            "proof_type": "non_membership",
+            "defaults_digest": hex::encode(sbom_common::DEFAULTS_DIGEST),
+        });
+        let inconsistent = |file: &serde_json::Value| -> Vec<&'static str> {
+            let file: ProofFile = serde_json::from_value(file.clone()).unwrap();
+            let found = file_consistency(&file, &outputs, Some(100));
+            found.iter().map(|found| found.field).collect()
+        };
+        assert_eq!(inconsistent(&consistent), Vec::<&str>::new());
+
+        let list_info = serde_json::json!({
+            "source_uri": "https://example.org/banned.json",
+            "publisher": "example",
+            "version": "1",
+            "entry_count": 1,
+        });
+        for (field, value) in [
+            ("root_hash", serde_json::json!("02".repeat(32))),
+            ("banned_list_hash", serde_json::json!("23".repeat(32))),
+            ("compliant", serde_json::json!(false)),
+            ("proof_count", serde_json::json!(3)),
+            ("proof_size", serde_json::json!(99)),
+            ("reasons_hash", serde_json::json!("33".repeat(32))),
+            ("list_info_hash", serde_json::json!("44".repeat(32))),
+            ("list_info", list_info),
+            ("salt_commitment", serde_json::json!("55".repeat(32))),
+            ("key_type", serde_json::json!("cpe")),
+            ("proof_type", serde_json::json!("membership")),
+            ("defaults_digest", serde_json::json!("00".repeat(32))),
+            ("requester_commitment", serde_json::json!("66".repeat(32))),
+        ] {
+            let mut edited = consistent.clone();
+            edited[field] = value;
+            assert_eq!(inconsistent(&edited), [field]);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn verified_files_with_edited_fields_are_tampered() {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let config = Config {
+            allow_dev_receipts: true,
+            ..Config::default()
+        };
+        let app =
+            test::init_service(App::new().configure(|cfg| configure_app(cfg, state(config)))).await;
+
+        // The receipt commits root 0x11 and is not compliant
+        let file = serde_json::json!({
+            "root_hash": "12".repeat(32),
+            "banned_list_hash": "22".repeat(32),
+            "compliant": true,
+            "image_id": ["7", "7", "7", "7", "7", "7", "7", "7"],
+            "proof": general_purpose::STANDARD.encode(receipt_bytes(journal(1))),
+        });
+        let req = test::TestRequest::post()
+            .uri("/verify-file")
+            .set_json(&file)
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["proof_verified"], true, "{body}");
+        assert_eq!(body["tampered"], true, "{body}");
+        assert_eq!(body["root_hash"], "11".repeat(32));
+        let inconsistency = &body["file_consistency"][0];
+        assert_eq!(inconsistency["field"], "root_hash", "{body}");
+        assert_eq!(inconsistency["file_value"], "12".repeat(32));
+        assert_eq!(body["file_consistency"].as_array().unwrap().len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn revealed_salts_must_match_the_commitment() {
+        let list = ["pkg:npm/a@1".to_string(), "pkg:npm/b@2".to_string()];
+        let salt = [9u8; 32];
+        let committed = compute_salt_commitment(&salt);
+        let blinded = outputs(
+            compute_banned_list_commitment(&list, Some(&salt)),
+            committed,
+        );
+        let check = |salt: Option<&str>, outputs: &DecodedOutputs| {
+            let commitment = outputs.salt_commitment.filter(|hash| *hash != [0; 32]);
+            check_revealed_list(&list, salt, outputs, commitment)
+        };
+
+        assert!(check(Some(&hex::encode(salt)), &blinded).is_ok());
+        for salt in [Some(hex::encode([8u8; 32])), None] {
+            let refused = check(salt.as_deref(), &blinded);
+            assert!(
+                matches!(refused, Err(Error::VerificationFailed(_))),
+                "{salt:?}"
+            );
+        }
+        let refused = check(Some("not hex"), &blinded);
+        assert!(matches!(refused, Err(Error::InvalidProof(_))));
+
+        // A salt revealed for a list that was never blinded is refused as well
+        let plain = outputs(compute_banned_list_hash(&list), [0; 32]);
+        assert!(check(None, &plain).is_ok());
+        let refused = check(Some(&hex::encode(salt)), &plain);
+        assert!(matches!(refused, Err(Error::VerificationFailed(_))));
+    }
+}
@@ -1,7 +1,11 @@
+pub mod catalog;
 pub mod config;
+pub mod cors;
 pub mod error;
+pub mod fetch;
 pub mod handlers;
 pub mod models;
+pub mod pool;
 
 pub use error::{Error, Result};
 
@@ -1,6 +1,8 @@
-use actix_web::{middleware, web, App, HttpServer};
+use actix_web::{App, HttpServer};
+use std::time::SystemTime;
 use tracing_subscriber::filter::EnvFilter;
-use verifier_service::{config::Config, handlers};
+use verifier_service::catalog::ListCatalog;
+use verifier_service::{config::Config, cors, handlers};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -11,14 +13,66 @@ async fn main() -> std::io::Result<()> {
     let config = Config::from_env();
     
     tracing::info!("Starting verifier-service on port {}", config.port);
+    tracing::info!(
+        "Verifying with {} worker(s), queueing up to {} request(s)",
+        config.verify_workers,
+        config.verify_queue
+    );
+    if config.allow_dev_receipts {
+        tracing::warn!("ALLOW_DEV_RECEIPTS is set: fake dev-mode receipts are accepted");
+        // RISC Zero itself only verifies fake receipts in dev mode
+        if !risc0_zkvm::is_dev_mode() {
+            tracing::warn!("RISC0_DEV_MODE is not set, so dev-mode receipts still fail to verify");
+        }
+    }
+    cors::policy(&config).map_err(|err_msg| {
+        tracing::error!("{}", err_msg);
+        std::io::Error::other(err_msg)
+    })?;
+    if !config.cors_allowed_origins.is_empty() {
+        tracing::info!(
+            "Cross-origin requests are allowed from {}",
+            config.cors_allowed_origins.join(", ")
+        );
+    }
     
-    HttpServer::new(|| {
-        App::new()
-            .wrap(middleware::Logger::default())
-            .route("/health", web::get().to(handlers::health))
-            .route("/verify", web::post().to(handlers::verify))
+    let catalog = match &config.list_catalog {
+        Some(path) => {
+            let key = config
+                .list_catalog_key
+                .as_deref()
+                .ok_or("LIST_CATALOG is set but LIST_CATALOG_KEY is not")
+                .and_then(|key| hex::decode(key).map_err(|_| "LIST_CATALOG_KEY must be hex"))
+                .map_err(std::io::Error::other)?;
+            let catalog = ListCatalog::load(path, &key, config.list_catalog_max_age)
+                .map_err(std::io::Error::other)?;
+            tracing::info!(
+                "Loaded list catalog from {} with {} version(s)",
+                catalog.publisher(),
+                catalog.len()
+            );
+            if catalog.is_stale(SystemTime::now()) {
+                tracing::warn!(
+                    "List catalog is older than {}s; list versions are refused until it is refreshed",
+                    config.list_catalog_max_age.as_secs()
+                );
+            }
+            Some(catalog)
+        }
+        None => None,
+    };
+
+    let port = config.port;
+    let mut state = handlers::AppState::new(config);
+    if let Some(catalog) = catalog {
+        state = state.with_catalog(catalog);
+    }
+
+    HttpServer::new(move || {
+        let state = state.clone();
+        App::new().configure(|cfg| handlers::configure_app(cfg, state))
     })
-    .bind(("0.0.0.0", config.port))?
+    .bind(("0.0.0.0", port))?
     .run()
     .await
 }
@@ -1,13 +1,79 @@
+use sbom_common::api_schema::ApiSchema;
+use sbom_common::input::status;
+use sbom_common::{failure, KeyType, ProofType};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// Bump whenever a request or response model below changes shape, then regenerate the
+/// definitions in `docs/api` (see the `api-schema` binary).
+pub const API_SCHEMA_VERSION: u32 = 8;
+
+/// The HTTP request and response models, as served at `GET /schema`
+pub fn api_schema() -> ApiSchema {
+    ApiSchema::new("verifier-service", API_SCHEMA_VERSION)
+        .with::<VerifyProofRequest>()
+        .with::<VerifyRawQuery>()
+        .with::<VerifyProofResponse>()
+        .with::<ProofFile>()
+        .with::<VerifyFileResponse>()
+        .with::<VerifyReceiptRequest>()
+        .with::<VerifyReceiptResponse>()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct VerifyProofRequest {
+    #[serde(default)]
     pub root_hash: String,
+    /// Named root resolved through this verifier's alias registry
+    #[serde(default)]
+    pub root_alias: Option<String>,
+    /// May be omitted when `banned_list` is revealed or `expected_list_version` is given instead
+    #[serde(default)]
     pub banned_list_hash: String,
+    /// Published list version, resolved to a banned_list_hash through the signed list catalog
+    #[serde(default)]
+    pub expected_list_version: Option<String>,
     pub compliant: bool,
+    /// May be omitted with `proof_url`
+    #[serde(default)]
     pub image_id: Vec<String>,
+    /// Base64 receipt; omitted when `proof_url` is given instead
+    #[serde(default)]
     pub proof: String,
+    /// HTTPS URL of a proof file as persisted by the proving-service, fetched instead of
+    /// sending `proof`. The file supplies the receipt, and `image_id`, `root_hash`,
+    /// `banned_list_hash` and `proof_size` where the request leaves them out
+    #[serde(default)]
+    pub proof_url: Option<String>,
     pub generation_duration_ms: Option<u64>,
+    /// Reject receipts that committed fewer proofs than this
+    #[serde(default)]
+    pub expected_min_proof_count: Option<u32>,
+    /// Hex commitment to the (leaf_index, value) pairs the receipt must contain
+    #[serde(default)]
+    pub expected_reasons_hash: Option<String>,
+    /// Hex commitment to the banned list provenance (list_info) the receipt must contain
+    #[serde(default)]
+    pub expected_list_info_hash: Option<String>,
+    /// Receipt size in bytes as reported by the prover, used to diagnose truncation
+    #[serde(default)]
+    pub proof_size: Option<usize>,
+    /// Revealed banned list, e.g. the `purls` of the proof: the journal's banned_list_hash
+    /// is recomputed from these keys, in order, so `banned_list_hash` may then be omitted
+    #[serde(default)]
+    pub banned_list: Option<Vec<String>>,
+    /// Revealed hex salt that blinded the banned list commitment; requires `banned_list`
+    #[serde(default)]
+    pub banned_list_salt: Option<String>,
+    /// Kind of identifier the banned list is keyed by; the journal must state the same
+    #[serde(default)]
+    pub key_type: KeyType,
+    /// Statement `compliant` is claimed to attest; the journal must state the same
+    #[serde(default)]
+    pub proof_type: ProofType,
+    /// Hex commitment to the API key id the proof must be bound to
+    #[serde(default)]
+    pub expected_requester_commitment: Option<String>,
 }
 
 impl VerifyProofRequest {
@@ -16,6 +82,36 @@ impl VerifyProofRequest {
         if self.proof.is_empty() {
             return Err("Proof cannot be empty".to_string());
         }
+        self.validate_claims()
+    }
+
+    /// Take the receipt of the proof file fetched from `proof_url`, and the claims the
+    /// request leaves out from the file
+    pub fn fill_from_file(&mut self, file: ProofFile) -> Result<(), String> {
+        if !self.proof.is_empty() {
+            return Err("Send either proof or proof_url, not both".to_string());
+        }
+        self.proof = file.proof.ok_or(
+            "the fetched file links its receipt instead of embedding it; fetch the receipt and use /verify/raw",
+        )?;
+        if self.image_id.is_empty() {
+            self.image_id = file.image_id;
+        }
+        if self.root_hash.is_empty() && self.root_alias.is_none() {
+            self.root_hash = file.root_hash;
+        }
+        if self.banned_list_hash.is_empty()
+            && self.banned_list.is_none()
+            && self.expected_list_version.is_none()
+        {
+            self.banned_list_hash = file.banned_list_hash;
+        }
+        self.proof_size = self.proof_size.or(file.proof_size);
+        Ok(())
+    }
+
+    /// Validate everything except the proof itself, which `/verify/raw` carries in the body
+    pub fn validate_claims(&self) -> Result<(), String> {
         if self.image_id.is_empty() {
             return Err("Image ID cannot be empty".to_string());
         }
@@ -25,29 +121,690 @@ impl VerifyProofRequest {
                 self.image_id.len()
             ));
         }
-        if self.root_hash.is_empty() {
-            return Err("Root hash cannot be empty".to_string());
+        if self.root_hash.is_empty() && self.root_alias.is_none() {
+            return Err("Either root_hash or root_alias must be provided".to_string());
+        }
+        if self.banned_list_hash.is_empty() && self.banned_list.is_none() && self.expected_list_version.is_none() {
+            return Err(
+                "One of banned_list_hash, banned_list or expected_list_version must be provided".to_string(),
+            );
         }
-        if self.banned_list_hash.is_empty() {
-            return Err("Banned list hash cannot be empty".to_string());
+        if self.banned_list_salt.is_some() && self.banned_list.is_none() {
+            return Err("banned_list_salt can only be checked together with banned_list".to_string());
         }
         Ok(())
     }
 }
 
+/// Query parameters of `/verify/raw`: the claims of `VerifyProofRequest`, with the
+/// receipt sent as the raw request body instead of base64 in JSON
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct VerifyRawQuery {
+    #[serde(default)]
+    pub root_hash: String,
+    #[serde(default)]
+    pub root_alias: Option<String>,
+    #[serde(default)]
+    pub banned_list_hash: String,
+    #[serde(default)]
+    pub expected_list_version: Option<String>,
+    pub compliant: bool,
+    /// Comma-separated image ID words
+    pub image_id: String,
+    #[serde(default)]
+    pub generation_duration_ms: Option<u64>,
+    #[serde(default)]
+    pub expected_min_proof_count: Option<u32>,
+    #[serde(default)]
+    pub expected_reasons_hash: Option<String>,
+    #[serde(default)]
+    pub expected_list_info_hash: Option<String>,
+    #[serde(default)]
+    pub proof_size: Option<usize>,
+    #[serde(default)]
+    pub key_type: KeyType,
+    #[serde(default)]
+    pub proof_type: ProofType,
+    #[serde(default)]
+    pub expected_requester_commitment: Option<String>,
+}
+
+impl From<VerifyRawQuery> for VerifyProofRequest {
+    fn from(query: VerifyRawQuery) -> Self {
+        Self {
+            root_hash: query.root_hash,
+            root_alias: query.root_alias,
+            banned_list_hash: query.banned_list_hash,
+            expected_list_version: query.expected_list_version,
+            compliant: query.compliant,
+            image_id: query
+                .image_id
+                .split(',')
+                .map(|word| word.trim().to_string())
+                .filter(|word| !word.is_empty())
+                .collect(),
+            proof: String::new(),
+            proof_url: None,
+            generation_duration_ms: query.generation_duration_ms,
+            expected_min_proof_count: query.expected_min_proof_count,
+            expected_reasons_hash: query.expected_reasons_hash,
+            expected_list_info_hash: query.expected_list_info_hash,
+            proof_size: query.proof_size,
+            banned_list: None,
+            banned_list_salt: None,
+            key_type: query.key_type,
+            proof_type: query.proof_type,
+            expected_requester_commitment: query.expected_requester_commitment,
+        }
+    }
+}
+
+/// A proof record as persisted by the proving-service (`proof_<timestamp>.json`).
+/// Only the fields that can be checked against the journal are read.
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct ProofFile {
+    pub root_hash: String,
+    pub banned_list_hash: String,
+    pub compliant: bool,
+    pub image_id: Vec<String>,
+    /// Base64 receipt; absent when the receipt was stored separately and linked
+    #[serde(default)]
+    pub proof: Option<String>,
+    #[serde(default)]
+    pub proof_count: Option<u32>,
+    #[serde(default)]
+    pub proof_size: Option<usize>,
+    #[serde(default)]
+    pub reasons_hash: Option<String>,
+    #[serde(default)]
+    pub list_info: Option<ProofFileListInfo>,
+    #[serde(default)]
+    pub list_info_hash: Option<String>,
+    #[serde(default)]
+    pub salt_commitment: Option<String>,
+    /// Absent in files written before non-purl keys, which are purl lists
+    #[serde(default)]
+    pub key_type: KeyType,
+    /// Absent in files written before membership proofs, which are non-membership proofs
+    #[serde(default)]
+    pub proof_type: ProofType,
+    /// Absent in files written before the guest committed its default hash table
+    #[serde(default)]
+    pub defaults_digest: Option<String>,
+    /// Absent in files of unsigned requests and files written before proofs were bound
+    /// to their requester
+    #[serde(default)]
+    pub requester_commitment: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct ProofFileListInfo {
+    pub source_uri: String,
+    pub publisher: String,
+    pub version: String,
+    pub entry_count: u64,
+}
+
+/// A metadata field of a proof file that disagrees with the journal of its own receipt
+#[derive(Serialize, Debug, PartialEq, Eq, JsonSchema)]
+pub struct FileInconsistency {
+    pub field: &'static str,
+    pub file_value: String,
+    pub journal_value: String,
+}
+
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct VerifyFileResponse {
+    /// The receipt verifies against the file's image ID
+    pub proof_verified: bool,
+    /// The receipt verifies, but the file's metadata does not describe it
+    pub tampered: bool,
+    pub file_consistency: Vec<FileInconsistency>,
+    /// Values decoded from the journal, which are authoritative over the file's
+    pub root_hash: String,
+    pub banned_list_hash: String,
+    pub compliant: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_count: Option<u32>,
+    /// Kind of the receipt that verified: "composite", "succinct", "groth16" or "fake"
+    pub receipt_kind: &'static str,
+    pub image_id: Vec<String>,
+}
+
+/// Body of `/verify-receipt`: only the receipt and the image ID it must verify against.
+/// Nothing about the journal is expected; its outputs are returned instead
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct VerifyReceiptRequest {
+    /// Base64 receipt
+    pub proof: String,
+    pub image_id: Vec<String>,
+}
+
+/// Everything the journal of a verified receipt states, decoded without comparing it to
+/// any expectation. Fields the journal's layout predates are absent
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct VerifyReceiptResponse {
+    /// The receipt verifies against the image ID
+    pub proof_verified: bool,
+    pub root_hash: String,
+    pub banned_list_hash: String,
+    pub compliant: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasons_hash: Option<String>,
+    /// Present only when the journal committed provenance for the banned list
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_info_hash: Option<String>,
+    /// Present only when the journal's banned_list_hash is blinded with a salt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salt_commitment: Option<String>,
+    /// Kind of identifier the journal states the banned list is keyed by
+    pub key_type: &'static str,
+    /// Statement the journal states `compliant` attests
+    pub proof_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub defaults_digest: Option<String>,
+    /// Present only when the proof is bound to the API key of a signed request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requester_commitment: Option<String>,
+    /// Why the proofs are not compliant; absent when they are and for journals that
+    /// predate first_failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_failure: Option<FirstFailure>,
+    /// Kind of the receipt that verified: "composite", "succinct", "groth16" or "fake"
+    pub receipt_kind: &'static str,
+    pub image_id: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct MerklePublicOutputs {
     pub root_hash: [u8; 32],
     pub banned_list_hash: [u8; 32],
     pub compliant: bool,
+    pub proof_count: u32,
+    pub reasons_hash: [u8; 32],
+    pub list_info_hash: [u8; 32],
+    pub input_status: u32,
+    pub salt_commitment: [u8; 32],
+    pub key_type: u32,
+    pub proof_type: u32,
+    pub defaults_digest: [u8; 32],
+    pub requester_commitment: [u8; 32],
+    pub first_failure: u32,
+    pub first_failure_index: u32,
+    pub first_failure_key_hash: [u8; 32],
+}
+
+/// Journal committed by guests that predate the `first_failure` fields (schema version 10)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerklePublicOutputsV10 {
+    pub root_hash: [u8; 32],
+    pub banned_list_hash: [u8; 32],
+    pub compliant: bool,
+    pub proof_count: u32,
+    pub reasons_hash: [u8; 32],
+    pub list_info_hash: [u8; 32],
+    pub input_status: u32,
+    pub salt_commitment: [u8; 32],
+    pub key_type: u32,
+    pub proof_type: u32,
+    pub defaults_digest: [u8; 32],
+    pub requester_commitment: [u8; 32],
+}
+
+/// Journal committed by guests that predate `requester_commitment` (schema version 9)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerklePublicOutputsV9 {
+    pub root_hash: [u8; 32],
+    pub banned_list_hash: [u8; 32],
+    pub compliant: bool,
+    pub proof_count: u32,
+    pub reasons_hash: [u8; 32],
+    pub list_info_hash: [u8; 32],
+    pub input_status: u32,
+    pub salt_commitment: [u8; 32],
+    pub key_type: u32,
+    pub proof_type: u32,
+    pub defaults_digest: [u8; 32],
+}
+
+/// Journal committed by guests that predate `defaults_digest` (schema version 8)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerklePublicOutputsV8 {
+    pub root_hash: [u8; 32],
+    pub banned_list_hash: [u8; 32],
+    pub compliant: bool,
+    pub proof_count: u32,
+    pub reasons_hash: [u8; 32],
+    pub list_info_hash: [u8; 32],
+    pub input_status: u32,
+    pub salt_commitment: [u8; 32],
+    pub key_type: u32,
+    pub proof_type: u32,
+}
+
+/// Journal committed by guests that predate `proof_type` (schema version 7)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerklePublicOutputsV7 {
+    pub root_hash: [u8; 32],
+    pub banned_list_hash: [u8; 32],
+    pub compliant: bool,
+    pub proof_count: u32,
+    pub reasons_hash: [u8; 32],
+    pub list_info_hash: [u8; 32],
+    pub input_status: u32,
+    pub salt_commitment: [u8; 32],
+    pub key_type: u32,
+}
+
+/// Journal committed by guests that predate `key_type` (schema version 6)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerklePublicOutputsV6 {
+    pub root_hash: [u8; 32],
+    pub banned_list_hash: [u8; 32],
+    pub compliant: bool,
+    pub proof_count: u32,
+    pub reasons_hash: [u8; 32],
+    pub list_info_hash: [u8; 32],
+    pub input_status: u32,
+    pub salt_commitment: [u8; 32],
+}
+
+/// Journal committed by guests that predate `salt_commitment` (schema version 5)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerklePublicOutputsV5 {
+    pub root_hash: [u8; 32],
+    pub banned_list_hash: [u8; 32],
+    pub compliant: bool,
+    pub proof_count: u32,
+    pub reasons_hash: [u8; 32],
+    pub list_info_hash: [u8; 32],
+    pub input_status: u32,
+}
+
+/// Journal committed by guests that predate `input_status` (schema version 4)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerklePublicOutputsV4 {
+    pub root_hash: [u8; 32],
+    pub banned_list_hash: [u8; 32],
+    pub compliant: bool,
+    pub proof_count: u32,
+    pub reasons_hash: [u8; 32],
+    pub list_info_hash: [u8; 32],
+}
+
+/// Journal committed by guests that predate `list_info_hash` (schema version 3)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerklePublicOutputsV3 {
+    pub root_hash: [u8; 32],
+    pub banned_list_hash: [u8; 32],
+    pub compliant: bool,
+    pub proof_count: u32,
+    pub reasons_hash: [u8; 32],
+}
+
+/// Journal committed by guests that predate `reasons_hash` (schema version 2)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerklePublicOutputsV2 {
+    pub root_hash: [u8; 32],
+    pub banned_list_hash: [u8; 32],
+    pub compliant: bool,
+    pub proof_count: u32,
+}
+
+/// Journal committed by guests that predate `proof_count` (schema version 1)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerklePublicOutputsV1 {
+    pub root_hash: [u8; 32],
+    pub banned_list_hash: [u8; 32],
+    pub compliant: bool,
+}
+
+/// Journal contents normalized across schema versions
+#[derive(Debug)]
+pub struct DecodedOutputs {
+    pub root_hash: [u8; 32],
+    pub banned_list_hash: [u8; 32],
+    pub compliant: bool,
+    /// `None` for legacy journals that did not commit a proof count
+    pub proof_count: Option<u32>,
+    /// `None` for legacy journals that did not commit a reasons hash
+    pub reasons_hash: Option<[u8; 32]>,
+    /// `None` for legacy journals that did not commit a list info hash
+    pub list_info_hash: Option<[u8; 32]>,
+    /// Always `status::OK` for legacy journals, whose guests had no input framing to reject
+    pub input_status: u32,
+    /// `None` for legacy journals, whose guests could not blind the banned list
+    pub salt_commitment: Option<[u8; 32]>,
+    /// `KeyType::code`; always purl for legacy journals, whose guests only bound purls
+    pub key_type: u32,
+    /// `ProofType::code`; always non-membership for legacy journals, whose guests could
+    /// only attest absence
+    pub proof_type: u32,
+    /// `None` for legacy journals, whose guests did not commit their default hash table
+    pub defaults_digest: Option<[u8; 32]>,
+    /// `None` for legacy journals, whose guests could not bind a proof to its requester;
+    /// all zeros for proofs of unsigned requests
+    pub requester_commitment: Option<[u8; 32]>,
+    /// `None` when the proofs were compliant, and for legacy journals, whose guests did
+    /// not say why they were not
+    pub first_failure: Option<FirstFailure>,
+}
+
+impl From<MerklePublicOutputs> for DecodedOutputs {
+    fn from(outputs: MerklePublicOutputs) -> Self {
+        Self {
+            root_hash: outputs.root_hash,
+            banned_list_hash: outputs.banned_list_hash,
+            compliant: outputs.compliant,
+            proof_count: Some(outputs.proof_count),
+            reasons_hash: Some(outputs.reasons_hash),
+            list_info_hash: Some(outputs.list_info_hash),
+            input_status: outputs.input_status,
+            salt_commitment: Some(outputs.salt_commitment),
+            key_type: outputs.key_type,
+            proof_type: outputs.proof_type,
+            defaults_digest: Some(outputs.defaults_digest),
+            requester_commitment: Some(outputs.requester_commitment),
+            first_failure: FirstFailure::from_journal(
+                outputs.first_failure,
+                outputs.first_failure_index,
+                outputs.first_failure_key_hash,
+            ),
+        }
+    }
+}
+
+impl From<MerklePublicOutputsV10> for DecodedOutputs {
+    fn from(outputs: MerklePublicOutputsV10) -> Self {
+        Self {
+            root_hash: outputs.root_hash,
+            banned_list_hash: outputs.banned_list_hash,
+            compliant: outputs.compliant,
+            proof_count: Some(outputs.proof_count),
+            reasons_hash: Some(outputs.reasons_hash),
+            list_info_hash: Some(outputs.list_info_hash),
+            input_status: outputs.input_status,
+            salt_commitment: Some(outputs.salt_commitment),
+            key_type: outputs.key_type,
+            proof_type: outputs.proof_type,
+            defaults_digest: Some(outputs.defaults_digest),
+            requester_commitment: Some(outputs.requester_commitment),
+            first_failure: None,
+        }
+    }
+}
+
+impl From<MerklePublicOutputsV9> for DecodedOutputs {
+    fn from(outputs: MerklePublicOutputsV9) -> Self {
+        Self {
+            root_hash: outputs.root_hash,
+            banned_list_hash: outputs.banned_list_hash,
+            compliant: outputs.compliant,
+            proof_count: Some(outputs.proof_count),
+            reasons_hash: Some(outputs.reasons_hash),
+            list_info_hash: Some(outputs.list_info_hash),
+            input_status: outputs.input_status,
+            salt_commitment: Some(outputs.salt_commitment),
+            key_type: outputs.key_type,
+            proof_type: outputs.proof_type,
+            defaults_digest: Some(outputs.defaults_digest),
+            requester_commitment: None,
+            first_failure: None,
+        }
+    }
 }
 
-#[derive(Serialize, Debug)]
+impl From<MerklePublicOutputsV8> for DecodedOutputs {
+    fn from(outputs: MerklePublicOutputsV8) -> Self {
+        Self {
+            root_hash: outputs.root_hash,
+            banned_list_hash: outputs.banned_list_hash,
+            compliant: outputs.compliant,
+            proof_count: Some(outputs.proof_count),
+            reasons_hash: Some(outputs.reasons_hash),
+            list_info_hash: Some(outputs.list_info_hash),
+            input_status: outputs.input_status,
+            salt_commitment: Some(outputs.salt_commitment),
+            key_type: outputs.key_type,
+            proof_type: outputs.proof_type,
+            defaults_digest: None,
+            requester_commitment: None,
+            first_failure: None,
+        }
+    }
+}
+
+impl From<MerklePublicOutputsV7> for DecodedOutputs {
+    fn from(outputs: MerklePublicOutputsV7) -> Self {
+        Self {
+            root_hash: outputs.root_hash,
+            banned_list_hash: outputs.banned_list_hash,
+            compliant: outputs.compliant,
+            proof_count: Some(outputs.proof_count),
+            reasons_hash: Some(outputs.reasons_hash),
+            list_info_hash: Some(outputs.list_info_hash),
+            input_status: outputs.input_status,
+            salt_commitment: Some(outputs.salt_commitment),
+            key_type: outputs.key_type,
+            proof_type: ProofType::NonMembership.code(),
+            defaults_digest: None,
+            requester_commitment: None,
+            first_failure: None,
+        }
+    }
+}
+
+impl From<MerklePublicOutputsV6> for DecodedOutputs {
+    fn from(outputs: MerklePublicOutputsV6) -> Self {
+        Self {
+            root_hash: outputs.root_hash,
+            banned_list_hash: outputs.banned_list_hash,
+            compliant: outputs.compliant,
+            proof_count: Some(outputs.proof_count),
+            reasons_hash: Some(outputs.reasons_hash),
+            list_info_hash: Some(outputs.list_info_hash),
+            input_status: outputs.input_status,
+            salt_commitment: Some(outputs.salt_commitment),
+            key_type: KeyType::Purl.code(),
+            proof_type: ProofType::NonMembership.code(),
+            defaults_digest: None,
+            requester_commitment: None,
+            first_failure: None,
+        }
+    }
+}
+
+impl From<MerklePublicOutputsV5> for DecodedOutputs {
+    fn from(outputs: MerklePublicOutputsV5) -> Self {
+        Self {
+            root_hash: outputs.root_hash,
+            banned_list_hash: outputs.banned_list_hash,
+            compliant: outputs.compliant,
+            proof_count: Some(outputs.proof_count),
+            reasons_hash: Some(outputs.reasons_hash),
+            list_info_hash: Some(outputs.list_info_hash),
+            input_status: outputs.input_status,
+            salt_commitment: None,
+            key_type: KeyType::Purl.code(),
+            proof_type: ProofType::NonMembership.code(),
+            defaults_digest: None,
+            requester_commitment: None,
+            first_failure: None,
+        }
+    }
+}
+
+impl From<MerklePublicOutputsV4> for DecodedOutputs {
+    fn from(outputs: MerklePublicOutputsV4) -> Self {
+        Self {
+            root_hash: outputs.root_hash,
+            banned_list_hash: outputs.banned_list_hash,
+            compliant: outputs.compliant,
+            proof_count: Some(outputs.proof_count),
+            reasons_hash: Some(outputs.reasons_hash),
+            list_info_hash: Some(outputs.list_info_hash),
+            input_status: status::OK,
+            salt_commitment: None,
+            key_type: KeyType::Purl.code(),
+            proof_type: ProofType::NonMembership.code(),
+            defaults_digest: None,
+            requester_commitment: None,
+            first_failure: None,
+        }
+    }
+}
+
+impl From<MerklePublicOutputsV3> for DecodedOutputs {
+    fn from(outputs: MerklePublicOutputsV3) -> Self {
+        Self {
+            root_hash: outputs.root_hash,
+            banned_list_hash: outputs.banned_list_hash,
+            compliant: outputs.compliant,
+            proof_count: Some(outputs.proof_count),
+            reasons_hash: Some(outputs.reasons_hash),
+            list_info_hash: None,
+            input_status: status::OK,
+            salt_commitment: None,
+            key_type: KeyType::Purl.code(),
+            proof_type: ProofType::NonMembership.code(),
+            defaults_digest: None,
+            requester_commitment: None,
+            first_failure: None,
+        }
+    }
+}
+
+impl From<MerklePublicOutputsV2> for DecodedOutputs {
+    fn from(outputs: MerklePublicOutputsV2) -> Self {
+        Self {
+            root_hash: outputs.root_hash,
+            banned_list_hash: outputs.banned_list_hash,
+            compliant: outputs.compliant,
+            proof_count: Some(outputs.proof_count),
+            reasons_hash: None,
+            list_info_hash: None,
+            input_status: status::OK,
+            salt_commitment: None,
+            key_type: KeyType::Purl.code(),
+            proof_type: ProofType::NonMembership.code(),
+            defaults_digest: None,
+            requester_commitment: None,
+            first_failure: None,
+        }
+    }
+}
+
+impl From<MerklePublicOutputsV1> for DecodedOutputs {
+    fn from(outputs: MerklePublicOutputsV1) -> Self {
+        Self {
+            root_hash: outputs.root_hash,
+            banned_list_hash: outputs.banned_list_hash,
+            compliant: outputs.compliant,
+            proof_count: None,
+            reasons_hash: None,
+            list_info_hash: None,
+            input_status: status::OK,
+            salt_commitment: None,
+            key_type: KeyType::Purl.code(),
+            proof_type: ProofType::NonMembership.code(),
+            defaults_digest: None,
+            requester_commitment: None,
+            first_failure: None,
+        }
+    }
+}
+
+/// The first proof the guest found keeping the proofs from being compliant
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+pub struct FirstFailure {
+    /// `sbom_common::failure::name` of the committed code, e.g. "bad_hex" or "root_mismatch"
+    pub reason: &'static str,
+    /// Position of the failing proof in the proven request; absent when no single proof
+    /// failed, as for unparsable proofs or a strict-mode list mismatch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+    /// Leaf index of the failing proof's key (`compute_purl_hash` for a purl); absent
+    /// with `index` or when the key maps to no leaf
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_hash: Option<String>,
+}
+
+impl FirstFailure {
+    /// The failure the journal's `first_failure` fields state, if any
+    pub fn from_journal(code: u32, index: u32, key_hash: [u8; 32]) -> Option<Self> {
+        if code == failure::NONE {
+            return None;
+        }
+        let names_proof = failure::names_proof(code);
+        Some(Self {
+            reason: failure::name(code),
+            index: names_proof.then_some(index),
+            key_hash: (names_proof && key_hash != [0u8; 32]).then(|| hex::encode(key_hash)),
+        })
+    }
+}
+
+#[derive(Serialize, Debug, JsonSchema)]
 pub struct VerifyProofResponse {
     pub proof_verified: bool,
     pub root_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root_alias: Option<String>,
     pub banned_list_hash: String,
+    /// Published list version the banned_list_hash was resolved from via the list catalog
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_version: Option<String>,
     pub compliant: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_count: Option<u32>,
+    /// True when the journal predates proof_count, so no count could be checked
+    pub count_unverified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasons_hash: Option<String>,
+    /// Present only when the journal committed provenance for the banned list
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_info_hash: Option<String>,
+    /// Present only when the journal's banned_list_hash is blinded with a salt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salt_commitment: Option<String>,
+    /// True when the banned list was revealed and checked against the journal
+    pub banned_list_revealed: bool,
+    /// Kind of identifier the journal states the banned list is keyed by
+    pub key_type: &'static str,
+    /// Statement the journal states `compliant` attests
+    pub proof_type: &'static str,
+    /// Digest of the default hash table the guest verified against; absent when the
+    /// journal predates defaults_digest
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub defaults_digest: Option<String>,
+    /// Commitment to the API key id the proof is bound to; absent for proofs of unsigned
+    /// requests and journals that predate requester_commitment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requester_commitment: Option<String>,
+    /// Why the proofs are not compliant, as committed in the journal; absent when they
+    /// are and for journals that predate first_failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_failure: Option<FirstFailure>,
+    /// Kind of the receipt that verified: "composite", "succinct", "groth16" or "fake"
+    pub receipt_kind: &'static str,
     pub image_id: Vec<String>,
     pub generation_duration_ms: Option<u64>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA_GOLDEN: &str = include_str!("../../docs/api/verifier-service.schema.json");
+    const TYPESCRIPT_GOLDEN: &str = include_str!("../../docs/api/verifier-service.d.ts");
+
+    #[test]
+    fn the_checked_in_definitions_match_the_models() {
+        let schema = api_schema();
+        let golden: serde_json::Value = serde_json::from_str(SCHEMA_GOLDEN).unwrap();
+        assert_eq!(serde_json::to_value(schema.json_schema()).unwrap(), golden);
+        assert_eq!(schema.typescript(), TYPESCRIPT_GOLDEN);
+    }
+}
@@ -0,0 +1,252 @@
+//! Bounded pool for CPU-heavy receipt verification.
+//!
+//! Every endpoint that verifies a receipt runs the work through one shared
+//! [`VerifyPool`]: at most `workers` jobs run on the blocking thread pool at a time, at
+//! most `queue` more wait for a worker, and anything beyond that is refused with
+//! [`Error::Overloaded`] (429) instead of piling up until every request is slow.
+
+use sbom_common::observability::{metric, metrics as registry};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+use crate::error::{Error, Result};
+
+/// Seconds a refused client is told to wait before retrying
+pub const RETRY_AFTER_SECS: u64 = 1;
+
+#[derive(Clone)]
+pub struct VerifyPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    workers: Arc<Semaphore>,
+    /// Jobs that may be admitted at once: workers plus queue slots
+    capacity: usize,
+    queue: usize,
+    /// Admitted jobs, running or waiting
+    admitted: AtomicUsize,
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+}
+
+/// Snapshot of the pool's occupancy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+    pub in_flight: usize,
+    pub queued: usize,
+    /// Most jobs ever seen running at once
+    pub peak_in_flight: usize,
+}
+
+impl VerifyPool {
+    /// A pool running up to `workers` jobs (at least one) with `queue` waiting slots
+    pub fn new(workers: usize, queue: usize) -> Self {
+        let workers = workers.max(1);
+        Self {
+            inner: Arc::new(Inner {
+                workers: Arc::new(Semaphore::new(workers)),
+                capacity: workers + queue,
+                queue,
+                admitted: AtomicUsize::new(0),
+                in_flight: AtomicUsize::new(0),
+                peak_in_flight: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Run `job` on a worker once one is free. Fails immediately with
+    /// [`Error::Overloaded`] when every worker is busy and the queue is full.
+    pub async fn run<T, F>(&self, job: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let admission = Admission::new(&self.inner)?;
+
+        let permit = self
+            .inner
+            .workers
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| Error::InternalError(format!("Verification pool closed: {e}")))?;
+
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = {
+                let _running = Running::new(&inner);
+                job()
+            };
+            drop(permit);
+            drop(admission);
+            result
+        })
+        .await
+        .map_err(|e| Error::InternalError(format!("Verification task failed to run: {e}")))?
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let admitted = self.inner.admitted.load(Ordering::SeqCst);
+        let in_flight = self.inner.in_flight.load(Ordering::SeqCst);
+        PoolStats {
+            in_flight,
+            queued: admitted.saturating_sub(in_flight),
+            peak_in_flight: self.inner.peak_in_flight.load(Ordering::SeqCst),
+        }
+    }
+}
+
+impl Inner {
+    fn publish(&self) {
+        let admitted = self.admitted.load(Ordering::SeqCst);
+        let in_flight = self.in_flight.load(Ordering::SeqCst);
+        let _ = registry().set_gauge(metric::VERIFICATIONS_IN_FLIGHT, &[], in_flight as i64);
+        let _ = registry().set_gauge(
+            metric::VERIFICATIONS_QUEUED,
+            &[],
+            admitted.saturating_sub(in_flight) as i64,
+        );
+    }
+}
+
+/// A claimed slot in the pool, released on drop even if the caller goes away while queued
+struct Admission {
+    inner: Arc<Inner>,
+}
+
+impl Admission {
+    fn new(inner: &Arc<Inner>) -> Result<Self> {
+        let admitted = inner.admitted.fetch_add(1, Ordering::SeqCst);
+        if admitted >= inner.capacity {
+            inner.admitted.fetch_sub(1, Ordering::SeqCst);
+            return Err(Error::Overloaded(format!(
+                "all verification workers are busy and {} request(s) are already queued; retry in {}s",
+                inner.queue,
+                RETRY_AFTER_SECS
+            )));
+        }
+        inner.publish();
+        Ok(Self {
+            inner: inner.clone(),
+        })
+    }
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        self.inner.admitted.fetch_sub(1, Ordering::SeqCst);
+        self.inner.publish();
+    }
+}
+
+/// Marks a job as running on a worker for as long as it lives
+struct Running<'a> {
+    inner: &'a Inner,
+}
+
+impl<'a> Running<'a> {
+    fn new(inner: &'a Inner) -> Self {
+        let running = inner.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        inner.peak_in_flight.fetch_max(running, Ordering::SeqCst);
+        inner.publish();
+        Self { inner }
+    }
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.inner.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.inner.publish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::join_all;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Wait until the pool reports `in_flight` running and `queued` waiting jobs
+    async fn settle(pool: &VerifyPool, in_flight: usize, queued: usize) {
+        for _ in 0..500 {
+            let stats = pool.stats();
+            if stats.in_flight == in_flight && stats.queued == queued {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        panic!(
+            "pool never reached {} running, {} queued: {:?}",
+            in_flight,
+            queued,
+            pool.stats()
+        );
+    }
+
+    #[actix_rt::test]
+    async fn concurrency_never_exceeds_the_workers() {
+        let pool = VerifyPool::new(3, 100);
+        let running = Arc::new(AtomicUsize::new(0));
+        let high_water = Arc::new(AtomicUsize::new(0));
+
+        let jobs = (0..40).map(|i| {
+            let (running, high_water) = (running.clone(), high_water.clone());
+            pool.run(move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                high_water.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(5));
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(i)
+            })
+        });
+        let results: Vec<usize> = join_all(jobs)
+            .await
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+
+        assert_eq!(results, (0..40).collect::<Vec<_>>());
+        assert!(high_water.load(Ordering::SeqCst) <= 3);
+        assert!(pool.stats().peak_in_flight <= 3);
+        settle(&pool, 0, 0).await;
+    }
+
+    #[actix_rt::test]
+    async fn refuses_work_beyond_the_queue() {
+        let pool = VerifyPool::new(1, 1);
+        let (release, blocked) = mpsc::channel::<()>();
+        let running = {
+            let pool = pool.clone();
+            actix_rt::spawn(async move {
+                pool.run(move || {
+                    blocked.recv().unwrap();
+                    Ok(())
+                })
+                .await
+            })
+        };
+        settle(&pool, 1, 0).await;
+        let queued = {
+            let pool = pool.clone();
+            actix_rt::spawn(async move { pool.run(|| Ok(())).await })
+        };
+        settle(&pool, 1, 1).await;
+
+        let refused = pool.run(|| Ok(())).await;
+        assert!(
+            matches!(refused, Err(Error::Overloaded(_))),
+            "{:?}",
+            refused.err()
+        );
+
+        release.send(()).unwrap();
+        running.await.unwrap().unwrap();
+        queued.await.unwrap().unwrap();
+        settle(&pool, 0, 0).await;
+        pool.run(|| Ok(())).await.unwrap();
+    }
+}